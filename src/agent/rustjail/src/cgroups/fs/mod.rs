@@ -18,7 +18,7 @@ use cgroups::{
     DeviceResource, HugePageResource, MaxValue, NetworkPriority,
 };
 
-use crate::cgroups::{rule_for_all_devices, Manager as CgroupManager};
+use crate::cgroups::{devicefilter, rule_for_all_devices, Manager as CgroupManager};
 use crate::container::DEFAULT_DEVICES;
 use anyhow::{anyhow, Context, Result};
 use libc::{self, pid_t};
@@ -42,6 +42,8 @@ use std::sync::{Arc, RwLock};
 use super::DevicesCgroupInfo;
 
 const GUEST_CPUS_PATH: &str = "/sys/devices/system/cpu/online";
+const GUEST_MEMS_PATH: &str = "/sys/devices/system/node/online";
+const GUEST_NODE_PATH: &str = "/sys/devices/system/node";
 
 // Convenience function to obtain the scope logger.
 fn sl() -> slog::Logger {
@@ -142,6 +144,19 @@ impl CgroupManager for Manager {
         }
         self.cgroup.apply(res)?;
 
+        // The unified hierarchy has no devices controller and the allowlist
+        // prepared above is silently ignored there, so enforce it with a
+        // cgroup-device eBPF program instead.
+        if cgroups::hierarchies::is_cgroup2_unified_mode() && !self.devcg_allowed_all {
+            if let Some(devices) = r.devices() {
+                if !devices.is_empty() {
+                    let cg_path = format!("/sys/fs/cgroup/{}", self.cpath);
+                    devicefilter::attach_device_filter(devices, &cg_path)
+                        .with_context(|| format!("attach device filter to {}", cg_path))?;
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -217,11 +232,19 @@ impl CgroupManager for Manager {
         Ok(result)
     }
 
-    fn update_cpuset_path(&self, guest_cpuset: &str, container_cpuset: &str) -> Result<()> {
+    fn update_cpuset_path(
+        &self,
+        guest_cpuset: &str,
+        guest_memset: &str,
+        container_cpuset: &str,
+    ) -> Result<()> {
         if guest_cpuset.is_empty() {
             return Ok(());
         }
-        info!(sl(), "update_cpuset_path to: {}", guest_cpuset);
+        info!(
+            sl(),
+            "update_cpuset_path to cpus: {} mems: {}", guest_cpuset, guest_memset
+        );
 
         let h = cgroups::hierarchies::auto();
         let root_cg = h.root_control_group();
@@ -261,6 +284,9 @@ impl CgroupManager for Manager {
             let cg = new_cgroup(cgroups::hierarchies::auto(), r_path)?;
             let cpuset_controller: &CpuSetController = cg.controller_of().unwrap();
             cpuset_controller.set_cpus(guest_cpuset)?;
+            if !guest_memset.is_empty() {
+                cpuset_controller.set_mems(guest_memset)?;
+            }
         }
 
         if !container_cpuset.is_empty() {
@@ -390,11 +416,13 @@ fn set_block_io_resources(
         .as_ref()
         .unwrap_or(&default_weight_device);
     for d in weight_device.iter() {
+        // A per-device weight overrides the cgroup-wide one, which is
+        // already applied above through res.blkio.weight.
         let dr = BlkIoDeviceResource {
             major: d.major() as u64,
             minor: d.minor() as u64,
-            weight: blkio.weight(),
-            leaf_weight: blkio.leaf_weight(),
+            weight: d.weight(),
+            leaf_weight: d.leaf_weight(),
         };
         blk_device_resources.push(dr);
     }
@@ -434,8 +462,24 @@ fn set_cpu_resources(cg: &cgroups::Cgroup, cpu: &LinuxCpu) -> Result<()> {
         }
     }
 
-    if let Some(mems) = cpu.mems() {
-        cpuset_controller.set_mems(mems)?;
+    match cpu.mems() {
+        Some(mems) => cpuset_controller.set_mems(mems)?,
+        None => {
+            // No explicit memory nodes requested: co-locate the container's
+            // memory with the NUMA nodes its cpus live on instead of letting
+            // allocations all land on node 0.
+            if let Some(cpus) = cpu.cpus() {
+                match numa_mems_for_cpus(cpus) {
+                    Ok(Some(mems)) => {
+                        if let Err(e) = cpuset_controller.set_mems(&mems) {
+                            warn!(sl(), "write cpuset.mems failed: {:?}", e);
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(e) => warn!(sl(), "failed to map cpus {} to NUMA nodes: {:?}", cpus, e),
+                }
+            }
+        }
     }
 
     let cpu_controller: &CpuController = cg.controller_of().unwrap();
@@ -1170,6 +1214,18 @@ impl Manager {
         })
     }
 
+    /// Apply hugetlb limits directly through the cgroup filesystem.
+    ///
+    /// The systemd cgroup driver relies on this since systemd exposes no
+    /// unit property for the hugetlb controller.
+    pub fn set_hugepages(&self, hugepage_limits: &[LinuxHugepageLimit]) -> Result<()> {
+        let res = &mut cgroups::Resources::default();
+        set_hugepages_resources(&self.cgroup, hugepage_limits, res);
+        self.cgroup.apply(res)?;
+
+        Ok(())
+    }
+
     fn get_paths_and_mounts(
         cpath: &str,
     ) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
@@ -1332,6 +1388,85 @@ pub fn get_guest_cpuset() -> Result<String> {
     Ok(c.trim().to_string())
 }
 
+pub fn get_guest_memset() -> Result<String> {
+    // A guest kernel without NUMA support doesn't expose the node mask.
+    if !Path::new(GUEST_MEMS_PATH).exists() {
+        return Ok(String::new());
+    }
+    let m = fs::read_to_string(GUEST_MEMS_PATH)?;
+    Ok(m.trim().to_string())
+}
+
+// Parse a cpuset list-format string such as "0-3,7" into the ids it contains.
+fn parse_cpuset_list(list: &str) -> Result<Vec<u32>> {
+    let mut ids = Vec::new();
+    for part in list.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start: u32 = start.trim().parse()?;
+                let end: u32 = end.trim().parse()?;
+                if end < start {
+                    return Err(anyhow!("invalid cpuset range {:?}", part));
+                }
+                ids.extend(start..=end);
+            }
+            None => ids.push(part.parse()?),
+        }
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids)
+}
+
+// Map a cpuset.cpus list to the NUMA nodes hosting those cpus, so a
+// container's memory can be co-located with its cpus. Returns Ok(None)
+// when the guest exposes no NUMA topology.
+fn numa_mems_for_cpus(cpus: &str) -> Result<Option<String>> {
+    let node_dir = Path::new(GUEST_NODE_PATH);
+    if !node_dir.exists() {
+        return Ok(None);
+    }
+
+    let cpu_ids = parse_cpuset_list(cpus)?;
+    let mut nodes = Vec::new();
+
+    for entry in fs::read_dir(node_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let node_id: u32 = match name.strip_prefix("node").map(str::parse) {
+            Some(Ok(id)) => id,
+            _ => continue,
+        };
+
+        let cpulist = match fs::read_to_string(entry.path().join("cpulist")) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let node_cpus = parse_cpuset_list(cpulist.trim())?;
+        if cpu_ids.iter().any(|id| node_cpus.binary_search(id).is_ok()) {
+            nodes.push(node_id);
+        }
+    }
+
+    if nodes.is_empty() {
+        return Ok(None);
+    }
+    nodes.sort_unstable();
+
+    Ok(Some(
+        nodes
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    ))
+}
+
 // Since the OCI spec is designed for cgroup v1, in some cases
 // there is need to convert from the cgroup v1 configuration to cgroup v2
 // the formula for cpuShares is y = (1 + ((x - 2) * 9999) / 262142)
@@ -1388,6 +1523,7 @@ mod tests {
     use test_utils::skip_if_not_root;
 
     use super::default_allowed_devices;
+    use super::parse_cpuset_list;
     use crate::cgroups::fs::{
         line_to_vec, lines_to_map, Manager, DEFAULT_ALLOWED_DEVICES, WILDCARD,
     };
@@ -1648,4 +1784,20 @@ mod tests {
             vec![DevicePermissions::MkNod]
         );
     }
+
+    #[test]
+    fn test_parse_cpuset_list() {
+        assert_eq!(parse_cpuset_list("").unwrap(), Vec::<u32>::new());
+        assert_eq!(parse_cpuset_list("3").unwrap(), vec![3]);
+        assert_eq!(parse_cpuset_list("0-3").unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(
+            parse_cpuset_list("0-2,5,7-8").unwrap(),
+            vec![0, 1, 2, 5, 7, 8]
+        );
+        assert_eq!(parse_cpuset_list("2,0-1,2").unwrap(), vec![0, 1, 2]);
+
+        assert!(parse_cpuset_list("3-1").is_err());
+        assert!(parse_cpuset_list("a-b").is_err());
+        assert!(parse_cpuset_list("nonsense").is_err());
+    }
 }
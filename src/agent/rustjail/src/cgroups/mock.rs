@@ -58,7 +58,7 @@ impl CgroupManager for Manager {
         Ok(Vec::new())
     }
 
-    fn update_cpuset_path(&self, _: &str, _: &str) -> Result<()> {
+    fn update_cpuset_path(&self, _: &str, _: &str, _: &str) -> Result<()> {
         Ok(())
     }
 
@@ -11,6 +11,7 @@ use std::any::Any;
 
 use cgroups::freezer::FreezerState;
 
+pub mod devicefilter;
 pub mod fs;
 pub mod mock;
 pub mod notifier;
@@ -50,7 +51,7 @@ pub trait Manager {
         Err(anyhow!("not supported!"))
     }
 
-    fn update_cpuset_path(&self, _: &str, _: &str) -> Result<()> {
+    fn update_cpuset_path(&self, _: &str, _: &str, _: &str) -> Result<()> {
         Err(anyhow!("not supported!"))
     }
 
@@ -66,6 +66,12 @@ impl CgroupManager for Manager {
 
         self.dbus_client.set_properties(&properties)?;
 
+        // systemd has no unit property for the hugetlb controller, so
+        // hugepage limits are written to the cgroup filesystem directly.
+        if let Some(hugepage_limits) = r.hugepage_limits() {
+            self.fs_manager.set_hugepages(hugepage_limits)?;
+        }
+
         Ok(())
     }
 
@@ -90,9 +96,14 @@ impl CgroupManager for Manager {
         self.fs_manager.get_pids()
     }
 
-    fn update_cpuset_path(&self, guest_cpuset: &str, container_cpuset: &str) -> Result<()> {
+    fn update_cpuset_path(
+        &self,
+        guest_cpuset: &str,
+        guest_memset: &str,
+        container_cpuset: &str,
+    ) -> Result<()> {
         self.fs_manager
-            .update_cpuset_path(guest_cpuset, container_cpuset)
+            .update_cpuset_path(guest_cpuset, guest_memset, container_cpuset)
     }
 
     fn get_cgroup_path(&self, cg: &str) -> Result<String> {
@@ -56,7 +56,6 @@ use protobuf::MessageField;
 use regex::Regex;
 use std::collections::HashMap;
 use std::os::unix::io::FromRawFd;
-use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
 use slog::{info, o, Logger};
@@ -135,9 +134,16 @@ lazy_static! {
         m.insert("mnt", CloneFlags::CLONE_NEWNS);
         m.insert("uts", CloneFlags::CLONE_NEWUTS);
         m.insert("cgroup", CloneFlags::CLONE_NEWCGROUP);
+        m.insert("time", *CLONE_NEWTIME);
         m
     };
 
+    // CLONE_NEWTIME is part of the stable kernel uapi
+    // (include/uapi/linux/sched.h) but not defined by the nix version in
+    // use.
+    pub static ref CLONE_NEWTIME: CloneFlags =
+        unsafe { CloneFlags::from_bits_unchecked(0x80) };
+
     // type to name hashmap, better to be in NAMESPACES
     pub static ref TYPETONAME: HashMap<oci::LinuxNamespaceType, &'static str> = {
         let mut m = HashMap::new();
@@ -148,6 +154,7 @@ lazy_static! {
         m.insert(oci::LinuxNamespaceType::Mount, "mnt");
         m.insert(oci::LinuxNamespaceType::Cgroup, "cgroup");
         m.insert(oci::LinuxNamespaceType::Uts, "uts");
+        m.insert(oci::LinuxNamespaceType::Time, "time");
         m
     };
 
@@ -489,16 +496,18 @@ fn do_init_child(cwfd: RawFd) -> Result<()> {
         )?;
     }
 
-    // set rlimit
-    let default_rlimits = Vec::new();
-    let process_rlimits = p.rlimits().as_ref().unwrap_or(&default_rlimits);
-    for rl in process_rlimits.iter() {
-        log_child!(cfd_log, "set resource limit: {:?}", rl);
-        setrlimit(
-            Resource::from_str(&rl.typ().to_string())?,
-            Rlim::from_raw(rl.soft()),
-            Rlim::from_raw(rl.hard()),
-        )?;
+    // Set the rlimits. This runs for the init process as well as for
+    // every exec'd process, since both come through here.
+    setup_rlimits(cfd_log, p)?;
+
+    if let Some(io_priority) = p.io_priority() {
+        log_child!(cfd_log, "set io priority: {:?}", io_priority);
+        setup_io_priority(io_priority)?;
+    }
+
+    if let Some(scheduler) = p.scheduler() {
+        log_child!(cfd_log, "set scheduler: {:?}", scheduler);
+        setup_scheduler(scheduler)?;
     }
 
     //
@@ -537,6 +546,7 @@ fn do_init_child(cwfd: RawFd) -> Result<()> {
 
     let mut mount_fd = -1;
     let mut bind_device = false;
+    let mut cgroupns_joined = false;
     for (s, fd) in to_join {
         if s == CloneFlags::CLONE_NEWNS {
             mount_fd = fd;
@@ -564,16 +574,35 @@ fn do_init_child(cwfd: RawFd) -> Result<()> {
             setid(Uid::from_raw(0), Gid::from_raw(0))?;
             bind_device = true;
         }
+
+        if s == CloneFlags::CLONE_NEWCGROUP {
+            cgroupns_joined = true;
+        }
     }
 
     let selinux_enabled = selinux::is_enabled()?;
 
     sched::unshare(to_new & !CloneFlags::CLONE_NEWUSER)?;
 
-    if cgroups::hierarchies::is_cgroup2_unified_mode() {
+    // The parent has already moved this process into its cgroup, so an
+    // unshared cgroup namespace is rooted at the container's own subtree
+    // and /sys/fs/cgroup only shows that subtree on cgroup v2 guests.
+    // Keep unsharing by default on cgroup v2, but not when the spec
+    // already created a cgroup namespace through to_new above or asked
+    // to join an existing one by path.
+    if cgroups::hierarchies::is_cgroup2_unified_mode()
+        && !cgroupns_joined
+        && !to_new.contains(CloneFlags::CLONE_NEWCGROUP)
+    {
+        log_child!(cfd_log, "unshare cgroup namespace");
         sched::unshare(CloneFlags::CLONE_NEWCGROUP)?;
     }
 
+    if to_new.contains(*CLONE_NEWTIME) {
+        log_child!(cfd_log, "setup time namespace");
+        setup_timens(linux.time_offsets().as_ref())?;
+    }
+
     if userns {
         bind_device = true;
     }
@@ -1107,6 +1136,36 @@ impl BaseContainer for LinuxContainer {
                     });
                 }
 
+                // Both output pipes share one connection: frame each
+                // chunk so the peer can tell the streams apart and see
+                // per-stream EOF.
+                if proc_io.framed_output {
+                    if let Some(stdout_stream) = proc_io.stdout.take() {
+                        let shared_stream = Arc::new(tokio::sync::Mutex::new(stdout_stream));
+                        for (stream_id, pipe_fd) in [
+                            (crate::framed_io::StreamId::Stdout, p.parent_stdout.unwrap()),
+                            (crate::framed_io::StreamId::Stderr, p.parent_stderr.unwrap()),
+                        ] {
+                            debug!(logger, "framed copy to output stream"; "stream" => format!("{:?}", stream_id));
+                            let wgw_output = proc_io.wg_output.worker();
+                            let mut pipe = unsafe { File::from_raw_fd(pipe_fd) };
+                            let shared_stream = shared_stream.clone();
+                            let logger = logger.clone();
+                            tokio::spawn(async move {
+                                let res = crate::framed_io::framed_copy(
+                                    &mut pipe,
+                                    &shared_stream,
+                                    stream_id,
+                                )
+                                .await;
+                                debug!(logger, "framed copy end: {:?}", res;
+                                    "stream" => format!("{:?}", stream_id));
+                                wgw_output.done();
+                            });
+                        }
+                    }
+                }
+
                 // copy from parent_stdout to stdout stream
                 if let Some(mut stdout_stream) = proc_io.stdout.take() {
                     debug!(logger, "copy from parent_stdout to stdout stream");
@@ -1646,6 +1705,40 @@ fn write_mappings(logger: &Logger, path: &str, maps: &[LinuxIdMapping]) -> Resul
     Ok(())
 }
 
+// Apply the clock offsets from the OCI spec to the new time namespace and
+// join it. unshare(CLONE_NEWTIME) only places future children of the
+// calling process in the new namespace, and the offsets can only be
+// written while no process has joined it, so the order matters: write the
+// offsets through /proc/self/timens_offsets first, then enter the
+// namespace via /proc/self/ns/time_for_children.
+fn setup_timens(offsets: Option<&HashMap<String, String>>) -> Result<()> {
+    if let Some(offsets) = offsets {
+        let mut data = String::new();
+        for (clock, offset) in offsets {
+            if clock != "monotonic" && clock != "boottime" {
+                return Err(anyhow!("unsupported time namespace clock {}", clock));
+            }
+            data.push_str(&format!("{} {}\n", clock, offset));
+        }
+
+        if !data.is_empty() {
+            fs::write("/proc/self/timens_offsets", data.as_bytes())
+                .context("write time namespace offsets")?;
+        }
+    }
+
+    let fd = fcntl::open(
+        "/proc/self/ns/time_for_children",
+        OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .context("open time namespace fd")?;
+    defer!(unistd::close(fd).unwrap());
+    sched::setns(fd, *CLONE_NEWTIME).context("join time namespace")?;
+
+    Ok(())
+}
+
 fn setid(uid: Uid, gid: Gid) -> Result<()> {
     // set uid/gid
     capctl::prctl::set_keepcaps(true)
@@ -1669,6 +1762,19 @@ fn setid(uid: Uid, gid: Gid) -> Result<()> {
 }
 
 impl LinuxContainer {
+    /// Re-attach the bookkeeping of a container whose processes kept
+    /// running across an in-place agent re-exec.
+    pub fn restore_runtime_state(
+        &mut self,
+        init_process_pid: pid_t,
+        init_process_start_time: u64,
+        status: ContainerState,
+    ) {
+        self.init_process_pid = init_process_pid;
+        self.init_process_start_time = init_process_start_time;
+        self.status.transition(status);
+    }
+
     pub fn new<T: Into<String> + Display + Clone>(
         id: T,
         base: T,
@@ -1760,6 +1866,201 @@ impl LinuxContainer {
 use std::fs::OpenOptions;
 use std::io::Write;
 
+// Map an OCI rlimit type onto the matching kernel resource. All the
+// POSIX rlimit types the runtime spec defines for Linux are supported.
+fn rlimit_resource(typ: oci::PosixRlimitType) -> Resource {
+    match typ {
+        oci::PosixRlimitType::RlimitCpu => Resource::CPU,
+        oci::PosixRlimitType::RlimitFsize => Resource::FSIZE,
+        oci::PosixRlimitType::RlimitData => Resource::DATA,
+        oci::PosixRlimitType::RlimitStack => Resource::STACK,
+        oci::PosixRlimitType::RlimitCore => Resource::CORE,
+        oci::PosixRlimitType::RlimitRss => Resource::RSS,
+        oci::PosixRlimitType::RlimitNproc => Resource::NPROC,
+        oci::PosixRlimitType::RlimitNofile => Resource::NOFILE,
+        oci::PosixRlimitType::RlimitMemlock => Resource::MEMLOCK,
+        oci::PosixRlimitType::RlimitAs => Resource::AS,
+        oci::PosixRlimitType::RlimitLocks => Resource::LOCKS,
+        oci::PosixRlimitType::RlimitSigpending => Resource::SIGPENDING,
+        oci::PosixRlimitType::RlimitMsgqueue => Resource::MSGQUEUE,
+        oci::PosixRlimitType::RlimitNice => Resource::NICE,
+        oci::PosixRlimitType::RlimitRtprio => Resource::RTPRIO,
+        oci::PosixRlimitType::RlimitRttime => Resource::RTTIME,
+    }
+}
+
+// Apply the rlimits requested for a process, after checking that each
+// soft limit does not exceed its hard limit as getrlimit(2) requires.
+fn setup_rlimits(cfd_log: RawFd, p: &oci::Process) -> Result<()> {
+    let default_rlimits = Vec::new();
+    let rlimits = p.rlimits().as_ref().unwrap_or(&default_rlimits);
+
+    for rl in rlimits.iter() {
+        if rl.soft() > rl.hard() {
+            return Err(anyhow!(
+                "rlimit {} soft limit {} is greater than hard limit {}",
+                rl.typ(),
+                rl.soft(),
+                rl.hard()
+            ));
+        }
+
+        log_child!(cfd_log, "set resource limit: {:?}", rl);
+        setrlimit(
+            rlimit_resource(rl.typ()),
+            Rlim::from_raw(rl.soft()),
+            Rlim::from_raw(rl.hard()),
+        )
+        .with_context(|| format!("set rlimit {}", rl.typ()))?;
+    }
+
+    Ok(())
+}
+
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_SHIFT: i64 = 13;
+const IOPRIO_CLASS_RT: i64 = 1;
+const IOPRIO_CLASS_BE: i64 = 2;
+const IOPRIO_CLASS_IDLE: i64 = 3;
+
+// Apply the OCI ioPriority settings to the current process with
+// ioprio_set(2), so the configured I/O scheduling class takes effect for
+// both the init and exec processes.
+fn setup_io_priority(io_priority: &oci::LinuxIOPriority) -> Result<()> {
+    let class = match io_priority.class() {
+        oci::IOPriorityClass::IoprioClassRt => IOPRIO_CLASS_RT,
+        oci::IOPriorityClass::IoprioClassBe => IOPRIO_CLASS_BE,
+        oci::IOPriorityClass::IoprioClassIdle => IOPRIO_CLASS_IDLE,
+    };
+
+    let priority = io_priority.priority();
+    if !(0..=7).contains(&priority) {
+        return Err(anyhow!("invalid io priority {}", priority));
+    }
+
+    let ioprio = (class << IOPRIO_CLASS_SHIFT) | priority;
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) };
+    if ret < 0 {
+        return Err(anyhow!(
+            "failed to set io priority {:?}: {}",
+            io_priority,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
+
+// Scheduling policy numbers understood by sched_setattr(2).
+const SCHED_OTHER: u32 = 0;
+const SCHED_FIFO: u32 = 1;
+const SCHED_RR: u32 = 2;
+const SCHED_BATCH: u32 = 3;
+const SCHED_IDLE: u32 = 5;
+const SCHED_DEADLINE: u32 = 6;
+
+// Scheduling flags understood by sched_setattr(2).
+const SCHED_FLAG_RESET_ON_FORK: u64 = 0x01;
+const SCHED_FLAG_RECLAIM: u64 = 0x02;
+const SCHED_FLAG_DL_OVERRUN: u64 = 0x04;
+const SCHED_FLAG_KEEP_POLICY: u64 = 0x08;
+const SCHED_FLAG_KEEP_PARAMS: u64 = 0x10;
+const SCHED_FLAG_UTIL_CLAMP_MIN: u64 = 0x20;
+const SCHED_FLAG_UTIL_CLAMP_MAX: u64 = 0x40;
+
+// struct sched_attr of sched_setattr(2), in its initial (VER0) layout.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SchedAttr {
+    size: u32,
+    sched_policy: u32,
+    sched_flags: u64,
+    sched_nice: i32,
+    sched_priority: u32,
+    sched_runtime: u64,
+    sched_deadline: u64,
+    sched_period: u64,
+}
+
+const SCHED_ATTR_SIZE_VER0: u32 = std::mem::size_of::<SchedAttr>() as u32;
+
+fn sched_policy_number(policy: oci::LinuxSchedulerPolicy) -> Result<u32> {
+    Ok(match policy {
+        oci::LinuxSchedulerPolicy::SchedOther => SCHED_OTHER,
+        oci::LinuxSchedulerPolicy::SchedFifo => SCHED_FIFO,
+        oci::LinuxSchedulerPolicy::SchedRr => SCHED_RR,
+        oci::LinuxSchedulerPolicy::SchedBatch => SCHED_BATCH,
+        oci::LinuxSchedulerPolicy::SchedIdle => SCHED_IDLE,
+        oci::LinuxSchedulerPolicy::SchedDeadline => SCHED_DEADLINE,
+        // SCHED_ISO was never implemented in the mainline kernel.
+        oci::LinuxSchedulerPolicy::SchedIso => {
+            return Err(anyhow!("scheduler policy SCHED_ISO is not supported"))
+        }
+    })
+}
+
+fn sched_flag_number(flag: oci::LinuxSchedulerFlag) -> u64 {
+    match flag {
+        oci::LinuxSchedulerFlag::SchedResetOnFork => SCHED_FLAG_RESET_ON_FORK,
+        oci::LinuxSchedulerFlag::SchedFlagReclaim => SCHED_FLAG_RECLAIM,
+        oci::LinuxSchedulerFlag::SchedFlagDLOverrun => SCHED_FLAG_DL_OVERRUN,
+        oci::LinuxSchedulerFlag::SchedFlagKeepPolicy => SCHED_FLAG_KEEP_POLICY,
+        oci::LinuxSchedulerFlag::SchedFlagKeepParams => SCHED_FLAG_KEEP_PARAMS,
+        oci::LinuxSchedulerFlag::SchedFlagUtilClampMin => SCHED_FLAG_UTIL_CLAMP_MIN,
+        oci::LinuxSchedulerFlag::SchedFlagUtilClampMax => SCHED_FLAG_UTIL_CLAMP_MAX,
+    }
+}
+
+// Apply the OCI scheduler settings to the current process with
+// sched_setattr(2), so real-time workloads get the requested policy for
+// both the init and exec processes. The attributes stick across the later
+// capability drop and execve.
+fn setup_scheduler(scheduler: &oci::Scheduler) -> Result<()> {
+    let policy = sched_policy_number(scheduler.policy())?;
+
+    // The kernel only lets privileged processes select a real-time or
+    // deadline policy; check up front so the error names the missing
+    // capability instead of a bare EPERM.
+    if matches!(policy, SCHED_FIFO | SCHED_RR | SCHED_DEADLINE)
+        && !caps::has_cap(
+            None,
+            caps::CapSet::Effective,
+            caps::Capability::CAP_SYS_NICE,
+        )
+        .map_err(|e| anyhow!(e.to_string()))?
+    {
+        return Err(anyhow!(
+            "scheduler policy {} requires CAP_SYS_NICE",
+            scheduler.policy()
+        ));
+    }
+
+    let mut attr = SchedAttr {
+        size: SCHED_ATTR_SIZE_VER0,
+        sched_policy: policy,
+        sched_nice: scheduler.nice().unwrap_or(0),
+        sched_priority: scheduler.priority().unwrap_or(0) as u32,
+        sched_runtime: scheduler.runtime().unwrap_or(0),
+        sched_deadline: scheduler.deadline().unwrap_or(0),
+        sched_period: scheduler.period().unwrap_or(0),
+        ..Default::default()
+    };
+    for flag in scheduler.flags().iter().flatten() {
+        attr.sched_flags |= sched_flag_number(*flag);
+    }
+
+    let ret = unsafe { libc::syscall(libc::SYS_sched_setattr, 0, &attr as *const SchedAttr, 0) };
+    if ret < 0 {
+        return Err(anyhow!(
+            "failed to set scheduler {:?}: {}",
+            scheduler,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
+
 fn set_sysctls(sysctls: &HashMap<String, String>) -> Result<()> {
     for (key, value) in sysctls {
         let name = format!("/proc/sys/{}", key.replace('.', "/"));
@@ -1845,6 +2146,103 @@ mod tests {
         set_stdio_permissions(Uid::from_raw(old_uid)).unwrap();
     }
 
+    #[test]
+    fn test_setup_io_priority() {
+        let io_priority = oci::LinuxIOPriorityBuilder::default()
+            .class(oci::IOPriorityClass::IoprioClassBe)
+            .priority(4i64)
+            .build()
+            .unwrap();
+        setup_io_priority(&io_priority).unwrap();
+
+        let ioprio = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0) } as i64;
+        assert_eq!(ioprio >> IOPRIO_CLASS_SHIFT, IOPRIO_CLASS_BE);
+        assert_eq!(ioprio & 0xff, 4);
+    }
+
+    #[test]
+    fn test_setup_io_priority_invalid_priority() {
+        let io_priority = oci::LinuxIOPriorityBuilder::default()
+            .class(oci::IOPriorityClass::IoprioClassBe)
+            .priority(8i64)
+            .build()
+            .unwrap();
+        assert!(setup_io_priority(&io_priority).is_err());
+    }
+
+    #[test]
+    fn test_setup_scheduler() {
+        skip_if_not_root!();
+
+        let scheduler = oci::SchedulerBuilder::default()
+            .policy(oci::LinuxSchedulerPolicy::SchedBatch)
+            .nice(5i32)
+            .build()
+            .unwrap();
+        setup_scheduler(&scheduler).unwrap();
+
+        let policy = unsafe { libc::sched_getscheduler(0) };
+        assert_eq!(policy as u32, SCHED_BATCH);
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        assert_eq!(nice, 5);
+
+        // Restore the default policy for the test runner.
+        let scheduler = oci::SchedulerBuilder::default()
+            .policy(oci::LinuxSchedulerPolicy::SchedOther)
+            .nice(0i32)
+            .build()
+            .unwrap();
+        setup_scheduler(&scheduler).unwrap();
+    }
+
+    #[test]
+    fn test_setup_scheduler_unsupported_policy() {
+        let scheduler = oci::SchedulerBuilder::default()
+            .policy(oci::LinuxSchedulerPolicy::SchedIso)
+            .build()
+            .unwrap();
+        let err = setup_scheduler(&scheduler).unwrap_err();
+        assert!(err.to_string().contains("SCHED_ISO"));
+    }
+
+    #[test]
+    fn test_rlimit_resource() {
+        assert_eq!(
+            rlimit_resource(oci::PosixRlimitType::RlimitMemlock),
+            Resource::MEMLOCK
+        );
+        assert_eq!(
+            rlimit_resource(oci::PosixRlimitType::RlimitRtprio),
+            Resource::RTPRIO
+        );
+        assert_eq!(
+            rlimit_resource(oci::PosixRlimitType::RlimitNofile),
+            Resource::NOFILE
+        );
+    }
+
+    #[test]
+    fn test_setup_rlimits_rejects_soft_above_hard() {
+        let (rfd, wfd) = unistd::pipe().unwrap();
+        defer!({
+            unistd::close(rfd).unwrap();
+            unistd::close(wfd).unwrap();
+        });
+
+        let process = oci::ProcessBuilder::default()
+            .rlimits(vec![oci::PosixRlimitBuilder::default()
+                .typ(oci::PosixRlimitType::RlimitNofile)
+                .soft(2048u64)
+                .hard(1024u64)
+                .build()
+                .unwrap()])
+            .build()
+            .unwrap();
+
+        let err = setup_rlimits(wfd, &process).unwrap_err();
+        assert!(err.to_string().contains("greater than hard limit"));
+    }
+
     #[test]
     fn test_namespaces() {
         lazy_static::initialize(&NAMESPACES);
@@ -0,0 +1,127 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Framed stdout/stderr multiplexing over a single output connection.
+//!
+//! When the shim passes the same hybrid-vsock port for the stdout and
+//! stderr of a non-TTY process, the agent tags every chunk with the
+//! stream it came from instead of blindly interleaving bytes. Each frame
+//! is a five byte header - stream id (u8) and payload length (u32, big
+//! endian) - followed by the payload. A zero length frame signals EOF
+//! for that stream, so the peer sees each stream end individually even
+//! though the connection stays open until both are done.
+
+use std::io;
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Frame header length: stream id (u8) + payload length (u32 BE).
+pub const FRAME_HEADER_SIZE: usize = 5;
+
+/// Largest payload carried in one frame, which also bounds the copy
+/// buffer.
+const MAX_FRAME_PAYLOAD: usize = 32 * 1024;
+
+/// Stream id carried in every frame header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StreamId {
+    Stdout = 1,
+    Stderr = 2,
+}
+
+/// Copy everything from `reader` to `writer`, wrapping each chunk in a
+/// frame tagged with `id` and terminating with a zero length EOF frame.
+/// The writer is shared with the task forwarding the other stream, so
+/// it is only locked per frame. Returns the number of payload bytes
+/// forwarded.
+pub async fn framed_copy<R, W>(
+    reader: &mut R,
+    writer: &Arc<Mutex<W>>,
+    id: StreamId,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; MAX_FRAME_PAYLOAD];
+    let mut written: u64 = 0;
+
+    loop {
+        let n = reader.read(&mut buf).await?;
+
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        header[0] = id as u8;
+        header[1..].copy_from_slice(&(n as u32).to_be_bytes());
+
+        let mut w = writer.lock().await;
+        w.write_all(&header).await?;
+        if n == 0 {
+            // EOF frame.
+            w.flush().await?;
+            return Ok(written);
+        }
+        w.write_all(&buf[..n]).await?;
+        written += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_frames(mut data: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut frames = Vec::new();
+        while !data.is_empty() {
+            let len = u32::from_be_bytes(data[1..5].try_into().unwrap()) as usize;
+            frames.push((data[0], data[5..5 + len].to_vec()));
+            data = &data[5 + len..];
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn test_framed_copy() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+
+        let mut stdout_data = &b"out"[..];
+        let copied = framed_copy(&mut stdout_data, &writer, StreamId::Stdout)
+            .await
+            .unwrap();
+        assert_eq!(copied, 3);
+
+        let mut stderr_data = &b"err"[..];
+        framed_copy(&mut stderr_data, &writer, StreamId::Stderr)
+            .await
+            .unwrap();
+
+        let frames = parse_frames(&writer.lock().await);
+        assert_eq!(
+            frames,
+            vec![
+                (StreamId::Stdout as u8, b"out".to_vec()),
+                (StreamId::Stdout as u8, vec![]),
+                (StreamId::Stderr as u8, b"err".to_vec()),
+                (StreamId::Stderr as u8, vec![]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_framed_copy_empty_input() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+
+        let mut data = &b""[..];
+        let copied = framed_copy(&mut data, &writer, StreamId::Stderr)
+            .await
+            .unwrap();
+        assert_eq!(copied, 0);
+
+        let frames = parse_frames(&writer.lock().await);
+        assert_eq!(frames, vec![(StreamId::Stderr as u8, vec![])]);
+    }
+}
@@ -32,6 +32,7 @@ pub mod cgroups;
 #[cfg(feature = "standard-oci-runtime")]
 pub mod console;
 pub mod container;
+pub mod framed_io;
 pub mod mount;
 pub mod pipestream;
 pub mod process;
@@ -22,6 +22,7 @@ use std::os::unix::io::RawFd;
 use std::path::{Component, Path, PathBuf};
 
 use path_absolutize::*;
+use safe_path::scoped_join;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -276,7 +277,7 @@ pub fn init_rootfs(
             // first check that we have non-default options required before attempting a
             // remount
             if mount_typ == "bind" && !pgflags.is_empty() {
-                let dest = secure_join(rootfs, mount_dest);
+                let dest = secure_join(rootfs, mount_dest)?;
                 mount(
                     None::<&str>,
                     dest.as_str(),
@@ -493,7 +494,7 @@ fn mount_cgroups(
     unistd::chdir(&olddir)?;
 
     if flags.contains(MsFlags::MS_RDONLY) {
-        let dest = format!("{}{}", rootfs, &mount_dest);
+        let dest = secure_join(rootfs, &mount_dest)?;
         mount(
             Some(dest.as_str()),
             dest.as_str(),
@@ -762,42 +763,18 @@ fn parse_mount(m: &Mount) -> (MsFlags, MsFlags, String) {
 // - `rootfs` is the absolute path to the root of the containers root filesystem directory.
 // - `unsafe_path` is path inside a container. It is unsafe since it may try to "escape" from the containers
 //    rootfs by using one or more "../" path elements or is its a symlink to path.
-fn secure_join(rootfs: &str, unsafe_path: &str) -> String {
-    let mut path = PathBuf::from(format!("{}/", rootfs));
-    let unsafe_p = Path::new(&unsafe_path);
-
-    for it in unsafe_p.iter() {
-        let it_p = Path::new(&it);
-
-        // if it_p leads with "/", path.push(it) will be replace as it, so ignore "/"
-        if it_p.has_root() {
-            continue;
-        };
-
-        path.push(it);
-        if let Ok(v) = path.read_link() {
-            if v.is_absolute() {
-                path = PathBuf::from(format!("{}{}", rootfs, v.to_str().unwrap()));
-            } else {
-                path.pop();
-                for it in v.iter() {
-                    path.push(it);
-                    if path.exists() {
-                        path = path.canonicalize().unwrap();
-                        if !path.starts_with(rootfs) {
-                            path = PathBuf::from(rootfs.to_string());
-                        }
-                    }
-                }
-            }
-        }
-        // skip any ".."
-        if path.ends_with("..") {
-            path.pop();
-        }
-    }
+fn secure_join(rootfs: &str, unsafe_path: &str) -> Result<String> {
+    let joined = scoped_join(rootfs, unsafe_path).with_context(|| {
+        format!(
+            "failed to securely join {:?} to rootfs {:?}",
+            unsafe_path, rootfs
+        )
+    })?;
 
-    path.to_str().unwrap().to_string()
+    joined
+        .to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("joined path {:?} is not valid UTF-8", joined))
 }
 
 fn mount_from(
@@ -811,7 +788,7 @@ fn mount_from(
     let mut d = String::from(data);
     let mount_dest = m.destination().display().to_string();
     let mount_typ = m.typ().as_ref().unwrap();
-    let dest = secure_join(rootfs, &mount_dest);
+    let dest = secure_join(rootfs, &mount_dest)?;
 
     let mount_source = m.source().as_ref().unwrap().display().to_string();
     let src = if mount_typ == "bind" {
@@ -1610,7 +1587,8 @@ mod tests {
             rootfs: &'a str,
             unsafe_path: &'a str,
             symlink_path: &'a str,
-            result: &'a str,
+            // Expected result, or None if secure_join() is expected to fail.
+            result: Option<&'a str>,
         }
 
         // create tempory directory to simulate container rootfs with symlink
@@ -1619,53 +1597,53 @@ mod tests {
 
         let tests = &[
             TestData {
-                name: "rootfs_not_exist",
+                name: "rootfs does not exist",
                 rootfs: "/home/rootfs",
                 unsafe_path: "a/b/c",
                 symlink_path: "",
-                result: "/home/rootfs/a/b/c",
+                result: None,
             },
             TestData {
-                name: "relative_path",
-                rootfs: "/home/rootfs",
-                unsafe_path: "../../../a/b/c",
+                name: "plain relative path",
+                rootfs: rootfs_path,
+                unsafe_path: "a/b/c",
                 symlink_path: "",
-                result: "/home/rootfs/a/b/c",
+                result: Some(&format!("{}/a/b/c", rootfs_path)),
             },
             TestData {
-                name: "skip any ..",
-                rootfs: "/home/rootfs",
-                unsafe_path: "../../../a/../../b/../../c",
+                name: "relative path escaping the rootfs",
+                rootfs: rootfs_path,
+                unsafe_path: "../../../a/b/c",
                 symlink_path: "",
-                result: "/home/rootfs/a/b/c",
+                result: Some(&format!("{}/a/b/c", rootfs_path)),
             },
             TestData {
-                name: "rootfs is null",
-                rootfs: "",
-                unsafe_path: "",
+                name: "interior .. elements constrained to the rootfs",
+                rootfs: rootfs_path,
+                unsafe_path: "../../../a/../../b/../../c",
                 symlink_path: "",
-                result: "/",
+                result: Some(&format!("{}/c", rootfs_path)),
             },
             TestData {
                 name: "relative softlink beyond container rootfs",
                 rootfs: rootfs_path,
                 unsafe_path: "1",
                 symlink_path: "../../../",
-                result: rootfs_path,
+                result: Some(rootfs_path),
             },
             TestData {
                 name: "abs softlink points to the non-exist directory",
                 rootfs: rootfs_path,
                 unsafe_path: "2",
                 symlink_path: "/dddd",
-                result: &format!("{}/dddd", rootfs_path).as_str().to_owned(),
+                result: Some(&format!("{}/dddd", rootfs_path)),
             },
             TestData {
                 name: "abs softlink points to the root",
                 rootfs: rootfs_path,
                 unsafe_path: "3",
                 symlink_path: "/",
-                result: &format!("{}/", rootfs_path).as_str().to_owned(),
+                result: Some(rootfs_path),
             },
         ];
 
@@ -1683,7 +1661,14 @@ mod tests {
             let msg = format!("{}, result: {:?}", msg, result);
 
             // Perform the checks
-            assert!(result == t.result, "{}", msg);
+            match t.result {
+                Some(expected) => {
+                    let result = result.unwrap();
+                    // Compare as paths so a trailing '/' is not significant.
+                    assert_eq!(Path::new(&result), Path::new(expected), "{}", msg);
+                }
+                None => assert!(result.is_err(), "{}", msg),
+            }
         }
     }
 
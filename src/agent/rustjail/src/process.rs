@@ -55,6 +55,10 @@ pub struct ProcessIo {
     pub stdin: Option<VsockStream>,
     pub stdout: Option<VsockStream>,
     pub stderr: Option<VsockStream>,
+    // stdout and stderr of a non-TTY process share the stdout stream,
+    // with each chunk wrapped in a frame that tags its origin (see the
+    // framed_io module).
+    pub framed_output: bool,
     // used to wait for all process outputs to be copied to the vsock streams
     // only used when tty is used.
     pub wg_output: WaitGroup,
@@ -70,6 +74,7 @@ impl ProcessIo {
             stdin,
             stdout,
             stderr,
+            framed_output: false,
             wg_output: WaitGroup::new(),
         }
     }
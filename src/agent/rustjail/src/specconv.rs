@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use oci_spec::runtime::Spec;
+use oci_spec::runtime::{Linux, Spec};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct CreateOpts {
@@ -16,3 +16,89 @@ pub struct CreateOpts {
     pub rootless_cgroup: bool,
     pub container_name: String,
 }
+
+/// Merge additional paths to mask and to remount read-only into the
+/// spec's linux section, on top of whatever the image or the runtime
+/// already requested. The merged lists are applied by `finish_rootfs()`
+/// like the OCI defaults, so hardening a sandbox does not require
+/// rebuilding its images.
+pub fn append_protected_paths(spec: &mut Spec, masked: &[String], readonly: &[String]) {
+    if masked.is_empty() && readonly.is_empty() {
+        return;
+    }
+
+    if spec.linux().is_none() {
+        spec.set_linux(Some(Linux::default()));
+    }
+    // Safe to unwrap() because of the check above.
+    let linux = spec.linux_mut().as_mut().unwrap();
+
+    if !masked.is_empty() {
+        let mut paths = linux.masked_paths().clone().unwrap_or_default();
+        for path in masked {
+            if !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+        linux.set_masked_paths(Some(paths));
+    }
+
+    if !readonly.is_empty() {
+        let mut paths = linux.readonly_paths().clone().unwrap_or_default();
+        for path in readonly {
+            if !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+        linux.set_readonly_paths(Some(paths));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_protected_paths() {
+        let mut spec = Spec::default();
+        spec.set_linux(Some(Linux::default()));
+        spec.linux_mut()
+            .as_mut()
+            .unwrap()
+            .set_masked_paths(Some(vec!["/proc/kcore".to_string()]));
+
+        append_protected_paths(
+            &mut spec,
+            &["/proc/kcore".to_string(), "/proc/keys".to_string()],
+            &["/proc/sys/kernel".to_string()],
+        );
+
+        let linux = spec.linux().as_ref().unwrap();
+        // Duplicates are not appended twice.
+        assert_eq!(
+            linux.masked_paths().as_ref().unwrap(),
+            &vec!["/proc/kcore".to_string(), "/proc/keys".to_string()]
+        );
+        assert_eq!(
+            linux.readonly_paths().as_ref().unwrap(),
+            &vec!["/proc/sys/kernel".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_append_protected_paths_no_linux_section() {
+        let mut spec = Spec::default();
+        spec.set_linux(None);
+
+        // Empty lists leave the spec untouched.
+        append_protected_paths(&mut spec, &[], &[]);
+        assert!(spec.linux().is_none());
+
+        append_protected_paths(&mut spec, &["/proc/keys".to_string()], &[]);
+        let linux = spec.linux().as_ref().unwrap();
+        assert_eq!(
+            linux.masked_paths().as_ref().unwrap(),
+            &vec!["/proc/keys".to_string()]
+        );
+    }
+}
@@ -15,7 +15,7 @@ use anyhow::{anyhow, Context};
 //   - <xxxx> is an adapter domain ID
 // [1] https://www.ibm.com/docs/en/linuxonibm/pdf/lku5dd05.pdf
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Address {
     pub adapter_id: u8,
     pub adapter_domain: u16,
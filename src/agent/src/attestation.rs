@@ -0,0 +1,345 @@
+// Copyright (c) 2026 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+// Attestation-agent client wrapper and workload-facing proxy.
+//
+// The attestation-agent owns the TEE evidence devices, so workloads must
+// not talk to it (or the devices) directly. When
+// `agent.enable_attestation_proxy` is set, the kata-agent serves a small
+// ttrpc socket inside the guest and forwards only the read-only
+// GetEvidence/GetToken calls to the attestation-agent; everything else of
+// the attestation-agent API stays private to the guest components.
+
+use crate::rpc::ttrpc_error;
+use crate::AGENT_CONFIG;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use protocols::{
+    attestation_agent, attestation_agent_ttrpc_async,
+    attestation_agent_ttrpc_async::AttestationAgentServiceClient,
+};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+// The proxy accepts runtime data up to this size. Relying parties only
+// ever bind digests or nonces into the evidence, so anything larger is a
+// misuse of the attestation-agent rather than a legitimate request.
+const MAX_RUNTIME_DATA_SIZE: usize = 4096;
+
+// Nanoseconds; the attestation-agent shares the guest components API
+// timeout configured for the CDH.
+lazy_static! {
+    static ref AA_API_TIMEOUT: i64 = AGENT_CONFIG.cdh_api_timeout.as_nanos() as i64;
+    pub static ref AA_CLIENT: OnceCell<AAClient> = OnceCell::new();
+}
+
+// Convenience function to obtain the scope logger.
+fn sl() -> slog::Logger {
+    slog_scope::logger().new(o!("subsystem" => "attestation"))
+}
+
+#[derive(Clone)]
+pub struct AAClient {
+    attestation_client: AttestationAgentServiceClient,
+}
+
+impl AAClient {
+    pub fn new(aa_socket_uri: &str) -> Result<Self> {
+        let client = ttrpc::asynchronous::Client::connect(aa_socket_uri)?;
+        let attestation_client =
+            attestation_agent_ttrpc_async::AttestationAgentServiceClient::new(client);
+        Ok(AAClient { attestation_client })
+    }
+}
+
+pub async fn init_aa_client(aa_socket_uri: &str) -> Result<()> {
+    AA_CLIENT
+        .get_or_try_init(|| async {
+            AAClient::new(aa_socket_uri).context("Failed to create attestation-agent client")
+        })
+        .await?;
+    Ok(())
+}
+
+/// Check if the attestation-agent client is initialized
+pub async fn is_aa_client_initialized() -> bool {
+    AA_CLIENT.get().is_some()
+}
+
+// The proxy service itself: each call is validated and then forwarded
+// verbatim to the attestation-agent.
+struct ProxyService;
+
+fn aa_client() -> ttrpc::Result<&'static AAClient> {
+    AA_CLIENT.get().ok_or_else(|| {
+        ttrpc_error(
+            ttrpc::Code::UNAVAILABLE,
+            "attestation-agent not initialized",
+        )
+    })
+}
+
+#[async_trait]
+impl attestation_agent_ttrpc_async::AttestationAgentService for ProxyService {
+    async fn get_evidence(
+        &self,
+        _ctx: &::ttrpc::asynchronous::TtrpcContext,
+        req: attestation_agent::GetEvidenceRequest,
+    ) -> ttrpc::Result<attestation_agent::GetEvidenceResponse> {
+        info!(sl(), "proxying GetEvidence request");
+
+        if req.RuntimeData.len() > MAX_RUNTIME_DATA_SIZE {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                format!(
+                    "runtime data size {} exceeds the limit of {} bytes",
+                    req.RuntimeData.len(),
+                    MAX_RUNTIME_DATA_SIZE
+                ),
+            ));
+        }
+
+        aa_client()?
+            .attestation_client
+            .get_evidence(ttrpc::context::with_timeout(*AA_API_TIMEOUT), &req)
+            .await
+    }
+
+    async fn get_token(
+        &self,
+        _ctx: &::ttrpc::asynchronous::TtrpcContext,
+        req: attestation_agent::GetTokenRequest,
+    ) -> ttrpc::Result<attestation_agent::GetTokenResponse> {
+        info!(sl(), "proxying GetToken request"; "token_type" => req.TokenType.as_str());
+
+        if req.TokenType.is_empty() {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                "empty token type",
+            ));
+        }
+
+        aa_client()?
+            .attestation_client
+            .get_token(ttrpc::context::with_timeout(*AA_API_TIMEOUT), &req)
+            .await
+    }
+
+    async fn extend_runtime_measurement(
+        &self,
+        _ctx: &::ttrpc::asynchronous::TtrpcContext,
+        _req: attestation_agent::ExtendRuntimeMeasurementRequest,
+    ) -> ttrpc::Result<attestation_agent::ExtendRuntimeMeasurementResponse> {
+        // Extending a register mutates the TEE state: only the kata-agent
+        // itself may do that, workloads get the read-only calls.
+        Err(ttrpc_error(
+            ttrpc::Code::PERMISSION_DENIED,
+            "ExtendRuntimeMeasurement is not proxied to workloads",
+        ))
+    }
+}
+
+/// Runtime measurement log. Events that change the trusted state of a
+/// running sandbox (e.g. a policy replaced through SetPolicy) are
+/// appended here as `<domain> <event> <digest>` lines, so the
+/// attestation-agent can fold them into the TEE evidence and a relying
+/// party can replay what happened after launch.
+pub const MEASUREMENT_LOG_PATH: &str = "/run/confidential-containers/measurement-log";
+
+/// Append an entry to the runtime measurement log.
+pub fn append_measurement(domain: &str, event: &str, digest: &str) -> Result<()> {
+    use std::io::Write;
+
+    let path = std::path::Path::new(MEASUREMENT_LOG_PATH);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("open measurement log")?;
+    writeln!(log, "{} {} {}", domain, event, digest).context("append measurement log")?;
+
+    info!(sl(), "measurement recorded";
+        "domain" => domain, "event" => event, "digest" => digest);
+    Ok(())
+}
+
+/// Record an event in the measurement log and extend the platform
+/// runtime measurement register (a vTPM PCR or a TDX RTMR, whichever the
+/// attestation-agent drives) with it. The log entry is written first so
+/// it never lacks an event the register saw; when no attestation-agent
+/// is running (no TEE, offline mode) the log is the only record.
+pub async fn extend_runtime_measurement(domain: &str, event: &str, digest: &str) -> Result<()> {
+    append_measurement(domain, event, digest)?;
+
+    match AA_CLIENT.get() {
+        Some(client) => {
+            let mut req = attestation_agent::ExtendRuntimeMeasurementRequest::new();
+            req.set_Domain(domain.to_string());
+            req.set_Operation(event.to_string());
+            req.set_Content(digest.to_string());
+            client
+                .attestation_client
+                .extend_runtime_measurement(ttrpc::context::with_timeout(*AA_API_TIMEOUT), &req)
+                .await
+                .context("extend runtime measurement register")?;
+        }
+        None => debug!(
+            sl(),
+            "no attestation-agent: measurement only recorded in the log";
+            "domain" => domain, "event" => event,
+        ),
+    }
+
+    Ok(())
+}
+
+/// Start the attestation proxy server on the given socket URI. The caller
+/// owns the returned server and is responsible for shutting it down.
+pub async fn start_proxy(proxy_socket_uri: &str) -> Result<ttrpc::asynchronous::Server> {
+    let service = Box::new(ProxyService {})
+        as Box<dyn attestation_agent_ttrpc_async::AttestationAgentService + Send + Sync>;
+    let service =
+        attestation_agent_ttrpc_async::create_attestation_agent_service(Arc::new(service));
+
+    let mut server = ttrpc::asynchronous::Server::new()
+        .bind(proxy_socket_uri)
+        .context("bind attestation proxy socket")?
+        .register_service(service);
+
+    server.start().await.context("start attestation proxy")?;
+    info!(sl(), "attestation proxy started"; "socket" => proxy_socket_uri);
+
+    Ok(server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::attestation_agent_ttrpc_async::AttestationAgentService;
+    use test_utils::skip_if_not_root;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    struct TestService;
+
+    #[async_trait]
+    impl attestation_agent_ttrpc_async::AttestationAgentService for TestService {
+        async fn get_evidence(
+            &self,
+            _ctx: &::ttrpc::asynchronous::TtrpcContext,
+            _req: attestation_agent::GetEvidenceRequest,
+        ) -> ttrpc::Result<attestation_agent::GetEvidenceResponse> {
+            let mut resp = attestation_agent::GetEvidenceResponse::new();
+            resp.set_Evidence("evidence".into());
+            Ok(resp)
+        }
+
+        async fn get_token(
+            &self,
+            _ctx: &::ttrpc::asynchronous::TtrpcContext,
+            _req: attestation_agent::GetTokenRequest,
+        ) -> ttrpc::Result<attestation_agent::GetTokenResponse> {
+            let mut resp = attestation_agent::GetTokenResponse::new();
+            resp.set_Token("token".into());
+            Ok(resp)
+        }
+    }
+
+    fn start_test_aa_server(aa_socket_uri: String) {
+        tokio::spawn(async move {
+            let service = Box::new(TestService {})
+                as Box<dyn attestation_agent_ttrpc_async::AttestationAgentService + Send + Sync>;
+            let service =
+                attestation_agent_ttrpc_async::create_attestation_agent_service(Arc::new(service));
+
+            let mut server = ttrpc::asynchronous::Server::new()
+                .bind(&aa_socket_uri)
+                .unwrap()
+                .register_service(service);
+
+            server.start().await.unwrap();
+
+            let mut interrupt = signal(SignalKind::interrupt()).unwrap();
+            tokio::select! {
+                _ = interrupt.recv() => {
+                    server.shutdown().await.unwrap();
+                }
+            };
+        });
+    }
+
+    #[tokio::test]
+    async fn test_proxy_validation() {
+        // Validation failures must be reported without consulting the
+        // attestation-agent, so no client is needed.
+        let proxy = ProxyService {};
+        let ctx = ttrpc::asynchronous::TtrpcContext {
+            fd: -1,
+            mh: Default::default(),
+            metadata: Default::default(),
+            timeout_nano: 0,
+        };
+
+        let mut req = attestation_agent::GetEvidenceRequest::new();
+        req.set_RuntimeData(vec![0u8; MAX_RUNTIME_DATA_SIZE + 1]);
+        assert!(proxy.get_evidence(&ctx, req).await.is_err());
+
+        let req = attestation_agent::GetTokenRequest::new();
+        assert!(proxy.get_token(&ctx, req).await.is_err());
+
+        // The mutating call is refused outright, never forwarded.
+        let req = attestation_agent::ExtendRuntimeMeasurementRequest::new();
+        assert!(proxy.extend_runtime_measurement(&ctx, req).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_forwarding() {
+        skip_if_not_root!();
+        let test_dir = tempfile::tempdir().expect("failed to create tmpdir");
+        let aa_socket_uri = format!(
+            "unix://{}",
+            test_dir.path().join("aa.sock").to_str().unwrap()
+        );
+        let proxy_socket_uri = format!(
+            "unix://{}",
+            test_dir.path().join("proxy.sock").to_str().unwrap()
+        );
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = rt.enter();
+        start_test_aa_server(aa_socket_uri.clone());
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        init_aa_client(&aa_socket_uri).await.unwrap();
+        let mut proxy = start_proxy(&proxy_socket_uri).await.unwrap();
+
+        let client = ttrpc::asynchronous::Client::connect(&proxy_socket_uri).unwrap();
+        let client = AttestationAgentServiceClient::new(client);
+
+        let resp = client
+            .get_evidence(
+                ttrpc::context::with_timeout(*AA_API_TIMEOUT),
+                &attestation_agent::GetEvidenceRequest::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.Evidence, b"evidence");
+
+        let mut req = attestation_agent::GetTokenRequest::new();
+        req.set_TokenType("kbs".to_string());
+        let resp = client
+            .get_token(ttrpc::context::with_timeout(*AA_API_TIMEOUT), &req)
+            .await
+            .unwrap();
+        assert_eq!(resp.Token, b"token");
+
+        proxy.shutdown().await.unwrap();
+        rt.shutdown_background();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+    }
+}
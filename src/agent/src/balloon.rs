@@ -0,0 +1,147 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Virtio-balloon free page reporting coordination.
+//!
+//! Free page reporting lets the guest kernel hand freed pages back to the
+//! host as they accumulate, but the kernel only reports pages that are
+//! actually free. When the runtime inflates the balloon to shrink the
+//! sandbox, clean page cache and fragmented free lists can keep most of
+//! the memory unreportable. This module tunes the reporting threshold at
+//! startup and runs reclaim (drop caches, compaction) on request so the
+//! host gets memory back quickly instead of waiting for guest memory
+//! pressure.
+
+use anyhow::{Context, Result};
+use slog::Logger;
+use std::fs;
+use std::path::Path;
+use tracing::instrument;
+
+use crate::linux_abi::{
+    PROC_COMPACTION_PROACTIVENESS, PROC_COMPACT_MEMORY, PROC_DROP_CACHES, SYSFS_NUMA_NODE_PATH,
+    SYSFS_PAGE_REPORTING_ORDER_PATH,
+};
+
+// Report free pages down to pageblock/2 order (typically 2MB/2 = 1MB on
+// x86_64) instead of the kernel default of pageblock order. Smaller
+// chunks get reported sooner after the balloon inflates, at the cost of
+// slightly more reporting traffic.
+const PAGE_REPORTING_ORDER: &str = "5";
+
+// Writing "3" frees both page cache and reclaimable slab objects; dirty
+// pages are untouched, so this is safe without a prior sync.
+const DROP_CACHES_ALL: &str = "3";
+
+/// Lower the free page reporting threshold so freed memory is returned
+/// to the host promptly. A kernel without `CONFIG_PAGE_REPORTING`, or
+/// a guest without a reporting-capable balloon device, is not an error:
+/// reclaim still works, only the asynchronous hinting is lost.
+#[instrument]
+pub fn setup_free_page_reporting(logger: &Logger) -> Result<()> {
+    if !Path::new(SYSFS_PAGE_REPORTING_ORDER_PATH).exists() {
+        info!(
+            logger,
+            "guest kernel does not support free page reporting, skipping setup"
+        );
+        return Ok(());
+    }
+
+    fs::write(SYSFS_PAGE_REPORTING_ORDER_PATH, PAGE_REPORTING_ORDER)
+        .context("set page reporting order")?;
+    info!(
+        logger,
+        "free page reporting enabled with order {}", PAGE_REPORTING_ORDER
+    );
+
+    Ok(())
+}
+
+/// Drop guest kernel caches at the requested level (1 = page cache,
+/// 2 = reclaimable slab objects, 3 = both). Dirty pages are written back
+/// first so the amount of cache left behind does not depend on how much
+/// unsynced data the previous workload produced; benchmarks rely on this
+/// to start every run from a comparable cache state.
+#[instrument]
+pub fn drop_caches(logger: &Logger, level: u32) -> Result<()> {
+    // drop_caches only discards clean pages.
+    nix::unistd::sync();
+
+    fs::write(PROC_DROP_CACHES, level.to_string()).context("drop caches")?;
+    info!(logger, "dropped caches at level {}", level);
+
+    Ok(())
+}
+
+/// Release as much guest memory as possible ahead of a balloon inflate:
+/// optionally drop clean caches, then compact memory so the freed pages
+/// coalesce into chunks large enough for the balloon and for free page
+/// reporting to hand back to the host.
+#[instrument]
+pub fn reclaim_guest_memory(
+    logger: &Logger,
+    drop_caches: bool,
+    compact_memory: bool,
+) -> Result<()> {
+    if drop_caches {
+        fs::write(PROC_DROP_CACHES, DROP_CACHES_ALL).context("drop caches")?;
+        info!(logger, "dropped page cache and slab caches");
+    }
+
+    if compact_memory {
+        // Compaction support depends on CONFIG_COMPACTION; treat an
+        // absent knob as best-effort rather than a failure.
+        if Path::new(PROC_COMPACT_MEMORY).exists() {
+            fs::write(PROC_COMPACT_MEMORY, "1").context("compact memory")?;
+            info!(logger, "triggered memory compaction");
+        } else {
+            info!(logger, "guest kernel does not support memory compaction");
+        }
+    }
+
+    Ok(())
+}
+
+/// Compact guest memory on demand, ahead of hugepage-backed allocations,
+/// memory hotplug or a balloon deflate. An empty node list compacts all
+/// memory; otherwise each listed NUMA node is compacted through its
+/// sysfs `compact` knob. When `proactiveness` is given the kernel keeps
+/// defragmenting in the background afterwards.
+#[instrument]
+pub fn compact_memory(
+    logger: &Logger,
+    numa_nodes: &[i32],
+    proactiveness: Option<u32>,
+) -> Result<()> {
+    if let Some(p) = proactiveness {
+        // Absent on kernels without CONFIG_COMPACTION or older than 5.9;
+        // the synchronous pass below still works, so only log it.
+        if Path::new(PROC_COMPACTION_PROACTIVENESS).exists() {
+            fs::write(PROC_COMPACTION_PROACTIVENESS, p.to_string())
+                .context("set compaction proactiveness")?;
+            info!(logger, "set compaction proactiveness to {}", p);
+        } else {
+            info!(logger, "guest kernel does not support proactive compaction");
+        }
+    }
+
+    if numa_nodes.is_empty() {
+        if !Path::new(PROC_COMPACT_MEMORY).exists() {
+            info!(logger, "guest kernel does not support memory compaction");
+            return Ok(());
+        }
+        fs::write(PROC_COMPACT_MEMORY, "1").context("compact memory")?;
+        info!(logger, "compacted all guest memory");
+        return Ok(());
+    }
+
+    for node in numa_nodes {
+        let path = format!("{}/node{}/compact", SYSFS_NUMA_NODE_PATH, node);
+        fs::write(&path, "1").with_context(|| format!("compact NUMA node {}", node))?;
+    }
+    info!(logger, "compacted NUMA nodes {:?}", numa_nodes);
+
+    Ok(())
+}
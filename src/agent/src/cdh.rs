@@ -8,7 +8,7 @@
 // https://github.com/confidential-containers/guest-components/tree/main/confidential-data-hub
 
 use crate::AGENT_CONFIG;
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use derivative::Derivative;
 use protocols::{
     confidential_data_hub, confidential_data_hub_ttrpc_async,
@@ -26,6 +26,7 @@ lazy_static! {
 }
 
 const SEALED_SECRET_PREFIX: &str = "sealed.";
+const SEALED_ENV_URI_PREFIX: &str = "sealed+kbs://";
 
 // Convenience function to obtain the scope logger.
 fn sl() -> slog::Logger {
@@ -116,6 +117,40 @@ pub async fn unseal_env(env: &str) -> Result<String> {
     Ok((*env.to_owned()).to_string())
 }
 
+/// Resolve an environment entry of the form `KEY=sealed+kbs://...` through
+/// the Confidential Data Hub. These URIs are resolved in the guest right
+/// before the container process is created, so the plaintext never appears
+/// in the host-visible OCI spec. Only keys listed in the
+/// `agent.sealed_env_allowed_keys` allow-list may use this mechanism;
+/// any other key carrying such a URI fails container creation.
+pub async fn unseal_env_uri(env: &str) -> Result<String> {
+    let Some((key, value)) = env.split_once('=') else {
+        return Ok(env.to_string());
+    };
+
+    if !value.starts_with(SEALED_ENV_URI_PREFIX) {
+        return Ok(env.to_string());
+    }
+
+    if !AGENT_CONFIG
+        .sealed_env_allowed_keys
+        .iter()
+        .any(|allowed| allowed == key)
+    {
+        bail!(
+            "env key {} is not listed in agent.sealed_env_allowed_keys, refusing to resolve its sealed env URI",
+            key
+        );
+    }
+
+    let cdh_client = CDH_CLIENT
+        .get()
+        .ok_or_else(|| anyhow!("Confidential Data Hub not initialized"))?;
+
+    let unsealed_value = cdh_client.unseal_secret_async(value).await?;
+    Ok(format!("{}={}", key, std::str::from_utf8(&unsealed_value)?))
+}
+
 pub async fn unseal_file(path: &str) -> Result<()> {
     let cdh_client = CDH_CLIENT
         .get()
@@ -286,6 +321,14 @@ mod tests {
         let unchanged_env = unseal_env(&normal_env).await.unwrap();
         assert_eq!(unchanged_env, String::from("key=testdata"));
 
+        // Sealed env URIs are only resolved for allow-listed keys; with the
+        // default (empty) allow-list any such URI must be rejected, while
+        // other values pass through untouched.
+        let uri_env = String::from("key=sealed+kbs://default/test/secret");
+        assert!(unseal_env_uri(&uri_env).await.is_err());
+        let plain_env = String::from("key=testdata");
+        assert_eq!(unseal_env_uri(&plain_env).await.unwrap(), plain_env);
+
         // Test sealed secret as files
         let sealed_dir = test_dir_path.join("..test");
         fs::create_dir(&sealed_dir).unwrap();
@@ -7,7 +7,9 @@ use anyhow::{anyhow, bail, ensure, Context, Result};
 use serde::Deserialize;
 use std::env;
 use std::fs;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::time;
 use strum_macros::{Display, EnumString};
 use tracing::instrument;
@@ -21,19 +23,47 @@ const TRACE_MODE_OPTION: &str = "agent.trace";
 const LOG_LEVEL_OPTION: &str = "agent.log";
 const SERVER_ADDR_OPTION: &str = "agent.server_addr";
 const PASSFD_LISTENER_PORT: &str = "agent.passfd_listener_port";
+const EXEC_MUX_PORT_OPTION: &str = "agent.exec_mux_port";
+const GUEST_TIME_SYNC_OPTION: &str = "agent.guest_time_sync";
 const HOTPLUG_TIMOUT_OPTION: &str = "agent.hotplug_timeout";
 const CDH_API_TIMOUT_OPTION: &str = "agent.cdh_api_timeout";
 const DEBUG_CONSOLE_VPORT_OPTION: &str = "agent.debug_console_vport";
 const LOG_VPORT_OPTION: &str = "agent.log_vport";
+const SHUTDOWN_REASON_VPORT_OPTION: &str = "agent.shutdown_reason_vport";
 const CONTAINER_PIPE_SIZE_OPTION: &str = "agent.container_pipe_size";
+const MAX_CONTAINERS_OPTION: &str = "agent.max_containers";
+const MAX_EXEC_SESSIONS_OPTION: &str = "agent.max_exec_sessions";
+const EXEC_MUX_BUFFER_LIMIT_OPTION: &str = "agent.exec_mux_buffer_limit";
+const RPC_CONCURRENCY_LIMIT_OPTION: &str = "agent.rpc_concurrency_limit";
+const RPC_RATE_LIMIT_OPTION: &str = "agent.rpc_rate_limit";
+const MAX_OPEN_FILES_OPTION: &str = "agent.max_open_files";
+const MEMORY_ONLINE_MOVABLE_OPTION: &str = "agent.memory_online_movable";
+const OOM_GROUP_OPTION: &str = "agent.oom_group";
+const FUSE_ALLOW_OTHER_OPTION: &str = "agent.fuse_allow_other";
+const SYSCTL_ALLOWED_PREFIXES_OPTION: &str = "agent.sysctl_allowed_prefixes";
+const SYSCTL_DENIED_PREFIXES_OPTION: &str = "agent.sysctl_denied_prefixes";
+const MASKED_PATHS_OPTION: &str = "agent.masked_paths";
+const ALLOWED_ENDPOINTS_OPTION: &str = "agent.allowed_endpoints";
+const DENIED_ENDPOINTS_OPTION: &str = "agent.denied_endpoints";
+const READONLY_PATHS_OPTION: &str = "agent.readonly_paths";
 const CGROUP_NO_V1: &str = "cgroup_no_v1";
 const UNIFIED_CGROUP_HIERARCHY_OPTION: &str = "systemd.unified_cgroup_hierarchy";
 const CONFIG_FILE: &str = "agent.config_file";
+/// Optional overrides layered on top of the other configuration sources
+/// and re-read on every reload. The file typically lives on a
+/// hot-pluggable device mounted by the host, or is written from the
+/// debug console to toggle debug settings on a live sandbox.
+pub const CONFIG_OVERRIDES_FILE: &str = "/run/kata-containers/agent-config-overrides.toml";
 const GUEST_COMPONENTS_REST_API_OPTION: &str = "agent.guest_components_rest_api";
 const GUEST_COMPONENTS_PROCS_OPTION: &str = "agent.guest_components_procs";
 #[cfg(feature = "guest-pull")]
 const IMAGE_REGISTRY_AUTH_OPTION: &str = "agent.image_registry_auth";
 const SECURE_STORAGE_INTEGRITY_OPTION: &str = "agent.secure_storage_integrity";
+const SEALED_ENV_ALLOWED_KEYS_OPTION: &str = "agent.sealed_env_allowed_keys";
+const ENABLE_ATTESTATION_PROXY_OPTION: &str = "agent.enable_attestation_proxy";
+const OFFLINE_CONFIDENTIAL_OPTION: &str = "agent.offline_confidential";
+const WRITABLE_LAYER_INTEGRITY_OPTION: &str = "agent.writable_layer_integrity";
+const CONFIDENTIAL_OPTION: &str = "agent.confidential";
 
 #[cfg(feature = "guest-pull")]
 const ENABLE_SIGNATURE_VERIFICATION: &str = "agent.enable_signature_verification";
@@ -41,6 +71,9 @@ const ENABLE_SIGNATURE_VERIFICATION: &str = "agent.enable_signature_verification
 #[cfg(feature = "guest-pull")]
 const IMAGE_POLICY_FILE: &str = "agent.image_policy_file";
 
+#[cfg(feature = "guest-pull")]
+const IMAGE_LAYER_CACHE_SIZE_OPTION: &str = "agent.image_layer_cache_size";
+
 // Configure the proxy settings for HTTPS requests in the guest,
 // to solve the problem of not being able to access the specified image in some cases.
 const HTTPS_PROXY: &str = "agent.https_proxy";
@@ -71,6 +104,25 @@ const DEFAULT_LOG_LEVEL: slog::Level = slog::Level::Info;
 const DEFAULT_HOTPLUG_TIMEOUT: time::Duration = time::Duration::from_secs(3);
 const DEFAULT_CDH_API_TIMEOUT: time::Duration = time::Duration::from_secs(50);
 const DEFAULT_CONTAINER_PIPE_SIZE: i32 = 0;
+// A zero limit means "no ceiling enforced".
+const DEFAULT_MAX_CONTAINERS: u32 = 0;
+const DEFAULT_MAX_EXEC_SESSIONS: u32 = 0;
+const DEFAULT_EXEC_MUX_BUFFER_LIMIT: u64 = 0;
+const DEFAULT_RPC_CONCURRENCY_LIMIT: u32 = 0;
+const DEFAULT_RPC_RATE_LIMIT: u32 = 0;
+const DEFAULT_MAX_OPEN_FILES: u64 = 0;
+#[cfg(feature = "guest-pull")]
+const DEFAULT_IMAGE_LAYER_CACHE_SIZE: u64 = 0;
+// Sysctl name prefixes containers may set, matching the namespaced
+// sysctls runc considers safe. An empty denylist means nothing extra is
+// blocked beyond keys outside the allowed prefixes.
+const DEFAULT_SYSCTL_ALLOWED_PREFIXES: &[&str] = &[
+    "kernel.shm",
+    "kernel.msg",
+    "kernel.sem",
+    "fs.mqueue.",
+    "net.",
+];
 const VSOCK_ADDR: &str = "vsock://-1";
 
 // Environment variables used for development and testing
@@ -81,6 +133,8 @@ const TRACING_ENV_VAR: &str = "KATA_AGENT_TRACING";
 // Policy file environment variable to pass a policy document
 // to initialize agent policy engine.
 const POLICY_FILE_VAR: &str = "KATA_AGENT_POLICY_FILE";
+#[cfg(feature = "agent-policy")]
+const POLICY_AUDIT_OPTION: &str = "agent.policy_audit";
 
 const ERR_INVALID_LOG_LEVEL: &str = "invalid log level";
 const ERR_INVALID_LOG_LEVEL_PARAM: &str = "invalid log level parameter";
@@ -134,9 +188,27 @@ pub struct AgentConfig {
     pub cdh_api_timeout: time::Duration,
     pub debug_console_vport: i32,
     pub log_vport: i32,
+    pub shutdown_reason_vport: i32,
     pub container_pipe_size: i32,
+    pub max_containers: u32,
+    pub max_exec_sessions: u32,
+    pub exec_mux_buffer_limit: u64,
+    pub rpc_concurrency_limit: u32,
+    pub rpc_rate_limit: u32,
+    pub max_open_files: u64,
+    pub memory_online_movable: bool,
+    pub oom_group: bool,
+    pub fuse_allow_other: bool,
+    pub sysctl_allowed_prefixes: Vec<String>,
+    pub sysctl_denied_prefixes: Vec<String>,
+    pub masked_paths: Vec<String>,
+    pub readonly_paths: Vec<String>,
+    pub allowed_endpoints: Vec<String>,
+    pub denied_endpoints: Vec<String>,
     pub server_addr: String,
     pub passfd_listener_port: i32,
+    pub exec_mux_port: i32,
+    pub guest_time_sync: bool,
     pub cgroup_no_v1: String,
     pub unified_cgroup_hierarchy: bool,
     pub tracing: bool,
@@ -148,12 +220,21 @@ pub struct AgentConfig {
     #[cfg(feature = "guest-pull")]
     pub image_registry_auth: String,
     pub secure_storage_integrity: bool,
+    pub sealed_env_allowed_keys: Vec<String>,
+    pub enable_attestation_proxy: bool,
+    pub offline_confidential: bool,
+    pub writable_layer_integrity: bool,
+    pub confidential: bool,
     #[cfg(feature = "guest-pull")]
     pub enable_signature_verification: bool,
     #[cfg(feature = "guest-pull")]
     pub image_policy_file: String,
+    #[cfg(feature = "guest-pull")]
+    pub image_layer_cache_size: u64,
     #[cfg(feature = "agent-policy")]
     pub policy_file: String,
+    #[cfg(feature = "agent-policy")]
+    pub policy_audit: bool,
     pub mem_agent: Option<MemAgentConfig>,
 }
 
@@ -172,9 +253,27 @@ pub struct AgentConfigBuilder {
     pub cdh_api_timeout: Option<time::Duration>,
     pub debug_console_vport: Option<i32>,
     pub log_vport: Option<i32>,
+    pub shutdown_reason_vport: Option<i32>,
     pub container_pipe_size: Option<i32>,
+    pub max_containers: Option<u32>,
+    pub max_exec_sessions: Option<u32>,
+    pub exec_mux_buffer_limit: Option<u64>,
+    pub rpc_concurrency_limit: Option<u32>,
+    pub rpc_rate_limit: Option<u32>,
+    pub max_open_files: Option<u64>,
+    pub memory_online_movable: Option<bool>,
+    pub oom_group: Option<bool>,
+    pub fuse_allow_other: Option<bool>,
+    pub sysctl_allowed_prefixes: Option<Vec<String>>,
+    pub sysctl_denied_prefixes: Option<Vec<String>>,
+    pub masked_paths: Option<Vec<String>>,
+    pub readonly_paths: Option<Vec<String>>,
+    pub allowed_endpoints: Option<Vec<String>>,
+    pub denied_endpoints: Option<Vec<String>>,
     pub server_addr: Option<String>,
     pub passfd_listener_port: Option<i32>,
+    pub exec_mux_port: Option<i32>,
+    pub guest_time_sync: Option<bool>,
     pub unified_cgroup_hierarchy: Option<bool>,
     pub tracing: Option<bool>,
     pub https_proxy: Option<String>,
@@ -184,12 +283,21 @@ pub struct AgentConfigBuilder {
     #[cfg(feature = "guest-pull")]
     pub image_registry_auth: Option<String>,
     pub secure_storage_integrity: Option<bool>,
+    pub sealed_env_allowed_keys: Option<Vec<String>>,
+    pub enable_attestation_proxy: Option<bool>,
+    pub offline_confidential: Option<bool>,
+    pub writable_layer_integrity: Option<bool>,
+    pub confidential: Option<bool>,
     #[cfg(feature = "guest-pull")]
     pub enable_signature_verification: Option<bool>,
     #[cfg(feature = "guest-pull")]
     pub image_policy_file: Option<String>,
+    #[cfg(feature = "guest-pull")]
+    pub image_layer_cache_size: Option<u64>,
     #[cfg(feature = "agent-policy")]
     pub policy_file: Option<String>,
+    #[cfg(feature = "agent-policy")]
+    pub policy_audit: Option<bool>,
     pub mem_agent_enable: Option<bool>,
     pub mem_agent_memcg_disable: Option<bool>,
     pub mem_agent_memcg_swap: Option<bool>,
@@ -270,9 +378,30 @@ impl Default for AgentConfig {
             cdh_api_timeout: DEFAULT_CDH_API_TIMEOUT,
             debug_console_vport: 0,
             log_vport: 0,
+            shutdown_reason_vport: 0,
             container_pipe_size: DEFAULT_CONTAINER_PIPE_SIZE,
+            max_containers: DEFAULT_MAX_CONTAINERS,
+            max_exec_sessions: DEFAULT_MAX_EXEC_SESSIONS,
+            exec_mux_buffer_limit: DEFAULT_EXEC_MUX_BUFFER_LIMIT,
+            rpc_concurrency_limit: DEFAULT_RPC_CONCURRENCY_LIMIT,
+            rpc_rate_limit: DEFAULT_RPC_RATE_LIMIT,
+            max_open_files: DEFAULT_MAX_OPEN_FILES,
+            memory_online_movable: false,
+            oom_group: false,
+            fuse_allow_other: false,
+            sysctl_allowed_prefixes: DEFAULT_SYSCTL_ALLOWED_PREFIXES
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            sysctl_denied_prefixes: Vec::new(),
+            masked_paths: Vec::new(),
+            readonly_paths: Vec::new(),
+            allowed_endpoints: Vec::new(),
+            denied_endpoints: Vec::new(),
             server_addr: format!("{}:{}", VSOCK_ADDR, DEFAULT_AGENT_VSOCK_PORT),
             passfd_listener_port: 0,
+            exec_mux_port: 0,
+            guest_time_sync: false,
             cgroup_no_v1: String::from(""),
             unified_cgroup_hierarchy: false,
             tracing: false,
@@ -284,26 +413,92 @@ impl Default for AgentConfig {
             #[cfg(feature = "guest-pull")]
             image_registry_auth: String::from(""),
             secure_storage_integrity: false,
+            sealed_env_allowed_keys: Vec::new(),
+            enable_attestation_proxy: false,
+            offline_confidential: false,
+            writable_layer_integrity: false,
+            confidential: false,
             #[cfg(feature = "guest-pull")]
             enable_signature_verification: false,
             #[cfg(feature = "guest-pull")]
             image_policy_file: String::from(""),
+            #[cfg(feature = "guest-pull")]
+            image_layer_cache_size: DEFAULT_IMAGE_LAYER_CACHE_SIZE,
             #[cfg(feature = "agent-policy")]
             policy_file: String::from(""),
+            #[cfg(feature = "agent-policy")]
+            policy_audit: false,
             mem_agent: None,
         }
     }
 }
 
+/// Handle to the live agent configuration.
+///
+/// The configuration is rebuilt from its sources and swapped wholesale
+/// on reload; dereferencing the handle yields the current snapshot.
+/// Replaced snapshots are intentionally leaked because concurrent RPC
+/// handlers may still hold references into them - reloads are rare
+/// enough that the leak doesn't matter.
+pub struct ConfigHandle {
+    current: AtomicPtr<AgentConfig>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: AgentConfig) -> Self {
+        ConfigHandle {
+            current: AtomicPtr::new(Box::into_raw(Box::new(config))),
+        }
+    }
+
+    fn update(&self, config: AgentConfig) {
+        self.current
+            .store(Box::into_raw(Box::new(config)), Ordering::Release);
+    }
+}
+
+impl std::ops::Deref for ConfigHandle {
+    type Target = AgentConfig;
+
+    fn deref(&self) -> &AgentConfig {
+        // Safe because the pointer always comes from Box::into_raw and
+        // the pointee is never freed.
+        unsafe { &*self.current.load(Ordering::Acquire) }
+    }
+}
+
+/// Rebuild the agent configuration from all of its layered sources and
+/// swap the result into the live handle. Readers pick up the new values
+/// on their next access; values already copied out (e.g. ports of
+/// listeners that are running) keep their old settings.
+pub fn reload(logger: &slog::Logger) -> Result<()> {
+    let new_config = AgentConfig::from_cmdline("/proc/cmdline", env::args().collect())
+        .context("reload agent config")?;
+    info!(logger, "agent configuration reloaded";
+        "config" => format!("{:?}", new_config));
+    crate::AGENT_CONFIG.update(new_config);
+    Ok(())
+}
+
 impl FromStr for AgentConfig {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut agent_config: AgentConfig = Default::default();
+        agent_config.override_from_str(s)?;
+        Ok(agent_config)
+    }
+}
+
+impl AgentConfig {
+    /// Layer TOML configuration from `s` over the current values. Only
+    /// the options present in `s` are touched.
+    fn override_from_str(&mut self, s: &str) -> Result<()> {
         let agent_config_builder: AgentConfigBuilder =
             toml::from_str(s).map_err(anyhow::Error::new)?;
-        let mut agent_config: AgentConfig = Default::default();
+        let agent_config = self;
 
-        // Overwrite default values with the configuration files ones.
+        // Overwrite the current values with the configuration file ones.
         config_override!(agent_config_builder, agent_config, debug_console);
         config_override!(agent_config_builder, agent_config, dev_mode);
         config_override!(
@@ -316,9 +511,27 @@ impl FromStr for AgentConfig {
         config_override!(agent_config_builder, agent_config, cdh_api_timeout);
         config_override!(agent_config_builder, agent_config, debug_console_vport);
         config_override!(agent_config_builder, agent_config, log_vport);
+        config_override!(agent_config_builder, agent_config, shutdown_reason_vport);
         config_override!(agent_config_builder, agent_config, container_pipe_size);
+        config_override!(agent_config_builder, agent_config, max_containers);
+        config_override!(agent_config_builder, agent_config, max_exec_sessions);
+        config_override!(agent_config_builder, agent_config, exec_mux_buffer_limit);
+        config_override!(agent_config_builder, agent_config, rpc_concurrency_limit);
+        config_override!(agent_config_builder, agent_config, rpc_rate_limit);
+        config_override!(agent_config_builder, agent_config, max_open_files);
+        config_override!(agent_config_builder, agent_config, memory_online_movable);
+        config_override!(agent_config_builder, agent_config, oom_group);
+        config_override!(agent_config_builder, agent_config, fuse_allow_other);
+        config_override!(agent_config_builder, agent_config, sysctl_allowed_prefixes);
+        config_override!(agent_config_builder, agent_config, sysctl_denied_prefixes);
+        config_override!(agent_config_builder, agent_config, masked_paths);
+        config_override!(agent_config_builder, agent_config, readonly_paths);
+        config_override!(agent_config_builder, agent_config, allowed_endpoints);
+        config_override!(agent_config_builder, agent_config, denied_endpoints);
         config_override!(agent_config_builder, agent_config, server_addr);
         config_override!(agent_config_builder, agent_config, passfd_listener_port);
+        config_override!(agent_config_builder, agent_config, exec_mux_port);
+        config_override!(agent_config_builder, agent_config, guest_time_sync);
         config_override!(agent_config_builder, agent_config, unified_cgroup_hierarchy);
         config_override!(agent_config_builder, agent_config, tracing);
         config_override!(agent_config_builder, agent_config, https_proxy);
@@ -338,11 +551,19 @@ impl FromStr for AgentConfig {
                 enable_signature_verification
             );
             config_override!(agent_config_builder, agent_config, image_policy_file);
+            config_override!(agent_config_builder, agent_config, image_layer_cache_size);
         }
         config_override!(agent_config_builder, agent_config, secure_storage_integrity);
+        config_override!(agent_config_builder, agent_config, sealed_env_allowed_keys);
+        config_override!(agent_config_builder, agent_config, enable_attestation_proxy);
+        config_override!(agent_config_builder, agent_config, offline_confidential);
+        config_override!(agent_config_builder, agent_config, writable_layer_integrity);
+        config_override!(agent_config_builder, agent_config, confidential);
 
         #[cfg(feature = "agent-policy")]
         config_override!(agent_config_builder, agent_config, policy_file);
+        #[cfg(feature = "agent-policy")]
+        config_override!(agent_config_builder, agent_config, policy_audit);
 
         if agent_config_builder.mem_agent_enable.unwrap_or(false) {
             let mut mac = MemAgentConfig::default();
@@ -412,14 +633,49 @@ impl FromStr for AgentConfig {
             agent_config.mem_agent = Some(mac);
         }
 
-        Ok(agent_config)
+        Ok(())
+    }
+
+    /// Build the configuration by merging all of its layered sources:
+    /// the kernel command line, the bootstrap configuration file it may
+    /// point at (or the one given with `--config`), environment
+    /// variables and, last, the runtime overrides file.
+    pub fn from_cmdline(file: &str, args: Vec<String>) -> Result<AgentConfig> {
+        let mut config = AgentConfig::from_bootstrap_sources(file, args)?;
+
+        if Path::new(CONFIG_OVERRIDES_FILE).exists() {
+            let overrides = fs::read_to_string(CONFIG_OVERRIDES_FILE).with_context(|| {
+                format!("Failed to read overrides file {}", CONFIG_OVERRIDES_FILE)
+            })?;
+            config
+                .override_from_str(&overrides)
+                .context("AgentConfig overrides file")?;
+        }
+
+        config.apply_confidential_hardening();
+        Ok(config)
+    }
+
+    // Collapse the confidential hardening profile into the individual
+    // options it implies. Runs after every configuration source has been
+    // layered, so no later source can re-enable a debug surface the
+    // profile disabled.
+    fn apply_confidential_hardening(&mut self) {
+        if !self.confidential {
+            return;
+        }
+
+        self.debug_console = false;
+        for ep in ["CopyFile", "SetPolicy"] {
+            if !self.denied_endpoints.iter().any(|e| e == ep) {
+                self.denied_endpoints.push(ep.to_string());
+            }
+        }
     }
-}
 
-impl AgentConfig {
     #[instrument]
     #[allow(clippy::redundant_closure_call)]
-    pub fn from_cmdline(file: &str, args: Vec<String>) -> Result<AgentConfig> {
+    fn from_bootstrap_sources(file: &str, args: Vec<String>) -> Result<AgentConfig> {
         // If config file specified in the args, generate our config from it
         let config_position = args.iter().position(|a| a == "--config" || a == "-c");
         if let Some(config_position) = config_position {
@@ -504,6 +760,13 @@ impl AgentConfig {
                 get_number_value,
                 |port: &i32| *port > 0
             );
+            parse_cmdline_param!(
+                param,
+                SHUTDOWN_REASON_VPORT_OPTION,
+                config.shutdown_reason_vport,
+                get_number_value,
+                |port: &i32| *port > 0
+            );
             parse_cmdline_param!(
                 param,
                 PASSFD_LISTENER_PORT,
@@ -511,12 +774,122 @@ impl AgentConfig {
                 get_number_value,
                 |port: &i32| *port > 0
             );
+            parse_cmdline_param!(
+                param,
+                EXEC_MUX_PORT_OPTION,
+                config.exec_mux_port,
+                get_number_value,
+                |port: &i32| *port > 0
+            );
             parse_cmdline_param!(
                 param,
                 CONTAINER_PIPE_SIZE_OPTION,
                 config.container_pipe_size,
                 get_container_pipe_size
             );
+            // resource ceilings should be positive values; zero (the
+            // default) keeps the limit disabled
+            parse_cmdline_param!(
+                param,
+                MAX_CONTAINERS_OPTION,
+                config.max_containers,
+                get_number_value,
+                |limit: &u32| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                MAX_EXEC_SESSIONS_OPTION,
+                config.max_exec_sessions,
+                get_number_value,
+                |limit: &u32| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                EXEC_MUX_BUFFER_LIMIT_OPTION,
+                config.exec_mux_buffer_limit,
+                get_number_value,
+                |limit: &u64| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                RPC_CONCURRENCY_LIMIT_OPTION,
+                config.rpc_concurrency_limit,
+                get_number_value,
+                |limit: &u32| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                RPC_RATE_LIMIT_OPTION,
+                config.rpc_rate_limit,
+                get_number_value,
+                |limit: &u32| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                MAX_OPEN_FILES_OPTION,
+                config.max_open_files,
+                get_number_value,
+                |limit: &u64| *limit > 0
+            );
+            parse_cmdline_param!(
+                param,
+                MEMORY_ONLINE_MOVABLE_OPTION,
+                config.memory_online_movable,
+                get_bool_value
+            );
+            parse_cmdline_param!(param, OOM_GROUP_OPTION, config.oom_group, get_bool_value);
+            parse_cmdline_param!(
+                param,
+                FUSE_ALLOW_OTHER_OPTION,
+                config.fuse_allow_other,
+                get_bool_value
+            );
+            parse_cmdline_param!(
+                param,
+                GUEST_TIME_SYNC_OPTION,
+                config.guest_time_sync,
+                get_bool_value
+            );
+            parse_cmdline_param!(
+                param,
+                SYSCTL_ALLOWED_PREFIXES_OPTION,
+                config.sysctl_allowed_prefixes,
+                get_string_list_value
+            );
+            parse_cmdline_param!(
+                param,
+                SYSCTL_DENIED_PREFIXES_OPTION,
+                config.sysctl_denied_prefixes,
+                get_string_list_value
+            );
+            // extra paths masked or made read-only in every container,
+            // on top of whatever the OCI spec already requests
+            parse_cmdline_param!(
+                param,
+                MASKED_PATHS_OPTION,
+                config.masked_paths,
+                get_string_list_value
+            );
+            parse_cmdline_param!(
+                param,
+                READONLY_PATHS_OPTION,
+                config.readonly_paths,
+                get_string_list_value
+            );
+            // RPC endpoints allowed or denied independently of the policy
+            // engine; an empty allow list permits every endpoint
+            parse_cmdline_param!(
+                param,
+                ALLOWED_ENDPOINTS_OPTION,
+                config.allowed_endpoints,
+                get_string_list_value
+            );
+            parse_cmdline_param!(
+                param,
+                DENIED_ENDPOINTS_OPTION,
+                config.denied_endpoints,
+                get_string_list_value
+            );
             parse_cmdline_param!(
                 param,
                 CGROUP_NO_V1,
@@ -564,6 +937,12 @@ impl AgentConfig {
                     config.image_policy_file,
                     get_string_value
                 );
+                parse_cmdline_param!(
+                    param,
+                    IMAGE_LAYER_CACHE_SIZE_OPTION,
+                    config.image_layer_cache_size,
+                    get_number_value
+                );
             }
             parse_cmdline_param!(
                 param,
@@ -571,6 +950,43 @@ impl AgentConfig {
                 config.secure_storage_integrity,
                 get_bool_value
             );
+            parse_cmdline_param!(
+                param,
+                SEALED_ENV_ALLOWED_KEYS_OPTION,
+                config.sealed_env_allowed_keys,
+                get_string_list_value
+            );
+            parse_cmdline_param!(
+                param,
+                ENABLE_ATTESTATION_PROXY_OPTION,
+                config.enable_attestation_proxy,
+                get_bool_value
+            );
+            parse_cmdline_param!(
+                param,
+                OFFLINE_CONFIDENTIAL_OPTION,
+                config.offline_confidential,
+                get_bool_value
+            );
+            parse_cmdline_param!(
+                param,
+                WRITABLE_LAYER_INTEGRITY_OPTION,
+                config.writable_layer_integrity,
+                get_bool_value
+            );
+            parse_cmdline_param!(
+                param,
+                CONFIDENTIAL_OPTION,
+                config.confidential,
+                get_bool_value
+            );
+            #[cfg(feature = "agent-policy")]
+            parse_cmdline_param!(
+                param,
+                POLICY_AUDIT_OPTION,
+                config.policy_audit,
+                get_bool_value
+            );
 
             parse_cmdline_param!(param, MEM_AGENT_ENABLE, mem_agent_enable, get_bool_value);
 
@@ -815,6 +1231,19 @@ fn get_string_value(param: &str) -> Result<String> {
     Ok(value)
 }
 
+// Return the comma-separated list of values from a "name=value[,value..]"
+// string, with surrounding whitespace trimmed and empty entries dropped.
+#[instrument]
+fn get_string_list_value(param: &str) -> Result<Vec<String>> {
+    let value = get_string_value(param)?;
+
+    Ok(value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect())
+}
+
 #[instrument]
 fn get_container_pipe_size(param: &str) -> Result<i32> {
     let fields: Vec<&str> = param.split('=').collect();
@@ -885,6 +1314,7 @@ mod tests {
         assert!(!config.dev_mode);
         assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
         assert_eq!(config.hotplug_timeout, DEFAULT_HOTPLUG_TIMEOUT);
+        assert!(!config.oom_group);
         #[cfg(feature = "guest-pull")]
         {
             assert!(!config.enable_signature_verification);
@@ -918,12 +1348,20 @@ mod tests {
             #[cfg(feature = "guest-pull")]
             image_registry_auth: &'a str,
             secure_storage_integrity: bool,
+            enable_attestation_proxy: bool,
+            offline_confidential: bool,
+            writable_layer_integrity: bool,
+            confidential: bool,
             #[cfg(feature = "guest-pull")]
             enable_signature_verification: bool,
             #[cfg(feature = "guest-pull")]
             image_policy_file: &'a str,
+            #[cfg(feature = "guest-pull")]
+            image_layer_cache_size: u64,
             #[cfg(feature = "agent-policy")]
             policy_file: &'a str,
+            #[cfg(feature = "agent-policy")]
+            policy_audit: bool,
             mem_agent: Option<MemAgentConfig>,
         }
 
@@ -948,12 +1386,20 @@ mod tests {
                     #[cfg(feature = "guest-pull")]
                     image_registry_auth: "",
                     secure_storage_integrity: false,
+                    enable_attestation_proxy: false,
+                    offline_confidential: false,
+                    writable_layer_integrity: false,
+                    confidential: false,
                     #[cfg(feature = "guest-pull")]
                     enable_signature_verification: false,
                     #[cfg(feature = "guest-pull")]
                     image_policy_file: "",
+                    #[cfg(feature = "guest-pull")]
+                    image_layer_cache_size: 0,
                     #[cfg(feature = "agent-policy")]
                     policy_file: "",
+                    #[cfg(feature = "agent-policy")]
+                    policy_audit: false,
                     mem_agent: None,
                 }
             }
@@ -1439,6 +1885,50 @@ mod tests {
                 secure_storage_integrity: false,
                 ..Default::default()
             },
+            TestData {
+                contents: "agent.enable_attestation_proxy=true",
+                enable_attestation_proxy: true,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.enable_attestation_proxy=0",
+                enable_attestation_proxy: false,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.offline_confidential=true",
+                offline_confidential: true,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.offline_confidential=0",
+                offline_confidential: false,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.writable_layer_integrity=true",
+                writable_layer_integrity: true,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.writable_layer_integrity=0",
+                writable_layer_integrity: false,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.confidential=true",
+                confidential: true,
+                debug_console: false,
+                ..Default::default()
+            },
+            // The profile wins over an explicitly enabled debug console,
+            // whatever the order of the parameters.
+            TestData {
+                contents: "agent.debug_console agent.confidential=true",
+                confidential: true,
+                debug_console: false,
+                ..Default::default()
+            },
             #[cfg(feature = "guest-pull")]
             TestData {
                 contents: "agent.enable_signature_verification=true",
@@ -1457,6 +1947,18 @@ mod tests {
                 image_policy_file: "file:///etc/image-policy.json",
                 ..Default::default()
             },
+            #[cfg(feature = "guest-pull")]
+            TestData {
+                contents: "agent.image_layer_cache_size=512",
+                image_layer_cache_size: 512,
+                ..Default::default()
+            },
+            #[cfg(feature = "guest-pull")]
+            TestData {
+                contents: "agent.image_layer_cache_size=0",
+                image_layer_cache_size: 0,
+                ..Default::default()
+            },
             #[cfg(feature = "agent-policy")]
             // Test environment
             TestData {
@@ -1465,6 +1967,18 @@ mod tests {
                 policy_file: "/tmp/policy.rego",
                 ..Default::default()
             },
+            #[cfg(feature = "agent-policy")]
+            TestData {
+                contents: "agent.policy_audit=true",
+                policy_audit: true,
+                ..Default::default()
+            },
+            #[cfg(feature = "agent-policy")]
+            TestData {
+                contents: "agent.policy_audit=0",
+                policy_audit: false,
+                ..Default::default()
+            },
             TestData {
                 contents: "",
                 ..Default::default()
@@ -1568,14 +2082,37 @@ mod tests {
                     msg
                 );
                 assert_eq!(d.image_policy_file, config.image_policy_file, "{}", msg);
+                assert_eq!(
+                    d.image_layer_cache_size, config.image_layer_cache_size,
+                    "{}",
+                    msg
+                );
             }
             assert_eq!(
                 d.secure_storage_integrity, config.secure_storage_integrity,
                 "{}",
                 msg
             );
+            assert_eq!(
+                d.enable_attestation_proxy, config.enable_attestation_proxy,
+                "{}",
+                msg
+            );
+            assert_eq!(
+                d.offline_confidential, config.offline_confidential,
+                "{}",
+                msg
+            );
+            assert_eq!(
+                d.writable_layer_integrity, config.writable_layer_integrity,
+                "{}",
+                msg
+            );
+            assert_eq!(d.confidential, config.confidential, "{}", msg);
             #[cfg(feature = "agent-policy")]
             assert_eq!(d.policy_file, config.policy_file, "{}", msg);
+            #[cfg(feature = "agent-policy")]
+            assert_eq!(d.policy_audit, config.policy_audit, "{}", msg);
 
             assert_eq!(d.mem_agent, config.mem_agent, "{}", msg);
 
@@ -1773,6 +2310,19 @@ Caused by:
         assert_result!(expected, result, msg);
     }
 
+    #[rstest]
+    #[case("", Err(anyhow!(ERR_INVALID_GET_VALUE_PARAM)))]
+    #[case("x=", Err(anyhow!(ERR_INVALID_GET_VALUE_NO_VALUE)))]
+    #[case("x=net.", Ok(vec!["net.".into()]))]
+    #[case("x=net.,kernel.shm", Ok(vec!["net.".into(), "kernel.shm".into()]))]
+    #[case("x= net. , kernel.shm ", Ok(vec!["net.".into(), "kernel.shm".into()]))]
+    #[case("x=net.,,kernel.shm,", Ok(vec!["net.".into(), "kernel.shm".into()]))]
+    fn test_get_string_list_value(#[case] param: &str, #[case] expected: Result<Vec<String>>) {
+        let result = get_string_list_value(param);
+        let msg = format!("expected: {:?}, result: {:?}", expected, result);
+        assert_result!(expected, result, msg);
+    }
+
     #[rstest]
     #[case("", Err(anyhow!(ERR_INVALID_GET_VALUE_PARAM)))]
     #[case("=", Err(anyhow!(ERR_INVALID_GET_VALUE_NO_NAME)))]
@@ -1845,4 +2395,29 @@ Caused by:
 
         assert_eq!(config.mem_agent, Some(MemAgentConfig::default()),);
     }
+
+    #[test]
+    fn test_config_sandbox_limits() {
+        // The limits default to "disabled".
+        let config: AgentConfig = Default::default();
+        assert_eq!(config.max_containers, 0);
+        assert_eq!(config.max_exec_sessions, 0);
+        assert_eq!(config.exec_mux_buffer_limit, 0);
+        assert_eq!(config.max_open_files, 0);
+
+        let config = AgentConfig::from_str(
+            r#"
+               max_containers = 8
+               max_exec_sessions = 16
+               exec_mux_buffer_limit = 4194304
+               max_open_files = 1024
+              "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.max_containers, 8);
+        assert_eq!(config.max_exec_sessions, 16);
+        assert_eq!(config.exec_mux_buffer_limit, 4194304);
+        assert_eq!(config.max_open_files, 1024);
+    }
 }
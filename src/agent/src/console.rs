@@ -8,7 +8,7 @@ use crate::util;
 use anyhow::{anyhow, Result};
 use nix::fcntl::{self, FcntlArg, FdFlag, OFlag};
 use nix::libc::{STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
-use nix::pty::{openpty, OpenptyResult};
+use nix::pty::{openpty, OpenptyResult, Winsize};
 use nix::sys::socket::{self, AddressFamily, SockFlag, SockType, VsockAddr};
 use nix::sys::stat::Mode;
 use nix::sys::{signal, wait};
@@ -19,15 +19,37 @@ use std::ffi::CString;
 use std::os::unix::io::{FromRawFd, RawFd};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex as SyncMutex;
+use std::time::{Duration, Instant};
 
 use futures::StreamExt;
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::select;
 use tokio::sync::watch::Receiver;
 
-const CONSOLE_PATH: &str = "/dev/console";
+pub const CONSOLE_PATH: &str = "/dev/console";
+
+// Several troubleshooting sessions may be opened in parallel, each one
+// getting its own PTY and shell; let a few connects queue while earlier
+// ones are still being accepted.
+const DEBUG_CONSOLE_BACKLOG: usize = 8;
+
+// Sessions with no traffic in either direction for this long are torn
+// down, so connections whose client went away without closing the socket
+// don't pin PTYs and shell processes forever.
+const DEBUG_CONSOLE_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Default window size for a new session; clients adjust it by sending an
+// xterm window size report (see ResizeFilter).
+const DEFAULT_WINSIZE: Winsize = Winsize {
+    ws_row: 24,
+    ws_col: 80,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+};
 
 lazy_static! {
     static ref SHELLS: Arc<SyncMutex<Vec<String>>> = {
@@ -69,9 +91,10 @@ pub async fn debug_console_handler(
         )?;
         let addr = VsockAddr::new(libc::VMADDR_CID_ANY, port);
         socket::bind(listenfd, &addr)?;
-        socket::listen(listenfd, 1)?;
+        socket::listen(listenfd, DEBUG_CONSOLE_BACKLOG)?;
 
         let mut incoming = util::get_vsock_incoming(listenfd);
+        let mut session_id: u64 = 0;
 
         loop {
             select! {
@@ -85,9 +108,13 @@ pub async fn debug_console_handler(
                         // Accept a new connection
                         match conn {
                             Ok(stream) => {
-                                let logger = logger.clone();
+                                session_id += 1;
+                                let logger = logger.new(o!("session" => session_id));
                                 let shell = shell.clone();
-                                // Do not block(await) here, or we'll never receive the shutdown signal
+                                // Each session runs on its own task with its
+                                // own PTY, so parallel sessions don't kick
+                                // each other off. Do not block(await) here, or
+                                // we'll never receive the shutdown signal.
                                 tokio::spawn(async move {
                                     let _ = run_debug_console_vsock(logger, shell, stream).await;
                                 });
@@ -156,6 +183,193 @@ fn run_in_child(slave_fd: libc::c_int, shell: String) -> Result<()> {
     Ok(())
 }
 
+// Wall-clock of the last byte a session moved in either direction,
+// shared between the forwarding futures and the idle watchdog.
+struct Activity {
+    start: Instant,
+    last: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Activity {
+            start: Instant::now(),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        self.last
+            .store(self.start.elapsed().as_secs(), Ordering::Relaxed);
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.start
+            .elapsed()
+            .saturating_sub(Duration::from_secs(self.last.load(Ordering::Relaxed)))
+    }
+}
+
+// Resolves once the session has been idle for DEBUG_CONSOLE_IDLE_TIMEOUT.
+async fn idle_watch(activity: &Activity) {
+    let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if activity.idle_for() >= DEBUG_CONSOLE_IDLE_TIMEOUT {
+            return;
+        }
+    }
+}
+
+// Shell output to the client, counting traffic as session activity so a
+// session only watching output (e.g. tail -f) is not reaped as idle.
+async fn forward_output<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    activity: &Activity,
+) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        activity.touch();
+        writer.write_all(&buf[..n]).await?;
+    }
+}
+
+// Client input to the shell. Window size reports are consumed here and
+// applied to the PTY rather than forwarded, so each session can be
+// resized independently.
+async fn forward_input<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    master_fd: RawFd,
+    logger: &Logger,
+    activity: &Activity,
+) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = [0u8; 8192];
+    let mut out = Vec::with_capacity(8192);
+    let mut filter = ResizeFilter::default();
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        activity.touch();
+
+        out.clear();
+        filter.feed(&buf[..n], &mut out, |rows, cols| {
+            let win = libc::winsize {
+                ws_row: rows,
+                ws_col: cols,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            let err = unsafe { libc::ioctl(master_fd, libc::TIOCSWINSZ, &win) };
+            if err < 0 {
+                warn!(logger, "failed to resize debug console pty");
+            } else {
+                debug!(logger, "debug console resized"; "rows" => rows, "cols" => cols);
+            }
+        });
+        writer.write_all(&out).await?;
+    }
+}
+
+// Picks the xterm window size report (CSI 8 ; rows ; cols t) out of a
+// byte stream. The debug console protocol is a raw byte pipe, so this
+// report - which clients can obtain by querying their own terminal with
+// CSI 18 t - doubles as the in-band resize request.
+#[derive(Default)]
+struct ResizeFilter {
+    // Prefix of a possible window size report held back until it either
+    // completes or turns out to be ordinary input.
+    pending: Vec<u8>,
+}
+
+// Longest well-formed report: ESC [ 8 ; 65535 ; 65535 t
+const RESIZE_SEQ_MAX: usize = 18;
+
+impl ResizeFilter {
+    fn feed(&mut self, chunk: &[u8], out: &mut Vec<u8>, mut resize: impl FnMut(u16, u16)) {
+        for &b in chunk {
+            if self.pending.is_empty() {
+                if b == 0x1b {
+                    self.pending.push(b);
+                } else {
+                    out.push(b);
+                }
+                continue;
+            }
+
+            self.pending.push(b);
+            if !is_resize_prefix(&self.pending) || self.pending.len() > RESIZE_SEQ_MAX {
+                // Not a window size report after all: release what was
+                // held back and reconsider the last byte, which may
+                // start a new escape sequence.
+                let b = self.pending.pop().unwrap();
+                out.append(&mut self.pending);
+                if b == 0x1b {
+                    self.pending.push(b);
+                } else {
+                    out.push(b);
+                }
+            } else if b == b't' {
+                if let Some((rows, cols)) = parse_resize(&self.pending) {
+                    resize(rows, cols);
+                }
+                self.pending.clear();
+            }
+        }
+    }
+}
+
+fn is_resize_prefix(s: &[u8]) -> bool {
+    const HEAD: &[u8] = b"\x1b[8;";
+    if s.len() <= HEAD.len() {
+        return s == &HEAD[..s.len()];
+    }
+
+    let rest = &s[HEAD.len()..];
+    let mut semis = 0;
+    for (i, &b) in rest.iter().enumerate() {
+        match b {
+            b'0'..=b'9' => {}
+            b';' => {
+                semis += 1;
+                if semis > 1 {
+                    return false;
+                }
+            }
+            b't' if i == rest.len() - 1 && semis == 1 => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn parse_resize(s: &[u8]) -> Option<(u16, u16)> {
+    // ESC [ 8 ; rows ; cols t
+    let body = std::str::from_utf8(&s[4..s.len() - 1]).ok()?;
+    let (rows, cols) = body.split_once(';')?;
+    let rows = rows.parse().ok()?;
+    let cols = cols.parse().ok()?;
+    if rows == 0 || cols == 0 {
+        return None;
+    }
+    Some((rows, cols))
+}
+
 async fn run_in_parent<T: AsyncRead + AsyncWrite>(
     logger: Logger,
     stream: T,
@@ -170,14 +384,16 @@ async fn run_in_parent<T: AsyncRead + AsyncWrite>(
     let (mut socket_reader, mut socket_writer) = tokio::io::split(stream);
     let (mut master_reader, mut master_writer) = tokio::io::split(PipeStream::from_fd(master_fd));
 
+    let activity = Activity::new();
+
     select! {
-        res = tokio::io::copy(&mut master_reader, &mut socket_writer) => {
+        res = forward_output(&mut master_reader, &mut socket_writer, &activity) => {
             debug!(
                 logger,
                 "master closed: {:?}", res
             );
         }
-        res = tokio::io::copy(&mut socket_reader, &mut master_writer) => {
+        res = forward_input(&mut socket_reader, &mut master_writer, master_fd, &logger, &activity) => {
             // the shell run in child may not be exited, in some scenes
             // eg. directly Ctrl-C in the host to terminate the kata-runtime process
             // that will block this task，while waiting for the child to exit.
@@ -190,6 +406,12 @@ async fn run_in_parent<T: AsyncRead + AsyncWrite>(
                 "socket closed: {:?}", res
             );
         }
+        _ = idle_watch(&activity) => {
+            let _ = signal::kill(child_pid, Some(signal::Signal::SIGKILL))
+                .map_err(|e| warn!(logger, "kill child shell process {:?}", e));
+
+            info!(logger, "debug console session timed out while idle");
+        }
     }
 
     let wait_status = wait::waitpid(child_pid, None);
@@ -205,7 +427,7 @@ async fn run_debug_console_vsock<T: AsyncRead + AsyncWrite>(
 ) -> Result<()> {
     let logger = logger.new(o!("subsystem" => "debug-console-shell"));
 
-    let pseudo = openpty(None, None)?;
+    let pseudo = openpty(Some(&DEFAULT_WINSIZE), None)?;
     let _ = fcntl::fcntl(pseudo.master, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC));
     let _ = fcntl::fcntl(pseudo.slave, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC));
 
@@ -297,4 +519,56 @@ mod tests {
             "no shell found to launch debug console"
         );
     }
+
+    fn feed(filter: &mut ResizeFilter, chunk: &[u8]) -> (Vec<u8>, Vec<(u16, u16)>) {
+        let mut out = Vec::new();
+        let mut resizes = Vec::new();
+        filter.feed(chunk, &mut out, |rows, cols| resizes.push((rows, cols)));
+        (out, resizes)
+    }
+
+    #[test]
+    fn test_resize_filter_passthrough() {
+        let mut filter = ResizeFilter::default();
+
+        let (out, resizes) = feed(&mut filter, b"ls -l\r");
+        assert_eq!(out, b"ls -l\r");
+        assert!(resizes.is_empty());
+
+        // Other escape sequences (arrow keys, ...) pass through intact.
+        let (out, resizes) = feed(&mut filter, b"\x1b[A\x1b[2J");
+        assert_eq!(out, b"\x1b[A\x1b[2J");
+        assert!(resizes.is_empty());
+    }
+
+    #[test]
+    fn test_resize_filter_consumes_report() {
+        let mut filter = ResizeFilter::default();
+
+        let (out, resizes) = feed(&mut filter, b"before\x1b[8;50;120tafter");
+        assert_eq!(out, b"beforeafter");
+        assert_eq!(resizes, vec![(50, 120)]);
+    }
+
+    #[test]
+    fn test_resize_filter_split_across_reads() {
+        let mut filter = ResizeFilter::default();
+
+        let (out, resizes) = feed(&mut filter, b"\x1b[8;3");
+        assert!(out.is_empty());
+        assert!(resizes.is_empty());
+
+        let (out, resizes) = feed(&mut filter, b"0;100t");
+        assert!(out.is_empty());
+        assert_eq!(resizes, vec![(30, 100)]);
+    }
+
+    #[test]
+    fn test_resize_filter_rejects_zero_size() {
+        let mut filter = ResizeFilter::default();
+
+        let (out, resizes) = feed(&mut filter, b"\x1b[8;0;80t");
+        assert!(out.is_empty());
+        assert!(resizes.is_empty());
+    }
 }
@@ -0,0 +1,265 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Guest CPU topology and cache inspection.
+//!
+//! The topology a container sees (`nproc`, hwloc, thread-pool sizing in
+//! runtimes) is assembled from sysfs, and sysfs is only accurate when
+//! every vCPU the runtime provisioned is online and consistently
+//! described. The cpuid leaves are fixed by the VMM at boot; what the
+//! guest owns is the sysfs side: vCPUs are onlined on hotplug (see the
+//! sandbox module) and this module reads the resulting topology back, so
+//! inconsistencies that mislead sizing logic — fewer online CPUs than
+//! provisioned, sibling lists naming offline CPUs, missing cache
+//! descriptions — are reported right after the hotplug instead of
+//! surfacing as mysterious workload slowdowns.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use slog::Logger;
+
+const SYSFS_CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+/// One cache level as described by sysfs, read from cpu0 on the
+/// assumption that every vCPU of a VM is described alike.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CacheInfo {
+    pub level: u32,
+    pub cache_type: String,
+    pub size: String,
+}
+
+/// The guest CPU topology as containers will discover it.
+#[derive(Debug, Default)]
+pub struct CpuTopology {
+    pub sockets: usize,
+    pub cores: usize,
+    pub threads: usize,
+    pub caches: Vec<CacheInfo>,
+    /// CPUs whose thread sibling list names a CPU that is offline;
+    /// hwloc builds its object tree from these masks and mistrusts the
+    /// whole topology when they are stale.
+    pub stale_siblings: Vec<String>,
+}
+
+/// Read the online CPU topology and the cache description from a sysfs
+/// CPU directory (`/sys/devices/system/cpu` outside of tests).
+pub fn read_topology(root: &Path) -> Result<CpuTopology> {
+    let online = fs::read_to_string(root.join("online")).context("read online cpu list")?;
+    let online = parse_cpu_list(online.trim());
+    let online_set: HashSet<usize> = online.iter().copied().collect();
+
+    let mut sockets = HashSet::new();
+    let mut cores = HashSet::new();
+    let mut stale_siblings = Vec::new();
+
+    for cpu in &online {
+        let topology = root.join(format!("cpu{}", cpu)).join("topology");
+
+        let package = read_trim(&topology.join("physical_package_id")).unwrap_or_default();
+        let core = read_trim(&topology.join("core_id")).unwrap_or_default();
+        sockets.insert(package.clone());
+        cores.insert((package, core));
+
+        if let Some(siblings) = read_trim(&topology.join("thread_siblings_list")) {
+            if parse_cpu_list(&siblings)
+                .iter()
+                .any(|sibling| !online_set.contains(sibling))
+            {
+                stale_siblings.push(format!("cpu{}", cpu));
+            }
+        }
+    }
+
+    Ok(CpuTopology {
+        sockets: sockets.len(),
+        cores: cores.len(),
+        threads: online.len(),
+        caches: read_caches(root),
+        stale_siblings,
+    })
+}
+
+/// Read the topology after a CPU hotplug and report what containers will
+/// see, warning about anything that misleads sizing logic. Purely
+/// diagnostic, so failures are logged rather than propagated.
+pub fn check_after_hotplug(logger: &Logger, provisioned_cpus: i32) {
+    let topology = match read_topology(Path::new(SYSFS_CPU_ROOT)) {
+        Ok(topology) => topology,
+        Err(e) => {
+            warn!(logger, "failed to read guest cpu topology: {:?}", e);
+            return;
+        }
+    };
+
+    if topology.threads != provisioned_cpus as usize {
+        warn!(logger, "guest topology does not match provisioned vCPUs";
+            "online" => topology.threads,
+            "provisioned" => provisioned_cpus,
+        );
+    }
+
+    if !topology.stale_siblings.is_empty() {
+        warn!(logger, "thread sibling lists reference offline CPUs";
+            "cpus" => topology.stale_siblings.join(","),
+        );
+    }
+
+    if topology.caches.is_empty() {
+        warn!(
+            logger,
+            "no cache description in sysfs: tools inside containers will fall back to guesses"
+        );
+    }
+
+    info!(logger, "guest cpu topology after hotplug";
+        "sockets" => topology.sockets,
+        "cores" => topology.cores,
+        "threads" => topology.threads,
+        "caches" => format!("{:?}", topology.caches),
+    );
+}
+
+// The cache hierarchy of cpu0, one entry per index directory. A missing
+// or empty cache directory yields an empty vector.
+fn read_caches(root: &Path) -> Vec<CacheInfo> {
+    let cache_dir = root.join("cpu0").join("cache");
+    let entries = match fs::read_dir(&cache_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut caches = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !entry.file_name().to_string_lossy().starts_with("index") {
+            continue;
+        }
+        let level = match read_trim(&path.join("level")).and_then(|v| v.parse().ok()) {
+            Some(level) => level,
+            None => continue,
+        };
+        caches.push(CacheInfo {
+            level,
+            cache_type: read_trim(&path.join("type")).unwrap_or_default(),
+            size: read_trim(&path.join("size")).unwrap_or_default(),
+        });
+    }
+
+    caches.sort_by_key(|cache| (cache.level, cache.cache_type.clone()));
+    caches
+}
+
+// Parse a sysfs CPU list such as "0-3,5" into the CPUs it names.
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for part in list.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        match part.split_once('-') {
+            Some((first, last)) => {
+                if let (Ok(first), Ok(last)) = (first.parse::<usize>(), last.parse::<usize>()) {
+                    cpus.extend(first..=last);
+                }
+            }
+            None => {
+                if let Ok(cpu) = part.parse::<usize>() {
+                    cpus.push(cpu);
+                }
+            }
+        }
+    }
+    cpus
+}
+
+fn read_trim(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn fake_cpu(root: &Path, cpu: usize, package: &str, core: &str, siblings: &str) {
+        let base = format!("cpu{}/topology", cpu);
+        write(root, &format!("{}/physical_package_id", base), package);
+        write(root, &format!("{}/core_id", base), core);
+        write(root, &format!("{}/thread_siblings_list", base), siblings);
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0"), vec![0]);
+        assert_eq!(parse_cpu_list("0-3,5"), vec![0, 1, 2, 3, 5]);
+        assert_eq!(parse_cpu_list(""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_read_topology() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        write(root, "online", "0-1\n");
+        // One socket, one core, two hyperthreads.
+        fake_cpu(root, 0, "0", "0", "0-1");
+        fake_cpu(root, 1, "0", "0", "0-1");
+        write(root, "cpu0/cache/index0/level", "1");
+        write(root, "cpu0/cache/index0/type", "Data");
+        write(root, "cpu0/cache/index0/size", "32K");
+        write(root, "cpu0/cache/index2/level", "2");
+        write(root, "cpu0/cache/index2/type", "Unified");
+        write(root, "cpu0/cache/index2/size", "512K");
+
+        let topology = read_topology(root).unwrap();
+        assert_eq!(topology.sockets, 1);
+        assert_eq!(topology.cores, 1);
+        assert_eq!(topology.threads, 2);
+        assert!(topology.stale_siblings.is_empty());
+        assert_eq!(
+            topology.caches,
+            vec![
+                CacheInfo {
+                    level: 1,
+                    cache_type: "Data".to_string(),
+                    size: "32K".to_string(),
+                },
+                CacheInfo {
+                    level: 2,
+                    cache_type: "Unified".to_string(),
+                    size: "512K".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_topology_stale_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        // cpu1 went offline but cpu0 still lists it as a sibling.
+        write(root, "online", "0\n");
+        fake_cpu(root, 0, "0", "0", "0-1");
+
+        let topology = read_topology(root).unwrap();
+        assert_eq!(topology.threads, 1);
+        assert_eq!(topology.stale_siblings, vec!["cpu0".to_string()]);
+        assert!(topology.caches.is_empty());
+    }
+}
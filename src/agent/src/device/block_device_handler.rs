@@ -7,7 +7,7 @@
 #[cfg(target_arch = "s390x")]
 use crate::ccw;
 use crate::device::{
-    pcipath_to_sysfs, DeviceContext, DeviceHandler, DeviceInfo, SpecUpdate, BLOCK,
+    pcipath_to_sysfs_with_rescan, DeviceContext, DeviceHandler, DeviceInfo, SpecUpdate, BLOCK,
 };
 #[cfg(target_arch = "s390x")]
 use crate::linux_abi::CCW_ROOT_BUS_PATH;
@@ -110,7 +110,7 @@ pub async fn get_virtio_blk_pci_device_name(
     pcipath: &pci::Path,
 ) -> Result<String> {
     let root_bus_sysfs = format!("{}{}", SYSFS_DIR, create_pci_root_bus_path());
-    let sysfs_rel_path = pcipath_to_sysfs(&root_bus_sysfs, pcipath)?;
+    let sysfs_rel_path = pcipath_to_sysfs_with_rescan(&root_bus_sysfs, pcipath).await?;
     let matcher = VirtioBlkPciMatcher::new(&sysfs_rel_path);
 
     let uev = wait_for_uevent(sandbox, matcher).await?;
@@ -7,7 +7,9 @@
 use self::block_device_handler::{VirtioBlkMmioDeviceHandler, VirtioBlkPciDeviceHandler};
 use self::nvdimm_device_handler::VirtioNvdimmDeviceHandler;
 use self::scsi_device_handler::ScsiDeviceHandler;
+use self::vdpa_device_handler::VhostVdpaDeviceHandler;
 use self::vfio_device_handler::{VfioApDeviceHandler, VfioPciDeviceHandler};
+use crate::linux_abi::SYSFS_BUS_PCI_PATH;
 use crate::pci;
 use crate::sandbox::Sandbox;
 use anyhow::{anyhow, Context, Result};
@@ -36,6 +38,7 @@ pub mod block_device_handler;
 pub mod network_device_handler;
 pub mod nvdimm_device_handler;
 pub mod scsi_device_handler;
+pub mod vdpa_device_handler;
 pub mod vfio_device_handler;
 
 pub const BLOCK: &str = "block";
@@ -167,6 +170,7 @@ lazy_static! {
             Arc::new(ScsiDeviceHandler {}),
             Arc::new(VfioPciDeviceHandler {}),
             Arc::new(VfioApDeviceHandler {}),
+            Arc::new(VhostVdpaDeviceHandler {}),
             #[cfg(target_arch = "s390x")]
             Arc::new(self::block_device_handler::VirtioBlkCcwDeviceHandler {}),
         ];
@@ -468,8 +472,8 @@ fn update_spec_devices(
     let linux_devices = linux.devices_mut().as_mut().unwrap_or(&mut default_devices);
     for specdev in linux_devices.iter_mut() {
         let devtype = specdev.typ().as_str().to_string();
-        if let Some(update) = updates.remove(specdev.path().clone().display().to_string().as_str())
-        {
+        let container_path = specdev.path().clone().display().to_string();
+        if let Some(update) = updates.remove(container_path.as_str()) {
             let host_major = specdev.major();
             let host_minor = specdev.minor();
 
@@ -501,6 +505,17 @@ fn update_spec_devices(
                     host_minor
                 ));
             }
+        } else if crate::AGENT_CONFIG.confidential
+            && container_path != crate::rpc::TRUSTED_IMAGE_STORAGE_DEVICE
+        {
+            // A device entry no handler remapped is a direct request to
+            // expose a guest device node to the container. The
+            // confidential profile refuses those: the trusted storage
+            // device is the only /dev passthrough it allows.
+            return Err(anyhow!(
+                "confidential profile: device {} may not be exposed to the container",
+                container_path
+            ));
         }
     }
 
@@ -540,11 +555,90 @@ fn update_spec_devices(
                 }
             }
         }
+
+        // blockIO weight and throttle entries refer to devices by host
+        // major/minor as well. Remap them so that io.max / io.weight are
+        // applied to the device the guest actually sees after hotplug.
+        if let Some(blkio) = resources.block_io_mut().as_mut() {
+            update_spec_blockio(logger, blkio, &res_updates);
+        }
     }
 
     Ok(())
 }
 
+// update_spec_blockio remaps the host major/minor numbers in the
+// blockIO weight and throttle device lists to their guest equivalents.
+// blockIO entries always refer to block devices.
+#[instrument]
+fn update_spec_blockio(
+    logger: &Logger,
+    blkio: &mut oci::LinuxBlockIo,
+    res_updates: &HashMap<(String, i64, i64), DeviceInfo>,
+) {
+    if let Some(mut devices) = blkio.weight_device().clone() {
+        for d in devices.iter_mut() {
+            if let Some((major, minor)) =
+                remap_blockio_device(logger, res_updates, d.major(), d.minor())
+            {
+                d.set_major(major);
+                d.set_minor(minor);
+            }
+        }
+        blkio.set_weight_device(Some(devices));
+    }
+
+    let throttled = remap_throttle_devices(logger, res_updates, blkio.throttle_read_bps_device());
+    blkio.set_throttle_read_bps_device(throttled);
+    let throttled = remap_throttle_devices(logger, res_updates, blkio.throttle_write_bps_device());
+    blkio.set_throttle_write_bps_device(throttled);
+    let throttled = remap_throttle_devices(logger, res_updates, blkio.throttle_read_iops_device());
+    blkio.set_throttle_read_iops_device(throttled);
+    let throttled = remap_throttle_devices(logger, res_updates, blkio.throttle_write_iops_device());
+    blkio.set_throttle_write_iops_device(throttled);
+}
+
+// remap_blockio_device returns the guest major/minor numbers of a
+// blockIO device entry, or None if no update matches it.
+fn remap_blockio_device(
+    logger: &Logger,
+    res_updates: &HashMap<(String, i64, i64), DeviceInfo>,
+    host_major: i64,
+    host_minor: i64,
+) -> Option<(i64, i64)> {
+    res_updates
+        .get(&("b".to_string(), host_major, host_minor))
+        .map(|update| {
+            info!(
+                logger,
+                "update_spec_blockio() updating blockIO device";
+                "host_major" => host_major,
+                "host_minor" => host_minor,
+                "guest_major" => update.guest_major,
+                "guest_minor" => update.guest_minor,
+            );
+            (update.guest_major, update.guest_minor)
+        })
+}
+
+fn remap_throttle_devices(
+    logger: &Logger,
+    res_updates: &HashMap<(String, i64, i64), DeviceInfo>,
+    throttle_devices: &Option<Vec<oci::LinuxThrottleDevice>>,
+) -> Option<Vec<oci::LinuxThrottleDevice>> {
+    throttle_devices.clone().map(|mut devices| {
+        for d in devices.iter_mut() {
+            if let Some((major, minor)) =
+                remap_blockio_device(logger, res_updates, d.major(), d.minor())
+            {
+                d.set_major(major);
+                d.set_minor(minor);
+            }
+        }
+        devices
+    })
+}
+
 // pcipath_to_sysfs fetches the sysfs path for a PCI path, relative to
 // the sysfs path for the PCI host bridge, based on the PCI path
 // provided.
@@ -588,6 +682,83 @@ pub fn pcipath_to_sysfs(root_bus_sysfs: &str, pcipath: &pci::Path) -> Result<Str
     Ok(relpath)
 }
 
+// How long pcipath_to_sysfs_with_rescan() keeps retrying resolution, and
+// how long it pauses between rescan attempts. Enumerating a bus behind a
+// bridge is normally quick, so the interval is kept short.
+const PCI_RESCAN_TIMEOUT: Duration = Duration::from_secs(3);
+const PCI_RESCAN_INTERVAL: Duration = Duration::from_millis(50);
+
+// pcipath_to_sysfs_with_rescan resolves a PCI path like pcipath_to_sysfs,
+// but tolerates bridges whose secondary bus has not been enumerated yet,
+// which can happen with deeply nested switch/bridge topologies where the
+// uevent for a parent bridge arrives before its children are scanned. On
+// failure it asks every bridge already visible along the path (deepest
+// first) to rescan, then retries until PCI_RESCAN_TIMEOUT expires.
+#[instrument]
+pub async fn pcipath_to_sysfs_with_rescan(
+    root_bus_sysfs: &str,
+    pcipath: &pci::Path,
+) -> Result<String> {
+    let deadline = std::time::Instant::now() + PCI_RESCAN_TIMEOUT;
+
+    let last_err = loop {
+        match pcipath_to_sysfs(root_bus_sysfs, pcipath) {
+            Ok(relpath) => return Ok(relpath),
+            Err(e) => {
+                if std::time::Instant::now() >= deadline {
+                    break e;
+                }
+                rescan_pci_bridges(root_bus_sysfs, pcipath);
+                time::sleep(PCI_RESCAN_INTERVAL).await;
+            }
+        }
+    };
+
+    Err(last_err.context(format!(
+        "Could not resolve PCI path {} under {} despite rescanning for {:?}",
+        pcipath, root_bus_sysfs, PCI_RESCAN_TIMEOUT
+    )))
+}
+
+// Trigger a rescan of every bridge that can already be resolved along the
+// given PCI path, deepest first, then of the root bus itself. Write errors
+// are ignored: the attribute may not be there yet, which is exactly the
+// situation the following retry handles.
+fn rescan_pci_bridges(root_bus_sysfs: &str, pcipath: &pci::Path) {
+    let mut bridges = Vec::new();
+    let mut bus = "0000:00".to_string();
+    let mut relpath = String::new();
+
+    for i in 0..pcipath.len() {
+        relpath = format!("{}/{}:{}", relpath, bus, pcipath[i]);
+
+        let bridgepath = format!("{}{}", root_bus_sysfs, relpath);
+        if !PathBuf::from(&bridgepath).is_dir() {
+            break;
+        }
+        bridges.push(bridgepath.clone());
+
+        // Find out the bus exposed by the bridge; if it isn't there yet,
+        // the rescan of the bridges collected so far may create it.
+        let busfile = match fs::read_dir(format!("{}/pci_bus", bridgepath))
+            .ok()
+            .and_then(|mut files| files.next())
+        {
+            Some(Ok(busfile)) => busfile,
+            _ => break,
+        };
+        bus = match busfile.file_name().into_string() {
+            Ok(bus) => bus,
+            Err(_) => break,
+        };
+    }
+
+    for bridge in bridges.iter().rev() {
+        let _ = fs::write(format!("{}/rescan", bridge), "1");
+    }
+    let _ = fs::write(format!("{}/rescan", SYSFS_BUS_PCI_PATH), "1");
+}
+
 #[instrument]
 pub fn online_device(path: &str) -> Result<()> {
     fs::write(path, "1")?;
@@ -755,6 +926,74 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_update_spec_blockio() {
+        let logger = slog::Logger::root(slog::Discard, o!());
+
+        let null_rdev = fs::metadata("/dev/null").unwrap().rdev();
+        let guest_major = stat::major(null_rdev) as i64;
+        let guest_minor = stat::minor(null_rdev) as i64;
+        let (host_major, host_minor) = (254, 2);
+
+        let mut blkio = oci::LinuxBlockIoBuilder::default()
+            .weight_device(vec![oci::LinuxWeightDeviceBuilder::default()
+                .major(host_major)
+                .minor(host_minor)
+                .weight(200u16)
+                .build()
+                .unwrap()])
+            .throttle_read_bps_device(vec![
+                oci::LinuxThrottleDeviceBuilder::default()
+                    .major(host_major)
+                    .minor(host_minor)
+                    .rate(1048576u64)
+                    .build()
+                    .unwrap(),
+                // not hotplugged, must be left untouched
+                oci::LinuxThrottleDeviceBuilder::default()
+                    .major(8)
+                    .minor(0)
+                    .rate(2048u64)
+                    .build()
+                    .unwrap(),
+            ])
+            .throttle_write_iops_device(vec![oci::LinuxThrottleDeviceBuilder::default()
+                .major(host_major)
+                .minor(host_minor)
+                .rate(100u64)
+                .build()
+                .unwrap()])
+            .build()
+            .unwrap();
+
+        let res_updates = HashMap::from_iter(vec![(
+            ("b".to_string(), host_major, host_minor),
+            DeviceInfo {
+                cgroup_type: "b".to_string(),
+                guest_major,
+                guest_minor,
+            },
+        )]);
+
+        update_spec_blockio(&logger, &mut blkio, &res_updates);
+
+        let weight_devices = blkio.weight_device().clone().unwrap();
+        assert_eq!(weight_devices[0].major(), guest_major);
+        assert_eq!(weight_devices[0].minor(), guest_minor);
+        assert_eq!(weight_devices[0].weight(), Some(200));
+
+        let read_bps_devices = blkio.throttle_read_bps_device().clone().unwrap();
+        assert_eq!(read_bps_devices[0].major(), guest_major);
+        assert_eq!(read_bps_devices[0].minor(), guest_minor);
+        assert_eq!(read_bps_devices[0].rate(), 1048576);
+        assert_eq!(read_bps_devices[1].major(), 8);
+        assert_eq!(read_bps_devices[1].minor(), 0);
+
+        let write_iops_devices = blkio.throttle_write_iops_device().clone().unwrap();
+        assert_eq!(write_iops_devices[0].major(), guest_major);
+        assert_eq!(write_iops_devices[0].minor(), guest_minor);
+    }
+
     #[test]
     fn test_update_spec_devices_guest_host_conflict() {
         let logger = slog::Logger::root(slog::Discard, o!());
@@ -1130,6 +1369,26 @@ mod tests {
         assert_eq!(relpath.unwrap(), "/0000:00:02.0/0000:01:03.0/0000:02:04.0");
     }
 
+    #[tokio::test]
+    async fn test_pcipath_to_sysfs_with_rescan() {
+        let testdir = tempdir().expect("failed to create tmpdir");
+        let rootbuspath = testdir.path().to_str().unwrap();
+
+        let path234 = pci::Path::from_str("02/03/04").unwrap();
+
+        // Create mock sysfs files for bridges at 0000:00:02.0 (bus 01)
+        // and 0000:01:03.0 (bus 02)
+        let bridge2path = format!("{}{}", rootbuspath, "/0000:00:02.0");
+        let bridge3path = format!("{}/0000:01:03.0", bridge2path);
+        fs::create_dir_all(format!("{}/pci_bus/0000:01", bridge2path)).unwrap();
+        fs::create_dir_all(format!("{}/pci_bus/0000:02", bridge3path)).unwrap();
+
+        // When the topology is fully enumerated the result matches the
+        // plain resolver without any rescan being triggered.
+        let relpath = pcipath_to_sysfs_with_rescan(rootbuspath, &path234).await;
+        assert_eq!(relpath.unwrap(), "/0000:00:02.0/0000:01:03.0/0000:02:04.0");
+    }
+
     // We use device specific variants of this for real cases, but
     // they have some complications that make them troublesome to unit
     // test
@@ -3,7 +3,9 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 //
-use crate::device::pcipath_to_sysfs;
+#[cfg(target_arch = "s390x")]
+use crate::ccw;
+use crate::device::pcipath_to_sysfs_with_rescan;
 use crate::linux_abi::*;
 use crate::pci;
 use crate::sandbox::Sandbox;
@@ -19,7 +21,7 @@ pub async fn wait_for_net_interface(
     pcipath: &pci::Path,
 ) -> Result<()> {
     let root_bus_sysfs = format!("{}{}", SYSFS_DIR, create_pci_root_bus_path());
-    let sysfs_rel_path = pcipath_to_sysfs(&root_bus_sysfs, pcipath)?;
+    let sysfs_rel_path = pcipath_to_sysfs_with_rescan(&root_bus_sysfs, pcipath).await?;
 
     let matcher = NetPciMatcher::new(&sysfs_rel_path);
 
@@ -30,7 +32,37 @@ pub async fn wait_for_net_interface(
         r"[./]+{}/[a-z0-9/]*net/[a-z0-9/]*",
         matcher.devpath.as_str()
     );
-    let re = Regex::new(&pattern).expect("BUG: Failed to compile regex for NetPciMatcher");
+    if net_interface_exists(&pattern)? {
+        return Ok(());
+    }
+    let _uev = wait_for_uevent(sandbox, matcher).await?;
+
+    Ok(())
+}
+
+#[cfg(target_arch = "s390x")]
+pub async fn wait_for_ccw_net_interface(
+    sandbox: &Arc<Mutex<Sandbox>>,
+    device: &ccw::Device,
+) -> Result<()> {
+    let matcher = NetCcwMatcher::new(CCW_ROOT_BUS_PATH, device);
+
+    // Check if the interface is already added in case network is cold-plugged
+    // or the uevent loop is started before network is added.
+    // We check for the ccw device in the sysfs directory for network devices.
+    let pattern = format!(r"/{}/virtio[0-9]+/net/", device);
+    if net_interface_exists(&pattern)? {
+        return Ok(());
+    }
+    let _uev = wait_for_uevent(sandbox, matcher).await?;
+
+    Ok(())
+}
+
+// Check whether a network interface whose sysfs device path matches the given
+// pattern already exists, by scanning the interface symlinks in /sys/class/net.
+fn net_interface_exists(pattern: &str) -> Result<bool> {
+    let re = Regex::new(pattern).expect("BUG: Failed to compile regex for net interface check");
 
     for entry in fs::read_dir(SYSFS_NET_PATH)? {
         let entry = entry?;
@@ -41,12 +73,10 @@ pub async fn wait_for_net_interface(
             .ok_or_else(|| anyhow!("Expected symlink in dir {}", SYSFS_NET_PATH))?;
 
         if re.is_match(target_path_str) {
-            return Ok(());
+            return Ok(true);
         }
     }
-    let _uev = wait_for_uevent(sandbox, matcher).await?;
-
-    Ok(())
+    Ok(false)
 }
 
 #[derive(Debug)]
@@ -73,6 +103,35 @@ impl UeventMatcher for NetPciMatcher {
     }
 }
 
+#[cfg(target_arch = "s390x")]
+#[derive(Debug)]
+pub struct NetCcwMatcher {
+    rex: Regex,
+}
+
+#[cfg(target_arch = "s390x")]
+impl NetCcwMatcher {
+    pub fn new(root_bus_path: &str, device: &ccw::Device) -> Self {
+        let re = format!(
+            r"^{}/0\.[0-3]\.[0-9a-f]{{1,4}}/{}/virtio[0-9]+/net/",
+            root_bus_path, device
+        );
+        NetCcwMatcher {
+            rex: Regex::new(&re).expect("BUG: failed to compile NetCcwMatcher regex"),
+        }
+    }
+}
+
+#[cfg(target_arch = "s390x")]
+impl UeventMatcher for NetCcwMatcher {
+    fn is_match(&self, uev: &Uevent) -> bool {
+        self.rex.is_match(&uev.devpath)
+            && uev.subsystem == "net"
+            && !uev.interface.is_empty()
+            && uev.action == "add"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,4 +170,41 @@ mod tests {
         assert!(!matcher_a.is_match(&uev_c));
         assert!(!matcher_b.is_match(&uev_c));
     }
+
+    #[cfg(target_arch = "s390x")]
+    #[tokio::test]
+    async fn test_net_ccw_matcher() {
+        use std::str::FromStr;
+
+        let root_bus = CCW_ROOT_BUS_PATH;
+        let relpath = "0.0.0002";
+
+        let mut uev = crate::uevent::Uevent::default();
+        uev.action = crate::linux_abi::U_EVENT_ACTION_ADD.to_string();
+        uev.subsystem = String::from("net");
+        uev.interface = String::from("eth0");
+        uev.devpath = format!(
+            "{}/0.0.0001/{}/virtio1/net/{}",
+            root_bus, relpath, uev.interface
+        );
+
+        let device = crate::ccw::Device::from_str(relpath).unwrap();
+        let matcher = NetCcwMatcher::new(root_bus, &device);
+        assert!(matcher.is_match(&uev));
+
+        // A different ccw device
+        let mut uev_other = uev.clone();
+        uev_other.devpath = format!("{}/0.0.0001/0.0.0003/virtio1/net/eth1", root_bus);
+        assert!(!matcher.is_match(&uev_other));
+
+        // Wrong subsystem
+        let mut uev_block = uev.clone();
+        uev_block.subsystem = String::from("block");
+        assert!(!matcher.is_match(&uev_block));
+
+        // Removal event
+        let mut uev_remove = uev.clone();
+        uev_remove.action = crate::linux_abi::U_EVENT_ACTION_REMOVE.to_string();
+        assert!(!matcher.is_match(&uev_remove));
+    }
 }
@@ -0,0 +1,309 @@
+// Copyright (c) 2019 Ant Financial
+// Copyright (c) 2024 Intel Corporation
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use crate::device::vfio_device_handler::wait_for_pci_device;
+use crate::device::{
+    pcipath_to_sysfs_with_rescan, DeviceContext, DeviceHandler, DeviceInfo, SpecUpdate, BLOCK,
+};
+use crate::linux_abi::{create_pci_root_bus_path, SYSFS_BUS_VDPA_PATH, SYSFS_DIR, SYSTEM_DEV_PATH};
+use crate::pci;
+use crate::sandbox::Sandbox;
+use crate::uevent::{wait_for_uevent, Uevent, UeventMatcher};
+use anyhow::{anyhow, Context, Result};
+use kata_types::device::{DRIVER_VDPA_BLK_TYPE, DRIVER_VDPA_NET_TYPE};
+use protocols::agent::Device;
+use regex::Regex;
+use slog::Logger;
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+// The driver that exposes a vDPA device as a regular virtio device inside
+// the guest, giving containers a netdev/blockdev backed by the
+// hardware-offloaded data path.
+const VIRTIO_VDPA_DRIVER: &str = "virtio_vdpa";
+
+#[derive(Debug)]
+pub struct VhostVdpaDeviceHandler {}
+
+#[async_trait::async_trait]
+impl DeviceHandler for VhostVdpaDeviceHandler {
+    #[instrument]
+    fn driver_types(&self) -> &[&str] {
+        &[DRIVER_VDPA_NET_TYPE, DRIVER_VDPA_BLK_TYPE]
+    }
+
+    #[instrument]
+    async fn device_handler(&self, device: &Device, ctx: &mut DeviceContext) -> Result<SpecUpdate> {
+        let pcipath = pci::Path::from_str(&device.id)?;
+
+        // Wait for the parent PCI device, whose vendor driver registers
+        // the vDPA device on the vdpa bus.
+        wait_for_pci_device(ctx.sandbox, &pcipath).await?;
+
+        let root_bus_sysfs = format!("{}{}", SYSFS_DIR, create_pci_root_bus_path());
+        let sysfs_rel_path = pcipath_to_sysfs_with_rescan(&root_bus_sysfs, &pcipath).await?;
+
+        let vdpa_name = wait_for_vdpa_device(ctx.sandbox, &sysfs_rel_path).await?;
+
+        // Rebind the vDPA device to virtio_vdpa so that it surfaces as a
+        // plain virtio device; the vendor driver may have attached it to
+        // vhost_vdpa by default.
+        vdpa_driver_override(
+            ctx.logger,
+            SYSFS_BUS_VDPA_PATH,
+            &vdpa_name,
+            VIRTIO_VDPA_DRIVER,
+        )?;
+
+        match device.type_.as_str() {
+            DRIVER_VDPA_NET_TYPE => {
+                // The interface shows up on its own once the virtio-net
+                // driver probes; nothing in the spec needs updating.
+                wait_for_vdpa_net_interface(ctx.sandbox, &sysfs_rel_path, &vdpa_name).await?;
+                Ok(SpecUpdate::default())
+            }
+            DRIVER_VDPA_BLK_TYPE => {
+                let vm_path =
+                    get_vdpa_blk_device_name(ctx.sandbox, &sysfs_rel_path, &vdpa_name).await?;
+                Ok(DeviceInfo::new(&vm_path, true)
+                    .context("New device info")?
+                    .into())
+            }
+            _ => Err(anyhow!("Unsupported vDPA device type: {}", device.type_)),
+        }
+    }
+}
+
+// Wait for the vDPA device registered under the given PCI device and
+// return its name on the vdpa bus (e.g. "vdpa0").
+#[instrument]
+async fn wait_for_vdpa_device(sandbox: &Arc<Mutex<Sandbox>>, relpath: &str) -> Result<String> {
+    let matcher = VdpaBusMatcher::new(relpath);
+    let uev = wait_for_uevent(sandbox, matcher).await?;
+    uev.devpath
+        .rsplit('/')
+        .next()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Bad device path {:?} in uevent", &uev.devpath))
+}
+
+#[instrument]
+async fn wait_for_vdpa_net_interface(
+    sandbox: &Arc<Mutex<Sandbox>>,
+    relpath: &str,
+    vdpa_name: &str,
+) -> Result<()> {
+    let matcher = VdpaNetMatcher::new(relpath, vdpa_name);
+    let _uev = wait_for_uevent(sandbox, matcher).await?;
+    Ok(())
+}
+
+#[instrument]
+async fn get_vdpa_blk_device_name(
+    sandbox: &Arc<Mutex<Sandbox>>,
+    relpath: &str,
+    vdpa_name: &str,
+) -> Result<String> {
+    let matcher = VdpaBlkMatcher::new(relpath, vdpa_name);
+    let uev = wait_for_uevent(sandbox, matcher).await?;
+    Ok(format!("{}/{}", SYSTEM_DEV_PATH, &uev.devname))
+}
+
+// Force a given vDPA device to bind to the given driver, the vdpa bus
+// equivalent of pci_driver_override().
+#[instrument]
+fn vdpa_driver_override<T, U>(logger: &Logger, sysvdpa: T, devname: &str, drv: U) -> Result<()>
+where
+    T: AsRef<OsStr> + std::fmt::Debug,
+    U: AsRef<OsStr> + std::fmt::Debug,
+{
+    let sysvdpa = Path::new(&sysvdpa);
+    let drv = drv.as_ref();
+    info!(logger, "rebind_vdpa_driver: {} => {:?}", devname, drv);
+
+    let devpath = sysvdpa.join("devices").join(devname);
+    let overridepath = &devpath.join("driver_override");
+
+    fs::write(overridepath, drv.as_bytes())?;
+
+    let drvpath = &devpath.join("driver");
+    let need_unbind = match fs::read_link(drvpath) {
+        Ok(d) if d.file_name() == Some(drv) => return Ok(()), // Nothing to do
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false, // No current driver
+        Err(e) => return Err(anyhow!("Error checking driver on {}: {}", devname, e)),
+        Ok(_) => true, // Current driver needs unbinding
+    };
+    if need_unbind {
+        let unbindpath = &drvpath.join("unbind");
+        fs::write(unbindpath, devname)?;
+    }
+    let probepath = sysvdpa.join("drivers_probe");
+    fs::write(probepath, devname)?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct VdpaBusMatcher {
+    rex: Regex,
+}
+
+impl VdpaBusMatcher {
+    pub fn new(relpath: &str) -> VdpaBusMatcher {
+        let root_bus = create_pci_root_bus_path();
+        let re = format!(r"^{}{}/vdpa[0-9]+$", root_bus, relpath);
+
+        VdpaBusMatcher {
+            rex: Regex::new(&re).expect("BUG: failed to compile VdpaBusMatcher regex"),
+        }
+    }
+}
+
+impl UeventMatcher for VdpaBusMatcher {
+    fn is_match(&self, uev: &Uevent) -> bool {
+        uev.action == "add" && self.rex.is_match(&uev.devpath)
+    }
+}
+
+#[derive(Debug)]
+pub struct VdpaNetMatcher {
+    rex: Regex,
+}
+
+impl VdpaNetMatcher {
+    pub fn new(relpath: &str, vdpa_name: &str) -> VdpaNetMatcher {
+        let root_bus = create_pci_root_bus_path();
+        let re = format!(r"^{}{}/{}/virtio[0-9]+/net/", root_bus, relpath, vdpa_name);
+
+        VdpaNetMatcher {
+            rex: Regex::new(&re).expect("BUG: failed to compile VdpaNetMatcher regex"),
+        }
+    }
+}
+
+impl UeventMatcher for VdpaNetMatcher {
+    fn is_match(&self, uev: &Uevent) -> bool {
+        uev.action == "add"
+            && uev.subsystem == "net"
+            && !uev.interface.is_empty()
+            && self.rex.is_match(&uev.devpath)
+    }
+}
+
+#[derive(Debug)]
+pub struct VdpaBlkMatcher {
+    rex: Regex,
+}
+
+impl VdpaBlkMatcher {
+    pub fn new(relpath: &str, vdpa_name: &str) -> VdpaBlkMatcher {
+        let root_bus = create_pci_root_bus_path();
+        let re = format!(
+            r"^{}{}/{}/virtio[0-9]+/block/",
+            root_bus, relpath, vdpa_name
+        );
+
+        VdpaBlkMatcher {
+            rex: Regex::new(&re).expect("BUG: failed to compile VdpaBlkMatcher regex"),
+        }
+    }
+}
+
+impl UeventMatcher for VdpaBlkMatcher {
+    fn is_match(&self, uev: &Uevent) -> bool {
+        uev.subsystem == BLOCK && self.rex.is_match(&uev.devpath) && !uev.devname.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[allow(clippy::redundant_clone)]
+    async fn test_vdpa_matchers() {
+        let root_bus = create_pci_root_bus_path();
+        let relpath = "/0000:00:0a.0";
+
+        let mut uev_bus = crate::uevent::Uevent::default();
+        uev_bus.action = crate::linux_abi::U_EVENT_ACTION_ADD.to_string();
+        uev_bus.subsystem = String::from("vdpa");
+        uev_bus.devpath = format!("{}{}/vdpa0", root_bus, relpath);
+        let matcher_bus = VdpaBusMatcher::new(relpath);
+        assert!(matcher_bus.is_match(&uev_bus));
+
+        // A vdpa device under a different PCI device
+        let mut uev_other = uev_bus.clone();
+        uev_other.devpath = format!("{}/0000:00:0b.0/vdpa1", root_bus);
+        assert!(!matcher_bus.is_match(&uev_other));
+
+        // The virtio child device must not match the bus matcher
+        let mut uev_virtio = uev_bus.clone();
+        uev_virtio.devpath = format!("{}{}/vdpa0/virtio1", root_bus, relpath);
+        assert!(!matcher_bus.is_match(&uev_virtio));
+
+        let mut uev_net = uev_bus.clone();
+        uev_net.subsystem = String::from("net");
+        uev_net.interface = String::from("eth0");
+        uev_net.devpath = format!("{}{}/vdpa0/virtio1/net/eth0", root_bus, relpath);
+        let matcher_net = VdpaNetMatcher::new(relpath, "vdpa0");
+        assert!(matcher_net.is_match(&uev_net));
+        assert!(!matcher_net.is_match(&uev_bus));
+
+        let mut uev_blk = uev_bus.clone();
+        uev_blk.subsystem = BLOCK.to_string();
+        uev_blk.devname = String::from("vda");
+        uev_blk.devpath = format!("{}{}/vdpa0/virtio1/block/vda", root_bus, relpath);
+        let matcher_blk = VdpaBlkMatcher::new(relpath, "vdpa0");
+        assert!(matcher_blk.is_match(&uev_blk));
+        assert!(!matcher_blk.is_match(&uev_net));
+    }
+
+    #[test]
+    fn test_vdpa_driver_override() {
+        let logger = slog::Logger::root(slog::Discard, o!());
+        let testdir = tempfile::tempdir().expect("failed to create tmpdir");
+        let sysvdpa = testdir.path(); // Path to mock /sys/bus/vdpa
+
+        let dev0 = "vdpa0";
+        let dev0path = sysvdpa.join("devices").join(dev0);
+        let dev0drv = dev0path.join("driver");
+        let dev0override = dev0path.join("driver_override");
+
+        let drvapath = sysvdpa.join("drivers").join("vhost_vdpa");
+        let drvaunbind = drvapath.join("unbind");
+
+        let probepath = sysvdpa.join("drivers_probe");
+
+        // Start mocking dev0 as being unbound
+        fs::create_dir_all(&dev0path).unwrap();
+
+        vdpa_driver_override(&logger, sysvdpa, dev0, VIRTIO_VDPA_DRIVER).unwrap();
+        assert_eq!(
+            fs::read_to_string(&dev0override).unwrap(),
+            VIRTIO_VDPA_DRIVER
+        );
+        assert_eq!(fs::read_to_string(&probepath).unwrap(), dev0);
+
+        // Now mock dev0 already being attached to vhost_vdpa
+        fs::create_dir_all(&drvapath).unwrap();
+        std::os::unix::fs::symlink(&drvapath, dev0drv).unwrap();
+        std::fs::remove_file(&probepath).unwrap();
+
+        vdpa_driver_override(&logger, sysvdpa, dev0, VIRTIO_VDPA_DRIVER).unwrap();
+        assert_eq!(
+            fs::read_to_string(&dev0override).unwrap(),
+            VIRTIO_VDPA_DRIVER
+        );
+        assert_eq!(fs::read_to_string(&probepath).unwrap(), dev0);
+        assert_eq!(fs::read_to_string(drvaunbind).unwrap(), dev0);
+    }
+}
@@ -6,7 +6,9 @@
 
 #[cfg(target_arch = "s390x")]
 use crate::ap;
-use crate::device::{pcipath_to_sysfs, DevUpdate, DeviceContext, DeviceHandler, SpecUpdate};
+use crate::device::{
+    pcipath_to_sysfs_with_rescan, DevUpdate, DeviceContext, DeviceHandler, SpecUpdate,
+};
 use crate::linux_abi::*;
 use crate::pci;
 use crate::sandbox::Sandbox;
@@ -109,6 +111,10 @@ impl DeviceHandler for VfioApDeviceHandler {
             match device.type_.as_str() {
                 DRIVER_VFIO_AP_TYPE => {
                     wait_for_ap_device(ctx.sandbox, ap_address).await?;
+                    // The add uevent only signals that the queue device
+                    // exists; make sure it actually came online before
+                    // exposing the crypto node to the workload.
+                    check_ap_device(ctx.sandbox, ap_address).await?;
                 }
                 DRIVER_VFIO_AP_COLD_TYPE => {
                     check_ap_device(ctx.sandbox, ap_address).await?;
@@ -248,7 +254,7 @@ pub async fn wait_for_pci_device(
     pcipath: &pci::Path,
 ) -> Result<pci::Address> {
     let root_bus_sysfs = format!("{}{}", SYSFS_DIR, create_pci_root_bus_path());
-    let sysfs_rel_path = pcipath_to_sysfs(&root_bus_sysfs, pcipath)?;
+    let sysfs_rel_path = pcipath_to_sysfs_with_rescan(&root_bus_sysfs, pcipath).await?;
     let matcher = PciMatcher::new(&sysfs_rel_path)?;
 
     let uev = wait_for_uevent(sandbox, matcher).await?;
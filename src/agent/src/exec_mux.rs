@@ -0,0 +1,565 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Multiplexed exec I/O over a single vsock connection per container.
+//!
+//! Instead of opening one hybrid-vsock stream per stdio pipe (as the
+//! passfd_io listener does), the shim may open a single connection on
+//! `agent.exec_mux_port` and exchange framed stdio data for any number of
+//! exec sessions on it. Frames carry a stream id, a frame type and a
+//! payload. Flow control is window based: each data stream starts with
+//! `DEFAULT_WINDOW_SIZE` bytes of send credit and the consumer returns
+//! credit with `WindowUpdate` frames, so one slow stream can not stall the
+//! connection by filling socket buffers.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{Mutex, Notify};
+use tokio_vsock::SockAddr::Vsock;
+use tokio_vsock::{VsockListener, VsockStream};
+
+use crate::AGENT_CONFIG;
+
+/// Frame header: stream id (u32 BE) + frame type (u8) + payload length (u32 BE).
+pub const FRAME_HEADER_SIZE: usize = 9;
+
+/// Maximum payload carried by a single frame.
+pub const MAX_FRAME_PAYLOAD: usize = 64 * 1024;
+
+/// Initial send window granted to every data stream, in bytes.
+pub const DEFAULT_WINDOW_SIZE: u32 = 256 * 1024;
+
+/// Per-stream channel depth between the demux task and consumers.
+const STREAM_CHANNEL_SIZE: usize = 16;
+
+/// How often dead sessions are reaped and the pool maps are shrunk back
+/// to their occupancy.
+const SHRINK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Buffered inbound bytes across all sessions, i.e. demuxed data that no
+// consumer has picked up yet.
+static BUFFERED_BYTES: AtomicUsize = AtomicUsize::new(0);
+// Live exec streams across all sessions.
+static LIVE_STREAMS: AtomicUsize = AtomicUsize::new(0);
+// Live mux connections; kept in an atomic so that the sync metrics path
+// does not need the async session map lock.
+static LIVE_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Total inbound bytes currently buffered by the mux, for metrics.
+pub(crate) fn buffered_bytes() -> usize {
+    BUFFERED_BYTES.load(Ordering::Relaxed)
+}
+
+/// Number of live exec streams, for metrics.
+pub(crate) fn live_streams() -> usize {
+    LIVE_STREAMS.load(Ordering::Relaxed)
+}
+
+/// Number of live mux connections, for metrics.
+pub(crate) fn live_sessions() -> usize {
+    LIVE_SESSIONS.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ExecMuxError {
+    /// The configured `agent.exec_mux_buffer_limit` ceiling is reached;
+    /// new exec sessions are rejected until consumers drain their data.
+    #[error("exec mux buffered bytes {used} reached the configured ceiling {ceiling}")]
+    BufferCeilingReached { used: usize, ceiling: usize },
+}
+
+// Convenience function to obtain the scope logger.
+fn sl() -> slog::Logger {
+    slog_scope::logger().new(o!("subsystem" => "exec_mux"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    Stdin = 0,
+    Stdout = 1,
+    Stderr = 2,
+    /// Payload is a u32 BE amount of send credit returned to the peer.
+    WindowUpdate = 3,
+    /// Half-close of the stream in the sender's direction, no payload.
+    Close = 4,
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = anyhow::Error;
+
+    fn try_from(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(FrameType::Stdin),
+            1 => Ok(FrameType::Stdout),
+            2 => Ok(FrameType::Stderr),
+            3 => Ok(FrameType::WindowUpdate),
+            4 => Ok(FrameType::Close),
+            _ => Err(anyhow!("invalid exec mux frame type {}", v)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub stream_id: u32,
+    pub typ: FrameType,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(stream_id: u32, typ: FrameType, payload: Vec<u8>) -> Self {
+        Frame {
+            stream_id,
+            typ,
+            payload,
+        }
+    }
+
+    pub fn window_update(stream_id: u32, credit: u32) -> Self {
+        Frame {
+            stream_id,
+            typ: FrameType::WindowUpdate,
+            payload: credit.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Serialize the frame header followed by the payload.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(FRAME_HEADER_SIZE + self.payload.len());
+        buf.extend_from_slice(&self.stream_id.to_be_bytes());
+        buf.push(self.typ as u8);
+        buf.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.payload);
+        buf
+    }
+
+    /// Parse a frame header, returning the stream id, frame type and
+    /// payload length still to be read.
+    pub fn decode_header(header: &[u8; FRAME_HEADER_SIZE]) -> Result<(u32, FrameType, usize)> {
+        let stream_id = u32::from_be_bytes(header[0..4].try_into().unwrap());
+        let typ = FrameType::try_from(header[4])?;
+        let len = u32::from_be_bytes(header[5..9].try_into().unwrap()) as usize;
+        if len > MAX_FRAME_PAYLOAD {
+            bail!(
+                "exec mux frame payload {} exceeds limit {}",
+                len,
+                MAX_FRAME_PAYLOAD
+            );
+        }
+        Ok((stream_id, typ, len))
+    }
+
+    pub fn window_credit(&self) -> Result<u32> {
+        if self.typ != FrameType::WindowUpdate || self.payload.len() != 4 {
+            bail!("not a window update frame");
+        }
+        Ok(u32::from_be_bytes(self.payload[..].try_into().unwrap()))
+    }
+}
+
+/// Send-side window accounting for one stream.
+#[derive(Debug)]
+pub struct SendWindow {
+    credit: Mutex<u32>,
+    notify: Notify,
+}
+
+impl SendWindow {
+    pub fn new(initial: u32) -> Self {
+        SendWindow {
+            credit: Mutex::new(initial),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Reserve up to `wanted` bytes of send credit, waiting until at least
+    /// one byte is available. Returns the amount actually reserved.
+    pub async fn acquire(&self, wanted: usize) -> usize {
+        loop {
+            {
+                let mut credit = self.credit.lock().await;
+                if *credit > 0 {
+                    let granted = std::cmp::min(wanted as u32, *credit);
+                    *credit -= granted;
+                    return granted as usize;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Return credit granted by a peer window update.
+    pub async fn release(&self, amount: u32) {
+        let mut credit = self.credit.lock().await;
+        *credit = credit.saturating_add(amount);
+        self.notify.notify_waiters();
+    }
+
+    pub async fn available(&self) -> u32 {
+        *self.credit.lock().await
+    }
+}
+
+struct StreamState {
+    /// Demuxed stdin data handed to the exec session.
+    data_tx: Sender<Vec<u8>>,
+    /// Credit left for frames the agent sends on this stream.
+    send_window: Arc<SendWindow>,
+    /// Bytes sitting in `data_tx` that the consumer has not received yet.
+    buffered: Arc<AtomicUsize>,
+}
+
+/// Consumer side of a demuxed stream. Wraps the channel receiver so that
+/// buffered byte accounting is released as data is picked up.
+pub struct StreamReader {
+    data_rx: Receiver<Vec<u8>>,
+    buffered: Arc<AtomicUsize>,
+}
+
+impl StreamReader {
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        let data = self.data_rx.recv().await?;
+        self.buffered.fetch_sub(data.len(), Ordering::Relaxed);
+        BUFFERED_BYTES.fetch_sub(data.len(), Ordering::Relaxed);
+        Some(data)
+    }
+}
+
+/// One multiplexed connection, shared by every exec session of a container.
+pub struct MuxSession {
+    writer: Arc<Mutex<WriteHalf<VsockStream>>>,
+    streams: Arc<Mutex<HashMap<u32, StreamState>>>,
+    /// Set once the demux task exits, so the maintenance task can reap
+    /// the session from the pool.
+    closed: AtomicBool,
+}
+
+impl MuxSession {
+    fn new(stream: VsockStream) -> Arc<Self> {
+        let (reader, writer) = tokio::io::split(stream);
+        let session = Arc::new(MuxSession {
+            writer: Arc::new(Mutex::new(writer)),
+            streams: Arc::new(Mutex::new(HashMap::new())),
+            closed: AtomicBool::new(false),
+        });
+        session.spawn_demux(reader);
+        session
+    }
+
+    /// Register a stream id, returning the reader for demuxed inbound
+    /// (stdin) data and the send window for outbound frames. Fails with
+    /// [`ExecMuxError::BufferCeilingReached`] when the configured buffer
+    /// memory ceiling is already consumed.
+    pub async fn open_stream(
+        &self,
+        stream_id: u32,
+    ) -> Result<(StreamReader, Arc<SendWindow>), ExecMuxError> {
+        Self::ensure_buffer_capacity(AGENT_CONFIG.exec_mux_buffer_limit as usize)?;
+
+        let (data_tx, data_rx) = channel(STREAM_CHANNEL_SIZE);
+        let send_window = Arc::new(SendWindow::new(DEFAULT_WINDOW_SIZE));
+        let buffered = Arc::new(AtomicUsize::new(0));
+        self.streams.lock().await.insert(
+            stream_id,
+            StreamState {
+                data_tx,
+                send_window: send_window.clone(),
+                buffered: buffered.clone(),
+            },
+        );
+        LIVE_STREAMS.fetch_add(1, Ordering::Relaxed);
+        Ok((StreamReader { data_rx, buffered }, send_window))
+    }
+
+    /// A zero ceiling keeps the limit disabled, matching the other agent
+    /// resource ceilings.
+    fn ensure_buffer_capacity(ceiling: usize) -> Result<(), ExecMuxError> {
+        let used = BUFFERED_BYTES.load(Ordering::Relaxed);
+        if ceiling > 0 && used >= ceiling {
+            return Err(ExecMuxError::BufferCeilingReached { used, ceiling });
+        }
+        Ok(())
+    }
+
+    pub async fn close_stream(&self, stream_id: u32) {
+        if let Some(state) = self.streams.lock().await.remove(&stream_id) {
+            release_stream_accounting(&state);
+        }
+    }
+
+    /// Send process output on the stream, honoring the peer's receive
+    /// window. Blocks while the stream is out of credit.
+    pub async fn send_output(&self, stream_id: u32, typ: FrameType, mut data: &[u8]) -> Result<()> {
+        let window = {
+            let streams = self.streams.lock().await;
+            streams
+                .get(&stream_id)
+                .map(|s| s.send_window.clone())
+                .ok_or_else(|| anyhow!("unknown exec mux stream {}", stream_id))?
+        };
+
+        while !data.is_empty() {
+            let wanted = std::cmp::min(data.len(), MAX_FRAME_PAYLOAD);
+            let granted = window.acquire(wanted).await;
+            let (chunk, rest) = data.split_at(granted);
+            self.send_frame(&Frame::new(stream_id, typ, chunk.to_vec()))
+                .await?;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    async fn send_frame(&self, frame: &Frame) -> Result<()> {
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&frame.encode()).await?;
+        Ok(())
+    }
+
+    fn spawn_demux(self: &Arc<Self>, mut reader: ReadHalf<VsockStream>) {
+        let session = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut header = [0u8; FRAME_HEADER_SIZE];
+                if reader.read_exact(&mut header).await.is_err() {
+                    break;
+                }
+                let (stream_id, typ, len) = match Frame::decode_header(&header) {
+                    Ok(h) => h,
+                    Err(e) => {
+                        error!(sl(), "dropping exec mux connection: {:?}", e);
+                        break;
+                    }
+                };
+                let mut payload = vec![0u8; len];
+                if len > 0 && reader.read_exact(&mut payload).await.is_err() {
+                    break;
+                }
+
+                if let Err(e) = session
+                    .handle_frame(Frame::new(stream_id, typ, payload))
+                    .await
+                {
+                    warn!(sl(), "exec mux frame error: {:?}", e);
+                }
+            }
+            // Connection gone: wake up anything waiting on inbound data
+            // and give back the buffer accounting of every stream.
+            let mut streams = session.streams.lock().await;
+            for (_, state) in streams.drain() {
+                release_stream_accounting(&state);
+            }
+            session.closed.store(true, Ordering::Release);
+        });
+    }
+
+    async fn handle_frame(&self, frame: Frame) -> Result<()> {
+        match frame.typ {
+            FrameType::Stdin => {
+                let (tx, buffered) = {
+                    let streams = self.streams.lock().await;
+                    streams
+                        .get(&frame.stream_id)
+                        .map(|s| (s.data_tx.clone(), s.buffered.clone()))
+                        .ok_or_else(|| anyhow!("unknown exec mux stream {}", frame.stream_id))?
+                };
+                let credit = frame.payload.len() as u32;
+                buffered.fetch_add(frame.payload.len(), Ordering::Relaxed);
+                BUFFERED_BYTES.fetch_add(frame.payload.len(), Ordering::Relaxed);
+                if tx.send(frame.payload).await.is_err() {
+                    buffered.fetch_sub(credit as usize, Ordering::Relaxed);
+                    BUFFERED_BYTES.fetch_sub(credit as usize, Ordering::Relaxed);
+                    bail!("exec mux stream {} consumer gone", frame.stream_id);
+                }
+                // The consumer channel is bounded, so a successful send
+                // means the data has been taken off the connection; return
+                // the credit to the peer right away.
+                self.send_frame(&Frame::window_update(frame.stream_id, credit))
+                    .await?;
+            }
+            FrameType::WindowUpdate => {
+                let credit = frame.window_credit()?;
+                let streams = self.streams.lock().await;
+                if let Some(s) = streams.get(&frame.stream_id) {
+                    s.send_window.release(credit).await;
+                }
+            }
+            FrameType::Close => {
+                self.close_stream(frame.stream_id).await;
+            }
+            FrameType::Stdout | FrameType::Stderr => {
+                bail!("unexpected output frame from peer");
+            }
+        }
+        Ok(())
+    }
+}
+
+// Give back the global accounting held by a stream that is going away.
+// Data still sitting in its channel is dropped together with the state.
+fn release_stream_accounting(state: &StreamState) {
+    let pending = state.buffered.swap(0, Ordering::Relaxed);
+    BUFFERED_BYTES.fetch_sub(pending, Ordering::Relaxed);
+    LIVE_STREAMS.fetch_sub(1, Ordering::Relaxed);
+}
+
+lazy_static! {
+    static ref MUX_SESSIONS: Arc<Mutex<HashMap<u32, Arc<MuxSession>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Accept multiplexed exec I/O connections. The shim opens one connection
+/// per container and identifies it by its peer port, mirroring the
+/// passfd_io contract.
+pub(crate) async fn start_listen(port: u32) -> Result<()> {
+    info!(sl(), "start exec mux listener on port {}", port);
+    let mut listener = VsockListener::bind(libc::VMADDR_CID_ANY, port)?;
+    tokio::spawn(async move {
+        loop {
+            if let Ok((stream, Vsock(addr))) = listener.accept().await {
+                let port = addr.port();
+                let session = MuxSession::new(stream);
+                if MUX_SESSIONS.lock().await.insert(port, session).is_none() {
+                    LIVE_SESSIONS.fetch_add(1, Ordering::Relaxed);
+                }
+                info!(sl(), "accept exec mux connection from peer port {}", port);
+            }
+        }
+    });
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(SHRINK_INTERVAL).await;
+            shrink_sessions().await;
+        }
+    });
+    Ok(())
+}
+
+// Reap sessions whose connection has gone away and shrink the pool maps
+// back to their occupancy, so that a burst of dense exec usage does not
+// keep its high-water allocations for the lifetime of the sandbox.
+async fn shrink_sessions() {
+    let mut sessions = MUX_SESSIONS.lock().await;
+    let before = sessions.len();
+    sessions.retain(|_, session| !session.closed.load(Ordering::Acquire));
+    let reaped = before - sessions.len();
+    LIVE_SESSIONS.fetch_sub(reaped, Ordering::Relaxed);
+    sessions.shrink_to_fit();
+    for session in sessions.values() {
+        session.streams.lock().await.shrink_to_fit();
+    }
+    if reaped > 0 {
+        info!(sl(), "reaped {} dead exec mux sessions", reaped);
+    }
+}
+
+/// Look up the mux session registered for the given peer port.
+pub(crate) async fn get_session(port: u32) -> Option<Arc<MuxSession>> {
+    MUX_SESSIONS.lock().await.get(&port).cloned()
+}
+
+/// Drop the session for a container once it is removed.
+pub(crate) async fn remove_session(port: u32) {
+    if MUX_SESSIONS.lock().await.remove(&port).is_some() {
+        LIVE_SESSIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frame = Frame::new(7, FrameType::Stdout, b"hello".to_vec());
+        let encoded = frame.encode();
+        assert_eq!(encoded.len(), FRAME_HEADER_SIZE + 5);
+
+        let header: [u8; FRAME_HEADER_SIZE] = encoded[..FRAME_HEADER_SIZE].try_into().unwrap();
+        let (stream_id, typ, len) = Frame::decode_header(&header).unwrap();
+        assert_eq!(stream_id, 7);
+        assert_eq!(typ, FrameType::Stdout);
+        assert_eq!(len, 5);
+        assert_eq!(&encoded[FRAME_HEADER_SIZE..], b"hello");
+    }
+
+    #[test]
+    fn test_decode_header_rejects_bad_input() {
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        header[4] = 0xff;
+        assert!(Frame::decode_header(&header).is_err());
+
+        // Oversized payload length.
+        let frame = Frame::new(1, FrameType::Stdin, vec![]);
+        let mut encoded = frame.encode();
+        encoded[5..9].copy_from_slice(&((MAX_FRAME_PAYLOAD as u32 + 1).to_be_bytes()));
+        let header: [u8; FRAME_HEADER_SIZE] = encoded[..FRAME_HEADER_SIZE].try_into().unwrap();
+        assert!(Frame::decode_header(&header).is_err());
+    }
+
+    #[test]
+    fn test_window_update_frame() {
+        let frame = Frame::window_update(3, 4096);
+        assert_eq!(frame.window_credit().unwrap(), 4096);
+
+        let data = Frame::new(3, FrameType::Stdin, vec![0u8; 4]);
+        assert!(data.window_credit().is_err());
+    }
+
+    #[test]
+    fn test_buffer_ceiling() {
+        // A zero ceiling keeps the limit disabled.
+        assert!(MuxSession::ensure_buffer_capacity(0).is_ok());
+
+        BUFFERED_BYTES.fetch_add(1024, Ordering::Relaxed);
+        let err = MuxSession::ensure_buffer_capacity(512).unwrap_err();
+        assert!(matches!(
+            err,
+            ExecMuxError::BufferCeilingReached { ceiling: 512, .. }
+        ));
+        assert!(MuxSession::ensure_buffer_capacity(usize::MAX).is_ok());
+        BUFFERED_BYTES.fetch_sub(1024, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reader_releases_accounting() {
+        let (tx, data_rx) = channel(STREAM_CHANNEL_SIZE);
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let mut reader = StreamReader {
+            data_rx,
+            buffered: buffered.clone(),
+        };
+
+        buffered.fetch_add(5, Ordering::Relaxed);
+        BUFFERED_BYTES.fetch_add(5, Ordering::Relaxed);
+        tx.send(b"hello".to_vec()).await.unwrap();
+
+        assert_eq!(reader.recv().await.unwrap(), b"hello");
+        assert_eq!(buffered.load(Ordering::Relaxed), 0);
+
+        drop(tx);
+        assert!(reader.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_send_window_accounting() {
+        let window = SendWindow::new(10);
+        assert_eq!(window.acquire(4).await, 4);
+        assert_eq!(window.acquire(100).await, 6);
+        assert_eq!(window.available().await, 0);
+
+        window.release(8).await;
+        assert_eq!(window.acquire(3).await, 3);
+        assert_eq!(window.available().await, 5);
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Broadcast channel for process exit statuses.
+//!
+//! The SIGCHLD reaper publishes every container/exec exit here and the
+//! WaitProcesses streaming RPC forwards them to the shim, which can then
+//! watch all processes over a single stream instead of keeping one
+//! blocked WaitProcess call per process.
+
+use tokio::sync::broadcast;
+
+/// Exits buffered per subscriber; a subscriber lagging further behind
+/// than this misses events and has to fall back to WaitProcess.
+const CHANNEL_CAPACITY: usize = 128;
+
+#[derive(Clone, Debug)]
+pub struct ProcessExit {
+    pub container_id: String,
+    pub exec_id: String,
+    pub exit_code: i32,
+}
+
+lazy_static! {
+    static ref EXIT_TX: broadcast::Sender<ProcessExit> = broadcast::channel(CHANNEL_CAPACITY).0;
+}
+
+/// Publish an exit status to all connected WaitProcesses streams.
+pub fn publish(exit: ProcessExit) {
+    // Sending fails only when nobody is subscribed, which is fine.
+    let _ = EXIT_TX.send(exit);
+}
+
+/// Subscribe to exit statuses published from now on.
+pub fn subscribe() -> broadcast::Receiver<ProcessExit> {
+    EXIT_TX.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_subscribe() {
+        let mut rx = subscribe();
+
+        publish(ProcessExit {
+            container_id: "testcontainer".to_string(),
+            exec_id: "testexec".to_string(),
+            exit_code: 137,
+        });
+
+        let exit = rx.recv().await.unwrap();
+        assert_eq!(exit.container_id, "testcontainer");
+        assert_eq!(exit.exec_id, "testexec");
+        assert_eq!(exit.exit_code, 137);
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_subscriber() {
+        publish(ProcessExit {
+            container_id: "testcontainer".to_string(),
+            exec_id: String::new(),
+            exit_code: 0,
+        });
+    }
+}
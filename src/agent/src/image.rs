@@ -33,6 +33,10 @@ const K8S_CONTAINER_TYPE_KEYS: [&str; 2] = [
     "io.kubernetes.cri-o.ContainerType",
 ];
 
+/// Annotation prefix ocicrypt puts on encrypted layers (JWE headers,
+/// wrapped keys and keyprovider payloads all live under it).
+const OCICRYPT_ANNOTATION_PREFIX: &str = "org.opencontainers.image.enc";
+
 #[rustfmt::skip]
 lazy_static! {
     pub static ref IMAGE_SERVICE: Arc<Mutex<Option<ImageService>>> = Arc::new(Mutex::new(None));
@@ -80,6 +84,21 @@ impl ImageService {
                 debug!(sl(), "Use imagepolicy file {:?}", image_policy_file);
                 image_client.config.file_paths.policy_path = image_policy_file.clone();
             }
+
+            // Encrypted layers are handed to the ocicrypt keyprovider
+            // named in OCICRYPT_KEYPROVIDER_CONFIG, which the agent
+            // points at the CDH once its socket comes up, so wrapped
+            // layer keys are only ever unwrapped inside the TEE.
+            match env::var("OCICRYPT_KEYPROVIDER_CONFIG") {
+                Ok(path) => debug!(
+                    sl(),
+                    "encrypted layers are decrypted through keyprovider config {}", path
+                ),
+                Err(_) => debug!(
+                    sl(),
+                    "no ocicrypt keyprovider config: encrypted layers cannot be decrypted"
+                ),
+            }
         }
         Self { image_client }
     }
@@ -202,11 +221,24 @@ impl ImageService {
             .pull_image(image, &bundle_path, &None, &None)
             .await;
         match res {
-            Ok(image) => {
+            Ok(image_digest) => {
                 info!(
                     sl(),
-                    "pull and unpack image {image:?}, cid: {cid:?} succeeded."
+                    "pull and unpack image {image_digest:?}, cid: {cid:?} succeeded."
                 );
+                // Measure what was pulled before anything runs from it:
+                // the reference names what was asked for, the digest pins
+                // what was actually unpacked. A sandbox whose register
+                // cannot be extended must not run unmeasured images.
+                crate::attestation::extend_runtime_measurement("guest-pull", image, &image_digest)
+                    .await
+                    .context("measure pulled image")?;
+                // Keep the shared layer store within its configured cap;
+                // an eviction failure only delays the trim to the next
+                // pull, so it must not fail the pull that succeeded.
+                if let Err(e) = crate::image_cache::trim_layer_store(&sl()) {
+                    warn!(sl(), "failed to trim the image layer store: {:?}", e);
+                }
             }
             Err(e) => {
                 error!(
@@ -214,7 +246,10 @@ impl ImageService {
                     "pull and unpack image {image:?}, cid: {cid:?} failed with {:?}.",
                     e.to_string()
                 );
-                return Err(e);
+                // This error travels back to the CreateContainer caller,
+                // so a failed layer decryption is reported per layer
+                // rather than as a generic pull failure.
+                return Err(annotate_decrypt_failure(image, e));
             }
         };
         let image_bundle_path = scoped_join(&bundle_path, "rootfs")?;
@@ -222,6 +257,39 @@ impl ImageService {
     }
 }
 
+// Image-rs carries the digest of a layer it failed to decrypt in its
+// error chain. Pick the digest out and state whether the CDH keyprovider
+// was wired up at all, so the error CreateContainer reports names the
+// layer and the likely cause. Errors that are not about decryption pass
+// through untouched.
+fn annotate_decrypt_failure(image: &str, e: anyhow::Error) -> anyhow::Error {
+    let chain = format!("{:?}", e);
+    if !chain.contains(OCICRYPT_ANNOTATION_PREFIX)
+        && !chain.contains("decrypt")
+        && !chain.contains("keyprovider")
+    {
+        return e;
+    }
+
+    let layer = chain
+        .split(|c: char| c.is_whitespace() || c == '"' || c == ',')
+        .find(|word| word.starts_with("sha256:"))
+        .unwrap_or("<unknown>")
+        .to_string();
+
+    if env::var("OCICRYPT_KEYPROVIDER_CONFIG").is_err() {
+        return e.context(format!(
+            "failed to decrypt layer {} of image {}: no CDH keyprovider is available in this guest",
+            layer, image
+        ));
+    }
+
+    e.context(format!(
+        "failed to decrypt layer {} of image {} through the CDH keyprovider",
+        layer, image
+    ))
+}
+
 /// get_process overrides the OCI process spec with pause image process spec if needed
 pub fn get_process(
     ocip: &oci::Process,
@@ -0,0 +1,181 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Size cap and LRU eviction for the guest image layer store.
+//!
+//! Layers pulled in the guest land in a content-addressed store under
+//! the image work directory, where every container of the sandbox that
+//! shares a base layer reuses it instead of downloading it again. The
+//! store is never bounded though, and on a memory-backed rootfs it
+//! competes with the workloads for guest memory. When
+//! `agent.image_layer_cache_size` is set the store is trimmed after
+//! each pull: layers still mapped as a lower directory of a live
+//! overlay mount are pinned, the rest are evicted oldest first until
+//! the store fits the cap again.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use slog::Logger;
+
+use crate::image::KATA_IMAGE_WORK_DIR;
+use crate::AGENT_CONFIG;
+
+/// Subdirectory of the image work directory holding the
+/// content-addressed layers.
+const LAYER_STORE_SUBDIR: &str = "layers";
+
+const PROC_MOUNTS: &str = "/proc/mounts";
+
+struct LayerEntry {
+    path: PathBuf,
+    size: u64,
+    created: SystemTime,
+}
+
+/// Evict least recently pulled layers until the store fits the
+/// configured cap. A no-op when `agent.image_layer_cache_size` is left
+/// at its zero default.
+pub fn trim_layer_store(logger: &Logger) -> Result<()> {
+    let cap = AGENT_CONFIG.image_layer_cache_size;
+    if cap == 0 {
+        return Ok(());
+    }
+    let cap = cap << 20;
+
+    let store = Path::new(KATA_IMAGE_WORK_DIR).join(LAYER_STORE_SUBDIR);
+    if !store.is_dir() {
+        return Ok(());
+    }
+
+    let mut layers = collect_layers(&store)?;
+    let mut total: u64 = layers.iter().map(|layer| layer.size).sum();
+    if total <= cap {
+        return Ok(());
+    }
+
+    let mounts = fs::read_to_string(PROC_MOUNTS).context("read /proc/mounts")?;
+    let pinned = overlay_lower_dirs(&mounts);
+
+    layers.sort_by_key(|layer| layer.created);
+    for layer in layers {
+        if total <= cap {
+            break;
+        }
+        if pinned
+            .iter()
+            .any(|lower| lower.starts_with(&layer.path) || layer.path.starts_with(lower))
+        {
+            continue;
+        }
+        fs::remove_dir_all(&layer.path)
+            .with_context(|| format!("evict layer {}", layer.path.display()))?;
+        total -= layer.size;
+        info!(logger, "evicted image layer from the cache";
+            "layer" => layer.path.display().to_string(),
+            "size" => layer.size,
+        );
+    }
+
+    if total > cap {
+        warn!(logger, "image layer store exceeds its cap but every remaining layer is in use";
+            "size" => total,
+            "cap" => cap,
+        );
+    }
+
+    Ok(())
+}
+
+fn collect_layers(store: &Path) -> Result<Vec<LayerEntry>> {
+    let mut layers = Vec::new();
+    for entry in fs::read_dir(store).with_context(|| format!("read {}", store.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let created = entry
+            .metadata()
+            .and_then(|md| md.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        layers.push(LayerEntry {
+            size: dir_size(&path),
+            path,
+            created,
+        });
+    }
+    Ok(layers)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let mut total = 0;
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            if let Ok(md) = entry.metadata() {
+                if md.is_dir() {
+                    total += dir_size(&entry.path());
+                } else {
+                    total += md.len();
+                }
+            }
+        }
+    }
+    total
+}
+
+// Every lowerdir of every live overlay mount: a layer under one of
+// these directories is the rootfs of a running container and must not
+// be evicted.
+fn overlay_lower_dirs(mounts: &str) -> HashSet<PathBuf> {
+    let mut dirs = HashSet::new();
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (_source, _target, fstype, options) =
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(s), Some(t), Some(f), Some(o)) => (s, t, f, o),
+                _ => continue,
+            };
+        if fstype != "overlay" {
+            continue;
+        }
+        for option in options.split(',') {
+            if let Some(lower) = option.strip_prefix("lowerdir=") {
+                dirs.extend(lower.split(':').map(PathBuf::from));
+            }
+        }
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_lower_dirs() {
+        let mounts = concat!(
+            "proc /proc proc rw,relatime 0 0\n",
+            "overlay /run/kata-containers/cid/rootfs overlay ",
+            "rw,lowerdir=/run/kata-containers/image/layers/aa:",
+            "/run/kata-containers/image/layers/bb,upperdir=/up,workdir=/work 0 0\n",
+            "tmpfs /tmp tmpfs rw 0 0\n",
+        );
+
+        let dirs = overlay_lower_dirs(mounts);
+        assert_eq!(dirs.len(), 2);
+        assert!(dirs.contains(Path::new("/run/kata-containers/image/layers/aa")));
+        assert!(dirs.contains(Path::new("/run/kata-containers/image/layers/bb")));
+    }
+
+    #[test]
+    fn test_overlay_lower_dirs_ignores_other_mounts() {
+        let mounts = "ext4 /data ext4 rw,lowerdir=/should-not-match 0 0\n";
+        assert!(overlay_lower_dirs(mounts).is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright (c) 2026 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Initdata processing.
+//!
+//! Confidential guests receive a blob of per-sandbox configuration - the
+//! agent policy, the KBS configuration for the attestation-agent and the
+//! CDH, and any extra certificates - through a dedicated raw block device
+//! whose content the host measures at launch. At boot the agent locates
+//! that device, verifies and parses the blob and splits it into the
+//! per-component files, before the policy engine and the guest components
+//! that consume them are started. The blob's digest is written next to
+//! the extracted files so the attestation-agent can bind it into the TEE
+//! evidence and a relying party can check what configuration the sandbox
+//! actually booted with.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use slog::Logger;
+
+/// Magic bytes identifying an initdata device.
+const INITDATA_MAGIC: &[u8] = b"initdata";
+
+/// Largest blob the agent is willing to parse. Initdata carries small
+/// text files, so anything bigger is a malformed or hostile device.
+const INITDATA_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The initdata format version this agent understands.
+const INITDATA_VERSION: &str = "0.1.0";
+
+/// Directory the per-component files are extracted to.
+pub const INITDATA_PATH: &str = "/run/confidential-containers/initdata";
+
+/// Hex digest of the blob, bound into the TEE evidence by the
+/// attestation-agent.
+pub const INITDATA_DIGEST_PATH: &str = "/run/confidential-containers/initdata/digest";
+
+/// Attestation-agent configuration extracted from initdata, if any.
+pub const AA_CONFIG_PATH: &str = "/run/confidential-containers/initdata/aa.toml";
+
+/// CDH configuration extracted from initdata, if any.
+pub const CDH_CONFIG_PATH: &str = "/run/confidential-containers/initdata/cdh.toml";
+
+/// Agent policy extracted from initdata, if any.
+pub const POLICY_PATH: &str = "/run/confidential-containers/initdata/policy.rego";
+
+/// Pre-provisioned image decryption keys extracted from initdata, if
+/// any. In offline confidential mode the CDH configuration points its
+/// key broker at this file instead of a network KBS.
+pub const KEYS_PATH: &str = "/run/confidential-containers/initdata/keys.toml";
+
+/// Whitelisted policy signing keys extracted from initdata, if any: one
+/// hex-encoded ed25519 public key per line. When this file exists,
+/// SetPolicy only accepts policies carrying a valid signature from one
+/// of these keys.
+pub const POLICY_SIGNING_KEYS_PATH: &str =
+    "/run/confidential-containers/initdata/policy-signing-keys";
+
+// Convenience function to obtain the scope logger.
+fn sl() -> slog::Logger {
+    slog_scope::logger().new(o!("subsystem" => "initdata"))
+}
+
+// The TOML document carried by the device: a format version, the digest
+// algorithm the host used when measuring the blob, and one entry per
+// file to extract.
+#[derive(Debug, Deserialize)]
+struct InitData {
+    version: String,
+    algorithm: String,
+    data: HashMap<String, String>,
+}
+
+/// Locate the initdata device, if one was attached, and split its blob
+/// into the per-component files under [`INITDATA_PATH`]. A sandbox
+/// without an initdata device boots normally; a device carrying a
+/// malformed blob fails the boot, since silently dropping a measured
+/// policy or KBS configuration would be worse.
+pub fn process_initdata(logger: &Logger) -> Result<()> {
+    let device = match find_initdata_device() {
+        Some(device) => device,
+        None => {
+            info!(logger, "no initdata device found");
+            return Ok(());
+        }
+    };
+
+    info!(logger, "processing initdata device {:?}", device);
+    let blob = read_initdata_blob(&device)
+        .with_context(|| format!("read initdata blob from {:?}", device))?;
+    extract_initdata(&blob, Path::new(INITDATA_PATH)).context("extract initdata")
+}
+
+// Scan the block devices for one starting with the initdata magic.
+// Unreadable devices are skipped: the scan runs before any of them is
+// mounted, but some (e.g. an empty cdrom) fail the read.
+fn find_initdata_device() -> Option<PathBuf> {
+    let entries = fs::read_dir("/sys/class/block").ok()?;
+    for entry in entries.flatten() {
+        let device = Path::new("/dev").join(entry.file_name());
+        let mut magic = [0u8; 8];
+        let readable = File::open(&device)
+            .and_then(|mut f| f.read_exact(&mut magic))
+            .is_ok();
+        if readable && magic == INITDATA_MAGIC {
+            return Some(device);
+        }
+    }
+    None
+}
+
+// Device layout: 8 bytes of magic, the payload length as a little-endian
+// u64, then the payload itself; the rest of the device is ignored.
+fn read_initdata_blob(device: &Path) -> Result<Vec<u8>> {
+    let mut file = File::open(device)?;
+
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if magic != INITDATA_MAGIC {
+        bail!("bad initdata magic {:?}", magic);
+    }
+
+    let mut len = [0u8; 8];
+    file.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len == 0 || len > INITDATA_MAX_SIZE {
+        bail!("invalid initdata length {}", len);
+    }
+
+    let mut blob = vec![0u8; len as usize];
+    file.read_exact(&mut blob)?;
+    Ok(blob)
+}
+
+// Parse the blob and write its digest and one file per data entry into
+// dir, with permissions that keep the extracted secrets agent-private.
+fn extract_initdata(blob: &[u8], dir: &Path) -> Result<()> {
+    let initdata: InitData = toml::from_slice(blob).context("parse initdata TOML")?;
+
+    if initdata.version != INITDATA_VERSION {
+        bail!("unsupported initdata version {}", initdata.version);
+    }
+
+    // The digest covers the blob exactly as the host measured it.
+    let digest = match initdata.algorithm.as_str() {
+        "sha256" => hex_digest(Sha256::digest(blob).as_slice()),
+        "sha384" => hex_digest(Sha384::digest(blob).as_slice()),
+        "sha512" => hex_digest(Sha512::digest(blob).as_slice()),
+        algorithm => bail!("unsupported initdata digest algorithm {}", algorithm),
+    };
+
+    fs::create_dir_all(dir).with_context(|| format!("create initdata dir {:?}", dir))?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+
+    for (name, content) in &initdata.data {
+        // Entry names become file names as-is, so anything that would
+        // escape the initdata directory is rejected.
+        if name.is_empty() || name.contains('/') || name.contains("..") {
+            bail!("invalid initdata entry name {:?}", name);
+        }
+
+        let path = dir.join(name);
+        fs::write(&path, content).with_context(|| format!("write initdata entry {:?}", path))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        info!(sl(), "extracted initdata entry {:?}", path);
+    }
+
+    let digest_path = dir.join("digest");
+    fs::write(&digest_path, format!("{}:{}", initdata.algorithm, digest))
+        .with_context(|| format!("write initdata digest {:?}", digest_path))?;
+    fs::set_permissions(&digest_path, fs::Permissions::from_mode(0o600))?;
+
+    Ok(())
+}
+
+fn hex_digest(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const TEST_INITDATA: &str = r#"
+version = "0.1.0"
+algorithm = "sha256"
+
+[data]
+"aa.toml" = "[token_configs]"
+"cdh.toml" = "[kbc]"
+"policy.rego" = "package agent_policy"
+"#;
+
+    fn make_device(dir: &Path, blob: &[u8]) -> PathBuf {
+        let path = dir.join("device");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(INITDATA_MAGIC).unwrap();
+        file.write_all(&(blob.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(blob).unwrap();
+        // Trailing device space must be ignored.
+        file.write_all(&[0u8; 512]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_initdata_blob() {
+        let dir = tempdir().unwrap();
+        let device = make_device(dir.path(), TEST_INITDATA.as_bytes());
+        let blob = read_initdata_blob(&device).unwrap();
+        assert_eq!(blob, TEST_INITDATA.as_bytes());
+
+        let bad_magic = dir.path().join("bad-magic");
+        fs::write(&bad_magic, b"not-init-data").unwrap();
+        assert!(read_initdata_blob(&bad_magic).is_err());
+
+        let bad_len = dir.path().join("bad-len");
+        let mut file = File::create(&bad_len).unwrap();
+        file.write_all(INITDATA_MAGIC).unwrap();
+        file.write_all(&u64::MAX.to_le_bytes()).unwrap();
+        assert!(read_initdata_blob(&bad_len).is_err());
+    }
+
+    #[test]
+    fn test_extract_initdata() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("initdata");
+
+        extract_initdata(TEST_INITDATA.as_bytes(), &out).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(out.join("policy.rego")).unwrap(),
+            "package agent_policy"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("aa.toml")).unwrap(),
+            "[token_configs]"
+        );
+        assert_eq!(fs::read_to_string(out.join("cdh.toml")).unwrap(), "[kbc]");
+
+        let digest = fs::read_to_string(out.join("digest")).unwrap();
+        let expected = hex_digest(Sha256::digest(TEST_INITDATA.as_bytes()).as_slice());
+        assert_eq!(digest, format!("sha256:{}", expected));
+    }
+
+    #[test]
+    fn test_extract_initdata_rejects_bad_blobs() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("initdata");
+
+        let bad_version = TEST_INITDATA.replace("0.1.0", "9.9.9");
+        assert!(extract_initdata(bad_version.as_bytes(), &out).is_err());
+
+        let bad_algorithm = TEST_INITDATA.replace("sha256", "md5");
+        assert!(extract_initdata(bad_algorithm.as_bytes(), &out).is_err());
+
+        let traversal = TEST_INITDATA.replace("aa.toml", "../aa.toml");
+        assert!(extract_initdata(traversal.as_bytes(), &out).is_err());
+    }
+}
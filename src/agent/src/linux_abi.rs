@@ -88,18 +88,31 @@ pub const SYSFS_CPU_ONLINE_PATH: &str = "/sys/devices/system/cpu/online";
 pub const SYSFS_MEMORY_BLOCK_SIZE_PATH: &str = "/sys/devices/system/memory/block_size_bytes";
 pub const SYSFS_MEMORY_HOTPLUG_PROBE_PATH: &str = "/sys/devices/system/memory/probe";
 pub const SYSFS_MEMORY_ONLINE_PATH: &str = "/sys/devices/system/memory";
+pub const SYSFS_MEMORY_STATE_FILE: &str = "state";
+
+pub const SYSFS_HUGEPAGES_PREFIX: &str = "/sys/kernel/mm/hugepages";
+pub const DEV_HUGEPAGES_PATH: &str = "/dev/hugepages";
 
 pub const SYSFS_SCSI_HOST_PATH: &str = "/sys/class/scsi_host";
 pub const SYSFS_NET_PATH: &str = "/sys/class/net";
 
 pub const SYSFS_BUS_PCI_PATH: &str = "/sys/bus/pci";
+pub const SYSFS_BUS_VDPA_PATH: &str = "/sys/bus/vdpa";
 
 pub const SYSFS_CGROUPPATH: &str = "/sys/fs/cgroup";
 pub const SYSFS_ONLINE_FILE: &str = "online";
 
 pub const PROC_MOUNTSTATS: &str = "/proc/self/mountstats";
+pub const PROC_MOUNTS_FILE: &str = "/proc/mounts";
 pub const PROC_CGROUPS: &str = "/proc/cgroups";
 
+pub const PROC_DROP_CACHES: &str = "/proc/sys/vm/drop_caches";
+pub const PROC_COMPACT_MEMORY: &str = "/proc/sys/vm/compact_memory";
+pub const PROC_COMPACTION_PROACTIVENESS: &str = "/proc/sys/vm/compaction_proactiveness";
+pub const SYSFS_NUMA_NODE_PATH: &str = "/sys/devices/system/node";
+pub const SYSFS_PAGE_REPORTING_ORDER_PATH: &str =
+    "/sys/module/page_reporting/parameters/page_reporting_order";
+
 pub const SYSTEM_DEV_PATH: &str = "/dev";
 
 // Linux UEvent related consts.
@@ -28,7 +28,8 @@ use nix::sys::socket::{self, AddressFamily, SockFlag, SockType, VsockAddr};
 use nix::unistd::{self, dup, sync, Pid};
 use std::env;
 use std::ffi::OsStr;
-use std::fs::{self, File};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
 use std::os::unix::fs::{self as unixfs, FileTypeExt};
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
@@ -37,11 +38,17 @@ use std::process::Command;
 use std::sync::Arc;
 use tracing::{instrument, span};
 
+mod attestation;
+mod balloon;
 mod cdh;
 mod config;
 mod console;
+mod cpu_topology;
 mod device;
+mod exec_mux;
+mod exit_notifier;
 mod features;
+mod initdata;
 mod linux_abi;
 mod metrics;
 mod mount;
@@ -50,13 +57,18 @@ mod netlink;
 mod network;
 mod passfd_io;
 mod pci;
+mod port_forward;
 pub mod random;
 mod sandbox;
+mod shutdown_reason;
 mod signal;
 mod storage;
+mod time_sync;
 mod uevent;
+mod upgrade;
 mod util;
 mod version;
+mod virtiofs_monitor;
 mod watcher;
 
 use config::GuestComponentsProcs;
@@ -67,7 +79,9 @@ use slog::{debug, error, info, o, warn, Logger};
 use uevent::watch_uevents;
 
 use futures::future::join_all;
+use futures::StreamExt;
 use rustjail::pipestream::PipeStream;
+use std::time::Duration;
 use tokio::{
     io::AsyncWrite,
     sync::{
@@ -79,8 +93,11 @@ use tokio::{
 
 #[cfg(feature = "guest-pull")]
 mod image;
+#[cfg(feature = "guest-pull")]
+mod image_cache;
 
 mod rpc;
+mod rpc_limits;
 mod tracer;
 
 #[cfg(feature = "agent-policy")]
@@ -106,6 +123,11 @@ const CDH_PATH: &str = "/usr/local/bin/confidential-data-hub";
 const CDH_SOCKET: &str = "/run/confidential-containers/cdh.sock";
 const CDH_SOCKET_URI: &str = concatcp!(UNIX_SOCKET_PREFIX, CDH_SOCKET);
 
+// Guest-local socket on which the agent proxies the read-only part of the
+// attestation-agent API to workloads (see the attestation module).
+const ATTESTATION_PROXY_SOCKET: &str = "/run/kata-containers/attestation-proxy.sock";
+const ATTESTATION_PROXY_URI: &str = concatcp!(UNIX_SOCKET_PREFIX, ATTESTATION_PROXY_SOCKET);
+
 const API_SERVER_PATH: &str = "/usr/local/bin/api-server-rest";
 
 /// Path of ocicrypt config file. This is used by image-rs when decrypting image.
@@ -125,11 +147,21 @@ const OCICRYPT_CONFIG: &str = formatcp!(
 const DEFAULT_LAUNCH_PROCESS_TIMEOUT: i32 = 6;
 
 lazy_static! {
-    static ref AGENT_CONFIG: AgentConfig =
+    static ref AGENT_CONFIG: config::ConfigHandle =
         // Note: We can't do AgentOpts.parse() here to send through the processed arguments to AgentConfig
         // clap::Parser::parse() greedily process all command line input including cargo test parameters,
         // so should only be used inside main.
-        AgentConfig::from_cmdline("/proc/cmdline", env::args().collect()).unwrap();
+        config::ConfigHandle::new(
+            AgentConfig::from_cmdline("/proc/cmdline", env::args().collect()).unwrap_or_else(|e| {
+                // Report the parse failure as a config error rather than
+                // the generic panic the unwrap below would produce.
+                shutdown_reason::record(
+                    shutdown_reason::ShutdownKind::ConfigError,
+                    &format!("failed to parse agent configuration: {:#}", e),
+                );
+                panic!("failed to parse agent configuration: {:?}", e);
+            }),
+        );
 }
 
 #[cfg(feature = "agent-policy")]
@@ -170,27 +202,103 @@ fn announce(logger: &Logger, config: &AgentConfig) {
 }
 
 // Create a thread to handle reading from the logger pipe. The thread will
-// output to the vsock port specified, or stdout.
+// output to the vsock port specified, or stdout. When writing to vsock,
+// the task survives its peer disconnecting (e.g. across a shim restart):
+// logs buffer in the pipe while nobody is connected and forwarding
+// resumes with the next accepted connection instead of orphaning the
+// stream.
 async fn create_logger_task(rfd: RawFd, vsock_port: u32, shutdown: Receiver<bool>) -> Result<()> {
     let mut reader = PipeStream::from_fd(rfd);
-    let mut writer: Box<dyn AsyncWrite + Unpin + Send> = if vsock_port > 0 {
-        let listenfd = socket::socket(
-            AddressFamily::Vsock,
-            SockType::Stream,
-            SockFlag::SOCK_CLOEXEC,
-            None,
-        )?;
 
-        let addr = VsockAddr::new(libc::VMADDR_CID_ANY, vsock_port);
-        socket::bind(listenfd, &addr)?;
-        socket::listen(listenfd, 1)?;
+    if vsock_port == 0 {
+        let mut writer = tokio::io::stdout();
+        let _ = util::interruptable_io_copier(&mut reader, &mut writer, shutdown).await;
+        return Ok(());
+    }
 
-        Box::new(util::get_vsock_stream(listenfd).await?)
-    } else {
-        Box::new(tokio::io::stdout())
-    };
+    let listenfd = socket::socket(
+        AddressFamily::Vsock,
+        SockType::Stream,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+
+    let addr = VsockAddr::new(libc::VMADDR_CID_ANY, vsock_port);
+    socket::bind(listenfd, &addr)?;
+    socket::listen(listenfd, 1)?;
+
+    let mut incoming = util::get_vsock_incoming(listenfd);
+    loop {
+        let mut shutdown_wait = shutdown.clone();
+        let stream = tokio::select! {
+            _ = shutdown_wait.changed() => break,
+            stream = incoming.next() => match stream {
+                Some(Ok(stream)) => stream,
+                Some(Err(e)) => {
+                    eprintln!("WARNING: failed to accept log vsock connection: {:?}", e);
+                    continue;
+                }
+                None => break,
+            },
+        };
+
+        // Forward through a bounded, lossy ring: a host that stops
+        // draining the log stream must never back up the pipe into the
+        // logger and stall the agent. Discarded bytes are counted in the
+        // agent metrics.
+        let mut writer: Box<dyn AsyncWrite + Unpin + Send> = Box::new(stream);
+        match util::interruptable_ring_copier(
+            &mut reader,
+            &mut writer,
+            LOG_RING_CAPACITY,
+            util::RingPolicy::DropOldest,
+            metrics::add_log_dropped_bytes,
+            shutdown.clone(),
+        )
+        .await
+        {
+            // EOF on the logger pipe or a shutdown request: all done.
+            Ok(_) => break,
+            // The connection died, typically because the shim went away.
+            // Keep the pipe open and wait for the peer to come back.
+            Err(e) => eprintln!(
+                "WARNING: log forwarding connection lost, waiting for a reconnect: {:?}",
+                e
+            ),
+        }
+    }
+
+    Ok(())
+}
 
-    let _ = util::interruptable_io_copier(&mut reader, &mut writer, shutdown).await;
+// How many log bytes may sit in the forwarding ring waiting for the host
+// before the oldest are discarded.
+const LOG_RING_CAPACITY: usize = 2 << 20;
+
+// Interval of the liveness heartbeat emitted on the agent log.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+// Emit a periodic heartbeat on the agent log. The log stream reaches the
+// host over vsock, so a host watching it can tell a quiet-but-healthy
+// agent from one that is wedged or unreachable, and the regular traffic
+// makes a silently dead log connection surface as a write error the
+// forwarder recovers from.
+async fn heartbeat_task(logger: Logger, mut shutdown: Receiver<bool>) -> Result<()> {
+    let mut ticker = tokio::time::interval(HEARTBEAT_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately; the task starting is not an
+    // event worth recording.
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = ticker.tick() => match metrics::seconds_since_last_rpc() {
+                Some(idle) => info!(logger, "agent heartbeat"; "rpc_idle_secs" => idle),
+                None => info!(logger, "agent heartbeat"; "rpc_idle_secs" => "no rpc served yet"),
+            },
+        }
+    }
 
     Ok(())
 }
@@ -208,6 +316,8 @@ async fn real_main(init_mode: bool) -> std::result::Result<(), Box<dyn std::erro
 
     let (shutdown_tx, shutdown_rx) = channel(true);
 
+    let mut early_uevents_task: Option<JoinHandle<Result<()>>> = None;
+
     if init_mode {
         // dup a new file descriptor for this temporary logger writer,
         // since this logger would be dropped and it's writer would
@@ -222,15 +332,21 @@ async fn real_main(init_mode: bool) -> std::result::Result<(), Box<dyn std::erro
             logging::create_logger(NAME, "agent", slog::Level::Debug, writer);
 
         // Must mount proc fs before parsing kernel command line
-        general_mount(&logger).map_err(|e| {
-            error!(logger, "fail general mount: {}", e);
-            e
-        })?;
+        boot_step(
+            &logger,
+            "mount rootfs pseudo-filesystems",
+            general_mount(&logger),
+        )?;
 
         lazy_static::initialize(&AGENT_CONFIG);
         let cgroup_v2 = AGENT_CONFIG.unified_cgroup_hierarchy || AGENT_CONFIG.cgroup_no_v1 == "all";
 
         init_agent_as_init(&logger, cgroup_v2)?;
+
+        // Make early device activity visible until the sandbox uevent
+        // watcher takes over.
+        early_uevents_task = Some(tokio::spawn(uevent::early_uevent_reader(logger.clone())));
+
         drop(logger_async_guard);
     } else {
         lazy_static::initialize(&AGENT_CONFIG);
@@ -243,6 +359,13 @@ async fn real_main(init_mode: bool) -> std::result::Result<(), Box<dyn std::erro
 
     tasks.push(log_handle);
 
+    // Serve the shutdown reason channel as early as possible, so even
+    // failures during the rest of the boot sequence are reported.
+    let shutdown_reason_vport = config.shutdown_reason_vport as u32;
+    if shutdown_reason_vport > 0 {
+        shutdown_reason::start_listener(shutdown_reason_vport);
+    }
+
     let writer = unsafe { File::from_raw_fd(wfd) };
 
     // Recreate a logger with the log level get from "/proc/cmdline".
@@ -251,6 +374,11 @@ async fn real_main(init_mode: bool) -> std::result::Result<(), Box<dyn std::erro
 
     announce(&logger, config);
 
+    tasks.push(tokio::spawn(heartbeat_task(
+        logger.clone(),
+        shutdown_rx.clone(),
+    )));
+
     // This variable is required as it enables the global (and crucially static) logger,
     // which is required to satisfy the the lifetime constraints of the auto-generated gRPC code.
     let global_logger = slog_scope::set_global_logger(logger.new(o!("subsystem" => "rpc")));
@@ -282,6 +410,26 @@ async fn real_main(init_mode: bool) -> std::result::Result<(), Box<dyn std::erro
         passfd_io::start_listen(passfd_listener_port).await?;
     }
 
+    // Start the multiplexed exec io listener
+    let exec_mux_port = config.exec_mux_port as u32;
+    if exec_mux_port != 0 {
+        exec_mux::start_listen(exec_mux_port).await?;
+    }
+
+    // Start the managed guest time sync service
+    if config.guest_time_sync {
+        time_sync::start(&logger);
+    }
+
+    // Watch virtiofs shared-fs mounts so IO fails fast when virtiofsd dies
+    virtiofs_monitor::start(&logger);
+
+    // The sandbox uevent watcher spawned by start_sandbox() takes over
+    // from the boot-time reader.
+    if let Some(task) = early_uevents_task {
+        task.abort();
+    }
+
     // Start the sandbox and wait for its ttRPC server to end
     start_sandbox(&logger, config, init_mode, &mut tasks, shutdown_rx.clone()).await?;
 
@@ -351,6 +499,9 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         exit(0);
     }
 
+    // From here on a panic is an agent failure worth reporting to the shim.
+    shutdown_reason::install_panic_hook();
+
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
@@ -358,6 +509,14 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let init_mode = unistd::getpid() == Pid::from_raw(1);
     let result = rt.block_on(real_main(init_mode));
 
+    match &result {
+        Ok(()) => shutdown_reason::record(shutdown_reason::ShutdownKind::Clean, "agent exited"),
+        Err(e) => shutdown_reason::record(
+            shutdown_reason::ShutdownKind::Error,
+            &format!("agent exited with error: {:#}", e),
+        ),
+    }
+
     if init_mode {
         sync();
         let _ = reboot(RebootMode::RB_POWER_OFF);
@@ -386,8 +545,20 @@ async fn start_sandbox(
         tasks.push(debug_console_task);
     }
 
+    // Best effort: without free page reporting the host still reclaims
+    // memory through the balloon, just more slowly.
+    if let Err(e) = balloon::setup_free_page_reporting(logger) {
+        warn!(logger, "failed to set up free page reporting: {:?}", e);
+    }
+
     // Initialize unique sandbox structure.
-    let s = Sandbox::new(logger).context("Failed to create sandbox")?;
+    let mut s = Sandbox::new(logger).context("Failed to create sandbox")?;
+
+    // If this startup is the second half of an in-place upgrade,
+    // re-attach the state of the previous binary.
+    if let Some(state) = upgrade::pending_state(logger) {
+        upgrade::resume(logger, &mut s, state);
+    }
     if init_mode {
         s.rtnl.handle_localhost().await?;
     }
@@ -395,6 +566,11 @@ async fn start_sandbox(
     #[cfg(feature = "guest-pull")]
     image::set_proxy_env_vars().await;
 
+    // Split the measured initdata blob, if any, into its per-component
+    // files before the policy engine and the guest components that
+    // consume them are started.
+    initdata::process_initdata(logger).context("process initdata")?;
+
     #[cfg(feature = "agent-policy")]
     if let Err(e) = initialize_policy().await {
         error!(logger, "Failed to initialize agent policy: {:?}", e);
@@ -419,14 +595,14 @@ async fn start_sandbox(
     let (tx, rx) = tokio::sync::oneshot::channel();
     sandbox.lock().await.sender = Some(tx);
 
-    let gc_procs = config.guest_components_procs;
-    if !attestation_binaries_available(logger, &gc_procs) {
+    let mut attestation_proxy = None;
+    if !attestation_binaries_available(logger, config) {
         warn!(
             logger,
             "attestation binaries requested for launch not available"
         );
     } else {
-        init_attestation_components(logger, config).await?;
+        attestation_proxy = init_attestation_components(logger, config).await?;
     }
 
     let mut oma = None;
@@ -452,17 +628,26 @@ async fn start_sandbox(
     rx.await?;
     server.shutdown().await?;
 
+    if let Some(mut proxy) = attestation_proxy {
+        proxy.shutdown().await?;
+    }
+
     Ok(())
 }
 
 // Check if required attestation binaries are available on the rootfs.
-fn attestation_binaries_available(logger: &Logger, procs: &GuestComponentsProcs) -> bool {
-    let binaries = match procs {
+fn attestation_binaries_available(logger: &Logger, config: &AgentConfig) -> bool {
+    let mut binaries = match config.guest_components_procs {
         GuestComponentsProcs::AttestationAgent => vec![AA_PATH],
         GuestComponentsProcs::ConfidentialDataHub => vec![AA_PATH, CDH_PATH],
         GuestComponentsProcs::ApiServerRest => vec![AA_PATH, CDH_PATH, API_SERVER_PATH],
         _ => vec![],
     };
+    // The attestation-agent is not spawned in offline confidential mode,
+    // so an air-gapped image does not need to ship it.
+    if config.offline_confidential {
+        binaries.retain(|binary| *binary != AA_PATH);
+    }
     for binary in binaries.iter() {
         if !Path::new(binary).exists() {
             warn!(logger, "{} not found", binary);
@@ -477,15 +662,43 @@ async fn launch_guest_component_procs(logger: &Logger, config: &AgentConfig) ->
         return Ok(());
     }
 
-    debug!(logger, "spawning attestation-agent process {}", AA_PATH);
-    launch_process(
-        logger,
-        AA_PATH,
-        &vec!["--attestation_sock", AA_ATTESTATION_URI],
-        AA_ATTESTATION_SOCKET,
-        DEFAULT_LAUNCH_PROCESS_TIMEOUT,
-    )
-    .map_err(|e| anyhow!("launch_process {} failed: {:?}", AA_PATH, e))?;
+    // Offline confidential mode replaces the network KBS with the
+    // measured initdata bundle. Fail closed here rather than at the
+    // first key request: a sandbox that can neither attest nor decrypt
+    // its images should not come up at all.
+    if config.offline_confidential
+        && !Path::new(initdata::CDH_CONFIG_PATH).exists()
+        && !Path::new(initdata::KEYS_PATH).exists()
+    {
+        return Err(anyhow!(
+            "offline confidential mode is enabled but the initdata bundle carries neither a CDH configuration nor pre-provisioned keys"
+        ));
+    }
+
+    if config.offline_confidential {
+        // The attestation-agent exists to talk to a remote KBS, which an
+        // air-gapped sandbox does not have.
+        info!(
+            logger,
+            "offline confidential mode: not spawning {}", AA_PATH
+        );
+    } else {
+        debug!(logger, "spawning attestation-agent process {}", AA_PATH);
+        let mut aa_args = vec!["--attestation_sock", AA_ATTESTATION_URI];
+        // Configuration extracted from the measured initdata blob takes the
+        // place of whatever default configuration the rootfs ships.
+        if Path::new(initdata::AA_CONFIG_PATH).exists() {
+            aa_args.extend(["-c", initdata::AA_CONFIG_PATH]);
+        }
+        launch_process(
+            logger,
+            AA_PATH,
+            &aa_args,
+            AA_ATTESTATION_SOCKET,
+            DEFAULT_LAUNCH_PROCESS_TIMEOUT,
+        )
+        .map_err(|e| anyhow!("launch_process {} failed: {:?}", AA_PATH, e))?;
+    }
 
     // skip launch of confidential-data-hub and api-server-rest
     if config.guest_components_procs == GuestComponentsProcs::AttestationAgent {
@@ -497,10 +710,14 @@ async fn launch_guest_component_procs(logger: &Logger, config: &AgentConfig) ->
         "spawning confidential-data-hub process {}", CDH_PATH
     );
 
+    let mut cdh_args = vec![];
+    if Path::new(initdata::CDH_CONFIG_PATH).exists() {
+        cdh_args.extend(["-c", initdata::CDH_CONFIG_PATH]);
+    }
     launch_process(
         logger,
         CDH_PATH,
-        &vec![],
+        &cdh_args,
         CDH_SOCKET,
         DEFAULT_LAUNCH_PROCESS_TIMEOUT,
     )
@@ -532,7 +749,12 @@ async fn launch_guest_component_procs(logger: &Logger, config: &AgentConfig) ->
 // and the corresponding procs are enabled in the agent configuration. the process will be
 // launched in the background and the function will return immediately.
 // If the CDH is started, a CDH client will be instantiated and returned.
-async fn init_attestation_components(logger: &Logger, config: &AgentConfig) -> Result<()> {
+// If the attestation proxy is enabled and the attestation-agent is up, the
+// proxy server is started and returned so the caller can shut it down.
+async fn init_attestation_components(
+    logger: &Logger,
+    config: &AgentConfig,
+) -> Result<Option<ttrpc::asynchronous::Server>> {
     launch_guest_component_procs(logger, config).await?;
 
     // If a CDH socket exists, initialize the CDH client and enable ocicrypt
@@ -552,7 +774,36 @@ async fn init_attestation_components(logger: &Logger, config: &AgentConfig) -> R
         ),
     }
 
-    Ok(())
+    let mut attestation_proxy = None;
+    if config.offline_confidential {
+        // No attestation-agent runs in offline confidential mode, so
+        // there is nothing to proxy.
+        if config.enable_attestation_proxy {
+            warn!(
+                logger,
+                "attestation proxy is ignored in offline confidential mode"
+            );
+        }
+    } else {
+        match tokio::fs::metadata(AA_ATTESTATION_SOCKET).await {
+            Ok(md) if md.file_type().is_socket() => {
+                attestation::init_aa_client(AA_ATTESTATION_URI).await?;
+                if config.enable_attestation_proxy {
+                    attestation_proxy =
+                        Some(attestation::start_proxy(ATTESTATION_PROXY_URI).await?);
+                }
+            }
+            Ok(_) => debug!(logger, "File {} is not a socket", AA_ATTESTATION_SOCKET),
+            Err(err) => warn!(
+                logger,
+                "Failed to probe attestation-agent socket file {}: {:?}",
+                AA_ATTESTATION_SOCKET,
+                err
+            ),
+        }
+    }
+
+    Ok(attestation_proxy)
 }
 
 fn wait_for_path_to_exist(logger: &Logger, path: &str, timeout_secs: i32) -> Result<()> {
@@ -597,35 +848,130 @@ fn launch_process(
     Ok(())
 }
 
+// When the agent runs as init there is nowhere for early errors to go:
+// the regular logger only becomes visible once the log forwarder is up,
+// so a failed mount used to surface as a silent hang. Report every
+// early-boot step directly on the console before propagating the result,
+// so a stalled boot at least names the step that never completed.
+fn boot_step<T>(logger: &Logger, step: &str, result: Result<T>) -> Result<T> {
+    let line = match &result {
+        Ok(_) => format!("{} init: [ OK ] {}", NAME, step),
+        Err(e) => format!("{} init: [FAILED] {}: {:#}", NAME, step, e),
+    };
+
+    if let Ok(mut console) = OpenOptions::new().write(true).open(console::CONSOLE_PATH) {
+        let _ = writeln!(console, "{}", line);
+    }
+
+    match &result {
+        Ok(_) => debug!(logger, "{}", line),
+        Err(_) => error!(logger, "{}", line),
+    }
+
+    result
+}
+
 // init_agent_as_init will do the initializations such as setting up the rootfs
 // when this agent has been run as the init process.
 fn init_agent_as_init(logger: &Logger, unified_cgroup_hierarchy: bool) -> Result<()> {
-    cgroups_mount(logger, unified_cgroup_hierarchy).map_err(|e| {
-        error!(
-            logger,
-            "fail cgroups mount, unified_cgroup_hierarchy {}: {}", unified_cgroup_hierarchy, e
-        );
-        e
-    })?;
+    boot_step(
+        logger,
+        "mount cgroup hierarchy",
+        cgroups_mount(logger, unified_cgroup_hierarchy),
+    )?;
+    boot_step(logger, "set up /dev/ptmx", setup_ptmx())?;
+    boot_step(
+        logger,
+        "create session and controlling terminal",
+        setup_session(),
+    )?;
+
+    env::set_var("PATH", "/bin:/sbin/:/usr/bin/:/usr/sbin/");
+
+    // A missing hostname does not prevent the sandbox from working.
+    if let Err(e) = boot_step(logger, "set guest hostname", set_guest_hostname()) {
+        warn!(logger, "failed to set hostname: {:?}", e);
+    }
 
-    fs::remove_file(Path::new("/dev/ptmx"))?;
-    unixfs::symlink(Path::new("/dev/pts/ptmx"), Path::new("/dev/ptmx"))?;
+    boot_step(logger, "bring up loopback interface", setup_loopback())?;
+
+    Ok(())
+}
+
+// Replace the static /dev/ptmx with a symlink into the devpts instance.
+// Safe to run repeatedly, so a restarted init does not trip over its own
+// earlier setup.
+fn setup_ptmx() -> Result<()> {
+    let ptmx = Path::new("/dev/ptmx");
+    let target = Path::new("/dev/pts/ptmx");
+
+    if let Ok(existing) = ptmx.read_link() {
+        if existing == target {
+            return Ok(());
+        }
+    }
+    if ptmx.symlink_metadata().is_ok() {
+        fs::remove_file(ptmx).context("remove /dev/ptmx")?;
+    }
+    unixfs::symlink(target, ptmx).context("symlink /dev/ptmx to /dev/pts/ptmx")
+}
 
-    unistd::setsid()?;
+// As PID 1 the agent may already be a session leader, in which case
+// setsid fails with EPERM; either way stdin is made the controlling
+// terminal so console interaction keeps working.
+fn setup_session() -> Result<()> {
+    match unistd::setsid() {
+        Ok(_) | Err(nix::errno::Errno::EPERM) => (),
+        Err(e) => return Err(e).context("setsid"),
+    }
 
     unsafe {
         libc::ioctl(std::io::stdin().as_raw_fd(), libc::TIOCSCTTY, 1);
     }
 
-    env::set_var("PATH", "/bin:/sbin/:/usr/bin/:/usr/sbin/");
+    Ok(())
+}
 
+fn set_guest_hostname() -> Result<()> {
     let contents =
-        std::fs::read_to_string("/etc/hostname").unwrap_or_else(|_| String::from("localhost"));
+        fs::read_to_string("/etc/hostname").unwrap_or_else(|_| String::from("localhost"));
     let contents_array: Vec<&str> = contents.split(' ').collect();
     let hostname = contents_array[0].trim();
 
-    if unistd::sethostname(OsStr::new(hostname)).is_err() {
-        warn!(logger, "failed to set hostname");
+    unistd::sethostname(OsStr::new(hostname)).context("sethostname")
+}
+
+// Bring up the loopback interface with a plain ioctl, so localhost works
+// deterministically even before the netlink machinery is available.
+fn setup_loopback() -> Result<()> {
+    let fd = socket::socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+    // Take ownership so the socket is closed again on return.
+    let sock = unsafe { File::from_raw_fd(fd) };
+
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(b"lo\0") {
+        *dst = *src as libc::c_char;
+    }
+
+    unsafe {
+        if libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFFLAGS, &mut ifr) < 0 {
+            return Err(anyhow!(
+                "SIOCGIFFLAGS on lo: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        ifr.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        if libc::ioctl(sock.as_raw_fd(), libc::SIOCSIFFLAGS, &ifr) < 0 {
+            return Err(anyhow!(
+                "SIOCSIFFLAGS on lo: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
     }
 
     Ok(())
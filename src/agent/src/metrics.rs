@@ -5,16 +5,40 @@
 
 extern crate procfs;
 
-use prometheus::{Encoder, Gauge, GaugeVec, IntCounter, Opts, Registry, TextEncoder};
+use prometheus::{
+    Encoder, Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts,
+    Registry, TextEncoder,
+};
 
+use crate::sandbox::Sandbox;
 use anyhow::{anyhow, Result};
 use slog::warn;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::instrument;
 
 const NAMESPACE_KATA_AGENT: &str = "kata_agent";
 const NAMESPACE_KATA_GUEST: &str = "kata_guest";
 
+// Annotations set by the CRI on the container spec, used to label the
+// per-container metrics the way cAdvisor does.
+const CRI_CONTAINER_NAME_ANNOTATIONS: &[&str] = &[
+    "io.kubernetes.cri.container-name",
+    "io.kubernetes.cri-o.ContainerName",
+];
+const CRI_SANDBOX_NAME_ANNOTATIONS: &[&str] = &[
+    "io.kubernetes.cri.sandbox-name",
+    "io.kubernetes.cri-o.SandboxName",
+];
+const CRI_SANDBOX_NAMESPACE_ANNOTATIONS: &[&str] = &[
+    "io.kubernetes.cri.sandbox-namespace",
+    "io.kubernetes.cri-o.Namespace",
+];
+
+// The labels cAdvisor attaches to container metrics.
+const CONTAINER_LABELS: &[&str] = &["container", "pod", "namespace"];
+
 // Convenience function to obtain the scope logger.
 fn sl() -> slog::Logger {
     slog_scope::logger().new(o!("subsystem" => "metrics"))
@@ -52,6 +76,37 @@ lazy_static! {
     static ref AGENT_PROC_STAT: GaugeVec =
     GaugeVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"proc_stat"), "Agent process statistics."), &["item"]).unwrap();
 
+    static ref AGENT_EXEC_MUX_SESSIONS: Gauge =
+    Gauge::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"exec_mux_sessions"), "Live multiplexed exec connections").unwrap();
+
+    static ref AGENT_EXEC_MUX_STREAMS: Gauge =
+    Gauge::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"exec_mux_streams"), "Live multiplexed exec streams").unwrap();
+
+    static ref AGENT_EXEC_MUX_BUFFERED_BYTES: Gauge =
+    Gauge::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"exec_mux_buffered_bytes"), "Inbound exec data buffered by the agent in bytes").unwrap();
+
+    // per-RPC metrics, labelled with the ttrpc method name
+    static ref AGENT_RPC_DURATIONS: HistogramVec =
+    HistogramVec::new(HistogramOpts::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"rpc_durations_seconds"), "RPC handler latency in seconds.")
+        .buckets(prometheus::exponential_buckets(0.001, 2.0, 16).unwrap()), &["method"]).unwrap();
+
+    static ref AGENT_RPC_REQUEST_SIZES: HistogramVec =
+    HistogramVec::new(HistogramOpts::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"rpc_request_bytes"), "RPC request payload size in bytes.")
+        .buckets(prometheus::exponential_buckets(64.0, 4.0, 12).unwrap()), &["method"]).unwrap();
+
+    static ref AGENT_LOG_DROPPED_BYTES: IntCounter =
+    IntCounter::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"log_dropped_bytes"), "Log bytes discarded because the host did not drain the log vsock in time.").unwrap();
+
+    static ref AGENT_RPC_ERRORS: IntCounterVec =
+    IntCounterVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_AGENT,"rpc_errors"), "RPCs that returned an error."), &["method"]).unwrap();
+
+    // ttrpc serves each request on its own task, so the task id maps an
+    // error back to the method whose handler raised it.
+    static ref CURRENT_RPC: Mutex<HashMap<tokio::task::Id, &'static str>> = Mutex::new(HashMap::new());
+
+    // Start of the most recently served RPC, for the liveness heartbeat.
+    static ref LAST_RPC: Mutex<Option<Instant>> = Mutex::new(None);
+
     // guest os metrics
     static ref GUEST_LOAD: GaugeVec =
     GaugeVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_GUEST,"load"), "Guest system load."), &["item"]).unwrap();
@@ -73,17 +128,129 @@ lazy_static! {
 
     static ref GUEST_MEMINFO: GaugeVec =
     GaugeVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_GUEST,"meminfo"), "Statistics about memory usage in the system."), &["item"]).unwrap();
+
+    // per-container cgroup metrics, named and labelled following the
+    // cAdvisor conventions so that existing dashboards keep working.
+    // cAdvisor exposes the cumulative ones as counters; gauges set to the
+    // cumulative value encode the same series.
+    static ref CONTAINER_CPU_USAGE: GaugeVec =
+    GaugeVec::new(Opts::new("container_cpu_usage_seconds_total", "Cumulative cpu time consumed in seconds."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_CPU_USER: GaugeVec =
+    GaugeVec::new(Opts::new("container_cpu_user_seconds_total", "Cumulative user cpu time consumed in seconds."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_CPU_SYSTEM: GaugeVec =
+    GaugeVec::new(Opts::new("container_cpu_system_seconds_total", "Cumulative system cpu time consumed in seconds."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_USAGE: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_usage_bytes", "Current memory usage in bytes, including all memory regardless of when it was accessed."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_MAX_USAGE: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_max_usage_bytes", "Maximum memory usage recorded in bytes."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_CACHE: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_cache", "Number of bytes of page cache memory."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_RSS: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_rss", "Size of RSS in bytes."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_SWAP: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_swap", "Container swap usage in bytes."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_MEMORY_FAILCNT: GaugeVec =
+    GaugeVec::new(Opts::new("container_memory_failcnt", "Number of memory usage hits limits."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_SPEC_MEMORY_LIMIT: GaugeVec =
+    GaugeVec::new(Opts::new("container_spec_memory_limit_bytes", "Memory limit for the container."), CONTAINER_LABELS).unwrap();
+
+    static ref CONTAINER_PROCESSES: GaugeVec =
+    GaugeVec::new(Opts::new("container_processes", "Number of processes running inside the container."), CONTAINER_LABELS).unwrap();
 }
 
-#[instrument]
-pub fn get_metrics(_: &protocols::agent::GetMetricsRequest) -> Result<String> {
-    let mut registered = REGISTERED
+/// Measures one RPC, created by `trace_rpc_call!` at the top of every
+/// handler: the request payload size is observed on entry and the handler
+/// latency when the guard drops at the end of the handler.
+pub(crate) struct RpcCall {
+    method: &'static str,
+    start: Instant,
+}
+
+impl RpcCall {
+    pub(crate) fn start(method: &'static str, request_bytes: u64) -> Self {
+        AGENT_RPC_REQUEST_SIZES
+            .with_label_values(&[method])
+            .observe(request_bytes as f64);
+        if let Ok(mut current) = CURRENT_RPC.lock() {
+            if let Some(id) = tokio::task::try_id() {
+                current.insert(id, method);
+            }
+        }
+        if let Ok(mut last) = LAST_RPC.lock() {
+            *last = Some(Instant::now());
+        }
+        RpcCall {
+            method,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for RpcCall {
+    fn drop(&mut self) {
+        AGENT_RPC_DURATIONS
+            .with_label_values(&[self.method])
+            .observe(self.start.elapsed().as_secs_f64());
+        if let Ok(mut current) = CURRENT_RPC.lock() {
+            if let Some(id) = tokio::task::try_id() {
+                current.remove(&id);
+            }
+        }
+    }
+}
+
+/// Account log bytes the bounded forwarding ring had to discard.
+pub(crate) fn add_log_dropped_bytes(bytes: u64) {
+    AGENT_LOG_DROPPED_BYTES.inc_by(bytes);
+}
+
+/// Seconds since the last RPC started, or `None` when no RPC has been
+/// served yet.
+pub(crate) fn seconds_since_last_rpc() -> Option<u64> {
+    LAST_RPC
         .lock()
-        .map_err(|e| anyhow!("failed to check agent metrics register status {:?}", e))?;
+        .ok()
+        .and_then(|last| last.map(|start| start.elapsed().as_secs()))
+}
 
-    if !(*registered) {
-        register_metrics()?;
-        *registered = true;
+/// Count a failed RPC against the method being served on the current
+/// task. Errors raised outside an instrumented handler (e.g. by the
+/// attestation proxy) are not counted here.
+pub(crate) fn record_rpc_error() {
+    let method = tokio::task::try_id().and_then(|id| {
+        CURRENT_RPC
+            .lock()
+            .ok()
+            .and_then(|current| current.get(&id).copied())
+    });
+    if let Some(method) = method {
+        AGENT_RPC_ERRORS.with_label_values(&[method]).inc();
+    }
+}
+
+#[instrument]
+pub async fn get_metrics(
+    _: &protocols::agent::GetMetricsRequest,
+    sandbox: &Arc<tokio::sync::Mutex<Sandbox>>,
+) -> Result<String> {
+    {
+        let mut registered = REGISTERED
+            .lock()
+            .map_err(|e| anyhow!("failed to check agent metrics register status {:?}", e))?;
+
+        if !(*registered) {
+            register_metrics()?;
+            *registered = true;
+        }
     }
 
     AGENT_SCRAPE_COUNT.inc();
@@ -94,6 +261,9 @@ pub fn get_metrics(_: &protocols::agent::GetMetricsRequest) -> Result<String> {
     // update guest os metrics
     update_guest_metrics();
 
+    // update per-container cgroup metrics
+    update_container_metrics(&*sandbox.lock().await);
+
     // gather all metrics and return as a String
     let metric_families = REGISTRY.gather();
 
@@ -116,6 +286,13 @@ fn register_metrics() -> Result<()> {
     REGISTRY.register(Box::new(AGENT_PROC_STATUS.clone()))?;
     REGISTRY.register(Box::new(AGENT_IO_STAT.clone()))?;
     REGISTRY.register(Box::new(AGENT_PROC_STAT.clone()))?;
+    REGISTRY.register(Box::new(AGENT_EXEC_MUX_SESSIONS.clone()))?;
+    REGISTRY.register(Box::new(AGENT_EXEC_MUX_STREAMS.clone()))?;
+    REGISTRY.register(Box::new(AGENT_EXEC_MUX_BUFFERED_BYTES.clone()))?;
+    REGISTRY.register(Box::new(AGENT_RPC_DURATIONS.clone()))?;
+    REGISTRY.register(Box::new(AGENT_RPC_REQUEST_SIZES.clone()))?;
+    REGISTRY.register(Box::new(AGENT_RPC_ERRORS.clone()))?;
+    REGISTRY.register(Box::new(AGENT_LOG_DROPPED_BYTES.clone()))?;
 
     // guest metrics
     REGISTRY.register(Box::new(GUEST_LOAD.clone()))?;
@@ -126,6 +303,19 @@ fn register_metrics() -> Result<()> {
     REGISTRY.register(Box::new(GUEST_DISKSTAT.clone()))?;
     REGISTRY.register(Box::new(GUEST_MEMINFO.clone()))?;
 
+    // container metrics
+    REGISTRY.register(Box::new(CONTAINER_CPU_USAGE.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_CPU_USER.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_CPU_SYSTEM.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_USAGE.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_MAX_USAGE.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_CACHE.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_RSS.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_SWAP.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_MEMORY_FAILCNT.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_SPEC_MEMORY_LIMIT.clone()))?;
+    REGISTRY.register(Box::new(CONTAINER_PROCESSES.clone()))?;
+
     Ok(())
 }
 
@@ -182,6 +372,11 @@ fn update_agent_metrics() -> Result<()> {
         Ok(status) => set_gauge_vec_proc_status(&AGENT_PROC_STATUS, &status),
     }
 
+    // exec session multiplexer accounting
+    AGENT_EXEC_MUX_SESSIONS.set(crate::exec_mux::live_sessions() as f64);
+    AGENT_EXEC_MUX_STREAMS.set(crate::exec_mux::live_streams() as f64);
+    AGENT_EXEC_MUX_BUFFERED_BYTES.set(crate::exec_mux::buffered_bytes() as f64);
+
     Ok(())
 }
 
@@ -268,6 +463,108 @@ fn update_guest_metrics() {
     }
 }
 
+// update_container_metrics exports the cgroup stats of every running
+// container under the cAdvisor metric names, labelled with the container
+// name, pod name and namespace taken from the CRI annotations. The label
+// vectors are reset first so that series of removed containers disappear.
+#[instrument]
+fn update_container_metrics(sandbox: &Sandbox) {
+    CONTAINER_CPU_USAGE.reset();
+    CONTAINER_CPU_USER.reset();
+    CONTAINER_CPU_SYSTEM.reset();
+    CONTAINER_MEMORY_USAGE.reset();
+    CONTAINER_MEMORY_MAX_USAGE.reset();
+    CONTAINER_MEMORY_CACHE.reset();
+    CONTAINER_MEMORY_RSS.reset();
+    CONTAINER_MEMORY_SWAP.reset();
+    CONTAINER_MEMORY_FAILCNT.reset();
+    CONTAINER_SPEC_MEMORY_LIMIT.reset();
+    CONTAINER_PROCESSES.reset();
+
+    for ctr in sandbox.containers.values() {
+        let stats = match ctr.cgroup_manager.get_stats() {
+            Err(err) => {
+                info!(
+                    sl(),
+                    "failed to get cgroup stats for container {}: {:?}", ctr.id, err
+                );
+                continue;
+            }
+            Ok(stats) => stats,
+        };
+
+        let annotations = ctr
+            .config
+            .spec
+            .as_ref()
+            .and_then(|s| s.annotations().as_ref());
+        let container =
+            annotation_value(annotations, CRI_CONTAINER_NAME_ANNOTATIONS).unwrap_or(&ctr.id);
+        let pod = annotation_value(annotations, CRI_SANDBOX_NAME_ANNOTATIONS).unwrap_or("");
+        let namespace =
+            annotation_value(annotations, CRI_SANDBOX_NAMESPACE_ANNOTATIONS).unwrap_or("");
+        let labels = &[container, pod, namespace];
+
+        // cpu times are exported in seconds, following cAdvisor
+        let cpu = &stats.cpu_stats.cpu_usage;
+        CONTAINER_CPU_USAGE
+            .with_label_values(labels)
+            .set(cpu.total_usage as f64 / 1e9);
+        CONTAINER_CPU_USER
+            .with_label_values(labels)
+            .set(cpu.usage_in_usermode as f64 / 1e9);
+        CONTAINER_CPU_SYSTEM
+            .with_label_values(labels)
+            .set(cpu.usage_in_kernelmode as f64 / 1e9);
+
+        let memory = &stats.memory_stats;
+        CONTAINER_MEMORY_USAGE
+            .with_label_values(labels)
+            .set(memory.usage.usage as f64);
+        CONTAINER_MEMORY_MAX_USAGE
+            .with_label_values(labels)
+            .set(memory.usage.max_usage as f64);
+        CONTAINER_MEMORY_CACHE
+            .with_label_values(labels)
+            .set(memory.cache as f64);
+        // "rss" on cgroup v1, "anon" on the unified hierarchy
+        let rss = memory
+            .stats
+            .get("rss")
+            .or_else(|| memory.stats.get("anon"))
+            .copied()
+            .unwrap_or(0);
+        CONTAINER_MEMORY_RSS
+            .with_label_values(labels)
+            .set(rss as f64);
+        CONTAINER_MEMORY_SWAP
+            .with_label_values(labels)
+            .set(memory.swap_usage.usage as f64);
+        CONTAINER_MEMORY_FAILCNT
+            .with_label_values(labels)
+            .set(memory.usage.failcnt as f64);
+        CONTAINER_SPEC_MEMORY_LIMIT
+            .with_label_values(labels)
+            .set(memory.usage.limit as f64);
+
+        CONTAINER_PROCESSES
+            .with_label_values(labels)
+            .set(stats.pids_stats.current as f64);
+    }
+}
+
+// annotation_value returns the first of the given annotation keys that is
+// present on the container spec.
+fn annotation_value<'a>(
+    annotations: Option<&'a std::collections::HashMap<String, String>>,
+    keys: &[&str],
+) -> Option<&'a str> {
+    let annotations = annotations?;
+    keys.iter()
+        .find_map(|k| annotations.get(*k))
+        .map(|v| v.as_str())
+}
+
 #[instrument]
 fn set_gauge_vec_meminfo(gv: &prometheus::GaugeVec, meminfo: &procfs::Meminfo) {
     gv.with_label_values(&["mem_total"])
@@ -4,15 +4,24 @@
 //
 
 use std::collections::HashMap;
+use std::ffi::CString;
 use std::fmt::Debug;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
 use std::ops::Deref;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::path::Path;
 
 use anyhow::{anyhow, Context, Result};
 use kata_sys_util::mount::{get_linux_mount_info, parse_mount_options};
+use nix::fcntl::OFlag;
 use nix::mount::MsFlags;
+use nix::sched::CloneFlags;
+use nix::sys::signal::{self, Signal};
+use nix::sys::wait;
+use nix::unistd::{self, ForkResult};
+use oci_spec::runtime::LinuxIdMapping;
 use regex::Regex;
 use slog::Logger;
 use tracing::instrument;
@@ -158,9 +167,35 @@ pub fn general_mount(logger: &Logger) -> Result<()> {
         mount_to_rootfs(&logger, m)?;
     }
 
+    setup_hugetlbfs_mount(&logger)?;
+
     Ok(())
 }
 
+// Mount hugetlbfs under /dev/hugepages when the guest kernel supports
+// hugepages, so containers can request hugetlbfs backed mounts.
+#[instrument]
+fn setup_hugetlbfs_mount(logger: &Logger) -> Result<()> {
+    if !Path::new(SYSFS_HUGEPAGES_PREFIX).exists() {
+        info!(logger, "guest kernel does not support hugepages");
+        return Ok(());
+    }
+
+    let m = InitMount {
+        fstype: "hugetlbfs",
+        src: "hugetlbfs",
+        dest: DEV_HUGEPAGES_PATH,
+        options: vec!["nosuid", "nodev"],
+    };
+
+    mount_to_rootfs(logger, &m).or_else(|e| {
+        // Not being able to mount hugetlbfs only matters once a workload
+        // asks for hugepages, so don't fail the whole rootfs setup here.
+        warn!(logger, "could not mount hugetlbfs: {}", e);
+        Ok(())
+    })
+}
+
 #[inline]
 pub fn get_mount_fs_type(mount_point: &str) -> Result<String> {
     get_mount_fs_type_from_file(PROC_MOUNTSTATS, mount_point)
@@ -316,6 +351,170 @@ pub fn remove_mounts<P: AsRef<str> + std::fmt::Debug>(mounts: &[P]) -> Result<()
     Ok(())
 }
 
+// open_tree(2) flags.
+const OPEN_TREE_CLONE: u64 = 0x1;
+// mount_setattr(2) and open_tree(2) flag.
+const AT_RECURSIVE: u64 = 0x8000;
+// mount_setattr(2) attribute: attach the mount to a user namespace.
+const MOUNT_ATTR_IDMAP: u64 = 0x0010_0000;
+// move_mount(2) flag.
+const MOVE_MOUNT_F_EMPTY_PATH: u64 = 0x4;
+
+// Argument structure of mount_setattr(2).
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+// Create a user namespace holding the given uid/gid mappings and return a
+// file descriptor referring to it, suitable for MOUNT_ATTR_IDMAP. The
+// namespace is kept alive by a forked child that is reaped before
+// returning; the returned fd keeps the namespace itself alive.
+fn userns_fd_from_mappings(
+    uid_mappings: &[LinuxIdMapping],
+    gid_mappings: &[LinuxIdMapping],
+) -> Result<File> {
+    let (pipe_r, pipe_w) = unistd::pipe2(OFlag::O_CLOEXEC)?;
+
+    // Safety: the child only performs async-signal-safe operations
+    // (unshare, write, pause) before being killed by the parent.
+    let child = match unsafe { unistd::fork() }? {
+        ForkResult::Child => {
+            let _ = unistd::close(pipe_r);
+            if nix::sched::unshare(CloneFlags::CLONE_NEWUSER).is_ok() {
+                let _ = unistd::write(pipe_w, &[0u8]);
+            }
+            loop {
+                unistd::pause();
+            }
+        }
+        ForkResult::Parent { child } => child,
+    };
+
+    let _ = unistd::close(pipe_w);
+    defer!({
+        let _ = signal::kill(child, Signal::SIGKILL);
+        let _ = wait::waitpid(child, None);
+    });
+
+    let mut buf = [0u8; 1];
+    let ret = unistd::read(pipe_r, &mut buf);
+    let _ = unistd::close(pipe_r);
+    if ret != Ok(1) {
+        return Err(anyhow!("child process failed to unshare user namespace"));
+    }
+
+    for (path, mappings) in [
+        (format!("/proc/{}/uid_map", child), uid_mappings),
+        (format!("/proc/{}/gid_map", child), gid_mappings),
+    ] {
+        let data = mappings
+            .iter()
+            .filter(|m| m.size() != 0)
+            .map(|m| format!("{} {} {}\n", m.container_id(), m.host_id(), m.size()))
+            .collect::<String>();
+        fs::write(&path, data).context(format!("write mappings to {}", path))?;
+    }
+
+    // Rust opens files with O_CLOEXEC, as required for an fd that ends up
+    // attached to a mount.
+    File::open(format!("/proc/{}/ns/user", child)).context("open user namespace fd")
+}
+
+/// Bind mount `source` onto `destination` with the given uid/gid mappings
+/// applied, so that files owned by mapped host ids show up with the
+/// expected ownership inside a user-namespaced container. The mapping is
+/// attached to a detached copy of the source mount, leaving the original
+/// mount untouched.
+#[instrument]
+pub fn idmapped_bind_mount(
+    logger: &Logger,
+    source: &Path,
+    destination: &Path,
+    uid_mappings: &[LinuxIdMapping],
+    gid_mappings: &[LinuxIdMapping],
+) -> Result<()> {
+    let logger = logger.new(o!("subsystem" => "idmapped-mount"));
+
+    let userns = userns_fd_from_mappings(uid_mappings, gid_mappings)
+        .context("create user namespace for idmapped mount")?;
+
+    let src = CString::new(source.as_os_str().as_bytes())?;
+    let tree_fd = unsafe {
+        libc::syscall(
+            libc::SYS_open_tree,
+            libc::AT_FDCWD,
+            src.as_ptr(),
+            OPEN_TREE_CLONE | AT_RECURSIVE | libc::O_CLOEXEC as u64,
+        )
+    } as RawFd;
+    if tree_fd < 0 {
+        return Err(anyhow!(
+            "failed to clone mount tree of {}: {}",
+            source.display(),
+            io::Error::last_os_error()
+        ));
+    }
+    // Safety: tree_fd was checked to be a valid fd just above.
+    let _tree = unsafe { File::from_raw_fd(tree_fd) };
+
+    let attr = MountAttr {
+        attr_set: MOUNT_ATTR_IDMAP,
+        attr_clr: 0,
+        propagation: 0,
+        userns_fd: userns.as_raw_fd() as u64,
+    };
+    let empty = CString::new("")?;
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            tree_fd,
+            empty.as_ptr(),
+            libc::AT_EMPTY_PATH as u64 | AT_RECURSIVE,
+            &attr as *const MountAttr,
+            std::mem::size_of::<MountAttr>(),
+        )
+    };
+    if ret < 0 {
+        return Err(anyhow!(
+            "failed to idmap mount of {}: {}",
+            source.display(),
+            io::Error::last_os_error()
+        ));
+    }
+
+    let dst = CString::new(destination.as_os_str().as_bytes())?;
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_move_mount,
+            tree_fd,
+            empty.as_ptr(),
+            libc::AT_FDCWD,
+            dst.as_ptr(),
+            MOVE_MOUNT_F_EMPTY_PATH,
+        )
+    };
+    if ret < 0 {
+        return Err(anyhow!(
+            "failed to attach idmapped mount to {}: {}",
+            destination.display(),
+            io::Error::last_os_error()
+        ));
+    }
+
+    info!(
+        logger,
+        "attached idmapped mount of {} at {}",
+        source.display(),
+        destination.display()
+    );
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
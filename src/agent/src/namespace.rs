@@ -20,6 +20,7 @@ const PERSISTENT_NS_DIR: &str = "/var/run/sandbox-ns";
 pub const NSTYPEIPC: &str = "ipc";
 pub const NSTYPEUTS: &str = "uts";
 pub const NSTYPEPID: &str = "pid";
+pub const NSTYPEUSER: &str = "user";
 
 #[instrument]
 pub fn get_current_thread_ns_path(ns_type: &str) -> String {
@@ -166,6 +166,26 @@ impl Handle {
         Ok(())
     }
 
+    /// Deletes a link by name. The kernel drops the addresses and routes
+    /// that reference it, so hot-unplugging a secondary interface does
+    /// not require cleaning up the routing table separately.
+    pub async fn remove_interface(&mut self, name: &str) -> Result<()> {
+        let link = self.find_link(LinkFilter::Name(name)).await?;
+
+        if link.is_up() {
+            self.enable_link(link.index(), false).await?;
+        }
+
+        self.handle
+            .link()
+            .del(link.index())
+            .execute()
+            .await
+            .map_err(|err| anyhow!("Failure in LinkDelRequest for interface {}: {}", name, err))?;
+
+        Ok(())
+    }
+
     pub async fn handle_localhost(&self) -> Result<()> {
         let link = self.find_link(LinkFilter::Name("lo")).await?;
         self.enable_link(link.index(), true).await?;
@@ -364,7 +384,7 @@ impl Handle {
     /// Adds a list of routes from iterable object `I`.
     /// It can accept both a collection of routes or a single item (via `iter::once()`).
     /// It'll also take care of proper order when adding routes (gateways first, everything else after).
-    async fn add_routes<I>(&mut self, list: I) -> Result<()>
+    pub async fn add_routes<I>(&mut self, list: I) -> Result<()>
     where
         I: IntoIterator<Item = Route>,
     {
@@ -600,10 +620,30 @@ impl Handle {
         use packet::{NetlinkMessage, NetlinkPayload, RtnlMessage};
         use rtnetlink::Error;
 
-        const IFA_F_PERMANENT: u16 = 0x80; // See https://github.com/little-dude/netlink/blob/0185b2952505e271805902bf175fee6ea86c42b8/netlink-packet-route/src/rtnl/constants.rs#L770
+        // Neighbor cache constants, see
+        // https://github.com/little-dude/netlink/blob/0185b2952505e271805902bf175fee6ea86c42b8/netlink-packet-route/src/rtnl/constants.rs
+        const NUD_PERMANENT: u16 = 0x80;
+        const NTF_PROXY: u8 = 0x08;
+
+        let proxy = neigh.flags as u8 & NTF_PROXY != 0;
+        if proxy && !neigh.lladdr.is_empty() {
+            return Err(anyhow!(
+                "Proxy neighbor entry for {} must not carry a link-layer address",
+                ip_address
+            ));
+        }
 
         let link = self.find_link(LinkFilter::Name(&neigh.device)).await?;
 
+        // Explicit IPv6 proxy entries are only answered once proxy_ndp is
+        // enabled on the interface; IPv4 proxy ARP entries work without a
+        // sysctl counterpart.
+        if proxy && ip.is_ipv6() {
+            let sysctl = format!("/proc/sys/net/ipv6/conf/{}/proxy_ndp", neigh.device);
+            std::fs::write(&sysctl, "1")
+                .map_err(|e| anyhow!("Failed to enable proxy_ndp via {}: {:?}", sysctl, e))?;
+        }
+
         let message = NeighbourMessage {
             header: NeighbourHeader {
                 family: match ip {
@@ -614,7 +654,7 @@ impl Handle {
                 state: if neigh.state != 0 {
                     neigh.state as u16
                 } else {
-                    IFA_F_PERMANENT
+                    NUD_PERMANENT
                 },
                 flags: neigh.flags as u8,
                 ntype: NDA_UNSPEC as u8,
@@ -1084,4 +1124,59 @@ mod tests {
 
         clean_env_for_test_add_one_arp_neighbor(dummy_name, to_ip);
     }
+
+    #[tokio::test]
+    async fn test_add_proxy_arp_neighbor() {
+        skip_if_not_root!();
+
+        let to_ip = "169.254.1.2";
+        let dummy_name = "dummy_for_proxy";
+
+        prepare_env_for_test_add_one_arp_neighbor(dummy_name, to_ip);
+
+        let mut ip_address = IPAddress::new();
+        ip_address.set_address(to_ip.to_string());
+
+        let mut neigh = ARPNeighbor::new();
+        neigh.set_toIPAddress(ip_address);
+        neigh.set_device(dummy_name.to_string());
+        neigh.set_flags(0x08); // NTF_PROXY
+
+        Handle::new()
+            .unwrap()
+            .add_arp_neighbor(&neigh)
+            .await
+            .expect("Failed to add proxy ARP neighbor");
+
+        let stdout = Command::new("ip")
+            .args(["neigh", "show", "proxy", "dev", dummy_name])
+            .output()
+            .expect("failed to show proxy neigh")
+            .stdout;
+
+        let stdout = std::str::from_utf8(&stdout).expect("failed to convert stdout");
+        assert!(stdout.contains(to_ip), "missing proxy entry: {}", stdout);
+
+        clean_env_for_test_add_one_arp_neighbor(dummy_name, to_ip);
+    }
+
+    #[tokio::test]
+    async fn test_add_proxy_arp_neighbor_with_lladdr() {
+        let mut ip_address = IPAddress::new();
+        ip_address.set_address("169.254.1.3".to_string());
+
+        let mut neigh = ARPNeighbor::new();
+        neigh.set_toIPAddress(ip_address);
+        neigh.set_device("lo".to_string());
+        neigh.set_lladdr("6a:92:3a:59:70:aa".to_string());
+        neigh.set_flags(0x08); // NTF_PROXY
+
+        // Proxy entries answer on behalf of the address, a link-layer
+        // address makes no sense and the kernel would reject it.
+        Handle::new()
+            .unwrap()
+            .add_arp_neighbor(&neigh)
+            .await
+            .expect_err("proxy neighbor with lladdr must be rejected");
+    }
 }
@@ -27,6 +27,10 @@ impl Network {
     pub fn set_dns(&mut self, dns: String) {
         self.dns.push(dns);
     }
+
+    pub fn update_dns(&mut self, dns: Vec<String>) {
+        self.dns = dns;
+    }
 }
 
 pub fn setup_guest_dns(logger: Logger, dns_list: &[String]) -> Result<()> {
@@ -38,6 +42,38 @@ pub fn setup_guest_dns(logger: Logger, dns_list: &[String]) -> Result<()> {
     )
 }
 
+pub fn update_guest_dns(logger: Logger, dns_list: &[String]) -> Result<()> {
+    do_update_guest_dns(
+        logger,
+        dns_list,
+        KATA_GUEST_SANDBOX_DNS_FILE,
+        GUEST_DNS_FILE,
+    )
+}
+
+fn do_update_guest_dns(logger: Logger, dns_list: &[String], src: &str, dst: &str) -> Result<()> {
+    if !path::Path::new(src).exists() {
+        // DNS was never set up for this sandbox, so take the setup path
+        // to also establish the bind mount over /etc/resolv.conf.
+        return do_setup_guest_dns(logger, dns_list, src, dst);
+    }
+
+    let logger = logger.new(o!( "subsystem" => "network"));
+    info!(logger, "updating sandbox DNS"; "entries" => dns_list.len());
+
+    // The sandbox DNS file is bind mounted over the guest /etc/resolv.conf
+    // and over the containers' resolv.conf, so rewriting its contents is
+    // immediately visible to all of them.
+    let content = dns_list
+        .iter()
+        .map(|x| x.trim())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    fs::write(src, content)?;
+
+    Ok(())
+}
+
 fn do_setup_guest_dns(logger: Logger, dns_list: &[String], src: &str, dst: &str) -> Result<()> {
     let logger = logger.new(o!( "subsystem" => "network"));
 
@@ -141,4 +177,30 @@ mod tests {
         // umount /etc/resolv.conf
         let _ = mount::umount(dst_filename);
     }
+
+    #[test]
+    fn test_update_guest_dns() {
+        let drain = slog::Discard;
+        let logger = slog::Logger::root(drain, o!());
+
+        // create temp for /run/kata-containers/sandbox/resolv.conf
+        let src_dir = tempdir().expect("failed to create tmpdir");
+        let tmp = src_dir.path().join("resolv.conf");
+        let src_filename = tmp.to_str().expect("failed to get resolv file filename");
+
+        // the sandbox file already exists, so the update only rewrites it
+        // and no bind mount is attempted
+        fs::write(src_filename, "nameserver 1.2.3.4").expect("failed to write file contents");
+
+        let dns = vec![
+            "nameserver 5.6.7.8".to_string(),
+            "search example.com".to_string(),
+        ];
+
+        let result = do_update_guest_dns(logger, &dns, src_filename, "/nonexistent/resolv.conf");
+        assert!(result.is_ok(), "result should be ok, but {:?}", result);
+
+        let content = fs::read_to_string(src_filename).unwrap();
+        assert_eq!(dns.join("\n"), content);
+    }
 }
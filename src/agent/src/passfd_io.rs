@@ -38,7 +38,7 @@ pub(crate) async fn start_listen(port: u32) -> Result<()> {
     Ok(())
 }
 
-async fn take_stream(port: u32) -> Option<VsockStream> {
+pub(crate) async fn take_stream(port: u32) -> Option<VsockStream> {
     // There may be a race condition where the stream is accepted but
     // not yet inserted into the mapping. We will retry several times.
     // If it still fails, we just give up.
@@ -72,6 +72,21 @@ pub(crate) async fn take_io_streams(
     stderr_port: u32,
 ) -> ProcessIo {
     let stdin = take_io_stream!(stdin_port);
+
+    // Passing the same port for stdout and stderr selects framed output:
+    // both streams share the connection and every chunk is tagged with
+    // its origin, so identity and per-stream EOF survive the merge.
+    if stdout_port != 0 && stdout_port == stderr_port {
+        let stdout = take_io_stream!(stdout_port);
+        debug!(
+            sl(),
+            "take passfd io streams {} with framed output {}", stdin_port, stdout_port
+        );
+        let mut proc_io = ProcessIo::new(stdin, stdout, None);
+        proc_io.framed_output = true;
+        return proc_io;
+    }
+
     let stdout = take_io_stream!(stdout_port);
     let stderr = take_io_stream!(stderr_port);
     debug!(
@@ -3,8 +3,10 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use protobuf::MessageDyn;
+use sha2::{Digest, Sha256};
 use tokio::io::AsyncWriteExt;
 
 use crate::rpc::ttrpc_error;
@@ -25,6 +27,17 @@ async fn allow_request(policy: &mut AgentPolicy, ep: &str, request: &str) -> ttr
         Ok((allowed, prints)) => {
             if allowed {
                 Ok(())
+            } else if AGENT_CONFIG.policy_audit {
+                // Audit mode: record what the policy would have denied,
+                // with enough structure to fix the policy, but let the
+                // request through so it can be dry-run before enforcing.
+                warn!(sl!(), "policy audit: request would be denied";
+                    "endpoint" => ep,
+                    "rule" => format!("data.agent_policy.{ep}"),
+                    "denial" => prints.as_str(),
+                    "request" => request,
+                );
+                Ok(())
             } else {
                 Err(ttrpc_error(
                     ttrpc::Code::PERMISSION_DENIED,
@@ -40,19 +53,82 @@ async fn allow_request(policy: &mut AgentPolicy, ep: &str, request: &str) -> ttr
 }
 
 pub async fn is_allowed(req: &(impl MessageDyn + serde::Serialize)) -> ttrpc::Result<()> {
+    let ep = req.descriptor_dyn().name().to_string();
+    crate::rpc::is_endpoint_enabled(ep.strip_suffix("Request").unwrap_or(&ep))?;
+
     let request = serde_json::to_string(req).unwrap();
     let mut policy = AGENT_POLICY.lock().await;
-    allow_request(&mut policy, req.descriptor_dyn().name(), &request).await
+    allow_request(&mut policy, &ep, &request).await
 }
 
 pub async fn do_set_policy(req: &protocols::agent::SetPolicyRequest) -> ttrpc::Result<()> {
+    crate::rpc::is_endpoint_enabled("SetPolicy")?;
+
     let request = serde_json::to_string(req).unwrap();
     let mut policy = AGENT_POLICY.lock().await;
     allow_request(&mut policy, "SetPolicyRequest", &request).await?;
+
+    verify_policy_signature(&req.policy, &req.signature)
+        .map_err(|e| ttrpc_error(ttrpc::Code::PERMISSION_DENIED, e))?;
+
     policy
         .set_policy(&req.policy)
         .await
-        .map_err(|e| ttrpc_error(ttrpc::Code::INVALID_ARGUMENT, e))
+        .map_err(|e| ttrpc_error(ttrpc::Code::INVALID_ARGUMENT, e))?;
+
+    // Record the hash of the policy that is now in force, so the
+    // runtime measurement register and log tell a relying party which
+    // policy the sandbox switched to after launch.
+    let digest = format!("sha256:{:x}", Sha256::digest(req.policy.as_bytes()));
+    crate::attestation::extend_runtime_measurement("agent-policy", "set-policy", &digest)
+        .await
+        .map_err(|e| ttrpc_error(ttrpc::Code::INTERNAL, e))?;
+
+    Ok(())
+}
+
+// Check the SetPolicy signature against the signing keys whitelisted in
+// the measured initdata bundle. Without such a whitelist any policy is
+// accepted, as before; with one, an unsigned or mis-signed policy is
+// rejected, so a compromised shim cannot swap out a measured policy.
+fn verify_policy_signature(policy: &str, signature: &[u8]) -> Result<()> {
+    let keys_path = std::path::Path::new(crate::initdata::POLICY_SIGNING_KEYS_PATH);
+    if !keys_path.exists() {
+        return Ok(());
+    }
+
+    let signature = Signature::from_slice(signature)
+        .map_err(|e| anyhow!("policy signature is missing or malformed: {e}"))?;
+
+    let keys = std::fs::read_to_string(keys_path).context("read policy signing keys")?;
+    for line in keys.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let key_bytes: [u8; 32] = decode_hex(line)
+            .with_context(|| format!("hex decode policy signing key {line:?}"))?
+            .try_into()
+            .map_err(|_| anyhow!("policy signing key {line:?} is not 32 bytes"))?;
+        let key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| anyhow!("invalid policy signing key {line:?}: {e}"))?;
+
+        if key.verify(policy.as_bytes(), &signature).is_ok() {
+            return Ok(());
+        }
+    }
+
+    bail!("policy signature does not verify against any whitelisted signing key");
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        bail!("odd hex string length {}", hex.len());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(anyhow::Error::from))
+        .collect()
 }
 
 /// Singleton policy object.
@@ -122,6 +198,18 @@ impl AgentPolicy {
         if default_policy_file.is_empty() {
             default_policy_file = POLICY_DEFAULT_FILE.to_string();
         }
+        // A policy delivered through the measured initdata device
+        // overrides both the kernel parameter and the image default: it
+        // is the only source whose content is bound into the TEE
+        // evidence.
+        if std::path::Path::new(crate::initdata::POLICY_PATH).exists() {
+            default_policy_file = crate::initdata::POLICY_PATH.to_string();
+        } else if AGENT_CONFIG.offline_confidential {
+            // Offline confidential mode trusts only measured configuration,
+            // so a missing initdata policy fails the boot instead of
+            // silently falling back to the image default.
+            bail!("offline confidential mode requires the policy to be delivered through initdata");
+        }
         info!(sl!(), "default policy: {default_policy_file}");
 
         self.engine.add_policy_from_file(default_policy_file)?;
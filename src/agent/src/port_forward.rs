@@ -0,0 +1,80 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Proxy TCP connections into a container's network namespace.
+//!
+//! When the pod network lives entirely inside the guest, the shim cannot
+//! dial container ports directly for `kubectl port-forward`-style flows.
+//! Instead it pre-creates a passfd stream over vsock, then asks the agent
+//! to connect to the target port from within the container's network
+//! namespace and splice the two connections together.
+
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use anyhow::{anyhow, Context, Result};
+use nix::fcntl::{self, OFlag};
+use nix::sched::{setns, CloneFlags};
+use nix::sys::stat::Mode;
+use nix::unistd::Pid;
+use tokio::io::copy_bidirectional;
+use tokio_vsock::VsockStream;
+
+// Convenience function to obtain the scope logger.
+fn sl() -> slog::Logger {
+    slog_scope::logger().new(o!("subsystem" => "port_forward"))
+}
+
+/// Connect to `port` inside the network namespace of `netns_pid` and proxy
+/// all data between that connection and `stream` until either side closes.
+pub(crate) async fn start(netns_pid: Pid, port: u16, mut stream: VsockStream) -> Result<()> {
+    let mut tcp_stream = connect_in_netns(netns_pid, port).await?;
+
+    tokio::spawn(async move {
+        match copy_bidirectional(&mut stream, &mut tcp_stream).await {
+            Ok((tx, rx)) => {
+                debug!(sl(), "port forward to port {} done", port;
+                       "bytes_sent" => tx, "bytes_received" => rx);
+            }
+            Err(e) => {
+                warn!(sl(), "port forward to port {} failed: {:?}", port, e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+// Establish a TCP connection to 127.0.0.1:<port> from within the network
+// namespace of the given process. The connect is done on a dedicated
+// thread so that joining the namespace never affects a runtime worker
+// thread or any other task scheduled on it.
+async fn connect_in_netns(netns_pid: Pid, port: u16) -> Result<tokio::net::TcpStream> {
+    let netns_path = format!("/proc/{}/ns/net", netns_pid);
+    let netns = fcntl::open(
+        netns_path.as_str(),
+        OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .map_err(|e| anyhow!("failed to open {}: {}", netns_path, e))?;
+    // safe because the fd was opened by fcntl::open and is owned here.
+    let netns_file = unsafe { std::fs::File::from_raw_fd(netns) };
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let result = || -> Result<std::net::TcpStream> {
+            setns(netns_file.as_raw_fd(), CloneFlags::CLONE_NEWNET)
+                .map_err(|e| anyhow!("failed to join network namespace: {}", e))?;
+            let stream = std::net::TcpStream::connect(("127.0.0.1", port))
+                .with_context(|| format!("connect to port {}", port))?;
+            stream.set_nonblocking(true)?;
+            Ok(stream)
+        }();
+        // The receiver is only dropped if the RPC was cancelled.
+        let _ = tx.send(result);
+    });
+
+    let stream = rx.await.context("port forward connect thread died")??;
+    tokio::net::TcpStream::from_std(stream).context("convert std TCP stream")
+}
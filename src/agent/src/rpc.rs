@@ -6,12 +6,15 @@
 use async_trait::async_trait;
 use rustjail::{pipestream::PipeStream, process::StreamType};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf};
+use tokio::sync::broadcast;
 use tokio::sync::Mutex;
 
+use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::ffi::{CString, OsStr};
 use std::fmt::Debug;
 use std::io;
+use std::io::Read;
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::str::FromStr;
@@ -22,15 +25,16 @@ use ttrpc::{
     r#async::{Server as TtrpcServer, TtrpcContext},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use cgroups::freezer::FreezerState;
 use oci::{Hooks, LinuxNamespace, Spec};
 use oci_spec::runtime as oci;
 use protobuf::MessageField;
 use protocols::agent::{
-    AddSwapRequest, AgentDetails, CopyFileRequest, GetIPTablesRequest, GetIPTablesResponse,
-    GuestDetailsResponse, Interfaces, Metrics, OOMEvent, ReadStreamResponse, Routes,
-    SetIPTablesRequest, SetIPTablesResponse, StatsContainerResponse, VolumeStatsRequest,
+    AddSwapRequest, AgentDetails, CopyFileRequest, FilesystemUsage, GetGuestLogsResponse,
+    GetIPTablesRequest, GetIPTablesResponse, GetMemoryInfoResponse, GuestDetailsResponse,
+    Interfaces, Metrics, OOMEvent, OfflineCPUsResponse, OfflineMemoryResponse, ReadStreamResponse,
+    Routes, SetIPTablesRequest, SetIPTablesResponse, StatsContainerResponse, VolumeStatsRequest,
     WaitProcessResponse, WriteStreamResponse,
 };
 use protocols::csi::{
@@ -39,7 +43,7 @@ use protocols::csi::{
 use protocols::empty::Empty;
 use protocols::health::{
     health_check_response::ServingStatus as HealthCheckResponse_ServingStatus, HealthCheckResponse,
-    VersionCheckResponse,
+    HealthDetailResponse, SubsystemStatus, VersionCheckResponse,
 };
 use protocols::types::Interface;
 use protocols::{agent_ttrpc_async as agent_ttrpc, health_ttrpc_async as health_ttrpc};
@@ -48,6 +52,7 @@ use rustjail::container::{BaseContainer, Container, LinuxContainer, SYSTEMD_CGRO
 use rustjail::mount::parse_mount_table;
 use rustjail::process::Process;
 use rustjail::specconv::CreateOpts;
+use safe_path::scoped_join;
 
 use nix::errno::Errno;
 use nix::mount::MsFlags;
@@ -55,20 +60,28 @@ use nix::sys::{stat, statfs};
 use nix::unistd::{self, Pid};
 use rustjail::process::ProcessOperations;
 
+use crate::balloon;
+#[cfg(target_arch = "s390x")]
+use crate::ccw;
 use crate::cdh;
+use crate::config;
 use crate::device::block_device_handler::get_virtio_blk_pci_device_name;
+#[cfg(target_arch = "s390x")]
+use crate::device::network_device_handler::wait_for_ccw_net_interface;
 use crate::device::network_device_handler::wait_for_net_interface;
 use crate::device::{add_devices, handle_cdi_devices, update_env_pci};
 use crate::features::get_build_features;
 use crate::image::KATA_IMAGE_WORK_DIR;
 use crate::linux_abi::*;
 use crate::metrics::get_metrics;
-use crate::mount::baremount;
-use crate::namespace::{NSTYPEIPC, NSTYPEPID, NSTYPEUTS};
-use crate::network::setup_guest_dns;
+use crate::mount::{baremount, idmapped_bind_mount, remove_mounts};
+use crate::namespace::{NSTYPEIPC, NSTYPEPID, NSTYPEUSER, NSTYPEUTS};
+use crate::network::{setup_guest_dns, update_guest_dns};
 use crate::passfd_io;
 use crate::pci;
+use crate::port_forward;
 use crate::random;
+use crate::rpc_limits;
 use crate::sandbox::Sandbox;
 use crate::storage::{add_storages, update_ephemeral_mounts, STORAGE_HANDLERS};
 use crate::util;
@@ -100,13 +113,15 @@ use nix::unistd::{Gid, Uid};
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 
+use kata_types::annotations::Annotation;
 use kata_types::k8s;
 
 pub const CONTAINER_BASE: &str = "/run/kata-containers";
 const MODPROBE_PATH: &str = "/sbin/modprobe";
-const TRUSTED_IMAGE_STORAGE_DEVICE: &str = "/dev/trusted_store";
+pub(crate) const TRUSTED_IMAGE_STORAGE_DEVICE: &str = "/dev/trusted_store";
 /// the iptables seriers binaries could appear either in /sbin
 /// or /usr/sbin, we need to check both of them
 const USR_IPTABLES_SAVE: &str = "/usr/sbin/iptables-save";
@@ -118,6 +133,17 @@ const IP6TABLES_SAVE: &str = "/sbin/ip6tables-save";
 const USR_IP6TABLES_RESTORE: &str = "/usr/sbin/ip6tables-save";
 const IP6TABLES_RESTORE: &str = "/sbin/ip6tables-restore";
 const KATA_GUEST_SHARE_DIR: &str = "/run/kata-containers/shared/containers/";
+// Base directory for the dedicated /dev/shm tmpfs of containers that request
+// a custom shm size through the shm size annotation.
+const KATA_GUEST_SHM_DIR: &str = "/run/kata-containers/shm";
+
+// Directory holding the idmapped views of host-shared volumes created for
+// userns-enabled containers.
+const KATA_GUEST_IDMAP_DIR: &str = "/run/kata-containers/idmapped";
+
+// Payload compression algorithms the agent accepts, advertised to clients
+// through AgentDetails so they can negotiate per request.
+const SUPPORTED_PAYLOAD_COMPRESSION: &[&str] = &["zstd"];
 
 const ERR_CANNOT_GET_WRITER: &str = "Cannot get writer";
 const ERR_INVALID_BLOCK_SIZE: &str = "Invalid block size";
@@ -132,6 +158,10 @@ const IPTABLES_RESTORE_WAIT_SEC: u64 = 5;
 
 const CDI_TIMEOUT_LIMIT: u64 = 100;
 
+// Default per-source cap for GetGuestLogs responses, keeping the reply
+// comfortably below the ttRPC message size limit.
+const DEFAULT_GUEST_LOG_SIZE: usize = 1024 * 1024;
+
 // Convenience function to obtain the scope logger.
 fn sl() -> slog::Logger {
     slog_scope::logger()
@@ -139,14 +169,42 @@ fn sl() -> slog::Logger {
 
 // Convenience function to wrap an error and response to ttrpc client
 pub fn ttrpc_error(code: ttrpc::Code, err: impl Debug) -> ttrpc::Error {
+    crate::metrics::record_rpc_error();
     get_rpc_status(code, format!("{:?}", err))
 }
 
-#[cfg(not(feature = "agent-policy"))]
-async fn is_allowed(_req: &impl serde::Serialize) -> ttrpc::Result<()> {
+/// Endpoint name for a request type, e.g. "ExecProcess" for
+/// `protocols::agent::ExecProcessRequest`.
+fn endpoint_name<T>(_req: &T) -> &'static str {
+    let type_name = std::any::type_name::<T>();
+    let short = type_name.rsplit("::").next().unwrap_or(type_name);
+    short.strip_suffix("Request").unwrap_or(short)
+}
+
+/// Check an endpoint against the configured allow and deny lists. These
+/// are enforced independently of the policy engine so hardened guests can
+/// disable endpoints such as ExecProcess even without a policy document.
+pub fn is_endpoint_enabled(ep: &str) -> ttrpc::Result<()> {
+    let config = &AGENT_CONFIG;
+    let denied = config.denied_endpoints.iter().any(|e| e == ep);
+    let allowed =
+        config.allowed_endpoints.is_empty() || config.allowed_endpoints.iter().any(|e| e == ep);
+
+    if denied || !allowed {
+        return Err(ttrpc_error(
+            ttrpc::Code::PERMISSION_DENIED,
+            format!("{} endpoint is disabled by agent configuration", ep),
+        ));
+    }
+
     Ok(())
 }
 
+#[cfg(not(feature = "agent-policy"))]
+async fn is_allowed(req: &impl serde::Serialize) -> ttrpc::Result<()> {
+    is_endpoint_enabled(endpoint_name(req))
+}
+
 fn same<E>(e: E) -> E {
     e
 }
@@ -202,6 +260,10 @@ impl AgentService {
 
         kata_sys_util::validate::verify_id(&cid)?;
 
+        // Refuse the request up front if the sandbox is already at its
+        // configured container ceiling, before any storage or device setup.
+        self.sandbox.lock().await.ensure_container_capacity()?;
+
         let use_sandbox_pidns = req.sandbox_pidns();
 
         let mut oci = match req.OCI.into_option() {
@@ -238,6 +300,18 @@ impl AgentService {
 
         cdh_handler(&mut oci).await?;
 
+        if let Some(p) = oci.process_mut() {
+            apply_max_open_files(p)?;
+        }
+
+        // Fail early if the container requests more hugepages than the
+        // guest was booted with.
+        check_hugepage_limits(&oci)?;
+
+        // Reject sysctls outside the configured allowlist before any
+        // storage or container state is set up.
+        check_sysctls(&oci)?;
+
         // Both rootfs and volumes (invoked with --volume for instance) will
         // be processed the same way. The idea is to always mount any provided
         // storage to the specified MountPoint, so that it will match what's
@@ -258,6 +332,32 @@ impl AgentService {
 
         update_container_namespaces(&s, &mut oci, use_sandbox_pidns)?;
 
+        // Give the container a dedicated, fixed-size /dev/shm when the shm
+        // size annotation is present, instead of the tmpfs default of half
+        // of the guest memory.
+        let annotation = Annotation::new(oci.annotations().clone().unwrap_or_default());
+        if let Some(shm_size) = annotation
+            .get_container_resource_shm_size()
+            .map_err(|e| anyhow!("invalid shm size annotation: {:?}", e))?
+        {
+            setup_container_shm(&cid, shm_size, &mut oci)?;
+            s.shm_sizes.insert(cid.clone(), shm_size);
+        }
+
+        // Replace host-shared volume mounts of userns-enabled containers
+        // with idmapped views so file ownership follows the container's
+        // id mappings instead of showing up as the overflow ids.
+        setup_idmapped_volumes(&mut s, &cid, &mut oci)?;
+
+        // Apply the sandbox-wide hardening lists before the spec is
+        // written to the bundle, so every container masks the configured
+        // proc/sys interfaces regardless of its image defaults.
+        rustjail::specconv::append_protected_paths(
+            &mut oci,
+            &AGENT_CONFIG.masked_paths,
+            &AGENT_CONFIG.readonly_paths,
+        );
+
         // Append guest hooks
         append_guest_hooks(&s, &mut oci)?;
 
@@ -326,12 +426,27 @@ impl AgentService {
             if let Err(e) = ctr.destroy().await {
                 error!(sl(), "failed to destroy container: {:?}", e);
             }
+            s.fuse_daemon_pids.remove(&cid);
             if let Err(e) = remove_container_resources(&mut s, &cid).await {
                 error!(sl(), "failed to remove container resources: {:?}", e);
             }
             return Err(err);
         }
 
+        // Move the fuse daemons spawned for this container's storages
+        // into the container cgroup, so their cpu and memory use is
+        // accounted to the workload instead of the agent.
+        if let Some(pids) = s.fuse_daemon_pids.remove(&cid) {
+            for pid in pids {
+                if let Err(e) = ctr.cgroup_manager.as_ref().apply(pid) {
+                    warn!(
+                        sl(),
+                        "failed to move fuse daemon {} into container cgroup: {:?}", pid, e
+                    );
+                }
+            }
+        }
+
         s.update_shared_pidns(&ctr)?;
         s.setup_shared_mounts(&ctr, &req.shared_mounts)?;
         s.add_container(ctr);
@@ -357,6 +472,15 @@ impl AgentService {
 
         // start oom event loop
         if let Ok(cg_path) = ctr.cgroup_manager.as_ref().get_cgroup_path("memory") {
+            // On cgroup v2 the container can be killed as a unit on OOM, so
+            // a multi-process container never keeps running with a vital
+            // process missing.
+            if AGENT_CONFIG.oom_group && cgroups::hierarchies::is_cgroup2_unified_mode() {
+                if let Err(e) = set_memory_oom_group(&cg_path) {
+                    warn!(sl(), "failed to set memory.oom.group: {:?}", e);
+                }
+            }
+
             let rx = notifier::notify_oom(cid.as_str(), cg_path.to_string()).await?;
             s.run_oom_event_monitor(rx, cid).await;
         }
@@ -420,6 +544,7 @@ impl AgentService {
         };
 
         let mut sandbox = self.sandbox.lock().await;
+        sandbox.ensure_exec_capacity()?;
         let mut process = req
             .process
             .into_option()
@@ -428,8 +553,17 @@ impl AgentService {
         // Apply any necessary corrections for PCI addresses
         update_env_pci(&mut process.Env, &sandbox.pcimap)?;
 
+        // Resolve sealed env URIs through the Confidential Data Hub right
+        // before exec, subject to the same allow-list as at creation.
+        if cdh::is_cdh_client_initialized().await {
+            for env in process.Env.iter_mut() {
+                *env = cdh::unseal_env_uri(env).await?;
+            }
+        }
+
         let pipe_size = AGENT_CONFIG.container_pipe_size;
-        let ocip = process.into();
+        let mut ocip: oci::Process = process.into();
+        apply_max_open_files(&mut ocip)?;
         let p = Process::new(&sl(), &ocip, exec_id.as_str(), false, pipe_size, proc_io)?;
 
         let ctr = sandbox
@@ -608,6 +742,17 @@ impl AgentService {
 
         ctr.processes.remove(&pid);
 
+        // The termination message file lives only in the guest, so attach
+        // its contents to the exit notification; the runtime forwards it to
+        // populate the kubelet-visible status message.
+        if eid.is_empty() {
+            if let Ok(config) = ctr.config() {
+                if let Some(spec) = config.spec.as_ref() {
+                    resp.termination_message = read_termination_message(spec);
+                }
+            }
+        }
+
         Ok(resp)
     }
 
@@ -735,14 +880,50 @@ impl agent_ttrpc::AgentService for AgentService {
     async fn create_container(
         &self,
         ctx: &TtrpcContext,
-        req: protocols::agent::CreateContainerRequest,
+        mut req: protocols::agent::CreateContainerRequest,
     ) -> ttrpc::Result<Empty> {
         trace_rpc_call!(ctx, "create_container", req);
+
+        // Merge referenced sandbox-level spec fragments back into the
+        // request before the policy check, so the policy evaluates the
+        // same env and mounts a shim sending everything inline would
+        // have produced.
+        if !req.spec_fragment_ids.is_empty() {
+            let sandbox = self.sandbox.lock().await;
+            merge_spec_fragments(&sandbox, &mut req)
+                .map_err(|e| ttrpc_error(ttrpc::Code::INVALID_ARGUMENT, e))?;
+        }
+
         is_allowed(&req).await?;
+        let _permit = rpc_limits::throttle_expensive()
+            .await
+            .map_err(|e| ttrpc_error(ttrpc::Code::RESOURCE_EXHAUSTED, e))?;
         self.do_create_container(req).await.map_ttrpc_err(same)?;
         Ok(Empty::new())
     }
 
+    async fn add_spec_fragment(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::AddSpecFragmentRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "add_spec_fragment", req);
+        is_allowed(&req).await?;
+
+        if req.id.is_empty() {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                "empty spec fragment id",
+            ));
+        }
+
+        // Fragment ids are content-addressed, so re-registering an id is a
+        // no-op rather than an overwrite.
+        let mut sandbox = self.sandbox.lock().await;
+        sandbox.spec_fragments.entry(req.id.clone()).or_insert(req);
+        Ok(Empty::new())
+    }
+
     async fn start_container(
         &self,
         ctx: &TtrpcContext,
@@ -772,6 +953,9 @@ impl agent_ttrpc::AgentService for AgentService {
     ) -> ttrpc::Result<Empty> {
         trace_rpc_call!(ctx, "exec_process", req);
         is_allowed(&req).await?;
+        let _permit = rpc_limits::throttle_expensive()
+            .await
+            .map_err(|e| ttrpc_error(ttrpc::Code::RESOURCE_EXHAUSTED, e))?;
         self.do_exec_process(req).await.map_ttrpc_err(same)?;
         Ok(Empty::new())
     }
@@ -797,6 +981,40 @@ impl agent_ttrpc::AgentService for AgentService {
         self.do_wait_process(req).await.map_ttrpc_err(same)
     }
 
+    async fn wait_processes(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::WaitProcessesRequest,
+        sender: ::ttrpc::r#async::ServerStreamSender<protocols::agent::ProcessExitStatus>,
+    ) -> ttrpc::Result<()> {
+        trace_rpc_call!(ctx, "wait_processes", req);
+        is_allowed(&req).await?;
+
+        let mut exits = crate::exit_notifier::subscribe();
+        loop {
+            let exit = match exits.recv().await {
+                Ok(exit) => exit,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    return Err(ttrpc_error(
+                        ttrpc::Code::RESOURCE_EXHAUSTED,
+                        format!("missed {} exit statuses, re-query with WaitProcess", missed),
+                    ));
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            let mut status = protocols::agent::ProcessExitStatus::new();
+            status.container_id = exit.container_id;
+            status.exec_id = exit.exec_id;
+            status.status = exit.exit_code;
+
+            // A send error means the client hung up; stop streaming.
+            if sender.send(&status).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
     async fn update_container(
         &self,
         ctx: &TtrpcContext,
@@ -814,6 +1032,16 @@ impl agent_ttrpc::AgentService for AgentService {
             ctr.set(oci_res).map_ttrpc_err(same)?;
         }
 
+        // Resize the dedicated /dev/shm of the container when an updated shm
+        // size annotation is passed along with the resources.
+        let annotation = Annotation::new(req.annotations.clone());
+        let shm_size = annotation
+            .get_container_resource_shm_size()
+            .map_err(|e| ttrpc_error(ttrpc::Code::INVALID_ARGUMENT, e))?;
+        if let Some(shm_size) = shm_size {
+            resize_container_shm(&mut sandbox, &req.container_id, shm_size).map_ttrpc_err(same)?;
+        }
+
         Ok(Empty::new())
     }
 
@@ -829,7 +1057,13 @@ impl agent_ttrpc::AgentService for AgentService {
         let ctr = sandbox
             .get_container(&req.container_id)
             .map_ttrpc_err(ttrpc::Code::INVALID_ARGUMENT, "invalid container id")?;
-        ctr.stats().map_ttrpc_err(same)
+
+        let mut resp = ctr.stats().map_ttrpc_err(same)?;
+        if let Some(spec) = ctr.config().map_ttrpc_err(same)?.spec.as_ref() {
+            resp.filesystem_stats = collect_filesystem_stats(spec);
+        }
+
+        Ok(resp)
     }
 
     async fn pause_container(
@@ -1002,6 +1236,18 @@ impl agent_ttrpc::AgentService for AgentService {
                 .map_ttrpc_err(|e| format!("interface not available: {:?}", e))?;
         }
 
+        // For network devices passed on the ccw bus, check for the network
+        // interface to be available first.
+        #[cfg(target_arch = "s390x")]
+        if !interface.ccwPath.is_empty() {
+            let ccw_device = ccw::Device::from_str(&interface.ccwPath)
+                .map_ttrpc_err(|e| format!("Unexpected ccw-path for network interface: {:?}", e))?;
+
+            wait_for_ccw_net_interface(&self.sandbox, &ccw_device)
+                .await
+                .map_ttrpc_err(|e| format!("interface not available: {:?}", e))?;
+        }
+
         self.sandbox
             .lock()
             .await
@@ -1047,6 +1293,141 @@ impl agent_ttrpc::AgentService for AgentService {
         })
     }
 
+    async fn add_network(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::AddNetworkRequest,
+    ) -> ttrpc::Result<Interface> {
+        trace_rpc_call!(ctx, "add_network", req);
+        is_allowed(&req).await?;
+
+        let interface = req
+            .interface
+            .into_option()
+            .map_ttrpc_err(ttrpc::Code::INVALID_ARGUMENT, "empty add network request")?;
+
+        if interface.name.is_empty() {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                "hotplugged interface has no name",
+            ));
+        }
+
+        // The request is scoped to one interface: routes and sysctls for
+        // anything else would silently reconfigure networks set up by
+        // earlier attachments.
+        for route in &req.routes {
+            if route.device != interface.name {
+                return Err(ttrpc_error(
+                    ttrpc::Code::INVALID_ARGUMENT,
+                    format!(
+                        "route for device {} in add network request for {}",
+                        route.device, interface.name
+                    ),
+                ));
+            }
+        }
+
+        let mut sysctls = Vec::with_capacity(req.sysctls.len());
+        for (key, value) in &req.sysctls {
+            let path = interface_sysctl_path(key, &interface.name).map_ttrpc_err(
+                ttrpc::Code::INVALID_ARGUMENT,
+                format!(
+                    "sysctl {} does not target interface {}",
+                    key, interface.name
+                ),
+            )?;
+            sysctls.push((path, value));
+        }
+
+        // For network devices passed on the pci bus, check for the network interface
+        // to be available first.
+        let mut guest_pcipath = None;
+        if !interface.pciPath.is_empty() {
+            let pcipath = pci::Path::from_str(&interface.pciPath)
+                .map_ttrpc_err(|e| format!("Unexpected pci-path for network interface: {:?}", e))?;
+
+            wait_for_net_interface(&self.sandbox, &pcipath)
+                .await
+                .map_ttrpc_err(|e| format!("interface not available: {:?}", e))?;
+
+            guest_pcipath = Some(pcipath);
+        }
+
+        // For network devices passed on the ccw bus, check for the network
+        // interface to be available first.
+        #[cfg(target_arch = "s390x")]
+        if !interface.ccwPath.is_empty() {
+            let ccw_device = ccw::Device::from_str(&interface.ccwPath)
+                .map_ttrpc_err(|e| format!("Unexpected ccw-path for network interface: {:?}", e))?;
+
+            wait_for_ccw_net_interface(&self.sandbox, &ccw_device)
+                .await
+                .map_ttrpc_err(|e| format!("interface not available: {:?}", e))?;
+        }
+
+        let mut sandbox = self.sandbox.lock().await;
+
+        sandbox
+            .rtnl
+            .update_interface(&interface)
+            .await
+            .map_ttrpc_err(|e| format!("configure hotplugged interface: {:?}", e))?;
+
+        for (path, value) in sysctls {
+            fs::write(&path, value).map_ttrpc_err(|e| format!("apply sysctl {}: {:?}", path, e))?;
+        }
+
+        sandbox
+            .rtnl
+            .add_routes(req.routes)
+            .await
+            .map_ttrpc_err(|e| format!("add routes for hotplugged interface: {:?}", e))?;
+
+        if let Some(pcipath) = guest_pcipath {
+            sandbox
+                .network_pci_map
+                .insert(interface.name.clone(), pcipath);
+        }
+
+        Ok(interface)
+    }
+
+    async fn remove_network(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::RemoveNetworkRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "remove_network", req);
+        is_allowed(&req).await?;
+
+        if req.name.is_empty() {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                "empty remove network request",
+            ));
+        }
+
+        if req.name == "lo" {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                "refusing to remove the loopback interface",
+            ));
+        }
+
+        let mut sandbox = self.sandbox.lock().await;
+
+        sandbox
+            .rtnl
+            .remove_interface(&req.name)
+            .await
+            .map_ttrpc_err(|e| format!("remove interface: {:?}", e))?;
+
+        sandbox.network_pci_map.remove(&req.name);
+
+        Ok(Empty::new())
+    }
+
     async fn update_ephemeral_mounts(
         &self,
         ctx: &TtrpcContext,
@@ -1193,6 +1574,48 @@ impl agent_ttrpc::AgentService for AgentService {
         })
     }
 
+    async fn port_forward(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::PortForwardRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "port_forward", req);
+        is_allowed(&req).await?;
+
+        let port = u16::try_from(req.port)
+            .ok()
+            .filter(|p| *p != 0)
+            .ok_or_else(|| {
+                ttrpc_error(
+                    ttrpc::Code::INVALID_ARGUMENT,
+                    format!("invalid port {}", req.port),
+                )
+            })?;
+
+        let init_pid = self
+            .sandbox
+            .lock()
+            .await
+            .get_container(&req.container_id)
+            .map_ttrpc_err(ttrpc::Code::INVALID_ARGUMENT, "invalid container id")?
+            .init_process_pid;
+
+        // The shim connects the data stream to the passfd listener before
+        // issuing this request, so the stream must already be known.
+        let stream = passfd_io::take_stream(req.vport).await.ok_or_else(|| {
+            ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                format!("no passfd stream for vport {}", req.vport),
+            )
+        })?;
+
+        port_forward::start(Pid::from_raw(init_pid), port, stream)
+            .await
+            .map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
     async fn list_interfaces(
         &self,
         ctx: &TtrpcContext,
@@ -1265,6 +1688,13 @@ impl agent_ttrpc::AgentService for AgentService {
             }
 
             s.setup_shared_namespaces().await.map_ttrpc_err(same)?;
+
+            // The pause process's pid namespace becomes the sandbox-wide
+            // shared pid namespace every container joins, as Kubernetes
+            // shareProcessNamespace requires.
+            if req.sandbox_pidns {
+                s.setup_sandbox_pidns().map_ttrpc_err(same)?;
+            }
         }
 
         let m = add_storages(sl(), req.storages.clone(), &self.sandbox, None)
@@ -1353,6 +1783,20 @@ impl agent_ttrpc::AgentService for AgentService {
         Ok(Empty::new())
     }
 
+    async fn update_dns(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::UpdateDNSRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "update_dns", req);
+        is_allowed(&req).await?;
+
+        update_guest_dns(sl(), &req.dns).map_ttrpc_err(same)?;
+        self.sandbox.lock().await.network.update_dns(req.dns);
+
+        Ok(Empty::new())
+    }
+
     async fn online_cpu_mem(
         &self,
         ctx: &TtrpcContext,
@@ -1409,6 +1853,35 @@ impl agent_ttrpc::AgentService for AgentService {
         Ok(resp)
     }
 
+    async fn negotiate_api(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::NegotiateApiRequest,
+    ) -> ttrpc::Result<protocols::agent::NegotiateApiResponse> {
+        trace_rpc_call!(ctx, "negotiate_api", req);
+        is_allowed(&req).await?;
+
+        // A v1 client does not send this request at all, so a request
+        // without a major version is from a client that predates the
+        // field rather than one speaking "version 0".
+        let client_major = std::cmp::max(req.major, 1);
+        let supported = supported_api_capabilities();
+
+        let mut resp = protocols::agent::NegotiateApiResponse::new();
+        resp.major = std::cmp::min(client_major, API_MAJOR_VERSION);
+        resp.agent_version = AGENT_VERSION.to_string();
+        resp.capabilities = if req.capabilities.is_empty() {
+            supported
+        } else {
+            req.capabilities
+                .into_iter()
+                .filter(|c| supported.iter().any(|s| s == c))
+                .collect()
+        };
+
+        Ok(resp)
+    }
+
     async fn mem_hotplug_by_probe(
         &self,
         ctx: &TtrpcContext,
@@ -1422,6 +1895,176 @@ impl agent_ttrpc::AgentService for AgentService {
         Ok(Empty::new())
     }
 
+    async fn offline_cpus(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::OfflineCPUsRequest,
+    ) -> ttrpc::Result<OfflineCPUsResponse> {
+        trace_rpc_call!(ctx, "offline_cpus", req);
+        is_allowed(&req).await?;
+
+        let s = self.sandbox.lock().await;
+        let offlined = s.offline_cpus(req.nb_cpus as i32).map_ttrpc_err(same)?;
+
+        let mut resp = OfflineCPUsResponse::new();
+        resp.set_nb_offlined(offlined as u32);
+        Ok(resp)
+    }
+
+    async fn offline_memory(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::OfflineMemoryRequest,
+    ) -> ttrpc::Result<OfflineMemoryResponse> {
+        trace_rpc_call!(ctx, "offline_memory", req);
+        is_allowed(&req).await?;
+
+        let s = self.sandbox.lock().await;
+        let reclaimed = s.offline_memory(req.size).map_ttrpc_err(same)?;
+
+        let mut resp = OfflineMemoryResponse::new();
+        resp.set_reclaimed(reclaimed);
+        Ok(resp)
+    }
+
+    async fn reclaim_guest_memory(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::ReclaimGuestMemoryRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "reclaim_guest_memory", req);
+        is_allowed(&req).await?;
+
+        balloon::reclaim_guest_memory(&sl(), req.drop_caches, req.compact_memory)
+            .map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
+    async fn compact_memory(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::CompactMemoryRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "compact_memory", req);
+        is_allowed(&req).await?;
+
+        let proactiveness = if req.tune_proactiveness {
+            if req.proactiveness > 100 {
+                return Err(ttrpc_error(
+                    ttrpc::Code::INVALID_ARGUMENT,
+                    format!(
+                        "compaction proactiveness {} is outside 0-100",
+                        req.proactiveness
+                    ),
+                ));
+            }
+            Some(req.proactiveness)
+        } else {
+            None
+        };
+
+        balloon::compact_memory(&sl(), &req.numa_nodes, proactiveness).map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
+    async fn get_memory_info(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::GetMemoryInfoRequest,
+    ) -> ttrpc::Result<GetMemoryInfoResponse> {
+        trace_rpc_call!(ctx, "get_memory_info", req);
+        is_allowed(&req).await?;
+
+        let meminfo = procfs::Meminfo::new().map_ttrpc_err(same)?;
+
+        let mut resp = GetMemoryInfoResponse::new();
+        resp.total = meminfo.mem_total;
+        resp.free = meminfo.mem_free;
+        resp.available = meminfo.mem_available.unwrap_or(0);
+        resp.cached = meminfo.cached;
+        resp.dirty = meminfo.dirty;
+        resp.writeback = meminfo.writeback;
+        Ok(resp)
+    }
+
+    async fn drop_caches(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::DropCachesRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "drop_caches", req);
+        is_allowed(&req).await?;
+
+        if !(1..=3).contains(&req.level) {
+            return Err(ttrpc_error(
+                ttrpc::Code::INVALID_ARGUMENT,
+                format!("invalid drop_caches level {}, expected 1-3", req.level),
+            ));
+        }
+
+        balloon::drop_caches(&sl(), req.level).map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
+    async fn quiesce_sandbox(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::QuiesceSandboxRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "quiesce_sandbox", req);
+        is_allowed(&req).await?;
+
+        self.sandbox
+            .lock()
+            .await
+            .quiesce_containers()
+            .map_ttrpc_err(same)?;
+
+        if req.drop_caches {
+            // Level 3 discards both page cache and reclaimable slab
+            // objects; drop_caches syncs dirty pages first.
+            balloon::drop_caches(&sl(), 3).map_ttrpc_err(same)?;
+        } else {
+            // Still make the on-disk state consistent for the snapshot.
+            nix::unistd::sync();
+        }
+
+        Ok(Empty::new())
+    }
+
+    async fn thaw_sandbox(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::ThawSandboxRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "thaw_sandbox", req);
+        is_allowed(&req).await?;
+
+        self.sandbox
+            .lock()
+            .await
+            .thaw_containers()
+            .map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
+    async fn reload_config(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::ReloadConfigRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "reload_config", req);
+        is_allowed(&req).await?;
+
+        config::reload(&sl()).map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
     async fn set_guest_date_time(
         &self,
         ctx: &TtrpcContext,
@@ -1443,6 +2086,10 @@ impl agent_ttrpc::AgentService for AgentService {
         trace_rpc_call!(ctx, "copy_file", req);
         is_allowed(&req).await?;
 
+        let _permit = rpc_limits::throttle_expensive()
+            .await
+            .map_err(|e| ttrpc_error(ttrpc::Code::RESOURCE_EXHAUSTED, e))?;
+
         do_copy_file(&req).map_ttrpc_err(same)?;
 
         Ok(Empty::new())
@@ -1456,7 +2103,7 @@ impl agent_ttrpc::AgentService for AgentService {
         trace_rpc_call!(ctx, "get_metrics", req);
         is_allowed(&req).await?;
 
-        let s = get_metrics(&req).map_ttrpc_err(same)?;
+        let s = get_metrics(&req, &self.sandbox).await.map_ttrpc_err(same)?;
         let mut metrics = Metrics::new();
         metrics.set_metrics(s);
         Ok(metrics)
@@ -1520,6 +2167,19 @@ impl agent_ttrpc::AgentService for AgentService {
         Ok(resp)
     }
 
+    async fn resize_volume(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::ResizeVolumeRequest,
+    ) -> ttrpc::Result<Empty> {
+        trace_rpc_call!(ctx, "resize_volume", req);
+        is_allowed(&req).await?;
+
+        do_resize_volume(&req.volume_guest_path, req.size).map_ttrpc_err(same)?;
+
+        Ok(Empty::new())
+    }
+
     async fn add_swap(
         &self,
         ctx: &TtrpcContext,
@@ -1593,6 +2253,26 @@ impl agent_ttrpc::AgentService for AgentService {
         }
         Ok(Empty::new())
     }
+
+    async fn get_guest_logs(
+        &self,
+        ctx: &TtrpcContext,
+        req: protocols::agent::GetGuestLogsRequest,
+    ) -> ttrpc::Result<GetGuestLogsResponse> {
+        trace_rpc_call!(ctx, "get_guest_logs", req);
+        is_allowed(&req).await?;
+
+        let max_size = if req.max_size == 0 {
+            DEFAULT_GUEST_LOG_SIZE
+        } else {
+            req.max_size as usize
+        };
+
+        let mut resp = GetGuestLogsResponse::new();
+        resp.set_dmesg(get_kernel_ring_buffer(max_size).map_ttrpc_err(same)?);
+        resp.set_agent_logs(get_agent_journal(max_size));
+        Ok(resp)
+    }
 }
 
 #[derive(Clone)]
@@ -1623,6 +2303,134 @@ impl health_ttrpc::Health for HealthService {
 
         Ok(rep)
     }
+
+    async fn health_detail(
+        &self,
+        _ctx: &TtrpcContext,
+        _req: protocols::health::HealthDetailRequest,
+    ) -> ttrpc::Result<HealthDetailResponse> {
+        let mut resp = HealthDetailResponse::new();
+        resp.subsystems.push(probe_storage().await);
+        resp.subsystems.push(probe_network());
+        resp.subsystems.push(probe_policy().await);
+        resp.subsystems.push(probe_cdh().await);
+
+        let status = if resp
+            .subsystems
+            .iter()
+            .any(|s| s.status() == HealthCheckResponse_ServingStatus::NOT_SERVING)
+        {
+            HealthCheckResponse_ServingStatus::NOT_SERVING
+        } else {
+            HealthCheckResponse_ServingStatus::SERVING
+        };
+        resp.set_status(status);
+
+        Ok(resp)
+    }
+}
+
+// Timeout for the storage probe: a dead virtiofsd makes filesystem
+// operations on the shared directory hang rather than fail.
+const SUBSYSTEM_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn subsystem_status(
+    name: &str,
+    status: HealthCheckResponse_ServingStatus,
+    last_error: &str,
+) -> SubsystemStatus {
+    let mut s = SubsystemStatus::new();
+    s.set_name(name.to_string());
+    s.set_status(status);
+    s.set_last_error(last_error.to_string());
+    s
+}
+
+// Probe the host-shared storage by statting the shared directory. The
+// stat is performed on a blocking thread under a timeout so that a dead
+// virtiofsd shows up as NOT_SERVING instead of hanging the health RPC.
+async fn probe_storage() -> SubsystemStatus {
+    let probe = tokio::task::spawn_blocking(|| fs::metadata(KATA_GUEST_SHARE_DIR));
+    match tokio::time::timeout(SUBSYSTEM_PROBE_TIMEOUT, probe).await {
+        Ok(Ok(Ok(_))) => {
+            subsystem_status("storage", HealthCheckResponse_ServingStatus::SERVING, "")
+        }
+        // no shared directory means no host-shared storage to check
+        Ok(Ok(Err(ref e))) if e.kind() == std::io::ErrorKind::NotFound => {
+            subsystem_status("storage", HealthCheckResponse_ServingStatus::SERVING, "")
+        }
+        Ok(Ok(Err(e))) => subsystem_status(
+            "storage",
+            HealthCheckResponse_ServingStatus::NOT_SERVING,
+            &format!("failed to stat {}: {}", KATA_GUEST_SHARE_DIR, e),
+        ),
+        Ok(Err(e)) => subsystem_status(
+            "storage",
+            HealthCheckResponse_ServingStatus::NOT_SERVING,
+            &format!("storage probe task failed: {}", e),
+        ),
+        Err(_) => subsystem_status(
+            "storage",
+            HealthCheckResponse_ServingStatus::NOT_SERVING,
+            &format!(
+                "stat of {} timed out after {:?}",
+                KATA_GUEST_SHARE_DIR, SUBSYSTEM_PROBE_TIMEOUT
+            ),
+        ),
+    }
+}
+
+// Probe the network stack by listing the guest interfaces through sysfs.
+fn probe_network() -> SubsystemStatus {
+    match fs::read_dir("/sys/class/net") {
+        Ok(_) => subsystem_status("network", HealthCheckResponse_ServingStatus::SERVING, ""),
+        Err(e) => subsystem_status(
+            "network",
+            HealthCheckResponse_ServingStatus::NOT_SERVING,
+            &format!("failed to list network interfaces: {}", e),
+        ),
+    }
+}
+
+// Probe the policy engine by taking its lock under a timeout: a wedged
+// evaluation keeps the lock held and would block every policed RPC.
+#[cfg(feature = "agent-policy")]
+async fn probe_policy() -> SubsystemStatus {
+    match tokio::time::timeout(SUBSYSTEM_PROBE_TIMEOUT, crate::AGENT_POLICY.lock()).await {
+        Ok(_) => subsystem_status("policy", HealthCheckResponse_ServingStatus::SERVING, ""),
+        Err(_) => subsystem_status(
+            "policy",
+            HealthCheckResponse_ServingStatus::NOT_SERVING,
+            &format!(
+                "policy engine lock not acquired within {:?}",
+                SUBSYSTEM_PROBE_TIMEOUT
+            ),
+        ),
+    }
+}
+
+#[cfg(not(feature = "agent-policy"))]
+async fn probe_policy() -> SubsystemStatus {
+    subsystem_status(
+        "policy",
+        HealthCheckResponse_ServingStatus::UNKNOWN,
+        "agent built without the agent-policy feature",
+    )
+}
+
+// Probe the confidential data hub client. An uninitialized client is
+// reported as UNKNOWN rather than NOT_SERVING: most sandboxes never
+// configure a CDH socket.
+async fn probe_cdh() -> SubsystemStatus {
+    if cdh::is_cdh_client_initialized().await {
+        subsystem_status("cdh", HealthCheckResponse_ServingStatus::SERVING, "")
+    } else {
+        subsystem_status(
+            "cdh",
+            HealthCheckResponse_ServingStatus::UNKNOWN,
+            "CDH client not initialized",
+        )
+    }
 }
 
 fn get_memory_info(
@@ -1684,6 +2492,70 @@ fn get_volume_capacity_stats(path: &str) -> Result<VolumeUsage> {
     Ok(usage)
 }
 
+// Filesystem magic numbers from statfs(2), kept local since the nix
+// constants are not available on every libc the agent builds against.
+const EXT4_SUPER_MAGIC: u64 = 0xEF53;
+const XFS_SUPER_MAGIC: u64 = 0x58465342;
+
+nix::ioctl_write_ptr!(ext4_resize_fs, 'f', 16, u64);
+
+// Mirrors struct xfs_growfs_data from the kernel's xfs_fs.h.
+#[repr(C)]
+struct XfsGrowfsData {
+    newblocks: u64,
+    imaxpct: u32,
+}
+
+nix::ioctl_write_ptr!(xfs_grow_fs_data, 'X', 110, XfsGrowfsData);
+
+// Grow the filesystem mounted at `path` to `size` bytes, after the host
+// has expanded the backing block device. ext4 and xfs both support
+// online grow through ioctls on the mount point, so no filesystem tools
+// are needed in the guest image. Online shrink is not supported.
+fn do_resize_volume(path: &str, size: u64) -> Result<()> {
+    let st = statfs::statfs(path).with_context(|| format!("statfs {}", path))?;
+    let block_size = st.block_size() as u64;
+    ensure!(block_size > 0, "invalid block size for {}", path);
+
+    let new_blocks = size / block_size;
+    let cur_blocks = st.blocks();
+    if new_blocks < cur_blocks {
+        return Err(anyhow!(
+            "cannot shrink volume {} from {} to {} blocks online",
+            path,
+            cur_blocks,
+            new_blocks
+        ));
+    }
+
+    let file = File::open(path).with_context(|| format!("open {}", path))?;
+    let fs_magic = st.filesystem_type().0 as u64;
+    match fs_magic {
+        EXT4_SUPER_MAGIC => {
+            unsafe { ext4_resize_fs(file.as_raw_fd(), &new_blocks) }
+                .with_context(|| format!("resize ext4 volume {} to {} bytes", path, size))?;
+        }
+        XFS_SUPER_MAGIC => {
+            let growfs = XfsGrowfsData {
+                newblocks: new_blocks,
+                imaxpct: 0,
+            };
+            unsafe { xfs_grow_fs_data(file.as_raw_fd(), &growfs) }
+                .with_context(|| format!("resize xfs volume {} to {} bytes", path, size))?;
+        }
+        _ => {
+            return Err(anyhow!(
+                "online resize is not supported for filesystem type {:#x} on {}",
+                fs_magic,
+                path
+            ));
+        }
+    }
+
+    info!(sl(), "resized volume {} to {} bytes", path, size);
+    Ok(())
+}
+
 fn get_volume_inode_stats(path: &str) -> Result<VolumeUsage> {
     let mut usage = VolumeUsage::new();
 
@@ -1704,6 +2576,39 @@ pub fn have_seccomp() -> bool {
     false
 }
 
+// Major version of the agent API spoken by this build. It is only bumped
+// when the handshake semantics themselves change; individual optional
+// RPCs are announced as capabilities instead.
+const API_MAJOR_VERSION: u32 = 2;
+
+// Optional capabilities of this agent, reported by negotiate_api. A
+// capability is only listed when the corresponding RPCs are both compiled
+// in and enabled by the current configuration, so a client can rely on a
+// reported capability actually working.
+fn supported_api_capabilities() -> Vec<String> {
+    // "reclaim-guest-memory" covers both balloon RPCs, reclaim_guest_memory
+    // and compact_memory.
+    let mut caps = vec![
+        "add-network",
+        "add-spec-fragment",
+        "reclaim-guest-memory",
+        "wait-processes",
+    ];
+
+    if AGENT_CONFIG.mem_agent.is_some() {
+        caps.push("mem-agent");
+    }
+
+    if AGENT_CONFIG.enable_attestation_proxy {
+        caps.push("attestation-proxy");
+    }
+
+    #[cfg(feature = "agent-policy")]
+    caps.push("set-policy");
+
+    caps.iter().map(|c| c.to_string()).collect()
+}
+
 fn get_agent_details() -> AgentDetails {
     let mut detail = AgentDetails::new();
 
@@ -1714,6 +2619,11 @@ fn get_agent_details() -> AgentDetails {
     detail.device_handlers = Vec::new();
     detail.storage_handlers = STORAGE_HANDLERS.get_handlers();
     detail.extra_features = get_build_features();
+    detail.supported_compression = SUPPORTED_PAYLOAD_COMPRESSION
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    detail.kernel_version = nix::sys::utsname::uname().release().to_string();
 
     detail
 }
@@ -1772,6 +2682,24 @@ pub async fn start(
 // path set by the spec, since we will always ignore it. Indeed, it makes no
 // sense to rely on the namespace path provided by the host since namespaces
 // are different inside the guest.
+// Resolve a per-interface sysctl key like "net.ipv4.conf.eth1.accept_local"
+// to its procfs path, rejecting keys that do not target the given
+// interface. Interface names may themselves contain dots (vlan
+// interfaces like "eth0.100"), so the name is matched literally instead
+// of splitting the key blindly.
+fn interface_sysctl_path(key: &str, ifname: &str) -> Option<String> {
+    for family in ["ipv4", "ipv6"] {
+        let prefix = format!("net.{}.conf.{}.", family, ifname);
+        if let Some(knob) = key.strip_prefix(&prefix) {
+            if !knob.is_empty() && !knob.contains('.') {
+                return Some(format!("/proc/sys/net/{}/conf/{}/{}", family, ifname, knob));
+            }
+        }
+    }
+
+    None
+}
+
 fn update_container_namespaces(
     sandbox: &Sandbox,
     spec: &mut Spec,
@@ -1826,6 +2754,177 @@ fn update_container_namespaces(
     Ok(())
 }
 
+// Merge the env and mounts of the referenced sandbox-level spec fragments
+// into the request's OCI spec and clear the reference list, so that from
+// here on - the policy check included - the request is indistinguishable
+// from one whose shim sent everything inline. Entries already present in
+// the spec win, so a container can override a fragment value with one of
+// its own.
+fn merge_spec_fragments(
+    sandbox: &Sandbox,
+    req: &mut protocols::agent::CreateContainerRequest,
+) -> Result<()> {
+    let spec = req
+        .OCI
+        .as_mut()
+        .ok_or_else(|| anyhow!("no oci spec in the create container request"))?;
+
+    for id in &req.spec_fragment_ids {
+        let fragment = sandbox
+            .spec_fragments
+            .get(id)
+            .ok_or_else(|| anyhow!("unknown spec fragment {}", id))?;
+
+        if !fragment.env.is_empty() {
+            if let Some(process) = spec.Process.as_mut() {
+                for entry in &fragment.env {
+                    let name = entry.split('=').next().unwrap_or(entry);
+                    if !process
+                        .Env
+                        .iter()
+                        .any(|e| e.split('=').next() == Some(name))
+                    {
+                        process.Env.push(entry.clone());
+                    }
+                }
+            }
+        }
+
+        for m in &fragment.mounts {
+            if !spec.Mounts.iter().any(|e| e.destination == m.destination) {
+                spec.Mounts.push(m.clone());
+            }
+        }
+    }
+
+    req.spec_fragment_ids.clear();
+    Ok(())
+}
+
+// Return the path of the dedicated /dev/shm tmpfs of a container.
+fn container_shm_dir(cid: &str) -> PathBuf {
+    Path::new(KATA_GUEST_SHM_DIR).join(cid)
+}
+
+// Mount a dedicated tmpfs of `shm_size` bytes for the container and rewrite
+// the /dev/shm mount in the spec to bind mount it. Keeping the tmpfs in the
+// agent mount namespace allows it to be resized later without entering the
+// container.
+fn setup_container_shm(cid: &str, shm_size: u64, spec: &mut Spec) -> Result<()> {
+    let shm_dir = container_shm_dir(cid);
+    fs::create_dir_all(&shm_dir)?;
+
+    baremount(
+        Path::new("shm"),
+        &shm_dir,
+        "tmpfs",
+        MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+        format!("mode=1777,size={}", shm_size).as_str(),
+        &sl(),
+    )?;
+
+    if let Some(mounts) = spec.mounts_mut().as_mut() {
+        for m in mounts.iter_mut() {
+            if m.destination().display().to_string() == "/dev/shm" {
+                m.set_typ(Some("bind".to_string()));
+                m.set_source(Some(shm_dir.clone()));
+                m.set_options(Some(vec!["rbind".to_string()]));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Remount the dedicated /dev/shm tmpfs of a container with a new size. The
+// resize is visible in the container through the bind mount.
+fn resize_container_shm(sandbox: &mut Sandbox, cid: &str, shm_size: u64) -> Result<()> {
+    if !sandbox.shm_sizes.contains_key(cid) {
+        return Err(anyhow!(
+            "container {} has no dedicated /dev/shm to resize",
+            cid
+        ));
+    }
+
+    nix::mount::mount(
+        Some("shm"),
+        &container_shm_dir(cid),
+        Some("tmpfs"),
+        MsFlags::MS_REMOUNT | MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+        Some(format!("mode=1777,size={}", shm_size).as_str()),
+    )?;
+    sandbox.shm_sizes.insert(cid.to_string(), shm_size);
+
+    Ok(())
+}
+
+// Return the directory holding the idmapped volume mounts of a container.
+fn container_idmap_dir(cid: &str) -> PathBuf {
+    Path::new(KATA_GUEST_IDMAP_DIR).join(cid)
+}
+
+// When the container joins a new user namespace with id mappings, give each
+// of its host-shared volumes an idmapped view so the ownership of shared
+// files matches the container's mappings instead of resolving to the
+// overflow ids. The spec mount sources are rewritten to the idmapped mount
+// points; created mounts are recorded one by one so container removal can
+// clean up a partial setup.
+fn setup_idmapped_volumes(sandbox: &mut Sandbox, cid: &str, spec: &mut Spec) -> Result<()> {
+    let (uid_mappings, gid_mappings) = match spec.linux().as_ref() {
+        Some(linux) => {
+            let default_vec = Vec::new();
+            let has_userns = linux
+                .namespaces()
+                .as_ref()
+                .unwrap_or(&default_vec)
+                .iter()
+                .any(|ns| ns.typ().to_string() == NSTYPEUSER);
+            if !has_userns {
+                return Ok(());
+            }
+            (
+                linux.uid_mappings().clone().unwrap_or_default(),
+                linux.gid_mappings().clone().unwrap_or_default(),
+            )
+        }
+        None => return Ok(()),
+    };
+    if uid_mappings.is_empty() || gid_mappings.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(mounts) = spec.mounts_mut().as_mut() {
+        for (i, m) in mounts.iter_mut().enumerate() {
+            let source = match m.source().as_ref() {
+                Some(source) if source.starts_with(KATA_GUEST_SHARE_DIR) => source.clone(),
+                _ => continue,
+            };
+
+            let idmap_dest = container_idmap_dir(cid).join(i.to_string());
+            if source.is_dir() {
+                fs::create_dir_all(&idmap_dest)?;
+            } else {
+                fs::create_dir_all(container_idmap_dir(cid))?;
+                fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .open(&idmap_dest)?;
+            }
+
+            idmapped_bind_mount(&sl(), &source, &idmap_dest, &uid_mappings, &gid_mappings)?;
+
+            sandbox
+                .idmapped_mounts
+                .entry(cid.to_string())
+                .or_default()
+                .push(idmap_dest.display().to_string());
+            m.set_source(Some(idmap_dest));
+        }
+    }
+
+    Ok(())
+}
+
 async fn remove_container_resources(sandbox: &mut Sandbox, cid: &str) -> Result<()> {
     let mut cmounts: Vec<String> = vec![];
 
@@ -1850,6 +2949,35 @@ async fn remove_container_resources(sandbox: &mut Sandbox, cid: &str) -> Result<
         }
     }
 
+    if sandbox.shm_sizes.remove(cid).is_some() {
+        let shm_dir = container_shm_dir(cid).display().to_string();
+        if let Err(err) = remove_mounts(&[shm_dir.clone()]) {
+            error!(
+                sl(),
+                "failed to unmount container shm for {}, error: {:?}", cid, err
+            );
+        } else if let Err(err) = fs::remove_dir(&shm_dir) {
+            error!(
+                sl(),
+                "failed to remove container shm dir for {}, error: {:?}", cid, err
+            );
+        }
+    }
+
+    if let Some(mounts) = sandbox.idmapped_mounts.remove(cid) {
+        if let Err(err) = remove_mounts(&mounts) {
+            error!(
+                sl(),
+                "failed to unmount idmapped volumes for {}, error: {:?}", cid, err
+            );
+        } else if let Err(err) = fs::remove_dir_all(container_idmap_dir(cid)) {
+            error!(
+                sl(),
+                "failed to remove idmapped mount dir for {}, error: {:?}", cid, err
+            );
+        }
+    }
+
     sandbox.container_mounts.remove(cid);
     sandbox.containers.remove(cid);
     Ok(())
@@ -1874,6 +3002,331 @@ fn append_guest_hooks(s: &Sandbox, oci: &mut Spec) -> Result<()> {
     Ok(())
 }
 
+// Cap the number of files a container process may keep open by injecting
+// an RLIMIT_NOFILE entry when `agent.max_open_files` is configured. An
+// explicit RLIMIT_NOFILE in the OCI spec always wins over the agent ceiling.
+fn apply_max_open_files(process: &mut oci::Process) -> Result<()> {
+    let max_open_files = AGENT_CONFIG.max_open_files;
+    if max_open_files == 0 {
+        return Ok(());
+    }
+
+    let mut rlimits = process.rlimits().clone().unwrap_or_default();
+    if rlimits
+        .iter()
+        .any(|rl| rl.typ() == oci::PosixRlimitType::RlimitNofile)
+    {
+        return Ok(());
+    }
+
+    rlimits.push(
+        oci::PosixRlimitBuilder::default()
+            .typ(oci::PosixRlimitType::RlimitNofile)
+            .hard(max_open_files)
+            .soft(max_open_files)
+            .build()?,
+    );
+    process.set_rlimits(Some(rlimits));
+
+    Ok(())
+}
+
+// Parse an OCI hugepage size string such as "64KB", "2MB" or "1GB" into
+// bytes.
+fn huge_page_size_bytes(page_size: &str) -> Result<u64> {
+    let (digits, shift) = if let Some(d) = page_size.strip_suffix("KB") {
+        (d, 10)
+    } else if let Some(d) = page_size.strip_suffix("MB") {
+        (d, 20)
+    } else if let Some(d) = page_size.strip_suffix("GB") {
+        (d, 30)
+    } else {
+        return Err(anyhow!("invalid hugepage size {:?}", page_size));
+    };
+
+    let num: u64 = digits
+        .parse()
+        .map_err(|_| anyhow!("invalid hugepage size {:?}", page_size))?;
+
+    num.checked_mul(1 << shift)
+        .filter(|v| *v > 0)
+        .ok_or_else(|| anyhow!("invalid hugepage size {:?}", page_size))
+}
+
+// Check that the hugepage limits requested for a container can be
+// satisfied by the hugepages the guest was booted with, so a
+// misconfigured pod fails container creation with a clear error instead
+// of SIGBUS'ing at runtime.
+fn check_hugepage_limits(spec: &oci::Spec) -> Result<()> {
+    let limits = match spec
+        .linux()
+        .as_ref()
+        .and_then(|linux| linux.resources().as_ref())
+        .and_then(|resources| resources.hugepage_limits().as_ref())
+    {
+        Some(limits) => limits,
+        None => return Ok(()),
+    };
+
+    for l in limits {
+        if l.limit() == 0 {
+            continue;
+        }
+
+        let page_size = huge_page_size_bytes(l.page_size())?;
+        let nr_path = format!(
+            "{}/hugepages-{}kB/nr_hugepages",
+            SYSFS_HUGEPAGES_PREFIX,
+            page_size >> 10
+        );
+        let nr_hugepages: u64 = fs::read_to_string(&nr_path)
+            .map_err(|_| {
+                anyhow!(
+                    "hugepage size {} is not supported by the guest kernel",
+                    l.page_size()
+                )
+            })?
+            .trim()
+            .parse()
+            .with_context(|| format!("parse {}", nr_path))?;
+
+        let available = nr_hugepages.saturating_mul(page_size);
+        if l.limit() as u64 > available {
+            return Err(anyhow!(
+                "hugepage limit of {} bytes ({} pages) exceeds the {} bytes of {} hugepages the guest was booted with",
+                l.limit(),
+                l.limit() as u64 / page_size,
+                available,
+                l.page_size()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Check the sysctls requested for a container against the configured
+// allowed/denied prefixes. The sysctls themselves are written by rustjail
+// from inside the container's namespaces, so permitted net.* keys only
+// ever touch the network namespace the container actually joins.
+fn check_sysctls(spec: &oci::Spec) -> Result<()> {
+    let sysctls = match spec
+        .linux()
+        .as_ref()
+        .and_then(|linux| linux.sysctl().as_ref())
+    {
+        Some(sysctls) => sysctls,
+        None => return Ok(()),
+    };
+
+    let mut rejected = vec![];
+    for key in sysctls.keys() {
+        if let Some(prefix) = AGENT_CONFIG
+            .sysctl_denied_prefixes
+            .iter()
+            .find(|p| key.starts_with(p.as_str()))
+        {
+            rejected.push(format!("{} (matches denied prefix {:?})", key, prefix));
+        } else if !AGENT_CONFIG
+            .sysctl_allowed_prefixes
+            .iter()
+            .any(|p| key.starts_with(p.as_str()))
+        {
+            rejected.push(format!("{} (no allowed prefix matches)", key));
+        }
+    }
+
+    if !rejected.is_empty() {
+        rejected.sort();
+        return Err(anyhow!(
+            "sysctls rejected by the agent configuration: {}",
+            rejected.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+// Ask the kernel to OOM kill all tasks in the container cgroup atomically,
+// per the agent.oom_group configuration.
+fn set_memory_oom_group(cg_path: &str) -> Result<()> {
+    let path = Path::new(cg_path).join("memory.oom.group");
+    std::fs::write(&path, "1").with_context(|| format!("write {}", path.display()))
+}
+
+// Annotation carrying the container's termination message path, as set by
+// the CRI runtime from the pod spec.
+const TERMINATION_MESSAGE_PATH_ANNOTATION: &str = "io.kubernetes.cri.termination-message-path";
+const DEFAULT_TERMINATION_MESSAGE_PATH: &str = "/dev/termination-log";
+// Kubernetes caps a single container's termination message at 4096 bytes.
+const TERMINATION_MESSAGE_SIZE_LIMIT: u64 = 4096;
+
+// Read the container's termination message file from its rootfs, best
+// effort: a missing or unreadable file yields an empty message, and the
+// contents are capped at the Kubernetes per-container limit.
+fn read_termination_message(spec: &oci::Spec) -> Vec<u8> {
+    let path = spec
+        .annotations()
+        .as_ref()
+        .and_then(|a| a.get(TERMINATION_MESSAGE_PATH_ANNOTATION).cloned())
+        .unwrap_or_else(|| DEFAULT_TERMINATION_MESSAGE_PATH.to_string());
+
+    let rootfs = match spec.root() {
+        Some(root) => root.path(),
+        None => return Vec::new(),
+    };
+
+    // Resolve the message path inside the rootfs, rejecting traversal
+    // outside of it.
+    let file_path = match scoped_join(rootfs, path.trim_start_matches('/')) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(sl(), "invalid termination message path {}: {:?}", path, e);
+            return Vec::new();
+        }
+    };
+
+    let file = match std::fs::File::open(&file_path) {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut message = Vec::new();
+    if let Err(e) = file
+        .take(TERMINATION_MESSAGE_SIZE_LIMIT)
+        .read_to_end(&mut message)
+    {
+        warn!(
+            sl(),
+            "failed to read termination message {}: {:?}",
+            file_path.display(),
+            e
+        );
+        return Vec::new();
+    }
+
+    message
+}
+
+// Collect filesystem usage for `path` via statfs(2). Cheap enough to run
+// on every StatsContainer call; project quota accounting can refine this
+// later for filesystems sharing a block device.
+fn filesystem_usage(path: &str) -> Result<FilesystemUsage> {
+    let st = statfs::statfs(path)?;
+    let block_size = st.block_size() as u64;
+
+    let mut usage = FilesystemUsage::new();
+    usage.set_mount_point(path.to_string());
+    usage.set_total_bytes(st.blocks().saturating_mul(block_size));
+    usage.set_used_bytes((st.blocks().saturating_sub(st.blocks_free())).saturating_mul(block_size));
+    usage.set_total_inodes(st.files());
+    usage.set_used_inodes(st.files().saturating_sub(st.files_free()));
+
+    Ok(usage)
+}
+
+// Gather filesystem usage for the container rootfs and for bind mounted
+// volumes that live on guest-local storage, so the host can account
+// ephemeral storage (e.g. for kubelet eviction). Mounts whose source is
+// outside CONTAINER_BASE (/proc, /sys, host devices) are skipped, and a
+// path that fails statfs is dropped rather than failing the whole stats
+// request.
+fn collect_filesystem_stats(spec: &oci::Spec) -> Vec<FilesystemUsage> {
+    let mut paths: Vec<String> = Vec::new();
+
+    if let Some(root) = spec.root() {
+        paths.push(root.path().display().to_string());
+    }
+
+    let default_mnts = Vec::new();
+    for m in spec.mounts().as_ref().unwrap_or(&default_mnts) {
+        if let Some(source) = m.source() {
+            if source.starts_with(CONTAINER_BASE) {
+                let source = source.display().to_string();
+                if !paths.contains(&source) {
+                    paths.push(source);
+                }
+            }
+        }
+    }
+
+    let mut stats = Vec::new();
+    for path in paths {
+        match filesystem_usage(&path) {
+            Ok(usage) => stats.push(usage),
+            Err(e) => warn!(sl(), "failed to stat filesystem {}: {:?}", path, e),
+        }
+    }
+
+    stats
+}
+
+// Read the kernel ring buffer (dmesg), returning at most the last
+// `max_size` bytes.
+fn get_kernel_ring_buffer(max_size: usize) -> Result<Vec<u8>> {
+    const SYSLOG_ACTION_READ_ALL: libc::c_int = 3;
+    const SYSLOG_ACTION_SIZE_BUFFER: libc::c_int = 10;
+
+    // SAFETY: passing a null buffer is allowed for SIZE_BUFFER.
+    let size = unsafe { libc::klogctl(SYSLOG_ACTION_SIZE_BUFFER, std::ptr::null_mut(), 0) };
+    if size < 0 {
+        return Err(anyhow!(io::Error::last_os_error()).context("get kernel ring buffer size"));
+    }
+
+    let mut buf = vec![0u8; size as usize];
+    // SAFETY: buf is valid for the length we pass.
+    let len = unsafe {
+        libc::klogctl(
+            SYSLOG_ACTION_READ_ALL,
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len() as libc::c_int,
+        )
+    };
+    if len < 0 {
+        return Err(anyhow!(io::Error::last_os_error()).context("read kernel ring buffer"));
+    }
+    buf.truncate(len as usize);
+
+    if buf.len() > max_size {
+        buf.drain(..buf.len() - max_size);
+    }
+    Ok(buf)
+}
+
+// Collect recent kata-agent log records from the systemd journal. When the
+// agent runs as init there is no journal, so an empty result is returned
+// rather than an error.
+fn get_agent_journal(max_size: usize) -> Vec<u8> {
+    let output = match Command::new("journalctl")
+        .args([
+            "--identifier=kata-agent",
+            "--no-pager",
+            "--output=short-iso",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            warn!(
+                sl(),
+                "journalctl failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Vec::new();
+        }
+        Err(e) => {
+            info!(sl(), "no agent journal available: {:?}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut logs = output;
+    if logs.len() > max_size {
+        logs.drain(..logs.len() - max_size);
+    }
+    logs
+}
+
 // Check if the container process installed the
 // handler for specific signal.
 fn is_signal_handled(proc_status_file: &str, signum: u32) -> bool {
@@ -1949,8 +3402,19 @@ fn do_set_guest_date_time(sec: i64, usec: i64) -> Result<()> {
     Ok(())
 }
 
+fn decompress_payload<'a>(compression: &str, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    match compression {
+        "" => Ok(Cow::Borrowed(data)),
+        "zstd" => Ok(Cow::Owned(
+            zstd::stream::decode_all(data).context("decompress zstd payload")?,
+        )),
+        _ => Err(anyhow!("unsupported payload compression {:?}", compression)),
+    }
+}
+
 fn do_copy_file(req: &CopyFileRequest) -> Result<()> {
     let path = PathBuf::from(req.path.as_str());
+    let data = decompress_payload(req.compression.as_str(), &req.data)?;
 
     if !path.starts_with(CONTAINER_BASE) {
         return Err(anyhow!(
@@ -2002,7 +3466,7 @@ fn do_copy_file(req: &CopyFileRequest) -> Result<()> {
         if path.is_symlink() && path.exists() {
             unistd::unlink(&path)?;
         }
-        let src = PathBuf::from(OsStr::from_bytes(&req.data));
+        let src = PathBuf::from(OsStr::from_bytes(&data));
         unistd::symlinkat(&src, None, &path)?;
         let path_str = CString::new(path.as_os_str().as_bytes())?;
 
@@ -2021,7 +3485,7 @@ fn do_copy_file(req: &CopyFileRequest) -> Result<()> {
         .truncate(false)
         .open(&tmpfile)?;
 
-    file.write_all_at(req.data.as_slice(), req.offset as u64)?;
+    file.write_all_at(&data, req.offset as u64)?;
     let st = stat::stat(&tmpfile)?;
 
     if st.st_size != req.file_size {
@@ -2187,6 +3651,11 @@ async fn cdh_handler(oci: &mut Spec) -> Result<()> {
         .ok_or_else(|| anyhow!("Spec didn't contain process field"))?;
     if let Some(envs) = process.env_mut().as_mut() {
         for env in envs.iter_mut() {
+            // Sealed env URIs must resolve or the container must not be
+            // created, so the workload can never observe the sealed
+            // reference; plain `sealed.` values keep their historical
+            // best-effort behavior.
+            *env = cdh::unseal_env_uri(env).await?;
             match cdh::unseal_env(env).await {
                 Ok(unsealed_env) => *env = unsealed_env.to_string(),
                 Err(e) => {
@@ -2441,6 +3910,104 @@ mod tests {
         assert!(result.is_err(), "expected update routes to fail");
     }
 
+    #[tokio::test]
+    async fn test_add_network() {
+        let logger = slog::Logger::root(slog::Discard, o!());
+        let sandbox = Sandbox::new(&logger).unwrap();
+        let agent_service = Box::new(AgentService {
+            sandbox: Arc::new(Mutex::new(sandbox)),
+            init_mode: true,
+            oma: None,
+        });
+
+        let req = protocols::agent::AddNetworkRequest::default();
+        let ctx = mk_ttrpc_context();
+
+        let result = agent_service.add_network(&ctx, req).await;
+
+        assert!(result.is_err(), "expected add network to fail");
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_api() {
+        let logger = slog::Logger::root(slog::Discard, o!());
+        let sandbox = Sandbox::new(&logger).unwrap();
+        let agent_service = Box::new(AgentService {
+            sandbox: Arc::new(Mutex::new(sandbox)),
+            init_mode: true,
+            oma: None,
+        });
+        let ctx = mk_ttrpc_context();
+
+        // An empty capability list asks for the full supported set.
+        let req = protocols::agent::NegotiateApiRequest::default();
+        let resp = agent_service.negotiate_api(&ctx, req).await.unwrap();
+        assert_eq!(resp.major, 1, "a client without a major version is v1");
+        assert!(resp.capabilities.iter().any(|c| c == "add-network"));
+
+        // A named capability list is answered with the supported subset.
+        let mut req = protocols::agent::NegotiateApiRequest::default();
+        req.major = API_MAJOR_VERSION + 1;
+        req.capabilities = vec!["add-network".to_string(), "does-not-exist".to_string()];
+        let resp = agent_service.negotiate_api(&ctx, req).await.unwrap();
+        assert_eq!(resp.major, API_MAJOR_VERSION);
+        assert_eq!(resp.capabilities, vec!["add-network".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_spec_fragments() {
+        let logger = slog::Logger::root(slog::Discard, o!());
+        let mut sandbox = Sandbox::new(&logger).unwrap();
+
+        let mut fragment = protocols::agent::AddSpecFragmentRequest::new();
+        fragment.id = "frag-1".to_string();
+        fragment.env = vec!["A=fragment".to_string(), "B=2".to_string()];
+        sandbox.spec_fragments.insert(fragment.id.clone(), fragment);
+
+        let mut process = protocols::oci::Process::new();
+        process.Env = vec!["A=inline".to_string()];
+        let mut spec = protocols::oci::Spec::new();
+        spec.Process = MessageField::some(process);
+        let mut req = protocols::agent::CreateContainerRequest::new();
+        req.OCI = MessageField::some(spec);
+        req.spec_fragment_ids = vec!["frag-1".to_string()];
+
+        merge_spec_fragments(&sandbox, &mut req).unwrap();
+
+        // The merged request is indistinguishable from an inline one:
+        // the reference list is cleared, the fragment env is filled in,
+        // and the inline value wins over the fragment's.
+        assert!(req.spec_fragment_ids.is_empty());
+        let env = &req.OCI.as_ref().unwrap().Process.as_ref().unwrap().Env;
+        assert_eq!(env, &vec!["A=inline".to_string(), "B=2".to_string()]);
+
+        // Referencing an unregistered fragment is an error.
+        let mut req = protocols::agent::CreateContainerRequest::new();
+        req.OCI = MessageField::some(protocols::oci::Spec::new());
+        req.spec_fragment_ids = vec!["unknown".to_string()];
+        assert!(merge_spec_fragments(&sandbox, &mut req).is_err());
+    }
+
+    #[test]
+    fn test_interface_sysctl_path() {
+        assert_eq!(
+            interface_sysctl_path("net.ipv4.conf.eth1.accept_local", "eth1"),
+            Some("/proc/sys/net/ipv4/conf/eth1/accept_local".to_string())
+        );
+        assert_eq!(
+            interface_sysctl_path("net.ipv6.conf.eth0.100.disable_ipv6", "eth0.100"),
+            Some("/proc/sys/net/ipv6/conf/eth0.100/disable_ipv6".to_string())
+        );
+
+        // Wrong interface, sandbox-wide knob, or trailing garbage.
+        assert_eq!(
+            interface_sysctl_path("net.ipv4.conf.eth0.accept_local", "eth1"),
+            None
+        );
+        assert_eq!(interface_sysctl_path("net.ipv4.ip_forward", "eth1"), None);
+        assert_eq!(interface_sysctl_path("net.ipv4.conf.eth1.", "eth1"), None);
+    }
+
     #[tokio::test]
     async fn test_add_arp_neighbors() {
         let logger = slog::Logger::root(slog::Discard, o!());
@@ -3278,4 +4845,77 @@ COMMIT
             assert_eq!(d.result, result, "{}", msg);
         }
     }
+
+    #[test]
+    fn test_huge_page_size_bytes() {
+        assert_eq!(huge_page_size_bytes("64KB").unwrap(), 64 * 1024);
+        assert_eq!(huge_page_size_bytes("2MB").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(huge_page_size_bytes("1GB").unwrap(), 1024 * 1024 * 1024);
+
+        assert!(huge_page_size_bytes("2").is_err());
+        assert!(huge_page_size_bytes("2mb").is_err());
+        assert!(huge_page_size_bytes("MB").is_err());
+        assert!(huge_page_size_bytes("0MB").is_err());
+        assert!(huge_page_size_bytes("-2MB").is_err());
+    }
+
+    #[test]
+    fn test_filesystem_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        let usage = filesystem_usage(path).unwrap();
+        assert_eq!(usage.mount_point, path);
+        assert!(usage.total_bytes > 0);
+        assert!(usage.total_inodes > 0);
+        assert!(usage.used_bytes <= usage.total_bytes);
+        assert!(usage.used_inodes <= usage.total_inodes);
+
+        assert!(filesystem_usage("/does/not/exist").is_err());
+    }
+
+    #[test]
+    fn test_do_resize_volume() {
+        // A missing mount point must fail cleanly.
+        assert!(do_resize_volume("/does/not/exist", 1 << 30).is_err());
+
+        // Requesting a size below the current filesystem size is an
+        // online shrink, which is rejected for every filesystem type.
+        let dir = tempfile::tempdir().unwrap();
+        assert!(do_resize_volume(dir.path().to_str().unwrap(), 0).is_err());
+    }
+
+    #[test]
+    fn test_decompress_payload() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(100);
+
+        // An empty compression field means the payload is passed through.
+        let plain = decompress_payload("", &data).unwrap();
+        assert_eq!(plain.as_ref(), data.as_slice());
+
+        let compressed = zstd::stream::encode_all(data.as_slice(), 0).unwrap();
+        assert!(compressed.len() < data.len());
+        let decompressed = decompress_payload("zstd", &compressed).unwrap();
+        assert_eq!(decompressed.as_ref(), data.as_slice());
+
+        // Corrupt frames and unknown algorithms must be rejected.
+        assert!(decompress_payload("zstd", &data).is_err());
+        assert!(decompress_payload("lz4", &compressed).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_name() {
+        assert_eq!(
+            endpoint_name(&protocols::agent::ExecProcessRequest::new()),
+            "ExecProcess"
+        );
+        assert_eq!(
+            endpoint_name(&protocols::agent::CopyFileRequest::new()),
+            "CopyFile"
+        );
+        assert_eq!(
+            endpoint_name(&protocols::health::CheckRequest::new()),
+            "Check"
+        );
+    }
 }
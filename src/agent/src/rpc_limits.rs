@@ -0,0 +1,137 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Concurrency and rate limits for expensive agent RPCs.
+//!
+//! The agent usually serves a guest with very few vCPUs, so a misbehaving
+//! or overly eager client can starve running workloads simply by issuing
+//! many heavyweight requests (container creation, file copies) at once.
+//! This module provides a small throttling layer in front of those
+//! handlers: a semaphore caps how many run concurrently and a token
+//! bucket rejects callers that burst past the configured request rate.
+//! Both limits default to disabled.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::AGENT_CONFIG;
+
+pub const ERR_RPC_RATE_LIMITED: &str = "Expensive request rate limit for this sandbox exceeded";
+
+/// Continuously refilling token bucket: each request takes one token and
+/// tokens come back at `rate` per second, with at most one second of
+/// burst capacity.
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    rate: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32) -> Self {
+        TokenBucket {
+            capacity: rate as f64,
+            tokens: rate as f64,
+            rate: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+lazy_static! {
+    // A zero limit keeps the throttle disabled, matching the other agent
+    // resource ceilings.
+    static ref EXPENSIVE_PERMITS: Option<std::sync::Arc<Semaphore>> = {
+        let limit = AGENT_CONFIG.rpc_concurrency_limit;
+        if limit > 0 {
+            Some(std::sync::Arc::new(Semaphore::new(limit as usize)))
+        } else {
+            None
+        }
+    };
+    static ref RATE_BUCKET: Option<Mutex<TokenBucket>> = {
+        let rate = AGENT_CONFIG.rpc_rate_limit;
+        if rate > 0 {
+            Some(Mutex::new(TokenBucket::new(rate)))
+        } else {
+            None
+        }
+    };
+}
+
+/// Throttle an expensive RPC. Callers over the configured request rate
+/// are rejected right away; otherwise the call waits for one of the
+/// concurrency permits and holds it until the returned guard is dropped,
+/// i.e. for the duration of the handler.
+pub(crate) async fn throttle_expensive() -> Result<Option<OwnedSemaphorePermit>> {
+    if let Some(bucket) = RATE_BUCKET.as_ref() {
+        let allowed = bucket
+            .lock()
+            .map(|mut b| b.try_take())
+            .map_err(|e| anyhow!("rpc rate limiter lock poisoned: {:?}", e))?;
+        if !allowed {
+            return Err(anyhow!(ERR_RPC_RATE_LIMITED));
+        }
+    }
+
+    match EXPENSIVE_PERMITS.as_ref() {
+        // The semaphore is never closed, so acquire can only fail if it
+        // were; treat that as a hard error.
+        Some(sem) => Ok(Some(sem.clone().acquire_owned().await?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(2);
+
+        // The full burst capacity is available up front.
+        assert!(bucket.try_take());
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+
+        // Refill is continuous: after half a second at 2 tokens/s one
+        // token is back.
+        bucket.last_refill = Instant::now() - std::time::Duration::from_millis(600);
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+
+    #[test]
+    fn test_token_bucket_caps_burst() {
+        let mut bucket = TokenBucket::new(1);
+        assert!(bucket.try_take());
+
+        // A long idle period does not accumulate more than one second
+        // worth of burst.
+        bucket.last_refill = Instant::now() - std::time::Duration::from_secs(60);
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+}
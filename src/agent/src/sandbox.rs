@@ -16,19 +16,24 @@ use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use std::{thread, time};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use kata_types::cpu::CpuSet;
 use kata_types::mount::StorageDevice;
 use libc::{pid_t, syscall};
 use nix::fcntl::{self, OFlag};
 use nix::sched::{setns, unshare, CloneFlags};
+use nix::sys::signal::{kill, Signal};
 use nix::sys::stat::Mode;
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{fork, ForkResult, Pid};
 use oci::{Hook, Hooks};
 use oci_spec::runtime as oci;
 use protocols::agent::{OnlineCPUMemRequest, SharedMount};
 use regex::Regex;
+use runtime_spec::ContainerState;
 use rustjail::cgroups::{self as rustjail_cgroups, DevicesCgroupInfo};
 use rustjail::container::BaseContainer;
+use rustjail::container::Container;
 use rustjail::container::LinuxContainer;
 use rustjail::process::Process;
 use slog::Logger;
@@ -46,8 +51,12 @@ use crate::pci;
 use crate::storage::StorageDeviceGeneric;
 use crate::uevent::{Uevent, UeventMatcher};
 use crate::watcher::BindWatcher;
+use crate::AGENT_CONFIG;
 
 pub const ERR_INVALID_CONTAINER_ID: &str = "Invalid container id";
+pub const ERR_MAX_CONTAINERS_REACHED: &str = "Maximum container count for this sandbox reached";
+pub const ERR_MAX_EXEC_SESSIONS_REACHED: &str =
+    "Maximum exec session count for this sandbox reached";
 
 type UeventWatcher = (Box<dyn UeventMatcher>, oneshot::Sender<Uevent>);
 
@@ -104,11 +113,29 @@ pub struct Sandbox {
     pub network: Network,
     pub mounts: Vec<String>,
     pub container_mounts: HashMap<String, Vec<String>>,
+    // Sandbox-level OCI spec fragments registered through AddSpecFragment,
+    // merged into container specs that reference them by id.
+    pub spec_fragments: HashMap<String, protocols::agent::AddSpecFragmentRequest>,
+    // Size in bytes of the dedicated /dev/shm tmpfs of each container that
+    // requested one through the shm size annotation.
+    pub shm_sizes: HashMap<String, u64>,
+    // Idmapped mount points created for the host-shared volumes of each
+    // userns-enabled container, unmounted on container removal.
+    pub idmapped_mounts: HashMap<String, Vec<String>>,
+    // Pids of the fuse daemons spawned for each container's storages,
+    // moved into the container cgroup once it exists.
+    pub fuse_daemon_pids: HashMap<String, Vec<i32>>,
     pub uevent_map: HashMap<String, Uevent>,
     pub uevent_watchers: Vec<Option<UeventWatcher>>,
     pub shared_utsns: Namespace,
     pub shared_ipcns: Namespace,
     pub sandbox_pidns: Option<Namespace>,
+    // Pid of the pause process whose pid namespace is the sandbox-level
+    // shared pid namespace, killed when the sandbox is destroyed.
+    pub pause_pid: Option<pid_t>,
+    // Ids of the containers frozen by quiesce_containers(), so that
+    // thaw_containers() only resumes what the quiesce froze.
+    pub quiesced_containers: Vec<String>,
     pub storages: HashMap<String, StorageState>,
     pub running: bool,
     pub no_pivot_root: bool,
@@ -119,6 +146,10 @@ pub struct Sandbox {
     pub event_tx: Option<Sender<String>>,
     pub bind_watcher: BindWatcher,
     pub pcimap: HashMap<pci::Address, pci::Address>,
+    /// Guest PCI path of each hotplugged secondary network interface,
+    /// keyed by interface name. Entries are added by AddNetwork and
+    /// dropped again by RemoveNetwork.
+    pub network_pci_map: HashMap<String, pci::Path>,
     pub devcg_info: Arc<RwLock<DevicesCgroupInfo>>,
 }
 
@@ -138,11 +169,17 @@ impl Sandbox {
             containers: HashMap::new(),
             mounts: Vec::new(),
             container_mounts: HashMap::new(),
+            spec_fragments: HashMap::new(),
+            shm_sizes: HashMap::new(),
+            idmapped_mounts: HashMap::new(),
+            fuse_daemon_pids: HashMap::new(),
             uevent_map: HashMap::new(),
             uevent_watchers: Vec::new(),
             shared_utsns: Namespace::new(&logger),
             shared_ipcns: Namespace::new(&logger),
             sandbox_pidns: None,
+            pause_pid: None,
+            quiesced_containers: Vec::new(),
             storages: HashMap::new(),
             running: false,
             no_pivot_root: fs_type.eq(TYPE_ROOTFS),
@@ -153,6 +190,7 @@ impl Sandbox {
             event_tx: Some(tx),
             bind_watcher: BindWatcher::new(),
             pcimap: HashMap::new(),
+            network_pci_map: HashMap::new(),
             devcg_info: Arc::new(RwLock::new(DevicesCgroupInfo::default())),
         })
     }
@@ -230,6 +268,24 @@ impl Sandbox {
         Ok(true)
     }
 
+    /// Create the pause process whose pid namespace every container of
+    /// the sandbox joins, as Kubernetes shareProcessNamespace requires.
+    /// The pause process is pid 1 of the shared namespace, so containers
+    /// come and go independently and orphans reparented to it are reaped
+    /// instead of accumulating as zombies.
+    #[instrument]
+    pub fn setup_sandbox_pidns(&mut self) -> Result<()> {
+        let pause_pid = spawn_pause_process().context("spawn pause process")?;
+
+        let mut pid_ns = Namespace::new(&self.logger).get_pid();
+        pid_ns.path = format!("/proc/{}/ns/pid", pause_pid);
+
+        self.sandbox_pidns = Some(pid_ns);
+        self.pause_pid = Some(pause_pid);
+
+        Ok(())
+    }
+
     #[instrument]
     pub fn update_shared_pidns(&mut self, c: &LinuxContainer) -> Result<()> {
         // Populate the shared pid path only if this is an infra container and
@@ -258,10 +314,88 @@ impl Sandbox {
         self.containers.insert(c.id.clone(), c);
     }
 
+    /// Fail if creating one more container would exceed the configured
+    /// `agent.max_containers` ceiling. A zero limit disables the check.
+    pub fn ensure_container_capacity(&self) -> Result<()> {
+        let limit = AGENT_CONFIG.max_containers;
+        if limit > 0 && self.containers.len() as u32 >= limit {
+            return Err(anyhow!("{}: {}", ERR_MAX_CONTAINERS_REACHED, limit));
+        }
+        Ok(())
+    }
+
+    /// Fail if starting one more exec session would exceed the configured
+    /// `agent.max_exec_sessions` ceiling. Each container owns one init
+    /// process, so anything beyond that counts as an exec session.
+    pub fn ensure_exec_capacity(&self) -> Result<()> {
+        let limit = AGENT_CONFIG.max_exec_sessions;
+        if limit == 0 {
+            return Ok(());
+        }
+        let execs: usize = self
+            .containers
+            .values()
+            .map(|c| c.processes.len().saturating_sub(1))
+            .sum();
+        if execs as u32 >= limit {
+            return Err(anyhow!("{}: {}", ERR_MAX_EXEC_SESSIONS_REACHED, limit));
+        }
+        Ok(())
+    }
+
     pub fn get_container(&mut self, id: &str) -> Option<&mut LinuxContainer> {
         self.containers.get_mut(id)
     }
 
+    /// Freeze every created or running container ahead of a host-side VM
+    /// pause or snapshot, so guest state stops changing while it is taken.
+    /// On failure nothing is left half-frozen: containers frozen so far
+    /// are thawed again before the error is returned.
+    #[instrument]
+    pub fn quiesce_containers(&mut self) -> Result<()> {
+        let candidates: Vec<String> = self
+            .containers
+            .iter()
+            .filter(|(_, ctr)| {
+                matches!(
+                    ctr.status(),
+                    ContainerState::Running | ContainerState::Created
+                )
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut frozen: Vec<String> = Vec::new();
+        for id in candidates {
+            let result = match self.containers.get_mut(&id) {
+                Some(ctr) => ctr.pause(),
+                None => continue,
+            };
+            if let Err(e) = result {
+                for frozen_id in frozen {
+                    if let Some(ctr) = self.containers.get_mut(&frozen_id) {
+                        let _ = ctr.resume();
+                    }
+                }
+                return Err(e).context(format!("freeze container {}", id));
+            }
+            frozen.push(id);
+        }
+        self.quiesced_containers = frozen;
+        Ok(())
+    }
+
+    /// Thaw the containers frozen by quiesce_containers().
+    #[instrument]
+    pub fn thaw_containers(&mut self) -> Result<()> {
+        for id in std::mem::take(&mut self.quiesced_containers) {
+            if let Some(ctr) = self.containers.get_mut(&id) {
+                ctr.resume().context(format!("thaw container {}", id))?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn find_container_by_name(&self, name: &str) -> Option<&LinuxContainer> {
         self.containers
             .values()
@@ -278,6 +412,16 @@ impl Sandbox {
         None
     }
 
+    pub fn find_process_with_cid(&mut self, pid: pid_t) -> Option<(String, &mut Process)> {
+        for (id, c) in self.containers.iter_mut() {
+            if let Some(p) = c.processes.get_mut(&pid) {
+                return Some((id.clone(), p));
+            }
+        }
+
+        None
+    }
+
     pub fn find_container_process(&mut self, cid: &str, eid: &str) -> Result<&mut Process> {
         let ctr = self
             .get_container(cid)
@@ -298,6 +442,15 @@ impl Sandbox {
         for ctr in self.containers.values_mut() {
             ctr.destroy().await?;
         }
+
+        // Tearing down pid 1 collapses the shared pid namespace. The
+        // pause process was reparented away from us when its spawning
+        // child exited, so reaping it is best effort.
+        if let Some(pid) = self.pause_pid.take() {
+            let _ = kill(Pid::from_raw(pid), Signal::SIGKILL);
+            let _ = waitpid(Pid::from_raw(pid), None);
+        }
+
         Ok(())
     }
 
@@ -306,6 +459,10 @@ impl Sandbox {
         if req.nb_cpus > 0 {
             // online cpus
             online_cpus(&self.logger, req.nb_cpus as i32).context("online cpus")?;
+
+            // Report the topology containers will now discover and flag
+            // anything that would mislead their thread-pool sizing.
+            crate::cpu_topology::check_after_hotplug(&self.logger, req.nb_cpus as i32);
         }
 
         if !req.cpu_only {
@@ -318,6 +475,7 @@ impl Sandbox {
         }
 
         let guest_cpuset = rustjail_cgroups::fs::get_guest_cpuset()?;
+        let guest_memset = rustjail_cgroups::fs::get_guest_memset()?;
 
         for (_, ctr) in self.containers.iter() {
             match ctr
@@ -331,8 +489,11 @@ impl Sandbox {
             {
                 Some(cpu_set) => {
                     info!(self.logger, "updating {}", ctr.id.as_str());
-                    ctr.cgroup_manager
-                        .update_cpuset_path(guest_cpuset.as_str(), cpu_set)?;
+                    ctr.cgroup_manager.update_cpuset_path(
+                        guest_cpuset.as_str(),
+                        guest_memset.as_str(),
+                        cpu_set,
+                    )?;
                 }
                 None => continue,
             }
@@ -341,6 +502,50 @@ impl Sandbox {
         Ok(())
     }
 
+    /// Offline vCPUs ahead of a hot-unplug request from the runtime and
+    /// refresh the containers' cpuset cgroups so they stop referencing
+    /// CPUs about to go away. Returns the number of CPUs offlined.
+    #[instrument]
+    pub fn offline_cpus(&self, num: i32) -> Result<i32> {
+        let offlined = offline_cpus(&self.logger, num).context("offline cpus")?;
+        if offlined == 0 {
+            return Ok(0);
+        }
+
+        let guest_cpuset = rustjail_cgroups::fs::get_guest_cpuset()?;
+        let guest_memset = rustjail_cgroups::fs::get_guest_memset()?;
+        for (_, ctr) in self.containers.iter() {
+            match ctr
+                .config
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.linux().as_ref())
+                .and_then(|linux| linux.resources().as_ref())
+                .and_then(|resources| resources.cpu().as_ref())
+                .and_then(|cpus| cpus.cpus().as_ref())
+            {
+                Some(cpu_set) => {
+                    info!(self.logger, "updating {}", ctr.id.as_str());
+                    ctr.cgroup_manager.update_cpuset_path(
+                        guest_cpuset.as_str(),
+                        guest_memset.as_str(),
+                        cpu_set,
+                    )?;
+                }
+                None => continue,
+            }
+        }
+
+        Ok(offlined)
+    }
+
+    /// Offline memory blocks ahead of a hot-remove request from the
+    /// runtime, returning the number of bytes actually reclaimed.
+    #[instrument]
+    pub fn offline_memory(&self, size: u64) -> Result<u64> {
+        offline_memory(&self.logger, size).context("offline memory")
+    }
+
     #[instrument]
     pub fn add_hooks(&mut self, dir: &str) -> Result<()> {
         let mut hooks = Hooks::default();
@@ -573,7 +778,14 @@ impl Sandbox {
 }
 
 #[instrument]
-fn online_resources(logger: &Logger, path: &str, pattern: &str, num: i32) -> Result<i32> {
+fn online_resources(
+    logger: &Logger,
+    path: &str,
+    pattern: &str,
+    num: i32,
+    online_file: &str,
+    online_value: &str,
+) -> Result<i32> {
     let mut count = 0;
     let re = Regex::new(pattern)?;
 
@@ -592,7 +804,10 @@ fn online_resources(logger: &Logger, path: &str, pattern: &str, num: i32) -> Res
         let p = entry.path().join(SYSFS_ONLINE_FILE);
         if let Ok(c) = fs::read_to_string(&p) {
             // Try to online the object in offline state.
-            if c.trim().contains('0') && fs::write(&p, "1").is_ok() && num > 0 {
+            if c.trim().contains('0')
+                && fs::write(entry.path().join(online_file), online_value).is_ok()
+                && num > 0
+            {
                 count += 1;
                 if count == num {
                     break;
@@ -606,11 +821,150 @@ fn online_resources(logger: &Logger, path: &str, pattern: &str, num: i32) -> Res
 
 #[instrument]
 fn online_memory(logger: &Logger) -> Result<()> {
-    online_resources(logger, SYSFS_MEMORY_ONLINE_PATH, r"memory[0-9]+", -1)
-        .context("online memory resource")?;
+    // Hot-added blocks are onlined into ZONE_MOVABLE when requested, so
+    // that their pages stay migratable and the blocks can be offlined
+    // again on memory hot-remove.
+    let (online_file, online_value) = if AGENT_CONFIG.memory_online_movable {
+        (SYSFS_MEMORY_STATE_FILE, "online_movable")
+    } else {
+        (SYSFS_ONLINE_FILE, "1")
+    };
+    online_resources(
+        logger,
+        SYSFS_MEMORY_ONLINE_PATH,
+        r"memory[0-9]+",
+        -1,
+        online_file,
+        online_value,
+    )
+    .context("online memory resource")?;
     Ok(())
 }
 
+/// Offline up to `num` vCPUs ahead of a hot-unplug request, walking from
+/// the highest-numbered CPU down. The boot CPU (cpu0) is never offlined.
+/// Returns the number of CPUs actually offlined: a CPU whose tasks cannot
+/// be migrated is skipped rather than failing the whole request.
+#[instrument]
+fn offline_cpus(logger: &Logger, num: i32) -> Result<i32> {
+    let re = Regex::new(r"^cpu(\d+)$")?;
+    let mut cpus = Vec::new();
+    for e in fs::read_dir(SYSFS_CPU_PATH)? {
+        let entry = e?;
+        if let Some(name) = entry.file_name().to_str() {
+            if let Some(caps) = re.captures(name) {
+                let index: u64 = caps[1].parse()?;
+                // cpu0 cannot be offlined on most architectures, and
+                // taking the boot CPU away is never what we want anyway.
+                if index == 0 {
+                    continue;
+                }
+                cpus.push((index, entry.path()));
+            }
+        }
+    }
+    cpus.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut offlined = 0;
+    for (_, path) in cpus {
+        if offlined >= num {
+            break;
+        }
+
+        let p = path.join(SYSFS_ONLINE_FILE);
+        match fs::read_to_string(&p) {
+            Ok(c) if c.trim() == "1" => {
+                if let Err(e) = fs::write(&p, "0") {
+                    info!(logger, "cannot offline {}: {}", path.display(), e);
+                    continue;
+                }
+                offlined += 1;
+            }
+            _ => continue,
+        }
+    }
+
+    info!(logger, "offlined {} CPU(s)", offlined);
+    Ok(offlined)
+}
+
+/// Read the size of one hotpluggable memory block, in bytes.
+fn memory_block_size() -> Result<u64> {
+    let content =
+        fs::read_to_string(SYSFS_MEMORY_BLOCK_SIZE_PATH).context("read memory block size file")?;
+    let size = u64::from_str_radix(content.trim(), 16).context("parse memory block size")?;
+    ensure!(size > 0, "memory block size is zero");
+    Ok(size)
+}
+
+/// Offline up to `size` bytes of memory so the corresponding blocks can be
+/// removed by the VMM (ACPI eject or virtio-mem shrink). Blocks are walked
+/// from the highest number down, since the most recently hot-added memory
+/// sits at the top and is the most likely to be in ZONE_MOVABLE. Returns
+/// the number of bytes actually offlined.
+#[instrument]
+fn offline_memory(logger: &Logger, size: u64) -> Result<u64> {
+    let block_size = memory_block_size()?;
+    let want_blocks = if size == 0 {
+        u64::MAX
+    } else {
+        size / block_size
+    };
+    if want_blocks == 0 {
+        return Ok(0);
+    }
+
+    let re = Regex::new(r"^memory(\d+)$")?;
+    let mut blocks = Vec::new();
+    for e in fs::read_dir(SYSFS_MEMORY_ONLINE_PATH)? {
+        let entry = e?;
+        if let Some(name) = entry.file_name().to_str() {
+            if let Some(caps) = re.captures(name) {
+                let index: u64 = caps[1].parse()?;
+                blocks.push((index, entry.path()));
+            }
+        }
+    }
+    blocks.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut offlined: u64 = 0;
+    for (_, path) in blocks {
+        if offlined >= want_blocks {
+            break;
+        }
+
+        // Block zero and anything non-removable (e.g. holding kernel
+        // text or bootmem) can never be offlined; skip without error.
+        if let Ok(removable) = fs::read_to_string(path.join("removable")) {
+            if removable.trim() == "0" {
+                continue;
+            }
+        }
+
+        let state_file = path.join(SYSFS_MEMORY_STATE_FILE);
+        match fs::read_to_string(&state_file) {
+            Ok(state) if state.trim() != "offline" => {
+                // Offlining fails with EBUSY when pages cannot be
+                // migrated out; try the next block in that case.
+                if let Err(e) = fs::write(&state_file, "offline") {
+                    info!(logger, "cannot offline {}: {}", path.display(), e);
+                    continue;
+                }
+                offlined += 1;
+            }
+            _ => continue,
+        }
+    }
+
+    info!(
+        logger,
+        "offlined {} memory block(s) ({} bytes)",
+        offlined,
+        offlined * block_size
+    );
+    Ok(offlined * block_size)
+}
+
 // max wait for all CPUs to online will use 50 * 100 = 5 seconds.
 const ONLINE_CPUMEM_WAIT_MILLIS: u64 = 50;
 const ONLINE_CPUMEM_MAX_RETRIES: i32 = 100;
@@ -631,6 +985,8 @@ fn online_cpus(logger: &Logger, num: i32) -> Result<i32> {
             SYSFS_CPU_PATH,
             r"cpu[0-9]+",
             num - onlined_cpu_count,
+            SYSFS_ONLINE_FILE,
+            "1",
         )
         .context("online cpu resource")?;
 
@@ -659,6 +1015,71 @@ fn onlined_cpus() -> Result<i32> {
     Ok(online_cpu_set.len() as i32)
 }
 
+// Fork an intermediate child that unshares the pid namespace - which
+// only takes effect for its children - forks the pause process as pid 1
+// of the new namespace, reports that pid (as seen from the agent's
+// namespace) over a pipe and exits.
+fn spawn_pause_process() -> Result<pid_t> {
+    use std::io::Read;
+
+    let (read_fd, write_fd) = nix::unistd::pipe()?;
+
+    match unsafe { fork()? } {
+        ForkResult::Parent { child } => {
+            let _ = nix::unistd::close(write_fd);
+
+            let mut buf = [0u8; 4];
+            let mut pipe = unsafe { fs::File::from_raw_fd(read_fd) };
+            pipe.read_exact(&mut buf)
+                .context("read pause process pid")?;
+            let _ = waitpid(child, None);
+
+            let pause_pid = pid_t::from_ne_bytes(buf);
+            if pause_pid <= 0 {
+                return Err(anyhow!("failed to create pause process"));
+            }
+            Ok(pause_pid)
+        }
+        ForkResult::Child => {
+            // The agent is threaded, so only async-signal-safe calls are
+            // allowed between here and _exit.
+            let _ = nix::unistd::close(read_fd);
+            let pid = unshare(CloneFlags::CLONE_NEWPID)
+                .map_err(|_| ())
+                .and_then(|_| match unsafe { fork() } {
+                    Ok(ForkResult::Parent { child }) => Ok(child.as_raw()),
+                    Ok(ForkResult::Child) => pause_process_main(),
+                    Err(_) => Err(()),
+                })
+                .unwrap_or(-1);
+            let _ = nix::unistd::write(write_fd, &pid.to_ne_bytes());
+            unsafe { libc::_exit(0) }
+        }
+    }
+}
+
+extern "C" fn pause_sigchld_handler(_: libc::c_int) {}
+
+// Pid 1 of the shared pid namespace: reap whatever is reparented to us
+// and otherwise sleep. The process is torn down with SIGKILL from
+// Sandbox::destroy - ordinary signals cannot kill the init of a pid
+// namespace from outside.
+fn pause_process_main() -> std::result::Result<pid_t, ()> {
+    // pause() only returns for handled signals, and the default action
+    // of SIGCHLD is to ignore it.
+    unsafe {
+        libc::signal(libc::SIGCHLD, pause_sigchld_handler as libc::sighandler_t);
+    }
+    loop {
+        while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+            if matches!(status, WaitStatus::StillAlive) {
+                break;
+            }
+        }
+        unsafe { libc::pause() };
+    }
+}
+
 #[cfg(test)]
 #[allow(dead_code)]
 #[allow(unused_imports)]
@@ -960,6 +1381,30 @@ mod tests {
         assert_eq!(s.sandbox_pidns.unwrap().path, ns_path);
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn setup_sandbox_pidns() {
+        skip_if_not_root!();
+
+        let logger = slog::Logger::root(slog::Discard, o!());
+        let mut s = Sandbox::new(&logger).unwrap();
+
+        s.setup_sandbox_pidns().unwrap();
+
+        let pause_pid = s.pause_pid.expect("pause process pid");
+        let ns_path = format!("/proc/{}/ns/pid", pause_pid);
+        assert_eq!(s.sandbox_pidns.as_ref().unwrap().path, ns_path);
+        assert!(Path::new(&ns_path).exists());
+
+        // The pause pid namespace is distinct from ours.
+        let own_ns = fs::read_link("/proc/self/ns/pid").unwrap();
+        let pause_ns = fs::read_link(&ns_path).unwrap();
+        assert_ne!(own_ns, pause_ns);
+
+        s.destroy().await.unwrap();
+        assert!(s.pause_pid.is_none());
+    }
+
     #[tokio::test]
     #[serial]
     async fn add_guest_hooks() {
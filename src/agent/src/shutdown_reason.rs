@@ -0,0 +1,184 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Structured shutdown reason reporting.
+//!
+//! When the agent process dies the shim only sees the connection drop and
+//! cannot tell a guest kernel problem from an agent bug. This module
+//! records a one-line JSON reason record on the way down - from the panic
+//! hook, from a configuration parsing failure or from `main` returning -
+//! and delivers it over a dedicated vsock port the shim listens on, plus
+//! the pstore pmsg device so the reason survives a guest reboot where the
+//! platform provides persistent storage. A hard kill (e.g. the kernel OOM
+//! killer) leaves no record at all, which the shim reports as such: the
+//! absence of a record is itself the signal.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nix::sys::socket::{self, AddressFamily, SockFlag, SockType, VsockAddr};
+
+use crate::version;
+
+/// The pstore pmsg front end; writes end up as pmsg-ramoops records when
+/// the platform has pstore backing, and fail silently otherwise.
+const PSTORE_PMSG_PATH: &str = "/dev/pmsg0";
+
+/// Why the agent went down, roughly ordered from "our bug" to "expected".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownKind {
+    /// The agent panicked.
+    Panic,
+    /// The agent configuration could not be parsed.
+    ConfigError,
+    /// `real_main` returned an error.
+    Error,
+    /// The agent exited cleanly.
+    Clean,
+}
+
+impl ShutdownKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ShutdownKind::Panic => "panic",
+            ShutdownKind::ConfigError => "config-error",
+            ShutdownKind::Error => "error",
+            ShutdownKind::Clean => "clean",
+        }
+    }
+}
+
+lazy_static! {
+    // Connection accepted from the shim on the shutdown reason vport.
+    static ref REASON_SINK: Mutex<Option<File>> = Mutex::new(None);
+}
+
+// Only the first record describes the root cause: a panic during shutdown
+// of an already failed agent must not overwrite the original reason.
+static RECORDED: AtomicBool = AtomicBool::new(false);
+
+/// Accept shim connections on the shutdown reason vport. The shim connects
+/// right after boot and keeps the connection open, so a record written at
+/// exit time needs no handshake. Runs on a plain thread: the tokio runtime
+/// is already gone when the process is unwinding.
+pub fn start_listener(vport: u32) {
+    std::thread::spawn(move || {
+        let listenfd = match socket::socket(
+            AddressFamily::Vsock,
+            SockType::Stream,
+            SockFlag::SOCK_CLOEXEC,
+            None,
+        ) {
+            Ok(fd) => fd,
+            Err(e) => {
+                eprintln!("failed to create shutdown reason socket: {}", e);
+                return;
+            }
+        };
+
+        let addr = VsockAddr::new(libc::VMADDR_CID_ANY, vport);
+        if let Err(e) = socket::bind(listenfd, &addr).and_then(|_| socket::listen(listenfd, 1)) {
+            eprintln!("failed to listen on shutdown reason vport {}: {}", vport, e);
+            return;
+        }
+
+        // Keep accepting so the shim can reconnect; the latest connection
+        // replaces the previous one.
+        loop {
+            match socket::accept(listenfd) {
+                Ok(fd) => {
+                    let stream = unsafe { File::from_raw_fd(fd) };
+                    if let Ok(mut sink) = REASON_SINK.lock() {
+                        *sink = Some(stream);
+                    }
+                }
+                Err(_) => return,
+            }
+        }
+    });
+}
+
+/// Write the shutdown reason record to the shim connection and to pstore.
+/// Best effort on both paths: reporting why the agent dies must never be
+/// the reason it dies.
+pub fn record(kind: ShutdownKind, message: &str) {
+    if RECORDED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let record = serde_json::json!({
+        "kind": kind.as_str(),
+        "message": message,
+        "agent-version": version::AGENT_VERSION,
+        "pid": std::process::id(),
+        "timestamp": timestamp,
+    });
+    let mut line = record.to_string();
+    line.push('\n');
+
+    if let Ok(mut pmsg) = OpenOptions::new().write(true).open(PSTORE_PMSG_PATH) {
+        let _ = pmsg.write_all(line.as_bytes());
+    }
+
+    if let Ok(mut sink) = REASON_SINK.lock() {
+        if let Some(stream) = sink.as_mut() {
+            let _ = stream.write_all(line.as_bytes());
+            let _ = stream.flush();
+        }
+    }
+}
+
+/// Chain a panic hook that records the panic as the shutdown reason before
+/// the default hook prints the backtrace.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            (*s).to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+        let message = match info.location() {
+            Some(location) => format!("{} at {}", payload, location),
+            None => payload,
+        };
+        record(ShutdownKind::Panic, &message);
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown_kind_str() {
+        assert_eq!(ShutdownKind::Panic.as_str(), "panic");
+        assert_eq!(ShutdownKind::ConfigError.as_str(), "config-error");
+        assert_eq!(ShutdownKind::Error.as_str(), "error");
+        assert_eq!(ShutdownKind::Clean.as_str(), "clean");
+    }
+
+    #[test]
+    fn test_record_only_once() {
+        // Reset in case another test recorded first.
+        RECORDED.store(false, Ordering::SeqCst);
+        record(ShutdownKind::Clean, "first");
+        assert!(RECORDED.load(Ordering::SeqCst));
+        // A second record must be a no-op rather than an error.
+        record(ShutdownKind::Panic, "second");
+        assert!(RECORDED.load(Ordering::SeqCst));
+    }
+}
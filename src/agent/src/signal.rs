@@ -51,13 +51,13 @@ async fn handle_sigchild(logger: Logger, sandbox: Arc<Mutex<Sandbox>>) -> Result
             let sandbox_ref = sandbox.clone();
             let mut sandbox = sandbox_ref.lock().await;
 
-            let process = sandbox.find_process(raw_pid);
+            let process = sandbox.find_process_with_cid(raw_pid);
             if process.is_none() {
                 info!(logger, "child exited unexpectedly");
                 continue;
             }
 
-            let p = process.unwrap();
+            let (cid, p) = process.unwrap();
 
             let ret: i32 = match wait_status {
                 WaitStatus::Exited(_, c) => c,
@@ -77,6 +77,12 @@ async fn handle_sigchild(logger: Logger, sandbox: Arc<Mutex<Sandbox>>) -> Result
             p.exit_code = ret;
             let _ = p.exit_tx.take();
 
+            crate::exit_notifier::publish(crate::exit_notifier::ProcessExit {
+                container_id: cid,
+                exec_id: p.exec_id.clone(),
+                exit_code: ret,
+            });
+
             info!(logger, "notify term to close");
             // close the socket file to notify readStdio to close terminal specifically
             // in case this process's terminal has been inherited by its children.
@@ -96,6 +102,8 @@ pub async fn setup_signal_handler(
         .map_err(|err| anyhow!(err).context("failed to setup agent as a child subreaper"))?;
 
     let mut sigchild_stream = signal(SignalKind::child())?;
+    let mut sighup_stream = signal(SignalKind::hangup())?;
+    let mut sigusr2_stream = signal(SignalKind::user_defined2())?;
 
     loop {
         select! {
@@ -104,6 +112,24 @@ pub async fn setup_signal_handler(
                 break;
             }
 
+            // SIGHUP requests a configuration reload, e.g. after debug
+            // settings changed in the overrides file.
+            _ = sighup_stream.recv() => {
+                if let Err(e) = crate::config::reload(&logger) {
+                    error!(logger, "failed to reload configuration"; "error" => format!("{:?}", e));
+                }
+            }
+
+            // SIGUSR2 requests an in-place upgrade to a new agent
+            // binary delivered to the guest; on success this never
+            // returns.
+            _ = sigusr2_stream.recv() => {
+                if let Err(e) = crate::upgrade::trigger(&logger, &sandbox).await {
+                    error!(logger, "agent upgrade failed, continuing with current binary";
+                        "error" => format!("{:?}", e));
+                }
+            }
+
             _ = sigchild_stream.recv() => {
                 let result = handle_sigchild(logger.clone(), sandbox.clone()).await;
 
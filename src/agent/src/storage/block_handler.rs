@@ -29,7 +29,8 @@ use crate::device::block_device_handler::{
 use crate::device::nvdimm_device_handler::wait_for_pmem_device;
 use crate::device::scsi_device_handler::get_scsi_device_name;
 use crate::pci;
-use crate::storage::{common_storage_handler, new_device, StorageContext, StorageHandler};
+use crate::storage::dm_verity::common_verity_storage_handler;
+use crate::storage::{StorageContext, StorageHandler};
 
 #[derive(Debug)]
 pub struct VirtioBlkMmioHandler {}
@@ -44,7 +45,7 @@ impl StorageHandler for VirtioBlkMmioHandler {
     #[instrument]
     async fn create_device(
         &self,
-        storage: Storage,
+        mut storage: Storage,
         ctx: &mut StorageContext,
     ) -> Result<Arc<dyn StorageDevice>> {
         if !Path::new(&storage.source).exists() {
@@ -52,8 +53,7 @@ impl StorageHandler for VirtioBlkMmioHandler {
                 .await
                 .context("failed to get mmio device name")?;
         }
-        let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        common_verity_storage_handler(ctx.logger, &mut storage)
     }
 }
 
@@ -88,8 +88,7 @@ impl StorageHandler for VirtioBlkPciHandler {
             storage.source = dev_path;
         }
 
-        let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        common_verity_storage_handler(ctx.logger, &mut storage)
     }
 }
 
@@ -113,8 +112,7 @@ impl StorageHandler for VirtioBlkCcwHandler {
         let ccw_device = ccw::Device::from_str(&storage.source)?;
         let dev_path = get_virtio_blk_ccw_device_name(ctx.sandbox, &ccw_device).await?;
         storage.source = dev_path;
-        let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        common_verity_storage_handler(ctx.logger, &mut storage)
     }
 
     #[cfg(not(target_arch = "s390x"))]
@@ -148,8 +146,7 @@ impl StorageHandler for ScsiHandler {
         let dev_path = get_scsi_device_name(ctx.sandbox, &storage.source).await?;
         storage.source = dev_path;
 
-        let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        common_verity_storage_handler(ctx.logger, &mut storage)
     }
 }
 
@@ -166,13 +163,12 @@ impl StorageHandler for PmemHandler {
     #[instrument]
     async fn create_device(
         &self,
-        storage: Storage,
+        mut storage: Storage,
         ctx: &mut StorageContext,
     ) -> Result<Arc<dyn StorageDevice>> {
         // Retrieve the device for pmem storage
         wait_for_pmem_device(ctx.sandbox, &storage.source).await?;
 
-        let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        common_verity_storage_handler(ctx.logger, &mut storage)
     }
 }
@@ -0,0 +1,258 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Composefs-based container rootfs.
+//!
+//! A composefs image is an EROFS metadata-only filesystem whose regular
+//! files redirect into a content-addressed objects directory. The host
+//! shares both pieces through virtio-fs: the storage source is the EROFS
+//! image file and a driver option points at the objects directory. The
+//! handler loop-mounts the metadata image and combines it with the objects
+//! directory through an overlayfs data-only lower layer, so identical
+//! files are stored once across all images on the node. When the image
+//! carries fs-verity metadata the EROFS file itself is measured against
+//! the expected digest and overlayfs is told to require verity on every
+//! object file.
+
+use std::fs;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use kata_types::device::DRIVER_COMPOSEFS_TYPE;
+use kata_types::mount::StorageDevice;
+use nix::mount::MsFlags;
+use protocols::agent::Storage;
+use slog::Logger;
+use tracing::instrument;
+
+use crate::mount::{baremount, is_mounted, remove_mounts};
+use crate::storage::loopdev::{attach_loop_device, detach_loop_device};
+use crate::storage::{parse_options, StorageContext, StorageDeviceGeneric, StorageHandler};
+
+/// Driver option carrying the guest path of the objects directory.
+const COMPOSEFS_OPT_OBJECTS: &str = "composefs.objects";
+/// Driver option carrying the expected fs-verity digest of the EROFS image.
+const COMPOSEFS_OPT_VERITY: &str = "composefs.verity";
+
+// fs-verity digest algorithms from the uapi, only sha256/sha512 exist.
+const FS_VERITY_HASH_ALG_SHA256: u16 = 1;
+const FS_VERITY_HASH_ALG_SHA512: u16 = 2;
+const FS_VERITY_MAX_DIGEST_SIZE: u16 = 64;
+
+nix::ioctl_readwrite!(ioctl_fs_measure_verity, b'f', 134, FsVerityDigest);
+
+/// Fixed-size layout of `struct fsverity_digest` from the fs-verity uapi.
+#[repr(C)]
+struct FsVerityDigest {
+    digest_algorithm: u16,
+    digest_size: u16,
+    digest: [u8; FS_VERITY_MAX_DIGEST_SIZE as usize],
+}
+
+/// A mounted composefs image. Cleanup unwinds the overlay mount, the
+/// EROFS metadata mount and the loop device in reverse order.
+#[derive(Debug)]
+struct ComposeFsDevice {
+    inner: StorageDeviceGeneric,
+    meta_mount: String,
+    loop_device: String,
+}
+
+impl StorageDevice for ComposeFsDevice {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        if matches!(is_mounted(&self.meta_mount), Ok(true)) {
+            remove_mounts(&[self.meta_mount.clone()])?;
+        }
+        if Path::new(&self.meta_mount).is_dir() {
+            let _ = fs::remove_dir(&self.meta_mount);
+        }
+        detach_loop_device(&self.loop_device)
+    }
+}
+
+#[derive(Debug)]
+pub struct ComposeFsHandler {}
+
+#[async_trait::async_trait]
+impl StorageHandler for ComposeFsHandler {
+    #[instrument]
+    fn driver_types(&self) -> &[&str] {
+        &[DRIVER_COMPOSEFS_TYPE]
+    }
+
+    #[instrument]
+    async fn create_device(
+        &self,
+        storage: Storage,
+        ctx: &mut StorageContext,
+    ) -> Result<Arc<dyn StorageDevice>> {
+        let opts = parse_options(&storage.driver_options);
+        let objects = opts
+            .get(COMPOSEFS_OPT_OBJECTS)
+            .ok_or_else(|| anyhow!("composefs storage without {} option", COMPOSEFS_OPT_OBJECTS))?;
+        // The objects directory rides on the regular virtio-fs share, so
+        // by the time rootfs storages are processed it must be visible.
+        if !Path::new(objects).is_dir() {
+            return Err(anyhow!("composefs objects directory {} not found", objects));
+        }
+
+        let verity_digest = opts.get(COMPOSEFS_OPT_VERITY);
+        if let Some(digest) = verity_digest {
+            measure_verity_digest(&storage.source, digest)
+                .context("verify composefs image digest")?;
+        }
+
+        let meta_mount = format!("{}.meta", storage.mount_point.trim_end_matches('/'));
+        let loop_device = attach_loop_device(&storage.source)?;
+
+        match mount_composefs(
+            ctx.logger,
+            &loop_device,
+            &meta_mount,
+            objects,
+            &storage.mount_point,
+            verity_digest.is_some(),
+        ) {
+            Ok(()) => Ok(Arc::new(ComposeFsDevice {
+                inner: StorageDeviceGeneric::new(storage.mount_point.clone()),
+                meta_mount,
+                loop_device,
+            })),
+            Err(e) => {
+                if matches!(is_mounted(&meta_mount), Ok(true)) {
+                    let _ = remove_mounts(&[meta_mount.clone()]);
+                }
+                let _ = fs::remove_dir(&meta_mount);
+                if let Err(e) = detach_loop_device(&loop_device) {
+                    warn!(ctx.logger, "failed to detach loop device: {:?}", e);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+// Mount the EROFS metadata image read-only, then overlay it with the
+// objects directory as a data-only lower layer.
+#[instrument]
+fn mount_composefs(
+    logger: &Logger,
+    loop_device: &str,
+    meta_mount: &str,
+    objects: &str,
+    mount_point: &str,
+    require_verity: bool,
+) -> Result<()> {
+    fs::create_dir_all(meta_mount)
+        .with_context(|| format!("create metadata mountpoint {}", meta_mount))?;
+    baremount(
+        Path::new(loop_device),
+        Path::new(meta_mount),
+        "erofs",
+        MsFlags::MS_RDONLY,
+        "",
+        logger,
+    )
+    .context("mount composefs metadata image")?;
+
+    // The "::" separator marks the objects directory as data-only: lookups
+    // resolve in the metadata layer while file content is read from the
+    // object the metacopy redirect points at.
+    let mut options = format!(
+        "lowerdir={}::{},redirect_dir=follow,metacopy=on",
+        meta_mount, objects
+    );
+    if require_verity {
+        options.push_str(",verity=require");
+    }
+
+    fs::create_dir_all(mount_point)
+        .with_context(|| format!("create mountpoint {}", mount_point))?;
+    baremount(
+        Path::new("overlay"),
+        Path::new(mount_point),
+        "overlay",
+        MsFlags::MS_RDONLY,
+        &options,
+        logger,
+    )
+    .context("mount composefs overlay")
+}
+
+/// Measure the fs-verity digest of `path` and compare it with the expected
+/// hex digest. Fails when the file is not fs-verity enabled or the digest
+/// does not match.
+#[instrument]
+fn measure_verity_digest(path: &str, expected: &str) -> Result<()> {
+    let file = fs::File::open(path).with_context(|| format!("open composefs image {}", path))?;
+
+    let mut digest = FsVerityDigest {
+        digest_algorithm: 0,
+        digest_size: FS_VERITY_MAX_DIGEST_SIZE,
+        digest: [0; FS_VERITY_MAX_DIGEST_SIZE as usize],
+    };
+    unsafe { ioctl_fs_measure_verity(file.as_raw_fd(), &mut digest) }
+        .map_err(|e| anyhow!("failed to measure fs-verity digest of {}: {}", path, e))?;
+
+    if !matches!(
+        digest.digest_algorithm,
+        FS_VERITY_HASH_ALG_SHA256 | FS_VERITY_HASH_ALG_SHA512
+    ) {
+        return Err(anyhow!(
+            "unsupported fs-verity digest algorithm {} for {}",
+            digest.digest_algorithm,
+            path
+        ));
+    }
+
+    let measured: String = digest.digest[..digest.digest_size as usize]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if measured != expected.to_lowercase() {
+        return Err(anyhow!(
+            "fs-verity digest mismatch for {}: measured {}, expected {}",
+            path,
+            measured,
+            expected
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composefs_options() {
+        let options = vec![
+            format!("{}=/run/kata-containers/objects", COMPOSEFS_OPT_OBJECTS),
+            format!("{}={}", COMPOSEFS_OPT_VERITY, "a".repeat(64)),
+        ];
+        let opts = parse_options(&options);
+        assert_eq!(
+            opts.get(COMPOSEFS_OPT_OBJECTS).map(|s| s.as_str()),
+            Some("/run/kata-containers/objects")
+        );
+        assert_eq!(opts.get(COMPOSEFS_OPT_VERITY), Some(&"a".repeat(64)));
+    }
+
+    #[test]
+    fn test_measure_verity_digest_plain_file() {
+        // A plain file without fs-verity enabled must be rejected.
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+        let path = tmpfile.path().to_str().unwrap();
+        assert!(measure_verity_digest(path, &"a".repeat(64)).is_err());
+    }
+}
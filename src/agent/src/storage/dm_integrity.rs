@@ -0,0 +1,332 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! dm-integrity protection for writable storages.
+//!
+//! Read-only layers are covered by dm-verity, but writable layers and
+//! disk-backed emptyDirs have no root hash to check against, so a host
+//! can silently rewrite them. When `agent.writable_layer_integrity` is
+//! set those volumes are backed by a file on guest storage, attached to
+//! a loop device and remapped through a standalone dm-integrity target
+//! whose per-sector HMAC is keyed with a secret generated inside the
+//! guest at boot. In a TEE that key only ever exists in protected guest
+//! memory, so the host cannot forge tags and any tampering with the
+//! backing data surfaces as an I/O error on the next read. Memory-backed
+//! (tmpfs) emptyDirs need no such protection: they never leave guest
+//! memory.
+
+use std::fs::{self, OpenOptions};
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use devicemapper::{DevId, DmFlags, DmName, DmOptions, DM};
+use kata_types::mount::StorageDevice;
+use nix::mount::MsFlags;
+use sha2::{Digest, Sha256};
+use slog::Logger;
+use tracing::instrument;
+
+use crate::mount::baremount;
+use crate::storage::loopdev::{attach_loop_device_rw, detach_loop_device};
+use crate::storage::StorageDeviceGeneric;
+
+const DM_INTEGRITY_TARGET_TYPE: &str = "integrity";
+const DEVICE_MAPPER_PREFIX: &str = "/dev/mapper";
+
+/// Where the backing files of integrity volumes live.
+const INTEGRITY_VOLUME_DIR: &str = "/run/kata-containers/integrity";
+
+/// Tag size of an HMAC-SHA256 per data sector.
+const INTEGRITY_TAG_SIZE: u32 = 32;
+
+const INTEGRITY_KEY_SIZE: usize = 32;
+
+/// Backing file size for volumes that carry no explicit size limit. The
+/// file is sparse, so unused space costs nothing.
+const DEFAULT_VOLUME_SIZE: u64 = 1 << 30;
+
+// The first activation only formats the superblock, so its table covers
+// a token number of sectors; the real size is loaded afterwards.
+const FORMAT_SECTORS: u64 = 8;
+
+lazy_static! {
+    // Generated fresh on every boot: writable layers do not outlive the
+    // sandbox, so there is nothing to unseal across reboots, and a key
+    // that is never stored anywhere cannot be replayed by the host.
+    static ref INTEGRITY_KEY: Vec<u8> = {
+        let mut key = vec![0u8; INTEGRITY_KEY_SIZE];
+        fs::File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(&mut key))
+            .expect("read integrity key from /dev/urandom");
+        key
+    };
+}
+
+/// A writable volume protected by dm-integrity. Cleanup unmounts the
+/// filesystem and then unwinds the integrity target, the loop device and
+/// the backing file.
+#[derive(Debug)]
+struct IntegrityVolume {
+    inner: StorageDeviceGeneric,
+    device_name: String,
+    loop_device: String,
+    backing_file: String,
+}
+
+impl StorageDevice for IntegrityVolume {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        remove_integrity_device(&self.device_name)?;
+        detach_loop_device(&self.loop_device)?;
+        fs::remove_file(&self.backing_file)
+            .with_context(|| format!("remove backing file {}", self.backing_file))
+    }
+}
+
+/// A mount stacked on top of an integrity volume (e.g. the overlay of a
+/// writable layer whose upper directory lives on one). Cleanup unwinds
+/// the mount first and then the volume beneath it.
+pub(crate) struct IntegrityBackedMount {
+    pub(crate) inner: StorageDeviceGeneric,
+    pub(crate) volume: Arc<dyn StorageDevice>,
+}
+
+impl StorageDevice for IntegrityBackedMount {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        self.volume.cleanup()
+    }
+}
+
+/// Create a dm-integrity protected volume and mount it at `mount_point`.
+/// `size` is the usable size the caller wants, defaulting to
+/// [`DEFAULT_VOLUME_SIZE`]; the tag and journal overhead is added on top.
+#[instrument]
+pub(crate) fn create_integrity_volume(
+    logger: &Logger,
+    size: Option<u64>,
+    mount_point: &str,
+) -> Result<Arc<dyn StorageDevice>> {
+    let size = size.unwrap_or(DEFAULT_VOLUME_SIZE);
+    // One name per mount point: an emptyDir reused across a container
+    // restart maps back to the same device.
+    let device_name = format!("integrity-{:x}", Sha256::digest(mount_point.as_bytes()));
+
+    fs::create_dir_all(INTEGRITY_VOLUME_DIR)?;
+    let backing_file = Path::new(INTEGRITY_VOLUME_DIR).join(format!("{}.img", device_name));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&backing_file)
+        .with_context(|| format!("create backing file {:?}", backing_file))?;
+    // Interleaved tags plus journal and superblock eat into the device,
+    // so over-provision the (sparse) backing file.
+    file.set_len(size + size / 8 + (4 << 20))?;
+    drop(file);
+
+    let backing_file = backing_file.display().to_string();
+    let loop_device = attach_loop_device_rw(&backing_file)?;
+
+    let volume = setup_integrity_volume(logger, &device_name, &loop_device, mount_point);
+    match volume {
+        Ok(inner) => Ok(Arc::new(IntegrityVolume {
+            inner,
+            device_name,
+            loop_device,
+            backing_file,
+        })),
+        Err(e) => {
+            if let Err(e) = detach_loop_device(&loop_device) {
+                warn!(logger, "failed to detach loop device: {:?}", e);
+            }
+            let _ = fs::remove_file(&backing_file);
+            Err(e)
+        }
+    }
+}
+
+// Map the loop device through dm-integrity, make a filesystem on the
+// mapped device and mount it.
+fn setup_integrity_volume(
+    logger: &Logger,
+    device_name: &str,
+    loop_device: &str,
+    mount_point: &str,
+) -> Result<StorageDeviceGeneric> {
+    let mapped = create_integrity_device(logger, device_name, loop_device)?;
+
+    let result = mkfs(&mapped).and_then(|_| {
+        fs::create_dir_all(mount_point)?;
+        baremount(
+            Path::new(&mapped),
+            Path::new(mount_point),
+            "ext4",
+            MsFlags::empty(),
+            "",
+            logger,
+        )
+    });
+
+    match result {
+        Ok(()) => Ok(StorageDeviceGeneric::new(mount_point.to_string())),
+        Err(e) => {
+            if let Err(e) = remove_integrity_device(device_name) {
+                warn!(logger, "failed to remove integrity device: {:?}", e);
+            }
+            Err(e)
+        }
+    }
+}
+
+fn mkfs(device: &str) -> Result<()> {
+    let output = Command::new("mkfs.ext4")
+        .args(["-q", device])
+        .output()
+        .with_context(|| format!("run mkfs.ext4 on {}", device))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "mkfs.ext4 on {} failed: {}",
+            device,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+// Build the dm table for a standalone integrity target over the whole
+// device, journaled writes, HMAC-SHA256 tags keyed with the boot key.
+fn integrity_table(loop_device: &str, num_sectors: u64) -> (u64, u64, String, String) {
+    let params = format!(
+        "{} 0 {} J 1 internal_hash:hmac(sha256):0x{}",
+        loop_device,
+        INTEGRITY_TAG_SIZE,
+        hex_encode(&INTEGRITY_KEY),
+    );
+
+    (0, num_sectors, DM_INTEGRITY_TARGET_TYPE.to_string(), params)
+}
+
+// Create the dm-integrity target and return the path of the mapped
+// device. dm-integrity formats the (zeroed) superblock on the first
+// activation, and only then knows how many data sectors are left after
+// tag interleaving, so the device is activated twice: once with a token
+// table to format, then with the provided size read back from the status.
+#[instrument]
+fn create_integrity_device(
+    logger: &Logger,
+    device_name: &str,
+    loop_device: &str,
+) -> Result<String> {
+    let dm = DM::new().map_err(|e| anyhow!("failed to open device mapper: {}", e))?;
+    let name = DmName::new(device_name).map_err(|e| anyhow!("invalid dm name: {}", e))?;
+    let id = DevId::Name(name);
+
+    dm.device_create(name, None, DmOptions::default())
+        .map_err(|e| anyhow!("failed to create integrity device {}: {}", device_name, e))?;
+
+    let activate = || -> Result<String> {
+        let table = vec![integrity_table(loop_device, FORMAT_SECTORS)];
+        dm.table_load(&id, &table, DmOptions::default())
+            .and_then(|_| dm.device_suspend(&id, DmOptions::default()))
+            .map_err(|e| anyhow!("failed to format integrity device: {}", e))?;
+
+        let data_sectors = provided_data_sectors(&dm, &id)?;
+
+        let table = vec![integrity_table(loop_device, data_sectors)];
+        dm.device_suspend(&id, DmOptions::default().set_flags(DmFlags::DM_SUSPEND))
+            .and_then(|_| dm.table_load(&id, &table, DmOptions::default()))
+            .and_then(|_| dm.device_suspend(&id, DmOptions::default()))
+            .map_err(|e| anyhow!("failed to load integrity table: {}", e))?;
+
+        Ok(Path::new(DEVICE_MAPPER_PREFIX)
+            .join(device_name)
+            .display()
+            .to_string())
+    };
+
+    match activate() {
+        Ok(path) => {
+            info!(logger, "created integrity device"; "device" => &path);
+            Ok(path)
+        }
+        Err(e) => {
+            let _ = dm.device_remove(&id, DmOptions::default());
+            Err(e)
+        }
+    }
+}
+
+// The integrity status line is `<mismatches> <provided_data_sectors>
+// <recalculating position>`; the second field is the usable device size.
+fn provided_data_sectors(dm: &DM, id: &DevId) -> Result<u64> {
+    let (_info, status) = dm
+        .table_status(id, DmOptions::default())
+        .map_err(|e| anyhow!("failed to read integrity device status: {}", e))?;
+
+    let params = status
+        .first()
+        .map(|(_, _, _, params)| params.as_str())
+        .ok_or_else(|| anyhow!("empty integrity device status"))?;
+
+    params
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|sectors| *sectors > FORMAT_SECTORS)
+        .ok_or_else(|| anyhow!("unexpected integrity device status {:?}", params))
+}
+
+fn remove_integrity_device(device_name: &str) -> Result<()> {
+    let dm = DM::new().map_err(|e| anyhow!("failed to open device mapper: {}", e))?;
+    let name = DmName::new(device_name).map_err(|e| anyhow!("invalid dm name: {}", e))?;
+    dm.device_remove(&DevId::Name(name), DmOptions::default())
+        .map_err(|e| anyhow!("failed to remove integrity device {}: {}", device_name, e))?;
+    Ok(())
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_table() {
+        let (start, num_sectors, target, params) = integrity_table("/dev/loop7", 2048);
+
+        assert_eq!(start, 0);
+        assert_eq!(num_sectors, 2048);
+        assert_eq!(target, "integrity");
+        assert_eq!(
+            params,
+            format!(
+                "/dev/loop7 0 32 J 1 internal_hash:hmac(sha256):0x{}",
+                hex_encode(&INTEGRITY_KEY)
+            )
+        );
+    }
+
+    #[test]
+    fn test_integrity_key() {
+        // The key must be boot-unique entropy, not a zeroed buffer.
+        assert_eq!(INTEGRITY_KEY.len(), INTEGRITY_KEY_SIZE);
+        assert_ne!(*INTEGRITY_KEY, vec![0u8; INTEGRITY_KEY_SIZE]);
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! dm-verity protection for read-only block-backed storages.
+//!
+//! Image layers handed over as block devices may carry verity metadata in
+//! the (policy-checked) storage object. For those we insert a dm-verity
+//! target between the device and the mount point, so every block read is
+//! verified against the root hash supplied by the host. A device whose
+//! content does not match the root hash fails the mount, and with it the
+//! container creation.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use devicemapper::{DevId, DmFlags, DmName, DmOptions, DM};
+use kata_types::mount::{DmVerityInfo, StorageDevice};
+use protocols::agent::Storage;
+use slog::Logger;
+use tracing::instrument;
+
+use crate::storage::{common_storage_handler, new_device, StorageDeviceGeneric};
+
+/// Driver option carrying a JSON-encoded `DmVerityInfo`.
+pub const KATA_VIRTUAL_VOLUME_DM_VERITY: &str = "dm_verity";
+
+/// Version of the on-disk verity hash format, not of the dm target.
+const DM_VERITY_FORMAT_VERSION: u32 = 1;
+
+const DM_VERITY_TARGET_TYPE: &str = "verity";
+const DEVICE_MAPPER_PREFIX: &str = "/dev/mapper";
+
+const SECTOR_SHIFT: u64 = 9;
+const MIN_BLOCK_SIZE: u64 = 512;
+const MAX_BLOCK_SIZE: u64 = 512 * 1024;
+
+/// A block-backed storage mounted through a dm-verity target. Cleanup
+/// removes the target once the filesystem is unmounted.
+#[derive(Debug)]
+struct DmVerityDevice {
+    inner: StorageDeviceGeneric,
+    device_name: String,
+}
+
+impl StorageDevice for DmVerityDevice {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        remove_verity_device(&self.device_name)
+    }
+}
+
+/// Mount a block-backed storage, remapping its source through a dm-verity
+/// target when the driver options carry verity metadata.
+#[instrument]
+pub(crate) fn common_verity_storage_handler(
+    logger: &Logger,
+    storage: &mut Storage,
+) -> Result<Arc<dyn StorageDevice>> {
+    let verity = match get_verity_info(&storage.driver_options)? {
+        Some(v) => v,
+        None => {
+            let path = common_storage_handler(logger, storage)?;
+            return new_device(path);
+        }
+    };
+
+    // The root hash uniquely identifies the content, so it doubles as the
+    // device-mapper name and transparently dedups identical layers.
+    let device_name = verity.hash.clone();
+    storage.source = create_verity_device(logger, &verity, &storage.source)?;
+
+    match common_storage_handler(logger, storage) {
+        Ok(path) => Ok(Arc::new(DmVerityDevice {
+            inner: StorageDeviceGeneric::new(path),
+            device_name,
+        })),
+        Err(e) => {
+            if let Err(e) = remove_verity_device(&device_name) {
+                warn!(logger, "failed to remove verity device: {:?}", e);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Extract and validate the verity metadata from storage driver options,
+/// returning `None` when the storage does not request verity protection.
+#[instrument]
+pub(crate) fn get_verity_info(driver_options: &[String]) -> Result<Option<DmVerityInfo>> {
+    for option in driver_options.iter() {
+        if let Some((key, value)) = option.split_once('=') {
+            if key == KATA_VIRTUAL_VOLUME_DM_VERITY {
+                let verity: DmVerityInfo =
+                    serde_json::from_str(value).context("deserialize dm-verity information")?;
+                validate_verity_info(&verity)?;
+                return Ok(Some(verity));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Mirrors the checks the runtime performs on the host side, so a malformed
+// storage object is rejected before it reaches the device mapper.
+fn validate_verity_info(verity: &DmVerityInfo) -> Result<()> {
+    let expected_hash_len = match verity.hashtype.to_lowercase().as_str() {
+        "sha256" => 64,
+        "sha1" => 40,
+        _ => {
+            return Err(anyhow!(
+                "unsupported hash algorithm {} for dm-verity device {}",
+                verity.hashtype,
+                verity.hash
+            ))
+        }
+    };
+    if verity.hash.len() != expected_hash_len || verity.hash.chars().any(|c| !c.is_ascii_hexdigit())
+    {
+        return Err(anyhow!(
+            "invalid {} hash value {} for dm-verity device",
+            verity.hashtype,
+            verity.hash
+        ));
+    }
+
+    if verity.blocknum == 0 || verity.blocknum > u32::MAX as u64 {
+        return Err(anyhow!(
+            "invalid block count {} for dm-verity device {}",
+            verity.blocknum,
+            verity.hash
+        ));
+    }
+
+    for block_size in [verity.blocksize, verity.hashsize] {
+        if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) || !block_size.is_power_of_two()
+        {
+            return Err(anyhow!(
+                "unsupported verity block size: data_block_size = {}, hash_block_size = {}",
+                verity.blocksize,
+                verity.hashsize
+            ));
+        }
+    }
+
+    if verity.offset % verity.hashsize != 0 || verity.offset < verity.blocksize * verity.blocknum {
+        return Err(anyhow!(
+            "invalid hash area offset {} for dm-verity device {}",
+            verity.offset,
+            verity.hash
+        ));
+    }
+
+    Ok(())
+}
+
+// Build the dm table for a verity target whose data and hash areas live on
+// the same device, the hash area starting at `offset` and carrying no
+// superblock and no salt.
+fn verity_table(verity: &DmVerityInfo, source: &str) -> (u64, u64, String, String) {
+    let num_data_sectors = (verity.blocknum * verity.blocksize) >> SECTOR_SHIFT;
+    let hash_start_block = verity.offset / verity.hashsize;
+
+    let params = format!(
+        "{} {} {} {} {} {} {} {} {} -",
+        DM_VERITY_FORMAT_VERSION,
+        source,
+        source,
+        verity.blocksize,
+        verity.hashsize,
+        verity.blocknum,
+        hash_start_block,
+        verity.hashtype.to_lowercase(),
+        verity.hash,
+    );
+
+    (
+        0,
+        num_data_sectors,
+        DM_VERITY_TARGET_TYPE.to_string(),
+        params,
+    )
+}
+
+// Create the read-only dm-verity target and return the path of the mapped
+// device. Reads through the device fail with EIO if the content does not
+// match the root hash, which in turn fails the filesystem mount.
+#[instrument]
+fn create_verity_device(logger: &Logger, verity: &DmVerityInfo, source: &str) -> Result<String> {
+    let dm = DM::new().map_err(|e| anyhow!("failed to open device mapper: {}", e))?;
+    let name = DmName::new(&verity.hash).map_err(|e| anyhow!("invalid dm name: {}", e))?;
+    let id = DevId::Name(name);
+    let opts = DmOptions::default().set_flags(DmFlags::DM_READONLY);
+
+    let dev_path = Path::new(DEVICE_MAPPER_PREFIX).join(verity.hash.as_str());
+    if dev_path.exists() {
+        // Another container re-uses the same layer; the existing target
+        // already verifies against the same root hash.
+        info!(logger, "reusing existing verity device"; "device" => dev_path.display());
+        return Ok(dev_path.display().to_string());
+    }
+
+    dm.device_create(name, None, opts)
+        .map_err(|e| anyhow!("failed to create verity device {}: {}", verity.hash, e))?;
+
+    let table = vec![verity_table(verity, source)];
+    if let Err(e) = dm
+        .table_load(&id, &table, opts)
+        .and_then(|_| dm.device_suspend(&id, DmOptions::default()))
+    {
+        let _ = dm.device_remove(&id, DmOptions::default());
+        return Err(anyhow!(
+            "failed to load verity table for device {}: {}",
+            verity.hash,
+            e
+        ));
+    }
+
+    info!(logger, "created verity device"; "device" => dev_path.display());
+    Ok(dev_path.display().to_string())
+}
+
+fn remove_verity_device(device_name: &str) -> Result<()> {
+    let dm = DM::new().map_err(|e| anyhow!("failed to open device mapper: {}", e))?;
+    let name = DmName::new(device_name).map_err(|e| anyhow!("invalid dm name: {}", e))?;
+    dm.device_remove(&DevId::Name(name), DmOptions::default())
+        .map_err(|e| anyhow!("failed to remove verity device {}: {}", device_name, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verity_info() -> DmVerityInfo {
+        DmVerityInfo {
+            hashtype: "sha256".to_string(),
+            hash: "a".repeat(64),
+            blocknum: 1024,
+            blocksize: 4096,
+            hashsize: 4096,
+            offset: 1024 * 4096,
+        }
+    }
+
+    #[test]
+    fn test_get_verity_info() {
+        let verity = verity_info();
+        let options = vec![format!(
+            "{}={}",
+            KATA_VIRTUAL_VOLUME_DM_VERITY,
+            serde_json::to_string(&verity).unwrap()
+        )];
+        assert_eq!(get_verity_info(&options).unwrap(), Some(verity));
+
+        // No verity option requested.
+        assert_eq!(get_verity_info(&[]).unwrap(), None);
+        let options = vec!["other_option=value".to_string()];
+        assert_eq!(get_verity_info(&options).unwrap(), None);
+
+        // Malformed JSON payload.
+        let options = vec![format!("{}=not-json", KATA_VIRTUAL_VOLUME_DM_VERITY)];
+        assert!(get_verity_info(&options).is_err());
+    }
+
+    #[test]
+    fn test_validate_verity_info() {
+        assert!(validate_verity_info(&verity_info()).is_ok());
+
+        let mut verity = verity_info();
+        verity.hashtype = "md5".to_string();
+        assert!(validate_verity_info(&verity).is_err());
+
+        let mut verity = verity_info();
+        verity.hash = "xyz".to_string();
+        assert!(validate_verity_info(&verity).is_err());
+
+        let mut verity = verity_info();
+        verity.blocknum = 0;
+        assert!(validate_verity_info(&verity).is_err());
+
+        let mut verity = verity_info();
+        verity.blocksize = 1000;
+        assert!(validate_verity_info(&verity).is_err());
+
+        // Hash area overlapping the data area.
+        let mut verity = verity_info();
+        verity.offset = verity.blocksize;
+        assert!(validate_verity_info(&verity).is_err());
+    }
+
+    #[test]
+    fn test_verity_table() {
+        let verity = verity_info();
+        let (start, num_sectors, target, params) = verity_table(&verity, "/dev/vda");
+
+        assert_eq!(start, 0);
+        assert_eq!(num_sectors, 1024 * 4096 / 512);
+        assert_eq!(target, "verity");
+        assert_eq!(
+            params,
+            format!(
+                "1 /dev/vda /dev/vda 4096 4096 1024 1024 sha256 {} -",
+                verity.hash
+            )
+        );
+    }
+}
@@ -13,7 +13,7 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Result};
 use kata_sys_util::mount::parse_mount_options;
-use kata_types::mount::{StorageDevice, KATA_MOUNT_OPTION_FS_GID};
+use kata_types::mount::{StorageDevice, KATA_MOUNT_OPTION_FS_GID, KATA_MOUNT_OPTION_SIZE_LIMIT};
 use nix::unistd::Gid;
 use protocols::agent::Storage;
 use slog::Logger;
@@ -63,6 +63,19 @@ impl StorageHandler for EphemeralHandler {
             // do mount.
             let opts = parse_options(&storage.options);
             storage.options = Default::default();
+
+            // tmpfs enforces a size limit natively, so translate the
+            // sizeLimit option into a size= mount option instead of
+            // leaving the emptyDir limit advisory.
+            if let Some(size_limit) = opts.get(KATA_MOUNT_OPTION_SIZE_LIMIT) {
+                let limit = size_limit
+                    .parse::<u64>()
+                    .context("parse sizeLimit option")?;
+                if limit > 0 {
+                    storage.options.push(format!("size={}", limit));
+                }
+            }
+
             common_storage_handler(ctx.logger, &storage)?;
 
             // ephemeral_storage didn't support mount options except fsGroup.
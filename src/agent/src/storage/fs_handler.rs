@@ -8,13 +8,21 @@ use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::storage::{common_storage_handler, new_device, StorageContext, StorageHandler};
+use crate::storage::dm_integrity::{create_integrity_volume, IntegrityBackedMount};
+use crate::storage::project_quota::set_storage_size_limit;
+use crate::storage::{
+    common_storage_handler, new_device, StorageContext, StorageDeviceGeneric, StorageHandler,
+};
+use crate::AGENT_CONFIG;
 use anyhow::{anyhow, Context, Result};
 use kata_types::device::{DRIVER_9P_TYPE, DRIVER_OVERLAYFS_TYPE, DRIVER_VIRTIOFS_TYPE};
 use kata_types::mount::StorageDevice;
 use protocols::agent::Storage;
 use tracing::instrument;
 
+/// Option carrying the writable layer's ephemeral-storage limit in bytes.
+const FS_OPT_SIZE_LIMIT: &str = "io.katacontainers.fs-opt.size-limit=";
+
 #[derive(Debug)]
 pub struct OverlayfsHandler {}
 
@@ -31,6 +39,7 @@ impl StorageHandler for OverlayfsHandler {
         mut storage: Storage,
         ctx: &mut StorageContext,
     ) -> Result<Arc<dyn StorageDevice>> {
+        let mut integrity_volume = None;
         if storage
             .options
             .iter()
@@ -41,9 +50,39 @@ impl StorageHandler for OverlayfsHandler {
                 .clone()
                 .ok_or_else(|| anyhow!("No container id in rw overlay"))?;
             let cpath = Path::new(crate::rpc::CONTAINER_BASE).join(cid);
+
+            let size_limit = storage
+                .options
+                .iter()
+                .find_map(|o| o.strip_prefix(FS_OPT_SIZE_LIMIT))
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .context("parse size-limit option")?;
+            storage
+                .options
+                .retain(|o| !o.starts_with(FS_OPT_SIZE_LIMIT));
+
+            if AGENT_CONFIG.writable_layer_integrity {
+                // Put the writable layer (upper and work directory) on a
+                // dm-integrity volume, so the host cannot tamper with
+                // what the container wrote. The volume size bounds the
+                // layer, replacing the project quota.
+                integrity_volume = Some(create_integrity_volume(
+                    ctx.logger,
+                    size_limit.filter(|limit| *limit > 0),
+                    &cpath.to_string_lossy(),
+                )?);
+            } else if let Some(limit) = size_limit {
+                // Enforce the writable layer's size limit with a project
+                // quota on the parent of the upper and work directories,
+                // so the overlay workdir is accounted for as well.
+                if limit > 0 {
+                    set_storage_size_limit(ctx.logger, &cpath.to_string_lossy(), limit)?;
+                }
+            }
+
             let work = cpath.join("work");
             let upper = cpath.join("upper");
-
             fs::create_dir_all(&work).context("Creating overlay work directory")?;
             fs::create_dir_all(&upper).context("Creating overlay upper directory")?;
 
@@ -57,7 +96,13 @@ impl StorageHandler for OverlayfsHandler {
         }
 
         let path = common_storage_handler(ctx.logger, &storage)?;
-        new_device(path)
+        match integrity_volume {
+            Some(volume) => Ok(Arc::new(IntegrityBackedMount {
+                inner: StorageDeviceGeneric::new(path),
+                volume,
+            })),
+            None => new_device(path),
+        }
     }
 }
 
@@ -99,6 +144,13 @@ impl StorageHandler for VirtioFsHandler {
         ctx: &mut StorageContext,
     ) -> Result<Arc<dyn StorageDevice>> {
         let path = common_storage_handler(ctx.logger, &storage)?;
+        crate::virtiofs_monitor::register_mount(
+            ctx.logger,
+            &storage.source,
+            &storage.mount_point,
+            &storage.fstype,
+            &storage.options,
+        );
         new_device(path)
     }
 }
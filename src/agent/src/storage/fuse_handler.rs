@@ -0,0 +1,209 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! FUSE passthrough mounts for user-space filesystems.
+//!
+//! s3fs/gcsfuse style volumes cannot be mounted on the host and shared
+//! into the guest, so the daemon has to run in the guest instead. The
+//! runtime describes the volume as a "fuse" storage whose driver options
+//! name the daemon binary shipped in the guest image; the agent spawns
+//! the daemon in the foreground, waits for the mount to appear and keeps
+//! the pid around so do_create_container can move the daemon into the
+//! container cgroup once it exists. The daemon itself negotiates the
+//! kernel passthrough mode during FUSE_INIT, the agent only has to make
+//! sure /dev/fuse is present.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use kata_types::device::DRIVER_FUSE_TYPE;
+use kata_types::mount::StorageDevice;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::stat::{makedev, mknod, Mode, SFlag};
+use nix::unistd::Pid;
+use protocols::agent::Storage;
+use tracing::instrument;
+
+use crate::mount::is_mounted;
+use crate::storage::{parse_options, StorageContext, StorageDeviceGeneric, StorageHandler};
+use crate::AGENT_CONFIG;
+
+const FUSE_DEV_PATH: &str = "/dev/fuse";
+const FUSE_DEV_MAJOR: u64 = 10;
+const FUSE_DEV_MINOR: u64 = 229;
+
+/// Driver option naming the daemon command line, e.g.
+/// "fuse.daemon=/usr/local/bin/s3fs mybucket".
+const FUSE_OPT_DAEMON: &str = "fuse.daemon";
+
+/// How long to wait for the daemon to establish the mount.
+const FUSE_MOUNT_TIMEOUT: Duration = Duration::from_secs(10);
+const FUSE_MOUNT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A fuse mount served by a daemon the agent spawned. Cleanup unmounts
+/// the filesystem first so the daemon can flush, then reaps it.
+#[derive(Debug)]
+struct FuseDevice {
+    inner: StorageDeviceGeneric,
+    daemon_pid: i32,
+}
+
+impl StorageDevice for FuseDevice {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        // The daemon usually exits on its own once the mount is gone;
+        // ESRCH here just means it beat us to it.
+        match kill(Pid::from_raw(self.daemon_pid), Signal::SIGTERM) {
+            Ok(_) | Err(nix::errno::Errno::ESRCH) => Ok(()),
+            Err(e) => Err(anyhow!("failed to stop fuse daemon: {:?}", e)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FuseHandler {}
+
+#[async_trait::async_trait]
+impl StorageHandler for FuseHandler {
+    #[instrument]
+    fn driver_types(&self) -> &[&str] {
+        &[DRIVER_FUSE_TYPE]
+    }
+
+    #[instrument]
+    async fn create_device(
+        &self,
+        storage: Storage,
+        ctx: &mut StorageContext,
+    ) -> Result<Arc<dyn StorageDevice>> {
+        let opts = parse_options(&storage.driver_options);
+        let daemon = opts
+            .get(FUSE_OPT_DAEMON)
+            .ok_or_else(|| anyhow!("fuse storage misses the {} driver option", FUSE_OPT_DAEMON))?;
+
+        ensure_fuse_device_node().context("create /dev/fuse")?;
+        std::fs::create_dir_all(&storage.mount_point)
+            .with_context(|| format!("create mount point {}", storage.mount_point))?;
+
+        let mount_options = sanitize_mount_options(&storage.options, AGENT_CONFIG.fuse_allow_other);
+
+        let mut argv = daemon.split_whitespace();
+        let program = argv
+            .next()
+            .ok_or_else(|| anyhow!("empty fuse daemon command"))?;
+        let mut cmd = Command::new(program);
+        cmd.args(argv);
+        if !storage.source.is_empty() {
+            cmd.arg(&storage.source);
+        }
+        // Foreground mode so the pid stays the daemon's: cleanup signals
+        // it and do_create_container moves it into the container cgroup.
+        cmd.arg(&storage.mount_point).arg("-f");
+        if !mount_options.is_empty() {
+            cmd.arg("-o").arg(mount_options.join(","));
+        }
+        cmd.stdin(Stdio::null());
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("spawn fuse daemon {}", program))?;
+        let daemon_pid = child.id() as i32;
+
+        if let Err(e) = wait_for_mount(&mut child, &storage.mount_point) {
+            let _ = kill(Pid::from_raw(daemon_pid), Signal::SIGKILL);
+            let _ = child.wait();
+            return Err(e);
+        }
+
+        // Remember the pid so the daemon ends up in the container cgroup
+        // instead of staying accounted to the agent.
+        if let Some(cid) = ctx.cid {
+            let mut sandbox = ctx.sandbox.lock().await;
+            sandbox
+                .fuse_daemon_pids
+                .entry(cid.clone())
+                .or_default()
+                .push(daemon_pid);
+        }
+
+        Ok(Arc::new(FuseDevice {
+            inner: StorageDeviceGeneric::new(storage.mount_point.clone()),
+            daemon_pid,
+        }))
+    }
+}
+
+fn ensure_fuse_device_node() -> Result<()> {
+    if Path::new(FUSE_DEV_PATH).exists() {
+        return Ok(());
+    }
+    mknod(
+        FUSE_DEV_PATH,
+        SFlag::S_IFCHR,
+        Mode::from_bits_truncate(0o666),
+        makedev(FUSE_DEV_MAJOR, FUSE_DEV_MINOR),
+    )?;
+    Ok(())
+}
+
+// Drop allow_other unless the agent configuration permits it: it lets
+// every user in the guest, not just the container, see the mount.
+fn sanitize_mount_options(options: &[String], allow_other: bool) -> Vec<String> {
+    options
+        .iter()
+        .filter(|o| allow_other || o.as_str() != "allow_other")
+        .cloned()
+        .collect()
+}
+
+fn wait_for_mount(child: &mut std::process::Child, mount_point: &str) -> Result<()> {
+    let deadline = std::time::Instant::now() + FUSE_MOUNT_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Err(anyhow!("fuse daemon exited early with status {}", status));
+        }
+        if matches!(is_mounted(mount_point), Ok(true)) {
+            return Ok(());
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(anyhow!(
+                "fuse daemon did not mount {} within {:?}",
+                mount_point,
+                FUSE_MOUNT_TIMEOUT
+            ));
+        }
+        std::thread::sleep(FUSE_MOUNT_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_mount_options() {
+        let options = vec![
+            "ro".to_string(),
+            "allow_other".to_string(),
+            "noatime".to_string(),
+        ];
+
+        // allow_other denied by configuration: the option is dropped.
+        assert_eq!(
+            sanitize_mount_options(&options, false),
+            vec!["ro".to_string(), "noatime".to_string()]
+        );
+
+        // allow_other permitted: options pass through untouched.
+        assert_eq!(sanitize_mount_options(&options, true), options);
+    }
+}
@@ -8,10 +8,13 @@ use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::sync::Arc;
 
+use crate::storage::dm_integrity::create_integrity_volume;
+use crate::storage::project_quota::set_storage_size_limit;
 use crate::storage::{new_device, parse_options, StorageContext, StorageHandler, MODE_SETGID};
+use crate::AGENT_CONFIG;
 use anyhow::{Context, Result};
 use kata_types::device::DRIVER_LOCAL_TYPE;
-use kata_types::mount::{StorageDevice, KATA_MOUNT_OPTION_FS_GID};
+use kata_types::mount::{StorageDevice, KATA_MOUNT_OPTION_FS_GID, KATA_MOUNT_OPTION_SIZE_LIMIT};
 use nix::unistd::Gid;
 use protocols::agent::Storage;
 use tracing::instrument;
@@ -30,15 +33,35 @@ impl StorageHandler for LocalHandler {
     async fn create_device(
         &self,
         storage: Storage,
-        _ctx: &mut StorageContext,
+        ctx: &mut StorageContext,
     ) -> Result<Arc<dyn StorageDevice>> {
-        fs::create_dir_all(&storage.mount_point).context(format!(
-            "failed to create dir all {:?}",
-            &storage.mount_point
-        ))?;
-
         let opts = parse_options(&storage.options);
 
+        // Disk-backed emptyDirs hold writable workload data the host can
+        // otherwise rewrite unnoticed; with writable layer integrity
+        // enabled they are mounted from a dm-integrity volume instead of
+        // a plain directory. The volume size bounds usage, so the
+        // project quota below is not needed on this path.
+        let integrity_volume = if AGENT_CONFIG.writable_layer_integrity {
+            let size_limit = opts
+                .get(KATA_MOUNT_OPTION_SIZE_LIMIT)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .context("parse sizeLimit option")?
+                .filter(|limit| *limit > 0);
+            Some(create_integrity_volume(
+                ctx.logger,
+                size_limit,
+                &storage.mount_point,
+            )?)
+        } else {
+            fs::create_dir_all(&storage.mount_point).context(format!(
+                "failed to create dir all {:?}",
+                &storage.mount_point
+            ))?;
+            None
+        };
+
         let mut need_set_fsgid = false;
         if let Some(fsgid) = opts.get(KATA_MOUNT_OPTION_FS_GID) {
             let gid = fsgid.parse::<u32>()?;
@@ -61,6 +84,21 @@ impl StorageHandler for LocalHandler {
             fs::set_permissions(&storage.mount_point, permission)?;
         }
 
+        if let Some(device) = integrity_volume {
+            return Ok(device);
+        }
+
+        // Disk-backed emptyDirs carry their ephemeral-storage limit as a
+        // sizeLimit option; enforce it with a project quota.
+        if let Some(size_limit) = opts.get(KATA_MOUNT_OPTION_SIZE_LIMIT) {
+            let limit = size_limit
+                .parse::<u64>()
+                .context("parse sizeLimit option")?;
+            if limit > 0 {
+                set_storage_size_limit(ctx.logger, &storage.mount_point, limit)?;
+            }
+        }
+
         new_device("".to_string())
     }
 }
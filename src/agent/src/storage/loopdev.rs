@@ -0,0 +1,88 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Minimal loop device control for storages that arrive as image files
+//! on the shared filesystem rather than as block devices.
+
+use std::fs::OpenOptions;
+use std::os::unix::io::AsRawFd;
+
+use anyhow::{anyhow, Context, Result};
+use tracing::instrument;
+
+const LOOP_CONTROL_PATH: &str = "/dev/loop-control";
+const LOOP_ATTACH_RETRIES: u32 = 3;
+
+nix::ioctl_none_bad!(ioctl_loop_ctl_get_free, 0x4C82);
+nix::ioctl_write_int_bad!(ioctl_loop_set_fd, 0x4C00);
+nix::ioctl_none_bad!(ioctl_loop_clr_fd, 0x4C01);
+
+// Attach the image read-only to a free loop device and return its path.
+// The free index can be raced by other users of the loop control device,
+// so retry a few times on EBUSY like losetup does.
+#[instrument]
+pub(crate) fn attach_loop_device(image: &str) -> Result<String> {
+    attach_loop_device_with_mode(image, false)
+}
+
+// Attach the image read-write, for loop devices that back writable
+// volumes rather than immutable layers.
+#[instrument]
+pub(crate) fn attach_loop_device_rw(image: &str) -> Result<String> {
+    attach_loop_device_with_mode(image, true)
+}
+
+fn attach_loop_device_with_mode(image: &str, writable: bool) -> Result<String> {
+    let control = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(LOOP_CONTROL_PATH)
+        .with_context(|| format!("open {}", LOOP_CONTROL_PATH))?;
+    let backing = OpenOptions::new()
+        .read(true)
+        .write(writable)
+        .open(image)
+        .with_context(|| format!("open image {}", image))?;
+
+    let mut last_err = None;
+    for _ in 0..LOOP_ATTACH_RETRIES {
+        let index = unsafe { ioctl_loop_ctl_get_free(control.as_raw_fd()) }
+            .map_err(|e| anyhow!("failed to get free loop device: {}", e))?;
+        let path = format!("/dev/loop{}", index);
+        let device = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("open {}", path))?;
+
+        // The mode the backing file was opened with decides whether the
+        // loop device comes up read-only or read-write.
+        match unsafe { ioctl_loop_set_fd(device.as_raw_fd(), backing.as_raw_fd()) } {
+            Ok(_) => return Ok(path),
+            Err(nix::errno::Errno::EBUSY) => {
+                last_err = Some(nix::errno::Errno::EBUSY);
+                continue;
+            }
+            Err(e) => return Err(anyhow!("failed to attach {} to {}: {}", image, path, e)),
+        }
+    }
+
+    Err(anyhow!(
+        "failed to attach {} to a loop device: {:?}",
+        image,
+        last_err
+    ))
+}
+
+pub(crate) fn detach_loop_device(path: &str) -> Result<()> {
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("open {}", path))?;
+    unsafe { ioctl_loop_clr_fd(device.as_raw_fd()) }
+        .map_err(|e| anyhow!("failed to detach loop device {}: {}", path, e))?;
+    Ok(())
+}
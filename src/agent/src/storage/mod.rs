@@ -22,11 +22,14 @@ use tracing::instrument;
 
 use self::bind_watcher_handler::BindWatcherHandler;
 use self::block_handler::{PmemHandler, ScsiHandler, VirtioBlkMmioHandler, VirtioBlkPciHandler};
+use self::composefs_handler::ComposeFsHandler;
 use self::ephemeral_handler::EphemeralHandler;
 use self::fs_handler::{OverlayfsHandler, Virtio9pHandler, VirtioFsHandler};
+use self::fuse_handler::FuseHandler;
 #[cfg(feature = "guest-pull")]
 use self::image_pull_handler::ImagePullHandler;
 use self::local_handler::LocalHandler;
+use self::squashfs_handler::SquashfsHandler;
 use crate::mount::{baremount, is_mounted, remove_mounts};
 use crate::sandbox::Sandbox;
 
@@ -34,11 +37,18 @@ pub use self::ephemeral_handler::update_ephemeral_mounts;
 
 mod bind_watcher_handler;
 mod block_handler;
+mod composefs_handler;
+mod dm_integrity;
+mod dm_verity;
 mod ephemeral_handler;
 mod fs_handler;
+mod fuse_handler;
 #[cfg(feature = "guest-pull")]
 mod image_pull_handler;
 mod local_handler;
+mod loopdev;
+mod project_quota;
+mod squashfs_handler;
 
 const RW_MASK: u32 = 0o660;
 const RO_MASK: u32 = 0o440;
@@ -137,13 +147,16 @@ lazy_static! {
         let mut manager: StorageHandlerManager<Arc<dyn StorageHandler>> = StorageHandlerManager::new();
         let handlers: Vec<Arc<dyn StorageHandler>> = vec![
             Arc::new(Virtio9pHandler {}),
+            Arc::new(ComposeFsHandler {}),
             Arc::new(VirtioBlkMmioHandler {}),
             Arc::new(VirtioBlkPciHandler {}),
             Arc::new(EphemeralHandler {}),
+            Arc::new(FuseHandler {}),
             Arc::new(LocalHandler {}),
             Arc::new(PmemHandler {}),
             Arc::new(OverlayfsHandler {}),
             Arc::new(ScsiHandler {}),
+            Arc::new(SquashfsHandler {}),
             Arc::new(VirtioFsHandler {}),
             Arc::new(BindWatcherHandler {}),
             #[cfg(target_arch = "s390x")]
@@ -0,0 +1,301 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Project quota enforcement for ephemeral storage.
+//!
+//! Container writable layers and disk-backed emptyDirs live on a guest
+//! filesystem shared with other containers, so a size limit attached to
+//! them is only advisory unless the filesystem enforces it. When the
+//! backing XFS or ext4 filesystem is mounted with `prjquota`, each
+//! limited directory is assigned its own project ID with the inherit
+//! flag set and a hard block limit, turning the per-container
+//! ephemeral-storage limit into an ENOSPC inside the guest.
+
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{anyhow, Context, Result};
+use slog::Logger;
+use tracing::instrument;
+
+use crate::linux_abi::PROC_MOUNTS_FILE;
+
+/// First project ID handed out by the agent. Same base the kubelet uses
+/// for its own project quotas, well away from IDs administrators
+/// typically assign by hand.
+const FIRST_PROJECT_ID: u32 = 1_048_577;
+
+static NEXT_PROJECT_ID: AtomicU32 = AtomicU32::new(FIRST_PROJECT_ID);
+
+/// Mount option marking a filesystem with project quota accounting.
+const PRJQUOTA_MOUNT_OPTION: &str = "prjquota";
+
+// Constants from the kernel quota UAPI which libc does not expose.
+const PRJQUOTA: u32 = 2;
+const SUBCMDSHIFT: u32 = 8;
+const Q_SETQUOTA: u32 = 0x80_0008;
+const Q_XSETQLIM: u32 = 0x5804;
+const QIF_BLIMITS: u32 = 1;
+// dqblk block limits are expressed in KiB.
+const QIF_DQBLK_SHIFT: u64 = 10;
+
+// XFS quota constants from the kernel dqblk_xfs UAPI.
+const FS_DQUOT_VERSION: i8 = 1;
+const FS_PROJ_QUOTA: i8 = 2;
+const FS_DQ_BSOFT: u16 = 1 << 2;
+const FS_DQ_BHARD: u16 = 1 << 3;
+// fs_disk_quota block limits are expressed in 512-byte basic blocks.
+const XFS_BB_SHIFT: u64 = 9;
+
+const FS_XFLAG_PROJINHERIT: u32 = 0x0000_0200;
+
+/// struct fsxattr from the kernel fs UAPI.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct Fsxattr {
+    fsx_xflags: u32,
+    fsx_extsize: u32,
+    fsx_nextents: u32,
+    fsx_projid: u32,
+    fsx_cowextsize: u32,
+    fsx_pad: [u8; 8],
+}
+
+nix::ioctl_read!(fs_ioc_fsgetxattr, 'X', 31, Fsxattr);
+nix::ioctl_write_ptr!(fs_ioc_fssetxattr, 'X', 32, Fsxattr);
+
+/// struct fs_disk_quota from the kernel dqblk_xfs UAPI.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct FsDiskQuota {
+    d_version: i8,
+    d_flags: i8,
+    d_fieldmask: u16,
+    d_id: u32,
+    d_blk_hardlimit: u64,
+    d_blk_softlimit: u64,
+    d_ino_hardlimit: u64,
+    d_ino_softlimit: u64,
+    d_bcount: u64,
+    d_icount: u64,
+    d_itimer: i32,
+    d_btimer: i32,
+    d_iwarns: u16,
+    d_bwarns: u16,
+    d_padding2: i32,
+    d_rtb_hardlimit: u64,
+    d_rtb_softlimit: u64,
+    d_rtbcount: u64,
+    d_rtbtimer: i32,
+    d_rtbwarns: u16,
+    d_padding3: i16,
+    d_padding4: [i8; 8],
+}
+
+/// Enforce `size_limit` bytes on `path` through a project quota.
+///
+/// Best effort by design: when the backing filesystem is not XFS or ext4,
+/// or is not mounted with `prjquota`, the limit stays advisory and a log
+/// entry records why. Genuine quota failures are returned as errors so a
+/// misconfigured limit does not silently go unenforced.
+#[instrument]
+pub(crate) fn set_storage_size_limit(logger: &Logger, path: &str, size_limit: u64) -> Result<()> {
+    let content = fs::read_to_string(PROC_MOUNTS_FILE)
+        .with_context(|| format!("read {}", PROC_MOUNTS_FILE))?;
+    let mount = match find_backing_mount(&content, Path::new(path)) {
+        Some(v) => v,
+        None => return Err(anyhow!("no backing mount found for {}", path)),
+    };
+
+    if !matches!(mount.fs_type.as_str(), "xfs" | "ext4") {
+        info!(
+            logger,
+            "project quotas not supported on {}, size limit for {} stays advisory",
+            mount.fs_type,
+            path
+        );
+        return Ok(());
+    }
+    if !mount.has_project_quota {
+        info!(
+            logger,
+            "{} is not mounted with prjquota, size limit for {} stays advisory", mount.device, path
+        );
+        return Ok(());
+    }
+
+    let project_id = NEXT_PROJECT_ID.fetch_add(1, Ordering::SeqCst);
+    apply_project_id(path, project_id)
+        .with_context(|| format!("assign project ID {} to {}", project_id, path))?;
+
+    match mount.fs_type.as_str() {
+        "xfs" => set_xfs_quota(&mount.device, project_id, size_limit),
+        _ => set_ext4_quota(&mount.device, project_id, size_limit),
+    }
+    .with_context(|| {
+        format!(
+            "set {} byte project quota for {} on {}",
+            size_limit, path, mount.device
+        )
+    })?;
+
+    info!(
+        logger,
+        "enforcing {} byte size limit on {} with project ID {}", size_limit, path, project_id
+    );
+
+    Ok(())
+}
+
+// Tag the directory with the project ID and set the inherit flag, so
+// everything created below it is accounted to the same project.
+fn apply_project_id(path: &str, project_id: u32) -> Result<()> {
+    let dir = File::open(path)?;
+    let mut attr = Fsxattr::default();
+
+    unsafe { fs_ioc_fsgetxattr(dir.as_raw_fd(), &mut attr) }.context("FS_IOC_FSGETXATTR")?;
+    attr.fsx_projid = project_id;
+    attr.fsx_xflags |= FS_XFLAG_PROJINHERIT;
+    unsafe { fs_ioc_fssetxattr(dir.as_raw_fd(), &attr) }.context("FS_IOC_FSSETXATTR")?;
+
+    Ok(())
+}
+
+fn quotactl(cmd: u32, device: &str, project_id: u32, data: *mut libc::c_char) -> Result<()> {
+    let special = CString::new(device)?;
+    let ret = unsafe {
+        libc::quotactl(
+            qcmd(cmd, PRJQUOTA) as libc::c_int,
+            special.as_ptr(),
+            project_id as libc::c_int,
+            data,
+        )
+    };
+    if ret < 0 {
+        return Err(anyhow!("quotactl: {}", std::io::Error::last_os_error()));
+    }
+
+    Ok(())
+}
+
+fn set_ext4_quota(device: &str, project_id: u32, size_limit: u64) -> Result<()> {
+    let limit_kb = size_limit >> QIF_DQBLK_SHIFT;
+    let mut quota: libc::dqblk = unsafe { std::mem::zeroed() };
+    quota.dqb_bhardlimit = limit_kb;
+    quota.dqb_bsoftlimit = limit_kb;
+    quota.dqb_valid = QIF_BLIMITS;
+
+    quotactl(
+        Q_SETQUOTA,
+        device,
+        project_id,
+        &mut quota as *mut _ as *mut libc::c_char,
+    )
+}
+
+fn set_xfs_quota(device: &str, project_id: u32, size_limit: u64) -> Result<()> {
+    let limit_bb = size_limit >> XFS_BB_SHIFT;
+    let mut quota = FsDiskQuota {
+        d_version: FS_DQUOT_VERSION,
+        d_flags: FS_PROJ_QUOTA,
+        d_fieldmask: FS_DQ_BSOFT | FS_DQ_BHARD,
+        d_id: project_id,
+        d_blk_hardlimit: limit_bb,
+        d_blk_softlimit: limit_bb,
+        ..Default::default()
+    };
+
+    quotactl(
+        Q_XSETQLIM,
+        device,
+        project_id,
+        &mut quota as *mut _ as *mut libc::c_char,
+    )
+}
+
+fn qcmd(cmd: u32, qtype: u32) -> u32 {
+    (cmd << SUBCMDSHIFT) | (qtype & 0xff)
+}
+
+#[derive(Debug, PartialEq)]
+struct BackingMount {
+    device: String,
+    fs_type: String,
+    has_project_quota: bool,
+}
+
+// Find the mount entry backing `path`: the mount point that is the
+// longest path prefix of it.
+fn find_backing_mount(proc_mounts: &str, path: &Path) -> Option<BackingMount> {
+    let mut best: Option<(&Path, BackingMount)> = None;
+
+    for line in proc_mounts.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let mount_point = Path::new(fields[1]);
+        if !path.starts_with(mount_point) {
+            continue;
+        }
+        if let Some((best_point, _)) = &best {
+            if best_point.as_os_str().len() >= mount_point.as_os_str().len() {
+                continue;
+            }
+        }
+        best = Some((
+            mount_point,
+            BackingMount {
+                device: fields[0].to_string(),
+                fs_type: fields[2].to_string(),
+                has_project_quota: fields[3].split(',').any(|o| o == PRJQUOTA_MOUNT_OPTION),
+            },
+        ));
+    }
+
+    best.map(|(_, mount)| mount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_MOUNTS: &str = "\
+/dev/vda1 / ext4 rw,relatime 0 0
+/dev/vdb /run/kata-containers xfs rw,relatime,prjquota 0 0
+tmpfs /run/shm tmpfs rw,nosuid 0 0
+";
+
+    #[test]
+    fn test_find_backing_mount() {
+        let mount =
+            find_backing_mount(PROC_MOUNTS, Path::new("/run/kata-containers/cid/upper")).unwrap();
+        assert_eq!(mount.device, "/dev/vdb");
+        assert_eq!(mount.fs_type, "xfs");
+        assert!(mount.has_project_quota);
+
+        // Longest prefix wins over the root mount.
+        let mount = find_backing_mount(PROC_MOUNTS, Path::new("/run/shm/foo")).unwrap();
+        assert_eq!(mount.fs_type, "tmpfs");
+        assert!(!mount.has_project_quota);
+
+        let mount = find_backing_mount(PROC_MOUNTS, Path::new("/var/lib/data")).unwrap();
+        assert_eq!(mount.device, "/dev/vda1");
+        assert_eq!(mount.fs_type, "ext4");
+        assert!(!mount.has_project_quota);
+
+        assert_eq!(find_backing_mount("", Path::new("/var/lib/data")), None);
+    }
+
+    #[test]
+    fn test_qcmd() {
+        // QCMD(Q_SETQUOTA, PRJQUOTA) as the kernel computes it.
+        assert_eq!(qcmd(Q_SETQUOTA, PRJQUOTA), 0x8000_0802);
+        assert_eq!(qcmd(Q_XSETQLIM, PRJQUOTA), 0x0058_0402);
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Squashfs image layer storages.
+//!
+//! Image-acceleration snapshotters ship container image layers as
+//! squashfs artifacts. Each layer arrives either as an image file on the
+//! shared filesystem - mounted here through a loop device - or as a block
+//! device hotplugged by the runtime, which may additionally carry
+//! dm-verity metadata in the storage object. The mounted layers are then
+//! stacked into a rootfs by a regular overlayfs storage whose lowerdirs
+//! point at the per-layer mount points.
+
+use std::os::unix::fs::FileTypeExt;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use kata_types::device::DRIVER_SQUASHFS_TYPE;
+use kata_types::mount::StorageDevice;
+use protocols::agent::Storage;
+use tracing::instrument;
+
+use crate::storage::dm_verity::common_verity_storage_handler;
+use crate::storage::loopdev::{attach_loop_device, detach_loop_device};
+use crate::storage::{
+    common_storage_handler, StorageContext, StorageDeviceGeneric, StorageHandler,
+};
+
+/// A squashfs layer mounted from a loop device. Cleanup detaches the
+/// loop device once the filesystem is unmounted.
+#[derive(Debug)]
+struct SquashfsLoopDevice {
+    inner: StorageDeviceGeneric,
+    loop_device: String,
+}
+
+impl StorageDevice for SquashfsLoopDevice {
+    fn path(&self) -> Option<&str> {
+        self.inner.path()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()?;
+        detach_loop_device(&self.loop_device)
+    }
+}
+
+#[derive(Debug)]
+pub struct SquashfsHandler {}
+
+#[async_trait::async_trait]
+impl StorageHandler for SquashfsHandler {
+    #[instrument]
+    fn driver_types(&self) -> &[&str] {
+        &[DRIVER_SQUASHFS_TYPE]
+    }
+
+    #[instrument]
+    async fn create_device(
+        &self,
+        mut storage: Storage,
+        ctx: &mut StorageContext,
+    ) -> Result<Arc<dyn StorageDevice>> {
+        storage.fstype = "squashfs".to_string();
+        ensure_readonly(&mut storage.options);
+
+        let metadata = std::fs::metadata(&storage.source)
+            .with_context(|| format!("stat squashfs source {}", storage.source))?;
+
+        // Block-backed layers go through the common block path, which
+        // also inserts a dm-verity target when the storage requests it.
+        if metadata.file_type().is_block_device() {
+            return common_verity_storage_handler(ctx.logger, &mut storage);
+        }
+
+        let loop_device = attach_loop_device(&storage.source)?;
+        storage.source = loop_device.clone();
+
+        match common_storage_handler(ctx.logger, &storage) {
+            Ok(path) => Ok(Arc::new(SquashfsLoopDevice {
+                inner: StorageDeviceGeneric::new(path),
+                loop_device,
+            })),
+            Err(e) => {
+                if let Err(e) = detach_loop_device(&loop_device) {
+                    warn!(ctx.logger, "failed to detach loop device: {:?}", e);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+// Squashfs is read-only by construction; make the mount flags say so as
+// well no matter what the storage object carries.
+fn ensure_readonly(options: &mut Vec<String>) {
+    if !options.iter().any(|o| o == "ro") {
+        options.push("ro".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_readonly() {
+        let mut options = vec![];
+        ensure_readonly(&mut options);
+        assert_eq!(options, vec!["ro".to_string()]);
+
+        // Already read-only: no duplicate.
+        ensure_readonly(&mut options);
+        assert_eq!(options, vec!["ro".to_string()]);
+
+        let mut options = vec!["noatime".to_string()];
+        ensure_readonly(&mut options);
+        assert_eq!(options, vec!["noatime".to_string(), "ro".to_string()]);
+    }
+}
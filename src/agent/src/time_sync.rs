@@ -0,0 +1,216 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Managed guest time synchronization.
+//!
+//! The runtime sets the guest clock once at boot via `SetGuestDateTime`,
+//! after which the guest drifts on its own. When the host exposes a
+//! `ptp_kvm` device the agent can read the host clock directly, so this
+//! service periodically measures the offset against `CLOCK_REALTIME` and
+//! corrects it: small drift is slewed, large drift is stepped. A jump of
+//! `CLOCK_BOOTTIME` relative to `CLOCK_MONOTONIC` means the sandbox (or
+//! the host) was suspended, in which case the guest clock may be far off
+//! and the service resyncs at a much shorter interval until it settles.
+
+use std::fs;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use nix::errno::Errno;
+use slog::Logger;
+
+/// Steady-state interval between drift measurements.
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval used while aggressively resyncing after a suspend.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of short-interval measurements taken after a suspend.
+const RESYNC_TICKS: u32 = 5;
+
+/// Drift below this is measurement noise and left alone.
+const DRIFT_TOLERANCE_NS: i64 = 1_000_000; // 1ms
+
+/// Drift beyond this is stepped instead of slewed; the singleshot slew
+/// interface is limited to +-500ms anyway.
+const STEP_THRESHOLD_NS: i64 = 500_000_000; // 500ms
+
+/// A boottime/monotonic gap larger than this is treated as suspend/resume.
+const SUSPEND_GAP_NS: i64 = 2_000_000_000; // 2s
+
+const SYSFS_PTP_PATH: &str = "/sys/class/ptp";
+
+/// Open a posix clock by file descriptor, see clock_gettime(2).
+const CLOCKFD: libc::clockid_t = 3;
+
+// Convenience function to obtain the scope logger.
+fn sl() -> slog::Logger {
+    slog_scope::logger().new(o!("subsystem" => "time_sync"))
+}
+
+/// The kvm PTP clock, which reads the host's clock from inside the guest.
+struct PtpClock {
+    file: fs::File,
+}
+
+impl PtpClock {
+    /// Look for a PTP clock backed by the `ptp_kvm` driver and open it.
+    fn find_and_open() -> Result<Self> {
+        for entry in fs::read_dir(SYSFS_PTP_PATH).context("list ptp clocks")? {
+            let entry = entry?;
+            let name = fs::read_to_string(entry.path().join("clock_name")).unwrap_or_default();
+            if !name.to_lowercase().contains("kvm") {
+                continue;
+            }
+            let dev = PathBuf::from("/dev").join(entry.file_name());
+            let file = fs::File::open(&dev)
+                .with_context(|| format!("open ptp clock {}", dev.display()))?;
+            return Ok(PtpClock { file });
+        }
+        Err(anyhow!("no ptp_kvm clock found"))
+    }
+
+    fn clockid(&self) -> libc::clockid_t {
+        // FD_TO_CLOCKID from the kernel's posix clock API.
+        (!self.file.as_raw_fd()) << 3 | CLOCKFD
+    }
+
+    /// Host real time in nanoseconds since the epoch.
+    fn host_time_ns(&self) -> Result<i64> {
+        clock_ns(self.clockid())
+    }
+}
+
+fn clock_ns(clockid: libc::clockid_t) -> Result<i64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let ret = unsafe { libc::clock_gettime(clockid, &mut ts) };
+    Errno::result(ret)?;
+    Ok(ts.tv_sec * 1_000_000_000 + ts.tv_nsec)
+}
+
+/// Split a signed nanosecond offset into the (sec, nsec) pair expected by
+/// the timex interface, where nsec must lie in [0, 1e9).
+fn split_offset(offset_ns: i64) -> (i64, i64) {
+    let sec = offset_ns.div_euclid(1_000_000_000);
+    let nsec = offset_ns.rem_euclid(1_000_000_000);
+    (sec, nsec)
+}
+
+/// Step `CLOCK_REALTIME` by the given offset.
+fn step_clock(offset_ns: i64) -> Result<()> {
+    let (sec, nsec) = split_offset(offset_ns);
+    let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+    tx.modes = libc::ADJ_SETOFFSET | libc::ADJ_NANO;
+    tx.time.tv_sec = sec;
+    tx.time.tv_usec = nsec;
+    let ret = unsafe { libc::clock_adjtime(libc::CLOCK_REALTIME, &mut tx) };
+    Errno::result(ret).map(drop).context("step clock")
+}
+
+/// Gradually slew `CLOCK_REALTIME` by the given offset, which must be
+/// within the +-500ms singleshot range.
+fn slew_clock(offset_ns: i64) -> Result<()> {
+    let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+    tx.modes = libc::ADJ_OFFSET_SINGLESHOT;
+    tx.offset = offset_ns / 1_000;
+    let ret = unsafe { libc::clock_adjtime(libc::CLOCK_REALTIME, &mut tx) };
+    Errno::result(ret).map(drop).context("slew clock")
+}
+
+/// Correct the measured drift, returning a description of the action
+/// taken for logging.
+fn correct_drift(drift_ns: i64) -> Result<&'static str> {
+    if drift_ns.abs() < DRIFT_TOLERANCE_NS {
+        return Ok("none");
+    }
+    if drift_ns.abs() >= STEP_THRESHOLD_NS {
+        step_clock(drift_ns)?;
+        return Ok("step");
+    }
+    slew_clock(drift_ns)?;
+    Ok("slew")
+}
+
+/// Start the time sync service. Without a `ptp_kvm` clock there is no
+/// reference to correct against, so the service logs once and does not
+/// run.
+pub(crate) fn start(logger: &Logger) {
+    let logger = logger.new(o!("subsystem" => "time_sync"));
+
+    let ptp = match PtpClock::find_and_open() {
+        Ok(ptp) => ptp,
+        Err(e) => {
+            info!(logger, "guest time sync disabled: {:?}", e);
+            return;
+        }
+    };
+
+    info!(logger, "starting guest time sync service");
+    tokio::spawn(async move {
+        if let Err(e) = run_loop(ptp).await {
+            warn!(sl(), "guest time sync service stopped: {:?}", e);
+        }
+    });
+}
+
+async fn run_loop(ptp: PtpClock) -> Result<()> {
+    let mut last_gap_ns = clock_ns(libc::CLOCK_BOOTTIME)? - clock_ns(libc::CLOCK_MONOTONIC)?;
+    let mut resync_ticks = 0u32;
+
+    loop {
+        let interval = if resync_ticks > 0 {
+            resync_ticks -= 1;
+            RESYNC_INTERVAL
+        } else {
+            SYNC_INTERVAL
+        };
+        tokio::time::sleep(interval).await;
+
+        // Boottime keeps advancing across suspend while monotonic does
+        // not, so a growing gap between the two means we were suspended.
+        let gap_ns = clock_ns(libc::CLOCK_BOOTTIME)? - clock_ns(libc::CLOCK_MONOTONIC)?;
+        if gap_ns - last_gap_ns > SUSPEND_GAP_NS {
+            info!(
+                sl(),
+                "suspend/resume detected (slept ~{}s), resyncing guest clock",
+                (gap_ns - last_gap_ns) / 1_000_000_000
+            );
+            resync_ticks = RESYNC_TICKS;
+        }
+        last_gap_ns = gap_ns;
+
+        // The two reads are not atomic but the scheduling noise in
+        // between is far below the correction tolerance.
+        let drift_ns = ptp.host_time_ns()? - clock_ns(libc::CLOCK_REALTIME)?;
+        match correct_drift(drift_ns) {
+            Ok("none") => (),
+            Ok(action) => info!(
+                sl(),
+                "corrected guest clock drift";
+                "drift_ns" => drift_ns,
+                "action" => action
+            ),
+            Err(e) => warn!(sl(), "failed to correct guest clock drift: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_offset() {
+        assert_eq!(split_offset(0), (0, 0));
+        assert_eq!(split_offset(1_500_000_000), (1, 500_000_000));
+        assert_eq!(split_offset(-1), (-1, 999_999_999));
+        assert_eq!(split_offset(-1_500_000_000), (-2, 500_000_000));
+    }
+}
@@ -77,5 +77,10 @@ macro_rules! trace_rpc_call {
         // assign parent span from external context
         rpc_span.set_parent(parent_context);
         let _enter = rpc_span.enter();
+
+        // per-RPC latency and payload metrics; the guard observes the
+        // handler duration when it drops at the end of the method.
+        let _rpc_call_metric =
+            $crate::metrics::RpcCall::start($name, ::protobuf::Message::compute_size(&$req));
     };
 }
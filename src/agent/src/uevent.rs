@@ -159,6 +159,47 @@ pub async fn wait_for_uevent(
     Ok(uev)
 }
 
+// Open a netlink socket subscribed to the kernel uevent multicast group.
+fn uevent_socket() -> Result<TokioSocket> {
+    let mut socket;
+
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            protocols::NETLINK_KOBJECT_UEVENT as libc::c_int,
+        );
+        socket = TokioSocket::from_raw_fd(fd);
+    }
+
+    socket.bind(&SocketAddr::new(0, 1))?;
+
+    Ok(socket)
+}
+
+// Minimal udev-less uevent reader used while the agent boots as init.
+// devtmpfs already creates the device nodes, so nothing has to act on the
+// events; reading them makes early device activity visible in the boot
+// log, where a hang would otherwise leave no trace. The task is aborted
+// once the sandbox uevent watcher takes over.
+pub async fn early_uevent_reader(logger: Logger) -> Result<()> {
+    let logger = logger.new(o!("subsystem" => "early-uevent"));
+    let mut socket = uevent_socket()?;
+
+    loop {
+        let (buf, addr) = socket.recv_from_full().await?;
+        if addr.port_number() != 0 {
+            // not a kernel netlink message
+            continue;
+        }
+
+        if let Ok(text) = String::from_utf8(buf) {
+            let event = Uevent::new(&text);
+            debug!(logger, "early uevent"; "event" => format!("{:?}", event));
+        }
+    }
+}
+
 #[instrument]
 pub async fn watch_uevents(
     sandbox: Arc<Mutex<Sandbox>>,
@@ -173,18 +214,7 @@ pub async fn watch_uevents(
 
     info!(logger, "starting uevents handler");
 
-    let mut socket;
-
-    unsafe {
-        let fd = libc::socket(
-            libc::AF_NETLINK,
-            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
-            protocols::NETLINK_KOBJECT_UEVENT as libc::c_int,
-        );
-        socket = TokioSocket::from_raw_fd(fd);
-    }
-
-    socket.bind(&SocketAddr::new(0, 1))?;
+    let mut socket = uevent_socket()?;
 
     loop {
         select! {
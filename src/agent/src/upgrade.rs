@@ -0,0 +1,167 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Graceful in-place agent upgrade via re-exec.
+//!
+//! A new agent binary is delivered to the guest (e.g. with CopyFile) at
+//! [`UPGRADE_BINARY_PATH`]; SIGUSR2 then asks the running agent to
+//! serialize its sandbox and container bookkeeping, re-exec the new
+//! binary and resume serving ttrpc on the same vsock port. Because
+//! exec keeps the PID, container processes remain children of the agent
+//! and wait/reap semantics survive the upgrade. Open exec sessions and
+//! their stdio streams do not survive; clients have to reconnect them.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use nix::unistd::execv;
+use runtime_spec::ContainerState;
+use rustjail::container::{BaseContainer, LinuxContainer};
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+use tokio::sync::Mutex;
+
+use crate::rpc::CONTAINER_BASE;
+use crate::sandbox::Sandbox;
+
+/// Where the replacement agent binary is expected.
+pub const UPGRADE_BINARY_PATH: &str = "/run/kata-containers/kata-agent.next";
+
+/// State handed over to the new binary, consumed on startup.
+const UPGRADE_STATE_FILE: &str = "/run/kata-containers/agent-upgrade-state.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ContainerUpgradeState {
+    id: String,
+    config: rustjail::specconv::CreateOpts,
+    init_process_pid: i32,
+    init_process_start_time: u64,
+    status: ContainerState,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpgradeState {
+    sandbox_id: String,
+    hostname: String,
+    running: bool,
+    no_pivot_root: bool,
+    mounts: Vec<String>,
+    container_mounts: HashMap<String, Vec<String>>,
+    containers: Vec<ContainerUpgradeState>,
+}
+
+/// Serialize the sandbox state and replace the current process with the
+/// binary at [`UPGRADE_BINARY_PATH`]. Only returns on failure, with the
+/// current binary still serving.
+pub async fn trigger(logger: &Logger, sandbox: &Arc<Mutex<Sandbox>>) -> Result<()> {
+    let metadata = fs::metadata(UPGRADE_BINARY_PATH)
+        .with_context(|| format!("no upgrade binary at {}", UPGRADE_BINARY_PATH))?;
+    ensure!(
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        "{} is not an executable file",
+        UPGRADE_BINARY_PATH
+    );
+
+    let state = snapshot(sandbox).await;
+    fs::write(UPGRADE_STATE_FILE, serde_json::to_vec(&state)?)
+        .with_context(|| format!("write upgrade state to {}", UPGRADE_STATE_FILE))?;
+
+    info!(logger, "re-executing new agent binary";
+        "binary" => UPGRADE_BINARY_PATH,
+        "containers" => state.containers.len());
+
+    // Keep our own argv so cmdline parsing behaves identically.
+    let args: Vec<CString> = std::env::args()
+        .map(|a| CString::new(a).unwrap_or_default())
+        .collect();
+    let err = execv(&CString::new(UPGRADE_BINARY_PATH)?, &args);
+
+    // Only reached when exec failed: clean up so a later restart does
+    // not consume stale state.
+    let _ = fs::remove_file(UPGRADE_STATE_FILE);
+    Err(anyhow!("exec {} failed: {:?}", UPGRADE_BINARY_PATH, err))
+}
+
+async fn snapshot(sandbox: &Arc<Mutex<Sandbox>>) -> UpgradeState {
+    let s = sandbox.lock().await;
+    let containers = s
+        .containers
+        .iter()
+        .map(|(id, ctr)| ContainerUpgradeState {
+            id: id.clone(),
+            config: ctr.config.clone(),
+            init_process_pid: ctr.init_process_pid,
+            init_process_start_time: ctr.init_process_start_time,
+            status: ctr.status(),
+        })
+        .collect();
+
+    UpgradeState {
+        sandbox_id: s.id.clone(),
+        hostname: s.hostname.clone(),
+        running: s.running,
+        no_pivot_root: s.no_pivot_root,
+        mounts: s.mounts.clone(),
+        container_mounts: s.container_mounts.clone(),
+        containers,
+    }
+}
+
+/// State left behind by the previous binary, if this startup is the
+/// second half of an upgrade. The file is consumed so that a crash loop
+/// cannot replay stale state.
+pub fn pending_state(logger: &Logger) -> Option<UpgradeState> {
+    let data = fs::read(UPGRADE_STATE_FILE).ok()?;
+    let _ = fs::remove_file(UPGRADE_STATE_FILE);
+
+    match serde_json::from_slice(&data) {
+        Ok(state) => {
+            info!(logger, "resuming from agent upgrade state");
+            Some(state)
+        }
+        Err(e) => {
+            error!(logger, "discarding unreadable upgrade state";
+                "error" => format!("{:?}", e));
+            None
+        }
+    }
+}
+
+/// Re-attach the sandbox bookkeeping captured by the previous binary.
+/// Container processes kept running across the exec; only their
+/// in-memory representation is rebuilt here.
+pub fn resume(logger: &Logger, sandbox: &mut Sandbox, state: UpgradeState) {
+    sandbox.id = state.sandbox_id;
+    sandbox.hostname = state.hostname;
+    sandbox.running = state.running;
+    sandbox.no_pivot_root = state.no_pivot_root;
+    sandbox.mounts = state.mounts;
+    sandbox.container_mounts = state.container_mounts;
+
+    for c in state.containers {
+        match LinuxContainer::new(
+            c.id.as_str(),
+            CONTAINER_BASE,
+            Some(sandbox.devcg_info.clone()),
+            c.config,
+            logger,
+        ) {
+            Ok(mut ctr) => {
+                ctr.restore_runtime_state(c.init_process_pid, c.init_process_start_time, c.status);
+                sandbox.containers.insert(c.id, ctr);
+            }
+            Err(e) => {
+                // The workload process is still alive but unmanageable;
+                // leave it out rather than abort the whole resume.
+                error!(logger, "failed to re-attach container after upgrade";
+                    "container-id" => c.id, "error" => format!("{:?}", e));
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@
 
 use anyhow::{anyhow, Result};
 use futures::StreamExt;
+use std::collections::VecDeque;
 use std::io;
 use std::io::ErrorKind;
 use std::os::unix::io::{FromRawFd, RawFd};
@@ -57,6 +58,104 @@ where
     Ok(total_bytes)
 }
 
+/// Overflow policy for [`interruptable_ring_copier`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RingPolicy {
+    /// Stop reading while the ring is full, pushing back on the producer
+    /// until the consumer catches up. Nothing is lost; this is the flow
+    /// control that container stdout and stderr get from their pipes.
+    Block,
+    /// Keep reading and discard the oldest buffered bytes, so a slow or
+    /// absent consumer never stalls the producer. Suited to lossy
+    /// streams such as logs.
+    DropOldest,
+}
+
+/// Interruptable I/O copy through a bounded ring buffer, decoupling the
+/// reader from the writer: the ring absorbs bursts of up to `capacity`
+/// bytes and `policy` decides what a full ring does to the reader.
+/// `on_drop` is called with each count of discarded bytes as it happens,
+/// so callers can export live drop counters. Returns the number of bytes
+/// written and the number dropped.
+pub async fn interruptable_ring_copier<R: Sized, W: Sized, F>(
+    mut reader: R,
+    mut writer: W,
+    capacity: usize,
+    policy: RingPolicy,
+    mut on_drop: F,
+    mut shutdown: Receiver<bool>,
+) -> io::Result<(u64, u64)>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+    F: FnMut(u64),
+{
+    let mut ring: VecDeque<u8> = VecDeque::with_capacity(capacity);
+    let mut buf: [u8; BUF_SIZE] = [0; BUF_SIZE];
+    let mut written: u64 = 0;
+    let mut dropped: u64 = 0;
+    let mut eof = false;
+
+    loop {
+        if eof && ring.is_empty() {
+            break;
+        }
+
+        // A blocking ring never reads more than it has room for, so it
+        // can never overflow; a lossy one always drains the reader.
+        let read_limit = match policy {
+            RingPolicy::Block => (capacity - ring.len()).min(BUF_SIZE),
+            RingPolicy::DropOldest => BUF_SIZE,
+        };
+
+        tokio::select! {
+            _ = shutdown.changed() => break,
+
+            result = reader.read(&mut buf[..read_limit]), if !eof && read_limit > 0 => {
+                match result {
+                    Ok(0) => eof = true,
+                    Ok(len) => {
+                        let total = ring.len() + len;
+                        if total > capacity {
+                            // Oldest bytes go first: from the ring, then
+                            // from the front of the new chunk should that
+                            // alone overfill the ring.
+                            let overflow = total - capacity;
+                            let from_ring = overflow.min(ring.len());
+                            ring.drain(..from_ring);
+                            ring.extend(&buf[overflow - from_ring..len]);
+                            dropped += overflow as u64;
+                            on_drop(overflow as u64);
+                        } else {
+                            ring.extend(&buf[..len]);
+                        }
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            },
+
+            result = write_ring_front(&mut writer, &ring), if !ring.is_empty() => {
+                let len = result?;
+                ring.drain(..len);
+                written += len as u64;
+            },
+        };
+    }
+
+    Ok((written, dropped))
+}
+
+// Write the contiguous front of the ring; a helper keeps the ring borrow
+// confined to one select branch.
+async fn write_ring_front<W>(writer: &mut W, ring: &VecDeque<u8>) -> io::Result<usize>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let (front, _) = ring.as_slices();
+    writer.write(front).await
+}
+
 #[instrument]
 pub fn get_vsock_incoming(fd: RawFd) -> Incoming {
     unsafe { VsockListener::from_raw_fd(fd).incoming() }
@@ -191,6 +290,106 @@ mod tests {
         }
     }
 
+    // An async writer that is never ready, standing in for a consumer
+    // that stopped draining its stream.
+    struct PendingWriter;
+
+    impl tokio::io::AsyncWrite for PendingWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<Result<usize, io::Error>> {
+            Poll::Pending
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+            Poll::Pending
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), io::Error>> {
+            Poll::Pending
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ring_copier_copies_everything() {
+        let (tx, rx) = channel(true);
+        let reader = Cursor::new("hello world".to_string());
+        let writer = BufWriter::new();
+
+        let (written, dropped) = interruptable_ring_copier(
+            reader,
+            writer.clone(),
+            BUF_SIZE,
+            RingPolicy::DropOldest,
+            |_| {},
+            rx,
+        )
+        .await
+        .unwrap();
+
+        drop(tx);
+        assert_eq!(written, "hello world".len() as u64);
+        assert_eq!(dropped, 0);
+        assert_eq!(writer.to_string(), "hello world");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ring_copier_drop_oldest() {
+        let (tx, rx) = channel(true);
+        let reader = Cursor::new("0123456789".to_string());
+
+        let drops = Arc::new(Mutex::new(0u64));
+        let drops_seen = drops.clone();
+        let handle = tokio::spawn(interruptable_ring_copier(
+            reader,
+            PendingWriter,
+            4,
+            RingPolicy::DropOldest,
+            move |n| *drops_seen.lock().unwrap() += n,
+            rx,
+        ));
+
+        // Allow the copier to drain the reader, then shut it down: the
+        // writer never accepts anything.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(true).expect("failed to request shutdown");
+
+        let (written, dropped) = handle.await.unwrap().unwrap();
+        assert_eq!(written, 0);
+        // Ten bytes read into a four byte ring: the six oldest went.
+        assert_eq!(dropped, 6);
+        assert_eq!(*drops.lock().unwrap(), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ring_copier_block_policy_drops_nothing() {
+        let (tx, rx) = channel(true);
+        let reader = Cursor::new("0123456789".to_string());
+
+        let handle = tokio::spawn(interruptable_ring_copier(
+            reader,
+            PendingWriter,
+            4,
+            RingPolicy::Block,
+            |_| panic!("a blocking ring must not drop"),
+            rx,
+        ));
+
+        // The ring fills to its capacity and then pushes back on the
+        // reader instead of discarding data.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(true).expect("failed to request shutdown");
+
+        let (written, dropped) = handle.await.unwrap().unwrap();
+        assert_eq!(written, 0);
+        assert_eq!(dropped, 0);
+    }
+
     #[rstest]
     #[case("".into())]
     #[case("a".into())]
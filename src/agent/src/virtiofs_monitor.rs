@@ -0,0 +1,188 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Watchdog for virtiofs shared-fs mounts.
+//!
+//! When virtiofsd dies on the host, FUSE requests never complete and
+//! reads inside containers hang forever. This module probes every
+//! registered virtiofs mount in the background; when a probe hangs it
+//! aborts the FUSE connection so that pending and future IO fails with
+//! ENOTCONN instead of blocking, and then keeps trying to re-establish
+//! the superblock so the mount recovers once the shim has restarted
+//! virtiofsd.
+
+use std::fs;
+use std::os::linux::fs::MetadataExt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use nix::mount::{umount2, MntFlags};
+use slog::Logger;
+
+use crate::mount::baremount;
+use kata_sys_util::mount::parse_mount_options;
+
+/// How often every registered mount is probed.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// A probe that takes longer than this is treated as a hung connection.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const FUSE_CONNECTIONS_DIR: &str = "/sys/fs/fuse/connections";
+
+#[derive(Clone, Debug)]
+struct VirtiofsMount {
+    source: String,
+    mount_point: String,
+    fstype: String,
+    options: Vec<String>,
+    /// FUSE connection id, captured while the mount was healthy because
+    /// it cannot be queried anymore once the connection hangs.
+    conn_id: u64,
+    healthy: bool,
+}
+
+lazy_static! {
+    static ref MOUNTS: Mutex<Vec<VirtiofsMount>> = Mutex::new(Vec::new());
+}
+
+/// Register a virtiofs mount for monitoring. Called right after the
+/// mount succeeded, while stat on the mount point is still safe.
+pub fn register_mount(
+    logger: &Logger,
+    source: &str,
+    mount_point: &str,
+    fstype: &str,
+    options: &[String],
+) {
+    let conn_id = match fs::metadata(mount_point) {
+        // The control directory of an anonymous FUSE device is named
+        // after its minor number.
+        Ok(metadata) => libc::minor(metadata.st_dev()) as u64,
+        Err(e) => {
+            warn!(logger, "virtiofs monitor: cannot stat new mount";
+                "mount-point" => mount_point, "error" => format!("{:?}", e));
+            return;
+        }
+    };
+
+    let mut mounts = MOUNTS.lock().unwrap();
+    if mounts.iter().any(|m| m.mount_point == mount_point) {
+        return;
+    }
+    info!(logger, "virtiofs monitor: watching mount";
+        "mount-point" => mount_point, "connection" => conn_id);
+    mounts.push(VirtiofsMount {
+        source: source.to_string(),
+        mount_point: mount_point.to_string(),
+        fstype: fstype.to_string(),
+        options: options.to_vec(),
+        conn_id,
+        healthy: true,
+    });
+}
+
+/// Spawn the monitoring task. Idles cheaply until the first virtiofs
+/// mount gets registered.
+pub fn start(logger: &Logger) {
+    let logger = logger.new(o!("subsystem" => "virtiofs-monitor"));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PROBE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            probe_mounts(&logger).await;
+        }
+    });
+}
+
+async fn probe_mounts(logger: &Logger) {
+    let mounts: Vec<VirtiofsMount> = MOUNTS.lock().unwrap().clone();
+
+    for mount in mounts {
+        if mount.healthy {
+            if !probe_alive(&mount.mount_point).await {
+                handle_connection_loss(logger, &mount);
+            }
+        } else if let Err(e) = try_reestablish(logger, &mount) {
+            debug!(logger, "virtiofs mount not recovered yet";
+                "mount-point" => mount.mount_point.as_str(),
+                "error" => format!("{:?}", e));
+        }
+    }
+}
+
+/// Returns false if statfs on the mount point hangs or reports the
+/// connection as aborted.
+async fn probe_alive(mount_point: &str) -> bool {
+    let path = mount_point.to_string();
+    // The probe deliberately runs on the blocking pool: if the FUSE
+    // connection is dead, the statfs call may never return and must not
+    // wedge an async worker.
+    let probe = tokio::task::spawn_blocking(move || nix::sys::statfs::statfs(path.as_str()));
+
+    match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(Ok(_))) => true,
+        Ok(Ok(Err(_))) | Ok(Err(_)) | Err(_) => false,
+    }
+}
+
+/// The mount hung: abort the FUSE connection so blocked readers get
+/// ENOTCONN, and mark it for re-establishment.
+fn handle_connection_loss(logger: &Logger, mount: &VirtiofsMount) {
+    error!(logger, "virtiofs connection lost, aborting it so IO fails instead of hanging";
+        "mount-point" => mount.mount_point.as_str(),
+        "source" => mount.source.as_str());
+
+    let abort_path = format!("{}/{}/abort", FUSE_CONNECTIONS_DIR, mount.conn_id);
+    if let Err(e) = fs::write(&abort_path, "1") {
+        warn!(logger, "failed to abort FUSE connection";
+            "path" => abort_path, "error" => format!("{:?}", e));
+    }
+
+    set_healthy(&mount.mount_point, false);
+}
+
+/// Try to mount the share again. This only succeeds once the shim has
+/// restarted virtiofsd and the new device advertises the same tag.
+fn try_reestablish(logger: &Logger, mount: &VirtiofsMount) -> Result<()> {
+    // Drop the aborted superblock first; lazy detach keeps the paths of
+    // open file descriptors stable while they drain with ENOTCONN.
+    umount2(Path::new(&mount.mount_point), MntFlags::MNT_DETACH)
+        .with_context(|| format!("lazy umount {}", mount.mount_point))?;
+
+    let (flags, options) = parse_mount_options(&mount.options)?;
+    baremount(
+        Path::new(&mount.source),
+        Path::new(&mount.mount_point),
+        &mount.fstype,
+        flags,
+        &options,
+        logger,
+    )
+    .context("remount virtiofs share")?;
+
+    if let Ok(metadata) = fs::metadata(&mount.mount_point) {
+        update_conn_id(&mount.mount_point, libc::minor(metadata.st_dev()) as u64);
+    }
+    set_healthy(&mount.mount_point, true);
+    info!(logger, "virtiofs mount re-established";
+        "mount-point" => mount.mount_point.as_str());
+    Ok(())
+}
+
+fn set_healthy(mount_point: &str, healthy: bool) {
+    let mut mounts = MOUNTS.lock().unwrap();
+    if let Some(m) = mounts.iter_mut().find(|m| m.mount_point == mount_point) {
+        m.healthy = healthy;
+    }
+}
+
+fn update_conn_id(mount_point: &str, conn_id: u64) {
+    let mut mounts = MOUNTS.lock().unwrap();
+    if let Some(m) = mounts.iter_mut().find(|m| m.mount_point == mount_point) {
+        m.conn_id = conn_id;
+    }
+}
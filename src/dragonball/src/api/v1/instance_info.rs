@@ -58,6 +58,9 @@ pub struct InstanceInfo {
     pub tids: Vec<(u8, u32)>,
     /// Last instance downtime
     pub last_instance_downtime: u64,
+    /// TSC frequency of the vcpus in KHz, recorded so that snapshot metadata
+    /// can be validated against the host frequency on restore.
+    pub tsc_khz: Option<u32>,
 }
 
 impl InstanceInfo {
@@ -72,6 +75,7 @@ impl InstanceInfo {
             async_state: AsyncState::Uninitialized,
             tids: Vec::new(),
             last_instance_downtime: 0,
+            tsc_khz: None,
         }
     }
 }
@@ -87,6 +91,7 @@ impl Default for InstanceInfo {
             async_state: AsyncState::Uninitialized,
             tids: Vec::new(),
             last_instance_downtime: 0,
+            tsc_khz: None,
         }
     }
 }
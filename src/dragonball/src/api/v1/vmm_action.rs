@@ -27,6 +27,11 @@ use self::VmmActionError::MachineConfig;
 
 #[cfg(feature = "virtio-balloon")]
 pub use crate::device_manager::balloon_dev_mgr::{BalloonDeviceConfigInfo, BalloonDeviceError};
+#[cfg(feature = "virtio-crypto")]
+pub use crate::device_manager::crypto_dev_mgr::{CryptoDeviceConfigInfo, CryptoDeviceError};
+
+#[cfg(all(feature = "fault-injection", feature = "virtio-blk"))]
+pub use crate::device_manager::blk_dev_mgr::BlockDeviceFaultInjectInfo;
 #[cfg(any(feature = "virtio-blk", feature = "vhost-user-blk"))]
 pub use crate::device_manager::blk_dev_mgr::{
     BlockDeviceConfigInfo, BlockDeviceConfigUpdateInfo, BlockDeviceError, BlockDeviceMgr,
@@ -47,6 +52,8 @@ pub use crate::device_manager::vhost_net_dev_mgr::{
 use crate::device_manager::vhost_user_net_dev_mgr::{
     VhostUserNetDeviceConfigInfo, VhostUserNetDeviceError, VhostUserNetDeviceMgr,
 };
+#[cfg(all(feature = "fault-injection", feature = "virtio-net"))]
+pub use crate::device_manager::virtio_net_dev_mgr::VirtioNetDeviceFaultInjectInfo;
 #[cfg(feature = "virtio-net")]
 pub use crate::device_manager::virtio_net_dev_mgr::{
     VirtioNetDeviceConfigInfo, VirtioNetDeviceConfigUpdateInfo, VirtioNetDeviceError,
@@ -145,6 +152,11 @@ pub enum VmmActionError {
     /// Balloon device related errors.
     #[error("virtio-balloon device error: {0}")]
     Balloon(#[source] BalloonDeviceError),
+
+    #[cfg(feature = "virtio-crypto")]
+    /// Crypto device related errors.
+    #[error("virtio-crypto device error: {0}")]
+    Crypto(#[source] CryptoDeviceError),
     /// Setup tracing Failed.
     #[error("Setup tracing failed: {0}")]
     SetupTracingFailed(#[source] TraceError),
@@ -212,6 +224,11 @@ pub enum VmmAction {
     /// are the RX and TX rate limiters.
     UpdateBlockDevice(BlockDeviceConfigUpdateInfo),
 
+    #[cfg(all(feature = "fault-injection", feature = "virtio-blk"))]
+    /// Update the fault injection settings of a block device, after microVM start. Only
+    /// meant for chaos testing against degraded virtual devices.
+    UpdateBlockDeviceFaultInject(BlockDeviceFaultInjectInfo),
+
     #[cfg(any(
         feature = "virtio-net",
         feature = "vhost-net",
@@ -229,6 +246,11 @@ pub enum VmmAction {
     /// https://github.com/kata-containers/kata-containers/issues/8327
     UpdateNetworkInterface(VirtioNetDeviceConfigUpdateInfo),
 
+    #[cfg(all(feature = "fault-injection", feature = "virtio-net"))]
+    /// Update the fault injection settings of a virtio net device, after microVM start.
+    /// Only meant for chaos testing against degraded virtual devices.
+    UpdateNetworkFaultInject(VirtioNetDeviceFaultInjectInfo),
+
     #[cfg(any(feature = "virtio-fs", feature = "vhost-user-fs"))]
     /// Add a new shared fs device or update one that already exists using the
     /// `FsDeviceConfig` as input. This action can only be called before the microVM has
@@ -258,6 +280,10 @@ pub enum VmmAction {
     /// as input.
     InsertBalloonDevice(BalloonDeviceConfigInfo),
 
+    #[cfg(feature = "virtio-crypto")]
+    /// Add a new crypto device using the `CryptoDeviceConfig` as input.
+    InsertCryptoDevice(CryptoDeviceConfigInfo),
+
     #[cfg(feature = "host-device")]
     /// Add a VFIO assignment host device or update that already exists
     InsertHostDevice(HostDeviceConfig),
@@ -361,6 +387,10 @@ impl VmmService {
             VmmAction::RemoveBlockDevice(drive_id) => {
                 self.remove_block_device(vmm, event_mgr, &drive_id)
             }
+            #[cfg(all(feature = "fault-injection", feature = "virtio-blk"))]
+            VmmAction::UpdateBlockDeviceFaultInject(blk_fault) => {
+                self.update_blk_fault_inject(vmm, blk_fault)
+            }
             #[cfg(any(
                 feature = "virtio-net",
                 feature = "vhost-net",
@@ -380,6 +410,10 @@ impl VmmService {
             VmmAction::UpdateNetworkInterface(netif_update) => {
                 self.update_net_rate_limiters(vmm, netif_update)
             }
+            #[cfg(all(feature = "fault-injection", feature = "virtio-net"))]
+            VmmAction::UpdateNetworkFaultInject(netif_fault) => {
+                self.update_net_fault_inject(vmm, netif_fault)
+            }
             #[cfg(any(feature = "virtio-fs", feature = "vhost-user-fs"))]
             VmmAction::InsertFsDevice(fs_cfg) => self.add_fs_device(vmm, fs_cfg),
 
@@ -399,6 +433,10 @@ impl VmmService {
             VmmAction::InsertBalloonDevice(balloon_cfg) => {
                 self.add_balloon_device(vmm, event_mgr, balloon_cfg)
             }
+            #[cfg(feature = "virtio-crypto")]
+            VmmAction::InsertCryptoDevice(crypto_cfg) => {
+                self.add_crypto_device(vmm, event_mgr, crypto_cfg)
+            }
             #[cfg(feature = "host-device")]
             VmmAction::InsertHostDevice(mut hostdev_cfg) => {
                 self.add_vfio_device(vmm, &mut hostdev_cfg)
@@ -571,6 +609,7 @@ impl VmmService {
             return Err(MachineConfig(InvalidMemFilePath("".to_owned())));
         }
         config.vpmu_feature = machine_config.vpmu_feature;
+        config.tsc_invariant = machine_config.tsc_invariant;
 
         // If serial_path is:
         // - None, legacy_manager will create_stdio_console.
@@ -686,6 +725,24 @@ impl VmmService {
             .map_err(VmmActionError::Block)
     }
 
+    #[cfg(all(feature = "fault-injection", feature = "virtio-blk"))]
+    /// Updates the fault injection settings of an emulated block device as described
+    /// in `config`.
+    #[instrument(skip(self))]
+    fn update_blk_fault_inject(
+        &mut self,
+        vmm: &mut Vmm,
+        config: BlockDeviceFaultInjectInfo,
+    ) -> VmmRequestResult {
+        let vm = vmm.get_vm_mut().ok_or(VmmActionError::InvalidVMID)?;
+
+        vm.device_manager_mut()
+            .block_manager
+            .update_device_fault_inject(config)
+            .map(|_| VmmData::Empty)
+            .map_err(VmmActionError::Block)
+    }
+
     #[cfg(any(feature = "virtio-blk", feature = "vhost-user-blk"))]
     // Remove the device
     #[instrument(skip(self, event_mgr))]
@@ -751,6 +808,24 @@ impl VmmService {
             .map_err(VmmActionError::VirtioNet)
     }
 
+    #[cfg(all(feature = "fault-injection", feature = "virtio-net"))]
+    /// Updates the fault injection settings of an emulated net device as described
+    /// in `config`.
+    #[instrument(skip(self))]
+    fn update_net_fault_inject(
+        &mut self,
+        vmm: &mut Vmm,
+        config: VirtioNetDeviceFaultInjectInfo,
+    ) -> VmmRequestResult {
+        let vm = vmm.get_vm_mut().ok_or(VmmActionError::InvalidVMID)?;
+
+        vm.device_manager_mut()
+            .virtio_net_manager
+            .update_device_fault_inject(config)
+            .map(|_| VmmData::Empty)
+            .map_err(VmmActionError::VirtioNet)
+    }
+
     #[cfg(feature = "vhost-net")]
     fn add_vhost_net_device(
         &mut self,
@@ -1033,6 +1108,33 @@ impl VmmService {
             .map(|_| VmmData::Empty)
             .map_err(VmmActionError::Balloon)
     }
+
+    #[cfg(feature = "virtio-crypto")]
+    #[instrument(skip(self, event_mgr))]
+    fn add_crypto_device(
+        &mut self,
+        vmm: &mut Vmm,
+        event_mgr: &mut EventManager,
+        config: CryptoDeviceConfigInfo,
+    ) -> VmmRequestResult {
+        let vm = vmm.get_vm_mut().ok_or(VmmActionError::InvalidVMID)?;
+
+        let ctx = vm
+            .create_device_op_context(Some(event_mgr.epoll_manager()))
+            .map_err(|e| {
+                if let StartMicroVmError::UpcallServerNotReady = e {
+                    VmmActionError::UpcallServerNotReady
+                } else {
+                    VmmActionError::StartMicroVm(e)
+                }
+            })?;
+
+        vm.device_manager_mut()
+            .crypto_manager
+            .insert_or_update_device(ctx, config)
+            .map(|_| VmmData::Empty)
+            .map_err(VmmActionError::Crypto)
+    }
 }
 
 fn handle_cpu_topology(
@@ -224,6 +224,12 @@ pub mod leaf_0xa {
         pub const BR_INST_RETIRED_BITINDEX: u32 = 5;
         pub const BR_MIS_RETIRED_BITINDEX: u32 = 6;
     }
+
+    pub mod edx {
+        use crate::cpuid::bit_helper::BitRange;
+        pub const NUM_FIXED_COUNTERS: BitRange = bit_range!(4, 0);
+        pub const FIXED_CTR_BITLEN: BitRange = bit_range!(12, 5);
+    }
 }
 
 // Extended Topology Leaf
@@ -381,6 +387,16 @@ pub mod leaf_0x80000001 {
     }
 }
 
+// Advanced Power Management Information Leaf
+pub mod leaf_0x80000007 {
+    pub const LEAF_NUM: u32 = 0x8000_0007;
+
+    pub mod edx {
+        // The TSC rate is invariant across P-states, C-states and stop-grant transitions
+        pub const INVARIANT_TSC_BITINDEX: u32 = 8;
+    }
+}
+
 pub mod leaf_0x80000008 {
     pub const LEAF_NUM: u32 = 0x8000_0008;
 
@@ -38,7 +38,7 @@ type CpuIdEntry = kvm_bindings::kvm_cpuid_entry2;
 /// let kvm = Kvm::new().unwrap();
 /// let mut kvm_cpuid: CpuId = kvm.get_supported_cpuid(KVM_MAX_CPUID_ENTRIES).unwrap();
 ///
-/// let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).unwrap();
+/// let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false).unwrap();
 ///
 /// process_cpuid(&mut kvm_cpuid, &vm_spec).unwrap();
 ///
@@ -69,7 +69,7 @@ mod tests {
     #[test]
     fn test_invalid_cpuid() {
         let mut cpuid = CpuId::new(0).unwrap();
-        let vm_spec = VmSpec::new(0, 2, 1, 1, 1, VpmuFeatureLevel::Disabled).unwrap();
+        let vm_spec = VmSpec::new(0, 2, 1, 1, 1, VpmuFeatureLevel::Disabled, false).unwrap();
 
         process_cpuid(&mut cpuid, &vm_spec).unwrap();
     }
@@ -148,6 +148,7 @@ impl CpuidTransformer for AmdCpuidTransformer {
             leaf_0x1f::LEAF_NUM => Some(common::update_extended_topology_v2_entry),
             leaf_0x80000000::LEAF_NUM => Some(update_largest_extended_fn_entry),
             leaf_0x80000001::LEAF_NUM => Some(update_extended_feature_info_entry),
+            leaf_0x80000007::LEAF_NUM => Some(common::update_invariant_tsc_entry),
             leaf_0x80000008::LEAF_NUM => Some(update_amd_features_entry),
             leaf_0x8000001d::LEAF_NUM => Some(update_extended_cache_topology_entry),
             leaf_0x8000001e::LEAF_NUM => Some(update_extended_apic_id_entry),
@@ -167,8 +168,8 @@ mod test {
 
         let transformer = AmdCpuidTransformer::new();
 
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let mut cpuid = CpuId::from_entries(&[CpuIdEntry {
             function: cpu_leaf::leaf_0x7::LEAF_NUM,
             index: 0,
@@ -189,8 +190,8 @@ mod test {
         use cpu_leaf::leaf_0x7::index0::*;
 
         // Check that if index == 0 the entry is processed
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
             function: cpu_leaf::leaf_0x7::LEAF_NUM,
             index: 0,
@@ -215,8 +216,8 @@ mod test {
     fn test_update_largest_extended_fn_entry() {
         use crate::cpuid::cpu_leaf::leaf_0x80000000::*;
 
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
             function: LEAF_NUM,
             index: 0,
@@ -242,8 +243,8 @@ mod test {
     fn test_update_extended_feature_info_entry() {
         use crate::cpuid::cpu_leaf::leaf_0x80000001::*;
 
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
             function: LEAF_NUM,
             index: 0,
@@ -275,6 +276,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
@@ -318,6 +320,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
@@ -362,8 +365,8 @@ mod test {
 
     #[test]
     fn test_update_extended_cache_topology_entry() {
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let entry = &mut CpuIdEntry {
             function: cpu_leaf::leaf_0x8000001d::LEAF_NUM,
             index: 0,
@@ -244,6 +244,23 @@ pub fn update_cache_parameters_entry(
     Ok(())
 }
 
+/// Prepare content for CPUID extended level 8000_0007h: get advanced power management information.
+pub fn update_invariant_tsc_entry(entry: &mut CpuIdEntry, vm_spec: &VmSpec) -> Result<(), Error> {
+    use cpu_leaf::leaf_0x80000007::*;
+
+    // EDX bit 8: invariant TSC. A guest that sees this bit assumes the TSC
+    // runs at a constant rate forever, which breaks snapshots restored on a
+    // host with a different TSC frequency. Only keep the bit when it was
+    // explicitly requested and the host actually provides the guarantee.
+    let host_invariant_tsc = entry.edx.read_bit(edx::INVARIANT_TSC_BITINDEX);
+    entry.edx.write_bit(
+        edx::INVARIANT_TSC_BITINDEX,
+        vm_spec.tsc_invariant && host_invariant_tsc,
+    );
+
+    Ok(())
+}
+
 /// Replaces the `cpuid` entries corresponding to `function` with the entries from the host's cpuid.
 pub fn use_host_cpuid_function(
     cpuid: &mut CpuId,
@@ -306,6 +323,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
@@ -342,6 +360,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
@@ -385,6 +404,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
@@ -431,6 +451,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
@@ -575,6 +596,35 @@ mod test {
         check_update_extended_topology_v2_entry(4, 5, 2, 4, LEVEL_TYPE_DIE, 2, 1, 2);
     }
 
+    #[test]
+    fn test_update_invariant_tsc_entry() {
+        use crate::cpuid::cpu_leaf::leaf_0x80000007::*;
+
+        let check = |tsc_invariant: bool, host_bit: bool, expected: bool| {
+            let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, tsc_invariant)
+                .expect("Error creating vm_spec");
+            let entry = &mut kvm_cpuid_entry2 {
+                function: LEAF_NUM,
+                index: 0,
+                flags: 0,
+                eax: 0,
+                ebx: 0,
+                ecx: 0,
+                edx: *(0_u32).write_bit(edx::INVARIANT_TSC_BITINDEX, host_bit),
+                padding: [0, 0, 0],
+            };
+
+            assert!(update_invariant_tsc_entry(entry, &vm_spec).is_ok());
+            assert_eq!(entry.edx.read_bit(edx::INVARIANT_TSC_BITINDEX), expected);
+        };
+
+        // The bit is only kept when explicitly requested and the host provides it.
+        check(false, false, false);
+        check(false, true, false);
+        check(true, false, false);
+        check(true, true, true);
+    }
+
     #[test]
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     fn test_use_host_cpuid_function_with_count() {
@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::super::bit_helper::BitHelper;
+use super::super::common::get_cpuid;
 use super::super::cpu_leaf;
 use super::*;
 
@@ -35,10 +36,38 @@ fn update_power_management_entry(entry: &mut CpuIdEntry, _vm_spec: &VmSpec) -> R
 }
 
 fn update_perf_mon_entry(entry: &mut CpuIdEntry, vm_spec: &VmSpec) -> Result<(), Error> {
+    // The host's own view of the leaf shows whether PMU virtualization is
+    // allowed: AMD hosts and hosts with the kvm `enable_pmu` module
+    // parameter cleared report a zero version id here.
+    let (host_eax, host_edx) = get_cpuid(cpu_leaf::leaf_0xa::LEAF_NUM, 0)
+        .map(|r| (r.eax, r.edx))
+        .unwrap_or((0, 0));
+    do_update_perf_mon_entry(entry, vm_spec, host_eax, host_edx)
+}
+
+fn do_update_perf_mon_entry(
+    entry: &mut CpuIdEntry,
+    vm_spec: &VmSpec,
+    host_eax: u32,
+    host_edx: u32,
+) -> Result<(), Error> {
     use cpu_leaf::leaf_0xa::*;
 
+    // Degrade gracefully to Disabled when the host has no architectural
+    // PMU to virtualize, rather than handing the guest counters that can
+    // never count. The exposed version never exceeds what the host
+    // reports, capped at v2 (the highest level the transformer knows how
+    // to populate).
+    let host_version = host_eax.read_bits_in_range(&eax::PMC_VERSION_ID);
+    let vpmu_feature = if host_version == 0 {
+        VpmuFeatureLevel::Disabled
+    } else {
+        vm_spec.vpmu_feature
+    };
+    let version = std::cmp::min(host_version, 2);
+
     // Architectural Performance Monitor Leaf
-    match vm_spec.vpmu_feature {
+    match vpmu_feature {
         VpmuFeatureLevel::Disabled => {
             // Disable PMU
             entry.eax = 0;
@@ -48,7 +77,7 @@ fn update_perf_mon_entry(entry: &mut CpuIdEntry, vm_spec: &VmSpec) -> Result<(),
         }
         VpmuFeatureLevel::LimitedlyEnabled => {
             // Allow minimal vpmu ability (only instuctions and cycles pmu).
-            entry.eax.write_bits_in_range(&eax::PMC_VERSION_ID, 2);
+            entry.eax.write_bits_in_range(&eax::PMC_VERSION_ID, version);
             entry.eax.write_bits_in_range(&eax::BIT_LEN_PMEVENT, 7);
 
             // 0(false) means support for the targeted performance monitoring event
@@ -59,10 +88,13 @@ fn update_perf_mon_entry(entry: &mut CpuIdEntry, vm_spec: &VmSpec) -> Result<(),
             entry.ebx.write_bit(ebx::LLC_MISSES_BITINDEX, true);
             entry.ebx.write_bit(ebx::LLC_REF_BITINDEX, true);
             entry.ebx.write_bit(ebx::BR_MIS_RETIRED_BITINDEX, true);
+
+            // No fixed-function counters at this level.
+            entry.edx = 0;
         }
         VpmuFeatureLevel::FullyEnabled => {
             // Allow all supported vpmu ability
-            entry.eax.write_bits_in_range(&eax::PMC_VERSION_ID, 2);
+            entry.eax.write_bits_in_range(&eax::PMC_VERSION_ID, version);
             entry.eax.write_bits_in_range(&eax::BIT_LEN_PMEVENT, 7);
 
             // 0(false) means support for the targeted performance monitoring event
@@ -73,6 +105,20 @@ fn update_perf_mon_entry(entry: &mut CpuIdEntry, vm_spec: &VmSpec) -> Result<(),
             entry.ebx.write_bit(ebx::LLC_MISSES_BITINDEX, false);
             entry.ebx.write_bit(ebx::LLC_REF_BITINDEX, false);
             entry.ebx.write_bit(ebx::BR_MIS_RETIRED_BITINDEX, false);
+
+            // Mirror the host's fixed-function counters (architectural
+            // PMU v2 and later) so perf inside the guest can program them.
+            entry.edx = 0;
+            if version >= 2 {
+                entry.edx.write_bits_in_range(
+                    &edx::NUM_FIXED_COUNTERS,
+                    host_edx.read_bits_in_range(&edx::NUM_FIXED_COUNTERS),
+                );
+                entry.edx.write_bits_in_range(
+                    &edx::FIXED_CTR_BITLEN,
+                    host_edx.read_bits_in_range(&edx::FIXED_CTR_BITLEN),
+                );
+            }
         }
     };
     Ok(())
@@ -104,6 +150,7 @@ impl CpuidTransformer for IntelCpuidTransformer {
             leaf_0xb::LEAF_NUM => Some(common::update_extended_topology_entry),
             leaf_0x1f::LEAF_NUM => Some(common::update_extended_topology_v2_entry),
             0x8000_0002..=0x8000_0004 => Some(common::update_brand_string_entry),
+            leaf_0x80000007::LEAF_NUM => Some(common::update_invariant_tsc_entry),
             _ => None,
         }
     }
@@ -119,9 +166,17 @@ mod test {
     #[test]
     fn test_update_perf_mon_entry() {
         use crate::cpuid::cpu_leaf::leaf_0xa::*;
+
+        // A capable host: architectural PMU v2 with 3 x 48-bit
+        // fixed-function counters.
+        let host_eax = *(0_u32).write_bits_in_range(&eax::PMC_VERSION_ID, 2);
+        let host_edx = *(0_u32)
+            .write_bits_in_range(&edx::NUM_FIXED_COUNTERS, 3)
+            .write_bits_in_range(&edx::FIXED_CTR_BITLEN, 48);
+
         // Test when vpmu is off (level Disabled)
-        let vm_spec =
-            VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).expect("Error creating vm_spec");
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false)
+            .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
             function: LEAF_NUM,
             index: 0,
@@ -133,7 +188,7 @@ mod test {
             padding: [0, 0, 0],
         };
 
-        assert!(update_perf_mon_entry(entry, &vm_spec).is_ok());
+        assert!(do_update_perf_mon_entry(entry, &vm_spec, host_eax, host_edx).is_ok());
 
         assert_eq!(entry.eax, 0);
         assert_eq!(entry.ebx, 0);
@@ -141,7 +196,7 @@ mod test {
         assert_eq!(entry.edx, 0);
 
         // Test when only instructions and cycles pmu are enabled (level LimitedlyEnabled)
-        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::LimitedlyEnabled)
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::LimitedlyEnabled, false)
             .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
             function: 0x0,
@@ -154,7 +209,7 @@ mod test {
             padding: [0, 0, 0],
         };
 
-        assert!(update_perf_mon_entry(entry, &vm_spec).is_ok());
+        assert!(do_update_perf_mon_entry(entry, &vm_spec, host_eax, host_edx).is_ok());
         assert_eq!(entry.eax.read_bits_in_range(&eax::PMC_VERSION_ID), 2);
         assert_eq!(entry.eax.read_bits_in_range(&eax::BIT_LEN_PMEVENT), 7);
 
@@ -165,9 +220,10 @@ mod test {
         assert!(entry.ebx.read_bit(ebx::LLC_MISSES_BITINDEX));
         assert!(entry.ebx.read_bit(ebx::BR_INST_RETIRED_BITINDEX));
         assert!(entry.ebx.read_bit(ebx::BR_MIS_RETIRED_BITINDEX));
+        assert_eq!(entry.edx, 0);
 
         // Test when all vpmu features are enabled (level FullyEnabled)
-        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::FullyEnabled)
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::FullyEnabled, false)
             .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
             function: 0x0,
@@ -180,7 +236,7 @@ mod test {
             padding: [0, 0, 0],
         };
 
-        assert!(update_perf_mon_entry(entry, &vm_spec).is_ok());
+        assert!(do_update_perf_mon_entry(entry, &vm_spec, host_eax, host_edx).is_ok());
 
         assert_eq!(entry.eax.read_bits_in_range(&eax::PMC_VERSION_ID), 2);
         assert_eq!(entry.eax.read_bits_in_range(&eax::BIT_LEN_PMEVENT), 7);
@@ -192,6 +248,37 @@ mod test {
         assert!(!entry.ebx.read_bit(ebx::LLC_MISSES_BITINDEX));
         assert!(!entry.ebx.read_bit(ebx::BR_INST_RETIRED_BITINDEX));
         assert!(!entry.ebx.read_bit(ebx::BR_MIS_RETIRED_BITINDEX));
+
+        // The host's fixed-function counters are mirrored to the guest.
+        assert_eq!(entry.edx.read_bits_in_range(&edx::NUM_FIXED_COUNTERS), 3);
+        assert_eq!(entry.edx.read_bits_in_range(&edx::FIXED_CTR_BITLEN), 48);
+    }
+
+    #[test]
+    fn test_update_perf_mon_entry_host_without_pmu() {
+        use crate::cpuid::cpu_leaf::leaf_0xa::*;
+
+        // A host that disallows PMU virtualization reports version 0;
+        // the requested level degrades to Disabled.
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::FullyEnabled, false)
+            .expect("Error creating vm_spec");
+        let entry = &mut kvm_cpuid_entry2 {
+            function: LEAF_NUM,
+            index: 0,
+            flags: 0,
+            eax: 1,
+            ebx: 1,
+            ecx: 1,
+            edx: 1,
+            padding: [0, 0, 0],
+        };
+
+        assert!(do_update_perf_mon_entry(entry, &vm_spec, 0, 0).is_ok());
+
+        assert_eq!(entry.eax, 0);
+        assert_eq!(entry.ebx, 0);
+        assert_eq!(entry.ecx, 0);
+        assert_eq!(entry.edx, 0);
     }
 
     fn check_update_deterministic_cache_entry(
@@ -211,6 +298,7 @@ mod test {
             cores_per_die,
             dies_per_socket,
             VpmuFeatureLevel::Disabled,
+            false,
         )
         .expect("Error creating vm_spec");
         let entry = &mut kvm_cpuid_entry2 {
@@ -31,6 +31,10 @@ pub struct VmSpec {
     /// if vpmu feature is LimitedlyEnabled, it means minimal vpmu counters are supported (cycles and instructions)
     /// if vpmu feature is FullyEnabled, it means all vpmu counters are supported
     vpmu_feature: VpmuFeatureLevel,
+    /// whether to expose the invariant TSC bit to the guest; hidden by default
+    /// so that guests don't bake in a TSC frequency that may change across
+    /// snapshot restore on a different host
+    tsc_invariant: bool,
 }
 
 impl VmSpec {
@@ -43,6 +47,7 @@ impl VmSpec {
         cores_per_die: u8,
         dies_per_socket: u8,
         vpmu_feature: VpmuFeatureLevel,
+        tsc_invariant: bool,
     ) -> Result<VmSpec, Error> {
         let cpu_vendor_id = get_vendor_id().map_err(Error::InternalError)?;
         let brand_string =
@@ -57,6 +62,7 @@ impl VmSpec {
             cores_per_die,
             dies_per_socket,
             vpmu_feature,
+            tsc_invariant,
         })
     }
 
@@ -139,7 +145,7 @@ mod test {
         let num_entries = 5;
 
         let mut cpuid = CpuId::new(num_entries).unwrap();
-        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled);
+        let vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false);
         cpuid.as_mut_slice()[0].function = PROCESSED_FN;
         assert!(MockCpuidTransformer {}
             .process_cpuid(&mut cpuid, &vm_spec.unwrap())
@@ -164,7 +170,7 @@ mod test {
         let num_entries = 5;
 
         let mut cpuid = CpuId::new(num_entries).unwrap();
-        let mut vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled).unwrap();
+        let mut vm_spec = VmSpec::new(0, 1, 1, 1, 1, VpmuFeatureLevel::Disabled, false).unwrap();
 
         vm_spec.cpu_vendor_id = [1; 12];
         assert!(process_cpuid(&mut cpuid, &vm_spec).is_err());
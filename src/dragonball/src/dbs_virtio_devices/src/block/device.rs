@@ -24,6 +24,8 @@ use virtio_queue::QueueT;
 use vm_memory::GuestMemoryRegion;
 use vmm_sys_util::eventfd::{EventFd, EFD_NONBLOCK};
 
+#[cfg(feature = "fault-injection")]
+use crate::fault_inject::{FaultInjectConfig, FaultInjectState, FaultInjectUfile};
 use crate::{
     ActivateError, ActivateResult, ConfigResult, DbsGuestAddressSpace, Error, Result, VirtioDevice,
     VirtioDeviceConfig, VirtioDeviceInfo, TYPE_BLOCK,
@@ -69,6 +71,8 @@ pub struct Block<AS: DbsGuestAddressSpace> {
     kill_evts: Vec<EventFd>,
     evt_senders: Vec<mpsc::Sender<KillEvent>>,
     epoll_threads: Vec<thread::JoinHandle<()>>,
+    #[cfg(feature = "fault-injection")]
+    fault_inject: Arc<FaultInjectState>,
     phantom: PhantomData<AS>,
 }
 
@@ -114,6 +118,19 @@ impl<AS: DbsGuestAddressSpace> Block<AS> {
         let config_space =
             Self::build_config_space(disk_size, disk_image.get_max_size(), num_queues as u16);
 
+        // Wrap every backend into the fault injection layer. All faults
+        // default to disabled, so the wrapper is transparent until a fault
+        // configuration gets installed through the API.
+        #[cfg(feature = "fault-injection")]
+        let fault_inject = Arc::new(FaultInjectState::new());
+        #[cfg(feature = "fault-injection")]
+        let disk_images = disk_images
+            .into_iter()
+            .map(|image| {
+                Box::new(FaultInjectUfile::new(image, fault_inject.clone())) as Box<dyn Ufile>
+            })
+            .collect();
+
         Ok(Block {
             device_info: VirtioDeviceInfo::new(
                 BLK_DRIVER_NAME.to_string(),
@@ -126,6 +143,8 @@ impl<AS: DbsGuestAddressSpace> Block<AS> {
             rate_limiters,
             queue_sizes,
             subscriber_id: None,
+            #[cfg(feature = "fault-injection")]
+            fault_inject,
             phantom: PhantomData,
             evt_senders: Vec::with_capacity(num_queues),
             kill_evts: Vec::with_capacity(num_queues),
@@ -165,6 +184,19 @@ impl<AS: DbsGuestAddressSpace> Block<AS> {
         config
     }
 
+    /// Install a new fault injection configuration for every backend of
+    /// this device. Takes effect immediately for subsequent I/O requests.
+    #[cfg(feature = "fault-injection")]
+    pub fn set_fault_inject_config(&self, config: &FaultInjectConfig) {
+        self.fault_inject.update(config);
+    }
+
+    /// Get the active fault injection configuration.
+    #[cfg(feature = "fault-injection")]
+    pub fn fault_inject_config(&self) -> FaultInjectConfig {
+        self.fault_inject.config()
+    }
+
     pub fn set_patch_rate_limiters(&self, bytes: BucketUpdate, ops: BucketUpdate) -> Result<()> {
         if self.evt_senders.is_empty()
             || self.kill_evts.is_empty()
@@ -0,0 +1,311 @@
+// Copyright 2023 Alibaba Cloud. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use sha2::{Digest, Sha256};
+use virtio_bindings::bindings::virtio_blk::{VIRTIO_BLK_S_IOERR, VIRTIO_BLK_S_OK};
+use vmm_sys_util::eventfd::EventFd;
+
+use super::{IoDataDesc, Ufile};
+
+/// A read-only disk backend for compressed rootfs images (erofs/squashfs),
+/// backed by a shared, read-only mmap of the image file.
+///
+/// Reads are served by copying straight out of the mapping, so the image
+/// data lives only in the host page cache and is shared between all guests
+/// booted from the same image, avoiding the double caching a regular
+/// buffered block backend would cause.
+pub struct MmapFile {
+    file: File,
+    mmap_addr: *mut libc::c_void,
+    capacity: u64,
+    digest: String,
+    evtfd: EventFd,
+    pos: u64,
+    pending: Vec<(u16, u32)>,
+}
+
+// Safe because the mapping is private to this object, read-only and kept
+// alive for the whole lifetime of the object.
+unsafe impl Send for MmapFile {}
+
+impl MmapFile {
+    /// Creates a MmapFile instance backed by `file`.
+    ///
+    /// The whole image is mapped with `PROT_READ` and `MAP_SHARED`, and its
+    /// SHA-256 digest is computed up front so it can be reported for
+    /// measurement.
+    pub fn new(file: File) -> io::Result<Self> {
+        let capacity = file.metadata()?.len();
+        if capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "empty disk image",
+            ));
+        }
+
+        // Safe because we map a whole regular file owned by us and check the
+        // result for MAP_FAILED.
+        let mmap_addr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                capacity as usize,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if mmap_addr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Safe because the mapping created above covers `capacity` bytes and
+        // stays valid until munmap() in drop().
+        let content =
+            unsafe { std::slice::from_raw_parts(mmap_addr as *const u8, capacity as usize) };
+        let digest = format!("{:x}", Sha256::digest(content));
+
+        Ok(Self {
+            file,
+            mmap_addr,
+            capacity,
+            digest,
+            evtfd: EventFd::new(libc::EFD_NONBLOCK)?,
+            pos: 0,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Returns the SHA-256 digest of the image content, in hex.
+    pub fn image_digest(&self) -> &str {
+        &self.digest
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // Safe because the mapping stays valid for the lifetime of self.
+        unsafe { std::slice::from_raw_parts(self.mmap_addr as *const u8, self.capacity as usize) }
+    }
+}
+
+impl Drop for MmapFile {
+    fn drop(&mut self) {
+        // Safe because we unmap the exact mapping created in new().
+        unsafe {
+            libc::munmap(self.mmap_addr, self.capacity as usize);
+        }
+        // Drop of self.file closes the backing fd.
+        let _ = &self.file;
+    }
+}
+
+impl Read for MmapFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let content = self.as_slice();
+        let start = std::cmp::min(self.pos as usize, content.len());
+        let end = std::cmp::min(start + buf.len(), content.len());
+        let len = end - start;
+        buf[..len].copy_from_slice(&content[start..end]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl Write for MmapFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "write to read-only image",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MmapFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => add_offset(self.capacity, offset),
+            SeekFrom::Current(offset) => add_offset(self.pos, offset),
+        };
+        match new_pos {
+            Some(p) => {
+                self.pos = p;
+                Ok(p)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start of image",
+            )),
+        }
+    }
+}
+
+fn add_offset(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+impl Ufile for MmapFile {
+    fn get_capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    fn get_max_size(&self) -> u32 {
+        // Set max size to 1M to avoid interferes with rate limiter.
+        0x100000
+    }
+
+    fn get_device_id(&self) -> io::Result<String> {
+        // The content digest doubles as a stable device id.
+        Ok(self.digest.clone())
+    }
+
+    fn get_data_evt_fd(&self) -> RawFd {
+        self.evtfd.as_raw_fd()
+    }
+
+    fn io_read_submit(
+        &mut self,
+        offset: i64,
+        iovecs: &mut Vec<IoDataDesc>,
+        user_data: u16,
+    ) -> io::Result<usize> {
+        let mut status = VIRTIO_BLK_S_OK;
+        let mut pos = if offset < 0 {
+            status = VIRTIO_BLK_S_IOERR;
+            0
+        } else {
+            offset as usize
+        };
+
+        if status == VIRTIO_BLK_S_OK {
+            let content = self.as_slice();
+            for iov in iovecs.iter() {
+                match pos
+                    .checked_add(iov.data_len)
+                    .filter(|end| *end <= content.len())
+                {
+                    Some(end) => {
+                        // Safe because the descriptor addresses have been
+                        // validated against the guest address space by the
+                        // virtio-blk request parser.
+                        unsafe {
+                            std::ptr::copy_nonoverlapping(
+                                content[pos..end].as_ptr(),
+                                iov.data_addr as *mut u8,
+                                iov.data_len,
+                            );
+                        }
+                        pos = end;
+                    }
+                    None => {
+                        status = VIRTIO_BLK_S_IOERR;
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.pending.push((user_data, status));
+        self.evtfd.write(1)?;
+        Ok(1)
+    }
+
+    fn io_write_submit(
+        &mut self,
+        _offset: i64,
+        _iovecs: &mut Vec<IoDataDesc>,
+        _user_data: u16,
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "write to read-only image",
+        ))
+    }
+
+    fn io_complete(&mut self) -> io::Result<Vec<(u16, u32)>> {
+        match self.evtfd.read() {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        Ok(std::mem::take(&mut self.pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    fn new_image(content: &[u8]) -> MmapFile {
+        let mut file = vmm_sys_util::tempfile::TempFile::new().unwrap().into_file();
+        file.write_all(content).unwrap();
+        MmapFile::new(file).unwrap()
+    }
+
+    #[test]
+    fn test_mmapfile_basic() {
+        let image = new_image(b"0123456789");
+
+        assert_eq!(image.get_capacity(), 10);
+        // Digest of the content, not of the file metadata.
+        assert_eq!(image.get_device_id().unwrap(), image.image_digest());
+        assert_eq!(image.image_digest().len(), 64);
+    }
+
+    #[test]
+    fn test_mmapfile_empty_image() {
+        let file = vmm_sys_util::tempfile::TempFile::new().unwrap().into_file();
+        assert!(MmapFile::new(file).is_err());
+    }
+
+    #[test]
+    fn test_mmapfile_read_seek_write() {
+        let mut image = new_image(b"0123456789");
+
+        let mut buf = [0u8; 4];
+        image.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(image.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"2345");
+
+        // Reads past the end are truncated.
+        image.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(image.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"89");
+
+        assert!(image.seek(SeekFrom::Start(0)).is_ok());
+        assert!(image.write(b"xx").is_err());
+    }
+
+    #[test]
+    fn test_mmapfile_io_submit() {
+        let mut image = new_image(b"0123456789");
+        let mut buf = [0u8; 4];
+        let mut iovecs = vec![IoDataDesc {
+            data_addr: buf.as_mut_ptr() as u64,
+            data_len: 4,
+        }];
+
+        assert_eq!(image.io_read_submit(3, &mut iovecs, 7).unwrap(), 1);
+        assert_eq!(image.io_complete().unwrap(), vec![(7, VIRTIO_BLK_S_OK)]);
+        assert_eq!(&buf, b"3456");
+
+        // Out of range read completes with an error status.
+        assert_eq!(image.io_read_submit(8, &mut iovecs, 8).unwrap(), 1);
+        assert_eq!(image.io_complete().unwrap(), vec![(8, VIRTIO_BLK_S_IOERR)]);
+
+        assert!(image.io_write_submit(0, &mut iovecs, 9).is_err());
+    }
+}
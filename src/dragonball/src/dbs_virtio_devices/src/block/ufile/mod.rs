@@ -4,6 +4,9 @@
 mod localfile;
 pub use self::localfile::LocalFile;
 
+mod mmapfile;
+pub use self::mmapfile::MmapFile;
+
 pub mod aio;
 pub mod io_uring;
 
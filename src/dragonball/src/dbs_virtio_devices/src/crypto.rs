@@ -0,0 +1,1467 @@
+// Copyright (C) 2026 Alibaba Cloud. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Virtio-crypto device, exposing host crypto services to the guest.
+//!
+//! The device implements the symmetric cipher service of the virtio-crypto
+//! specification so guests can offload TLS record processing to the host.
+//! Cipher sessions are created/destroyed on the control queue and cipher
+//! operations are handled on the data queue. The actual crypto work is
+//! delegated to a pluggable [`CryptoBackend`]; the default backend drives
+//! the host kernel crypto API through `AF_ALG` sockets, and an OpenSSL
+//! based backend can be selected when the `virtio-crypto-openssl` feature
+//! is enabled. An optional rate limiter on the data queue bounds the host
+//! CPU time a single sandbox may consume with crypto requests.
+
+use std::any::Any;
+use std::cmp;
+use std::collections::HashMap;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+
+use dbs_device::resources::ResourceConstraint;
+use dbs_utils::epoll_manager::SubscriberId;
+use dbs_utils::epoll_manager::{EpollManager, EventOps, EventSet, Events, MutEventSubscriber};
+use dbs_utils::metric::{IncMetric, SharedIncMetric};
+use dbs_utils::rate_limiter::{RateLimiter, TokenType};
+use log::{debug, error, trace};
+use serde::Serialize;
+use virtio_bindings::bindings::virtio_blk::VIRTIO_F_VERSION_1;
+use virtio_queue::{DescriptorChain, QueueOwnedT, QueueSync, QueueT};
+use vm_memory::{
+    Address, ByteValued, Bytes, GuestAddress, GuestAddressSpace, GuestMemory, GuestMemoryRegion,
+    GuestRegionMmap,
+};
+
+use crate::device::{VirtioDevice, VirtioDeviceConfig, VirtioDeviceInfo, VirtioQueueConfig};
+use crate::{
+    ActivateError, ActivateResult, ConfigResult, DbsGuestAddressSpace, Error, Result, TYPE_CRYPTO,
+};
+
+const CRYPTO_DRIVER_NAME: &str = "virtio-crypto";
+
+const QUEUE_SIZE: u16 = 256;
+// One data queue plus the control queue.
+const NUM_QUEUES: usize = 2;
+const QUEUE_SIZES: &[u16] = &[QUEUE_SIZE; NUM_QUEUES];
+
+// New descriptors are pending on the data queue.
+const DATA_QUEUE_AVAIL_EVENT: u32 = 0;
+// New descriptors are pending on the control queue.
+const CTRL_QUEUE_AVAIL_EVENT: u32 = 1;
+// Data queue rate limiter budget is now available.
+const RATE_LIMITER_EVENT: u32 = 2;
+// The device has been dropped.
+const KILL_EVENT: u32 = 3;
+// Number of DeviceEventT events supported by this implementation.
+pub const CRYPTO_EVENTS_COUNT: u32 = 4;
+
+// Device configuration status bit: the device is ready to process requests.
+const VIRTIO_CRYPTO_S_HW_READY: u32 = 1;
+
+// Crypto services defined by the virtio specification.
+const VIRTIO_CRYPTO_SERVICE_CIPHER: u32 = 0;
+const VIRTIO_CRYPTO_SERVICE_AKCIPHER: u32 = 4;
+
+// Symmetric cipher algorithms defined by the virtio specification.
+/// AES in ECB mode.
+pub const VIRTIO_CRYPTO_CIPHER_AES_ECB: u32 = 2;
+/// AES in CBC mode.
+pub const VIRTIO_CRYPTO_CIPHER_AES_CBC: u32 = 3;
+/// AES in CTR mode.
+pub const VIRTIO_CRYPTO_CIPHER_AES_CTR: u32 = 4;
+
+// Opcodes are built as (service << 8) | op.
+const fn virtio_crypto_opcode(service: u32, op: u32) -> u32 {
+    (service << 8) | op
+}
+
+// Data queue opcodes.
+const VIRTIO_CRYPTO_CIPHER_ENCRYPT: u32 = virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_CIPHER, 0x00);
+const VIRTIO_CRYPTO_CIPHER_DECRYPT: u32 = virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_CIPHER, 0x01);
+
+// Control queue opcodes.
+const VIRTIO_CRYPTO_CIPHER_CREATE_SESSION: u32 =
+    virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_CIPHER, 0x02);
+const VIRTIO_CRYPTO_CIPHER_DESTROY_SESSION: u32 =
+    virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_CIPHER, 0x03);
+const VIRTIO_CRYPTO_AKCIPHER_CREATE_SESSION: u32 =
+    virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_AKCIPHER, 0x04);
+const VIRTIO_CRYPTO_AKCIPHER_DESTROY_SESSION: u32 =
+    virtio_crypto_opcode(VIRTIO_CRYPTO_SERVICE_AKCIPHER, 0x05);
+
+// Request status codes defined by the virtio specification.
+const VIRTIO_CRYPTO_OK: u32 = 0;
+const VIRTIO_CRYPTO_ERR: u32 = 1;
+const VIRTIO_CRYPTO_BADMSG: u32 = 2;
+const VIRTIO_CRYPTO_NOTSUPP: u32 = 3;
+const VIRTIO_CRYPTO_INVSESS: u32 = 4;
+const VIRTIO_CRYPTO_NOSPC: u32 = 5;
+
+// Fixed size of the device-readable header of control/data requests:
+// a 16/24 byte header followed by a 56/48 byte operation union.
+const CTRL_REQ_SIZE: usize = 72;
+const DATA_REQ_SIZE: usize = 72;
+// Offset of the operation union within control/data requests.
+const CTRL_REQ_UNION_OFFSET: usize = 16;
+const DATA_REQ_UNION_OFFSET: usize = 24;
+
+// Largest cipher key the device accepts, advertised in the config space.
+const MAX_CIPHER_KEY_LEN: u32 = 64;
+// Largest payload of a single crypto operation, advertised in the config space.
+const MAX_DATA_SIZE: usize = 64 * 1024;
+// Largest request the device bothers to read from the guest.
+const MAX_REQUEST_SIZE: usize = DATA_REQ_SIZE + MAX_CIPHER_KEY_LEN as usize + 2 * MAX_DATA_SIZE;
+// Upper bound on concurrently live sessions per device.
+const MAX_SESSIONS: usize = 1024;
+
+/// Error for virtio-crypto devices and backends.
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    /// The requested algorithm or operation is not supported.
+    #[error("unsupported crypto algorithm or operation")]
+    Unsupported,
+    /// The request refers to a session that does not exist.
+    #[error("invalid crypto session id {0}")]
+    InvalidSession(u64),
+    /// The request is malformed.
+    #[error("malformed crypto request")]
+    BadMessage,
+    /// The key length doesn't match the selected algorithm.
+    #[error("invalid key length {0} for crypto algorithm {1}")]
+    InvalidKeyLength(usize, u32),
+    /// No session slots are left on the device.
+    #[error("no free crypto session slots")]
+    NoFreeSessions,
+    /// The crypto backend failed to process the request.
+    #[error("crypto backend error: {0}")]
+    Backend(#[source] io::Error),
+}
+
+/// Specialized std::result::Result for crypto backend operations.
+pub type CryptoResult<T> = std::result::Result<T, CryptoError>;
+
+fn request_status(result: &CryptoError) -> u32 {
+    match result {
+        CryptoError::Unsupported => VIRTIO_CRYPTO_NOTSUPP,
+        CryptoError::InvalidSession(_) => VIRTIO_CRYPTO_INVSESS,
+        CryptoError::BadMessage | CryptoError::InvalidKeyLength(_, _) => VIRTIO_CRYPTO_BADMSG,
+        CryptoError::NoFreeSessions => VIRTIO_CRYPTO_NOSPC,
+        CryptoError::Backend(_) => VIRTIO_CRYPTO_ERR,
+    }
+}
+
+/// Crypto device associated metrics.
+#[derive(Default, Serialize)]
+pub struct CryptoDeviceMetrics {
+    /// Number of times when handling events on a crypto device.
+    pub event_count: SharedIncMetric,
+    /// Number of times when activate failed on a crypto device.
+    pub activate_fails: SharedIncMetric,
+    /// Number of times when handling events on a crypto device failed.
+    pub event_fails: SharedIncMetric,
+    /// Number of events associated with the control queue.
+    pub ctrl_queue_event_count: SharedIncMetric,
+    /// Number of events associated with the data queue.
+    pub data_queue_event_count: SharedIncMetric,
+    /// Number of events associated with the data queue rate limiter.
+    pub rate_limiter_event_count: SharedIncMetric,
+    /// Number of sessions created on the device.
+    pub session_create_count: SharedIncMetric,
+    /// Number of sessions destroyed on the device.
+    pub session_destroy_count: SharedIncMetric,
+    /// Number of cipher operations processed by the device.
+    pub cipher_op_count: SharedIncMetric,
+    /// Number of requests finished with a non-OK status.
+    pub request_fails: SharedIncMetric,
+}
+
+// Header of control queue requests, from the virtio specification.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct CtrlHeader {
+    opcode: u32,
+    algo: u32,
+    flag: u32,
+    reserved: u32,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for CtrlHeader {}
+
+// Cipher session parameters of a create session request.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct CipherSessionPara {
+    algo: u32,
+    keylen: u32,
+    op: u32,
+    padding: u32,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for CipherSessionPara {}
+
+// Device-writable response of a create session request.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct SessionInput {
+    session_id: u64,
+    status: u32,
+    padding: u32,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for SessionInput {}
+
+// Header of data queue requests, from the virtio specification.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct OpHeader {
+    opcode: u32,
+    algo: u32,
+    session_id: u64,
+    flag: u32,
+    padding: u32,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for OpHeader {}
+
+// Cipher parameters of a data queue request.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct CipherDataPara {
+    iv_len: u32,
+    src_data_len: u32,
+    dst_data_len: u32,
+    padding: u32,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for CipherDataPara {}
+
+// Device configuration space, from the virtio specification.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default)]
+struct VirtioCryptoConfig {
+    status: u32,
+    max_dataqueues: u32,
+    crypto_services: u32,
+    cipher_algo_l: u32,
+    cipher_algo_h: u32,
+    hash_algo: u32,
+    mac_algo_l: u32,
+    mac_algo_h: u32,
+    aead_algo: u32,
+    max_cipher_key_len: u32,
+    max_auth_key_len: u32,
+    akcipher_algo: u32,
+    max_size: u64,
+}
+
+// Safe because it only has data and has no implicit padding.
+unsafe impl ByteValued for VirtioCryptoConfig {}
+
+/// Backend performing the actual crypto operations for a virtio-crypto device.
+///
+/// A backend owns the sessions it creates; session IDs handed out here are
+/// passed verbatim to the guest and back.
+pub trait CryptoBackend: Send {
+    /// Bitmask of supported `VIRTIO_CRYPTO_SERVICE_*` services.
+    fn services(&self) -> u32;
+
+    /// Bitmask of supported `VIRTIO_CRYPTO_CIPHER_*` algorithms.
+    fn cipher_algos(&self) -> u64;
+
+    /// Bitmask of supported `VIRTIO_CRYPTO_AKCIPHER_*` algorithms.
+    fn akcipher_algos(&self) -> u32 {
+        0
+    }
+
+    /// Create a cipher session for `algo` with the given key.
+    fn create_cipher_session(&mut self, algo: u32, key: &[u8]) -> CryptoResult<u64>;
+
+    /// Destroy a session previously created on this backend.
+    fn destroy_session(&mut self, session_id: u64) -> CryptoResult<()>;
+
+    /// Run one cipher operation on an established session.
+    fn cipher_op(
+        &mut self,
+        session_id: u64,
+        encrypt: bool,
+        iv: &[u8],
+        src: &[u8],
+    ) -> CryptoResult<Vec<u8>>;
+}
+
+/// Host crypto backends selectable in the device configuration.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CryptoBackendType {
+    /// Kernel crypto API driven through `AF_ALG` sockets.
+    #[default]
+    Kernel,
+    /// OpenSSL, available with the `virtio-crypto-openssl` feature.
+    OpenSsl,
+}
+
+/// Build the crypto backend selected in the device configuration.
+pub fn build_backend(backend_type: CryptoBackendType) -> Result<Box<dyn CryptoBackend>> {
+    match backend_type {
+        CryptoBackendType::Kernel => Ok(Box::new(kernel::KernelCryptoBackend::new())),
+        #[cfg(feature = "virtio-crypto-openssl")]
+        CryptoBackendType::OpenSsl => Ok(Box::new(openssl_backend::OpensslCryptoBackend::new())),
+        #[cfg(not(feature = "virtio-crypto-openssl"))]
+        CryptoBackendType::OpenSsl => Err(Error::VirtioCryptoError(CryptoError::Unsupported)),
+    }
+}
+
+/// Crypto backend driving the host kernel crypto API through `AF_ALG`.
+pub mod kernel {
+    use std::fs::File;
+    use std::io::IoSlice;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    use nix::sys::socket::sockopt::AlgSetKey;
+    use nix::sys::socket::{
+        accept, bind, sendmsg, setsockopt, socket, AddressFamily, AlgAddr, ControlMessage,
+        MsgFlags, SockFlag, SockType,
+    };
+
+    use super::*;
+
+    // A bound `AF_ALG` transform socket plus the operation socket accepted
+    // from it. Both close with the session.
+    struct KernelSession {
+        _tfm: File,
+        op: File,
+    }
+
+    /// [`CryptoBackend`] implementation backed by the kernel crypto API.
+    pub struct KernelCryptoBackend {
+        next_session_id: u64,
+        sessions: HashMap<u64, KernelSession>,
+    }
+
+    impl KernelCryptoBackend {
+        /// Create a kernel crypto backend with no active sessions.
+        pub fn new() -> Self {
+            KernelCryptoBackend {
+                next_session_id: 1,
+                sessions: HashMap::new(),
+            }
+        }
+
+        fn alg_name(algo: u32) -> CryptoResult<&'static str> {
+            match algo {
+                VIRTIO_CRYPTO_CIPHER_AES_ECB => Ok("ecb(aes)"),
+                VIRTIO_CRYPTO_CIPHER_AES_CBC => Ok("cbc(aes)"),
+                VIRTIO_CRYPTO_CIPHER_AES_CTR => Ok("ctr(aes)"),
+                _ => Err(CryptoError::Unsupported),
+            }
+        }
+    }
+
+    impl Default for KernelCryptoBackend {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl CryptoBackend for KernelCryptoBackend {
+        fn services(&self) -> u32 {
+            1 << VIRTIO_CRYPTO_SERVICE_CIPHER
+        }
+
+        fn cipher_algos(&self) -> u64 {
+            1 << VIRTIO_CRYPTO_CIPHER_AES_ECB
+                | 1 << VIRTIO_CRYPTO_CIPHER_AES_CBC
+                | 1 << VIRTIO_CRYPTO_CIPHER_AES_CTR
+        }
+
+        fn create_cipher_session(&mut self, algo: u32, key: &[u8]) -> CryptoResult<u64> {
+            let name = Self::alg_name(algo)?;
+            if !matches!(key.len(), 16 | 24 | 32) {
+                return Err(CryptoError::InvalidKeyLength(key.len(), algo));
+            }
+            if self.sessions.len() >= MAX_SESSIONS {
+                return Err(CryptoError::NoFreeSessions);
+            }
+
+            let tfm = socket(
+                AddressFamily::Alg,
+                SockType::SeqPacket,
+                SockFlag::empty(),
+                None,
+            )
+            .map_err(|e| CryptoError::Backend(e.into()))?;
+            // Owns the fd from here on, including on the error paths below.
+            let tfm = unsafe { File::from_raw_fd(tfm) };
+
+            let addr = AlgAddr::new("skcipher", name);
+            bind(tfm.as_raw_fd(), &addr).map_err(|e| CryptoError::Backend(e.into()))?;
+            setsockopt(tfm.as_raw_fd(), AlgSetKey::default(), &key.to_vec())
+                .map_err(|e| CryptoError::Backend(e.into()))?;
+            let op = accept(tfm.as_raw_fd()).map_err(|e| CryptoError::Backend(e.into()))?;
+            let op = unsafe { File::from_raw_fd(op) };
+
+            let session_id = self.next_session_id;
+            self.next_session_id += 1;
+            self.sessions
+                .insert(session_id, KernelSession { _tfm: tfm, op });
+
+            Ok(session_id)
+        }
+
+        fn destroy_session(&mut self, session_id: u64) -> CryptoResult<()> {
+            self.sessions
+                .remove(&session_id)
+                .map(|_| ())
+                .ok_or(CryptoError::InvalidSession(session_id))
+        }
+
+        fn cipher_op(
+            &mut self,
+            session_id: u64,
+            encrypt: bool,
+            iv: &[u8],
+            src: &[u8],
+        ) -> CryptoResult<Vec<u8>> {
+            let session = self
+                .sessions
+                .get(&session_id)
+                .ok_or(CryptoError::InvalidSession(session_id))?;
+
+            let op: libc::c_int = if encrypt {
+                libc::ALG_OP_ENCRYPT
+            } else {
+                libc::ALG_OP_DECRYPT
+            };
+            let mut cmsgs = vec![ControlMessage::AlgSetOp(&op)];
+            if !iv.is_empty() {
+                cmsgs.push(ControlMessage::AlgSetIv(iv));
+            }
+            let iov = [IoSlice::new(src)];
+            sendmsg::<AlgAddr>(
+                session.op.as_raw_fd(),
+                &iov,
+                &cmsgs,
+                MsgFlags::empty(),
+                None,
+            )
+            .map_err(|e| match e {
+                nix::errno::Errno::EINVAL => CryptoError::BadMessage,
+                _ => CryptoError::Backend(e.into()),
+            })?;
+
+            let mut dst = vec![0u8; src.len()];
+            let mut read_count = 0;
+            while read_count < dst.len() {
+                let count = nix::unistd::read(session.op.as_raw_fd(), &mut dst[read_count..])
+                    .map_err(|e| CryptoError::Backend(e.into()))?;
+                if count == 0 {
+                    return Err(CryptoError::Backend(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "short read from AF_ALG socket",
+                    )));
+                }
+                read_count += count;
+            }
+
+            Ok(dst)
+        }
+    }
+}
+
+/// Crypto backend implemented with OpenSSL, useful on hosts where the
+/// kernel crypto user API is disabled.
+#[cfg(feature = "virtio-crypto-openssl")]
+pub mod openssl_backend {
+    use openssl::symm::{Cipher, Crypter, Mode};
+
+    use super::*;
+
+    struct OpensslSession {
+        algo: u32,
+        key: Vec<u8>,
+    }
+
+    /// [`CryptoBackend`] implementation backed by OpenSSL.
+    pub struct OpensslCryptoBackend {
+        next_session_id: u64,
+        sessions: HashMap<u64, OpensslSession>,
+    }
+
+    impl OpensslCryptoBackend {
+        /// Create an OpenSSL crypto backend with no active sessions.
+        pub fn new() -> Self {
+            OpensslCryptoBackend {
+                next_session_id: 1,
+                sessions: HashMap::new(),
+            }
+        }
+
+        fn cipher(algo: u32, keylen: usize) -> CryptoResult<Cipher> {
+            match (algo, keylen) {
+                (VIRTIO_CRYPTO_CIPHER_AES_ECB, 16) => Ok(Cipher::aes_128_ecb()),
+                (VIRTIO_CRYPTO_CIPHER_AES_ECB, 32) => Ok(Cipher::aes_256_ecb()),
+                (VIRTIO_CRYPTO_CIPHER_AES_CBC, 16) => Ok(Cipher::aes_128_cbc()),
+                (VIRTIO_CRYPTO_CIPHER_AES_CBC, 32) => Ok(Cipher::aes_256_cbc()),
+                (VIRTIO_CRYPTO_CIPHER_AES_CTR, 16) => Ok(Cipher::aes_128_ctr()),
+                (VIRTIO_CRYPTO_CIPHER_AES_CTR, 32) => Ok(Cipher::aes_256_ctr()),
+                (
+                    VIRTIO_CRYPTO_CIPHER_AES_ECB
+                    | VIRTIO_CRYPTO_CIPHER_AES_CBC
+                    | VIRTIO_CRYPTO_CIPHER_AES_CTR,
+                    keylen,
+                ) => Err(CryptoError::InvalidKeyLength(keylen, algo)),
+                _ => Err(CryptoError::Unsupported),
+            }
+        }
+    }
+
+    impl Default for OpensslCryptoBackend {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl CryptoBackend for OpensslCryptoBackend {
+        fn services(&self) -> u32 {
+            1 << VIRTIO_CRYPTO_SERVICE_CIPHER
+        }
+
+        fn cipher_algos(&self) -> u64 {
+            1 << VIRTIO_CRYPTO_CIPHER_AES_ECB
+                | 1 << VIRTIO_CRYPTO_CIPHER_AES_CBC
+                | 1 << VIRTIO_CRYPTO_CIPHER_AES_CTR
+        }
+
+        fn create_cipher_session(&mut self, algo: u32, key: &[u8]) -> CryptoResult<u64> {
+            // Validate the algorithm/key combination up front so session
+            // creation fails instead of every subsequent operation.
+            Self::cipher(algo, key.len())?;
+            if self.sessions.len() >= MAX_SESSIONS {
+                return Err(CryptoError::NoFreeSessions);
+            }
+
+            let session_id = self.next_session_id;
+            self.next_session_id += 1;
+            self.sessions.insert(
+                session_id,
+                OpensslSession {
+                    algo,
+                    key: key.to_vec(),
+                },
+            );
+
+            Ok(session_id)
+        }
+
+        fn destroy_session(&mut self, session_id: u64) -> CryptoResult<()> {
+            self.sessions
+                .remove(&session_id)
+                .map(|_| ())
+                .ok_or(CryptoError::InvalidSession(session_id))
+        }
+
+        fn cipher_op(
+            &mut self,
+            session_id: u64,
+            encrypt: bool,
+            iv: &[u8],
+            src: &[u8],
+        ) -> CryptoResult<Vec<u8>> {
+            let session = self
+                .sessions
+                .get(&session_id)
+                .ok_or(CryptoError::InvalidSession(session_id))?;
+
+            let cipher = Self::cipher(session.algo, session.key.len())?;
+            let mode = if encrypt {
+                Mode::Encrypt
+            } else {
+                Mode::Decrypt
+            };
+            let iv = if iv.is_empty() { None } else { Some(iv) };
+            let mut crypter = Crypter::new(cipher, mode, &session.key, iv)
+                .map_err(|e| CryptoError::Backend(io::Error::new(io::ErrorKind::Other, e)))?;
+            // The guest handles padding itself, like the kernel backend.
+            crypter.pad(false);
+
+            let mut dst = vec![0u8; src.len() + cipher.block_size()];
+            let mut count = crypter
+                .update(src, &mut dst)
+                .map_err(|_| CryptoError::BadMessage)?;
+            count += crypter
+                .finalize(&mut dst[count..])
+                .map_err(|_| CryptoError::BadMessage)?;
+            dst.truncate(count);
+
+            Ok(dst)
+        }
+    }
+}
+
+// Device-readable bytes and device-writable buffers of one request.
+type RequestBuffers = (Vec<u8>, Vec<(GuestAddress, u32)>);
+
+pub(crate) struct CryptoEpollHandler<
+    AS: GuestAddressSpace,
+    Q: QueueT + Send = QueueSync,
+    R: GuestMemoryRegion = GuestRegionMmap,
+> {
+    pub(crate) config: VirtioDeviceConfig<AS, Q, R>,
+    data_queue: VirtioQueueConfig<Q>,
+    ctrl_queue: VirtioQueueConfig<Q>,
+    rate_limiter: RateLimiter,
+    backend: Box<dyn CryptoBackend>,
+    metrics: Arc<CryptoDeviceMetrics>,
+}
+
+impl<AS: DbsGuestAddressSpace, Q: QueueT + Send, R: GuestMemoryRegion>
+    CryptoEpollHandler<AS, Q, R>
+{
+    // Split a descriptor chain into the device-readable bytes and the list
+    // of device-writable buffers.
+    fn collect_request<M: GuestMemory>(
+        mem: &M,
+        desc_chain: &mut DescriptorChain<&M>,
+    ) -> Result<RequestBuffers> {
+        let mut readable: Vec<u8> = Vec::new();
+        let mut writable: Vec<(GuestAddress, u32)> = Vec::new();
+
+        for desc in desc_chain {
+            if desc.is_write_only() {
+                writable.push((desc.addr(), desc.len()));
+            } else {
+                if !writable.is_empty() {
+                    // Device-readable descriptors must precede writable ones.
+                    return Err(Error::UnexpectedReadOnlyDescriptor);
+                }
+                if readable.len() + desc.len() as usize > MAX_REQUEST_SIZE {
+                    return Err(Error::DescriptorLengthTooBig);
+                }
+                let offset = readable.len();
+                readable.resize(offset + desc.len() as usize, 0);
+                mem.read_slice(&mut readable[offset..], desc.addr())
+                    .map_err(Error::GuestMemory)?;
+            }
+        }
+
+        Ok((readable, writable))
+    }
+
+    // Scatter `data` into the writable buffers starting at `offset`.
+    fn write_response<M: GuestMemory>(
+        mem: &M,
+        writable: &[(GuestAddress, u32)],
+        mut offset: usize,
+        mut data: &[u8],
+    ) -> Result<()> {
+        for (addr, len) in writable {
+            let len = *len as usize;
+            if offset >= len {
+                offset -= len;
+                continue;
+            }
+            let count = cmp::min(len - offset, data.len());
+            let addr = addr
+                .checked_add(offset as u64)
+                .ok_or(Error::InvalidGuestAddress(*addr))?;
+            mem.write_slice(&data[..count], addr)
+                .map_err(Error::GuestMemory)?;
+            data = &data[count..];
+            offset = 0;
+            if data.is_empty() {
+                return Ok(());
+            }
+        }
+
+        if data.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DescriptorChainTooShort)
+        }
+    }
+
+    // Handle one control queue request, returning the number of bytes
+    // written to the guest.
+    fn handle_ctrl_request(
+        mem: &AS::M,
+        backend: &mut dyn CryptoBackend,
+        metrics: &CryptoDeviceMetrics,
+        readable: &[u8],
+        writable: &[(GuestAddress, u32)],
+    ) -> u32 {
+        let writable_len: usize = writable.iter().map(|(_, len)| *len as usize).sum();
+        if readable.len() < CTRL_REQ_SIZE || writable_len == 0 {
+            metrics.request_fails.inc();
+            // Too malformed to even report a status.
+            return 0;
+        }
+
+        let header = CtrlHeader::from_slice(&readable[..std::mem::size_of::<CtrlHeader>()])
+            .copied()
+            .unwrap_or_default();
+        let opcode = header.opcode;
+
+        match opcode {
+            VIRTIO_CRYPTO_CIPHER_CREATE_SESSION => {
+                let result = Self::create_cipher_session(backend, readable);
+                let input = match result {
+                    Ok(session_id) => {
+                        metrics.session_create_count.inc();
+                        SessionInput {
+                            session_id,
+                            status: VIRTIO_CRYPTO_OK,
+                            ..Default::default()
+                        }
+                    }
+                    Err(e) => {
+                        metrics.request_fails.inc();
+                        debug!("{}: failed to create session: {}", CRYPTO_DRIVER_NAME, e);
+                        SessionInput {
+                            status: request_status(&e),
+                            ..Default::default()
+                        }
+                    }
+                };
+                Self::finish_ctrl_request(mem, writable, input.as_slice())
+            }
+            VIRTIO_CRYPTO_CIPHER_DESTROY_SESSION | VIRTIO_CRYPTO_AKCIPHER_DESTROY_SESSION => {
+                let mut session_id = [0u8; 8];
+                session_id
+                    .copy_from_slice(&readable[CTRL_REQ_UNION_OFFSET..CTRL_REQ_UNION_OFFSET + 8]);
+                let session_id = u64::from_le_bytes(session_id);
+                let status = match backend.destroy_session(session_id) {
+                    Ok(()) => {
+                        metrics.session_destroy_count.inc();
+                        VIRTIO_CRYPTO_OK
+                    }
+                    Err(e) => {
+                        metrics.request_fails.inc();
+                        debug!("{}: failed to destroy session: {}", CRYPTO_DRIVER_NAME, e);
+                        request_status(&e)
+                    }
+                };
+                Self::finish_ctrl_request(mem, writable, &[status as u8])
+            }
+            VIRTIO_CRYPTO_AKCIPHER_CREATE_SESSION => {
+                // No backend implements the akcipher service yet; report
+                // NOTSUPP in the session response the guest expects.
+                metrics.request_fails.inc();
+                let input = SessionInput {
+                    status: VIRTIO_CRYPTO_NOTSUPP,
+                    ..Default::default()
+                };
+                Self::finish_ctrl_request(mem, writable, input.as_slice())
+            }
+            _ => {
+                metrics.request_fails.inc();
+                debug!(
+                    "{}: unsupported control opcode 0x{:x}",
+                    CRYPTO_DRIVER_NAME, opcode
+                );
+                Self::finish_ctrl_request(mem, writable, &[VIRTIO_CRYPTO_NOTSUPP as u8])
+            }
+        }
+    }
+
+    fn create_cipher_session(
+        backend: &mut dyn CryptoBackend,
+        readable: &[u8],
+    ) -> CryptoResult<u64> {
+        let para = CipherSessionPara::from_slice(
+            &readable[CTRL_REQ_UNION_OFFSET
+                ..CTRL_REQ_UNION_OFFSET + std::mem::size_of::<CipherSessionPara>()],
+        )
+        .copied()
+        .ok_or(CryptoError::BadMessage)?;
+        let algo = para.algo;
+        let keylen = para.keylen as usize;
+
+        if keylen == 0
+            || keylen > MAX_CIPHER_KEY_LEN as usize
+            || readable.len() < CTRL_REQ_SIZE + keylen
+        {
+            return Err(CryptoError::BadMessage);
+        }
+        let key = &readable[CTRL_REQ_SIZE..CTRL_REQ_SIZE + keylen];
+
+        backend.create_cipher_session(algo, key)
+    }
+
+    fn finish_ctrl_request(mem: &AS::M, writable: &[(GuestAddress, u32)], response: &[u8]) -> u32 {
+        match Self::write_response(mem, writable, 0, response) {
+            Ok(()) => response.len() as u32,
+            Err(e) => {
+                error!(
+                    "{}: failed to write control response: {}",
+                    CRYPTO_DRIVER_NAME, e
+                );
+                0
+            }
+        }
+    }
+
+    // Handle one data queue request, returning the number of bytes written
+    // to the guest.
+    fn handle_data_request(
+        mem: &AS::M,
+        backend: &mut dyn CryptoBackend,
+        metrics: &CryptoDeviceMetrics,
+        readable: &[u8],
+        writable: &[(GuestAddress, u32)],
+    ) -> u32 {
+        let writable_len: usize = writable.iter().map(|(_, len)| *len as usize).sum();
+        if writable_len == 0 {
+            metrics.request_fails.inc();
+            return 0;
+        }
+        // The last device-writable byte is the status the guest inspects.
+        let status_offset = writable_len - 1;
+
+        let result = Self::cipher_data_op(backend, readable, status_offset);
+        let (status, dst) = match result {
+            Ok(dst) => {
+                metrics.cipher_op_count.inc();
+                (VIRTIO_CRYPTO_OK, dst)
+            }
+            Err(e) => {
+                metrics.request_fails.inc();
+                debug!("{}: data request failed: {}", CRYPTO_DRIVER_NAME, e);
+                (request_status(&e), Vec::new())
+            }
+        };
+
+        if !dst.is_empty() {
+            if let Err(e) = Self::write_response(mem, writable, 0, &dst) {
+                error!("{}: failed to write payload: {}", CRYPTO_DRIVER_NAME, e);
+                return 0;
+            }
+        }
+        if let Err(e) = Self::write_response(mem, writable, status_offset, &[status as u8]) {
+            error!("{}: failed to write status: {}", CRYPTO_DRIVER_NAME, e);
+            return 0;
+        }
+
+        writable_len as u32
+    }
+
+    fn cipher_data_op(
+        backend: &mut dyn CryptoBackend,
+        readable: &[u8],
+        status_offset: usize,
+    ) -> CryptoResult<Vec<u8>> {
+        if readable.len() < DATA_REQ_SIZE {
+            return Err(CryptoError::BadMessage);
+        }
+
+        let header = OpHeader::from_slice(&readable[..std::mem::size_of::<OpHeader>()])
+            .copied()
+            .ok_or(CryptoError::BadMessage)?;
+        let opcode = header.opcode;
+        let session_id = header.session_id;
+        let encrypt = match opcode {
+            VIRTIO_CRYPTO_CIPHER_ENCRYPT => true,
+            VIRTIO_CRYPTO_CIPHER_DECRYPT => false,
+            _ => return Err(CryptoError::Unsupported),
+        };
+
+        let para = CipherDataPara::from_slice(
+            &readable[DATA_REQ_UNION_OFFSET
+                ..DATA_REQ_UNION_OFFSET + std::mem::size_of::<CipherDataPara>()],
+        )
+        .copied()
+        .ok_or(CryptoError::BadMessage)?;
+        let iv_len = para.iv_len as usize;
+        let src_len = para.src_data_len as usize;
+        let dst_len = para.dst_data_len as usize;
+
+        if src_len == 0
+            || src_len > MAX_DATA_SIZE
+            || readable.len() < DATA_REQ_SIZE + iv_len + src_len
+            || dst_len > status_offset
+        {
+            return Err(CryptoError::BadMessage);
+        }
+        let iv = &readable[DATA_REQ_SIZE..DATA_REQ_SIZE + iv_len];
+        let src = &readable[DATA_REQ_SIZE + iv_len..DATA_REQ_SIZE + iv_len + src_len];
+
+        let dst = backend.cipher_op(session_id, encrypt, iv, src)?;
+        if dst.len() > dst_len {
+            return Err(CryptoError::BadMessage);
+        }
+
+        Ok(dst)
+    }
+
+    fn process_ctrl_queue(&mut self, mem: &AS::M) -> Result<()> {
+        let mut used: Vec<(u16, u32)> = Vec::new();
+        {
+            let queue = &mut self.ctrl_queue.queue_mut().lock();
+            let mut iter = queue.iter(mem)?;
+
+            for mut desc_chain in &mut iter {
+                let head_index = desc_chain.head_index();
+                let len = match Self::collect_request(mem, &mut desc_chain) {
+                    Ok((readable, writable)) => Self::handle_ctrl_request(
+                        mem,
+                        self.backend.as_mut(),
+                        &self.metrics,
+                        &readable,
+                        &writable,
+                    ),
+                    Err(e) => {
+                        self.metrics.request_fails.inc();
+                        error!(
+                            "{}: failed to parse control request: {}",
+                            CRYPTO_DRIVER_NAME, e
+                        );
+                        0
+                    }
+                };
+                used.push((head_index, len));
+            }
+        }
+
+        if !used.is_empty() {
+            for (desc_index, len) in used {
+                self.ctrl_queue.add_used(mem, desc_index, len);
+            }
+            self.ctrl_queue.notify()?;
+        }
+
+        Ok(())
+    }
+
+    fn process_data_queue(&mut self, mem: &AS::M) -> Result<()> {
+        let mut rate_limited = false;
+        let mut used: Vec<(u16, u32)> = Vec::new();
+        {
+            let queue = &mut self.data_queue.queue_mut().lock();
+            let mut iter = queue.iter(mem)?;
+
+            for mut desc_chain in &mut iter {
+                // If limiter.consume() fails it means there is no more
+                // TokenType::Ops budget and rate limiting is in effect.
+                if !self.rate_limiter.consume(1, TokenType::Ops) {
+                    rate_limited = true;
+                    break;
+                }
+
+                let head_index = desc_chain.head_index();
+                let (readable, writable) = match Self::collect_request(mem, &mut desc_chain) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        self.metrics.request_fails.inc();
+                        error!(
+                            "{}: failed to parse data request: {}",
+                            CRYPTO_DRIVER_NAME, e
+                        );
+                        used.push((head_index, 0));
+                        continue;
+                    }
+                };
+
+                // If limiter.consume() fails it means there is no more
+                // TokenType::Bytes budget and rate limiting is in effect.
+                if !self
+                    .rate_limiter
+                    .consume(readable.len() as u64, TokenType::Bytes)
+                {
+                    rate_limited = true;
+                    // revert the OPS consume()
+                    self.rate_limiter.manual_replenish(1, TokenType::Ops);
+                    break;
+                }
+
+                let len = Self::handle_data_request(
+                    mem,
+                    self.backend.as_mut(),
+                    &self.metrics,
+                    &readable,
+                    &writable,
+                );
+                used.push((head_index, len));
+            }
+            if rate_limited {
+                // If rate limiting kicked in, the queue had advanced one
+                // element that we aborted processing; go back one element
+                // so it can be processed next time.
+                iter.go_to_previous_position();
+            }
+        }
+
+        if !used.is_empty() {
+            for (desc_index, len) in used {
+                self.data_queue.add_used(mem, desc_index, len);
+            }
+            self.data_queue.notify()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<AS: DbsGuestAddressSpace, Q: QueueT + Send, R: GuestMemoryRegion> MutEventSubscriber
+    for CryptoEpollHandler<AS, Q, R>
+{
+    fn init(&mut self, ops: &mut EventOps) {
+        trace!(
+            target: CRYPTO_DRIVER_NAME,
+            "{}: CryptoEpollHandler::init()",
+            CRYPTO_DRIVER_NAME,
+        );
+
+        let events = Events::with_data(
+            self.data_queue.eventfd.as_ref(),
+            DATA_QUEUE_AVAIL_EVENT,
+            EventSet::IN,
+        );
+        if let Err(e) = ops.add(events) {
+            error!(
+                "{}: failed to register data queue event, {:?}",
+                CRYPTO_DRIVER_NAME, e
+            );
+        }
+
+        let events = Events::with_data(
+            self.ctrl_queue.eventfd.as_ref(),
+            CTRL_QUEUE_AVAIL_EVENT,
+            EventSet::IN,
+        );
+        if let Err(e) = ops.add(events) {
+            error!(
+                "{}: failed to register control queue event, {:?}",
+                CRYPTO_DRIVER_NAME, e
+            );
+        }
+
+        let rate_limiter_fd = self.rate_limiter.as_raw_fd();
+        if rate_limiter_fd >= 0 {
+            let events = Events::with_data_raw(rate_limiter_fd, RATE_LIMITER_EVENT, EventSet::IN);
+            if let Err(e) = ops.add(events) {
+                error!(
+                    "{}: failed to register rate limiter event, {:?}",
+                    CRYPTO_DRIVER_NAME, e
+                );
+            }
+        }
+    }
+
+    fn process(&mut self, events: Events, _ops: &mut EventOps) {
+        let guard = self.config.lock_guest_memory();
+        let mem = guard.deref();
+        let idx = events.data();
+
+        trace!(
+            target: CRYPTO_DRIVER_NAME,
+            "{}: CryptoEpollHandler::process() idx {}",
+            CRYPTO_DRIVER_NAME,
+            idx
+        );
+        self.metrics.event_count.inc();
+        match idx {
+            DATA_QUEUE_AVAIL_EVENT => {
+                self.metrics.data_queue_event_count.inc();
+                if let Err(e) = self.data_queue.consume_event() {
+                    self.metrics.event_fails.inc();
+                    error!(
+                        "{}: failed to get data queue event, {:?}",
+                        CRYPTO_DRIVER_NAME, e
+                    );
+                } else if !self.rate_limiter.is_blocked() {
+                    if let Err(e) = self.process_data_queue(mem) {
+                        self.metrics.event_fails.inc();
+                        error!(
+                            "{}: failed to process data queue, {:?}",
+                            CRYPTO_DRIVER_NAME, e
+                        );
+                    }
+                }
+            }
+            CTRL_QUEUE_AVAIL_EVENT => {
+                self.metrics.ctrl_queue_event_count.inc();
+                if let Err(e) = self.ctrl_queue.consume_event() {
+                    self.metrics.event_fails.inc();
+                    error!(
+                        "{}: failed to get control queue event, {:?}",
+                        CRYPTO_DRIVER_NAME, e
+                    );
+                } else if let Err(e) = self.process_ctrl_queue(mem) {
+                    self.metrics.event_fails.inc();
+                    error!(
+                        "{}: failed to process control queue, {:?}",
+                        CRYPTO_DRIVER_NAME, e
+                    );
+                }
+            }
+            RATE_LIMITER_EVENT => {
+                // Upon rate limiter event, call the rate limiter handler and
+                // restart processing the data queue.
+                self.metrics.rate_limiter_event_count.inc();
+                match self.rate_limiter.event_handler() {
+                    Ok(_) => {
+                        if let Err(e) = self.process_data_queue(mem) {
+                            self.metrics.event_fails.inc();
+                            error!(
+                                "{}: failed to resume data queue, {:?}",
+                                CRYPTO_DRIVER_NAME, e
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        self.metrics.event_fails.inc();
+                        error!(
+                            "{}: failed to get rate limiter event, {:?}",
+                            CRYPTO_DRIVER_NAME, e
+                        );
+                    }
+                }
+            }
+            KILL_EVENT => {
+                debug!("kill_evt received");
+            }
+            _ => {
+                error!("{}: unknown idx {}", CRYPTO_DRIVER_NAME, idx);
+            }
+        }
+    }
+}
+
+/// Virtio device exposing host crypto services to the guest OS.
+pub struct Crypto<AS: GuestAddressSpace> {
+    pub(crate) device_info: VirtioDeviceInfo,
+    backend: Option<Box<dyn CryptoBackend>>,
+    rate_limiter: Option<RateLimiter>,
+    pub(crate) subscriber_id: Option<SubscriberId>,
+    pub(crate) phantom: PhantomData<AS>,
+    metrics: Arc<CryptoDeviceMetrics>,
+}
+
+impl<AS: GuestAddressSpace> Crypto<AS> {
+    /// Create a new virtio-crypto device with the given backend.
+    ///
+    /// `rate_limiter` bounds the rate of data queue requests; control queue
+    /// traffic (session setup) is not rate limited.
+    pub fn new(
+        backend: Box<dyn CryptoBackend>,
+        rate_limiter: Option<RateLimiter>,
+        epoll_mgr: EpollManager,
+    ) -> Result<Self> {
+        let avail_features = 1u64 << VIRTIO_F_VERSION_1;
+
+        let config = VirtioCryptoConfig {
+            status: VIRTIO_CRYPTO_S_HW_READY,
+            max_dataqueues: 1,
+            crypto_services: backend.services(),
+            cipher_algo_l: backend.cipher_algos() as u32,
+            cipher_algo_h: (backend.cipher_algos() >> 32) as u32,
+            akcipher_algo: backend.akcipher_algos(),
+            max_cipher_key_len: MAX_CIPHER_KEY_LEN,
+            max_size: MAX_DATA_SIZE as u64,
+            ..Default::default()
+        };
+
+        Ok(Crypto {
+            device_info: VirtioDeviceInfo::new(
+                CRYPTO_DRIVER_NAME.to_string(),
+                avail_features,
+                Arc::new(QUEUE_SIZES.to_vec()),
+                config.as_slice().to_vec(),
+                epoll_mgr,
+            ),
+            backend: Some(backend),
+            rate_limiter,
+            subscriber_id: None,
+            phantom: PhantomData,
+            metrics: Arc::new(CryptoDeviceMetrics::default()),
+        })
+    }
+
+    /// Metrics of the crypto device.
+    pub fn metrics(&self) -> Arc<CryptoDeviceMetrics> {
+        self.metrics.clone()
+    }
+}
+
+impl<AS, Q, R> VirtioDevice<AS, Q, R> for Crypto<AS>
+where
+    AS: DbsGuestAddressSpace,
+    Q: QueueT + Send + 'static,
+    R: GuestMemoryRegion + Sync + Send + 'static,
+{
+    fn device_type(&self) -> u32 {
+        TYPE_CRYPTO
+    }
+
+    fn queue_max_sizes(&self) -> &[u16] {
+        &self.device_info.queue_sizes
+    }
+
+    fn get_avail_features(&self, page: u32) -> u32 {
+        self.device_info.get_avail_features(page)
+    }
+
+    fn set_acked_features(&mut self, page: u32, value: u32) {
+        trace!(
+            target: CRYPTO_DRIVER_NAME,
+            "{}: VirtioDevice::set_acked_features({}, 0x{:x})",
+            CRYPTO_DRIVER_NAME,
+            page,
+            value
+        );
+        self.device_info.set_acked_features(page, value)
+    }
+
+    fn read_config(&mut self, offset: u64, data: &mut [u8]) -> ConfigResult {
+        trace!(
+            target: CRYPTO_DRIVER_NAME,
+            "{}: VirtioDevice::read_config(0x{:x}, {:?})",
+            CRYPTO_DRIVER_NAME,
+            offset,
+            data
+        );
+        self.device_info.read_config(offset, data)
+    }
+
+    fn write_config(&mut self, offset: u64, data: &[u8]) -> ConfigResult {
+        // The whole virtio-crypto config space is read-only for the driver.
+        debug!(
+            "{}: guest driver attempted to write config space at 0x{:x}",
+            CRYPTO_DRIVER_NAME, offset
+        );
+        self.device_info.write_config(offset, data)
+    }
+
+    fn activate(&mut self, mut config: VirtioDeviceConfig<AS, Q, R>) -> ActivateResult {
+        self.device_info
+            .check_queue_sizes(&config.queues)
+            .inspect_err(|_| {
+                self.metrics.activate_fails.inc();
+            })?;
+
+        let backend = self.backend.take().ok_or_else(|| {
+            self.metrics.activate_fails.inc();
+            error!("{}: device has already been activated", CRYPTO_DRIVER_NAME);
+            ActivateError::InternalError
+        })?;
+
+        trace!(
+            "{}: activate acked_features 0x{:x}",
+            CRYPTO_DRIVER_NAME,
+            self.device_info.acked_features
+        );
+
+        // Queue layout per the specification: data queues first, then the
+        // control queue.
+        let data_queue = config.queues.remove(0);
+        let ctrl_queue = config.queues.remove(0);
+
+        let handler = Box::new(CryptoEpollHandler {
+            config,
+            data_queue,
+            ctrl_queue,
+            rate_limiter: self.rate_limiter.take().unwrap_or_default(),
+            backend,
+            metrics: self.metrics.clone(),
+        });
+
+        self.subscriber_id = Some(self.device_info.register_event_handler(handler));
+
+        Ok(())
+    }
+
+    fn get_resource_requirements(
+        &self,
+        requests: &mut Vec<ResourceConstraint>,
+        use_generic_irq: bool,
+    ) {
+        requests.push(ResourceConstraint::LegacyIrq { irq: None });
+        if use_generic_irq {
+            requests.push(ResourceConstraint::GenericIrq {
+                size: (self.device_info.queue_sizes.len() + 1) as u32,
+            });
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbs_interrupt::NoopNotifier;
+    use kvm_ioctls::Kvm;
+    use vm_memory::GuestMemoryMmap;
+
+    use std::convert::TryInto;
+
+    use super::kernel::KernelCryptoBackend;
+    use super::*;
+    use crate::tests::create_address_space;
+    use crate::ConfigError;
+
+    #[test]
+    fn test_crypto_virtio_device_normal() {
+        let epoll_mgr = EpollManager::default();
+        let backend = build_backend(CryptoBackendType::Kernel).unwrap();
+        let mut dev = Crypto::<Arc<GuestMemoryMmap>>::new(backend, None, epoll_mgr).unwrap();
+
+        assert_eq!(
+            VirtioDevice::<Arc<GuestMemoryMmap<()>>, QueueSync, GuestRegionMmap>::device_type(&dev),
+            TYPE_CRYPTO
+        );
+        let queue_size =
+            VirtioDevice::<Arc<GuestMemoryMmap<()>>, QueueSync, GuestRegionMmap>::queue_max_sizes(
+                &dev,
+            );
+        assert_eq!(queue_size, QUEUE_SIZES);
+
+        let mut config: [u8; 8] = Default::default();
+        VirtioDevice::<Arc<GuestMemoryMmap<()>>, QueueSync, GuestRegionMmap>::read_config(
+            &mut dev,
+            0,
+            &mut config,
+        )
+        .unwrap();
+        // status = S_HW_READY, max_dataqueues = 1.
+        assert_eq!(u32::from_le_bytes(config[0..4].try_into().unwrap()), 1);
+        assert_eq!(u32::from_le_bytes(config[4..8].try_into().unwrap()), 1);
+
+        let ret = VirtioDevice::<Arc<GuestMemoryMmap<()>>, QueueSync, GuestRegionMmap>::read_config(
+            &mut dev,
+            std::mem::size_of::<VirtioCryptoConfig>() as u64 + 1,
+            &mut config,
+        );
+        assert_eq!(
+            ret,
+            Err(ConfigError::InvalidOffset(
+                std::mem::size_of::<VirtioCryptoConfig>() as u64 + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_crypto_virtio_device_activate() {
+        let epoll_mgr = EpollManager::default();
+        let backend = build_backend(CryptoBackendType::Kernel).unwrap();
+        let mut dev =
+            Crypto::<Arc<GuestMemoryMmap>>::new(backend, Some(RateLimiter::default()), epoll_mgr)
+                .unwrap();
+
+        let mem = Arc::new(GuestMemoryMmap::from_ranges(&[(GuestAddress(0x0), 0x10000)]).unwrap());
+        let queues = vec![
+            VirtioQueueConfig::create(QUEUE_SIZE, 0).unwrap(),
+            VirtioQueueConfig::create(QUEUE_SIZE, 0).unwrap(),
+        ];
+        let kvm = Kvm::new().unwrap();
+        let vm_fd = Arc::new(kvm.create_vm().unwrap());
+        let address_space = create_address_space();
+        let config = VirtioDeviceConfig::<Arc<GuestMemoryMmap>>::new(
+            mem,
+            address_space,
+            vm_fd,
+            Default::default(),
+            queues,
+            None,
+            Arc::new(NoopNotifier::new()),
+        );
+
+        dev.activate(config).unwrap();
+        assert!(dev.subscriber_id.is_some());
+    }
+
+    #[test]
+    fn test_kernel_backend_cipher_roundtrip() {
+        let mut backend = KernelCryptoBackend::new();
+
+        // Unsupported algorithm and invalid key length are rejected.
+        assert!(matches!(
+            backend.create_cipher_session(0, &[0u8; 16]),
+            Err(CryptoError::Unsupported)
+        ));
+        assert!(matches!(
+            backend.create_cipher_session(VIRTIO_CRYPTO_CIPHER_AES_CBC, &[0u8; 15]),
+            Err(CryptoError::InvalidKeyLength(
+                15,
+                VIRTIO_CRYPTO_CIPHER_AES_CBC
+            ))
+        ));
+
+        // The test host may not expose the kernel crypto user API.
+        let session_id =
+            match backend.create_cipher_session(VIRTIO_CRYPTO_CIPHER_AES_CBC, &[7u8; 16]) {
+                Ok(v) => v,
+                Err(CryptoError::Backend(_)) => return,
+                Err(e) => panic!("unexpected session error: {}", e),
+            };
+
+        let iv = [1u8; 16];
+        let plaintext = [42u8; 32];
+        let ciphertext = backend
+            .cipher_op(session_id, true, &iv, &plaintext)
+            .unwrap();
+        assert_ne!(&ciphertext[..], &plaintext[..]);
+        let decrypted = backend
+            .cipher_op(session_id, false, &iv, &ciphertext)
+            .unwrap();
+        assert_eq!(&decrypted[..], &plaintext[..]);
+
+        assert!(matches!(
+            backend.cipher_op(0xdead, true, &iv, &plaintext),
+            Err(CryptoError::InvalidSession(0xdead))
+        ));
+
+        backend.destroy_session(session_id).unwrap();
+        assert!(matches!(
+            backend.destroy_session(session_id),
+            Err(CryptoError::InvalidSession(_))
+        ));
+    }
+
+    #[test]
+    fn test_crypto_request_status() {
+        assert_eq!(
+            request_status(&CryptoError::Unsupported),
+            VIRTIO_CRYPTO_NOTSUPP
+        );
+        assert_eq!(
+            request_status(&CryptoError::InvalidSession(1)),
+            VIRTIO_CRYPTO_INVSESS
+        );
+        assert_eq!(
+            request_status(&CryptoError::BadMessage),
+            VIRTIO_CRYPTO_BADMSG
+        );
+        assert_eq!(
+            request_status(&CryptoError::NoFreeSessions),
+            VIRTIO_CRYPTO_NOSPC
+        );
+        assert_eq!(
+            request_status(&CryptoError::Backend(io::Error::from_raw_os_error(5))),
+            VIRTIO_CRYPTO_ERR
+        );
+    }
+
+    #[test]
+    fn test_crypto_opcodes() {
+        assert_eq!(VIRTIO_CRYPTO_CIPHER_ENCRYPT, 0x0000);
+        assert_eq!(VIRTIO_CRYPTO_CIPHER_DECRYPT, 0x0001);
+        assert_eq!(VIRTIO_CRYPTO_CIPHER_CREATE_SESSION, 0x0002);
+        assert_eq!(VIRTIO_CRYPTO_CIPHER_DESTROY_SESSION, 0x0003);
+        assert_eq!(VIRTIO_CRYPTO_AKCIPHER_CREATE_SESSION, 0x0404);
+        assert_eq!(VIRTIO_CRYPTO_AKCIPHER_DESTROY_SESSION, 0x0405);
+    }
+}
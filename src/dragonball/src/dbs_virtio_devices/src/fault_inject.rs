@@ -0,0 +1,274 @@
+// Copyright 2026 Alibaba Cloud. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Test-only fault injection layer for virtio device backends.
+//!
+//! The fault injection layer lets chaos tests degrade virtual devices in a
+//! controlled way: extra I/O latency, a deterministic error rate and torn
+//! writes. It is compiled in only with the `fault-injection` feature and all
+//! faults default to "off", so an idle layer behaves exactly like the
+//! wrapped backend.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Faults to inject into a virtio device backend.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FaultInjectConfig {
+    /// Extra latency added to every read operation, in microseconds.
+    pub read_latency_us: u64,
+    /// Extra latency added to every write operation, in microseconds.
+    pub write_latency_us: u64,
+    /// How many operations out of every 1000 fail with an injected error.
+    /// Failures are spread deterministically so tests are reproducible.
+    pub error_rate_per_mille: u32,
+    /// Emulate torn writes by truncating multi-segment write requests, so
+    /// only a prefix of the data reaches the backend while the guest still
+    /// sees a successful completion.
+    pub torn_writes: bool,
+}
+
+/// Shared fault injection state for one device.
+///
+/// The device owns an `Arc<FaultInjectState>` and hands clones to its
+/// backends/handlers, so faults can be reconfigured through the API at
+/// runtime without stopping the I/O threads.
+#[derive(Debug, Default)]
+pub struct FaultInjectState {
+    read_latency_us: AtomicU64,
+    write_latency_us: AtomicU64,
+    error_rate_per_mille: AtomicU32,
+    torn_writes: AtomicBool,
+    op_count: AtomicU64,
+}
+
+impl FaultInjectState {
+    /// Create a fault injection state with all faults disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the active fault configuration.
+    pub fn update(&self, config: &FaultInjectConfig) {
+        self.read_latency_us
+            .store(config.read_latency_us, Ordering::Relaxed);
+        self.write_latency_us
+            .store(config.write_latency_us, Ordering::Relaxed);
+        self.error_rate_per_mille
+            .store(config.error_rate_per_mille.min(1000), Ordering::Relaxed);
+        self.torn_writes
+            .store(config.torn_writes, Ordering::Relaxed);
+    }
+
+    /// Get the active fault configuration.
+    pub fn config(&self) -> FaultInjectConfig {
+        FaultInjectConfig {
+            read_latency_us: self.read_latency_us.load(Ordering::Relaxed),
+            write_latency_us: self.write_latency_us.load(Ordering::Relaxed),
+            error_rate_per_mille: self.error_rate_per_mille.load(Ordering::Relaxed),
+            torn_writes: self.torn_writes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sleep for the configured read latency, blocking the calling I/O
+    /// thread like a slow backend would.
+    pub fn delay_read(&self) {
+        let us = self.read_latency_us.load(Ordering::Relaxed);
+        if us > 0 {
+            thread::sleep(Duration::from_micros(us));
+        }
+    }
+
+    /// Sleep for the configured write latency.
+    pub fn delay_write(&self) {
+        let us = self.write_latency_us.load(Ordering::Relaxed);
+        if us > 0 {
+            thread::sleep(Duration::from_micros(us));
+        }
+    }
+
+    /// Decide whether the next operation should fail, honoring the
+    /// configured error rate. Every call counts as one operation.
+    pub fn should_fail(&self) -> bool {
+        let rate = self.error_rate_per_mille.load(Ordering::Relaxed) as u64;
+        let count = self.op_count.fetch_add(1, Ordering::Relaxed);
+        rate > 0 && count % 1000 < rate
+    }
+
+    /// Whether torn writes are enabled.
+    pub fn torn_writes(&self) -> bool {
+        self.torn_writes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "virtio-blk")]
+pub use self::ufile::FaultInjectUfile;
+
+#[cfg(feature = "virtio-blk")]
+mod ufile {
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+    use std::os::unix::io::RawFd;
+    use std::sync::Arc;
+
+    use super::FaultInjectState;
+    use crate::block::{IoDataDesc, Ufile};
+
+    /// A [`Ufile`] that delegates to another backend after applying the
+    /// configured faults.
+    pub struct FaultInjectUfile {
+        inner: Box<dyn Ufile>,
+        state: Arc<FaultInjectState>,
+    }
+
+    impl FaultInjectUfile {
+        /// Wrap `inner` so its I/O is subject to the faults in `state`.
+        pub fn new(inner: Box<dyn Ufile>, state: Arc<FaultInjectState>) -> Self {
+            FaultInjectUfile { inner, state }
+        }
+
+        fn injected_error() -> io::Error {
+            io::Error::new(io::ErrorKind::Other, "injected backend I/O error")
+        }
+    }
+
+    impl Read for FaultInjectUfile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.state.delay_read();
+            if self.state.should_fail() {
+                return Err(Self::injected_error());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for FaultInjectUfile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.state.delay_write();
+            if self.state.should_fail() {
+                return Err(Self::injected_error());
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl Seek for FaultInjectUfile {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    impl Ufile for FaultInjectUfile {
+        fn get_capacity(&self) -> u64 {
+            self.inner.get_capacity()
+        }
+
+        fn get_max_size(&self) -> u32 {
+            self.inner.get_max_size()
+        }
+
+        fn get_device_id(&self) -> io::Result<String> {
+            self.inner.get_device_id()
+        }
+
+        fn get_data_evt_fd(&self) -> RawFd {
+            self.inner.get_data_evt_fd()
+        }
+
+        fn io_read_submit(
+            &mut self,
+            offset: i64,
+            iovecs: &mut Vec<IoDataDesc>,
+            user_data: u16,
+        ) -> io::Result<usize> {
+            self.state.delay_read();
+            if self.state.should_fail() {
+                return Err(Self::injected_error());
+            }
+            self.inner.io_read_submit(offset, iovecs, user_data)
+        }
+
+        fn io_write_submit(
+            &mut self,
+            offset: i64,
+            iovecs: &mut Vec<IoDataDesc>,
+            user_data: u16,
+        ) -> io::Result<usize> {
+            self.state.delay_write();
+            if self.state.should_fail() {
+                return Err(Self::injected_error());
+            }
+            // A torn write submits only a prefix of the data segments, while
+            // the completion still reports success to the guest.
+            if self.state.torn_writes() && iovecs.len() > 1 {
+                iovecs.truncate(iovecs.len().div_ceil(2));
+            }
+            self.inner.io_write_submit(offset, iovecs, user_data)
+        }
+
+        fn io_complete(&mut self) -> io::Result<Vec<(u16, u32)>> {
+            self.inner.io_complete()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_inject_state_default_is_noop() {
+        let state = FaultInjectState::new();
+        assert!(!state.should_fail());
+        assert!(!state.torn_writes());
+        assert_eq!(state.config(), FaultInjectConfig::default());
+    }
+
+    #[test]
+    fn test_fault_inject_error_rate() {
+        let state = FaultInjectState::new();
+        state.update(&FaultInjectConfig {
+            error_rate_per_mille: 250,
+            ..Default::default()
+        });
+
+        let failures = (0..1000).filter(|_| state.should_fail()).count();
+        assert_eq!(failures, 250);
+    }
+
+    #[test]
+    fn test_fault_inject_error_rate_is_capped() {
+        let state = FaultInjectState::new();
+        state.update(&FaultInjectConfig {
+            error_rate_per_mille: 5000,
+            ..Default::default()
+        });
+        assert_eq!(state.config().error_rate_per_mille, 1000);
+        assert!(state.should_fail());
+    }
+
+    #[test]
+    fn test_fault_inject_update_and_clear() {
+        let state = FaultInjectState::new();
+        let config = FaultInjectConfig {
+            read_latency_us: 10,
+            write_latency_us: 20,
+            error_rate_per_mille: 1000,
+            torn_writes: true,
+        };
+        state.update(&config);
+        assert_eq!(state.config(), config);
+        assert!(state.should_fail());
+        assert!(state.torn_writes());
+
+        state.update(&FaultInjectConfig::default());
+        assert!(!state.should_fail());
+        assert!(!state.torn_writes());
+    }
+}
@@ -44,6 +44,9 @@ pub use net_common::*;
 #[cfg(feature = "virtio-blk")]
 pub mod block;
 
+#[cfg(feature = "fault-injection")]
+pub mod fault_inject;
+
 #[cfg(feature = "virtio-fs")]
 pub mod fs;
 
@@ -53,6 +56,9 @@ pub mod mem;
 #[cfg(feature = "virtio-balloon")]
 pub mod balloon;
 
+#[cfg(feature = "virtio-crypto")]
+pub mod crypto;
+
 #[cfg(feature = "vhost")]
 pub mod vhost;
 
@@ -113,6 +119,8 @@ pub const TYPE_RNG: u32 = 4;
 pub const TYPE_BALLOON: u32 = 5;
 /// Virtio vsock device.
 pub const TYPE_VSOCK: u32 = 19;
+/// Virtio crypto device.
+pub const TYPE_CRYPTO: u32 = 20;
 /// Virtio mem device.
 pub const TYPE_MEM: u32 = 24;
 /// Virtio-fs virtual device.
@@ -275,6 +283,10 @@ pub enum Error {
     #[error("Virtio-balloon error: {0}")]
     VirtioBalloonError(#[from] balloon::BalloonError),
 
+    #[cfg(feature = "virtio-crypto")]
+    #[error("Virtio-crypto error: {0}")]
+    VirtioCryptoError(#[from] crypto::CryptoError),
+
     #[cfg(feature = "vhost")]
     /// Error from the vhost subsystem
     #[error("Vhost error: {0:?}")]
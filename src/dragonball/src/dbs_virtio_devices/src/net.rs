@@ -28,6 +28,8 @@ use vm_memory::{Bytes, GuestAddress, GuestAddressSpace, GuestMemoryRegion, Guest
 use vmm_sys_util::eventfd::EventFd;
 
 use crate::device::{VirtioDeviceConfig, VirtioDeviceInfo};
+#[cfg(feature = "fault-injection")]
+use crate::fault_inject::{FaultInjectConfig, FaultInjectState};
 use crate::{
     setup_config_space, vnet_hdr_len, ActivateError, ActivateResult, ConfigResult,
     DbsGuestAddressSpace, Error, NetDeviceMetrics, Result, TapError, VirtioDevice,
@@ -121,6 +123,8 @@ pub(crate) struct NetEpollHandler<
     patch_rate_limiter_fd: EventFd,
     receiver: Option<mpsc::Receiver<(BucketUpdate, BucketUpdate, BucketUpdate, BucketUpdate)>>,
     metrics: Arc<NetDeviceMetrics>,
+    #[cfg(feature = "fault-injection")]
+    fault_inject: Arc<FaultInjectState>,
 }
 
 impl<AS: DbsGuestAddressSpace, Q: QueueT + Send, R: GuestMemoryRegion> NetEpollHandler<AS, Q, R> {
@@ -260,6 +264,16 @@ impl<AS: DbsGuestAddressSpace, Q: QueueT + Send, R: GuestMemoryRegion> NetEpollH
         loop {
             match self.read_from_tap() {
                 Ok(count) => {
+                    // Emulate a degraded backend: delay the frame and drop
+                    // it entirely when the error rate says so.
+                    #[cfg(feature = "fault-injection")]
+                    {
+                        self.fault_inject.delay_read();
+                        if self.fault_inject.should_fail() {
+                            self.metrics.rx_fails.inc();
+                            continue;
+                        }
+                    }
                     self.rx.bytes_read = count;
                     if !self.rate_limited_rx_single_frame(mem) {
                         self.rx.deferred_frame = true;
@@ -375,6 +389,23 @@ impl<AS: DbsGuestAddressSpace, Q: QueueT + Send, R: GuestMemoryRegion> NetEpollH
                     }
                 }
 
+                // Emulate a degraded backend: delay the frame, drop it when
+                // the error rate says so, or tear it by sending a truncated
+                // frame to the tap.
+                #[cfg(feature = "fault-injection")]
+                {
+                    self.fault_inject.delay_write();
+                    if self.fault_inject.should_fail() {
+                        self.metrics.tx_fails.inc();
+                        self.tx.used_desc_heads[used_count] = header_index;
+                        used_count += 1;
+                        continue;
+                    }
+                    if self.fault_inject.torn_writes() {
+                        read_count /= 2;
+                    }
+                }
+
                 Self::write_to_tap(
                     &self.tx.frame_buf[..read_count],
                     &mut self.tap,
@@ -600,6 +631,8 @@ pub struct Net<AS: GuestAddressSpace> {
     patch_rate_limiter_fd: EventFd,
     sender: Option<mpsc::Sender<(BucketUpdate, BucketUpdate, BucketUpdate, BucketUpdate)>>,
     metrics: Arc<NetDeviceMetrics>,
+    #[cfg(feature = "fault-injection")]
+    fault_inject: Arc<FaultInjectState>,
 }
 
 impl<AS: GuestAddressSpace> Net<AS> {
@@ -661,6 +694,8 @@ impl<AS: GuestAddressSpace> Net<AS> {
             patch_rate_limiter_fd: EventFd::new(0).unwrap(),
             sender: None,
             metrics: Arc::new(NetDeviceMetrics::default()),
+            #[cfg(feature = "fault-injection")]
+            fault_inject: Arc::new(FaultInjectState::new()),
         })
     }
 
@@ -691,6 +726,19 @@ impl<AS: GuestAddressSpace> Net<AS> {
     pub fn metrics(&self) -> Arc<NetDeviceMetrics> {
         self.metrics.clone()
     }
+
+    /// Install a new fault injection configuration for this device. Takes
+    /// effect immediately, the epoll handler shares the same state.
+    #[cfg(feature = "fault-injection")]
+    pub fn set_fault_inject_config(&self, config: &FaultInjectConfig) {
+        self.fault_inject.update(config);
+    }
+
+    /// Get the active fault injection configuration.
+    #[cfg(feature = "fault-injection")]
+    pub fn fault_inject_config(&self) -> FaultInjectConfig {
+        self.fault_inject.config()
+    }
 }
 
 impl<AS: GuestAddressSpace + 'static> Net<AS> {
@@ -800,6 +848,8 @@ where
             patch_rate_limiter_fd,
             receiver: Some(receiver),
             metrics: self.metrics.clone(),
+            #[cfg(feature = "fault-injection")]
+            fault_inject: self.fault_inject.clone(),
         });
 
         self.subscriber_id = Some(self.device_info.register_event_handler(handler));
@@ -900,6 +950,8 @@ mod tests {
             patch_rate_limiter_fd: EventFd::new(0).unwrap(),
             receiver: None,
             metrics: Arc::new(NetDeviceMetrics::default()),
+            #[cfg(feature = "fault-injection")]
+            fault_inject: Arc::new(FaultInjectState::new()),
         }
     }
 
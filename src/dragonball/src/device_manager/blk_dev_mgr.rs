@@ -16,7 +16,9 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use dbs_virtio_devices as virtio;
-use dbs_virtio_devices::block::{aio::Aio, io_uring::IoUring, Block, LocalFile, Ufile};
+use dbs_virtio_devices::block::{aio::Aio, io_uring::IoUring, Block, LocalFile, MmapFile, Ufile};
+#[cfg(feature = "fault-injection")]
+use dbs_virtio_devices::fault_inject::FaultInjectConfig;
 #[cfg(feature = "vhost-user-blk")]
 use dbs_virtio_devices::vhost::vhost_user::block::VhostUserBlock;
 use serde_derive::{Deserialize, Serialize};
@@ -120,6 +122,9 @@ pub enum BlockDeviceType {
     Spdk,
     /// Local disk/file based low level device.
     RawBlock,
+    /// Read-only compressed rootfs image (erofs/squashfs) served from a
+    /// shared mmap of the host page cache, avoiding double caching.
+    MmapImage,
 }
 
 impl BlockDeviceType {
@@ -130,6 +135,9 @@ impl BlockDeviceType {
             BlockDeviceType::Spool
         } else if path.starts_with("spdk:/") {
             BlockDeviceType::Spdk
+        } else if path.ends_with(".erofs") || path.ends_with(".sqfs") || path.ends_with(".squashfs")
+        {
+            BlockDeviceType::MmapImage
         } else {
             BlockDeviceType::RawBlock
         }
@@ -145,6 +153,17 @@ pub struct BlockDeviceConfigUpdateInfo {
     pub rate_limiter: Option<RateLimiterConfigInfo>,
 }
 
+/// Fault injection configuration for a block device, used by chaos tests
+/// to emulate a degraded drive.
+#[cfg(feature = "fault-injection")]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct BlockDeviceFaultInjectInfo {
+    /// Unique identifier of the drive.
+    pub drive_id: String,
+    /// Faults to inject into the drive backend.
+    pub fault: FaultInjectConfig,
+}
+
 impl BlockDeviceConfigUpdateInfo {
     /// Provides a `BucketUpdate` description for the bandwidth rate limiter.
     pub fn bytes(&self) -> dbs_utils::rate_limiter::BucketUpdate {
@@ -586,6 +605,33 @@ impl BlockDeviceMgr {
                     }
                 }
             }
+            BlockDeviceType::MmapImage => {
+                if !cfg.is_read_only() {
+                    error!(
+                        ctx.logger(),
+                        "mmap image \"{}\" must be configured read-only",
+                        cfg.path_on_host().display()
+                    );
+                    return Err(virtio::Error::InvalidInput);
+                }
+                if cfg.num_queues != 1 {
+                    error!(
+                        ctx.logger(),
+                        "mmap image \"{}\" only supports a single queue",
+                        cfg.path_on_host().display()
+                    );
+                    return Err(virtio::Error::InvalidInput);
+                }
+                let file = OpenOptions::new().read(true).open(cfg.path_on_host())?;
+                let image = MmapFile::new(file)?;
+                info!(
+                    ctx.logger(),
+                    "Open image \"{}\" via shared mmap, digest sha256:{}",
+                    cfg.path_on_host().display(),
+                    image.image_digest()
+                );
+                block_files.push(Box::new(image));
+            }
             _ => {
                 error!(
                     ctx.logger(),
@@ -824,6 +870,34 @@ impl BlockDeviceMgr {
             None => Err(BlockDeviceError::InvalidDeviceId(new_cfg.drive_id)),
         }
     }
+
+    /// Update the fault injection settings of a virtio blk device.
+    #[cfg(feature = "fault-injection")]
+    pub fn update_device_fault_inject(
+        &mut self,
+        new_cfg: BlockDeviceFaultInjectInfo,
+    ) -> std::result::Result<(), BlockDeviceError> {
+        match self.get_index_of_drive_id(&new_cfg.drive_id) {
+            Some(index) => {
+                let device = self.info_list[index]
+                    .device
+                    .as_mut()
+                    .ok_or_else(|| BlockDeviceError::InvalidDeviceId("".to_owned()))?;
+                if let Some(mmio_dev) = device.as_any().downcast_ref::<DbsMmioV2Device>() {
+                    let guard = mmio_dev.state();
+                    let inner_dev = guard.get_inner_device();
+                    if let Some(blk_dev) = inner_dev
+                        .as_any()
+                        .downcast_ref::<virtio::block::Block<GuestAddressSpaceImpl>>()
+                    {
+                        blk_dev.set_fault_inject_config(&new_cfg.fault);
+                    }
+                }
+                Ok(())
+            }
+            None => Err(BlockDeviceError::InvalidDeviceId(new_cfg.drive_id)),
+        }
+    }
 }
 
 impl Default for BlockDeviceMgr {
@@ -0,0 +1,349 @@
+// Copyright (C) 2026 Alibaba Cloud. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::convert::TryInto;
+
+use dbs_virtio_devices as virtio;
+use serde_derive::{Deserialize, Serialize};
+use slog::{error, info};
+use virtio::crypto::{Crypto, CryptoBackendType};
+
+use crate::address_space_manager::GuestAddressSpaceImpl;
+use crate::config_manager::{
+    ConfigItem, DeviceConfigInfo, DeviceConfigInfos, RateLimiterConfigInfo,
+};
+use crate::device_manager::{DeviceManager, DeviceMgrError, DeviceOpContext};
+use crate::metric::METRICS;
+
+// The flag of whether to use the shared irq.
+const USE_SHARED_IRQ: bool = true;
+// The flag of whether to use the generic irq.
+const USE_GENERIC_IRQ: bool = false;
+
+/// Errors associated with `CryptoDeviceConfig`.
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoDeviceError {
+    /// The crypto device was already used.
+    #[error("the virtio-crypto ID was already added to a different device")]
+    CryptoDeviceAlreadyExists,
+
+    /// Cannot perform the requested operation after booting the microVM.
+    #[error("the update operation is not allowed after boot")]
+    UpdateNotAllowedPostBoot,
+
+    /// create crypto device error
+    #[error("failed to create virtio-crypto device, {0}")]
+    CreateCryptoDevice(#[source] virtio::Error),
+
+    /// hotplug crypto device error
+    #[error("cannot hotplug virtio-crypto device, {0}")]
+    HotplugDeviceFailed(#[source] DeviceMgrError),
+
+    /// create mmio device error
+    #[error("cannot create virtio-crypto mmio device, {0}")]
+    CreateMmioDevice(#[source] DeviceMgrError),
+
+    /// Cannot initialize a crypto device or add a device to the MMIO Bus.
+    #[error("failure while registering crypto device: {0}")]
+    RegisterCryptoDevice(#[source] DeviceMgrError),
+
+    /// The device manager errors.
+    #[error("DeviceManager error: {0}")]
+    DeviceManager(#[source] DeviceMgrError),
+}
+
+/// Host crypto backend selectable for a virtio-crypto device.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CryptoBackendConfigType {
+    /// Host kernel crypto API, driven through `AF_ALG` sockets.
+    Kernel,
+    /// OpenSSL, available when the VMM is built with OpenSSL support.
+    OpenSsl,
+}
+
+impl Default for CryptoBackendConfigType {
+    fn default() -> Self {
+        CryptoBackendConfigType::Kernel
+    }
+}
+
+impl From<CryptoBackendConfigType> for CryptoBackendType {
+    fn from(t: CryptoBackendConfigType) -> Self {
+        match t {
+            CryptoBackendConfigType::Kernel => CryptoBackendType::Kernel,
+            CryptoBackendConfigType::OpenSsl => CryptoBackendType::OpenSsl,
+        }
+    }
+}
+
+/// Configuration information for a virtio-crypto device.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CryptoDeviceConfigInfo {
+    /// Unique identifier of the crypto device.
+    pub crypto_id: String,
+    /// Host backend performing the crypto operations.
+    #[serde(default)]
+    pub backend: CryptoBackendConfigType,
+    /// Rate limiter for the data queue, bounding the crypto requests the
+    /// sandbox may issue.
+    pub rate_limiter: Option<RateLimiterConfigInfo>,
+    /// Use shared irq
+    pub use_shared_irq: Option<bool>,
+    /// Use generic irq
+    pub use_generic_irq: Option<bool>,
+}
+
+impl ConfigItem for CryptoDeviceConfigInfo {
+    type Err = CryptoDeviceError;
+
+    fn id(&self) -> &str {
+        &self.crypto_id
+    }
+
+    fn check_conflicts(&self, other: &Self) -> Result<(), CryptoDeviceError> {
+        if self.crypto_id.as_str() == other.crypto_id.as_str() {
+            Err(CryptoDeviceError::CryptoDeviceAlreadyExists)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Crypto Device Info
+pub type CryptoDeviceInfo = DeviceConfigInfo<CryptoDeviceConfigInfo>;
+
+/// Wrapper for the collection that holds all the Crypto Devices Configs
+#[derive(Clone)]
+pub struct CryptoDeviceMgr {
+    /// A list of `CryptoDeviceConfig` objects.
+    info_list: DeviceConfigInfos<CryptoDeviceConfigInfo>,
+    pub(crate) use_shared_irq: bool,
+}
+
+impl CryptoDeviceMgr {
+    /// Inserts `crypto_cfg` in the virtio-crypto device configuration list.
+    pub fn insert_or_update_device(
+        &mut self,
+        mut ctx: DeviceOpContext,
+        crypto_cfg: CryptoDeviceConfigInfo,
+    ) -> std::result::Result<(), CryptoDeviceError> {
+        if !cfg!(feature = "hotplug") && ctx.is_hotplug {
+            error!(ctx.logger(), "hotplug feature has been disabled.";
+            "subsystem" => "crypto_dev_mgr",);
+            return Err(CryptoDeviceError::UpdateNotAllowedPostBoot);
+        }
+
+        // If the id of the device already exists in the list, the operation is update.
+        if self
+            .get_index_of_crypto_dev(&crypto_cfg.crypto_id)
+            .is_some()
+        {
+            if ctx.is_hotplug {
+                // The backend and rate limits of a live device cannot be
+                // changed, sessions would be lost.
+                return Err(CryptoDeviceError::UpdateNotAllowedPostBoot);
+            }
+            self.info_list.insert_or_update(&crypto_cfg)?;
+        } else {
+            // Create a new crypto device
+            if !self.info_list.is_empty() {
+                error!(ctx.logger(), "only support one crypto device!"; "subsystem" => "crypto_dev_mgr");
+                return Err(CryptoDeviceError::CryptoDeviceAlreadyExists);
+            }
+
+            if !ctx.is_hotplug {
+                self.info_list.insert_or_update(&crypto_cfg)?;
+                return Ok(());
+            }
+
+            info!(ctx.logger(), "hotplug crypto device: {}", crypto_cfg.crypto_id; "subsystem" => "crypto_dev_mgr");
+            let device = Self::create_device(&crypto_cfg, &mut ctx)
+                .map_err(CryptoDeviceError::CreateCryptoDevice)?;
+            METRICS
+                .write()
+                .unwrap()
+                .crypto
+                .insert(crypto_cfg.crypto_id.clone(), device.metrics());
+
+            let mmio_dev = DeviceManager::create_mmio_virtio_device(
+                Box::new(device),
+                &mut ctx,
+                crypto_cfg.use_shared_irq.unwrap_or(self.use_shared_irq),
+                crypto_cfg.use_generic_irq.unwrap_or(USE_GENERIC_IRQ),
+            )
+            .map_err(CryptoDeviceError::CreateMmioDevice)?;
+            ctx.insert_hotplug_mmio_device(&mmio_dev, None)
+                .map_err(|e| {
+                    error!(
+                        ctx.logger(),
+                        "hotplug crypto device {} error: {}",
+                        &crypto_cfg.crypto_id, e;
+                        "subsystem" => "crypto_dev_mgr"
+                    );
+                    CryptoDeviceError::HotplugDeviceFailed(e)
+                })?;
+            let index = self.info_list.insert_or_update(&crypto_cfg)?;
+            self.info_list[index].set_device(mmio_dev);
+        }
+        Ok(())
+    }
+
+    /// Attaches all virtio-crypto devices from the CryptoDevicesConfig.
+    pub fn attach_devices(
+        &mut self,
+        ctx: &mut DeviceOpContext,
+    ) -> std::result::Result<(), CryptoDeviceError> {
+        for info in self.info_list.iter_mut() {
+            info!(ctx.logger(), "attach crypto device: {}", info.config.crypto_id; "subsystem" => "crypto_dev_mgr");
+
+            let device = Self::create_device(&info.config, ctx)
+                .map_err(CryptoDeviceError::CreateCryptoDevice)?;
+            METRICS
+                .write()
+                .unwrap()
+                .crypto
+                .insert(info.config.crypto_id.clone(), device.metrics());
+            let mmio_dev = DeviceManager::create_mmio_virtio_device(
+                Box::new(device),
+                ctx,
+                info.config.use_shared_irq.unwrap_or(self.use_shared_irq),
+                info.config.use_generic_irq.unwrap_or(USE_GENERIC_IRQ),
+            )
+            .map_err(CryptoDeviceError::RegisterCryptoDevice)?;
+            info.set_device(mmio_dev);
+        }
+
+        Ok(())
+    }
+
+    fn create_device(
+        cfg: &CryptoDeviceConfigInfo,
+        ctx: &mut DeviceOpContext,
+    ) -> std::result::Result<Crypto<GuestAddressSpaceImpl>, virtio::Error> {
+        let epoll_mgr = ctx.epoll_mgr.clone().ok_or(virtio::Error::InvalidInput)?;
+        let rate_limiter = match cfg.rate_limiter.as_ref() {
+            Some(rl) => Some(rl.try_into().map_err(virtio::Error::IOError)?),
+            None => None,
+        };
+        let backend = virtio::crypto::build_backend(cfg.backend.into())?;
+
+        Crypto::new(backend, rate_limiter, epoll_mgr)
+    }
+
+    fn get_index_of_crypto_dev(&self, crypto_id: &str) -> Option<usize> {
+        self.info_list
+            .iter()
+            .position(|info| info.config.crypto_id.eq(crypto_id))
+    }
+}
+
+impl Default for CryptoDeviceMgr {
+    /// Create a new `CryptoDeviceMgr` object..
+    fn default() -> Self {
+        CryptoDeviceMgr {
+            info_list: DeviceConfigInfos::new(),
+            use_shared_irq: USE_SHARED_IRQ,
+        }
+    }
+}
+
+impl Drop for CryptoDeviceMgr {
+    fn drop(&mut self) {
+        METRICS.write().unwrap().crypto.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device_manager::tests::create_address_space;
+    use crate::test_utils::tests::create_vm_for_test;
+
+    impl CryptoDeviceConfigInfo {
+        fn new_for_test() -> Self {
+            CryptoDeviceConfigInfo {
+                crypto_id: "crypto0".to_string(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[test]
+    fn test_crypto_config_check_conflicts() {
+        let config = CryptoDeviceConfigInfo::new_for_test();
+        let mut config2 = CryptoDeviceConfigInfo::new_for_test();
+        assert!(config.check_conflicts(&config2).is_err());
+        config2.crypto_id = "crypto1".to_string();
+        assert!(config.check_conflicts(&config2).is_ok());
+    }
+
+    #[test]
+    fn test_create_crypto_devices_configs() {
+        let mgr = CryptoDeviceMgr::default();
+        assert_eq!(mgr.info_list.len(), 0);
+        assert_eq!(mgr.get_index_of_crypto_dev("crypto0"), None);
+    }
+
+    #[test]
+    fn test_crypto_insert_or_update_device() {
+        //Init vm for test.
+        let mut vm = create_vm_for_test();
+
+        // Test for standard config
+        let device_op_ctx = DeviceOpContext::new(
+            Some(vm.epoll_manager().clone()),
+            vm.device_manager(),
+            Some(vm.vm_as().unwrap().clone()),
+            None,
+            false,
+            Some(vm.vm_config().clone()),
+            vm.shared_info().clone(),
+        );
+
+        let dummy_crypto_device = CryptoDeviceConfigInfo::new_for_test();
+        vm.device_manager_mut()
+            .crypto_manager
+            .insert_or_update_device(device_op_ctx, dummy_crypto_device)
+            .unwrap();
+        assert_eq!(vm.device_manager().crypto_manager.info_list.len(), 1);
+    }
+
+    #[test]
+    fn test_crypto_attach_device() {
+        //Init vm and insert crypto config for test.
+        let mut vm = create_vm_for_test();
+        let device_op_ctx = DeviceOpContext::new(
+            Some(vm.epoll_manager().clone()),
+            vm.device_manager(),
+            Some(vm.vm_as().unwrap().clone()),
+            Some(create_address_space()),
+            false,
+            Some(vm.vm_config().clone()),
+            vm.shared_info().clone(),
+        );
+
+        let dummy_crypto_device = CryptoDeviceConfigInfo::new_for_test();
+        vm.device_manager_mut()
+            .crypto_manager
+            .insert_or_update_device(device_op_ctx, dummy_crypto_device)
+            .unwrap();
+        assert_eq!(vm.device_manager().crypto_manager.info_list.len(), 1);
+
+        let mut device_op_ctx = DeviceOpContext::new(
+            Some(vm.epoll_manager().clone()),
+            vm.device_manager(),
+            Some(vm.vm_as().unwrap().clone()),
+            Some(create_address_space()),
+            false,
+            Some(vm.vm_config().clone()),
+            vm.shared_info().clone(),
+        );
+        assert!(vm
+            .device_manager_mut()
+            .crypto_manager
+            .attach_devices(&mut device_op_ctx)
+            .is_ok());
+        assert_eq!(vm.device_manager().crypto_manager.info_list.len(), 1);
+    }
+}
@@ -108,6 +108,12 @@ pub mod balloon_dev_mgr;
 #[cfg(feature = "virtio-balloon")]
 use self::balloon_dev_mgr::BalloonDeviceMgr;
 
+#[cfg(feature = "virtio-crypto")]
+/// Device manager for virtio-crypto devices.
+pub mod crypto_dev_mgr;
+#[cfg(feature = "virtio-crypto")]
+use self::crypto_dev_mgr::CryptoDeviceMgr;
+
 #[cfg(feature = "vhost-net")]
 /// Device manager for vhost-net devices.
 pub mod vhost_net_dev_mgr;
@@ -623,6 +629,9 @@ pub struct DeviceManager {
     #[cfg(feature = "virtio-balloon")]
     pub(crate) balloon_manager: BalloonDeviceMgr,
 
+    #[cfg(feature = "virtio-crypto")]
+    pub(crate) crypto_manager: CryptoDeviceMgr,
+
     #[cfg(feature = "vhost-net")]
     vhost_net_manager: VhostNetDeviceMgr,
 
@@ -666,6 +675,8 @@ impl DeviceManager {
             mem_manager: MemDeviceMgr::default(),
             #[cfg(feature = "virtio-balloon")]
             balloon_manager: BalloonDeviceMgr::default(),
+            #[cfg(feature = "virtio-crypto")]
+            crypto_manager: CryptoDeviceMgr::default(),
             #[cfg(feature = "vhost-net")]
             vhost_net_manager: VhostNetDeviceMgr::default(),
             #[cfg(feature = "vhost-user-net")]
@@ -837,6 +848,11 @@ impl DeviceManager {
         #[cfg(feature = "virtio-vsock")]
         self.vsock_manager.attach_devices(&mut ctx)?;
 
+        #[cfg(feature = "virtio-crypto")]
+        self.crypto_manager
+            .attach_devices(&mut ctx)
+            .map_err(StartMicroVmError::CryptoDeviceError)?;
+
         #[cfg(any(feature = "virtio-blk", feature = "vhost-user-blk"))]
         self.block_manager
             .generate_kernel_boot_args(kernel_config)
@@ -1329,6 +1345,8 @@ mod tests {
                 mem_manager: MemDeviceMgr::default(),
                 #[cfg(feature = "virtio-balloon")]
                 balloon_manager: BalloonDeviceMgr::default(),
+                #[cfg(feature = "virtio-crypto")]
+                crypto_manager: CryptoDeviceMgr::default(),
                 #[cfg(target_arch = "aarch64")]
                 mmio_device_info: HashMap::new(),
                 #[cfg(feature = "vhost-net")]
@@ -1378,6 +1396,7 @@ mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
         vm.set_vm_config(vm_config.clone());
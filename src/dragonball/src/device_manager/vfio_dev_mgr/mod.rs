@@ -43,6 +43,14 @@ use crate::resource_manager::{ResourceError, ResourceManager};
 // The flag of whether to use the shared irq.
 const USE_SHARED_IRQ: bool = true;
 
+/// PCI class code of an NVM Express controller (class 0x01, subclass 0x08,
+/// prog-if 0x02).
+const PCI_CLASS_NVME: u32 = 0x0108_02;
+
+/// PCI class of a bridge device (class 0x06), compared against the upper
+/// byte of the class code.
+const PCI_CLASS_BRIDGE: u32 = 0x06;
+
 /// Errors associated with the operations allowed on a host device
 #[derive(Debug, thiserror::Error)]
 pub enum VfioDeviceError {
@@ -122,6 +130,14 @@ pub enum VfioDeviceError {
     #[error("vfio container not found")]
     VfioContainerNotFound,
 
+    /// The IOMMU group of the device cannot be given to the guest as a whole.
+    #[error("IOMMU group of device {0} is not viable: {1}")]
+    IommuGroupNotViable(String, String),
+
+    /// Failed to reset the device while giving it back to the host.
+    #[error("failed to reset VFIO device {0}: {1:?}")]
+    ResetFailed(String, #[source] std::io::Error),
+
     /// Generic IO error.
     #[error("Generic IO error, {0}")]
     IoError(#[source] std::io::Error),
@@ -149,6 +165,10 @@ pub struct VfioPciDeviceConfig {
     pub guest_dev_id: Option<u8>,
     /// Clique ID for Nvidia GPUs and RDMA NICs
     pub clique_id: Option<u8>,
+    /// Hint that the function is an NVMe controller or namespace. When
+    /// unset the PCI class code from sysfs decides; the hint lets the
+    /// runtime force NVMe handling for functions with exotic class codes.
+    pub nvme: Option<bool>,
 }
 
 impl VfioPciDeviceConfig {
@@ -384,6 +404,15 @@ impl VfioDeviceMgr {
     ) -> Result<()> {
         let device = info.device.take().ok_or(VfioDeviceError::InvalidConfig)?;
         self.remove_pci_vfio_device(&device, ctx)?;
+
+        // A function level reset drops any guest-created submission queues,
+        // so the host nvme driver can rebind the controller without running
+        // into admin queue timeouts.
+        let sysfs_path = Self::build_sysfs_path(&info.config)?;
+        if Self::is_nvme_device(&info.config.dev_config, &sysfs_path) {
+            Self::reset_device(&sysfs_path)?;
+        }
+
         Ok(())
     }
 
@@ -574,6 +603,18 @@ impl VfioDeviceMgr {
             "subsystem" => "vfio_dev_mgr",
              "host_bdf" => &cfg.bus_slot_func,
         );
+        // NVMe functions get whole-group validation up front: handing an
+        // incomplete IOMMU group to the guest would either fail late in the
+        // VFIO ioctls or leave a host-driven function exposed to guest DMA.
+        if Self::is_nvme_device(cfg, &sysfs_path) {
+            Self::validate_iommu_group(&sysfs_path)?;
+            slog::info!(
+                ctx.logger(),
+                "attach NVMe function via vfio-pci";
+                "subsystem" => "vfio_dev_mgr",
+                "host_bdf" => &cfg.bus_slot_func,
+            );
+        }
         // safe to get pci_manager
         let pci_manager = self.create_pci_manager(
             ctx.irq_manager.clone(),
@@ -720,6 +761,84 @@ impl VfioDeviceMgr {
     pub fn get_pci_manager(&mut self) -> Option<&mut Arc<PciSystemManager>> {
         self.pci_vfio_manager.as_mut()
     }
+
+    /// Whether the host function is an NVMe controller, either by the
+    /// runtime's hint or by the PCI class code from sysfs.
+    fn is_nvme_device(cfg: &VfioPciDeviceConfig, sysfs_path: &str) -> bool {
+        if let Some(hint) = cfg.nvme {
+            return hint;
+        }
+        read_pci_class(Path::new(sysfs_path))
+            .map(|class| class == PCI_CLASS_NVME)
+            .unwrap_or(false)
+    }
+
+    /// Ensure every endpoint sharing the IOMMU group of `sysfs_path` is
+    /// bound to vfio-pci. The group is the smallest isolation unit the
+    /// IOMMU provides, so a function left with a host driver would be
+    /// reachable by guest DMA.
+    fn validate_iommu_group(sysfs_path: &str) -> Result<()> {
+        let not_viable = |reason: String| {
+            VfioDeviceError::IommuGroupNotViable(sysfs_path.to_string(), reason)
+        };
+
+        let group_link = std::fs::read_link(Path::new(sysfs_path).join("iommu_group"))
+            .map_err(|e| not_viable(format!("no IOMMU group: {}", e)))?;
+        let group = group_link
+            .file_name()
+            .ok_or_else(|| not_viable("malformed IOMMU group link".to_string()))?
+            .to_os_string();
+
+        let devices_dir = Path::new("/sys/kernel/iommu_groups")
+            .join(&group)
+            .join("devices");
+        let entries = std::fs::read_dir(&devices_dir)
+            .map_err(|e| not_viable(format!("cannot enumerate group devices: {}", e)))?;
+
+        for entry in entries.flatten() {
+            let dev_path = entry.path();
+
+            // Bridges stay with the host, they are not DMA initiators the
+            // guest could reach.
+            if let Some(class) = read_pci_class(&dev_path) {
+                if class >> 16 == PCI_CLASS_BRIDGE {
+                    continue;
+                }
+            }
+
+            let driver = std::fs::read_link(dev_path.join("driver"))
+                .ok()
+                .and_then(|p| p.file_name().map(|f| f.to_string_lossy().into_owned()));
+            if driver.as_deref() != Some("vfio-pci") {
+                return Err(not_viable(format!(
+                    "device {} is bound to {}, expected vfio-pci",
+                    entry.file_name().to_string_lossy(),
+                    driver.as_deref().unwrap_or("no driver"),
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Give the function back to the host in a clean state via a function
+    /// level reset, if the device supports one.
+    fn reset_device(sysfs_path: &str) -> Result<()> {
+        let reset_path = Path::new(sysfs_path).join("reset");
+        if reset_path.exists() {
+            std::fs::write(&reset_path, "1\n")
+                .map_err(|e| VfioDeviceError::ResetFailed(sysfs_path.to_string(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the PCI class code of a device from its sysfs `class` attribute,
+/// e.g. "0x010802" for an NVMe controller.
+fn read_pci_class(dev_path: &Path) -> Option<u32> {
+    std::fs::read_to_string(dev_path.join("class"))
+        .ok()
+        .and_then(|s| u32::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok())
 }
 
 #[cfg(all(test, feature = "test-mock"))]
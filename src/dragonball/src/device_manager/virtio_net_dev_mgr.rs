@@ -12,6 +12,8 @@ use std::sync::Arc;
 use dbs_utils::net::{MacAddr, Tap, TapError};
 use dbs_utils::rate_limiter::BucketUpdate;
 use dbs_virtio_devices as virtio;
+#[cfg(feature = "fault-injection")]
+use dbs_virtio_devices::fault_inject::FaultInjectConfig;
 use dbs_virtio_devices::net::Net;
 use dbs_virtio_devices::Error as VirtioError;
 use serde_derive::{Deserialize, Serialize};
@@ -103,6 +105,17 @@ pub struct VirtioNetDeviceConfigUpdateInfo {
     pub tx_rate_limiter: Option<RateLimiterConfigInfo>,
 }
 
+/// Fault injection configuration for a virtio net device, used by chaos
+/// tests to emulate a degraded network backend.
+#[cfg(feature = "fault-injection")]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct VirtioNetDeviceFaultInjectInfo {
+    /// ID of the guest network interface.
+    pub iface_id: String,
+    /// Faults to inject into the network backend.
+    pub fault: FaultInjectConfig,
+}
+
 impl VirtioNetDeviceConfigUpdateInfo {
     /// Provides a `BucketUpdate` description for the RX bandwidth rate limiter.
     pub fn rx_bytes(&self) -> BucketUpdate {
@@ -318,6 +331,36 @@ impl VirtioNetDeviceMgr {
         }
     }
 
+    /// Update the fault injection settings of a virtio net device.
+    #[cfg(feature = "fault-injection")]
+    pub fn update_device_fault_inject(
+        &mut self,
+        new_cfg: VirtioNetDeviceFaultInjectInfo,
+    ) -> std::result::Result<(), VirtioNetDeviceError> {
+        match self.get_index_of_iface_id(&new_cfg.iface_id) {
+            Some(index) => {
+                let device = self.info_list[index].device.as_mut().ok_or_else(|| {
+                    VirtioNetDeviceError::InvalidIfaceId(new_cfg.iface_id.clone())
+                })?;
+
+                if let Some(mmio_dev) = device.as_any().downcast_ref::<DbsMmioV2Device>() {
+                    let guard = mmio_dev.state();
+                    let inner_dev = guard.get_inner_device();
+                    if let Some(net_dev) = inner_dev
+                        .as_any()
+                        .downcast_ref::<virtio::net::Net<GuestAddressSpaceImpl>>()
+                    {
+                        net_dev.set_fault_inject_config(&new_cfg.fault);
+                    }
+                }
+                Ok(())
+            }
+            None => Err(VirtioNetDeviceError::InvalidIfaceId(
+                new_cfg.iface_id.clone(),
+            )),
+        }
+    }
+
     /// Attach all configured net device to the virtual machine instance.
     pub fn attach_devices(
         &mut self,
@@ -196,6 +196,11 @@ pub enum StartMicroVmError {
     #[error("virtio-balloon errors: {0}")]
     BalloonDeviceError(#[source] device_manager::balloon_dev_mgr::BalloonDeviceError),
 
+    #[cfg(feature = "virtio-crypto")]
+    /// Virtio-crypto errors.
+    #[error("virtio-crypto errors: {0}")]
+    CryptoDeviceError(#[source] device_manager::crypto_dev_mgr::CryptoDeviceError),
+
     /// Vhost-net device errors.
     #[cfg(feature = "vhost-net")]
     #[error("vhost-net errors: {0:?}")]
@@ -13,6 +13,8 @@ use dbs_legacy_devices::SerialDeviceMetrics;
 use dbs_utils::metric::SharedIncMetric;
 #[cfg(feature = "virtio-balloon")]
 use dbs_virtio_devices::balloon::BalloonDeviceMetrics;
+#[cfg(feature = "virtio-crypto")]
+use dbs_virtio_devices::crypto::CryptoDeviceMetrics;
 use lazy_static::lazy_static;
 use serde::Serialize;
 
@@ -80,6 +82,9 @@ pub struct DragonballMetrics {
     #[cfg(feature = "virtio-balloon")]
     /// Metrics related to balloon device.
     pub balloon: HashMap<String, Arc<BalloonDeviceMetrics>>,
+    #[cfg(feature = "virtio-crypto")]
+    /// Metrics related to crypto device.
+    pub crypto: HashMap<String, Arc<CryptoDeviceMetrics>>,
 }
 
 #[cfg(test)]
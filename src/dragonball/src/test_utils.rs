@@ -39,6 +39,7 @@ pub mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
         vm.set_vm_config(vm_config);
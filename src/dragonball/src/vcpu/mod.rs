@@ -32,4 +32,8 @@ pub struct VcpuConfig {
     /// if vpmu feature is FullyEnabled, it means all vpmu counters are supported
     /// For aarch64, VpmuFeatureLevel only supports Disabled and FullyEnabled.
     pub vpmu_feature: VpmuFeatureLevel,
+    /// whether to expose the invariant TSC CPUID bit to the guest (x86_64 only).
+    /// Hidden by default so that guests don't rely on a TSC frequency that may
+    /// change when a snapshot is restored on a different host.
+    pub tsc_invariant: bool,
 }
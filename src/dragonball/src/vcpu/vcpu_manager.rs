@@ -83,6 +83,16 @@ pub enum VcpuManagerError {
     #[error("Vcpu is not create")]
     VcpuNotCreate,
 
+    /// The TSC frequency of the vcpus is not available.
+    #[cfg(target_arch = "x86_64")]
+    #[error("the vCPU TSC frequency is not available")]
+    TscKhzUnavailable,
+
+    /// A snapshot TSC frequency cannot be restored on this host.
+    #[cfg(target_arch = "x86_64")]
+    #[error("snapshot TSC frequency {0} KHz cannot be restored on a host running at {1} KHz")]
+    TscFrequencyMismatch(u32, u32),
+
     /// The number of max_vcpu reached kvm's limitation
     #[error("specified vcpu count {0} is greater than max allowed count {1} by kvm")]
     MaxVcpuLimitation(u8, usize),
@@ -235,6 +245,8 @@ pub struct VcpuManager {
     // X86 specific fields.
     #[cfg(target_arch = "x86_64")]
     pub(crate) supported_cpuid: kvm_bindings::CpuId,
+    #[cfg(target_arch = "x86_64")]
+    tsc_khz: Option<u32>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -309,6 +321,7 @@ impl VcpuManager {
                 dies_per_socket: vm_config_info.cpu_topology.dies_per_socket,
                 sockets: vm_config_info.cpu_topology.sockets,
                 vpmu_feature: vpmu_feature_level,
+                tsc_invariant: vm_config_info.tsc_invariant,
             },
             vcpu_seccomp_filter,
             vcpu_state_event,
@@ -325,6 +338,8 @@ impl VcpuManager {
             upcall_channel: None,
             #[cfg(target_arch = "x86_64")]
             supported_cpuid,
+            #[cfg(target_arch = "x86_64")]
+            tsc_khz: None,
         }));
 
         let handler = Box::new(VcpuEpollHandler {
@@ -555,6 +570,21 @@ impl VcpuManager {
             }
         };
 
+        // Record the TSC frequency the vcpus run at, so that it can be stored
+        // in snapshot metadata and validated when the snapshot is restored.
+        #[cfg(target_arch = "x86_64")]
+        if self.tsc_khz.is_none() {
+            match kvm_vcpu.get_tsc_khz() {
+                Ok(khz) => {
+                    self.tsc_khz = Some(khz);
+                    self.shared_info.write().unwrap().tsc_khz = Some(khz);
+                }
+                Err(e) => {
+                    log::warn!("failed to get TSC frequency of vcpu {}: {}", cpu_index, e)
+                }
+            }
+        }
+
         let mut vcpu = self.create_vcpu_arch(cpu_index, kvm_vcpu, request_ts)?;
         METRICS
             .write()
@@ -825,6 +855,37 @@ impl VcpuManager {
     pub fn vpmu_feature(&self) -> VpmuFeatureLevel {
         self.vcpu_config.vpmu_feature
     }
+
+    /// get the TSC frequency in KHz the vcpus run at, recorded when the first
+    /// vcpu was created
+    #[cfg(target_arch = "x86_64")]
+    pub fn tsc_khz(&self) -> Option<u32> {
+        self.tsc_khz
+    }
+
+    /// Validate a TSC frequency recorded in snapshot metadata against the one
+    /// the vcpus currently run at. When the frequencies differ, try to scale
+    /// the guest TSC to the recorded frequency, which needs hardware TSC
+    /// scaling support (KVM_CAP_TSC_CONTROL); fail otherwise so the guest
+    /// never silently observes a TSC rate change after restore.
+    #[cfg(target_arch = "x86_64")]
+    pub fn validate_tsc_khz(&mut self, snapshot_khz: u32) -> Result<()> {
+        let current_khz = self.tsc_khz.ok_or(VcpuManagerError::TscKhzUnavailable)?;
+        if current_khz == snapshot_khz {
+            return Ok(());
+        }
+        for vcpu_info in self.vcpu_infos.iter() {
+            if let Some(vcpu_fd) = &vcpu_info.vcpu_fd {
+                vcpu_fd.set_tsc_khz(snapshot_khz).map_err(|_| {
+                    VcpuManagerError::TscFrequencyMismatch(snapshot_khz, current_khz)
+                })?;
+            }
+        }
+        self.tsc_khz = Some(snapshot_khz);
+        self.shared_info.write().unwrap().tsc_khz = Some(snapshot_khz);
+
+        Ok(())
+    }
 }
 
 #[cfg(feature = "hotplug")]
@@ -1133,6 +1194,7 @@ mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
         vm.set_vm_config(vm_config);
@@ -1182,6 +1244,7 @@ mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
         vm.set_vm_config(vm_config.clone());
@@ -136,6 +136,7 @@ impl Vcpu {
             vcpu_config.cores_per_die,
             vcpu_config.dies_per_socket,
             vcpu_config.vpmu_feature,
+            vcpu_config.tsc_invariant,
         )
         .map_err(VcpuError::CpuId)?;
         process_cpuid(&mut self.cpuid, &cpuid_vm_spec).map_err(|e| {
@@ -128,6 +128,10 @@ pub struct VmConfigInfo {
     pub cpu_topology: CpuTopology,
     /// vpmu support level
     pub vpmu_feature: u8,
+    /// Expose the invariant TSC CPUID bit to the guest (x86_64 only). Off by
+    /// default since it prevents restoring snapshots on hosts with a
+    /// different TSC frequency unless TSC scaling is available.
+    pub tsc_invariant: bool,
 
     /// Memory type that can be either hugetlbfs or shmem, default is shmem
     pub mem_type: String,
@@ -156,6 +160,7 @@ impl Default for VmConfigInfo {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             mem_type: String::from("shmem"),
             mem_file_path: String::from(""),
             mem_size_mib: 128,
@@ -933,6 +938,7 @@ pub mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
 
@@ -966,6 +972,7 @@ pub mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
         vm.set_vm_config(vm_config);
@@ -1015,6 +1022,7 @@ pub mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
 
@@ -1092,6 +1100,7 @@ pub mod tests {
                 sockets: 1,
             },
             vpmu_feature: 0,
+            tsc_invariant: false,
             pci_hotplug_enabled: false,
         };
 
@@ -6,8 +6,11 @@
 package agent_policy
 
 default AddARPNeighborsRequest := true
+default AddNetworkRequest := true
+default AddSpecFragmentRequest := true
 default AddSwapRequest := true
 default CloseStdinRequest := true
+default CompactMemoryRequest := true
 default CopyFileRequest := true
 default CreateContainerRequest := true
 default CreateSandboxRequest := true
@@ -19,11 +22,16 @@ default GuestDetailsRequest := true
 default ListInterfacesRequest := true
 default ListRoutesRequest := true
 default MemHotplugByProbeRequest := true
+default NegotiateApiRequest := true
 default OnlineCPUMemRequest := true
 default PauseContainerRequest := true
+default PortForwardRequest := true
 default PullImageRequest := true
 default ReadStreamRequest := true
+default QuiesceSandboxRequest := true
+default ReloadConfigRequest := true
 default RemoveContainerRequest := true
+default RemoveNetworkRequest := true
 default RemoveStaleVirtiofsShareMountsRequest := true
 default ReseedRandomDevRequest := true
 default ResumeContainerRequest := true
@@ -33,11 +41,14 @@ default SignalProcessRequest := true
 default StartContainerRequest := true
 default StartTracingRequest := true
 default StatsContainerRequest := true
+default ThawSandboxRequest := true
 default StopTracingRequest := true
 default TtyWinResizeRequest := true
 default UpdateContainerRequest := true
+default UpdateDNSRequest := true
 default UpdateEphemeralMountsRequest := true
 default UpdateInterfaceRequest := true
 default UpdateRoutesRequest := true
 default WaitProcessRequest := true
+default WaitProcessesRequest := true
 default WriteStreamRequest := true
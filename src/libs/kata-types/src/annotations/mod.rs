@@ -56,6 +56,9 @@ pub const KATA_ANNO_CONTAINER_RES_SWAPPINESS: &str =
 /// A container annotation to specify the Resources.Memory.Swap.
 pub const KATA_ANNO_CONTAINER_RES_SWAP_IN_BYTES: &str =
     "io.katacontainers.container.resource.swap_in_bytes";
+/// A container annotation to specify the size in bytes of the tmpfs mounted at /dev/shm.
+pub const KATA_ANNO_CONTAINER_RES_SHM_SIZE: &str =
+    "io.katacontainers.container.resource.shm_size";
 
 // Agent related annotations
 /// Prefix for Agent configurations.
@@ -433,6 +436,14 @@ impl Annotation {
     pub fn get_container_resource_swap_in_bytes(&self) -> Option<String> {
         self.get(KATA_ANNO_CONTAINER_RES_SWAP_IN_BYTES)
     }
+
+    /// Get the annotation to specify the size in bytes of the container's /dev/shm.
+    pub fn get_container_resource_shm_size(&self) -> Result<Option<u64>> {
+        self.get_value::<u64>(KATA_ANNO_CONTAINER_RES_SHM_SIZE)
+            .map_err(|_e| {
+                io::Error::new(io::ErrorKind::InvalidData, "parse u64 error".to_string())
+            })
+    }
 }
 
 impl Annotation {
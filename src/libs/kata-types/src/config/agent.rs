@@ -10,8 +10,8 @@ use crate::config::{ConfigOps, TomlConfig};
 pub use vendor::AgentVendor;
 
 use super::default::{
-    DEFAULT_AGENT_DIAL_TIMEOUT_MS, DEFAULT_AGENT_LOG_PORT, DEFAULT_AGENT_VSOCK_PORT,
-    DEFAULT_PASSFD_LISTENER_PORT,
+    DEFAULT_AGENT_DIAL_TIMEOUT_MS, DEFAULT_AGENT_LOG_PORT, DEFAULT_AGENT_SHUTDOWN_REASON_PORT,
+    DEFAULT_AGENT_VSOCK_PORT, DEFAULT_PASSFD_LISTENER_PORT,
 };
 use crate::eother;
 
@@ -103,6 +103,10 @@ pub struct Agent {
     #[serde(default = "default_passfd_listener_port")]
     pub passfd_listener_port: u32,
 
+    /// Port the agent reports a structured shutdown reason on, 0 disables it
+    #[serde(default = "default_shutdown_reason_port")]
+    pub shutdown_reason_port: u32,
+
     /// Agent connection dialing timeout value in millisecond
     #[serde(default = "default_dial_timeout")]
     pub dial_timeout_ms: u32,
@@ -152,6 +156,7 @@ impl std::default::Default for Agent {
             server_port: DEFAULT_AGENT_VSOCK_PORT,
             log_port: DEFAULT_AGENT_LOG_PORT,
             passfd_listener_port: DEFAULT_PASSFD_LISTENER_PORT,
+            shutdown_reason_port: DEFAULT_AGENT_SHUTDOWN_REASON_PORT,
             dial_timeout_ms: DEFAULT_AGENT_DIAL_TIMEOUT_MS,
             reconnect_timeout_ms: 3_000,
             request_timeout_ms: 30_000,
@@ -179,6 +184,10 @@ fn default_passfd_listener_port() -> u32 {
     DEFAULT_PASSFD_LISTENER_PORT
 }
 
+fn default_shutdown_reason_port() -> u32 {
+    DEFAULT_AGENT_SHUTDOWN_REASON_PORT
+}
+
 fn default_dial_timeout() -> u32 {
     // ms
     10
@@ -26,6 +26,7 @@ pub const DEFAULT_AGENT_VSOCK_PORT: u32 = 1024;
 pub const DEFAULT_AGENT_LOG_PORT: u32 = 1025;
 pub const DEFAULT_AGENT_DBG_CONSOLE_PORT: u32 = 1026;
 pub const DEFAULT_PASSFD_LISTENER_PORT: u32 = 1027;
+pub const DEFAULT_AGENT_SHUTDOWN_REASON_PORT: u32 = 1028;
 pub const DEFAULT_AGENT_TYPE_NAME: &str = AGENT_NAME_KATA;
 pub const DEFAULT_AGENT_DIAL_TIMEOUT_MS: u32 = 10;
 
@@ -34,6 +35,10 @@ pub const DEFAULT_HYPERVISOR: &str = HYPERVISOR_NAME_DRAGONBALL;
 
 pub const DEFAULT_INTERNETWORKING_MODEL: &str = "tcfilter";
 
+pub const DEFAULT_AUDIT_LOG_PATH: &str = "/var/log/kata/audit";
+pub const DEFAULT_AUDIT_LOG_MAX_SIZE_MB: u64 = 16;
+pub const DEFAULT_AUDIT_LOG_MAX_FILES: u32 = 3;
+
 pub const DEFAULT_BLOCK_DEVICE_TYPE: &str = "virtio-blk-pci";
 pub const DEFAULT_VHOST_USER_STORE_PATH: &str = "/var/run/vhost-user";
 pub const DEFAULT_BLOCK_NVDIMM_MEM_OFFSET: u64 = 0;
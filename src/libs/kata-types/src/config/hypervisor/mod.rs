@@ -72,6 +72,30 @@ const MAX_BRIDGE_SIZE: u32 = 5;
 
 const KERNEL_PARAM_DELIMITER: &str = " ";
 
+/// Minimum configurable virtio queue size.
+const MIN_VIRTIO_QUEUE_SIZE: u32 = 16;
+/// Maximum configurable virtio queue size.
+const MAX_VIRTIO_QUEUE_SIZE: u32 = 1024;
+
+/// Validate a configured virtio queue size: zero selects the hypervisor built-in default, any
+/// other value must be a power of two within [MIN_VIRTIO_QUEUE_SIZE, MAX_VIRTIO_QUEUE_SIZE].
+fn validate_queue_size(option: &str, size: u32) -> Result<()> {
+    if size == 0
+        || (size.is_power_of_two()
+            && (MIN_VIRTIO_QUEUE_SIZE..=MAX_VIRTIO_QUEUE_SIZE).contains(&size))
+    {
+        Ok(())
+    } else {
+        Err(eother!(
+            "Invalid {} {}, must be a power of two between {} and {}",
+            option,
+            size,
+            MIN_VIRTIO_QUEUE_SIZE,
+            MAX_VIRTIO_QUEUE_SIZE
+        ))
+    }
+}
+
 lazy_static! {
     static ref HYPERVISOR_PLUGINS: Mutex<HashMap<String, Arc<dyn ConfigPlugin>>> =
         Mutex::new(HashMap::new());
@@ -121,6 +145,13 @@ pub struct BlockDeviceInfo {
     #[serde(default)]
     pub block_device_cache_noflush: bool,
 
+    /// Default size of virtqueues for block devices.
+    ///
+    /// The default (0) lets the hypervisor choose; any other value must be a power of two
+    /// between 16 and 1024.
+    #[serde(default)]
+    pub virtio_blk_queue_size: u32,
+
     /// If false and nvdimm is supported, use nvdimm device to plug guest image.
     #[serde(default)]
     pub disable_image_nvdimm: bool,
@@ -201,6 +232,7 @@ impl BlockDeviceInfo {
                 self.block_device_driver
             ));
         }
+        validate_queue_size("virtio_blk_queue_size", self.virtio_blk_queue_size)?;
         validate_path!(
             self.vhost_user_store_path,
             "Invalid vhost-user-store-path {}: {}"
@@ -500,6 +532,26 @@ pub struct DeviceInfo {
     /// Enabling this will result in the VM balloon device having f_reporting=on set
     #[serde(default)]
     pub reclaim_guest_freed_memory: bool,
+
+    /// Number of times a hotplug control-plane operation (a QMP command or a
+    /// Cloud Hypervisor REST request) is retried after a transient failure
+    /// such as EAGAIN. Zero disables retries. Defaults to 3.
+    #[serde(default = "default_hotplug_retries")]
+    pub hotplug_retries: u32,
+
+    /// Base delay in milliseconds between hotplug retry attempts. The delay
+    /// grows exponentially with each attempt and some random jitter is added
+    /// to avoid synchronized retry storms. Defaults to 100 ms.
+    #[serde(default = "default_hotplug_retry_delay_ms")]
+    pub hotplug_retry_delay_ms: u64,
+}
+
+fn default_hotplug_retries() -> u32 {
+    3
+}
+
+fn default_hotplug_retry_delay_ms() -> u64 {
+    100
 }
 
 impl DeviceInfo {
@@ -508,6 +560,9 @@ impl DeviceInfo {
         if self.default_bridges > MAX_BRIDGE_SIZE {
             self.default_bridges = MAX_BRIDGE_SIZE;
         }
+        if self.hotplug_retry_delay_ms == 0 {
+            self.hotplug_retry_delay_ms = default_hotplug_retry_delay_ms();
+        }
 
         Ok(())
     }
@@ -788,6 +843,13 @@ pub struct NetworkInfo {
     /// network queues
     #[serde(default)]
     pub network_queues: u32,
+
+    /// Default size of virtqueues for network devices.
+    ///
+    /// The default (0) lets the hypervisor choose; any other value must be a power of two
+    /// between 16 and 1024.
+    #[serde(default)]
+    pub virtio_net_queue_size: u32,
 }
 
 impl NetworkInfo {
@@ -798,6 +860,7 @@ impl NetworkInfo {
 
     /// Validate the configuration information.
     pub fn validate(&self) -> Result<()> {
+        validate_queue_size("virtio_net_queue_size", self.virtio_net_queue_size)?;
         Ok(())
     }
 }
@@ -1034,6 +1097,7 @@ impl SharedFsInfo {
                 &self.virtio_fs_cache_size
             ));
         }
+        validate_queue_size("virtio_fs_queue_size", self.virtio_fs_queue_size)?;
         Ok(())
     }
 }
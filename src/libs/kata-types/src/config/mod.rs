@@ -37,6 +37,29 @@ pub use self::agent::AGENT_NAME_KATA;
 /// kata run dir
 pub const KATA_PATH: &str = "/run/kata";
 
+lazy_static! {
+    static ref KATA_STATE_ROOT: Mutex<String> = Mutex::new(String::from(KATA_PATH));
+}
+
+/// Override the process-wide state root directory used for sandbox state
+/// and ephemeral files.
+///
+/// All runtime classes share [`KATA_PATH`] by default, so one class can
+/// fill the tmpfs backing it and break the others. A runtime class may
+/// configure its own `state_root`, and the shim applies it here once,
+/// right after loading the runtime configuration.
+pub fn set_kata_state_root(root: &str) {
+    if !root.is_empty() {
+        *KATA_STATE_ROOT.lock().unwrap() = root.to_string();
+    }
+}
+
+/// Get the state root directory of the active runtime class, defaulting to
+/// [`KATA_PATH`].
+pub fn kata_state_root() -> String {
+    KATA_STATE_ROOT.lock().unwrap().clone()
+}
+
 // TODO: let agent use the constants here for consistency
 /// Debug console enabled flag for agent
 pub const DEBUG_CONSOLE_FLAG: &str = "agent.debug_console";
@@ -52,6 +75,8 @@ pub const LOG_LEVEL_DEBUG: &str = "debug";
 pub const DEBUG_CONSOLE_VPORT_OPTION: &str = "agent.debug_console_vport";
 /// Option of which port the agent's log will connect to
 pub const LOG_VPORT_OPTION: &str = "agent.log_vport";
+/// Option of which port the agent reports its shutdown reason on
+pub const SHUTDOWN_REASON_VPORT_OPTION: &str = "agent.shutdown_reason_vport";
 /// Option of setting the container's pipe size
 pub const CONTAINER_PIPE_SIZE_OPTION: &str = "agent.container_pipe_size";
 /// Option of setting the fd passthrough io listener port
@@ -205,6 +230,12 @@ impl TomlConfig {
                 let container_pipe_size = cfg.container_pipe_size.to_string();
                 kv.insert(CONTAINER_PIPE_SIZE_OPTION.to_string(), container_pipe_size);
             }
+            if cfg.shutdown_reason_port > 0 {
+                kv.insert(
+                    SHUTDOWN_REASON_VPORT_OPTION.to_string(),
+                    cfg.shutdown_reason_port.to_string(),
+                );
+            }
             if cfg.debug_console_enabled {
                 kv.insert(DEBUG_CONSOLE_FLAG.to_string(), "".to_string());
                 kv.insert(
@@ -175,6 +175,52 @@ pub struct Runtime {
     /// If fd passthrough io is enabled, the runtime will attempt to use the specified port instead of the default port.
     #[serde(default = "default_passfd_listener_port")]
     pub passfd_listener_port: u32,
+
+    /// Base directory for sandbox state and ephemeral files, the default value
+    /// is "/run/kata".
+    ///
+    /// All runtime classes share the default state root, so a noisy class can
+    /// fill the tmpfs backing it and break sandboxes of other classes. Giving
+    /// each runtime class its own `state_root` isolates their state and
+    /// ephemeral files, and lets the backing filesystems be sized separately.
+    #[serde(default)]
+    pub state_root: String,
+
+    /// Warn when the data under `state_root` exceeds this size, in MiB.
+    ///
+    /// The runtime checks the state root usage when loading the configuration
+    /// and logs a warning once the limit is exceeded, giving operators an
+    /// early signal before the backing tmpfs fills up. A value of 0 disables
+    /// the check.
+    #[serde(default)]
+    pub state_warn_limit_mb: u64,
+
+    /// If enabled, the runtime appends an audit record for every shim task
+    /// API call (create, exec, kill, update, ...) under `audit_log_path`.
+    ///
+    /// Records are JSON lines carrying a timestamp, the API name, the
+    /// sandbox and container identifiers and the peer credentials of the
+    /// caller. Each runtime class has its own configuration file, so audit
+    /// settings can differ per class.
+    #[serde(default)]
+    pub enable_audit_log: bool,
+
+    /// Directory holding the audit logs; each sandbox appends to its own
+    /// `<sandbox-id>.log` file inside it. The default value is
+    /// "/var/log/kata/audit".
+    #[serde(default)]
+    pub audit_log_path: String,
+
+    /// Rotate a sandbox audit log once it exceeds this size, in MiB.
+    /// Rotated files get a numeric suffix and the oldest one is deleted.
+    /// A value of 0 uses the default of 16 MiB.
+    #[serde(default)]
+    pub audit_log_max_size_mb: u64,
+
+    /// How many rotated audit log files to keep besides the active one.
+    /// A value of 0 uses the default of 3.
+    #[serde(default)]
+    pub audit_log_max_files: u32,
 }
 
 fn default_passfd_listener_port() -> u32 {
@@ -188,6 +234,16 @@ impl ConfigOps for Runtime {
             conf.runtime.internetworking_model = default::DEFAULT_INTERNETWORKING_MODEL.to_owned();
         }
 
+        if conf.runtime.audit_log_path.is_empty() {
+            conf.runtime.audit_log_path = default::DEFAULT_AUDIT_LOG_PATH.to_owned();
+        }
+        if conf.runtime.audit_log_max_size_mb == 0 {
+            conf.runtime.audit_log_max_size_mb = default::DEFAULT_AUDIT_LOG_MAX_SIZE_MB;
+        }
+        if conf.runtime.audit_log_max_files == 0 {
+            conf.runtime.audit_log_max_files = default::DEFAULT_AUDIT_LOG_MAX_FILES;
+        }
+
         for bind in conf.runtime.sandbox_bind_mounts.iter_mut() {
             // Split the bind mount, canonicalize the path and then append rw mode to it.
             let (real_path, mode) = split_bind_mounts(bind);
@@ -225,6 +281,22 @@ impl ConfigOps for Runtime {
             ));
         }
 
+        let state_root = &conf.runtime.state_root;
+        if !state_root.is_empty() && !state_root.starts_with('/') {
+            return Err(eother!(
+                "Invalid state_root `{}` in configuration file, it must be an absolute path",
+                state_root
+            ));
+        }
+
+        let audit_log_path = &conf.runtime.audit_log_path;
+        if !audit_log_path.is_empty() && !audit_log_path.starts_with('/') {
+            return Err(eother!(
+                "Invalid audit_log_path `{}` in configuration file, it must be an absolute path",
+                audit_log_path
+            ));
+        }
+
         for shared_mount in &conf.runtime.shared_mounts {
             shared_mount.validate()?;
         }
@@ -318,6 +390,14 @@ vfio_mode = "vfio,guest-kernel"
 [runtime]
 enable_debug = true
 vfio_mode = "guest_kernel"
+"#;
+        let config: TomlConfig = TomlConfig::load(content).unwrap();
+        config.validate().unwrap_err();
+
+        let content = r#"
+[runtime]
+enable_debug = true
+state_root = "relative/path"
 "#;
         let config: TomlConfig = TomlConfig::load(content).unwrap();
         config.validate().unwrap_err();
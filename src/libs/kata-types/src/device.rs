@@ -15,7 +15,7 @@ pub const DRIVER_BLK_MMIO_TYPE: &str = "mmioblk";
 pub const DRIVER_SCSI_TYPE: &str = "scsi";
 /// DRIVER_NVDIMM_TYPE is the device driver for nvdimm
 pub const DRIVER_NVDIMM_TYPE: &str = "nvdimm";
-/// DRIVER_VFIO_PCI_GK_TYPE is the device driver for vfio-pci 
+/// DRIVER_VFIO_PCI_GK_TYPE is the device driver for vfio-pci
 /// while the device will be bound to a guest kernel driver
 pub const DRIVER_VFIO_PCI_GK_TYPE: &str = "vfio-pci-gk";
 /// DRIVER_VFIO_PCI_TYPE is the device driver for vfio-pci
@@ -27,6 +27,11 @@ pub const DRIVER_VFIO_AP_TYPE: &str = "vfio-ap";
 /// DRIVER_VFIO_AP_COLD_TYPE is the device driver for vfio-ap coldplug.
 pub const DRIVER_VFIO_AP_COLD_TYPE: &str = "vfio-ap-cold";
 
+/// DRIVER_VDPA_NET_TYPE is the device driver for vhost-vdpa network devices
+pub const DRIVER_VDPA_NET_TYPE: &str = "vdpa-net";
+/// DRIVER_VDPA_BLK_TYPE is the device driver for vhost-vdpa block devices
+pub const DRIVER_VDPA_BLK_TYPE: &str = "vdpa-blk";
+
 /// DRIVER_9P_TYPE is the driver for 9pfs volume.
 pub const DRIVER_9P_TYPE: &str = "9p";
 /// DRIVER_EPHEMERAL_TYPE is the driver for ephemeral volume.
@@ -37,6 +42,12 @@ pub const DRIVER_LOCAL_TYPE: &str = "local";
 pub const DRIVER_OVERLAYFS_TYPE: &str = "overlayfs";
 /// DRIVER_VIRTIOFS_TYPE is the driver for virtio-fs volume.
 pub const DRIVER_VIRTIOFS_TYPE: &str = "virtio-fs";
+/// DRIVER_COMPOSEFS_TYPE is the driver for composefs image volume.
+pub const DRIVER_COMPOSEFS_TYPE: &str = "composefs";
+/// DRIVER_SQUASHFS_TYPE is the driver for squashfs image layer volume.
+pub const DRIVER_SQUASHFS_TYPE: &str = "squashfs";
+/// DRIVER_FUSE_TYPE is the driver for volumes served by an in-guest fuse daemon.
+pub const DRIVER_FUSE_TYPE: &str = "fuse";
 /// DRIVER_VIRTIOFS_TYPE is the driver for Bind watch volume.
 pub const DRIVER_WATCHABLE_BIND_TYPE: &str = "watchable-bind";
 
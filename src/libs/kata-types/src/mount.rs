@@ -31,6 +31,9 @@ pub const KATA_MOUNT_INFO_FILE_NAME: &str = "mountInfo.json";
 /// Specify `fsgid` for a volume or mount, `fsgid=1`.
 pub const KATA_MOUNT_OPTION_FS_GID: &str = "fsgid";
 
+/// Specify an ephemeral storage limit in bytes for a volume or mount, `sizeLimit=1048576`.
+pub const KATA_MOUNT_OPTION_SIZE_LIMIT: &str = "sizeLimit";
+
 /// KATA_DIRECT_VOLUME_ROOT_PATH is the root path used for concatenating with the direct-volume mount info file path
 pub const KATA_DIRECT_VOLUME_ROOT_PATH: &str = "/run/kata-containers/shared/direct-volumes";
 
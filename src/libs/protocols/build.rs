@@ -62,6 +62,56 @@ fn replace_text_in_file(file_name: &str, from: &str, to: &str) -> Result<(), std
     Ok(())
 }
 
+// The sync side of ttrpc has no streaming support, but the sync codegen
+// still emits server glue for streaming RPCs and that glue does not
+// compile. Strip the method handler, the default trait implementation
+// and the service registration; streaming methods are async-only.
+fn strip_sync_streaming_method(
+    file_name: &str,
+    method: &str,
+    method_snake: &str,
+) -> Result<(), std::io::Error> {
+    let mut src = File::open(file_name)?;
+    let mut contents = String::new();
+    src.read_to_string(&mut contents)?;
+    drop(src);
+
+    let handler = format!("{}Method", method);
+    let trait_fn = format!("fn {}(", method_snake);
+    let route = format!("\"/grpc.AgentService/{}\"", method);
+
+    let mut lines = contents.lines();
+    let mut kept: Vec<&str> = Vec::new();
+    while let Some(line) = lines.next() {
+        if line.contains(&handler) && line.ends_with('{') {
+            // The struct and MethodHandler impl blocks of the handler.
+            for skipped in lines.by_ref() {
+                if skipped == "}" {
+                    break;
+                }
+            }
+        } else if line.trim_start().starts_with(&trait_fn) {
+            // The default trait implementation.
+            for skipped in lines.by_ref() {
+                if skipped == "    }" {
+                    break;
+                }
+            }
+        } else if line.contains(&route) {
+            // The two-line methods.insert() registration.
+            lines.next();
+        } else {
+            kept.push(line);
+        }
+    }
+
+    let mut dst = File::create(file_name)?;
+    dst.write_all(kept.join("\n").as_bytes())?;
+    dst.write_all(b"\n")?;
+
+    Ok(())
+}
+
 fn use_serde(protos: &[&str], out_dir: &Path) -> Result<(), std::io::Error> {
     protos
         .iter()
@@ -203,6 +253,7 @@ fn real_main() -> Result<(), std::io::Error> {
             &[
                 "protos/agent.proto",
                 "protos/health.proto",
+                "protos/attestation_agent.proto",
                 "protos/confidential_data_hub.proto",
                 "protos/remote.proto",
             ],
@@ -211,6 +262,10 @@ fn real_main() -> Result<(), std::io::Error> {
 
         fs::rename("src/agent_ttrpc.rs", "src/agent_ttrpc_async.rs")?;
         fs::rename("src/health_ttrpc.rs", "src/health_ttrpc_async.rs")?;
+        fs::rename(
+            "src/attestation_agent_ttrpc.rs",
+            "src/attestation_agent_ttrpc_async.rs",
+        )?;
         fs::rename(
             "src/confidential_data_hub_ttrpc.rs",
             "src/confidential_data_hub_ttrpc_async.rs",
@@ -223,12 +278,15 @@ fn real_main() -> Result<(), std::io::Error> {
         &[
             "protos/agent.proto",
             "protos/health.proto",
+            "protos/attestation_agent.proto",
             "protos/confidential_data_hub.proto",
             "protos/remote.proto",
         ],
         false,
     )?;
 
+    strip_sync_streaming_method("src/agent_ttrpc.rs", "WaitProcesses", "wait_processes")?;
+
     // There is a message named 'Box' in oci.proto
     // so there is a struct named 'Box', we should replace Box<Self> to ::std::boxed::Box<Self>
     // to avoid the conflict.
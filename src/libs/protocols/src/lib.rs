@@ -17,14 +17,14 @@ pub mod health_ttrpc;
 #[cfg(feature = "async")]
 pub mod health_ttrpc_async;
 pub mod oci;
-#[cfg(feature = "with-serde")]
-mod serde_config;
-pub mod trans;
-pub mod types;
 pub mod remote;
 pub mod remote_ttrpc;
 #[cfg(feature = "async")]
 pub mod remote_ttrpc_async;
+#[cfg(feature = "with-serde")]
+mod serde_config;
+pub mod trans;
+pub mod types;
 
 #[cfg(feature = "with-serde")]
 pub use serde_config::{
@@ -32,6 +32,12 @@ pub use serde_config::{
     serialize_message_field,
 };
 
+pub mod attestation_agent;
+pub mod attestation_agent_ttrpc;
+
+#[cfg(feature = "async")]
+pub mod attestation_agent_ttrpc_async;
+
 pub mod confidential_data_hub;
 pub mod confidential_data_hub_ttrpc;
 
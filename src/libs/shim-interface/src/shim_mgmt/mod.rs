@@ -12,6 +12,8 @@ pub const DIRECT_VOLUME_PATH_KEY: &str = "path";
 pub const DIRECT_VOLUME_STATS_URL: &str = "/direct-volume/stats";
 /// URL for resizing direct volume
 pub const DIRECT_VOLUME_RESIZE_URL: &str = "/direct-volume/resize";
+/// URL for removing (hot-unplugging) direct volume
+pub const DIRECT_VOLUME_REMOVE_URL: &str = "/direct-volume/remove";
 /// URL for querying agent's socket
 pub const AGENT_URL: &str = "/agent-url";
 /// URL for operation on guest iptable (ipv4)
@@ -20,5 +22,10 @@ pub const IP_TABLE_URL: &str = "/iptables";
 pub const IP6_TABLE_URL: &str = "/ip6tables";
 /// URL for querying metrics inside shim
 pub const METRICS_URL: &str = "/metrics";
+/// URL for querying the estimated host memory footprint of the sandbox
+pub const MEM_FOOTPRINT_URL: &str = "/mem-footprint";
+/// URL for querying the host CPUs and devices the sandbox consumes, in
+/// the kubelet PodResources format
+pub const POD_RESOURCES_URL: &str = "/pod-resources";
 
 pub const ERR_NO_SHIM_SERVER: &str = "Failed to create shim management server";
@@ -6,6 +6,7 @@
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use tokio::sync::mpsc;
 use tracing::instrument;
 use ttrpc::context as ttrpc_ctx;
 
@@ -16,6 +17,9 @@ use crate::{kata::KataAgent, Agent, AgentManager, HealthService};
 /// millisecond to nanosecond
 const MILLISECOND_TO_NANOSECOND: i64 = 1_000_000;
 
+/// exit statuses buffered while the caller is not reading them
+const EXIT_STATUS_CHANNEL_SIZE: usize = 128;
+
 /// new ttrpc context with timeout
 fn new_ttrpc_ctx(timeout: i64) -> ttrpc_ctx::Context {
     ttrpc_ctx::with_timeout(timeout)
@@ -35,11 +39,15 @@ impl AgentManager for KataAgent {
         self.start_log_forwarder()
             .await
             .context("connect log forwarder")?;
+        self.start_shutdown_reason_monitor()
+            .await
+            .context("connect shutdown reason monitor")?;
         Ok(())
     }
 
     async fn stop(&self) {
         self.stop_log_forwarder().await;
+        self.stop_shutdown_reason_monitor().await;
     }
 
     async fn agent_sock(&self) -> Result<String> {
@@ -68,11 +76,15 @@ macro_rules! impl_health_service {
 
 impl_health_service!(
     check | crate::CheckRequest | crate::HealthCheckResponse,
-    version | crate::CheckRequest | crate::VersionCheckResponse
+    version | crate::CheckRequest | crate::VersionCheckResponse,
+    health_detail | crate::HealthDetailRequest | crate::HealthDetailResponse
 );
 
+// Unary methods are listed as `name | request | response | timeout`;
+// items after the `;` are methods whose shape the list cannot express,
+// e.g. streaming calls, and are passed through verbatim.
 macro_rules! impl_agent {
-    ($($name: tt | $req: ty | $resp: ty | $new_timeout: expr),*) => {
+    ($($name: tt | $req: ty | $resp: ty | $new_timeout: expr),* ; $($extra_fn: item)*) => {
         #[async_trait]
         impl Agent for KataAgent {
             #[instrument(skip(req))]
@@ -88,12 +100,15 @@ macro_rules! impl_agent {
                 let resp = client.$name(new_ttrpc_ctx(timeout * MILLISECOND_TO_NANOSECOND), &r).await?;
                 Ok(resp.into())
             })*
+
+            $($extra_fn)*
         }
     };
 }
 
 impl_agent!(
     create_container | crate::CreateContainerRequest | crate::Empty | None,
+    add_spec_fragment | crate::AddSpecFragmentRequest | crate::Empty | None,
     start_container | crate::ContainerID | crate::Empty | None,
     remove_container | crate::RemoveContainerRequest | crate::Empty | None,
     exec_process | crate::ExecProcessRequest | crate::Empty | None,
@@ -110,7 +125,11 @@ impl_agent!(
     tty_win_resize | crate::TtyWinResizeRequest | crate::Empty | None,
     update_interface | crate::UpdateInterfaceRequest | crate::Interface | None,
     update_routes | crate::UpdateRoutesRequest | crate::Routes | None,
+    add_network | crate::AddNetworkRequest | crate::Interface | None,
+    remove_network | crate::RemoveNetworkRequest | crate::Empty | None,
+    update_dns | crate::UpdateDNSRequest | crate::Empty | None,
     add_arp_neighbors | crate::AddArpNeighborRequest | crate::Empty | None,
+    port_forward | crate::PortForwardRequest | crate::Empty | None,
     list_interfaces | crate::Empty | crate::Interfaces | None,
     list_routes | crate::Empty | crate::Routes | None,
     create_sandbox | crate::CreateSandboxRequest | crate::Empty | None,
@@ -122,6 +141,53 @@ impl_agent!(
     get_volume_stats | crate::VolumeStatsRequest | crate::VolumeStatsResponse | None,
     resize_volume | crate::ResizeVolumeRequest | crate::Empty | None,
     online_cpu_mem | crate::OnlineCPUMemRequest | crate::Empty | None,
+    reclaim_guest_memory | crate::ReclaimGuestMemoryRequest | crate::Empty | None,
+    compact_memory | crate::CompactMemoryRequest | crate::Empty | None,
+    quiesce_sandbox | crate::QuiesceSandboxRequest | crate::Empty | None,
+    thaw_sandbox | crate::ThawSandboxRequest | crate::Empty | None,
+    reload_config | crate::ReloadConfigRequest | crate::Empty | None,
     get_metrics | crate::Empty | crate::MetricsResponse | None,
-    get_guest_details | crate::GetGuestDetailsRequest | crate::GuestDetailsResponse | None
+    get_guest_details | crate::GetGuestDetailsRequest | crate::GuestDetailsResponse | None,
+    negotiate_api | crate::NegotiateApiRequest | crate::NegotiateApiResponse | None;
+
+    async fn wait_processes(
+        &self,
+        req: crate::WaitProcessesRequest,
+    ) -> Result<mpsc::Receiver<crate::ProcessExitStatus>> {
+        let r = req.into();
+        let (client, _, _) = self.get_agent_client().await.context("get client")?;
+
+        // The stream stays open for the sandbox lifetime, so no timeout.
+        let mut stream = client
+            .wait_processes(new_ttrpc_ctx(0), &r)
+            .await
+            .context("wait processes")?;
+
+        let (tx, rx) = mpsc::channel(EXIT_STATUS_CHANNEL_SIZE);
+        tokio::spawn(async move {
+            loop {
+                match stream.recv().await {
+                    Ok(Some(status)) => {
+                        if tx.send(status.into()).await.is_err() {
+                            // The receiver side was dropped.
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!(sl!(), "wait_processes stream failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    async fn negotiated_api(&self) -> Result<crate::NegotiatedApi> {
+        // The inherent method on KataAgent performs (and caches) the
+        // actual handshake.
+        KataAgent::negotiated_api(self).await
+    }
 );
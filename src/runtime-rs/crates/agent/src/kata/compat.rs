@@ -0,0 +1,162 @@
+// Copyright (c) 2026 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Compatibility layer around the API v2 version handshake.
+//!
+//! A v2 shim probes the agent once with `NegotiateApi`; agents that
+//! predate the handshake answer with NOT_FOUND, which is folded into
+//! "API v1, no optional capabilities" so the shim falls back to the
+//! behavior it always had. The reverse direction needs no shim at all:
+//! every v1 RPC keeps its meaning under v2, the handshake only adds
+//! information, so old shims keep working against new agents during
+//! upgrades.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{kata::KataAgent, Agent, NegotiateApiRequest, NegotiateApiResponse};
+
+/// Highest API major version this shim speaks.
+pub const CLIENT_API_MAJOR: u32 = 2;
+
+/// Capability names as they appear in the handshake. Every call site of
+/// an RPC that old agents lack gates on the matching constant, so the
+/// names are shared rather than spelled out inline.
+pub const CAP_ADD_NETWORK: &str = "add-network";
+pub const CAP_ADD_SPEC_FRAGMENT: &str = "add-spec-fragment";
+pub const CAP_MEM_AGENT: &str = "mem-agent";
+/// Covers both balloon RPCs, `reclaim_guest_memory` and `compact_memory`.
+pub const CAP_RECLAIM_GUEST_MEMORY: &str = "reclaim-guest-memory";
+pub const CAP_WAIT_PROCESSES: &str = "wait-processes";
+
+/// Optional capabilities this shim knows how to use; the handshake
+/// answers with the subset the agent actually supports.
+pub const CLIENT_CAPABILITIES: &[&str] = &[
+    CAP_ADD_NETWORK,
+    CAP_ADD_SPEC_FRAGMENT,
+    CAP_MEM_AGENT,
+    CAP_RECLAIM_GUEST_MEMORY,
+    CAP_WAIT_PROCESSES,
+];
+
+/// Outcome of the version handshake with the running agent.
+#[derive(Clone, Debug)]
+pub struct NegotiatedApi {
+    /// API major version both sides speak.
+    pub major: u32,
+    /// Version of the agent build; empty for a v1 agent.
+    pub agent_version: String,
+    capabilities: HashSet<String>,
+}
+
+impl NegotiatedApi {
+    /// An agent that does not implement the handshake at all.
+    pub fn v1() -> Self {
+        NegotiatedApi {
+            major: 1,
+            agent_version: String::new(),
+            capabilities: HashSet::new(),
+        }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+impl From<NegotiateApiResponse> for NegotiatedApi {
+    fn from(resp: NegotiateApiResponse) -> Self {
+        NegotiatedApi {
+            major: resp.major,
+            agent_version: resp.agent_version,
+            capabilities: resp.capabilities.into_iter().collect(),
+        }
+    }
+}
+
+// ttrpc reports a method the server never registered as a NOT_FOUND rpc
+// status, which is how a pre-handshake agent shows itself.
+fn is_unimplemented(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<ttrpc::Error>(),
+        Some(ttrpc::Error::RpcStatus(status)) if status.code() == ttrpc::Code::NOT_FOUND
+    )
+}
+
+impl KataAgent {
+    /// Version and capabilities negotiated with the running agent,
+    /// cached after the first handshake. Transport errors are returned
+    /// as-is so a flaky connection is not mistaken for an old agent.
+    pub async fn negotiated_api(&self) -> Result<NegotiatedApi> {
+        {
+            let inner = self.inner.read().await;
+            if let Some(api) = &inner.negotiated_api {
+                return Ok(api.clone());
+            }
+        }
+
+        let req = NegotiateApiRequest {
+            major: CLIENT_API_MAJOR,
+            capabilities: CLIENT_CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        };
+        let api = match self.negotiate_api(req).await {
+            Ok(resp) => resp.into(),
+            Err(err) if is_unimplemented(&err) => NegotiatedApi::v1(),
+            Err(err) => return Err(err),
+        };
+
+        let mut inner = self.inner.write().await;
+        Ok(inner.negotiated_api.get_or_insert(api).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The v1 fallback reports no optional capabilities, so every
+    // capability-gated call site takes its legacy path against an agent
+    // that predates the handshake.
+    #[test]
+    fn test_v1_fallback_supports_nothing() {
+        let api = NegotiatedApi::v1();
+        assert_eq!(api.major, 1);
+        assert!(api.agent_version.is_empty());
+        for cap in CLIENT_CAPABILITIES {
+            assert!(!api.supports(cap));
+        }
+    }
+
+    #[test]
+    fn test_negotiated_capabilities() {
+        let api: NegotiatedApi = NegotiateApiResponse {
+            major: 2,
+            agent_version: "3.18.0".to_string(),
+            capabilities: vec![CAP_ADD_SPEC_FRAGMENT.to_string()],
+        }
+        .into();
+
+        assert!(api.supports(CAP_ADD_SPEC_FRAGMENT));
+        assert!(!api.supports(CAP_RECLAIM_GUEST_MEMORY));
+    }
+
+    #[test]
+    fn test_unimplemented_detection() {
+        let not_found = anyhow::Error::from(ttrpc::Error::RpcStatus(ttrpc::get_status(
+            ttrpc::Code::NOT_FOUND,
+            "/grpc.AgentService/NegotiateApi is not supported",
+        )));
+        assert!(is_unimplemented(&not_found));
+
+        let internal = anyhow::Error::from(ttrpc::Error::RpcStatus(ttrpc::get_status(
+            ttrpc::Code::INTERNAL,
+            "boom",
+        )));
+        assert!(!is_unimplemented(&internal));
+
+        assert!(!is_unimplemented(&anyhow::anyhow!("connection reset")));
+    }
+}
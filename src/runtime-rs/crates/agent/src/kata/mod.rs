@@ -5,8 +5,11 @@
 //
 
 mod agent;
+pub mod compat;
 mod trans;
 
+pub use compat::{NegotiatedApi, CLIENT_API_MAJOR, CLIENT_CAPABILITIES};
+
 use std::{
     os::unix::io::{IntoRawFd, RawFd},
     sync::Arc,
@@ -18,7 +21,7 @@ use protocols::{agent_ttrpc_async as agent_ttrpc, health_ttrpc_async as health_t
 use tokio::sync::RwLock;
 use ttrpc::asynchronous::Client;
 
-use crate::{log_forwarder::LogForwarder, sock};
+use crate::{log_forwarder::LogForwarder, shutdown_reason::ShutdownReasonMonitor, sock};
 
 // https://github.com/firecracker-microvm/firecracker/blob/master/docs/vsock.md
 #[derive(Debug, Default)]
@@ -42,6 +45,12 @@ pub(crate) struct KataAgentInner {
 
     /// Log forwarder
     log_forwarder: LogForwarder,
+
+    /// Shutdown reason monitor
+    shutdown_reason_monitor: ShutdownReasonMonitor,
+
+    /// Outcome of the API version handshake, cached after the first call
+    pub(crate) negotiated_api: Option<compat::NegotiatedApi>,
 }
 
 impl std::fmt::Debug for KataAgentInner {
@@ -70,6 +79,8 @@ impl KataAgent {
                 socket_address: "".to_string(),
                 config,
                 log_forwarder: LogForwarder::new(),
+                shutdown_reason_monitor: ShutdownReasonMonitor::new(),
+                negotiated_api: None,
             })),
         }
     }
@@ -123,6 +134,9 @@ impl KataAgent {
         let c = Client::new(fd);
         inner.client = Some(c);
         inner.client_fd = fd;
+        // A reconnect may reach a different agent build, e.g. after a
+        // guest upgrade, so renegotiate the API on the next call.
+        inner.negotiated_api = None;
         Ok(())
     }
 
@@ -147,6 +161,30 @@ impl KataAgent {
         inner.log_forwarder.stop();
     }
 
+    pub(crate) async fn start_shutdown_reason_monitor(&self) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        let port = inner.config.shutdown_reason_port;
+        if port == 0 {
+            return Ok(());
+        }
+        let config = sock::ConnectConfig::new(
+            inner.config.dial_timeout_ms as u64,
+            inner.config.reconnect_timeout_ms as u64,
+        );
+        let address = inner.socket_address.clone();
+        inner
+            .shutdown_reason_monitor
+            .start(&address, port, config)
+            .await
+            .context("start shutdown reason monitor")?;
+        Ok(())
+    }
+
+    pub(crate) async fn stop_shutdown_reason_monitor(&self) {
+        let mut inner = self.inner.write().await;
+        inner.shutdown_reason_monitor.stop();
+    }
+
     pub(crate) async fn agent_sock(&self) -> Result<String> {
         let inner = self.inner.read().await;
         Ok(format!(
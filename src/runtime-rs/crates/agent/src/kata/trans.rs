@@ -13,19 +13,24 @@ use protocols::{
 
 use crate::{
     types::{
-        ARPNeighbor, ARPNeighbors, AddArpNeighborRequest, AgentDetails, BlkioStats,
-        BlkioStatsEntry, CgroupStats, CheckRequest, CloseStdinRequest, ContainerID,
-        CopyFileRequest, CpuStats, CpuUsage, CreateContainerRequest, CreateSandboxRequest, Device,
-        Empty, ExecProcessRequest, FSGroup, FSGroupChangePolicy, GetIPTablesRequest,
-        GetIPTablesResponse, GuestDetailsResponse, HealthCheckResponse, HugetlbStats, IPAddress,
-        IPFamily, Interface, Interfaces, KernelModule, MemHotplugByProbeRequest, MemoryData,
-        MemoryStats, MetricsResponse, NetworkStats, OnlineCPUMemRequest, PidsStats,
-        ReadStreamRequest, ReadStreamResponse, RemoveContainerRequest, ReseedRandomDevRequest,
-        ResizeVolumeRequest, Route, Routes, SetGuestDateTimeRequest, SetIPTablesRequest,
-        SetIPTablesResponse, SharedMount, SignalProcessRequest, StatsContainerResponse, Storage,
-        StringUser, ThrottlingData, TtyWinResizeRequest, UpdateContainerRequest,
-        UpdateInterfaceRequest, UpdateRoutesRequest, VersionCheckResponse, VolumeStatsRequest,
-        VolumeStatsResponse, WaitProcessRequest, WriteStreamRequest,
+        ARPNeighbor, ARPNeighbors, AddArpNeighborRequest, AddNetworkRequest,
+        AddSpecFragmentRequest, AgentDetails, BlkioStats, BlkioStatsEntry, CgroupStats,
+        CheckRequest, CloseStdinRequest, CompactMemoryRequest, ContainerID, CopyFileRequest,
+        CpuStats, CpuUsage, CreateContainerRequest, CreateSandboxRequest, Device, Empty,
+        ExecProcessRequest, FSGroup, FSGroupChangePolicy, FilesystemUsage, GetIPTablesRequest,
+        GetIPTablesResponse, GuestDetailsResponse, HealthCheckResponse, HealthDetailRequest,
+        HealthDetailResponse, HugetlbStats, IPAddress, IPFamily, Interface, Interfaces,
+        KernelModule, MemHotplugByProbeRequest, MemoryData, MemoryStats, MetricsResponse,
+        NegotiateApiRequest, NegotiateApiResponse, NetworkStats, OnlineCPUMemRequest, PidsStats,
+        PortForwardRequest, ProcessExitStatus, QuiesceSandboxRequest, ReadStreamRequest,
+        ReadStreamResponse, ReclaimGuestMemoryRequest, ReloadConfigRequest, RemoveContainerRequest,
+        RemoveNetworkRequest, ReseedRandomDevRequest, ResizeVolumeRequest, Route, Routes,
+        SetGuestDateTimeRequest, SetIPTablesRequest, SetIPTablesResponse, SharedMount,
+        SignalProcessRequest, StatsContainerResponse, Storage, StringUser, SubsystemStatus,
+        ThawSandboxRequest, ThrottlingData, TtyWinResizeRequest, UpdateContainerRequest,
+        UpdateDNSRequest, UpdateInterfaceRequest, UpdateRoutesRequest, VersionCheckResponse,
+        VolumeStatsRequest, VolumeStatsResponse, WaitProcessRequest, WaitProcessesRequest,
+        WriteStreamRequest,
     },
     GetGuestDetailsRequest, OomEventResponse, WaitProcessResponse, WriteStreamResponse,
 };
@@ -277,6 +282,18 @@ impl From<CreateContainerRequest> for agent::CreateContainerRequest {
             stdin_port: from.stdin_port.unwrap_or_default(),
             stdout_port: from.stdout_port.unwrap_or_default(),
             stderr_port: from.stderr_port.unwrap_or_default(),
+            spec_fragment_ids: from.spec_fragment_ids,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<AddSpecFragmentRequest> for agent::AddSpecFragmentRequest {
+    fn from(from: AddSpecFragmentRequest) -> Self {
+        Self {
+            id: from.id,
+            env: from.env,
+            mounts: trans_vec(from.mounts),
             ..Default::default()
         }
     }
@@ -554,11 +571,24 @@ impl From<agent::NetworkStats> for NetworkStats {
 }
 
 // translate ttrpc::agent response to interface::agent response
+impl From<agent::FilesystemUsage> for FilesystemUsage {
+    fn from(src: agent::FilesystemUsage) -> Self {
+        Self {
+            mount_point: src.mount_point,
+            used_bytes: src.used_bytes,
+            total_bytes: src.total_bytes,
+            used_inodes: src.used_inodes,
+            total_inodes: src.total_inodes,
+        }
+    }
+}
+
 impl From<agent::StatsContainerResponse> for StatsContainerResponse {
     fn from(src: agent::StatsContainerResponse) -> Self {
         Self {
             cgroup_stats: into_option(src.cgroup_stats),
             network_stats: trans_vec(src.network_stats),
+            filesystem_stats: trans_vec(src.filesystem_stats),
         }
     }
 }
@@ -628,6 +658,26 @@ impl From<UpdateRoutesRequest> for agent::UpdateRoutesRequest {
     }
 }
 
+impl From<AddNetworkRequest> for agent::AddNetworkRequest {
+    fn from(from: AddNetworkRequest) -> Self {
+        Self {
+            interface: from_option(from.interface),
+            routes: trans_vec(from.routes),
+            sysctls: from.sysctls,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<RemoveNetworkRequest> for agent::RemoveNetworkRequest {
+    fn from(from: RemoveNetworkRequest) -> Self {
+        Self {
+            name: from.name,
+            ..Default::default()
+        }
+    }
+}
+
 impl From<Empty> for agent::ListRoutesRequest {
     fn from(_: Empty) -> Self {
         Self {
@@ -701,6 +751,64 @@ impl From<OnlineCPUMemRequest> for agent::OnlineCPUMemRequest {
     }
 }
 
+impl From<ReclaimGuestMemoryRequest> for agent::ReclaimGuestMemoryRequest {
+    fn from(from: ReclaimGuestMemoryRequest) -> Self {
+        Self {
+            drop_caches: from.drop_caches,
+            compact_memory: from.compact_memory,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<CompactMemoryRequest> for agent::CompactMemoryRequest {
+    fn from(from: CompactMemoryRequest) -> Self {
+        Self {
+            numa_nodes: from.numa_nodes,
+            tune_proactiveness: from.proactiveness.is_some(),
+            proactiveness: from.proactiveness.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<QuiesceSandboxRequest> for agent::QuiesceSandboxRequest {
+    fn from(from: QuiesceSandboxRequest) -> Self {
+        Self {
+            drop_caches: from.drop_caches,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ThawSandboxRequest> for agent::ThawSandboxRequest {
+    fn from(_: ThawSandboxRequest) -> Self {
+        Self::default()
+    }
+}
+
+impl From<ReloadConfigRequest> for agent::ReloadConfigRequest {
+    fn from(_: ReloadConfigRequest) -> Self {
+        Self::default()
+    }
+}
+
+impl From<WaitProcessesRequest> for agent::WaitProcessesRequest {
+    fn from(_: WaitProcessesRequest) -> Self {
+        Self::default()
+    }
+}
+
+impl From<agent::ProcessExitStatus> for ProcessExitStatus {
+    fn from(from: agent::ProcessExitStatus) -> Self {
+        Self {
+            container_id: from.container_id,
+            exec_id: from.exec_id,
+            status: from.status,
+        }
+    }
+}
+
 impl From<ReseedRandomDevRequest> for agent::ReseedRandomDevRequest {
     fn from(from: ReseedRandomDevRequest) -> Self {
         Self {
@@ -748,6 +856,8 @@ impl From<agent::AgentDetails> for AgentDetails {
             storage_handlers: trans_vec(src.storage_handlers),
             supports_seccomp: src.supports_seccomp,
             extra_features: trans_vec(src.extra_features),
+            supported_compression: trans_vec(src.supported_compression),
+            kernel_version: src.kernel_version,
         }
     }
 }
@@ -762,6 +872,26 @@ impl From<agent::GuestDetailsResponse> for GuestDetailsResponse {
     }
 }
 
+impl From<NegotiateApiRequest> for agent::NegotiateApiRequest {
+    fn from(from: NegotiateApiRequest) -> Self {
+        Self {
+            major: from.major,
+            capabilities: trans_vec(from.capabilities),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<agent::NegotiateApiResponse> for NegotiateApiResponse {
+    fn from(src: agent::NegotiateApiResponse) -> Self {
+        Self {
+            major: src.major,
+            agent_version: src.agent_version,
+            capabilities: trans_vec(src.capabilities),
+        }
+    }
+}
+
 impl From<CopyFileRequest> for agent::CopyFileRequest {
     fn from(from: CopyFileRequest) -> Self {
         Self {
@@ -773,6 +903,7 @@ impl From<CopyFileRequest> for agent::CopyFileRequest {
             gid: from.gid,
             offset: from.offset,
             data: from.data,
+            compression: from.compression,
             ..Default::default()
         }
     }
@@ -782,6 +913,7 @@ impl From<agent::WaitProcessResponse> for WaitProcessResponse {
     fn from(from: agent::WaitProcessResponse) -> Self {
         Self {
             status: from.status,
+            termination_message: from.termination_message,
         }
     }
 }
@@ -828,6 +960,33 @@ impl From<health::VersionCheckResponse> for VersionCheckResponse {
     }
 }
 
+impl From<HealthDetailRequest> for health::HealthDetailRequest {
+    fn from(_from: HealthDetailRequest) -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+}
+
+impl From<health::SubsystemStatus> for SubsystemStatus {
+    fn from(from: health::SubsystemStatus) -> Self {
+        Self {
+            name: from.name,
+            status: from.status.value() as u32,
+            last_error: from.last_error,
+        }
+    }
+}
+
+impl From<health::HealthDetailResponse> for HealthDetailResponse {
+    fn from(from: health::HealthDetailResponse) -> Self {
+        Self {
+            status: from.status.value() as u32,
+            subsystems: trans_vec(from.subsystems),
+        }
+    }
+}
+
 impl From<agent::Metrics> for MetricsResponse {
     fn from(from: Metrics) -> Self {
         Self {
@@ -864,6 +1023,26 @@ impl From<csi::VolumeStatsResponse> for VolumeStatsResponse {
     }
 }
 
+impl From<UpdateDNSRequest> for agent::UpdateDNSRequest {
+    fn from(from: UpdateDNSRequest) -> Self {
+        Self {
+            dns: from.dns,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<PortForwardRequest> for agent::PortForwardRequest {
+    fn from(from: PortForwardRequest) -> Self {
+        Self {
+            container_id: from.container_id,
+            port: from.port,
+            vport: from.vport,
+            ..Default::default()
+        }
+    }
+}
+
 impl From<ResizeVolumeRequest> for agent::ResizeVolumeRequest {
     fn from(from: ResizeVolumeRequest) -> Self {
         Self {
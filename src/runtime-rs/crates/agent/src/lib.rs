@@ -10,21 +10,30 @@ extern crate slog;
 logging::logger_with_subsystem!(sl, "agent");
 
 pub mod kata;
+pub use kata::compat::{
+    NegotiatedApi, CAP_ADD_NETWORK, CAP_ADD_SPEC_FRAGMENT, CAP_MEM_AGENT, CAP_RECLAIM_GUEST_MEMORY,
+    CAP_WAIT_PROCESSES,
+};
 mod log_forwarder;
+mod shutdown_reason;
 mod sock;
 pub mod types;
 pub use types::{
-    ARPNeighbor, ARPNeighbors, AddArpNeighborRequest, BlkioStatsEntry, CheckRequest,
-    CloseStdinRequest, ContainerID, ContainerProcessID, CopyFileRequest, CreateContainerRequest,
-    CreateSandboxRequest, Empty, ExecProcessRequest, GetGuestDetailsRequest, GetIPTablesRequest,
-    GetIPTablesResponse, GuestDetailsResponse, HealthCheckResponse, IPAddress, IPFamily, Interface,
-    Interfaces, ListProcessesRequest, MemHotplugByProbeRequest, MetricsResponse,
-    OnlineCPUMemRequest, OomEventResponse, ReadStreamRequest, ReadStreamResponse,
-    RemoveContainerRequest, ReseedRandomDevRequest, ResizeVolumeRequest, Route, Routes,
-    SetGuestDateTimeRequest, SetIPTablesRequest, SetIPTablesResponse, SignalProcessRequest,
-    StatsContainerResponse, Storage, TtyWinResizeRequest, UpdateContainerRequest,
-    UpdateInterfaceRequest, UpdateRoutesRequest, VersionCheckResponse, VolumeStatsRequest,
-    VolumeStatsResponse, WaitProcessRequest, WaitProcessResponse, WriteStreamRequest,
+    ARPNeighbor, ARPNeighbors, AddArpNeighborRequest, AddNetworkRequest, AddSpecFragmentRequest,
+    BlkioStatsEntry, CheckRequest, CloseStdinRequest, CompactMemoryRequest, ContainerID,
+    ContainerProcessID, CopyFileRequest, CreateContainerRequest, CreateSandboxRequest, Empty,
+    ExecProcessRequest, GetGuestDetailsRequest, GetIPTablesRequest, GetIPTablesResponse,
+    GuestDetailsResponse, HealthCheckResponse, HealthDetailRequest, HealthDetailResponse,
+    IPAddress, IPFamily, Interface, Interfaces, ListProcessesRequest, MemHotplugByProbeRequest,
+    MetricsResponse, NegotiateApiRequest, NegotiateApiResponse, OnlineCPUMemRequest,
+    OomEventResponse, PortForwardRequest, ProcessExitStatus, QuiesceSandboxRequest,
+    ReadStreamRequest, ReadStreamResponse, ReclaimGuestMemoryRequest, ReloadConfigRequest,
+    RemoveContainerRequest, RemoveNetworkRequest, ReseedRandomDevRequest, ResizeVolumeRequest,
+    Route, Routes, SetGuestDateTimeRequest, SetIPTablesRequest, SetIPTablesResponse,
+    SignalProcessRequest, StatsContainerResponse, Storage, SubsystemStatus, ThawSandboxRequest,
+    TtyWinResizeRequest, UpdateContainerRequest, UpdateDNSRequest, UpdateInterfaceRequest,
+    UpdateRoutesRequest, VersionCheckResponse, VolumeStatsRequest, VolumeStatsResponse,
+    WaitProcessRequest, WaitProcessResponse, WaitProcessesRequest, WriteStreamRequest,
     WriteStreamResponse,
 };
 
@@ -48,6 +57,7 @@ pub trait AgentManager: Send + Sync {
 pub trait HealthService: Send + Sync {
     async fn check(&self, req: CheckRequest) -> Result<HealthCheckResponse>;
     async fn version(&self, req: CheckRequest) -> Result<VersionCheckResponse>;
+    async fn health_detail(&self, req: HealthDetailRequest) -> Result<HealthDetailResponse>;
 }
 
 #[async_trait]
@@ -56,6 +66,13 @@ pub trait Agent: AgentManager + HealthService + Send + Sync {
     async fn create_sandbox(&self, req: CreateSandboxRequest) -> Result<Empty>;
     async fn destroy_sandbox(&self, req: Empty) -> Result<Empty>;
     async fn online_cpu_mem(&self, req: OnlineCPUMemRequest) -> Result<Empty>;
+    async fn reclaim_guest_memory(&self, req: ReclaimGuestMemoryRequest) -> Result<Empty>;
+    /// Defragment guest memory so hugepage allocations, memory hotplug
+    /// and balloon deflate find contiguous blocks.
+    async fn compact_memory(&self, req: CompactMemoryRequest) -> Result<Empty>;
+    async fn quiesce_sandbox(&self, req: QuiesceSandboxRequest) -> Result<Empty>;
+    async fn thaw_sandbox(&self, req: ThawSandboxRequest) -> Result<Empty>;
+    async fn reload_config(&self, req: ReloadConfigRequest) -> Result<Empty>;
 
     // network
     async fn add_arp_neighbors(&self, req: AddArpNeighborRequest) -> Result<Empty>;
@@ -63,9 +80,19 @@ pub trait Agent: AgentManager + HealthService + Send + Sync {
     async fn list_routes(&self, req: Empty) -> Result<Routes>;
     async fn update_interface(&self, req: UpdateInterfaceRequest) -> Result<Interface>;
     async fn update_routes(&self, req: UpdateRoutesRequest) -> Result<Routes>;
+    /// Hotplug a secondary interface (e.g. a Multus attachment) into a
+    /// running sandbox: its routes are appended to the existing table
+    /// and its sysctls only affect the new interface.
+    async fn add_network(&self, req: AddNetworkRequest) -> Result<Interface>;
+    async fn remove_network(&self, req: RemoveNetworkRequest) -> Result<Empty>;
+    async fn update_dns(&self, req: UpdateDNSRequest) -> Result<Empty>;
+    async fn port_forward(&self, req: PortForwardRequest) -> Result<Empty>;
 
     // container
     async fn create_container(&self, req: CreateContainerRequest) -> Result<Empty>;
+    /// Register a sandbox-level spec fragment whose env and mounts later
+    /// create_container requests reference by id instead of resending.
+    async fn add_spec_fragment(&self, req: AddSpecFragmentRequest) -> Result<Empty>;
     async fn pause_container(&self, req: ContainerID) -> Result<Empty>;
     async fn remove_container(&self, req: RemoveContainerRequest) -> Result<Empty>;
     async fn resume_container(&self, req: ContainerID) -> Result<Empty>;
@@ -77,6 +104,13 @@ pub trait Agent: AgentManager + HealthService + Send + Sync {
     async fn exec_process(&self, req: ExecProcessRequest) -> Result<Empty>;
     async fn signal_process(&self, req: SignalProcessRequest) -> Result<Empty>;
     async fn wait_process(&self, req: WaitProcessRequest) -> Result<WaitProcessResponse>;
+    /// Stream the exit statuses of all container and exec processes as
+    /// they happen, instead of one blocked wait_process call per
+    /// process. The channel closes when the agent connection drops.
+    async fn wait_processes(
+        &self,
+        req: WaitProcessesRequest,
+    ) -> Result<tokio::sync::mpsc::Receiver<ProcessExitStatus>>;
 
     // io and tty
     async fn close_stdin(&self, req: CloseStdinRequest) -> Result<Empty>;
@@ -94,4 +128,15 @@ pub trait Agent: AgentManager + HealthService + Send + Sync {
     async fn get_volume_stats(&self, req: VolumeStatsRequest) -> Result<VolumeStatsResponse>;
     async fn resize_volume(&self, req: ResizeVolumeRequest) -> Result<Empty>;
     async fn get_guest_details(&self, req: GetGuestDetailsRequest) -> Result<GuestDetailsResponse>;
+
+    // api negotiation
+    /// Raw version handshake; agents that predate API v2 fail it with
+    /// NOT_FOUND. Callers normally want [`Agent::negotiated_api`]
+    /// instead, which folds that failure into a v1 answer and caches the
+    /// result.
+    async fn negotiate_api(&self, req: NegotiateApiRequest) -> Result<NegotiateApiResponse>;
+    /// Version and capabilities negotiated with the running agent. Every
+    /// caller of an RPC that old agents lack checks the matching `CAP_*`
+    /// capability here and takes its legacy path when it is missing.
+    async fn negotiated_api(&self) -> Result<NegotiatedApi>;
 }
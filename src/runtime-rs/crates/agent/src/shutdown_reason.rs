@@ -0,0 +1,130 @@
+// Copyright (c) 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+// The agent writes a one-line JSON record on its dedicated shutdown
+// reason vport when it goes down (panic, config error, error or clean
+// exit). Reading it here lets the shim log why the agent died instead of
+// only seeing the ttrpc connection drop. A connection that closes without
+// any record means the agent had no chance to report - a hard kill such
+// as the guest OOM killer - and is logged as such.
+
+use anyhow::Result;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::sock;
+
+const REASON_KIND_CLEAN: &str = "clean";
+
+pub(crate) struct ShutdownReasonMonitor {
+    task_handler: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl ShutdownReasonMonitor {
+    pub(crate) fn new() -> Self {
+        Self { task_handler: None }
+    }
+
+    pub(crate) fn stop(&mut self) {
+        let task_handler = self.task_handler.take();
+        if let Some(handler) = task_handler {
+            handler.abort();
+            info!(sl!(), "abort shutdown reason monitor thread");
+        }
+    }
+
+    // Connect to the agent's shutdown reason vport and log whatever
+    // records arrive until the connection closes.
+    pub(crate) async fn start(
+        &mut self,
+        address: &str,
+        port: u32,
+        config: sock::ConnectConfig,
+    ) -> Result<()> {
+        let logger = sl!().clone();
+        let address = address.to_string();
+        let task_handler = tokio::spawn(async move {
+            let sock = match sock::new(&address, port) {
+                Ok(sock) => sock,
+                Err(err) => {
+                    error!(
+                        logger,
+                        "failed to new sock for address {:?} port {} error {:?}",
+                        address,
+                        port,
+                        err
+                    );
+                    return;
+                }
+            };
+
+            let stream = match sock.connect(&config).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!(logger, "connect shutdown reason vsock failed: {:?}", err);
+                    return;
+                }
+            };
+
+            let stream = BufReader::new(stream);
+            let mut lines = stream.lines();
+            let mut recorded = false;
+            while let Ok(Some(l)) = lines.next_line().await {
+                recorded = true;
+                let (kind, message) = parse_shutdown_reason(&l);
+                if kind == REASON_KIND_CLEAN {
+                    info!(logger, "agent shutdown reason: {}: {}", kind, message);
+                } else {
+                    error!(logger, "agent shutdown reason: {}: {}", kind, message);
+                }
+            }
+
+            if !recorded {
+                warn!(
+                    logger,
+                    "agent exited without recording a shutdown reason; \
+                     it was killed without warning (e.g. guest OOM kill or kernel panic)"
+                );
+            }
+        });
+        self.task_handler = Some(task_handler);
+        Ok(())
+    }
+}
+
+// Pull the kind and message out of a reason record, falling back to the
+// raw line when it is not the JSON the agent is supposed to send.
+pub fn parse_shutdown_reason(s: &str) -> (String, String) {
+    let v: serde_json::Result<serde_json::Value> = serde_json::from_str(s);
+    match v {
+        Ok(val) => {
+            let kind = val["kind"].as_str().unwrap_or("unknown").to_string();
+            let message = val["message"].as_str().unwrap_or(s).to_string();
+            (kind, message)
+        }
+        Err(_err) => ("unknown".to_string(), s.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_shutdown_reason;
+
+    #[test]
+    fn test_parse_shutdown_reason() {
+        let (kind, message) =
+            parse_shutdown_reason(r#"{"kind":"panic","message":"index out of bounds"}"#);
+        assert_eq!(kind, "panic");
+        assert_eq!(message, "index out of bounds");
+
+        let (kind, message) = parse_shutdown_reason(r#"{"kind":"clean","message":"agent exited"}"#);
+        assert_eq!(kind, "clean");
+        assert_eq!(message, "agent exited");
+
+        // Not a valid record: keep the raw line.
+        let (kind, message) = parse_shutdown_reason("not json");
+        assert_eq!(kind, "unknown");
+        assert_eq!(message, "not json");
+    }
+}
@@ -133,6 +133,14 @@ pub struct CreateContainerRequest {
     pub stdin_port: Option<u32>,
     pub stdout_port: Option<u32>,
     pub stderr_port: Option<u32>,
+    pub spec_fragment_ids: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct AddSpecFragmentRequest {
+    pub id: String,
+    pub env: Vec<String>,
+    pub mounts: Vec<oci::Mount>,
 }
 
 #[derive(PartialEq, Clone, Default)]
@@ -363,15 +371,28 @@ pub struct NetworkStats {
     pub tx_dropped: u64,
 }
 
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct FilesystemUsage {
+    pub mount_point: String,
+    pub used_bytes: u64,
+    pub total_bytes: u64,
+    pub used_inodes: u64,
+    pub total_inodes: u64,
+}
+
 #[derive(PartialEq, Clone, Default, Debug)]
 pub struct StatsContainerResponse {
     pub cgroup_stats: Option<CgroupStats>,
     pub network_stats: Vec<NetworkStats>,
+    pub filesystem_stats: Vec<FilesystemUsage>,
 }
 
 #[derive(PartialEq, Clone, Default, Debug)]
 pub struct WaitProcessResponse {
     pub status: i32,
+    /// Contents of the container's termination message file, streamed back
+    /// by the agent when the init process exits.
+    pub termination_message: Vec<u8>,
 }
 
 #[derive(PartialEq, Clone, Default)]
@@ -407,6 +428,18 @@ pub struct UpdateRoutesRequest {
     pub route: Option<Routes>,
 }
 
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct AddNetworkRequest {
+    pub interface: Option<Interface>,
+    pub routes: Vec<Route>,
+    pub sysctls: ::std::collections::HashMap<String, String>,
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct RemoveNetworkRequest {
+    pub name: String,
+}
+
 #[derive(Deserialize, PartialEq, Clone, Default, Debug)]
 pub struct ARPNeighbor {
     pub to_ip_address: Option<IPAddress>,
@@ -500,6 +533,42 @@ pub struct OnlineCPUMemRequest {
     pub cpu_only: bool,
 }
 
+#[derive(PartialEq, Clone, Default)]
+pub struct ReclaimGuestMemoryRequest {
+    pub drop_caches: bool,
+    pub compact_memory: bool,
+}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct CompactMemoryRequest {
+    /// NUMA nodes to compact; empty compacts all guest memory.
+    pub numa_nodes: Vec<i32>,
+    /// Background compaction aggressiveness (0-100) to leave behind,
+    /// None keeps the guest's current setting.
+    pub proactiveness: Option<u32>,
+}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct QuiesceSandboxRequest {
+    pub drop_caches: bool,
+}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct ThawSandboxRequest {}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct ReloadConfigRequest {}
+
+#[derive(PartialEq, Clone, Default)]
+pub struct WaitProcessesRequest {}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct ProcessExitStatus {
+    pub container_id: String,
+    pub exec_id: String,
+    pub status: i32,
+}
+
 #[derive(PartialEq, Clone, Default)]
 pub struct ReseedRandomDevRequest {
     pub data: ::std::vec::Vec<u8>,
@@ -530,6 +599,8 @@ pub struct AgentDetails {
     pub storage_handlers: Vec<std::string::String>,
     pub supports_seccomp: bool,
     pub extra_features: Vec<std::string::String>,
+    pub supported_compression: Vec<std::string::String>,
+    pub kernel_version: String,
 }
 
 #[derive(PartialEq, Clone, Default)]
@@ -539,6 +610,19 @@ pub struct GuestDetailsResponse {
     pub support_mem_hotplug_probe: bool,
 }
 
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct NegotiateApiRequest {
+    pub major: u32,
+    pub capabilities: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct NegotiateApiResponse {
+    pub major: u32,
+    pub agent_version: String,
+    pub capabilities: Vec<String>,
+}
+
 #[derive(PartialEq, Clone, Default)]
 pub struct CopyFileRequest {
     pub path: String,
@@ -549,6 +633,7 @@ pub struct CopyFileRequest {
     pub gid: i32,
     pub offset: i64,
     pub data: ::std::vec::Vec<u8>,
+    pub compression: String,
 }
 
 #[derive(PartialEq, Clone, Default, Debug)]
@@ -575,6 +660,22 @@ pub struct VersionCheckResponse {
     pub agent_version: String,
 }
 
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct HealthDetailRequest {}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct SubsystemStatus {
+    pub name: String,
+    pub status: u32,
+    pub last_error: String,
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct HealthDetailResponse {
+    pub status: u32,
+    pub subsystems: Vec<SubsystemStatus>,
+}
+
 #[derive(PartialEq, Clone, Default, Debug)]
 pub struct MetricsResponse {
     pub metrics: String,
@@ -585,6 +686,21 @@ pub struct OomEventResponse {
     pub container_id: String,
 }
 
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct UpdateDNSRequest {
+    /// New DNS entries (resolv.conf lines) for the sandbox.
+    pub dns: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct PortForwardRequest {
+    pub container_id: String,
+    /// TCP port to connect to inside the container's network namespace.
+    pub port: u32,
+    /// Host-side vport number of the passfd stream carrying the data.
+    pub vport: u32,
+}
+
 // ResizeVolumeRequest is also the common struct for serialization and deserialization with json
 // between shim-client HTTP calls to the shim-mgmt-server
 #[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
@@ -7,6 +7,7 @@
 use super::inner::CloudHypervisorInner;
 use crate::device::pci_path::PciPath;
 use crate::device::DeviceType;
+use crate::retry::{retry_hotplug_op_async, RetryPolicy};
 use crate::HybridVsockDevice;
 use crate::NetworkConfig;
 use crate::NetworkDevice;
@@ -35,6 +36,15 @@ pub const DEFAULT_FS_QUEUES: usize = 1;
 const DEFAULT_FS_QUEUE_SIZE: u16 = 1024;
 
 impl CloudHypervisorInner {
+    // Retry policy for transient REST API failures, from the device_info
+    // section of the hypervisor config.
+    fn hotplug_retry_policy(&self) -> RetryPolicy {
+        self.config
+            .as_ref()
+            .map(|cfg| RetryPolicy::from(&cfg.device_info))
+            .unwrap_or_default()
+    }
+
     pub(crate) async fn add_device(&mut self, device: DeviceType) -> Result<DeviceType> {
         if self.state != VmmState::VmRunning {
             // If the VM is not running, add the device to the pending list to
@@ -158,11 +168,15 @@ impl CloudHypervisorInner {
             ..Default::default()
         };
 
-        let response = cloud_hypervisor_vm_fs_add(
-            socket.try_clone().context("failed to clone socket")?,
-            fs_config,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_fs_add", || async {
+                cloud_hypervisor_vm_fs_add(
+                    socket.try_clone().context("failed to clone socket")?,
+                    fs_config.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "fs add response: {:?}", detail);
@@ -199,11 +213,15 @@ impl CloudHypervisorInner {
             ..Default::default()
         };
 
-        let response = cloud_hypervisor_vm_device_add(
-            socket.try_clone().context("failed to clone socket")?,
-            device_config,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_device_add", || async {
+                cloud_hypervisor_vm_device_add(
+                    socket.try_clone().context("failed to clone socket")?,
+                    device_config.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "VFIO add response: {:?}", detail);
@@ -244,11 +262,15 @@ impl CloudHypervisorInner {
             id: clh_device_id.clone(),
         };
 
-        let response = cloud_hypervisor_vm_device_remove(
-            socket.try_clone().context("failed to clone socket")?,
-            rm_data,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_device_remove", || async {
+                cloud_hypervisor_vm_device_remove(
+                    socket.try_clone().context("failed to clone socket")?,
+                    rm_data.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "vfio remove response: {:?}", detail);
@@ -294,11 +316,15 @@ impl CloudHypervisorInner {
             ..Default::default()
         };
 
-        let response = cloud_hypervisor_vm_vsock_add(
-            socket.try_clone().context("failed to clone socket")?,
-            vsock_config,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_vsock_add", || async {
+                cloud_hypervisor_vm_vsock_add(
+                    socket.try_clone().context("failed to clone socket")?,
+                    vsock_config.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "hvsock add response: {:?}", detail);
@@ -317,11 +343,15 @@ impl CloudHypervisorInner {
 
         let disk_config = DiskConfig::try_from(device.config)?;
 
-        let response = cloud_hypervisor_vm_blockdev_add(
-            socket.try_clone().context("failed to clone socket")?,
-            disk_config,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_blockdev_add", || async {
+                cloud_hypervisor_vm_blockdev_add(
+                    socket.try_clone().context("failed to clone socket")?,
+                    disk_config.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "blockdev add response: {:?}", detail);
@@ -346,11 +376,15 @@ impl CloudHypervisorInner {
 
         let clh_net_config = NetConfig::try_from(device.config)?;
 
-        let response = cloud_hypervisor_vm_netdev_add(
-            socket.try_clone().context("failed to clone socket")?,
-            clh_net_config,
-        )
-        .await?;
+        let response =
+            retry_hotplug_op_async(&self.hotplug_retry_policy(), "vm_netdev_add", || async {
+                cloud_hypervisor_vm_netdev_add(
+                    socket.try_clone().context("failed to clone socket")?,
+                    clh_net_config.clone(),
+                )
+                .await
+            })
+            .await?;
 
         if let Some(detail) = response {
             debug!(sl!(), "netdev add response: {:?}", detail);
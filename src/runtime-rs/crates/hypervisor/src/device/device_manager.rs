@@ -206,6 +206,15 @@ impl DeviceManager {
         ))
     }
 
+    /// Snapshot of the configurations of all attached devices.
+    pub async fn get_all_device_info(&self) -> Vec<DeviceType> {
+        let mut infos = Vec::new();
+        for device in self.devices.values() {
+            infos.push(device.lock().await.get_device_info().await);
+        }
+        infos
+    }
+
     async fn get_device_info(&self, device_id: &str) -> Result<DeviceType> {
         if let Some(dev) = self.devices.get(device_id) {
             return Ok(dev.lock().await.get_device_info().await);
@@ -217,7 +226,7 @@ impl DeviceManager {
         ))
     }
 
-    async fn find_device(&self, host_path: String) -> Option<String> {
+    pub async fn find_device(&self, host_path: String) -> Option<String> {
         for (device_id, dev) in &self.devices {
             match dev.lock().await.get_device_info().await {
                 DeviceType::Block(device) => {
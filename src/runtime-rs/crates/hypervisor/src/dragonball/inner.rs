@@ -23,7 +23,7 @@ use kata_types::{
     capabilities::{Capabilities, CapabilityBits},
     config::{
         hypervisor::{HugePageType, Hypervisor as HypervisorConfig},
-        KATA_PATH, PASSFD_LISTENER_PORT,
+        kata_state_root, PASSFD_LISTENER_PORT,
     },
 };
 use nix::mount::MsFlags;
@@ -191,7 +191,7 @@ impl DragonballInner {
             .map_err(|e| anyhow!("Failed to create dir {} err : {:?}", self.jailer_root, e))?;
 
         // create run dir
-        self.run_dir = [KATA_PATH, self.id.as_str()].join("/");
+        self.run_dir = [kata_state_root().as_str(), self.id.as_str()].join("/");
         create_dir_all(self.run_dir.as_str())
             .with_context(|| format!("failed to create dir {}", self.run_dir.as_str()))?;
 
@@ -29,6 +29,7 @@ use dragonball::device_manager::{
 const MB_TO_B: u32 = 1024 * 1024;
 const DEFAULT_VIRTIO_FS_NUM_QUEUES: i32 = 1;
 const DEFAULT_VIRTIO_FS_QUEUE_SIZE: i32 = 1024;
+const DEFAULT_VIRTIO_BLK_QUEUE_SIZE: i32 = 256;
 
 const VIRTIO_FS: &str = "virtio-fs";
 const INLINE_VIRTIO_FS: &str = "inline-virtio-fs";
@@ -216,6 +217,11 @@ impl DragonballInner {
             is_direct: self.config.blockdev_info.block_device_cache_direct,
             no_drop,
             is_read_only: read_only,
+            queue_size: if self.config.blockdev_info.virtio_blk_queue_size > 0 {
+                self.config.blockdev_info.virtio_blk_queue_size as u16
+            } else {
+                DEFAULT_VIRTIO_BLK_QUEUE_SIZE as u16
+            },
             ..Default::default()
         };
         self.vmm_instance
@@ -353,6 +359,8 @@ impl DragonballInner {
             },
             queue_size: if config.queue_size > 0 {
                 config.queue_size as u16
+            } else if self.config.shared_fs.virtio_fs_queue_size > 0 {
+                self.config.shared_fs.virtio_fs_queue_size as u16
             } else {
                 DEFAULT_VIRTIO_FS_QUEUE_SIZE as u16
             },
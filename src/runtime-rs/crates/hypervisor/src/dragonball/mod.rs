@@ -295,7 +295,15 @@ pub(crate) fn build_dragonball_network_config(
 
     DragonballNetworkConfig {
         num_queues: Some(nconfig.queue_num),
-        queue_size: Some(nconfig.queue_size as u16),
+        // Fall back to the queue size configured for the hypervisor, or
+        // the Dragonball built-in default when neither is set.
+        queue_size: if nconfig.queue_size > 0 {
+            Some(nconfig.queue_size as u16)
+        } else if hconfig.network_info.virtio_net_queue_size > 0 {
+            Some(hconfig.network_info.virtio_net_queue_size as u16)
+        } else {
+            None
+        },
         backend,
         guest_mac: nconfig.guest_mac.clone().map(|mac| {
             // We are safety since mac address is checked by endpoints.
@@ -7,7 +7,7 @@ use crate::firecracker::{sl, FcInner};
 use crate::{VcpuThreadIds, VmmState, HYPERVISOR_FIRECRACKER};
 use anyhow::{anyhow, Context, Result};
 use kata_types::capabilities::Capabilities;
-use kata_types::config::KATA_PATH;
+use kata_types::config::kata_state_root;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 use tokio::fs;
@@ -27,7 +27,7 @@ impl FcInner {
         if !self.config.jailer_path.is_empty() {
             debug!(sl(), "Running jailed");
             self.jailed = true;
-            self.jailer_root = KATA_PATH.to_string();
+            self.jailer_root = kata_state_root();
             debug!(sl(), "jailer_root: {:?}", self.jailer_root);
             self.vm_path = [
                 self.jailer_root.clone(),
@@ -40,7 +40,7 @@ impl FcInner {
             debug!(sl(), "Rundir: {:?}", self.run_dir);
             let _ = self.remount_jailer_with_exec().await;
         } else {
-            self.vm_path = [KATA_PATH.to_string(), id.to_string()].join("/");
+            self.vm_path = [kata_state_root(), id.to_string()].join("/");
             debug!(sl(), "VM Path: {:?}", self.vm_path);
             self.run_dir = [self.vm_path.clone(), "run".to_string()].join("/");
             debug!(sl(), "Rundir: {:?}", self.run_dir);
@@ -0,0 +1,275 @@
+// Copyright (c) 2019-2022 Alibaba Cloud
+// Copyright (c) 2019-2022 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Lifecycle management for helper daemons that run next to the VMM, such as
+//! virtiofsd or other vhost-user backends. The daemons share the same needs:
+//! spawn the binary, optionally place it into a cgroup, capture its stderr
+//! into the runtime log, detect when it is ready, apply a restart policy when
+//! it exits unexpectedly, and kill it on sandbox teardown.
+
+use std::process::Stdio;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, Command},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        RwLock,
+    },
+};
+
+/// Policy applied when a helper process exits without having been shut down.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestartPolicy {
+    /// Never restart; only report the unexpected exit.
+    Never,
+    /// Restart the helper with the same arguments, at most `max_restarts`
+    /// times over the lifetime of the sandbox.
+    OnFailure { max_restarts: u32 },
+}
+
+/// Callback invoked when the helper exits unexpectedly and the restart policy
+/// does not (or can no longer) bring it back. The argument is a human
+/// readable description of the exit.
+pub type UnexpectedExitHandler = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Static description of a helper daemon.
+#[derive(Debug, Clone)]
+pub struct HelperProcessConfig {
+    /// Short name used in log messages, e.g. "virtiofsd".
+    pub name: String,
+    /// Path of the helper binary.
+    pub path: String,
+    /// Arguments passed to the helper binary.
+    pub args: Vec<String>,
+    /// Substring of a stderr line that signals the helper is ready to serve.
+    /// When `None` the helper is considered ready as soon as it is spawned.
+    pub ready_pattern: Option<String>,
+    /// Cgroup directory the helper pid is written into after spawning, so
+    /// that the daemon is accounted to the sandbox rather than the runtime.
+    pub cgroup_dir: Option<String>,
+    /// Policy applied when the helper exits unexpectedly.
+    pub restart_policy: RestartPolicy,
+}
+
+#[derive(Default, Debug)]
+struct HelperProcessInner {
+    pid: Option<u32>,
+    restarts: u32,
+    shutdown: bool,
+}
+
+/// A running helper daemon.
+pub struct HelperProcess {
+    config: HelperProcessConfig,
+    inner: Arc<RwLock<HelperProcessInner>>,
+    on_unexpected_exit: Option<UnexpectedExitHandler>,
+}
+
+impl std::fmt::Debug for HelperProcess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HelperProcess")
+            .field("config", &self.config)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl HelperProcess {
+    /// Spawn the helper described by `config` and wait for it to become
+    /// ready. `on_unexpected_exit` is called when the helper dies and the
+    /// restart policy does not recover it.
+    pub async fn spawn(
+        config: HelperProcessConfig,
+        on_unexpected_exit: Option<UnexpectedExitHandler>,
+    ) -> Result<Arc<Self>> {
+        let helper = Arc::new(Self {
+            config,
+            inner: Arc::new(RwLock::new(HelperProcessInner::default())),
+            on_unexpected_exit,
+        });
+
+        helper.spawn_once().await?;
+
+        Ok(helper)
+    }
+
+    /// Pid of the helper, `None` once it has been shut down.
+    pub async fn pid(&self) -> Option<u32> {
+        self.inner.read().await.pid
+    }
+
+    /// Kill the helper. Safe to call multiple times; intended to run before
+    /// the VMM itself is stopped so that no backend disappears under a
+    /// running guest, and again as part of final sandbox cleanup.
+    pub async fn shutdown(&self) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        inner.shutdown = true;
+
+        if let Some(pid) = inner.pid.take() {
+            info!(sl!(), "shutdown {} pid {}", self.config.name, pid);
+            let pid = ::nix::unistd::Pid::from_raw(pid as i32);
+            if let Err(err) = ::nix::sys::signal::kill(pid, nix::sys::signal::SIGKILL) {
+                if err != ::nix::Error::ESRCH {
+                    return Err(anyhow!(
+                        "failed to kill {} pid {} {}",
+                        self.config.name,
+                        pid,
+                        err
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn spawn_once(self: &Arc<Self>) -> Result<()> {
+        let mut cmd = Command::new(&self.config.path);
+        let child_cmd = cmd.args(&self.config.args).stderr(Stdio::piped());
+        let child = child_cmd
+            .spawn()
+            .with_context(|| format!("spawn {}", self.config.name))?;
+
+        {
+            let mut inner = self.inner.write().await;
+            inner.pid = child.id();
+        }
+
+        if let (Some(cgroup_dir), Some(pid)) = (&self.config.cgroup_dir, child.id()) {
+            let procs = [cgroup_dir.as_str(), "cgroup.procs"].join("/");
+            tokio::fs::write(&procs, pid.to_string())
+                .await
+                .with_context(|| {
+                    format!(
+                        "move {} pid {} into cgroup {}",
+                        self.config.name, pid, procs
+                    )
+                })?;
+        }
+
+        let (tx, mut rx): (Sender<Result<()>>, Receiver<Result<()>>) = channel(100);
+        tokio::spawn(self.clone().monitor(child, tx));
+
+        if self.config.ready_pattern.is_none() {
+            return Ok(());
+        }
+
+        // A closed channel means monitor() returned without ever seeing the
+        // ready pattern, i.e. the helper died (or its stderr broke) while
+        // starting up.
+        // TODO: support timeout
+        let ready = rx
+            .recv()
+            .await
+            .unwrap_or_else(|| Err(anyhow!("{} exited before becoming ready", self.config.name)));
+        match ready {
+            Ok(_) => {
+                info!(sl!(), "start {} successfully", self.config.name);
+                Ok(())
+            }
+            Err(e) => {
+                error!(sl!(), "failed to start {} {}", self.config.name, e);
+                self.shutdown()
+                    .await
+                    .with_context(|| format!("shutdown {}", self.config.name))?;
+                Err(anyhow!("failed to start {}", self.config.name))
+            }
+        }
+    }
+
+    async fn monitor(self: Arc<Self>, mut child: Child, tx: Sender<Result<()>>) -> Result<()> {
+        let stderr = child.stderr.as_mut().unwrap();
+        let stderr_reader = BufReader::new(stderr);
+        let mut lines = stderr_reader.lines();
+
+        let mut ready_sent = false;
+        while let Some(buffer) = lines.next_line().await.context("read next line")? {
+            let trim_buffer = buffer.trim_end();
+            if !trim_buffer.is_empty() {
+                info!(sl!(), "source: {} {}", self.config.name, trim_buffer);
+            }
+            if let Some(pattern) = &self.config.ready_pattern {
+                if !ready_sent && buffer.contains(pattern.as_str()) {
+                    // spawn_once only listens for the first notification.
+                    let _ = tx.send(Ok(())).await;
+                    ready_sent = true;
+                }
+            }
+        }
+
+        let status = child.wait().await;
+        info!(sl!(), "wait {} {:?}", self.config.name, status);
+
+        // An exit before the ready pattern showed up - a helper rejecting
+        // its arguments, typically - is a startup failure spawn_once has to
+        // hear about, not just an unexpected exit.
+        if self.config.ready_pattern.is_some() && !ready_sent {
+            let _ = tx
+                .send(Err(anyhow!(
+                    "{} exited with status {:?} before becoming ready",
+                    self.config.name,
+                    status
+                )))
+                .await;
+        }
+
+        // A shutdown() call means the exit was requested on purpose;
+        // anything else is a crash the restart policy has to handle.
+        let restart = {
+            let mut inner = self.inner.write().await;
+            if inner.shutdown {
+                return Ok(());
+            }
+            inner.pid = None;
+
+            match self.config.restart_policy {
+                RestartPolicy::Never => false,
+                RestartPolicy::OnFailure { max_restarts } => {
+                    if inner.restarts < max_restarts {
+                        inner.restarts += 1;
+                        true
+                    } else {
+                        false
+                    }
+                }
+            }
+        };
+
+        if restart {
+            warn!(
+                sl!(),
+                "{} exited unexpectedly with status {:?}, restarting", self.config.name, status
+            );
+            // Box the recursive call to keep the async state machine finite.
+            let respawn: std::pin::Pin<
+                Box<dyn std::future::Future<Output = Result<()>> + Send + '_>,
+            > = Box::pin(self.spawn_once());
+            if let Err(e) = respawn.await {
+                error!(sl!(), "failed to restart {}: {:?}", self.config.name, e);
+                self.report_unexpected_exit(format!(
+                    "{} exited unexpectedly with status {:?} and could not be restarted: {:?}",
+                    self.config.name, status, e
+                ));
+            }
+        } else {
+            self.report_unexpected_exit(format!(
+                "{} exited unexpectedly with status {:?}",
+                self.config.name, status
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn report_unexpected_exit(&self, message: String) {
+        if let Some(handler) = &self.on_unexpected_exit {
+            handler(message);
+        }
+    }
+}
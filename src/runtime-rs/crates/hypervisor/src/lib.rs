@@ -17,10 +17,12 @@ use device::DeviceType;
 pub mod dragonball;
 #[cfg(not(target_arch = "s390x"))]
 pub mod firecracker;
+pub mod helper_process;
 mod kernel_param;
 pub mod qemu;
 pub mod remote;
 pub use kernel_param::Param;
+pub mod retry;
 pub mod utils;
 use std::collections::HashMap;
 
@@ -6,15 +6,17 @@
 use super::cmdline_generator::{get_network_device, QemuCmdLine, QMP_SOCKET_FILE};
 use super::qmp::Qmp;
 use crate::{
-    hypervisor_persist::HypervisorState, utils::enter_netns, HypervisorConfig, MemoryConfig,
-    VcpuThreadIds, VsockDevice, HYPERVISOR_QEMU,
+    hypervisor_persist::HypervisorState,
+    retry::{retry_hotplug_op, RetryPolicy},
+    utils::enter_netns,
+    HypervisorConfig, MemoryConfig, VcpuThreadIds, VsockDevice, HYPERVISOR_QEMU,
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use kata_sys_util::netns::NetnsGuard;
 use kata_types::{
     capabilities::{Capabilities, CapabilityBits},
-    config::KATA_PATH,
+    config::kata_state_root,
 };
 use persist::sandbox_persist::Persist;
 use std::cmp::Ordering;
@@ -64,7 +66,7 @@ impl QemuInner {
         self.id = id.to_string();
         self.netns = netns;
 
-        let vm_path = [KATA_PATH, self.id.as_str()].join("/");
+        let vm_path = [kata_state_root().as_str(), self.id.as_str()].join("/");
         std::fs::create_dir_all(vm_path)?;
 
         Ok(())
@@ -273,7 +275,7 @@ impl QemuInner {
 
     pub(crate) async fn cleanup(&self) -> Result<()> {
         info!(sl!(), "QemuInner::cleanup()");
-        let vm_path = [KATA_PATH, self.id.as_str()].join("/");
+        let vm_path = [kata_state_root().as_str(), self.id.as_str()].join("/");
         std::fs::remove_dir_all(vm_path)?;
         Ok(())
     }
@@ -305,14 +307,19 @@ impl QemuInner {
             new_vcpus = self.config.cpu_info.default_maxvcpus;
         }
 
+        let retry_policy = RetryPolicy::from(&self.config.device_info);
         if let Some(ref mut qmp) = self.qmp {
             match new_vcpus.cmp(&old_vcpus) {
                 Ordering::Greater => {
-                    let hotplugged = qmp.hotplug_vcpus(new_vcpus - old_vcpus)?;
+                    let hotplugged = retry_hotplug_op(&retry_policy, "hotplug_vcpus", || {
+                        qmp.hotplug_vcpus(new_vcpus - old_vcpus)
+                    })?;
                     new_vcpus = old_vcpus + hotplugged;
                 }
                 Ordering::Less => {
-                    let hotunplugged = qmp.hotunplug_vcpus(old_vcpus - new_vcpus)?;
+                    let hotunplugged = retry_hotplug_op(&retry_policy, "hotunplug_vcpus", || {
+                        qmp.hotunplug_vcpus(old_vcpus - new_vcpus)
+                    })?;
                     new_vcpus = old_vcpus - hotunplugged;
                 }
                 Ordering::Equal => {}
@@ -403,6 +410,7 @@ impl QemuInner {
             bytes as u64 * (1 << 20)
         }
 
+        let retry_policy = RetryPolicy::from(&self.config.device_info);
         let qmp = match self.qmp {
             Some(ref mut qmp) => qmp,
             None => {
@@ -475,8 +483,10 @@ impl QemuInner {
                     "hotplugging {} B of memory",
                     new_hotplugged_mem - cur_hotplugged_memory
                 );
-                qmp.hotplug_memory(new_hotplugged_mem - cur_hotplugged_memory)
-                    .context("qemu hotplug memory")?;
+                retry_hotplug_op(&retry_policy, "hotplug_memory", || {
+                    qmp.hotplug_memory(new_hotplugged_mem - cur_hotplugged_memory)
+                })
+                .context("qemu hotplug memory")?;
                 info!(
                     sl!(),
                     "hotplugged memory after hotplugging: {}",
@@ -491,8 +501,11 @@ impl QemuInner {
                     "hotunplugging {} B of memory",
                     cur_hotplugged_memory - new_hotplugged_mem
                 );
-                let res =
-                    qmp.hotunplug_memory((cur_hotplugged_memory - new_hotplugged_mem).try_into()?);
+                let hotunplugged_mem: i64 =
+                    (cur_hotplugged_memory - new_hotplugged_mem).try_into()?;
+                let res = retry_hotplug_op(&retry_policy, "hotunplug_memory", || {
+                    qmp.hotunplug_memory(hotunplugged_mem)
+                });
                 if let Err(err) = res {
                     info!(sl!(), "hotunplugging failed: {:?}", err);
                 } else {
@@ -561,6 +574,7 @@ impl QemuInner {
     }
 
     fn hotplug_device(&mut self, device: DeviceType) -> Result<DeviceType> {
+        let retry_policy = RetryPolicy::from(&self.config.device_info);
         let qmp = match self.qmp {
             Some(ref mut qmp) => qmp,
             None => return Err(anyhow!("QMP not initialized")),
@@ -573,7 +587,9 @@ impl QemuInner {
                     &network_device.config.host_dev_name,
                     network_device.config.guest_mac.clone().unwrap(),
                 )?;
-                qmp.hotplug_network_device(&netdev, &virtio_net_device)?
+                retry_hotplug_op(&retry_policy, "hotplug_network_device", || {
+                    qmp.hotplug_network_device(&netdev, &virtio_net_device)
+                })?
             }
             _ => info!(sl!(), "hotplugging of {:#?} is unsupported", device),
         }
@@ -0,0 +1,269 @@
+// Copyright (c) 2026 Red Hat
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Bounded retry with backoff for hypervisor control-plane operations.
+//!
+//! A transient failure from the hypervisor control plane (for instance an
+//! EAGAIN while talking to the QMP socket or to the Cloud Hypervisor REST
+//! API) used to fail the whole hotplug operation, and with it typically the
+//! container creation. This module classifies control-plane errors as
+//! retriable or permanent and retries the retriable ones a bounded number
+//! of times with exponential backoff and jitter. Retries are counted per
+//! operation so they can be exposed through the shim metrics endpoint.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use kata_types::config::hypervisor::DeviceInfo;
+use lazy_static::lazy_static;
+use rand::Rng;
+
+// Upper bound on the backoff delay of a single retry, whatever the
+// configured base delay and the attempt number.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3);
+
+lazy_static! {
+    // Number of retries performed so far, per operation name.
+    static ref HOTPLUG_RETRIES: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+/// Classification of a failed control-plane operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryClass {
+    /// The failure is transient and the operation may be retried as-is.
+    Retriable,
+    /// Retrying cannot succeed, e.g. an invalid argument or a missing device.
+    Permanent,
+}
+
+/// Classify a control-plane error by scanning its chain for well-known
+/// transient failures.
+pub fn classify_control_plane_error(err: &anyhow::Error) -> RetryClass {
+    for cause in err.chain() {
+        if let Some(io_err) = cause.downcast_ref::<std::io::Error>() {
+            return match io_err.kind() {
+                std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::Interrupted
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::ConnectionReset => RetryClass::Retriable,
+                _ => RetryClass::Permanent,
+            };
+        }
+        if let Some(errno) = cause.downcast_ref::<nix::Error>() {
+            return match errno {
+                nix::Error::EAGAIN | nix::Error::EINTR | nix::Error::EBUSY => RetryClass::Retriable,
+                _ => RetryClass::Permanent,
+            };
+        }
+    }
+
+    // Some clients only surface the underlying failure as a formatted
+    // message, e.g. QMP errors forwarded by qapi.
+    let msg = format!("{:#}", err).to_lowercase();
+    if msg.contains("temporarily unavailable")
+        || msg.contains("try again")
+        || msg.contains("device or resource busy")
+    {
+        RetryClass::Retriable
+    } else {
+        RetryClass::Permanent
+    }
+}
+
+/// Bounded retry policy for hotplug control-plane operations, normally
+/// derived from the device_info section of the hypervisor config.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl From<&DeviceInfo> for RetryPolicy {
+    fn from(device_info: &DeviceInfo) -> Self {
+        RetryPolicy {
+            max_retries: device_info.hotplug_retries,
+            base_delay: Duration::from_millis(device_info.hotplug_retry_delay_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry attempt `attempt` (1-based): exponential backoff
+    /// with up to one base delay of random jitter added to avoid
+    /// synchronized retries.
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)));
+        let jitter_ms = rand::thread_rng().gen_range(0..=self.base_delay.as_millis() as u64);
+        std::cmp::min(backoff, MAX_RETRY_DELAY) + Duration::from_millis(jitter_ms)
+    }
+
+    // Decide what to do about a failed attempt: either return the delay to
+    // apply before retrying or give the error back to be propagated.
+    fn next_delay(&self, op: &str, attempt: u32, err: anyhow::Error) -> Result<Duration> {
+        if attempt > self.max_retries || classify_control_plane_error(&err) == RetryClass::Permanent
+        {
+            return Err(err);
+        }
+
+        let delay = self.delay(attempt);
+        warn!(
+            sl!(),
+            "transient {} failure (attempt {}/{}), retrying in {:?}: {:#}",
+            op,
+            attempt,
+            self.max_retries,
+            delay,
+            err
+        );
+        count_retry(op);
+        Ok(delay)
+    }
+}
+
+fn count_retry(op: &str) {
+    if let Ok(mut counts) = HOTPLUG_RETRIES.lock() {
+        *counts.entry(op.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// Snapshot of the per-operation hotplug retry counts, for the metrics
+/// endpoint.
+pub fn hotplug_retry_counts() -> Vec<(String, u64)> {
+    match HOTPLUG_RETRIES.lock() {
+        Ok(counts) => counts.iter().map(|(op, n)| (op.clone(), *n)).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Run a synchronous hotplug operation, retrying transient failures
+/// according to `policy`.
+pub fn retry_hotplug_op<T>(
+    policy: &RetryPolicy,
+    op: &str,
+    mut f: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Ok(result) => return Ok(result),
+            Err(err) => {
+                attempt += 1;
+                std::thread::sleep(policy.next_delay(op, attempt, err)?);
+            }
+        }
+    }
+}
+
+/// Asynchronous counterpart of [`retry_hotplug_op`], for REST-based
+/// hypervisor control planes.
+pub async fn retry_hotplug_op_async<T, F, Fut>(
+    policy: &RetryPolicy,
+    op: &str,
+    mut f: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(result) => return Ok(result),
+            Err(err) => {
+                attempt += 1;
+                tokio::time::sleep(policy.next_delay(op, attempt, err)?).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_classify_control_plane_error() {
+        let err = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::WouldBlock));
+        assert_eq!(classify_control_plane_error(&err), RetryClass::Retriable);
+
+        let err = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert_eq!(classify_control_plane_error(&err), RetryClass::Permanent);
+
+        let err = anyhow::Error::from(nix::Error::EAGAIN).context("hotplugging vcpus");
+        assert_eq!(classify_control_plane_error(&err), RetryClass::Retriable);
+
+        let err = anyhow!("Resource temporarily unavailable");
+        assert_eq!(classify_control_plane_error(&err), RetryClass::Retriable);
+
+        let err = anyhow!("no free slots on PCI bridges");
+        assert_eq!(classify_control_plane_error(&err), RetryClass::Permanent);
+    }
+
+    #[test]
+    fn test_retry_hotplug_op_retries_transient_failures() {
+        let mut failures_left = 2;
+        let result = retry_hotplug_op(&fast_policy(3), "test_transient", || {
+            if failures_left > 0 {
+                failures_left -= 1;
+                Err(anyhow::Error::from(nix::Error::EAGAIN))
+            } else {
+                Ok(42)
+            }
+        });
+        assert_eq!(result.unwrap(), 42);
+
+        let counts = hotplug_retry_counts();
+        let count = counts
+            .iter()
+            .find(|(op, _)| op == "test_transient")
+            .map(|(_, n)| *n)
+            .unwrap_or(0);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_retry_hotplug_op_gives_up_eventually() {
+        let mut attempts = 0;
+        let result: Result<()> = retry_hotplug_op(&fast_policy(2), "test_bounded", || {
+            attempts += 1;
+            Err(anyhow::Error::from(nix::Error::EAGAIN))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_retry_hotplug_op_permanent_failure_not_retried() {
+        let mut attempts = 0;
+        let result: Result<()> = retry_hotplug_op(&fast_policy(3), "test_permanent", || {
+            attempts += 1;
+            Err(anyhow!("device already exists"))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
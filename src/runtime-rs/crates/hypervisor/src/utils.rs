@@ -11,7 +11,7 @@ use std::{
 };
 
 use anyhow::{anyhow, Context, Result};
-use kata_types::config::KATA_PATH;
+use kata_types::config::kata_state_root;
 use nix::{
     fcntl,
     sched::{setns, CloneFlags},
@@ -44,7 +44,7 @@ pub fn get_child_threads(pid: u32) -> HashSet<u32> {
 // Return the path for a _hypothetical_ sandbox: the path does *not* exist
 // yet, and for this reason safe-path cannot be used.
 pub fn get_sandbox_path(sid: &str) -> String {
-    [KATA_PATH, sid].join("/")
+    [kata_state_root().as_str(), sid].join("/")
 }
 
 pub fn get_hvsock_path(sid: &str) -> String {
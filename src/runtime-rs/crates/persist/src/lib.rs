@@ -6,7 +6,7 @@
 
 pub mod sandbox_persist;
 use anyhow::{anyhow, Context, Ok, Result};
-use kata_types::config::KATA_PATH;
+use kata_types::config::kata_state_root;
 use serde::de;
 use std::{fs::File, io::BufReader};
 
@@ -18,10 +18,10 @@ pub fn to_disk<T: serde::Serialize>(value: &T, sid: &str, jailer_path: &str) ->
     verify_id(sid).context("failed to verify sid")?;
     // FIXME: handle jailed case
     let mut path = match jailer_path {
-        "" => scoped_join(KATA_PATH, sid)?,
+        "" => scoped_join(kata_state_root(), sid)?,
         _ => scoped_join(jailer_path, "root")?,
     };
-    //let mut path = scoped_join(KATA_PATH, sid)?;
+    //let mut path = scoped_join(kata_state_root(), sid)?;
     if path.exists() {
         path.push(PERSIST_FILE);
         let f = File::create(path)
@@ -39,7 +39,7 @@ where
     T: de::DeserializeOwned,
 {
     verify_id(sid).context("failed to verify sid")?;
-    let mut path = scoped_join(KATA_PATH, sid)?;
+    let mut path = scoped_join(kata_state_root(), sid)?;
     if path.exists() {
         path.push(PERSIST_FILE);
         let file = File::open(path).context("failed to open the file")?;
@@ -51,7 +51,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{from_disk, to_disk, KATA_PATH};
+    use crate::{from_disk, kata_state_root, to_disk};
     use serde::{Deserialize, Serialize};
     use std::fs::DirBuilder;
     use std::{fs, result::Result::Ok};
@@ -73,7 +73,7 @@ mod tests {
         assert!(to_disk(&data, ".#cdscd.", "").is_err());
 
         let sid = "aadede";
-        let sandbox_dir = [KATA_PATH, sid].join("/");
+        let sandbox_dir = [kata_state_root().as_str(), sid].join("/");
         if DirBuilder::new()
             .recursive(true)
             .create(&sandbox_dir)
@@ -74,6 +74,12 @@ pub struct CgroupsResource {
 }
 
 impl CgroupsResource {
+    /// Relative path of the sandbox resource controller, as handed over
+    /// by the orchestrator through the OCI spec.
+    pub fn sandbox_cgroup_path(&self) -> &str {
+        &self.cgroup_config.path
+    }
+
     pub fn new(sid: &str, toml_config: &TomlConfig) -> Result<Self> {
         let config = CgroupConfig::new(sid, toml_config)?;
 
@@ -0,0 +1,58 @@
+// Copyright (c) 2023 Kata Containers Community
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Sandbox condition reporting.
+//!
+//! Notable sandbox-level warning conditions (device hotplug failure,
+//! virtiofsd restart, guest OOM, ...) are sent through a channel to the
+//! runtime, which forwards them as containerd events so they surface in
+//! `kubectl get events` instead of only in node logs.
+
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A device or resource hotplug request failed.
+pub const REASON_HOTPLUG_FAILED: &str = "HotplugFailed";
+/// The virtiofsd daemon backing the shared filesystem went away.
+pub const REASON_VIRTIOFSD_RESTART: &str = "VirtiofsdRestart";
+/// A container inside the guest was OOM killed.
+pub const REASON_GUEST_OOM: &str = "GuestOOM";
+
+/// A notable sandbox condition, to be surfaced to the operator.
+#[derive(Clone, Debug, Serialize)]
+pub struct SandboxCondition {
+    /// Sandbox the condition applies to.
+    pub sandbox_id: String,
+    /// Well-known reason, one of the `REASON_*` constants.
+    pub reason: String,
+    /// Human readable description of the condition.
+    pub message: String,
+}
+
+/// Sending half of the sandbox condition channel.
+pub type SandboxConditionSender = UnboundedSender<SandboxCondition>;
+
+/// Report a sandbox condition, best effort: conditions are advisory and
+/// must never fail the operation that triggered them.
+pub fn send_condition(
+    sender: &Option<SandboxConditionSender>,
+    sandbox_id: &str,
+    reason: &str,
+    message: String,
+) {
+    let condition = SandboxCondition {
+        sandbox_id: sandbox_id.to_string(),
+        reason: reason.to_string(),
+        message,
+    };
+
+    warn!(sl!(), "sandbox condition: {:?}", condition);
+
+    if let Some(tx) = sender {
+        if let Err(e) = tx.send(condition) {
+            warn!(sl!(), "failed to send sandbox condition: {:?}", e);
+        }
+    }
+}
@@ -52,6 +52,7 @@ impl CpuResource {
         op: ResourceUpdateOp,
         hypervisor: &dyn Hypervisor,
     ) -> Result<()> {
+        let old_resource = self.snapshot_container_cpu_resources(cid).await;
         self.update_container_cpu_resources(cid, linux_cpus, op)
             .await
             .context("update container cpu resources")?;
@@ -64,8 +65,70 @@ impl CpuResource {
             return Ok(());
         }
 
-        let curr_vcpus = self
+        match self
             .do_update_cpu_resources(vcpu_required, op, hypervisor)
+            .await
+        {
+            Result::Ok(curr_vcpus) => {
+                self.update_current_vcpu(curr_vcpus).await;
+                Ok(())
+            }
+            Err(e) => {
+                // the hotplug failed, so keep the accounting in sync with
+                // the vcpus actually given to the guest
+                self.restore_container_cpu_resources(cid, old_resource)
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    // snapshot of the container's accounting entry, taken before an update
+    // so a failed resize can be rolled back
+    pub(crate) async fn snapshot_container_cpu_resources(
+        &self,
+        cid: &str,
+    ) -> Option<LinuxContainerCpuResources> {
+        self.container_cpu_resources.read().await.get(cid).cloned()
+    }
+
+    async fn restore_container_cpu_resources(
+        &self,
+        cid: &str,
+        old_resource: Option<LinuxContainerCpuResources>,
+    ) {
+        let mut resources = self.container_cpu_resources.write().await;
+        match old_resource {
+            Some(r) => {
+                resources.insert(cid.to_owned(), r);
+            }
+            None => {
+                resources.remove(cid);
+            }
+        }
+    }
+
+    // roll the container's accounting entry back to a snapshot and resize
+    // the vcpus to match, undoing a partially applied update
+    pub(crate) async fn rollback_update(
+        &self,
+        cid: &str,
+        old_resource: Option<LinuxContainerCpuResources>,
+        hypervisor: &dyn Hypervisor,
+    ) -> Result<()> {
+        self.restore_container_cpu_resources(cid, old_resource)
+            .await;
+        let vcpu_required = self
+            .calc_cpu_resources()
+            .await
+            .context("calculate vcpus required")?;
+
+        if vcpu_required == self.current_vcpu().await {
+            return Ok(());
+        }
+
+        let curr_vcpus = self
+            .do_update_cpu_resources(vcpu_required, ResourceUpdateOp::Update, hypervisor)
             .await?;
         self.update_current_vcpu(curr_vcpus).await;
         Ok(())
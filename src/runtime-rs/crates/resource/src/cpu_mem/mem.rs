@@ -4,6 +4,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use agent::{Agent, CompactMemoryRequest, ReclaimGuestMemoryRequest, CAP_RECLAIM_GUEST_MEMORY};
 use anyhow::{Context, Ok, Result};
 use hypervisor::Hypervisor;
 use oci::LinuxResources;
@@ -18,6 +19,17 @@ use crate::ResourceUpdateOp;
 // MIB_TO_BYTES_SHIFT the number to shift needed to convert MiB to Bytes
 pub const MIB_TO_BYTES_SHIFT: i32 = 20;
 
+// Old agents have neither balloon RPC; skip the best-effort reclaim and
+// compaction calls against them instead of sending RPCs that can only
+// fail.
+async fn reclaim_supported(agent: &dyn Agent) -> bool {
+    agent
+        .negotiated_api()
+        .await
+        .map(|api| api.supports(CAP_RECLAIM_GUEST_MEMORY))
+        .unwrap_or(false)
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct MemResource {
     /// Default memory
@@ -25,13 +37,18 @@ pub struct MemResource {
 
     /// MemResource of each container
     pub(crate) container_mem_resources: Arc<RwLock<HashMap<String, LinuxResources>>>,
+
+    /// Last memory size requested from the hypervisor, in MiB
+    pub(crate) current_mem_mb: Arc<RwLock<u32>>,
 }
 
 impl MemResource {
     pub fn new(init_size_manager: InitialSizeManager) -> Result<Self> {
+        let orig_toml_default_mem = init_size_manager.get_orig_toml_default_mem();
         Ok(Self {
             container_mem_resources: Arc::new(RwLock::new(HashMap::new())),
-            orig_toml_default_mem: init_size_manager.get_orig_toml_default_mem(),
+            orig_toml_default_mem,
+            current_mem_mb: Arc::new(RwLock::new(orig_toml_default_mem)),
         })
     }
 
@@ -41,7 +58,9 @@ impl MemResource {
         linux_resources: Option<&LinuxResources>,
         op: ResourceUpdateOp,
         hypervisor: &dyn Hypervisor,
+        agent: &dyn Agent,
     ) -> Result<()> {
+        let old_resource = self.snapshot_container_mem_resources(cid).await;
         self.update_container_mem_resources(cid, linux_resources, op)
             .await
             .context("update container memory resources")?;
@@ -53,11 +72,142 @@ impl MemResource {
         mem_sb_mb += self.orig_toml_default_mem;
         info!(sl!(), "calculate mem_sb_mb {}", mem_sb_mb);
 
-        let _curr_mem = self
+        // The balloon can only inflate into memory the guest has actually
+        // freed, so when shrinking ask the agent to drop caches and compact
+        // memory first. Best effort: the resize still works without it,
+        // the host just gets the memory back more slowly.
+        let curr_mem_mb = *self.current_mem_mb.read().await;
+        if mem_sb_mb < curr_mem_mb && reclaim_supported(agent).await {
+            if let Err(e) = agent
+                .reclaim_guest_memory(ReclaimGuestMemoryRequest {
+                    drop_caches: true,
+                    compact_memory: true,
+                })
+                .await
+            {
+                warn!(sl!(), "failed to reclaim guest memory: {:?}", e);
+            }
+        } else if mem_sb_mb > curr_mem_mb && reclaim_supported(agent).await {
+            // After long uptime the free lists are fragmented; compact
+            // before the hotplug (or balloon deflate) so hugepage-backed
+            // allocations in the grown guest succeed. Best effort, the
+            // resize itself does not depend on it.
+            if let Err(e) = agent.compact_memory(CompactMemoryRequest::default()).await {
+                warn!(sl!(), "failed to compact guest memory: {:?}", e);
+            }
+        }
+
+        if let Err(e) = self
             .do_update_mem_resource(mem_sb_mb, hypervisor)
+            .await
+            .context("failed to update_mem_resource")
+        {
+            // the resize failed, so keep the accounting in sync with the
+            // memory actually given to the guest
+            self.restore_container_mem_resources(cid, old_resource)
+                .await;
+            return Err(e);
+        }
+        *self.current_mem_mb.write().await = mem_sb_mb;
+
+        Ok(())
+    }
+
+    // snapshot of the container's accounting entry, taken before an update
+    // so a failed resize can be rolled back
+    pub(crate) async fn snapshot_container_mem_resources(
+        &self,
+        cid: &str,
+    ) -> Option<LinuxResources> {
+        self.container_mem_resources.read().await.get(cid).cloned()
+    }
+
+    async fn restore_container_mem_resources(
+        &self,
+        cid: &str,
+        old_resource: Option<LinuxResources>,
+    ) {
+        let mut resources = self.container_mem_resources.write().await;
+        match old_resource {
+            Some(r) => {
+                resources.insert(cid.to_owned(), r);
+            }
+            None => {
+                resources.remove(cid);
+            }
+        }
+    }
+
+    // roll the container's accounting entry back to a snapshot and resize
+    // the sandbox memory to match, undoing a partially applied update
+    pub(crate) async fn rollback_update(
+        &self,
+        cid: &str,
+        old_resource: Option<LinuxResources>,
+        hypervisor: &dyn Hypervisor,
+    ) -> Result<()> {
+        self.restore_container_mem_resources(cid, old_resource)
+            .await;
+
+        let mut mem_sb_mb = self
+            .total_mems()
+            .await
+            .context("failed to calculate total memory requirement for containers")?;
+        mem_sb_mb += self.orig_toml_default_mem;
+
+        if mem_sb_mb == *self.current_mem_mb.read().await {
+            return Ok(());
+        }
+
+        self.do_update_mem_resource(mem_sb_mb, hypervisor)
+            .await
+            .context("failed to update_mem_resource")?;
+        *self.current_mem_mb.write().await = mem_sb_mb;
+        Ok(())
+    }
+
+    /// Shrink the sandbox by `shrink_mb` in response to host memory
+    /// pressure, but never below what the containers are entitled to.
+    /// Guest caches are reclaimed first so the balloon finds free pages.
+    pub(crate) async fn shrink_for_pressure(
+        &self,
+        shrink_mb: u32,
+        hypervisor: &dyn Hypervisor,
+        agent: &dyn Agent,
+    ) -> Result<()> {
+        let mut floor_mb = self
+            .total_mems()
+            .await
+            .context("failed to calculate total memory requirement for containers")?;
+        floor_mb += self.orig_toml_default_mem;
+
+        let curr_mem_mb = *self.current_mem_mb.read().await;
+        let target_mb = std::cmp::max(curr_mem_mb.saturating_sub(shrink_mb), floor_mb);
+        if target_mb >= curr_mem_mb {
+            return Ok(());
+        }
+
+        if reclaim_supported(agent).await {
+            if let Err(e) = agent
+                .reclaim_guest_memory(ReclaimGuestMemoryRequest {
+                    drop_caches: true,
+                    compact_memory: true,
+                })
+                .await
+            {
+                warn!(sl!(), "failed to reclaim guest memory: {:?}", e);
+            }
+        }
+
+        self.do_update_mem_resource(target_mb, hypervisor)
             .await
             .context("failed to update_mem_resource")?;
+        *self.current_mem_mb.write().await = target_mb;
 
+        info!(
+            sl!(),
+            "shrunk sandbox memory from {}MB to {}MB under host pressure", curr_mem_mb, target_mb
+        );
         Ok(())
     }
 
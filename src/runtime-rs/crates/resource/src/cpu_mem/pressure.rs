@@ -0,0 +1,216 @@
+// Copyright 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Host memory pressure monitor.
+//!
+//! Polls PSI memory pressure on the parent cgroup the orchestrator put
+//! the sandbox into (falling back to the host-wide /proc/pressure/memory)
+//! and, when the short-term average crosses a threshold, shrinks sandboxes
+//! whose guest holds more free memory than a slack target: guest caches
+//! are reclaimed first, then the balloon or virtio-mem takes back the
+//! surplus.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use agent::Agent;
+use anyhow::{anyhow, Context, Result};
+use hypervisor::Hypervisor;
+
+use crate::cpu_mem::mem::MemResource;
+
+/// How often memory pressure is sampled.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Shrink only when the "some" avg10 pressure exceeds this percentage,
+/// i.e. runnable tasks stalled on memory for more than a tenth of the
+/// last ten seconds.
+const PRESSURE_AVG10_THRESHOLD: f64 = 10.0;
+
+/// Free memory left to the guest after a pressure-driven shrink, in MiB,
+/// so it does not immediately fall into reclaim itself.
+const GUEST_SLACK_MB: u32 = 128;
+
+const CGROUP_V2_ROOT: &str = "/sys/fs/cgroup";
+const HOST_MEMORY_PRESSURE_PATH: &str = "/proc/pressure/memory";
+const MEMORY_PRESSURE_FILE: &str = "memory.pressure";
+
+const GUEST_MEM_AVAILABLE_METRIC: &str = "kata_guest_meminfo{item=\"mem_available\"}";
+
+pub(crate) struct PressureMonitor {
+    sid: String,
+    agent: Arc<dyn Agent>,
+    hypervisor: Arc<dyn Hypervisor>,
+    mem_resource: MemResource,
+    psi_path: PathBuf,
+}
+
+impl PressureMonitor {
+    /// Returns `None` when no PSI source is available, e.g. a kernel
+    /// built without CONFIG_PSI or booted with psi=0.
+    pub(crate) fn new(
+        sid: &str,
+        agent: Arc<dyn Agent>,
+        hypervisor: Arc<dyn Hypervisor>,
+        mem_resource: MemResource,
+        sandbox_cgroup_path: &str,
+    ) -> Option<Self> {
+        let psi_path = psi_source(sandbox_cgroup_path)?;
+        info!(
+            sl!(),
+            "monitoring memory pressure from {}",
+            psi_path.display()
+        );
+
+        Some(Self {
+            sid: sid.to_string(),
+            agent,
+            hypervisor,
+            mem_resource,
+            psi_path,
+        })
+    }
+
+    pub(crate) async fn run(self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let avg10 = match self.read_pressure() {
+                Ok(avg10) => avg10,
+                Err(e) => {
+                    // The cgroup goes away when the sandbox is torn down;
+                    // stop monitoring instead of logging forever.
+                    info!(sl!(), "stop pressure monitor for {}: {:?}", self.sid, e);
+                    return;
+                }
+            };
+
+            if avg10 < PRESSURE_AVG10_THRESHOLD {
+                continue;
+            }
+
+            if let Err(e) = self.shrink_idle_memory(avg10).await {
+                warn!(
+                    sl!(),
+                    "failed to shrink sandbox {} under pressure: {:?}", self.sid, e
+                );
+            }
+        }
+    }
+
+    fn read_pressure(&self) -> Result<f64> {
+        let content = std::fs::read_to_string(&self.psi_path)
+            .with_context(|| format!("read {}", self.psi_path.display()))?;
+        parse_psi_some_avg10(&content)
+    }
+
+    /// Hand surplus guest memory back to the host: everything above the
+    /// slack target is reclaimed and ballooned away. A guest close to its
+    /// slack target is considered busy and left alone.
+    async fn shrink_idle_memory(&self, avg10: f64) -> Result<()> {
+        let metrics = self
+            .agent
+            .get_metrics(agent::Empty::new())
+            .await
+            .context("get guest metrics")?;
+        let available_mb = parse_guest_available_mb(&metrics.metrics)
+            .ok_or_else(|| anyhow!("guest meminfo metric not found"))?;
+
+        if available_mb <= (GUEST_SLACK_MB as u64) * 2 {
+            return Ok(());
+        }
+        let shrink_mb = (available_mb - GUEST_SLACK_MB as u64) as u32;
+
+        info!(
+            sl!(),
+            "memory pressure avg10 {} on {}: reclaiming {}MB from sandbox {}",
+            avg10,
+            self.psi_path.display(),
+            shrink_mb,
+            self.sid
+        );
+
+        self.mem_resource
+            .shrink_for_pressure(shrink_mb, self.hypervisor.as_ref(), self.agent.as_ref())
+            .await
+    }
+}
+
+// Prefer the pressure of the parent cgroup the sandbox lives in, so only
+// pressure in the kata part of the node triggers ballooning; fall back to
+// the host-wide PSI when the cgroup has no pressure file (cgroup v1).
+fn psi_source(sandbox_cgroup_path: &str) -> Option<PathBuf> {
+    let cgroup_dir =
+        PathBuf::from(CGROUP_V2_ROOT).join(sandbox_cgroup_path.trim_start_matches('/'));
+    if let Some(parent) = cgroup_dir.parent() {
+        let psi = parent.join(MEMORY_PRESSURE_FILE);
+        if psi.exists() {
+            return Some(psi);
+        }
+    }
+
+    let host = PathBuf::from(HOST_MEMORY_PRESSURE_PATH);
+    if host.exists() {
+        return Some(host);
+    }
+
+    None
+}
+
+// Parse the "some" avg10 value from PSI file content of the form:
+//   some avg10=0.00 avg60=0.00 avg300=0.00 total=0
+//   full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+fn parse_psi_some_avg10(content: &str) -> Result<f64> {
+    for line in content.lines() {
+        if let Some(rest) = line.strip_prefix("some") {
+            for field in rest.split_whitespace() {
+                if let Some(value) = field.strip_prefix("avg10=") {
+                    return value
+                        .parse::<f64>()
+                        .with_context(|| format!("parse avg10 {:?}", value));
+                }
+            }
+        }
+    }
+    Err(anyhow!("no \"some avg10\" value in PSI content"))
+}
+
+// Extract the guest's available memory in MiB from the agent's
+// prometheus metrics.
+fn parse_guest_available_mb(metrics: &str) -> Option<u64> {
+    for line in metrics.lines() {
+        if let Some(value) = line.strip_prefix(GUEST_MEM_AVAILABLE_METRIC) {
+            return value.trim().parse::<f64>().ok().map(|v| v as u64 >> 20);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_psi_some_avg10() {
+        let content = "some avg10=1.50 avg60=0.30 avg300=0.10 total=12345\n\
+                       full avg10=0.70 avg60=0.10 avg300=0.00 total=6789\n";
+        assert_eq!(parse_psi_some_avg10(content).unwrap(), 1.50);
+
+        assert!(parse_psi_some_avg10("").is_err());
+        assert!(parse_psi_some_avg10("full avg10=0.70 total=6789").is_err());
+    }
+
+    #[test]
+    fn test_parse_guest_available_mb() {
+        let metrics = format!(
+            "kata_guest_meminfo{{item=\"mem_free\"}} {}\n\
+             kata_guest_meminfo{{item=\"mem_available\"}} {}\n",
+            512 << 20,
+            256 << 20
+        );
+        assert_eq!(parse_guest_available_mb(&metrics), Some(256));
+
+        assert_eq!(parse_guest_available_mb("no meminfo here"), None);
+    }
+}
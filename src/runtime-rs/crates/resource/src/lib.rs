@@ -13,6 +13,7 @@ extern crate slog;
 logging::logger_with_subsystem!(sl, "resource");
 
 pub mod cgroups;
+pub mod condition;
 pub mod manager;
 mod manager_inner;
 pub mod network;
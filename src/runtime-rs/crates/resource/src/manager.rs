@@ -20,6 +20,7 @@ use tokio::sync::RwLock;
 use tracing::instrument;
 
 use crate::cdi_devices::ContainerDevice;
+use crate::condition::SandboxConditionSender;
 use crate::cpu_mem::initial_size::InitialSizeManager;
 use crate::network::NetworkConfig;
 use crate::resource_persist::ResourceState;
@@ -50,14 +51,22 @@ impl ResourceManager {
         hypervisor: Arc<dyn Hypervisor>,
         toml_config: Arc<TomlConfig>,
         init_size_manager: InitialSizeManager,
+        condition_sender: Option<SandboxConditionSender>,
     ) -> Result<Self> {
         // Regist resource logger for later use.
         logging::register_subsystem_logger("runtimes", "resource");
 
         Ok(Self {
             inner: Arc::new(RwLock::new(
-                ResourceManagerInner::new(sid, agent, hypervisor, toml_config, init_size_manager)
-                    .await?,
+                ResourceManagerInner::new(
+                    sid,
+                    agent,
+                    hypervisor,
+                    toml_config,
+                    init_size_manager,
+                    condition_sender,
+                )
+                .await?,
             )),
         })
     }
@@ -121,6 +130,11 @@ impl ResourceManager {
         inner.handler_devices(cid, linux).await
     }
 
+    pub async fn remove_direct_volume(&self, volume_path: &str) -> Result<()> {
+        let inner = self.inner.read().await;
+        inner.remove_direct_volume(volume_path).await
+    }
+
     pub async fn dump(&self) {
         let inner = self.inner.read().await;
         inner.dump().await
@@ -27,7 +27,11 @@ use tokio::{runtime, sync::RwLock};
 use crate::{
     cdi_devices::{sort_options_by_pcipath, ContainerDevice, DeviceInfo},
     cgroups::{CgroupArgs, CgroupsResource},
-    cpu_mem::{cpu::CpuResource, initial_size::InitialSizeManager, mem::MemResource},
+    condition::{self, SandboxConditionSender},
+    cpu_mem::{
+        cpu::CpuResource, initial_size::InitialSizeManager, mem::MemResource,
+        pressure::PressureMonitor,
+    },
     manager::ManagerArgs,
     network::{self, Network, NetworkConfig},
     resource_persist::ResourceState,
@@ -51,6 +55,7 @@ pub(crate) struct ResourceManagerInner {
     pub cgroups_resource: CgroupsResource,
     pub cpu_resource: CpuResource,
     pub mem_resource: MemResource,
+    condition_sender: Option<SandboxConditionSender>,
 }
 
 impl ResourceManagerInner {
@@ -60,6 +65,7 @@ impl ResourceManagerInner {
         hypervisor: Arc<dyn Hypervisor>,
         toml_config: Arc<TomlConfig>,
         init_size_manager: InitialSizeManager,
+        condition_sender: Option<SandboxConditionSender>,
     ) -> Result<Self> {
         let topo_config = TopologyConfigInfo::new(&toml_config);
         // create device manager
@@ -83,6 +89,7 @@ impl ResourceManagerInner {
             cgroups_resource,
             cpu_resource,
             mem_resource,
+            condition_sender,
         })
     }
 
@@ -107,7 +114,8 @@ impl ResourceManagerInner {
                         .await?
                         .is_fs_sharing_supported()
                     {
-                        let share_fs = share_fs::new(&self.sid, &c).context("new share fs")?;
+                        let share_fs = share_fs::new(&self.sid, &c, self.condition_sender.clone())
+                            .context("new share fs")?;
                         share_fs
                             .setup_device_before_start_vm(
                                 self.hypervisor.as_ref(),
@@ -243,6 +251,18 @@ impl ResourceManagerInner {
             self.handle_routes(network).await.context("handle routes")?;
         }
 
+        // Watch host memory pressure and hand surplus guest memory back
+        // to the host when the node gets tight.
+        if let Some(monitor) = PressureMonitor::new(
+            &self.sid,
+            self.agent.clone(),
+            self.hypervisor.clone(),
+            self.mem_resource.clone(),
+            self.cgroups_resource.sandbox_cgroup_path(),
+        ) {
+            tokio::spawn(monitor.run());
+        }
+
         Ok(())
     }
 
@@ -293,6 +313,12 @@ impl ResourceManagerInner {
             .await
     }
 
+    pub async fn remove_direct_volume(&self, volume_path: &str) -> Result<()> {
+        self.volume_resource
+            .remove_direct_volume(volume_path, self.device_manager.as_ref())
+            .await
+    }
+
     pub async fn handler_devices(&self, _cid: &str, linux: &Linux) -> Result<Vec<ContainerDevice>> {
         let mut devices = vec![];
 
@@ -456,36 +482,135 @@ impl ResourceManagerInner {
     ) -> Result<Option<LinuxResources>> {
         let linux_cpus = || -> Option<&LinuxCpu> { linux_resources.as_ref()?.cpu().as_ref() }();
 
+        // snapshots of the container's accounting entries, used to roll a
+        // partially applied resize back so sandbox sizing stays consistent
+        // with the cgroups actually enforced
+        let cpu_snapshot = self
+            .cpu_resource
+            .snapshot_container_cpu_resources(cid)
+            .await;
+        let mem_snapshot = self
+            .mem_resource
+            .snapshot_container_mem_resources(cid)
+            .await;
+
         // if static_sandbox_resource_mgmt, we will not have to update sandbox's cpu or mem resource
         if !self.toml_config.runtime.static_sandbox_resource_mgmt {
             // update cpu
-            self.cpu_resource
+            if let Err(e) = self
+                .cpu_resource
                 .update_cpu_resources(cid, linux_cpus, op, self.hypervisor.as_ref())
-                .await?;
+                .await
+            {
+                condition::send_condition(
+                    &self.condition_sender,
+                    &self.sid,
+                    condition::REASON_HOTPLUG_FAILED,
+                    format!(
+                        "failed to update vcpu resources for container {}: {:?}",
+                        cid, e
+                    ),
+                );
+                return Err(e);
+            }
             // update memory
-            self.mem_resource
-                .update_mem_resources(cid, linux_resources, op, self.hypervisor.as_ref())
-                .await?;
+            if let Err(e) = self
+                .mem_resource
+                .update_mem_resources(
+                    cid,
+                    linux_resources,
+                    op,
+                    self.hypervisor.as_ref(),
+                    self.agent.as_ref(),
+                )
+                .await
+            {
+                condition::send_condition(
+                    &self.condition_sender,
+                    &self.sid,
+                    condition::REASON_HOTPLUG_FAILED,
+                    format!(
+                        "failed to update memory resources for container {}: {:?}",
+                        cid, e
+                    ),
+                );
+                // the vcpus were already resized for the new request, undo that
+                if let Err(re) = self
+                    .cpu_resource
+                    .rollback_update(cid, cpu_snapshot.clone(), self.hypervisor.as_ref())
+                    .await
+                {
+                    warn!(
+                        sl!(),
+                        "failed to roll back vcpu resources for container {}: {:?}", cid, re
+                    );
+                }
+                return Err(e);
+            }
 
-            self.agent
+            if let Err(e) = self
+                .agent
                 .online_cpu_mem(OnlineCPUMemRequest {
                     wait: false,
                     nb_cpus: self.cpu_resource.current_vcpu().await,
                     cpu_only: false,
                 })
                 .await
-                .context("online vcpus")?;
+                .context("online vcpus")
+            {
+                self.rollback_cpu_mem(cid, cpu_snapshot.clone(), mem_snapshot.clone())
+                    .await;
+                return Err(e);
+            }
         }
 
         // we should firstly update the vcpus and mems, and then update the host cgroups
-        self.cgroups_resource
+        if let Err(e) = self
+            .cgroups_resource
             .update_cgroups(cid, linux_resources, op, self.hypervisor.as_ref())
-            .await?;
+            .await
+        {
+            self.rollback_cpu_mem(cid, cpu_snapshot, mem_snapshot).await;
+            return Err(e);
+        }
 
         // update the linux resources for agent
         self.agent_linux_resources(linux_resources)
     }
 
+    // best-effort undo of the vcpu and memory changes of a failed resize
+    async fn rollback_cpu_mem(
+        &self,
+        cid: &str,
+        cpu_snapshot: Option<kata_types::cpu::LinuxContainerCpuResources>,
+        mem_snapshot: Option<LinuxResources>,
+    ) {
+        if self.toml_config.runtime.static_sandbox_resource_mgmt {
+            return;
+        }
+
+        if let Err(e) = self
+            .cpu_resource
+            .rollback_update(cid, cpu_snapshot, self.hypervisor.as_ref())
+            .await
+        {
+            warn!(
+                sl!(),
+                "failed to roll back vcpu resources for container {}: {:?}", cid, e
+            );
+        }
+        if let Err(e) = self
+            .mem_resource
+            .rollback_update(cid, mem_snapshot, self.hypervisor.as_ref())
+            .await
+        {
+            warn!(
+                sl!(),
+                "failed to roll back memory resources for container {}: {:?}", cid, e
+            );
+        }
+    }
+
     fn agent_linux_resources(
         &self,
         linux_resources: Option<&LinuxResources>,
@@ -558,6 +683,7 @@ impl Persist for ResourceManagerInner {
             toml_config: Arc::new(TomlConfig::default()),
             cpu_resource: CpuResource::default(),
             mem_resource: MemResource::default(),
+            condition_sender: None,
         })
     }
 }
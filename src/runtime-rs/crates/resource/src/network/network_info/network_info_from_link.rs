@@ -7,7 +7,7 @@
 use std::convert::TryFrom;
 
 use agent::{ARPNeighbor, IPAddress, IPFamily, Interface, Route};
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use futures::stream::TryStreamExt;
 use netlink_packet_route::{
@@ -174,10 +174,10 @@ fn generate_route(name: &str, route: &RouteMessage) -> Result<Option<Route>> {
             .map(|(addr, _)| addr.to_string())
             .unwrap_or_default(),
         scope: route.header.scope as u32,
-        family: if route.header.address_family == libc::AF_INET as u8 {
-            IPFamily::V4
-        } else {
-            IPFamily::V6
+        family: match route.header.address_family as i32 {
+            libc::AF_INET => IPFamily::V4,
+            libc::AF_INET6 => IPFamily::V6,
+            family => return Err(anyhow!("unsupported route address family {}", family)),
         },
     }))
 }
@@ -228,3 +228,59 @@ impl NetworkInfo for NetworkInfoFromLink {
         Ok(self.neighs.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netlink_packet_route::nlas::route::Nla as RouteNla;
+
+    const FD00_1: [u8; 16] = [0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    const FE80_1: [u8; 16] = [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+
+    // Mock the netlink route message of an IPv6-only host.
+    fn ipv6_route_msg() -> RouteMessage {
+        let mut msg = RouteMessage::default();
+        msg.header.address_family = libc::AF_INET6 as u8;
+        msg.header.destination_prefix_length = 64;
+        msg.header.protocol = libc::RTPROT_STATIC;
+        msg.nlas = vec![
+            RouteNla::Destination(FD00_1.to_vec()),
+            RouteNla::Gateway(FE80_1.to_vec()),
+        ];
+        msg
+    }
+
+    #[test]
+    fn test_generate_route_ipv6() {
+        let route = generate_route("eth0", &ipv6_route_msg()).unwrap().unwrap();
+        assert_eq!(route.dest, "fd00::1/64");
+        assert_eq!(route.gateway, "fe80::1");
+        assert_eq!(route.device, "eth0");
+        assert_eq!(route.family, IPFamily::V6);
+    }
+
+    #[test]
+    fn test_generate_route_skips_kernel_routes() {
+        let mut msg = ipv6_route_msg();
+        msg.header.protocol = libc::RTPROT_KERNEL;
+        assert!(generate_route("eth0", &msg).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_generate_neigh_ipv6() {
+        // Mock the netlink neighbour message of an IPv6-only host.
+        let mut msg = NeighbourMessage::default();
+        msg.header.family = libc::AF_INET6 as u8;
+        msg.nlas = vec![
+            Nla::Destination(FD00_1.to_vec()),
+            Nla::LinkLocalAddress(vec![0x0a, 0x0b, 0x80, 0x03, 0x04, 0x05]),
+        ];
+
+        let neigh = generate_neigh("eth0", &msg).unwrap();
+        assert_eq!(neigh.device, "eth0");
+        assert_eq!(neigh.ll_addr, "0a:0b:80:03:04:05");
+        let to_ip = neigh.to_ip_address.unwrap();
+        assert_eq!(to_ip.family, IPFamily::V6);
+        assert_eq!(to_ip.address, "fd00::1");
+    }
+}
@@ -30,10 +30,14 @@ impl TryFrom<AddressMessage> for Address {
     type Error = anyhow::Error;
     fn try_from(msg: AddressMessage) -> Result<Self> {
         let AddressMessage { header, nlas } = msg;
+        // Seed the addresses with the unspecified address of the message's
+        // family so that fields absent from the message (e.g. broadcast on
+        // IPv6) stay family consistent on IPv6-only hosts.
+        let unspecified = unspecified_ip(header.family);
         let mut addr = Address {
-            addr: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-            peer: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-            broadcast: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+            addr: unspecified,
+            peer: unspecified,
+            broadcast: unspecified,
             label: String::default(),
             flags: 0,
             scope: header.scope,
@@ -65,6 +69,17 @@ impl TryFrom<AddressMessage> for Address {
     }
 }
 
+/// The unspecified (all-zero) address of the given netlink family,
+/// defaulting to IPv4 for unknown families.
+#[inline]
+pub(crate) fn unspecified_ip(family: u8) -> IpAddr {
+    if family as u16 == AF_INET6 {
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    }
+}
+
 pub(crate) fn parse_ip(ip: &[u8], family: u8) -> Result<IpAddr> {
     let support_len = if family as u16 == AF_INET { 4 } else { 16 };
     if ip.len() != support_len {
@@ -146,6 +161,25 @@ mod tests {
         assert!(parse_ip(fail_ipv6.as_slice(), AF_INET6 as u8).is_err());
     }
 
+    #[test]
+    fn test_address_from_ipv6_message() {
+        // Mock the netlink address message of an IPv6-only interface:
+        // no broadcast address is ever present for IPv6.
+        let mut msg = AddressMessage::default();
+        msg.header.family = AF_INET6 as u8;
+        msg.header.prefix_len = 64;
+        msg.nlas = vec![Nla::Address(vec![
+            0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        ])];
+
+        let addr = Address::try_from(msg).unwrap();
+        assert_eq!(addr.addr, IpAddr::from_str("fd00::1").unwrap());
+        assert_eq!(addr.perfix_len, 64);
+        // fields absent from the message stay in the message's family
+        assert_eq!(addr.broadcast, IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        assert_eq!(addr.peer, IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+    }
+
     #[test]
     fn test_parse_ip_cidr() {
         let test_cases = [
@@ -25,7 +25,9 @@ use std::{collections::HashMap, fmt::Debug, path::PathBuf, sync::Arc};
 use agent::Storage;
 use anyhow::{anyhow, Context, Ok, Result};
 use async_trait::async_trait;
-use kata_types::config::hypervisor::SharedFsInfo;
+use kata_types::config::{hypervisor::SharedFsInfo, kata_state_root, KATA_PATH};
+
+use crate::condition::SandboxConditionSender;
 use oci_spec::runtime as oci;
 use tokio::sync::RwLock;
 
@@ -37,6 +39,20 @@ const INLINE_VIRTIO_FS: &str = "inline-virtio-fs";
 
 const KATA_HOST_SHARED_DIR: &str = "/run/kata-containers/shared/sandboxes/";
 
+/// Get the host-side shared directory for sandboxes.
+///
+/// The historical default is kept for compatibility, but a runtime class
+/// configured with its own state root gets its shared directory under that
+/// root so classes do not compete for the same tmpfs.
+pub(crate) fn host_shared_dir() -> String {
+    let state_root = kata_state_root();
+    if state_root == KATA_PATH {
+        KATA_HOST_SHARED_DIR.to_string()
+    } else {
+        format!("{}/shared/sandboxes/", state_root)
+    }
+}
+
 /// share fs (for example virtio-fs) mount path in the guest
 const KATA_GUEST_SHARE_DIR: &str = "/run/kata-containers/shared/containers/";
 
@@ -149,7 +165,11 @@ pub trait ShareFsMount: Send + Sync {
     async fn cleanup(&self, sid: &str) -> Result<()>;
 }
 
-pub fn new(id: &str, config: &SharedFsInfo) -> Result<Arc<dyn ShareFs>> {
+pub fn new(
+    id: &str,
+    config: &SharedFsInfo,
+    condition_sender: Option<SandboxConditionSender>,
+) -> Result<Arc<dyn ShareFs>> {
     let shared_fs = config.shared_fs.clone();
     let shared_fs = shared_fs.unwrap_or_default();
     match shared_fs.as_str() {
@@ -157,7 +177,8 @@ pub fn new(id: &str, config: &SharedFsInfo) -> Result<Arc<dyn ShareFs>> {
             ShareVirtioFsInline::new(id, config).context("new inline virtio fs")?,
         )),
         VIRTIO_FS => Ok(Arc::new(
-            ShareVirtioFsStandalone::new(id, config).context("new standalone virtio fs")?,
+            ShareVirtioFsStandalone::new(id, config, condition_sender)
+                .context("new standalone virtio fs")?,
         )),
         _ => Err(anyhow!("unsupported shred fs {:?}", &shared_fs)),
     }
@@ -4,20 +4,14 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::{collections::HashMap, process::Stdio, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    process::{Child, Command},
-    sync::{
-        mpsc::{channel, Receiver, Sender},
-        Mutex, RwLock,
-    },
-};
+use tokio::sync::{Mutex, RwLock};
 
 use agent::Storage;
+use hypervisor::helper_process::{HelperProcess, HelperProcessConfig, RestartPolicy};
 use hypervisor::{device::device_manager::DeviceManager, Hypervisor};
 use kata_types::config::hypervisor::SharedFsInfo;
 
@@ -25,6 +19,7 @@ use super::{
     share_virtio_fs::generate_sock_path, utils::ensure_dir_exist, utils::get_host_ro_shared_path,
     virtio_fs_share_mount::VirtiofsShareMount, MountedInfo, ShareFs, ShareFsMount,
 };
+use crate::condition::{self, SandboxConditionSender};
 use crate::share_fs::{
     share_virtio_fs::{
         prepare_virtiofs, FS_TYPE_VIRTIO_FS, KATA_VIRTIO_FS_DEV_TYPE, MOUNT_GUEST_TAG,
@@ -46,7 +41,7 @@ pub struct ShareVirtioFsStandaloneConfig {
 
 #[derive(Default, Debug)]
 struct ShareVirtioFsStandaloneInner {
-    pid: Option<u32>,
+    helper: Option<Arc<HelperProcess>>,
 }
 
 pub(crate) struct ShareVirtioFsStandalone {
@@ -54,10 +49,15 @@ pub(crate) struct ShareVirtioFsStandalone {
     config: ShareVirtioFsStandaloneConfig,
     share_fs_mount: Arc<dyn ShareFsMount>,
     mounted_info_set: Arc<Mutex<HashMap<String, MountedInfo>>>,
+    condition_sender: Option<SandboxConditionSender>,
 }
 
 impl ShareVirtioFsStandalone {
-    pub(crate) fn new(id: &str, config: &SharedFsInfo) -> Result<Self> {
+    pub(crate) fn new(
+        id: &str,
+        config: &SharedFsInfo,
+        condition_sender: Option<SandboxConditionSender>,
+    ) -> Result<Self> {
         Ok(Self {
             inner: Arc::new(RwLock::new(ShareVirtioFsStandaloneInner::default())),
             config: ShareVirtioFsStandaloneConfig {
@@ -68,6 +68,7 @@ impl ShareVirtioFsStandalone {
             },
             share_fs_mount: Arc::new(VirtiofsShareMount::new(id)),
             mounted_info_set: Arc::new(Mutex::new(HashMap::new())),
+            condition_sender,
         })
     }
 
@@ -110,70 +111,55 @@ impl ShareVirtioFsStandalone {
             .virtiofsd_args(&sock_path, disable_guest_selinux)
             .context("virtiofsd args")?;
 
-        let mut cmd = Command::new(&self.config.virtio_fs_daemon);
-        let child_cmd = cmd.args(&args).stderr(Stdio::piped());
-        let child = child_cmd.spawn().context("spawn virtiofsd")?;
-
-        // update virtiofsd pid{
+        // A crashed virtiofsd cannot simply be respawned: the vhost-user
+        // connection to the VMM is gone with it, so instead of a restart
+        // policy the unexpected exit is reported as a sandbox condition.
+        let sandbox_id = self.config.id.clone();
+        let condition_sender = self.condition_sender.clone();
+        let helper = match HelperProcess::spawn(
+            HelperProcessConfig {
+                name: String::from("virtiofsd"),
+                path: self.config.virtio_fs_daemon.clone(),
+                args,
+                ready_pattern: Some(String::from("Waiting for vhost-user socket connection")),
+                cgroup_dir: None,
+                restart_policy: RestartPolicy::Never,
+            },
+            Some(Arc::new(move |message| {
+                condition::send_condition(
+                    &condition_sender,
+                    &sandbox_id,
+                    condition::REASON_VIRTIOFSD_RESTART,
+                    message,
+                );
+            })),
+        )
+        .await
         {
-            let mut inner = self.inner.write().await;
-            inner.pid = child.id();
-        }
-
-        let (tx, mut rx): (Sender<Result<()>>, Receiver<Result<()>>) = channel(100);
-        tokio::spawn(run_virtiofsd(child, tx));
-
-        // TODO: support timeout
-        match rx.recv().await.unwrap() {
-            Ok(_) => {
-                info!(sl!(), "start virtiofsd successfully");
-                Ok(())
-            }
+            Ok(helper) => helper,
             Err(e) => {
-                error!(sl!(), "failed to start virtiofsd {}", e);
                 self.shutdown_virtiofsd()
                     .await
                     .context("shutdown_virtiofsd")?;
-                Err(anyhow!("failed to start virtiofsd"))
+                return Err(e).context("spawn virtiofsd");
             }
-        }
-    }
+        };
 
-    async fn shutdown_virtiofsd(&self) -> Result<()> {
         let mut inner = self.inner.write().await;
-
-        if let Some(pid) = inner.pid.take() {
-            info!(sl!(), "shutdown virtiofsd pid {}", pid);
-            let pid = ::nix::unistd::Pid::from_raw(pid as i32);
-            if let Err(err) = ::nix::sys::signal::kill(pid, nix::sys::signal::SIGKILL) {
-                if err != ::nix::Error::ESRCH {
-                    return Err(anyhow!("failed to kill virtiofsd pid {} {}", pid, err));
-                }
-            }
-        }
-        inner.pid = None;
+        inner.helper = Some(helper);
 
         Ok(())
     }
-}
 
-async fn run_virtiofsd(mut child: Child, tx: Sender<Result<()>>) -> Result<()> {
-    let stderr = child.stderr.as_mut().unwrap();
-    let stderr_reader = BufReader::new(stderr);
-    let mut lines = stderr_reader.lines();
+    async fn shutdown_virtiofsd(&self) -> Result<()> {
+        let mut inner = self.inner.write().await;
 
-    while let Some(buffer) = lines.next_line().await.context("read next line")? {
-        let trim_buffer = buffer.trim_end();
-        if !trim_buffer.is_empty() {
-            info!(sl!(), "source: virtiofsd {}", trim_buffer);
-        }
-        if buffer.contains("Waiting for vhost-user socket connection") {
-            tx.send(Ok(())).await.unwrap();
+        if let Some(helper) = inner.helper.take() {
+            helper.shutdown().await.context("shutdown virtiofsd")?;
         }
-    }
 
-    info!(sl!(), "wait virtiofsd {:?}", child.wait().await);
-    Ok(())
+        Ok(())
+    }
 }
 
 #[async_trait]
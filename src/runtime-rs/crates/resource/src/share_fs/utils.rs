@@ -67,15 +67,15 @@ pub(crate) fn share_to_guest(
 //
 // 3. host-guest shared files/directories are mounted one-level under /run/kata-containers/shared/sandboxes/$sbx_id/rw/passthrough and thus present to guest at one level under run/kata-containers/shared/containers/passthrough.
 pub(crate) fn get_host_ro_shared_path(id: &str) -> PathBuf {
-    Path::new(KATA_HOST_SHARED_DIR).join(id).join("ro")
+    Path::new(&host_shared_dir()).join(id).join("ro")
 }
 
 pub fn get_host_rw_shared_path(sid: &str) -> PathBuf {
-    Path::new(KATA_HOST_SHARED_DIR).join(sid).join("rw")
+    Path::new(&host_shared_dir()).join(sid).join("rw")
 }
 
 pub fn get_host_shared_path(sid: &str) -> PathBuf {
-    Path::new(KATA_HOST_SHARED_DIR).join(sid)
+    Path::new(&host_shared_dir()).join(sid)
 }
 
 fn do_get_guest_any_path(
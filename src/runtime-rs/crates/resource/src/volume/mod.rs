@@ -20,7 +20,7 @@ use std::{sync::Arc, vec::Vec};
 use self::hugepage::{get_huge_page_limits_map, get_huge_page_option};
 use crate::{share_fs::ShareFs, volume::block_volume::is_block_volume};
 use agent::Agent;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use hypervisor::device::device_manager::DeviceManager;
 use kata_sys_util::mount::get_mount_options;
@@ -124,6 +124,44 @@ impl VolumeResource {
         Ok(volumes)
     }
 
+    /// Hot-remove a direct-assigned volume when the CSI driver unstages
+    /// it: the backing device is unplugged from the VM and the volume is
+    /// dropped from the tracked list. kata-ctl posts the device path
+    /// recorded in the mount info, so the volume is looked up through
+    /// the device manager by the host path of its backing device, with
+    /// the guest mount point kept as a fallback for callers that pass
+    /// the storage path instead.
+    pub async fn remove_direct_volume(
+        &self,
+        volume_path: &str,
+        d: &RwLock<DeviceManager>,
+    ) -> Result<()> {
+        let device_id = d.read().await.find_device(volume_path.to_string()).await;
+
+        let mut inner = self.inner.write().await;
+        let pos = inner.volumes.iter().position(|v| {
+            if let (Some(did), Ok(Some(vid))) = (device_id.as_ref(), v.get_device_id()) {
+                if vid == *did {
+                    return true;
+                }
+            }
+            v.get_storage()
+                .map(|storages| storages.iter().any(|s| s.mount_point == volume_path))
+                .unwrap_or(false)
+        });
+
+        match pos {
+            Some(pos) => {
+                let volume = inner.volumes.remove(pos);
+                volume
+                    .cleanup(d)
+                    .await
+                    .with_context(|| format!("cleanup direct volume {}", volume_path))
+            }
+            None => Err(anyhow!("no direct volume found for {}", volume_path)),
+        }
+    }
+
     pub async fn dump(&self) {
         let inner = self.inner.read().await;
         for v in &inner.volumes {
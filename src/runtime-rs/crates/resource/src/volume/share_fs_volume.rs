@@ -13,7 +13,7 @@ use std::{
     sync::Arc,
 };
 
-use agent::Agent;
+use agent::{Agent, GetGuestDetailsRequest};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use hypervisor::device::device_manager::DeviceManager;
@@ -29,6 +29,11 @@ use oci_spec::runtime as oci;
 
 const SYS_MOUNT_PREFIX: [&str; 2] = ["/proc", "/sys"];
 
+/// Payloads below this size are sent uncompressed: the CPU cost of zstd
+/// outweighs the vsock bandwidth saved for small files.
+const COPY_FILE_COMPRESS_THRESHOLD: usize = 32 * 1024;
+const ZSTD_COMPRESSION: &str = "zstd";
+
 // copy file to container's rootfs if filesystem sharing is not supported, otherwise
 // bind mount it in the shared directory.
 // Ignore /dev, directories and all other device files. We handle
@@ -105,6 +110,24 @@ impl ShareFsVolume {
                     file.read_to_end(&mut buffer)
                         .with_context(|| format!("Failed to read file: {:?}", src))?;
 
+                    // Compress large payloads when the agent advertises
+                    // support, to cut vsock bandwidth for the copy.
+                    let mut compression = String::new();
+                    if buffer.len() >= COPY_FILE_COMPRESS_THRESHOLD
+                        && agent_supports_compression(&agent, ZSTD_COMPRESSION).await
+                    {
+                        match zstd::stream::encode_all(buffer.as_slice(), 0) {
+                            Ok(compressed) if compressed.len() < buffer.len() => {
+                                buffer = compressed;
+                                compression = ZSTD_COMPRESSION.to_string();
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                warn!(sl!(), "failed to compress copy payload: {:?}", e)
+                            }
+                        }
+                    }
+
                     // Create gRPC request
                     let r = agent::CopyFileRequest {
                         path: dest.clone(),
@@ -113,6 +136,7 @@ impl ShareFsVolume {
                         gid: file_metadata.gid() as i32,
                         file_mode: file_metadata.mode(),
                         data: buffer,
+                        compression,
                         ..Default::default()
                     };
 
@@ -307,6 +331,24 @@ impl Volume for ShareFsVolume {
     }
 }
 
+// Check whether the agent advertises the given payload compression
+// algorithm in its details, treating any query failure as "unsupported".
+async fn agent_supports_compression(agent: &Arc<dyn Agent>, algorithm: &str) -> bool {
+    match agent
+        .get_guest_details(GetGuestDetailsRequest::default())
+        .await
+    {
+        Ok(resp) => resp
+            .agent_details
+            .map(|details| details.supported_compression.iter().any(|c| c == algorithm))
+            .unwrap_or(false),
+        Err(e) => {
+            warn!(sl!(), "failed to get guest details: {:?}", e);
+            false
+        }
+    }
+}
+
 pub(crate) fn is_share_fs_volume(m: &oci::Mount) -> bool {
     let mount_type = get_mount_type(m);
     (mount_type == "bind" || mount_type == mount::KATA_EPHEMERAL_VOLUME_TYPE)
@@ -8,6 +8,7 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use containerd_shim_protos::events::task::{TaskExit, TaskOOM};
 use containerd_shim_protos::protobuf::Message as ProtobufMessage;
+use resource::condition::SandboxCondition;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 /// message receiver buffer size
@@ -86,3 +87,20 @@ impl Event for TaskExit {
         self.write_to_bytes().context("get exit value")
     }
 }
+
+const SANDBOX_CONDITION_EVENT_TOPIC: &str = "/kata/sandbox/condition";
+const SANDBOX_CONDITION_EVENT_URL: &str = "katacontainers.io/sandbox.Condition";
+
+impl Event for SandboxCondition {
+    fn r#type(&self) -> String {
+        SANDBOX_CONDITION_EVENT_TOPIC.to_string()
+    }
+
+    fn type_url(&self) -> String {
+        SANDBOX_CONDITION_EVENT_URL.to_string()
+    }
+
+    fn value(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("get sandbox condition value")
+    }
+}
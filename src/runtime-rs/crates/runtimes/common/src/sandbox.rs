@@ -36,6 +36,7 @@ pub trait Sandbox: Send + Sync {
     async fn get_iptables(&self, is_ipv6: bool) -> Result<Vec<u8>>;
     async fn direct_volume_stats(&self, volume_path: &str) -> Result<String>;
     async fn direct_volume_resize(&self, resize_req: agent::ResizeVolumeRequest) -> Result<()>;
+    async fn direct_volume_remove(&self, volume_path: &str) -> Result<()>;
     async fn agent_sock(&self) -> Result<String>;
     async fn wait_process(
         &self,
@@ -47,4 +48,12 @@ pub trait Sandbox: Send + Sync {
     // metrics function
     async fn agent_metrics(&self) -> Result<String>;
     async fn hypervisor_metrics(&self) -> Result<String>;
+
+    /// Best estimate of host memory attributable to the sandbox, in bytes.
+    async fn mem_footprint_bytes(&self) -> Result<u64>;
+
+    /// Host CPUs and passthrough devices the sandbox consumes, serialized
+    /// in the kubelet PodResources format so topology-aware schedulers can
+    /// account Kata pods like runc ones.
+    async fn pod_resources(&self) -> Result<String>;
 }
@@ -13,7 +13,8 @@ use common::{
 use hypervisor::Param;
 use kata_sys_util::{mount::get_mount_path, spec::load_oci_spec};
 use kata_types::{
-    annotations::Annotation, config::default::DEFAULT_GUEST_DNS_FILE, config::TomlConfig,
+    annotations::Annotation, config::default::DEFAULT_GUEST_DNS_FILE, config::set_kata_state_root,
+    config::TomlConfig,
 };
 #[cfg(feature = "linux")]
 use linux_container::LinuxContainer;
@@ -67,6 +68,7 @@ struct RuntimeHandlerManagerInner {
     msg_sender: Sender<Message>,
     kata_tracer: Arc<Mutex<KataTracer>>,
     runtime_instance: Option<Arc<RuntimeInstance>>,
+    toml_config: Option<Arc<TomlConfig>>,
 }
 
 impl std::fmt::Debug for RuntimeHandlerManagerInner {
@@ -86,6 +88,7 @@ impl RuntimeHandlerManagerInner {
             msg_sender,
             kata_tracer: Arc::new(Mutex::new(tracer)),
             runtime_instance: None,
+            toml_config: None,
         })
     }
 
@@ -134,6 +137,7 @@ impl RuntimeHandlerManagerInner {
 
         let instance = Arc::new(runtime_instance);
         self.runtime_instance = Some(instance.clone());
+        self.toml_config = Some(config);
 
         Ok(())
     }
@@ -216,6 +220,9 @@ impl RuntimeHandlerManagerInner {
         tokio::task::spawn(Arc::new(shim_mgmt_svr).run());
         info!(sl!(), "shim management http server starts");
 
+        // Watch the runtime for stalls for the rest of the sandbox lifetime.
+        crate::shim_metrics::spawn_runtime_monitor();
+
         Ok(())
     }
 
@@ -306,6 +313,17 @@ impl RuntimeHandlerManager {
             .ok_or_else(|| anyhow!("runtime not ready"))
     }
 
+    /// Identifier of the sandbox this shim serves.
+    pub async fn sandbox_id(&self) -> String {
+        self.inner.read().await.id.clone()
+    }
+
+    /// The runtime configuration loaded for this sandbox, available once
+    /// the first create request initialized the runtime instance.
+    pub async fn runtime_config(&self) -> Option<Arc<TomlConfig>> {
+        self.inner.read().await.toml_config.clone()
+    }
+
     async fn get_kata_tracer(&self) -> Result<Arc<Mutex<KataTracer>>> {
         let inner = self.inner.read().await;
         Ok(inner.get_kata_tracer())
@@ -585,10 +603,64 @@ fn load_config(an: &HashMap<String, String>, option: &Option<Vec<u8>>) -> Result
     // validate configuration and return the error
     toml_config.validate()?;
 
+    // Apply the per-runtime-class state root before any component derives
+    // paths from it, and warn early when the state root is running out of
+    // its configured budget.
+    set_kata_state_root(&toml_config.runtime.state_root);
+    check_state_root_usage(&logger, &toml_config);
+
     info!(logger, "get config content {:?}", &toml_config);
     Ok(toml_config)
 }
 
+// Warn when the data under the state root exceeds the configured limit, so
+// operators notice a filling tmpfs before sandbox creation starts to fail.
+// A limit of 0 disables the check.
+fn check_state_root_usage(logger: &slog::Logger, config: &TomlConfig) {
+    let limit_mb = config.runtime.state_warn_limit_mb;
+    if limit_mb == 0 {
+        return;
+    }
+
+    let state_root = kata_types::config::kata_state_root();
+    let logger = logger.clone();
+    // The recursive walk hits the filesystem; run it on the blocking pool
+    // so a large or slow state root cannot stall the async workers.
+    tokio::task::spawn_blocking(move || {
+        let used = dir_size(std::path::Path::new(&state_root));
+        if used > limit_mb.saturating_mul(1024 * 1024) {
+            warn!(
+                logger,
+                "state root {} uses {} bytes, more than the configured limit of {} MiB",
+                state_root,
+                used,
+                limit_mb
+            );
+        }
+    });
+}
+
+// Best effort recursive directory size: unreadable entries are skipped and
+// symlinks are not followed, so shared mounts are not double counted.
+fn dir_size(path: &std::path::Path) -> u64 {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    let mut size = 0u64;
+    for entry in entries.flatten() {
+        if let Ok(metadata) = entry.path().symlink_metadata() {
+            if metadata.is_dir() {
+                size = size.saturating_add(dir_size(&entry.path()));
+            } else if metadata.is_file() {
+                size = size.saturating_add(metadata.len());
+            }
+        }
+    }
+    size
+}
+
 // this update the agent-specfic kernel parameters into hypervisor's bootinfo
 // the agent inside the VM will read from file cmdline to get the params and function
 fn update_agent_kernel_params(config: &mut TomlConfig) -> Result<()> {
@@ -9,6 +9,7 @@ use anyhow::{anyhow, Result};
 use prometheus::{Encoder, Gauge, GaugeVec, Opts, Registry, TextEncoder};
 use slog::warn;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const NAMESPACE_KATA_SHIM: &str = "kata_shim";
 
@@ -36,6 +37,20 @@ lazy_static! {
     static ref SHIM_IO_STAT: GaugeVec = GaugeVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_SHIM,"io_stat"), "Kata containerd shim v2 process IO statistics."), &["item"]).unwrap();
 
     static ref SHIM_OPEN_FDS: Gauge = Gauge::new(format!("{}_{}", NAMESPACE_KATA_SHIM, "fds"), "Kata containerd shim v2 open FDs.").unwrap();
+
+    static ref SHIM_HOTPLUG_RETRIES: GaugeVec = GaugeVec::new(Opts::new(format!("{}_{}",NAMESPACE_KATA_SHIM,"hypervisor_hotplug_retries"), "Retries of hypervisor control-plane hotplug operations after transient failures."), &["op"]).unwrap();
+
+    static ref SHIM_SANDBOX_MEM_FOOTPRINT: Gauge = Gauge::new(format!("{}_{}", NAMESPACE_KATA_SHIM, "sandbox_mem_footprint_bytes"), "Estimated host memory attributable to the sandbox, in bytes.").unwrap();
+
+    static ref SHIM_TOKIO_WORKERS: Gauge = Gauge::new(format!("{}_{}", NAMESPACE_KATA_SHIM, "tokio_workers"), "Worker threads of the shim tokio runtime.").unwrap();
+
+    static ref SHIM_TOKIO_POLL_DELAY: Gauge = Gauge::new(format!("{}_{}", NAMESPACE_KATA_SHIM, "tokio_poll_delay_seconds"), "Sampled delay between a ready task yielding and being polled again; grows when blocking work stalls the runtime.").unwrap();
+}
+
+/// Record the latest sandbox memory footprint estimate so that it is
+/// included in the next metrics gathering.
+pub fn set_sandbox_mem_footprint(bytes: u64) {
+    SHIM_SANDBOX_MEM_FOOTPRINT.set(bytes as f64);
 }
 
 pub fn get_shim_metrics() -> Result<String> {
@@ -66,6 +81,10 @@ fn register_shim_metrics() -> Result<()> {
     REGISTRY.register(Box::new(SHIM_PROC_STAT.clone()))?;
     REGISTRY.register(Box::new(SHIM_IO_STAT.clone()))?;
     REGISTRY.register(Box::new(SHIM_OPEN_FDS.clone()))?;
+    REGISTRY.register(Box::new(SHIM_HOTPLUG_RETRIES.clone()))?;
+    REGISTRY.register(Box::new(SHIM_SANDBOX_MEM_FOOTPRINT.clone()))?;
+    REGISTRY.register(Box::new(SHIM_TOKIO_WORKERS.clone()))?;
+    REGISTRY.register(Box::new(SHIM_TOKIO_POLL_DELAY.clone()))?;
 
     // TODO:
     // REGISTRY.register(Box::new(RPC_DURATIONS_HISTOGRAM.clone()))?;
@@ -120,12 +139,46 @@ fn update_shim_metrics() -> Result<()> {
         }
     }
 
+    for (op, count) in hypervisor::retry::hotplug_retry_counts() {
+        SHIM_HOTPLUG_RETRIES
+            .with_label_values(&[op.as_str()])
+            .set(count as f64);
+    }
+
+    // Scrapes run inside the runtime, so the handle is normally present;
+    // stay silent otherwise rather than failing the whole scrape.
+    // num_workers is the only RuntimeMetrics accessor that is stable in
+    // the tokio release pinned by this workspace; the task-level counters
+    // are cfg(tokio_unstable) there, so the runtime health signal beyond
+    // this comes from the sampled poll delay below.
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        SHIM_TOKIO_WORKERS.set(handle.metrics().num_workers() as f64);
+    }
+
     // TODO:
     // RPC_DURATIONS_HISTOGRAM & SHIM_POD_OVERHEAD_CPU & SHIM_POD_OVERHEAD_MEMORY
 
     Ok(())
 }
 
+/// How often the poll delay sample task wakes up.
+const POLL_DELAY_SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Spawn a task that periodically measures how long a ready task waits
+/// before the runtime polls it again. Blocking calls that sneak onto the
+/// async workers show up here as a growing delay, long before they stall
+/// the shim outright.
+pub fn spawn_runtime_monitor() {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(POLL_DELAY_SAMPLE_INTERVAL).await;
+            let start = Instant::now();
+            tokio::task::yield_now().await;
+            SHIM_TOKIO_POLL_DELAY.set(start.elapsed().as_secs_f64());
+        }
+    });
+}
+
 fn set_gauge_vec_proc_status(gv: &prometheus::GaugeVec, status: &procfs::process::Status) {
     gv.with_label_values(&["vmpeak"])
         .set(status.vmpeak.unwrap_or(0) as f64);
@@ -7,7 +7,7 @@
 // This defines the handlers corresponding to the url when a request is sent to destined url,
 // the handler function should be invoked, and the corresponding data will be in the response
 
-use crate::shim_metrics::get_shim_metrics;
+use crate::shim_metrics::{get_shim_metrics, set_sandbox_mem_footprint};
 use agent::ResizeVolumeRequest;
 use anyhow::{anyhow, Context, Result};
 use common::Sandbox;
@@ -16,8 +16,9 @@ use std::sync::Arc;
 use url::Url;
 
 use shim_interface::shim_mgmt::{
-    AGENT_URL, DIRECT_VOLUME_PATH_KEY, DIRECT_VOLUME_RESIZE_URL, DIRECT_VOLUME_STATS_URL,
-    IP6_TABLE_URL, IP_TABLE_URL, METRICS_URL,
+    AGENT_URL, DIRECT_VOLUME_PATH_KEY, DIRECT_VOLUME_REMOVE_URL, DIRECT_VOLUME_RESIZE_URL,
+    DIRECT_VOLUME_STATS_URL, IP6_TABLE_URL, IP_TABLE_URL, MEM_FOOTPRINT_URL, METRICS_URL,
+    POD_RESOURCES_URL,
 };
 
 // main router for response, this works as a multiplexer on
@@ -44,7 +45,12 @@ pub(crate) async fn handler_mux(
         (&Method::POST, DIRECT_VOLUME_RESIZE_URL) => {
             direct_volume_resize_handler(sandbox, req).await
         }
+        (&Method::POST, DIRECT_VOLUME_REMOVE_URL) => {
+            direct_volume_remove_handler(sandbox, req).await
+        }
         (&Method::GET, METRICS_URL) => metrics_url_handler(sandbox, req).await,
+        (&Method::GET, MEM_FOOTPRINT_URL) => mem_footprint_handler(sandbox, req).await,
+        (&Method::GET, POD_RESOURCES_URL) => pod_resources_handler(sandbox, req).await,
         _ => Ok(not_found(req).await),
     }
 }
@@ -149,6 +155,56 @@ async fn direct_volume_resize_handler(
     }
 }
 
+// triggered by the CSI driver's NodeUnstageVolume: hot-unplug the
+// direct-assigned volume's backing device once the container-level
+// unmount is done
+async fn direct_volume_remove_handler(
+    sandbox: Arc<dyn Sandbox>,
+    req: Request<Body>,
+) -> Result<Response<Body>> {
+    let params = Url::parse(&req.uri().to_string())
+        .map_err(|e| anyhow!(e))?
+        .query_pairs()
+        .into_owned()
+        .collect::<std::collections::HashMap<String, String>>();
+    let volume_path = params
+        .get(DIRECT_VOLUME_PATH_KEY)
+        .context("shim-mgmt: volume path key not found in request params")?;
+    let result = sandbox.direct_volume_remove(volume_path).await;
+    match result {
+        Ok(_) => Ok(Response::new(Body::from(""))),
+        _ => Err(anyhow!("handler: Failed to remove direct volume")),
+    }
+}
+
+// returns the estimated host memory footprint of the sandbox, for
+// cluster autoscaler tuning and pod overhead configuration
+async fn mem_footprint_handler(
+    sandbox: Arc<dyn Sandbox>,
+    _req: Request<Body>,
+) -> Result<Response<Body>> {
+    let bytes = sandbox
+        .mem_footprint_bytes()
+        .await
+        .context("handler: failed to get sandbox memory footprint")?;
+    set_sandbox_mem_footprint(bytes);
+    let body = serde_json::json!({ "mem_footprint_bytes": bytes }).to_string();
+    Ok(Response::new(Body::from(body)))
+}
+
+// Reports the host CPUs and passthrough devices the sandbox consumes in
+// the kubelet PodResources format, for the node's Pod Resources adapter.
+async fn pod_resources_handler(
+    sandbox: Arc<dyn Sandbox>,
+    _req: Request<Body>,
+) -> Result<Response<Body>> {
+    let body = sandbox
+        .pod_resources()
+        .await
+        .context("handler: failed to get pod resources")?;
+    Ok(Response::new(Body::from(body)))
+}
+
 // returns the url for metrics
 async fn metrics_url_handler(
     sandbox: Arc<dyn Sandbox>,
@@ -157,6 +213,9 @@ async fn metrics_url_handler(
     // get metrics from agent, hypervisor, and shim
     let agent_metrics = sandbox.agent_metrics().await.unwrap_or_default();
     let hypervisor_metrics = sandbox.hypervisor_metrics().await.unwrap_or_default();
+    if let Ok(bytes) = sandbox.mem_footprint_bytes().await {
+        set_sandbox_mem_footprint(bytes);
+    }
     let shim_metrics = get_shim_metrics().unwrap_or_default();
 
     Ok(Response::new(Body::from(format!(
@@ -4,10 +4,10 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use agent::Agent;
+use agent::{Agent, NegotiatedApi, CAP_ADD_SPEC_FRAGMENT};
 use anyhow::{anyhow, Context, Result};
 use common::{
     error::Error,
@@ -24,7 +24,7 @@ use oci::{LinuxResources, Process as OCIProcess};
 use resource::{
     cdi_devices::container_device::annotate_container_devices, ResourceManager, ResourceUpdateOp,
 };
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 use super::{
     process::{Process, ProcessWatcher},
@@ -32,6 +32,11 @@ use super::{
 };
 use crate::container_manager::logger_with_process;
 
+/// Env lists and mount tables smaller than these are cheaper to resend
+/// in every create_container request than to register as fragments.
+const ENV_FRAGMENT_MIN: usize = 32;
+const MOUNT_FRAGMENT_MIN: usize = 16;
+
 pub struct Exec {
     pub(crate) process: Process,
     pub(crate) oci_process: OCIProcess,
@@ -47,6 +52,7 @@ pub struct Container {
     resource_manager: Arc<ResourceManager>,
     logger: slog::Logger,
     pub(crate) passfd_listener_addr: Option<(String, u32)>,
+    spec_fragments: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Container {
@@ -57,6 +63,7 @@ impl Container {
         agent: Arc<dyn Agent>,
         resource_manager: Arc<ResourceManager>,
         passfd_listener_addr: Option<(String, u32)>,
+        spec_fragments: Arc<Mutex<HashSet<String>>>,
     ) -> Result<Self> {
         let container_id = ContainerID::new(&config.container_id).context("new container id")?;
         let logger = sl!().new(o!("container_id" => config.container_id.clone()));
@@ -90,6 +97,7 @@ impl Container {
             resource_manager,
             logger,
             passfd_listener_addr,
+            spec_fragments,
         })
     }
 
@@ -222,6 +230,32 @@ impl Container {
                 .await?;
         }
 
+        // Large env lists and mount tables shared across a pod's
+        // containers cross the vsock once as sandbox-level fragments;
+        // later requests reference them by id. A handshake failure is
+        // treated like a v1 agent: the spec stays inline, the one format
+        // every agent understands.
+        let api = self.agent.negotiated_api().await.unwrap_or_else(|e| {
+            warn!(
+                self.logger,
+                "api handshake failed, assuming a v1 agent: {:?}", e
+            );
+            NegotiatedApi::v1()
+        });
+        let mut spec_fragment_ids = Vec::new();
+        for fragment in extract_spec_fragments_if_supported(&api, &mut spec) {
+            let id = fragment.id.clone();
+            let mut registered = self.spec_fragments.lock().await;
+            if !registered.contains(&id) {
+                self.agent
+                    .add_spec_fragment(fragment)
+                    .await
+                    .context("agent add spec fragment")?;
+                registered.insert(id.clone());
+            }
+            spec_fragment_ids.push(id);
+        }
+
         // create container
         let r = agent::CreateContainerRequest {
             process_id: agent::ContainerProcessID::new(&config.container_id, ""),
@@ -245,6 +279,7 @@ impl Container {
                 .passfd_io
                 .as_ref()
                 .and_then(|io| io.stderr_port),
+            spec_fragment_ids,
             ..Default::default()
         };
 
@@ -540,7 +575,8 @@ impl Container {
 
     pub async fn update(&self, resources: &LinuxResources) -> Result<()> {
         let mut inner = self.inner.write().await;
-        inner.linux_resources = Some(resources.clone());
+        let old_resources = inner.linux_resources.clone();
+
         // update vcpus, mems and host cgroups
         let agent_resources = self
             .resource_manager
@@ -556,10 +592,28 @@ impl Container {
             resources: agent_resources,
             mounts: Vec::new(),
         };
-        self.agent
-            .update_container(req)
-            .await
-            .context("agent update container")?;
+        if let Err(e) = self.agent.update_container(req).await {
+            // the guest cgroups were not updated, so roll the sandbox
+            // sizing and host cgroups back to the previous container
+            // resources to keep them consistent with the guest
+            if let Err(re) = self
+                .resource_manager
+                .update_linux_resource(
+                    &self.config.container_id,
+                    old_resources.as_ref(),
+                    ResourceUpdateOp::Update,
+                )
+                .await
+            {
+                warn!(
+                    self.logger,
+                    "failed to roll back sandbox resources after agent update failure: {:?}", re
+                );
+            }
+            return Err(e).context("agent update container");
+        }
+
+        inner.linux_resources = Some(resources.clone());
         Ok(())
     }
 
@@ -645,6 +699,71 @@ fn amend_spec(
     Ok(())
 }
 
+// Only agents that negotiated the capability understand fragment
+// references; against anyone else - a v1 agent during an upgrade in
+// particular - the spec is left untouched and sent inline.
+fn extract_spec_fragments_if_supported(
+    api: &NegotiatedApi,
+    spec: &mut oci::Spec,
+) -> Vec<agent::AddSpecFragmentRequest> {
+    if !api.supports(CAP_ADD_SPEC_FRAGMENT) {
+        return Vec::new();
+    }
+
+    extract_spec_fragments(spec)
+}
+
+// extract_spec_fragments moves env lists and mount tables above the
+// fragment thresholds out of the spec into content-addressed fragments.
+// The agent fills them back in when it creates the container.
+fn extract_spec_fragments(spec: &mut oci::Spec) -> Vec<agent::AddSpecFragmentRequest> {
+    let mut fragments = Vec::new();
+
+    if let Some(process) = spec.process_mut() {
+        let env = process.env().clone().unwrap_or_default();
+        if env.len() >= ENV_FRAGMENT_MIN {
+            let fragment = agent::AddSpecFragmentRequest {
+                id: fragment_id("env", env.iter().map(|e| e.as_bytes())),
+                env,
+                ..Default::default()
+            };
+            process.set_env(None);
+            fragments.push(fragment);
+        }
+    }
+
+    let mounts = spec.mounts().clone().unwrap_or_default();
+    if mounts.len() >= MOUNT_FRAGMENT_MIN {
+        let encoded: Vec<String> = mounts
+            .iter()
+            .map(|m| serde_json::to_string(m).unwrap_or_default())
+            .collect();
+        let fragment = agent::AddSpecFragmentRequest {
+            id: fragment_id("mounts", encoded.iter().map(|m| m.as_bytes())),
+            mounts,
+            ..Default::default()
+        };
+        spec.set_mounts(None);
+        fragments.push(fragment);
+    }
+
+    fragments
+}
+
+// fragment_id derives a fragment id from its content, so identical
+// fragments registered by different containers collapse to one entry.
+fn fragment_id<'a>(kind: &str, parts: impl Iterator<Item = &'a [u8]>) -> String {
+    use sha2::Digest;
+
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(kind.as_bytes());
+    for part in parts {
+        hasher.update([0u8]);
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
 // is_pid_namespace_enabled checks if Pid namespace for a container needs to be shared with its sandbox
 // pid namespace.
 fn is_pid_namespace_enabled(spec: &oci::Spec) -> bool {
@@ -788,4 +907,75 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_extract_spec_fragments() {
+        let env: Vec<String> = (0..ENV_FRAGMENT_MIN)
+            .map(|i| format!("VAR{}=value{}", i, i))
+            .collect();
+        let mut spec = oci::SpecBuilder::default()
+            .process(oci::ProcessBuilder::default().env(env).build().unwrap())
+            .build()
+            .unwrap();
+
+        // A small spec stays inline.
+        let mut small = oci::Spec::default();
+        assert!(extract_spec_fragments(&mut small).is_empty());
+
+        let fragments = extract_spec_fragments(&mut spec);
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(fragments[0].env.len(), ENV_FRAGMENT_MIN);
+        assert!(spec.process().as_ref().unwrap().env().is_none());
+
+        // The id is derived from the content, so the same env list maps
+        // to the same fragment.
+        let mut other = oci::SpecBuilder::default()
+            .process(
+                oci::ProcessBuilder::default()
+                    .env(fragments[0].env.clone())
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let other_fragments = extract_spec_fragments(&mut other);
+        assert_eq!(other_fragments[0].id, fragments[0].id);
+    }
+
+    #[test]
+    fn test_v1_agent_keeps_spec_inline() {
+        let env: Vec<String> = (0..ENV_FRAGMENT_MIN)
+            .map(|i| format!("VAR{}=value{}", i, i))
+            .collect();
+        let mut spec = oci::SpecBuilder::default()
+            .process(oci::ProcessBuilder::default().env(env).build().unwrap())
+            .build()
+            .unwrap();
+
+        // A v1-fallback agent never negotiated the capability, so even a
+        // spec above the thresholds is left inline.
+        let v1 = NegotiatedApi::v1();
+        assert!(extract_spec_fragments_if_supported(&v1, &mut spec).is_empty());
+        assert_eq!(
+            spec.process()
+                .as_ref()
+                .unwrap()
+                .env()
+                .as_ref()
+                .unwrap()
+                .len(),
+            ENV_FRAGMENT_MIN
+        );
+
+        // An agent that negotiated the capability gets the fragment.
+        let v2: NegotiatedApi = agent::NegotiateApiResponse {
+            major: 2,
+            agent_version: String::new(),
+            capabilities: vec![CAP_ADD_SPEC_FRAGMENT.to_string()],
+        }
+        .into();
+        let fragments = extract_spec_fragments_if_supported(&v2, &mut spec);
+        assert_eq!(fragments.len(), 1);
+        assert!(spec.process().as_ref().unwrap().env().is_none());
+    }
 }
@@ -7,7 +7,10 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use agent::Agent;
 use common::{
@@ -24,7 +27,7 @@ use oci::Process as OCIProcess;
 use oci_spec::runtime as oci;
 use resource::ResourceManager;
 use runtime_spec as spec;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::instrument;
 
 use kata_sys_util::{hooks::HookStates, netns::NetnsGuard};
@@ -38,6 +41,9 @@ pub struct VirtContainerManager {
     resource_manager: Arc<ResourceManager>,
     agent: Arc<dyn Agent>,
     hypervisor: Arc<dyn Hypervisor>,
+    /// ids of the sandbox-level spec fragments already registered with
+    /// the agent, shared by all containers of the sandbox.
+    spec_fragments: Arc<Mutex<HashSet<String>>>,
 }
 
 impl std::fmt::Debug for VirtContainerManager {
@@ -71,6 +77,7 @@ impl VirtContainerManager {
             resource_manager,
             agent,
             hypervisor,
+            spec_fragments: Default::default(),
         }
     }
 }
@@ -86,6 +93,7 @@ impl ContainerManager for VirtContainerManager {
             self.agent.clone(),
             self.resource_manager.clone(),
             self.hypervisor.get_passfd_listener_addr().await.ok(),
+            self.spec_fragments.clone(),
         )
         .await
         .context("new container")?;
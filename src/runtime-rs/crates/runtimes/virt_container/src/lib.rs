@@ -19,7 +19,11 @@ use std::sync::Arc;
 use agent::{kata::KataAgent, AGENT_KATA};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use common::{message::Message, types::SandboxConfig, RuntimeHandler, RuntimeInstance};
+use common::{
+    message::{Action, Message},
+    types::SandboxConfig,
+    RuntimeHandler, RuntimeInstance,
+};
 use hypervisor::Hypervisor;
 #[cfg(all(feature = "dragonball", not(target_arch = "s390x")))]
 use hypervisor::{dragonball::Dragonball, HYPERVISOR_DRAGONBALL};
@@ -104,6 +108,20 @@ impl RuntimeHandler for VirtContainer {
 
         // get uds from hypervisor and get config from toml_config
         let agent = new_agent(&config).context("new agent")?;
+
+        // Forward sandbox conditions reported by the resource manager as
+        // containerd events, so they surface in `kubectl get events`.
+        let (condition_tx, mut condition_rx) = tokio::sync::mpsc::unbounded_channel();
+        let condition_forwarder = msg_sender.clone();
+        tokio::spawn(async move {
+            while let Some(condition) = condition_rx.recv().await {
+                let msg = Message::new(Action::Event(Arc::new(condition)));
+                if let Err(err) = condition_forwarder.send(msg).await {
+                    warn!(sl!(), "failed to forward sandbox condition {:?}", err);
+                }
+            }
+        });
+
         let resource_manager = Arc::new(
             ResourceManager::new(
                 sid,
@@ -111,6 +129,7 @@ impl RuntimeHandler for VirtContainer {
                 hypervisor.clone(),
                 config,
                 init_size_manager,
+                Some(condition_tx),
             )
             .await?,
         );
@@ -16,6 +16,7 @@ use common::types::utils::option_system_time_into;
 use common::types::ContainerProcess;
 use common::{types::SandboxConfig, ContainerManager, Sandbox, SandboxNetworkEnv};
 use containerd_shim_protos::events::task::{TaskExit, TaskOOM};
+use hypervisor::device::DeviceType;
 use hypervisor::VsockConfig;
 #[cfg(not(target_arch = "s390x"))]
 use hypervisor::HYPERVISOR_FIRECRACKER;
@@ -33,6 +34,7 @@ use kata_types::config::TomlConfig;
 use oci_spec::runtime as oci;
 use persist::{self, sandbox_persist::Persist};
 use protobuf::SpecialFields;
+use resource::condition::{SandboxCondition, REASON_GUEST_OOM};
 use resource::manager::ManagerArgs;
 use resource::network::{dan_config_path, DanNetworkConfig, NetworkConfig, NetworkWithNetNsConfig};
 use resource::{ResourceConfig, ResourceManager};
@@ -449,6 +451,7 @@ impl Sandbox for VirtSandbox {
 
         let agent = self.agent.clone();
         let sender = self.msg_sender.clone();
+        let sandbox_id = id.to_string();
         info!(sl!(), "oom watcher start");
         tokio::spawn(async move {
             loop {
@@ -472,6 +475,21 @@ impl Sandbox for VirtSandbox {
                                 "failed to send oom event for {} error {:?}", cid, err
                             );
                         }
+
+                        // Also surface the OOM as a sandbox condition so it
+                        // reaches `kubectl get events` with a well-known reason.
+                        let condition = SandboxCondition {
+                            sandbox_id: sandbox_id.clone(),
+                            reason: REASON_GUEST_OOM.to_string(),
+                            message: format!("container {} was OOM killed in the guest", cid),
+                        };
+                        let msg = Message::new(Action::Event(Arc::new(condition)));
+                        if let Err(err) = lock_sender.send(msg).await.context("send condition") {
+                            error!(
+                                sl!(),
+                                "failed to send oom condition for {} error {:?}", cid, err
+                            );
+                        }
                     }
                     Err(err) => {
                         warn!(sl!(), "failed to get oom event error {:?}", err);
@@ -599,6 +617,14 @@ impl Sandbox for VirtSandbox {
         Ok(())
     }
 
+    async fn direct_volume_remove(&self, volume_path: &str) -> Result<()> {
+        self.resource_manager
+            .remove_direct_volume(volume_path)
+            .await
+            .context("sandbox: failed to remove direct-volume")?;
+        Ok(())
+    }
+
     async fn set_iptables(&self, is_ipv6: bool, data: Vec<u8>) -> Result<Vec<u8>> {
         info!(sl!(), "sb: set_iptables invoked");
         let req = SetIPTablesRequest { is_ipv6, data };
@@ -632,6 +658,113 @@ impl Sandbox for VirtSandbox {
     async fn hypervisor_metrics(&self) -> Result<String> {
         self.hypervisor.get_hypervisor_metrics().await
     }
+
+    async fn mem_footprint_bytes(&self) -> Result<u64> {
+        // Sum the host RSS of the VMM and any helper processes it reports.
+        // Memory the guest has released through the balloon's free page
+        // reporting is no longer resident, so it is already excluded.
+        let pids = self
+            .hypervisor
+            .get_pids()
+            .await
+            .context("get hypervisor pids")?;
+        Ok(pids.iter().map(|pid| proc_rss_bytes(*pid)).sum())
+    }
+
+    async fn pod_resources(&self) -> Result<String> {
+        // Host CPUs the vCPU threads are allowed to run on. Without CPU
+        // pinning this is the whole machine, which is exactly what the
+        // sandbox may consume.
+        let thread_ids = self
+            .hypervisor
+            .get_thread_ids()
+            .await
+            .context("get vcpu thread ids")?;
+        let mut cpu_ids: Vec<u32> = Vec::new();
+        for tid in thread_ids.vcpus.values() {
+            cpu_ids.extend(cpus_allowed_list(*tid));
+        }
+        cpu_ids.sort_unstable();
+        cpu_ids.dedup();
+
+        // Host devices passed through to the guest, reported with their
+        // host PCI addresses so device plugins' allocations can be
+        // correlated.
+        let device_manager = self.resource_manager.get_device_manager().await;
+        let mut devices = Vec::new();
+        for info in device_manager.read().await.get_all_device_info().await {
+            if let DeviceType::Vfio(vfio) = info {
+                let device_ids: Vec<String> = vfio
+                    .devices
+                    .iter()
+                    .map(|dev| dev.bus_slot_func.clone())
+                    .collect();
+                devices.push(serde_json::json!({
+                    "resource_name": vfio.driver_type,
+                    "device_ids": device_ids,
+                }));
+            }
+        }
+
+        Ok(serde_json::json!({
+            "containers": [{
+                "name": self.sid,
+                "cpu_ids": cpu_ids,
+                "devices": devices,
+            }],
+        })
+        .to_string())
+    }
+}
+
+// Parse the "Cpus_allowed_list" line of /proc/<tid>/status, e.g.
+// "0-3,8". A thread that has already exited contributes nothing.
+fn cpus_allowed_list(tid: u32) -> Vec<u32> {
+    let status = match std::fs::read_to_string(format!("/proc/{}/status", tid)) {
+        Ok(status) => status,
+        Err(_) => return Vec::new(),
+    };
+    let mut cpus = Vec::new();
+    for line in status.lines() {
+        if let Some(rest) = line.strip_prefix("Cpus_allowed_list:") {
+            for part in rest.trim().split(',') {
+                match part.split_once('-') {
+                    Some((start, end)) => {
+                        if let (Ok(start), Ok(end)) = (start.parse::<u32>(), end.parse::<u32>()) {
+                            cpus.extend(start..=end);
+                        }
+                    }
+                    None => {
+                        if let Ok(cpu) = part.parse::<u32>() {
+                            cpus.push(cpu);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    cpus
+}
+
+// Read the resident set size of a process from /proc/<pid>/status, in
+// bytes. A process that has already exited simply contributes nothing.
+fn proc_rss_bytes(pid: u32) -> u64 {
+    let status = match std::fs::read_to_string(format!("/proc/{}/status", pid)) {
+        Ok(status) => status,
+        Err(_) => return 0,
+    };
+    for line in status.lines() {
+        if let Some(rest) = line.strip_prefix("VmRSS:") {
+            if let Some(kb) = rest
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse::<u64>().ok())
+            {
+                return kb * 1024;
+            }
+        }
+    }
+    0
 }
 
 #[async_trait]
@@ -0,0 +1,220 @@
+// Copyright (c) 2026 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Append-only audit trail of shim task service operations.
+//!
+//! When `enable_audit_log` is set in the runtime configuration, every
+//! task API call is recorded as one JSON line carrying a timestamp, the
+//! API name, the sandbox and container identifiers and the peer
+//! credentials of the ttRPC client. Each runtime class has its own
+//! configuration file, so compliance-sensitive classes can enable the
+//! trail without touching the others.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use common::types::TaskRequest;
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use serde::Serialize;
+
+/// One audit record, serialized as a single JSON line.
+#[derive(Serialize)]
+pub(crate) struct AuditEntry {
+    pub timestamp: String,
+    pub api: String,
+    pub sandbox_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exec_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer_pid: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer_uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer_gid: Option<u32>,
+    pub result: &'static str,
+}
+
+impl AuditEntry {
+    pub(crate) fn new(
+        api: String,
+        (container_id, exec_id): (Option<String>, Option<String>),
+        sandbox_id: &str,
+        fd: RawFd,
+        success: bool,
+    ) -> Self {
+        let creds = peer_credentials(fd);
+
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+            api,
+            sandbox_id: sandbox_id.to_string(),
+            container_id,
+            exec_id,
+            peer_pid: creds.map(|c| c.0),
+            peer_uid: creds.map(|c| c.1),
+            peer_gid: creds.map(|c| c.2),
+            result: if success { "ok" } else { "error" },
+        }
+    }
+}
+
+/// Appends audit records to `<dir>/<sandbox-id>.log`, rotating the file
+/// by size so a chatty client cannot fill the log filesystem.
+pub(crate) struct AuditLogger {
+    path: PathBuf,
+    max_size: u64,
+    max_files: u32,
+    file: Mutex<File>,
+}
+
+impl AuditLogger {
+    pub(crate) fn new(
+        dir: &str,
+        sandbox_id: &str,
+        max_size_mb: u64,
+        max_files: u32,
+    ) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("create audit log dir {}", dir))?;
+        let path = Path::new(dir).join(format!("{}.log", sandbox_id));
+        let file = open_append(&path)?;
+
+        Ok(Self {
+            path,
+            max_size: max_size_mb.saturating_mul(1024 * 1024),
+            max_files,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry).context("serialize audit entry")?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        if file.metadata()?.len() + line.len() as u64 > self.max_size {
+            *file = self.rotate()?;
+        }
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("append to audit log {}", self.path.display()))
+    }
+
+    // Drop the oldest rotated file, shift the others one slot up and
+    // turn the active file into `<path>.1`.
+    fn rotate(&self) -> Result<File> {
+        for i in (1..self.max_files).rev() {
+            let from = self.rotated_path(i);
+            if from.exists() {
+                let _ = fs::rename(from, self.rotated_path(i + 1));
+            }
+        }
+
+        if self.max_files > 0 {
+            fs::rename(&self.path, self.rotated_path(1))
+                .with_context(|| format!("rotate audit log {}", self.path.display()))?;
+        } else {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("truncate audit log {}", self.path.display()))?;
+        }
+
+        open_append(&self.path)
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open audit log {}", path.display()))
+}
+
+// Peer credentials of the task service client, taken from the unix
+// socket the request arrived on. `None` when the transport is not a
+// socket, e.g. in unit tests.
+fn peer_credentials(fd: RawFd) -> Option<(i32, u32, u32)> {
+    getsockopt(fd, PeerCredentials)
+        .ok()
+        .map(|c| (c.pid(), c.uid(), c.gid()))
+}
+
+/// Container and exec identifiers carried by a task request, captured
+/// before the request is handed to the runtime.
+pub(crate) fn request_ids(req: &TaskRequest) -> (Option<String>, Option<String>) {
+    let process_ids = |p: &common::types::ContainerProcess| {
+        let exec_id = if p.exec_id.is_empty() {
+            None
+        } else {
+            Some(p.exec_id.clone())
+        };
+        (Some(p.container_id.container_id.clone()), exec_id)
+    };
+
+    match req {
+        TaskRequest::CreateContainer(config) => (Some(config.container_id.clone()), None),
+        TaskRequest::CloseProcessIO(p)
+        | TaskRequest::DeleteProcess(p)
+        | TaskRequest::WaitProcess(p)
+        | TaskRequest::StartProcess(p)
+        | TaskRequest::StateProcess(p) => process_ids(p),
+        TaskRequest::ExecProcess(req) => process_ids(&req.process),
+        TaskRequest::KillProcess(req) => process_ids(&req.process),
+        TaskRequest::ResizeProcessPTY(req) => process_ids(&req.process),
+        TaskRequest::ShutdownContainer(req) => (Some(req.container_id.clone()), None),
+        TaskRequest::PauseContainer(id)
+        | TaskRequest::ResumeContainer(id)
+        | TaskRequest::StatsContainer(id)
+        | TaskRequest::ConnectContainer(id) => (Some(id.container_id.clone()), None),
+        TaskRequest::UpdateContainer(req) => (Some(req.container_id.clone()), None),
+        TaskRequest::Pid => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().to_str().unwrap();
+
+        // A tiny limit so the second entry triggers a rotation.
+        let logger = AuditLogger::new(dir_path, "sandbox", 0, 2).unwrap();
+
+        let entry = AuditEntry {
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            api: "ExecProcess".to_string(),
+            sandbox_id: "sandbox".to_string(),
+            container_id: Some("container".to_string()),
+            exec_id: None,
+            peer_pid: None,
+            peer_uid: None,
+            peer_gid: None,
+            result: "ok",
+        };
+
+        logger.append(&entry).unwrap();
+        logger.append(&entry).unwrap();
+        logger.append(&entry).unwrap();
+
+        assert!(dir.path().join("sandbox.log").exists());
+        assert!(dir.path().join("sandbox.log.1").exists());
+        assert!(dir.path().join("sandbox.log.2").exists());
+        assert!(!dir.path().join("sandbox.log.3").exists());
+    }
+}
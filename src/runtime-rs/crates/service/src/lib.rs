@@ -9,6 +9,7 @@ extern crate slog;
 
 logging::logger_with_subsystem!(sl, "service");
 
+mod audit;
 mod event;
 mod manager;
 mod task_service;
@@ -11,7 +11,7 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use common::message::{Action, Message};
 use containerd_shim_protos::shim_async;
-use kata_types::config::KATA_PATH;
+use kata_types::config::kata_state_root;
 use runtimes::RuntimeHandlerManager;
 use tokio::sync::mpsc::{channel, Receiver};
 use ttrpc::asynchronous::Server;
@@ -124,7 +124,7 @@ impl ServiceManager {
             warn!(sl!(), "failed to clean up runtime state, {}", e);
         }
 
-        let temp_dir = [KATA_PATH, sid].join("/");
+        let temp_dir = [kata_state_root().as_str(), sid].join("/");
         if fs::metadata(temp_dir.as_str()).is_ok() {
             // try to remove dir and skip the result
             if let Err(e) = fs::remove_dir_all(temp_dir) {
@@ -16,13 +16,56 @@ use ttrpc::{self, r#async::TtrpcContext};
 
 use runtimes::RuntimeHandlerManager;
 
+use crate::audit::{self, AuditEntry, AuditLogger};
+
+// Audit logging cannot be decided before the first create request loaded
+// the runtime configuration, so the state starts out unknown.
+enum AuditState {
+    Unknown,
+    Disabled,
+    Enabled(Arc<AuditLogger>),
+}
+
 pub(crate) struct TaskService {
     handler: Arc<RuntimeHandlerManager>,
+    audit: tokio::sync::Mutex<AuditState>,
 }
 
 impl TaskService {
     pub(crate) fn new(handler: Arc<RuntimeHandlerManager>) -> Self {
-        Self { handler }
+        Self {
+            handler,
+            audit: tokio::sync::Mutex::new(AuditState::Unknown),
+        }
+    }
+
+    async fn audit_logger(&self) -> Option<Arc<AuditLogger>> {
+        let mut state = self.audit.lock().await;
+        if let AuditState::Unknown = *state {
+            // Keep probing until the runtime configuration is loaded.
+            let config = self.handler.runtime_config().await?;
+            if config.runtime.enable_audit_log {
+                let sid = self.handler.sandbox_id().await;
+                match AuditLogger::new(
+                    &config.runtime.audit_log_path,
+                    &sid,
+                    config.runtime.audit_log_max_size_mb,
+                    config.runtime.audit_log_max_files,
+                ) {
+                    Ok(logger) => *state = AuditState::Enabled(Arc::new(logger)),
+                    Err(err) => {
+                        warn!(sl!(), "failed to open audit log: {:?}", err);
+                    }
+                }
+            } else {
+                *state = AuditState::Disabled;
+            }
+        }
+
+        match &*state {
+            AuditState::Enabled(logger) => Some(logger.clone()),
+            _ => None,
+        }
     }
 
     async fn handler_message<TtrpcReq, TtrpcResp>(
@@ -36,15 +79,28 @@ impl TaskService {
         TtrpcResp: TryFrom<TaskResponse>,
         <TtrpcResp as TryFrom<TaskResponse>>::Error: std::fmt::Debug,
     {
-        let r = req.try_into().map_err(|err| {
+        let r: TaskRequest = req.try_into().map_err(|err| {
             ttrpc::Error::Others(format!("failed to translate from shim {:?}", err))
         })?;
+        // Capture the audit fields before the request is moved into the
+        // handler; the entry itself is written once the outcome is known.
+        let api = r.to_string();
+        let ids = audit::request_ids(&r);
         let logger = sl!().new(o!("stream id" =>  ctx.mh.stream_id));
         debug!(logger, "====> task service {:?}", &r);
-        let resp =
-            self.handler.handler_message(r).await.map_err(|err| {
-                ttrpc::Error::Others(format!("failed to handle message {:?}", err))
-            })?;
+        let result = self
+            .handler
+            .handler_message(r)
+            .await
+            .map_err(|err| ttrpc::Error::Others(format!("failed to handle message {:?}", err)));
+        if let Some(audit_logger) = self.audit_logger().await {
+            let sid = self.handler.sandbox_id().await;
+            let entry = AuditEntry::new(api, ids, &sid, ctx.fd, result.is_ok());
+            if let Err(err) = audit_logger.append(&entry) {
+                warn!(logger, "failed to append audit log entry: {:?}", err);
+            }
+        }
+        let resp = result?;
         debug!(logger, "<==== task service {:?}", &resp);
         resp.try_into()
             .map_err(|err| ttrpc::Error::Others(format!("failed to translate to shim {:?}", err)))
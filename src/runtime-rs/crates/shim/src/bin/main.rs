@@ -20,6 +20,10 @@ use shim::{config, Args, Error, ShimExecutor};
 const DEFAULT_TOKIO_RUNTIME_WORKER_THREADS: usize = 2;
 // env to config tokio runtime worker threads
 const ENV_TOKIO_RUNTIME_WORKER_THREADS: &str = "TOKIO_RUNTIME_WORKER_THREADS";
+// Bound the blocking pool so runaway spawn_blocking usage surfaces as
+// queueing (visible in the runtime metrics) instead of unbounded threads.
+const DEFAULT_TOKIO_RUNTIME_MAX_BLOCKING_THREADS: usize = 16;
+const ENV_TOKIO_RUNTIME_MAX_BLOCKING_THREADS: &str = "TOKIO_RUNTIME_MAX_BLOCKING_THREADS";
 
 #[derive(Debug)]
 enum Action {
@@ -120,8 +124,14 @@ fn get_tokio_runtime() -> Result<tokio::runtime::Runtime> {
         .parse()
         .unwrap_or(DEFAULT_TOKIO_RUNTIME_WORKER_THREADS);
 
+    let max_blocking_threads = std::env::var(ENV_TOKIO_RUNTIME_MAX_BLOCKING_THREADS)
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or(DEFAULT_TOKIO_RUNTIME_MAX_BLOCKING_THREADS);
+
     let rt = tokio::runtime::Builder::new_multi_thread()
         .worker_threads(worker_threads)
+        .max_blocking_threads(max_blocking_threads)
         .enable_all()
         .build()
         .context("prepare tokio runtime")?;
@@ -9,22 +9,36 @@ import future.keywords.every
 
 # Default values, returned by OPA when rules cannot be evaluated to true.
 default AddARPNeighborsRequest := false
+default AddNetworkRequest := false
+# Registering a fragment only stores data: the env and mounts it carries
+# are merged into the CreateContainerRequest that references them before
+# that request is evaluated, so they are checked by the
+# CreateContainerRequest rules like inline values.
+default AddSpecFragmentRequest := true
 default AddSwapRequest := false
 default CloseStdinRequest := false
+default CompactMemoryRequest := false
 default CopyFileRequest := false
 default CreateContainerRequest := false
 default CreateSandboxRequest := false
 default DestroySandboxRequest := true
+default DropCachesRequest := false
 default ExecProcessRequest := false
+default GetMemoryInfoRequest := true
 default GetOOMEventRequest := true
 default GuestDetailsRequest := true
 default ListInterfacesRequest := false
 default ListRoutesRequest := false
 default MemHotplugByProbeRequest := false
+default NegotiateApiRequest := true
 default OnlineCPUMemRequest := true
 default PauseContainerRequest := false
+default PortForwardRequest := false
 default ReadStreamRequest := false
+default QuiesceSandboxRequest := false
+default ReloadConfigRequest := false
 default RemoveContainerRequest := true
+default RemoveNetworkRequest := false
 default RemoveStaleVirtiofsShareMountsRequest := true
 default ReseedRandomDevRequest := false
 default ResumeContainerRequest := false
@@ -34,13 +48,16 @@ default SignalProcessRequest := true
 default StartContainerRequest := true
 default StartTracingRequest := false
 default StatsContainerRequest := true
+default ThawSandboxRequest := false
 default StopTracingRequest := false
 default TtyWinResizeRequest := true
 default UpdateContainerRequest := false
+default UpdateDNSRequest := true
 default UpdateEphemeralMountsRequest := false
 default UpdateInterfaceRequest := true
 default UpdateRoutesRequest := true
 default WaitProcessRequest := true
+default WaitProcessesRequest := true
 default WriteStreamRequest := false
 
 # AllowRequestsFailingPolicy := true configures the Agent to *allow any
@@ -0,0 +1,128 @@
+// Copyright (c) 2026 Microsoft Corporation
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use crate::policy;
+
+use clap::ValueEnum;
+use log::debug;
+use serde::Deserialize;
+use std::fs;
+
+/// CRI implementations whose runtime defaults genpolicy can derive from
+/// their configuration files, instead of using the bundled settings.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CriRuntime {
+    /// containerd, configured through config.toml.
+    Containerd,
+    /// CRI-O, configured through crio.conf.
+    Crio,
+}
+
+/// Defaults a CRI injects into every container, derived from the CRI
+/// configuration file. Different CRIs (and different distro configurations
+/// of the same CRI) inject different default env variables, capabilities
+/// and devices, so deriving them from the actual configuration reduces
+/// policy mismatches across distros.
+#[derive(Clone, Debug, Default)]
+pub struct CriDefaults {
+    /// Environment variables added to every container.
+    pub env: Vec<String>,
+
+    /// Override for the default (non-privileged) capability set.
+    pub capabilities: Option<Vec<String>>,
+
+    /// Devices created in every container.
+    pub devices: Vec<policy::KataLinuxDevice>,
+}
+
+/// Subset of crio.conf that influences the generated policy.
+#[derive(Debug, Deserialize)]
+struct CrioConfig {
+    crio: Option<CrioTable>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrioTable {
+    runtime: Option<CrioRuntimeTable>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrioRuntimeTable {
+    default_env: Option<Vec<String>>,
+    default_capabilities: Option<Vec<String>>,
+    additional_devices: Option<Vec<String>>,
+}
+
+/// Load the container defaults of the given CRI from its configuration
+/// file.
+pub fn load_defaults(runtime: CriRuntime, config_path: &str) -> CriDefaults {
+    let contents = fs::read_to_string(config_path)
+        .unwrap_or_else(|e| panic!("Cannot read CRI config file {}: {}", config_path, e));
+
+    let defaults = match runtime {
+        CriRuntime::Containerd => containerd_defaults(config_path, &contents),
+        CriRuntime::Crio => crio_defaults(config_path, &contents),
+    };
+    debug!("CRI defaults from {}: {:?}", config_path, &defaults);
+    defaults
+}
+
+fn containerd_defaults(config_path: &str, contents: &str) -> CriDefaults {
+    // containerd compiles its OCI defaults into the binary and they match
+    // the values genpolicy already uses, so the configuration file is only
+    // parsed to catch a wrong --cri-config path early.
+    if let Err(e) = contents.parse::<toml::Value>() {
+        panic!("Cannot parse containerd config file {}: {}", config_path, e);
+    }
+    CriDefaults::default()
+}
+
+fn crio_defaults(config_path: &str, contents: &str) -> CriDefaults {
+    let config: CrioConfig = toml::from_str(contents)
+        .unwrap_or_else(|e| panic!("Cannot parse CRI-O config file {}: {}", config_path, e));
+
+    let mut defaults = CriDefaults::default();
+    let runtime = match config.crio.and_then(|c| c.runtime) {
+        Some(runtime) => runtime,
+        None => return defaults,
+    };
+
+    if let Some(env) = runtime.default_env {
+        defaults.env = env;
+    }
+
+    if let Some(caps) = runtime.default_capabilities {
+        defaults.capabilities = Some(caps.iter().map(|c| capability_name(c)).collect());
+    }
+
+    for device in runtime.additional_devices.unwrap_or_default() {
+        // "<host path>:<container path>:<permissions>", where the container
+        // path and the permissions are optional.
+        let mut fields = device.split(':');
+        let host_path = fields.next().unwrap_or_default();
+        let container_path = fields.next().unwrap_or(host_path);
+
+        defaults.devices.push(policy::KataLinuxDevice {
+            // CRI-O resolves the device type from the host device node,
+            // which genpolicy cannot inspect; assume a character device,
+            // by far the most common case.
+            Type: "c".to_string(),
+            Path: container_path.to_string(),
+        });
+    }
+
+    defaults
+}
+
+// CRI-O capability names may omit the CAP_ prefix used by the OCI runtime
+// spec.
+fn capability_name(name: &str) -> String {
+    let name = name.to_uppercase();
+    if name.starts_with("CAP_") {
+        name
+    } else {
+        format!("CAP_{}", name)
+    }
+}
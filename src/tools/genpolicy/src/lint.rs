@@ -0,0 +1,185 @@
+// Copyright (c) 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+// Allow K8s YAML field names.
+#![allow(non_snake_case)]
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::settings;
+use crate::utils;
+use crate::yaml;
+
+/// Env variable names that suggest their literal value is a credential
+/// which should come from a Secret instead of the input YAML.
+const SECRET_NAME_MARKERS: &[&str] = &[
+    "PASSWORD",
+    "PASSWD",
+    "SECRET",
+    "TOKEN",
+    "API_KEY",
+    "APIKEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+/// Static checks over the input YAML and the settings file, catching
+/// common mistakes before policy generation proceeds. Returns the list
+/// of findings; an empty list means the input looks clean.
+pub fn lint(config: &utils::Config) -> Result<Vec<String>> {
+    let mut findings = Vec::new();
+
+    lint_settings(config, &mut findings);
+
+    let yaml_contents = yaml::get_input_yaml(&config.yaml_file)?;
+    for document in serde_yaml::Deserializer::from_str(&yaml_contents) {
+        let doc_mapping = Value::deserialize(document)?;
+        if doc_mapping != Value::Null {
+            lint_resource(&config.settings, &doc_mapping, &mut findings);
+        }
+    }
+
+    Ok(findings)
+}
+
+fn lint_settings(config: &utils::Config, findings: &mut Vec<String>) {
+    let exec = &config.settings.request_defaults.ExecProcessRequest;
+    if exec.commands.is_some() {
+        findings.push(
+            "settings: ExecProcessRequest.commands is deprecated - use allowed_commands instead"
+                .to_string(),
+        );
+    }
+}
+
+fn lint_resource(settings: &settings::Settings, doc_mapping: &Value, findings: &mut Vec<String>) {
+    let kind = get_str(doc_mapping, "kind").unwrap_or("?");
+    let name = doc_mapping
+        .get("metadata")
+        .and_then(|m| get_str(m, "name"))
+        .unwrap_or("?");
+    let location = format!("{kind}/{name}");
+
+    let Some(spec) = get_pod_spec(doc_mapping, kind) else {
+        return;
+    };
+
+    if get_str(spec, "runtimeClassName").is_none() {
+        findings.push(format!(
+            "{location}: no runtimeClassName - this workload would not run inside a Kata sandbox"
+        ));
+    }
+
+    for containers_key in ["containers", "initContainers"] {
+        if let Some(containers) = spec.get(containers_key).and_then(|c| c.as_sequence()) {
+            for container in containers {
+                lint_container(&location, container, findings);
+            }
+        }
+    }
+
+    lint_volumes(settings, &location, spec, findings);
+}
+
+fn lint_container(location: &str, container: &Value, findings: &mut Vec<String>) {
+    let container_name = get_str(container, "name").unwrap_or("?");
+
+    if let Some(image) = get_str(container, "image") {
+        if !image.contains('@') {
+            let tag = image.rsplit(':').next().filter(|t| !t.contains('/'));
+            if tag.is_none() || tag == Some("latest") {
+                findings.push(format!(
+                    "{location}: container {container_name}: image {image} uses the implicit or \
+                    latest tag - the policy will match a different image after every push"
+                ));
+            } else {
+                findings.push(format!(
+                    "{location}: container {container_name}: image {image} is referenced by a \
+                    mutable tag - pin it by digest so the policy keeps matching the same layers"
+                ));
+            }
+        }
+    }
+
+    if let Some(context) = container.get("securityContext") {
+        if context.get("privileged").and_then(|p| p.as_bool()) == Some(true) {
+            findings.push(format!(
+                "{location}: container {container_name}: privileged securityContext defeats the \
+                isolation the policy is supposed to provide"
+            ));
+        }
+    }
+
+    if let Some(env) = container.get("env").and_then(|e| e.as_sequence()) {
+        for var in env {
+            let var_name = get_str(var, "name").unwrap_or("?");
+            let upper_name = var_name.to_uppercase();
+            if get_str(var, "value").is_some()
+                && SECRET_NAME_MARKERS.iter().any(|m| upper_name.contains(m))
+            {
+                findings.push(format!(
+                    "{location}: container {container_name}: env variable {var_name} looks like \
+                    a secret but has a literal value - the generated policy embeds it in clear \
+                    text; use a valueFrom secretKeyRef instead"
+                ));
+            }
+        }
+    }
+}
+
+fn lint_volumes(
+    settings: &settings::Settings,
+    location: &str,
+    spec: &Value,
+    findings: &mut Vec<String>,
+) {
+    let Some(volumes) = spec.get("volumes").and_then(|v| v.as_sequence()) else {
+        return;
+    };
+
+    for volume in volumes {
+        let volume_name = get_str(volume, "name").unwrap_or("?");
+        if let Some(host_path) = volume.get("hostPath") {
+            let path = get_str(host_path, "path").unwrap_or("?");
+            let allowed = settings
+                .mount_destinations
+                .iter()
+                .any(|d| path.starts_with(d.as_str()));
+            if !allowed {
+                findings.push(format!(
+                    "{location}: hostPath volume {volume_name} ({path}) has no matching \
+                    mount_destinations entry in the settings file"
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the pod spec of the input resource, if it has one.
+fn get_pod_spec<'a>(doc_mapping: &'a Value, kind: &str) -> Option<&'a Value> {
+    let spec = doc_mapping.get("spec")?;
+    match kind {
+        "Pod" => Some(spec),
+        "CronJob" => spec
+            .get("jobTemplate")
+            .and_then(|j| j.get("spec"))
+            .and_then(|s| s.get("template"))
+            .and_then(|t| t.get("spec")),
+        "DaemonSet"
+        | "Deployment"
+        | "Job"
+        | "ReplicaSet"
+        | "ReplicationController"
+        | "StatefulSet" => spec.get("template").and_then(|t| t.get("spec")),
+        _ => None,
+    }
+}
+
+fn get_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(|v| v.as_str())
+}
@@ -7,10 +7,12 @@ use log::{debug, info};
 
 mod config_map;
 mod containerd;
+mod cri;
 mod cronjob;
 mod daemon_set;
 mod deployment;
 mod job;
+mod lint;
 mod list;
 mod mount_and_storage;
 mod no_policy;
@@ -20,6 +22,7 @@ mod pod;
 mod pod_template;
 mod policy;
 mod registry;
+#[cfg(target_family = "unix")]
 mod registry_containerd;
 mod replica_set;
 mod replication_controller;
@@ -47,6 +50,18 @@ async fn main() {
         return;
     }
 
+    if config.lint {
+        let findings = lint::lint(&config).unwrap();
+        for finding in &findings {
+            println!("{finding}");
+        }
+        if !findings.is_empty() {
+            std::process::exit(1);
+        }
+        info!("No issues found.");
+        return;
+    }
+
     debug!("Creating policy from yaml, settings, and rules.rego files...");
     let mut policy = policy::AgentPolicy::from_files(&config).await.unwrap();
 
@@ -296,6 +296,9 @@ struct SecurityContext {
     #[serde(skip_serializing_if = "Option::is_none")]
     runAsUser: Option<i64>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    runAsNonRoot: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     seccompProfile: Option<SeccompProfile>,
 }
@@ -352,6 +355,24 @@ struct Capabilities {
     drop: Option<Vec<String>>,
 }
 
+/// Capabilities a container may add under the "baseline" Pod Security
+/// Standards profile.
+const PSS_BASELINE_CAPABILITIES: [&str; 13] = [
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+
 /// See Reference / Kubernetes API / Workload Resources / Pod.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct ContainerPort {
@@ -953,6 +974,94 @@ impl Container {
             }
         }
     }
+
+    /// Enforce the "baseline" Pod Security Standards controls that map to
+    /// this container, panicking when the input YAML requests something
+    /// the profile forbids.
+    pub fn enforce_pss_baseline(&self, is_privileged: bool) {
+        if is_privileged {
+            panic!(
+                "Container {} is privileged - rejected by the baseline Pod Security Standards profile",
+                &self.name
+            );
+        }
+
+        if let Some(context) = &self.securityContext {
+            if let Some(capabilities) = &context.capabilities {
+                for c in capabilities.add.as_deref().unwrap_or_default() {
+                    if !PSS_BASELINE_CAPABILITIES.contains(&c.as_str()) {
+                        panic!(
+                            "Container {} adds capability {} - not in the baseline Pod Security Standards allow list",
+                            &self.name, c
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Enforce the "restricted" Pod Security Standards controls on top of
+    /// the baseline ones, tightening the generated process so the agent
+    /// policy checks match what a PSS admission controller would require.
+    pub fn enforce_pss_restricted(&self, process: &mut policy::KataProcess) {
+        // allowPrivilegeEscalation must be explicitly disallowed;
+        // tighten the generated process to match.
+        if let Some(context) = &self.securityContext {
+            if context.allowPrivilegeEscalation == Some(true) {
+                panic!(
+                    "Container {} allows privilege escalation - rejected by the restricted Pod Security Standards profile",
+                    &self.name
+                );
+            }
+        }
+        process.NoNewPrivileges = true;
+
+        let context = self.securityContext.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Container {} has no securityContext - the restricted Pod Security Standards profile requires dropped capabilities and a seccomp profile",
+                &self.name
+            )
+        });
+
+        if context.runAsNonRoot == Some(false) || process.User.UID == 0 {
+            panic!(
+                "Container {} runs as root - the restricted Pod Security Standards profile requires runAsNonRoot with a non-zero runAsUser",
+                &self.name
+            );
+        }
+
+        let capabilities = context.capabilities.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Container {} must drop ALL capabilities for the restricted Pod Security Standards profile",
+                &self.name
+            )
+        });
+        let drop = capabilities.drop.as_deref().unwrap_or_default();
+        if !drop.iter().any(|c| c.as_str() == "ALL") {
+            panic!(
+                "Container {} must drop ALL capabilities for the restricted Pod Security Standards profile",
+                &self.name
+            );
+        }
+        for c in capabilities.add.as_deref().unwrap_or_default() {
+            if c.as_str() != "NET_BIND_SERVICE" {
+                panic!(
+                    "Container {} adds capability {} - the restricted Pod Security Standards profile only allows NET_BIND_SERVICE",
+                    &self.name, c
+                );
+            }
+        }
+
+        match &context.seccompProfile {
+            Some(profile)
+                if profile.profile_type == "RuntimeDefault"
+                    || profile.profile_type == "Localhost" => {}
+            _ => panic!(
+                "Container {} must set a RuntimeDefault or Localhost seccompProfile for the restricted Pod Security Standards profile",
+                &self.name
+            ),
+        }
+    }
 }
 
 fn compress_default_capabilities(
@@ -573,6 +573,11 @@ impl AgentPolicy {
         let mut linux = containerd::get_linux(is_privileged);
         linux.Namespaces = get_kata_namespaces(is_pause_container, use_host_network);
 
+        // Add the devices the configured CRI creates in every container.
+        if let Some(cri_defaults) = &self.config.cri_defaults {
+            linux.Devices.extend(cri_defaults.devices.iter().cloned());
+        }
+
         if !c_settings.Linux.MaskedPaths.is_empty() {
             linux.MaskedPaths.clone_from(&c_settings.Linux.MaskedPaths);
         }
@@ -643,6 +648,16 @@ impl AgentPolicy {
             .registry
             .get_process(&mut process, yaml_has_command, yaml_has_args);
 
+        // Add the default env variables the configured CRI injects into
+        // every container.
+        if let Some(cri_defaults) = &self.config.cri_defaults {
+            for env in &cri_defaults.env {
+                if !process.Env.contains(env) {
+                    process.Env.push(env.clone());
+                }
+            }
+        }
+
         if let Some(tty) = yaml_container.tty {
             process.Terminal = tty;
             if tty && !is_pause_container {
@@ -676,6 +691,21 @@ impl AgentPolicy {
         resource.get_process_fields(&mut process);
         yaml_container.get_process_fields(&mut process);
 
+        // The pause container is Kata infrastructure, not part of the
+        // user's workload, so Pod Security Standards don't apply to it.
+        if !is_pause_container {
+            match self.config.pss_profile {
+                Some(utils::PssProfile::Baseline) => {
+                    yaml_container.enforce_pss_baseline(is_privileged);
+                }
+                Some(utils::PssProfile::Restricted) => {
+                    yaml_container.enforce_pss_baseline(is_privileged);
+                    yaml_container.enforce_pss_restricted(&mut process);
+                }
+                None => {}
+            }
+        }
+
         process
     }
 }
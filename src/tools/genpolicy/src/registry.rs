@@ -29,6 +29,61 @@ use std::{
 };
 use tokio::io::AsyncWriteExt;
 
+/// Zstd-compressed layer media types, produced e.g. by containerd with
+/// zstd compression enabled. oci_distribution::manifest only names the
+/// gzip variants.
+pub const DOCKER_IMAGE_LAYER_ZSTD_MEDIA_TYPE: &str =
+    "application/vnd.docker.image.rootfs.diff.tar.zstd";
+pub const OCI_IMAGE_LAYER_ZSTD_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+zstd";
+
+/// Compression scheme of a layer blob, derived from its media type.
+#[derive(Clone, Copy, Debug)]
+pub enum LayerCompression {
+    Gzip,
+    Zstd,
+}
+
+pub fn layer_compression(media_type: &str) -> Option<LayerCompression> {
+    match media_type {
+        manifest::IMAGE_DOCKER_LAYER_GZIP_MEDIA_TYPE | manifest::IMAGE_LAYER_GZIP_MEDIA_TYPE => {
+            Some(LayerCompression::Gzip)
+        }
+        DOCKER_IMAGE_LAYER_ZSTD_MEDIA_TYPE | OCI_IMAGE_LAYER_ZSTD_MEDIA_TYPE => {
+            Some(LayerCompression::Zstd)
+        }
+        _ => None,
+    }
+}
+
+impl LayerCompression {
+    /// Extension used for the downloaded blob before decompression.
+    pub fn extension(self) -> &'static str {
+        match self {
+            LayerCompression::Gzip => "gz",
+            LayerCompression::Zstd => "zst",
+        }
+    }
+
+    /// Stream-decompress a downloaded blob into the tar file.
+    pub fn decompress(
+        self,
+        compressed: std::fs::File,
+        decompressed: &mut std::fs::File,
+    ) -> Result<()> {
+        match self {
+            LayerCompression::Gzip => {
+                let mut decoder = flate2::read::GzDecoder::new(compressed);
+                std::io::copy(&mut decoder, decompressed).map_err(|e| anyhow!(e))?;
+            }
+            LayerCompression::Zstd => {
+                let mut decoder = zstd::stream::read::Decoder::new(compressed)?;
+                std::io::copy(&mut decoder, decompressed).map_err(|e| anyhow!(e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Container image properties obtained from an OCI repository.
 #[derive(Clone, Debug, Default)]
 pub struct Container {
@@ -340,17 +395,14 @@ async fn get_image_layers(
     let mut layers = Vec::new();
 
     for layer in &manifest.layers {
-        if layer
-            .media_type
-            .eq(manifest::IMAGE_DOCKER_LAYER_GZIP_MEDIA_TYPE)
-            || layer.media_type.eq(manifest::IMAGE_LAYER_GZIP_MEDIA_TYPE)
-        {
+        if let Some(compression) = layer_compression(&layer.media_type) {
             if layer_index < config_layer.rootfs.diff_ids.len() {
                 let (verity_hash, passwd) = get_verity_and_users(
                     layers_cache_file_path.clone(),
                     client,
                     reference,
                     &layer.digest,
+                    compression,
                     &config_layer.rootfs.diff_ids[layer_index].clone(),
                 )
                 .await?;
@@ -360,7 +412,7 @@ async fn get_image_layers(
                     passwd: passwd.to_owned(),
                 });
             } else {
-                return Err(anyhow!("Too many Docker gzip layers"));
+                return Err(anyhow!("Too many image layers"));
             }
 
             layer_index += 1;
@@ -375,6 +427,7 @@ async fn get_verity_and_users(
     client: &mut Client,
     reference: &Reference,
     layer_digest: &str,
+    compression: LayerCompression,
     diff_id: &str,
 ) -> Result<(String, String)> {
     let temp_dir = tempfile::tempdir_in(".")?;
@@ -385,7 +438,7 @@ async fn get_verity_and_users(
     decompressed_path.set_extension("tar");
 
     let mut compressed_path = decompressed_path.clone();
-    compressed_path.set_extension("gz");
+    compressed_path.set_extension(compression.extension());
 
     let mut verity_hash = "".to_string();
     let mut passwd = "".to_string();
@@ -407,6 +460,7 @@ async fn get_verity_and_users(
             client,
             reference,
             layer_digest,
+            compression,
             &decompressed_path,
             &compressed_path,
         )
@@ -525,6 +579,7 @@ async fn create_decompressed_layer_file(
     client: &mut Client,
     reference: &Reference,
     layer_digest: &str,
+    compression: LayerCompression,
     decompressed_path: &Path,
     compressed_path: &Path,
 ) -> Result<()> {
@@ -546,8 +601,7 @@ async fn create_decompressed_layer_file(
         .create(true)
         .truncate(true)
         .open(decompressed_path)?;
-    let mut gz_decoder = flate2::read::GzDecoder::new(compressed_file);
-    std::io::copy(&mut gz_decoder, &mut decompressed_file).map_err(|e| anyhow!(e))?;
+    compression.decompress(compressed_file, &mut decompressed_file)?;
 
     info!("Adding tarfs index to layer");
     decompressed_file.seek(std::io::SeekFrom::Start(0))?;
@@ -590,6 +644,7 @@ pub fn get_verity_hash_and_users(path: &Path) -> Result<(String, String)> {
 }
 
 pub async fn get_container(config: &Config, image: &str) -> Result<Container> {
+    #[cfg(target_family = "unix")]
     if let Some(socket_path) = &config.containerd_socket_path {
         return Container::new_containerd_pull(
             config.layers_cache_file_path.clone(),
@@ -598,6 +653,18 @@ pub async fn get_container(config: &Config, image: &str) -> Result<Container> {
         )
         .await;
     }
+
+    // The containerd content store is reached over a unix socket, so
+    // that shortcut only exists on unix hosts; everywhere else the
+    // built-in registry client does the pull.
+    #[cfg(not(target_family = "unix"))]
+    if config.containerd_socket_path.is_some() {
+        return Err(anyhow!(
+            "pulling through containerd is only supported on unix hosts, \
+             drop the containerd socket option to use the built-in registry client"
+        ));
+    }
+
     Container::new(config, image).await
 }
 
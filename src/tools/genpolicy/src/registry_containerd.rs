@@ -6,8 +6,8 @@
 // Allow Docker image config field names.
 #![allow(non_snake_case)]
 use crate::registry::{
-    add_verity_and_users_to_store, get_verity_hash_and_users, read_verity_and_users_from_store,
-    Container, DockerConfigLayer, ImageLayer,
+    add_verity_and_users_to_store, get_verity_hash_and_users, layer_compression,
+    read_verity_and_users_from_store, Container, DockerConfigLayer, ImageLayer, LayerCompression,
 };
 
 use anyhow::{anyhow, Result};
@@ -261,13 +261,12 @@ pub async fn get_image_layers(
 
     for layer in layers {
         let layer_media_type = layer["mediaType"].as_str().unwrap();
-        if layer_media_type.eq("application/vnd.docker.image.rootfs.diff.tar.gzip")
-            || layer_media_type.eq("application/vnd.oci.image.layer.v1.tar+gzip")
-        {
+        if let Some(compression) = layer_compression(layer_media_type) {
             if layer_index < config_layer.rootfs.diff_ids.len() {
                 let (verity_hash, passwd) = get_verity_and_users(
                     layers_cache_file_path.clone(),
                     layer["digest"].as_str().unwrap(),
+                    compression,
                     client,
                     &config_layer.rootfs.diff_ids[layer_index].clone(),
                 )
@@ -279,7 +278,7 @@ pub async fn get_image_layers(
                 };
                 layersVec.push(imageLayer);
             } else {
-                return Err(anyhow!("Too many Docker gzip layers"));
+                return Err(anyhow!("Too many image layers"));
             }
             layer_index += 1;
         }
@@ -291,6 +290,7 @@ pub async fn get_image_layers(
 async fn get_verity_and_users(
     layers_cache_file_path: Option<String>,
     layer_digest: &str,
+    compression: LayerCompression,
     client: &containerd_client::Client,
     diff_id: &str,
 ) -> Result<(String, String)> {
@@ -302,7 +302,7 @@ async fn get_verity_and_users(
     decompressed_path.set_extension("tar");
 
     let mut compressed_path = decompressed_path.clone();
-    compressed_path.set_extension("gz");
+    compressed_path.set_extension(compression.extension());
 
     let mut verity_hash = "".to_string();
     let mut passwd = "".to_string();
@@ -322,6 +322,7 @@ async fn get_verity_and_users(
         if let Err(e) = create_decompressed_layer_file(
             client,
             layer_digest,
+            compression,
             &decompressed_path,
             &compressed_path,
         )
@@ -362,6 +363,7 @@ async fn get_verity_and_users(
 async fn create_decompressed_layer_file(
     client: &containerd_client::Client,
     layer_digest: &str,
+    compression: LayerCompression,
     decompressed_path: &Path,
     compressed_path: &Path,
 ) -> Result<()> {
@@ -384,7 +386,7 @@ async fn create_decompressed_layer_file(
 
     while let Some(chunk) = stream.message().await? {
         if chunk.offset < 0 {
-            return Err(anyhow!("Too many Docker gzip layers"));
+            return Err(anyhow!("negative offset reading layer {layer_digest}"));
         }
         file.seek(io::SeekFrom::Start(chunk.offset as u64)).await?;
         file.write_all(&chunk.data).await?;
@@ -401,8 +403,7 @@ async fn create_decompressed_layer_file(
         .create(true)
         .truncate(true)
         .open(decompressed_path)?;
-    let mut gz_decoder = flate2::read::GzDecoder::new(compressed_file);
-    std::io::copy(&mut gz_decoder, &mut decompressed_file).map_err(|e| anyhow!(e))?;
+    compression.decompress(compressed_file, &mut decompressed_file)?;
 
     info!("Adding tarfs index to layer");
     decompressed_file.seek(std::io::SeekFrom::Start(0))?;
@@ -3,8 +3,21 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+use crate::cri;
 use crate::settings;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+/// Pod Security Standards profile used to tighten the generated policy.
+/// See https://kubernetes.io/docs/concepts/security/pod-security-standards/
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum PssProfile {
+    /// Minimally restrictive: forbids privileged containers and
+    /// capabilities beyond the baseline allow list.
+    Baseline,
+    /// Hardened best practices: baseline plus no privilege escalation,
+    /// non-root user, dropped capabilities and a runtime seccomp profile.
+    Restricted,
+}
 
 #[derive(Debug, Parser)]
 struct CommandLineOptions {
@@ -96,6 +109,32 @@ struct CommandLineOptions {
         require_equals = true
     )]
     layers_cache_file_path: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        help = "Tighten the generated rules according to a Pod Security Standards profile, rejecting input YAML that violates the profile"
+    )]
+    pss: Option<PssProfile>,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Derive the default env variables, capabilities and devices injected into containers from the configuration of this CRI implementation instead of the bundled settings. Requires --cri-config."
+    )]
+    cri: Option<cri::CriRuntime>,
+
+    #[clap(
+        long,
+        help = "Path to the CRI configuration file used with --cri (e.g. /etc/containerd/config.toml or /etc/crio/crio.conf)"
+    )]
+    cri_config: Option<String>,
+
+    #[clap(
+        long,
+        help = "Check the input YAML and the settings file for common mistakes instead of generating a policy. Exits with a non-zero status if any issue is found."
+    )]
+    lint: bool,
+
     #[clap(short, long, help = "Print version information and exit")]
     version: bool,
 }
@@ -117,6 +156,9 @@ pub struct Config {
     pub base64_out: bool,
     pub containerd_socket_path: Option<String>,
     pub layers_cache_file_path: Option<String>,
+    pub pss_profile: Option<PssProfile>,
+    pub cri_defaults: Option<cri::CriDefaults>,
+    pub lint: bool,
     pub version: bool,
 }
 
@@ -141,7 +183,20 @@ impl Config {
             layers_cache_file_path = Some(String::from("./layers-cache.json"));
         }
 
-        let settings = settings::Settings::new(&args.json_settings_path);
+        let cri_defaults = match (args.cri, &args.cri_config) {
+            (Some(cri), Some(path)) => Some(cri::load_defaults(cri, path)),
+            (Some(_), None) => panic!("--cri requires the --cri-config parameter"),
+            (None, Some(_)) => panic!("--cri-config requires the --cri parameter"),
+            (None, None) => None,
+        };
+
+        let mut settings = settings::Settings::new(&args.json_settings_path);
+        if let Some(caps) = cri_defaults
+            .as_ref()
+            .and_then(|defaults| defaults.capabilities.as_ref())
+        {
+            settings.common.default_caps = caps.clone();
+        }
 
         Self {
             use_cache: args.use_cached_files,
@@ -156,6 +211,9 @@ impl Config {
             base64_out: args.base64_out,
             containerd_socket_path: args.containerd_socket_path,
             layers_cache_file_path,
+            pss_profile: args.pss,
+            cri_defaults,
+            lint: args.lint,
             version: args.version,
         }
     }
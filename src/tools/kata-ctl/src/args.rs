@@ -49,9 +49,15 @@ pub enum Commands {
     /// Test if system can run Kata Containers
     Check(CheckArgument),
 
+    /// Manage per-node runtime configuration overrides
+    Config(ConfigCommand),
+
     /// Directly assign a volume to Kata Containers to manage
     DirectVolume(DirectVolumeCommand),
 
+    /// Boot a disposable sandbox and run an echo container to verify the node
+    Doctor(DoctorArgument),
+
     /// Display settings
     Env(EnvArgument),
 
@@ -70,8 +76,11 @@ pub enum Commands {
     /// Start a monitor to get metrics of Kata Containers
     Monitor(MonitorArgument),
 
+    /// Sandbox network diagnostics
+    Net(NetCommand),
+
     /// Display version details
-    Version,
+    Version(VersionCommand),
 
     /// Parse Logs and output in various formats
     LogParser(LogParser),
@@ -105,6 +114,79 @@ pub enum CheckSubCommand {
     List,
 }
 
+#[derive(Debug, Args)]
+pub struct DoctorArgument {
+    /// Runtime class to exercise, e.g. kata-dragonball. Its trailing
+    /// component selects configuration-<name>.toml from the standard
+    /// configuration directories.
+    #[clap(long, default_value = "kata-dragonball")]
+    pub runtime_class: String,
+
+    /// Path to a static busybox binary copied into the test container rootfs.
+    #[clap(long, default_value = "/bin/busybox")]
+    pub busybox: String,
+
+    /// Seconds to wait for the echo container to finish.
+    #[clap(long, default_value_t = 120)]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigCommand {
+    #[clap(subcommand)]
+    pub config_cmd: ConfigSubCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigSubCommand {
+    /// Set a configuration override for a runtime class
+    Set(ConfigSetArgs),
+
+    /// Remove a configuration override from a runtime class
+    Unset(ConfigUnsetArgs),
+
+    /// List the configuration overrides set for a runtime class
+    List(ConfigListArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigSetArgs {
+    /// Runtime class whose configuration to override, e.g. kata-qemu
+    #[clap(long = "runtime-class")]
+    pub runtime_class: String,
+
+    /// Dotted configuration key, e.g. hypervisor.qemu.default_vcpus
+    pub key: String,
+
+    /// Value for the key; parsed as TOML, falling back to a plain string
+    pub value: String,
+
+    /// Run the host checks after applying the override
+    #[clap(long, action)]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigUnsetArgs {
+    /// Runtime class whose configuration to override, e.g. kata-qemu
+    #[clap(long = "runtime-class")]
+    pub runtime_class: String,
+
+    /// Dotted configuration key, e.g. hypervisor.qemu.default_vcpus
+    pub key: String,
+
+    /// Run the host checks after removing the override
+    #[clap(long, action)]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigListArgs {
+    /// Runtime class whose configuration overrides to list, e.g. kata-qemu
+    #[clap(long = "runtime-class")]
+    pub runtime_class: String,
+}
+
 #[derive(Debug, Args)]
 pub struct EnvArgument {
     /// Format output as JSON
@@ -188,6 +270,38 @@ pub struct DirectVolResizeArgs {
     pub resize_size: u64,
 }
 
+#[derive(Debug, Args)]
+pub struct NetCommand {
+    #[clap(subcommand)]
+    pub net_cmd: NetSubCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NetSubCommand {
+    /// Run host and guest network probes for a sandbox and print a report
+    Diag(NetDiagArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct NetDiagArgs {
+    /// pod sandbox ID.
+    pub sandbox_id: String,
+    #[clap(short = 'p', long = "kata-debug-port", default_value_t = 1026)]
+    /// kata debug console vport used to run the guest probes, default is 1026.
+    pub vport: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct VersionCommand {
+    /// Also query the components of a running sandbox (shim, agent,
+    /// guest kernel, hypervisor) and warn on unsupported combinations.
+    #[clap(long)]
+    pub all: bool,
+
+    /// pod sandbox ID, required with --all.
+    pub sandbox_id: Option<String>,
+}
+
 #[derive(Debug, Args)]
 pub struct ExecArguments {
     /// pod sandbox ID.
@@ -27,10 +27,14 @@ use std::io;
 use std::process::exit;
 
 use ops::check_ops::{
-    handle_check, handle_factory, handle_iptables, handle_metrics, handle_monitor, handle_version,
+    handle_check, handle_factory, handle_iptables, handle_metrics, handle_monitor,
 };
+use ops::config_ops::handle_config;
+use ops::doctor_ops::handle_doctor;
 use ops::env_ops::handle_env;
 use ops::exec_ops::handle_exec;
+use ops::net_ops::handle_net;
+use ops::version_ops::handle_version;
 use ops::volume_ops::handle_direct_volume;
 use slog::{error, o};
 
@@ -64,14 +68,17 @@ fn real_main() -> Result<()> {
     let res = if let Some(command) = args.command {
         match command {
             Commands::Check(args) => handle_check(args),
+            Commands::Config(args) => handle_config(args),
             Commands::DirectVolume(args) => handle_direct_volume(args),
+            Commands::Doctor(args) => handle_doctor(args),
             Commands::Exec(args) => handle_exec(args),
             Commands::Env(args) => handle_env(args),
             Commands::Factory => handle_factory(),
             Commands::Iptables(args) => handle_iptables(args),
             Commands::Metrics(args) => handle_metrics(args),
             Commands::Monitor(args) => handle_monitor(args),
-            Commands::Version => handle_version(),
+            Commands::Net(args) => handle_net(args),
+            Commands::Version(args) => handle_version(args),
             Commands::LogParser(args) => log_parser(args),
         }
     } else {
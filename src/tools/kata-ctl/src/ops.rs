@@ -4,7 +4,11 @@
 //
 
 pub mod check_ops;
+pub mod config_ops;
+pub mod doctor_ops;
 pub mod env_ops;
 pub mod exec_ops;
+pub mod net_ops;
 pub mod version;
+pub mod version_ops;
 pub mod volume_ops;
@@ -0,0 +1,376 @@
+// Copyright (c) 2022 Intel Corporation
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+// Manage per-node runtime configuration overrides as drop-in fragments.
+//
+// Overrides are written to a single kata-ctl managed fragment in the
+// config.d directory next to the runtime class' base configuration file,
+// named so that it sorts (and therefore merges) last. Before every
+// modification the current fragment is saved to a backup directory, and a
+// fragment that fails validation is rolled back automatically.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use kata_types::config::TomlConfig;
+
+use crate::args::{
+    CheckArgument, CheckSubCommand, ConfigCommand, ConfigListArgs, ConfigSetArgs, ConfigSubCommand,
+    ConfigUnsetArgs,
+};
+use crate::ops::check_ops::handle_check;
+
+// The kata-ctl managed drop-in fragment. The "zz-" prefix makes it merge
+// after any hand-written fragments, so kata-ctl overrides always win.
+const MANAGED_FRAGMENT_NAME: &str = "zz-kata-ctl.toml";
+const DROP_IN_DIR: &str = "config.d";
+const BACKUP_DIR: &str = "config.d.bak";
+
+// The top level tables a key may live under, mirroring the fields of
+// TomlConfig. Serde ignores unknown tables when the merged configuration
+// is deserialized, so a typo here would otherwise go unnoticed.
+const VALID_TOP_LEVEL_TABLES: [&str; 3] = ["agent", "hypervisor", "runtime"];
+
+pub fn handle_config(config_cmd: ConfigCommand) -> Result<()> {
+    match config_cmd.config_cmd {
+        ConfigSubCommand::Set(args) => handle_config_set(args),
+        ConfigSubCommand::Unset(args) => handle_config_unset(args),
+        ConfigSubCommand::List(args) => handle_config_list(args),
+    }
+}
+
+fn handle_config_set(args: ConfigSetArgs) -> Result<()> {
+    let base_config = config_file_for_runtime_class(&args.runtime_class)?;
+    validate_key(&args.key)?;
+    let value = parse_value(&args.value);
+
+    let mut fragment = read_fragment(&base_config)?;
+    set_key(&mut fragment, &args.key, value);
+
+    modify_fragment(&base_config, fragment)?;
+    println!("Set {} for runtime class {}", args.key, args.runtime_class);
+
+    if args.check {
+        handle_check(CheckArgument {
+            command: CheckSubCommand::NoNetworkChecks,
+        })?;
+    }
+    Ok(())
+}
+
+fn handle_config_unset(args: ConfigUnsetArgs) -> Result<()> {
+    let base_config = config_file_for_runtime_class(&args.runtime_class)?;
+    validate_key(&args.key)?;
+
+    let mut fragment = read_fragment(&base_config)?;
+    if !unset_key(&mut fragment, &args.key) {
+        return Err(anyhow!(
+            "no override for {} is set for runtime class {}",
+            args.key,
+            args.runtime_class
+        ));
+    }
+
+    modify_fragment(&base_config, fragment)?;
+    println!(
+        "Unset {} for runtime class {}",
+        args.key, args.runtime_class
+    );
+
+    if args.check {
+        handle_check(CheckArgument {
+            command: CheckSubCommand::NoNetworkChecks,
+        })?;
+    }
+    Ok(())
+}
+
+fn handle_config_list(args: ConfigListArgs) -> Result<()> {
+    let base_config = config_file_for_runtime_class(&args.runtime_class)?;
+    let fragment = read_fragment(&base_config)?;
+
+    let mut overrides = Vec::new();
+    flatten("", &toml::Value::Table(fragment), &mut overrides);
+
+    if overrides.is_empty() {
+        println!("No overrides set for runtime class {}", args.runtime_class);
+    } else {
+        for (key, value) in overrides {
+            println!("{} = {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+// Map a runtime class like "kata-qemu" to the base configuration file it
+// uses, by probing the default configuration file locations.
+fn config_file_for_runtime_class(runtime_class: &str) -> Result<PathBuf> {
+    let flavor = runtime_class.strip_prefix("kata-").unwrap_or(runtime_class);
+
+    for path in TomlConfig::get_default_config_file_list() {
+        let candidate = if flavor == runtime_class {
+            // The plain "kata" runtime class uses the default file name.
+            path.clone()
+        } else {
+            path.with_file_name(format!("configuration-{}.toml", flavor))
+        };
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(anyhow!(
+        "no configuration file found for runtime class {}",
+        runtime_class
+    ))
+}
+
+fn fragment_path(base_config: &Path) -> Result<PathBuf> {
+    let dir = base_config
+        .parent()
+        .ok_or_else(|| anyhow!("configuration file {:?} has no parent", base_config))?;
+    Ok(dir.join(DROP_IN_DIR).join(MANAGED_FRAGMENT_NAME))
+}
+
+fn read_fragment(base_config: &Path) -> Result<toml::value::Table> {
+    let path = fragment_path(base_config)?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents)
+            .with_context(|| format!("malformed managed fragment {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(toml::value::Table::new()),
+        Err(e) => Err(e).with_context(|| format!("read managed fragment {}", path.display())),
+    }
+}
+
+// Back up the current fragment (if any), write the new one, then reload
+// the full configuration including drop-ins and validate it. If the
+// result doesn't validate, the previous fragment is put back in place.
+fn modify_fragment(base_config: &Path, fragment: toml::value::Table) -> Result<()> {
+    let path = fragment_path(base_config)?;
+    let backup = backup_fragment(base_config, &path)?;
+
+    let outcome = write_fragment(&path, &fragment).and_then(|_| {
+        let (config, _) =
+            TomlConfig::load_from_file(base_config).context("load updated configuration")?;
+        config.validate().context("validate updated configuration")
+    });
+
+    if let Err(e) = outcome {
+        rollback_fragment(&path, backup.as_deref())?;
+        return Err(e.context("configuration override rejected, previous state restored"));
+    }
+    Ok(())
+}
+
+// Keep a timestamped copy of the current fragment so that a bad change can
+// be rolled back by hand; returns the backup path, if a fragment existed.
+fn backup_fragment(base_config: &Path, fragment: &Path) -> Result<Option<PathBuf>> {
+    if !fragment.is_file() {
+        return Ok(None);
+    }
+
+    let dir = base_config
+        .parent()
+        .ok_or_else(|| anyhow!("configuration file {:?} has no parent", base_config))?
+        .join(BACKUP_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("create backup dir {}", dir.display()))?;
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let backup = dir.join(format!("{}.{}", MANAGED_FRAGMENT_NAME, stamp));
+    fs::copy(fragment, &backup)
+        .with_context(|| format!("back up fragment to {}", backup.display()))?;
+    Ok(Some(backup))
+}
+
+fn write_fragment(path: &Path, fragment: &toml::value::Table) -> Result<()> {
+    if fragment.is_empty() {
+        if path.is_file() {
+            fs::remove_file(path)
+                .with_context(|| format!("remove empty fragment {}", path.display()))?;
+        }
+        return Ok(());
+    }
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("fragment path {:?} has no parent", path))?;
+    fs::create_dir_all(dir).with_context(|| format!("create drop-in dir {}", dir.display()))?;
+
+    let contents = toml::to_string(&fragment).context("serialize fragment")?;
+    fs::write(path, contents).with_context(|| format!("write fragment {}", path.display()))
+}
+
+fn rollback_fragment(path: &Path, backup: Option<&Path>) -> Result<()> {
+    match backup {
+        Some(backup) => {
+            fs::copy(backup, path)
+                .with_context(|| format!("restore fragment from {}", backup.display()))?;
+        }
+        None => {
+            if path.is_file() {
+                fs::remove_file(path)
+                    .with_context(|| format!("remove rejected fragment {}", path.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_key(key: &str) -> Result<()> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+        return Err(anyhow!(
+            "key {:?} must have at least two non-empty dot-separated segments, e.g. hypervisor.qemu.default_vcpus",
+            key
+        ));
+    }
+    if !VALID_TOP_LEVEL_TABLES.contains(&segments[0]) {
+        return Err(anyhow!(
+            "key {:?} must start with one of {}",
+            key,
+            VALID_TOP_LEVEL_TABLES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+// Parse the value as TOML so that numbers, booleans and arrays keep their
+// type; anything that doesn't parse is treated as a plain string.
+fn parse_value(value: &str) -> toml::Value {
+    #[derive(serde::Deserialize)]
+    struct Wrapper {
+        value: toml::Value,
+    }
+
+    toml::from_str::<Wrapper>(&format!("value = {}", value))
+        .map(|w| w.value)
+        .unwrap_or_else(|_| toml::Value::String(value.to_string()))
+}
+
+fn set_key(table: &mut toml::value::Table, key: &str, value: toml::Value) {
+    let mut segments: Vec<&str> = key.split('.').collect();
+    let leaf = segments.pop().expect("validate_key ensures segments");
+
+    let mut current = table;
+    for segment in segments {
+        let entry = current
+            .entry(segment.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        if !entry.is_table() {
+            // A scalar in the middle of the path is replaced by a table.
+            *entry = toml::Value::Table(toml::value::Table::new());
+        }
+        current = entry.as_table_mut().expect("just ensured this is a table");
+    }
+    current.insert(leaf.to_string(), value);
+}
+
+// Remove the given key from the fragment, pruning tables left empty.
+// Returns false if the key was not set.
+fn unset_key(table: &mut toml::value::Table, key: &str) -> bool {
+    let mut segments: Vec<&str> = key.split('.').collect();
+    let leaf = segments.pop().expect("validate_key ensures segments");
+
+    fn remove(table: &mut toml::value::Table, path: &[&str], leaf: &str) -> bool {
+        match path.split_first() {
+            None => table.remove(leaf).is_some(),
+            Some((head, rest)) => {
+                let removed = match table.get_mut(*head).and_then(|v| v.as_table_mut()) {
+                    Some(sub) => remove(sub, rest, leaf),
+                    None => false,
+                };
+                if removed
+                    && table
+                        .get(*head)
+                        .and_then(|v| v.as_table())
+                        .map(|t| t.is_empty())
+                        .unwrap_or(false)
+                {
+                    table.remove(*head);
+                }
+                removed
+            }
+        }
+    }
+
+    remove(table, &segments, leaf)
+}
+
+fn flatten(prefix: &str, value: &toml::Value, out: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, val) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, val, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("hypervisor.qemu.default_vcpus").is_ok());
+        assert!(validate_key("runtime.enable_debug").is_ok());
+        assert!(validate_key("hypervisor").is_err());
+        assert!(validate_key("hypervisor..default_vcpus").is_err());
+        assert!(validate_key("nonsense.key").is_err());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("4"), toml::Value::Integer(4));
+        assert_eq!(parse_value("true"), toml::Value::Boolean(true));
+        assert_eq!(
+            parse_value("\"quoted\""),
+            toml::Value::String("quoted".to_string())
+        );
+        assert_eq!(
+            parse_value("plain string"),
+            toml::Value::String("plain string".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_unset_key() {
+        let mut table = toml::value::Table::new();
+
+        set_key(
+            &mut table,
+            "hypervisor.qemu.default_vcpus",
+            toml::Value::Integer(4),
+        );
+        set_key(
+            &mut table,
+            "runtime.enable_debug",
+            toml::Value::Boolean(true),
+        );
+
+        let mut flat = Vec::new();
+        flatten("", &toml::Value::Table(table.clone()), &mut flat);
+        assert_eq!(flat.len(), 2);
+
+        // Unsetting a key that isn't there fails and leaves the rest alone
+        assert!(!unset_key(&mut table, "hypervisor.qemu.default_memory"));
+        assert!(unset_key(&mut table, "hypervisor.qemu.default_vcpus"));
+
+        // Empty parent tables are pruned
+        assert!(!table.contains_key("hypervisor"));
+        assert!(table.contains_key("runtime"));
+
+        assert!(unset_key(&mut table, "runtime.enable_debug"));
+        assert!(table.is_empty());
+    }
+}
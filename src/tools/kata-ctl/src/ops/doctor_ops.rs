@@ -0,0 +1,241 @@
+// Copyright (c) 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Description:
+// End-to-end node self test. Boots a disposable sandbox through the
+// embedded runtime library, runs a busybox echo container inside it,
+// prints a timing breakdown for every phase and cleans everything up -
+// the closest thing to "is this node actually able to run Kata" short
+// of scheduling a pod.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use common::message::Message;
+use common::types::{
+    ContainerConfig, ContainerProcess, ShutdownRequest, TaskRequest, TaskResponse,
+};
+use runtimes::RuntimeHandlerManager;
+use tokio::sync::mpsc::channel;
+
+use crate::args::DoctorArgument;
+
+const MESSAGE_BUFFER_SIZE: usize = 8;
+const WORKER_THREADS: usize = 2;
+
+// Directories searched for the runtime class configuration file.
+const CONFIG_DIRS: &[&str] = &[
+    "/etc/kata-containers",
+    "/usr/share/defaults/kata-containers",
+];
+
+const ECHO_MESSAGE: &str = "kata-ctl doctor: hello from the sandbox";
+
+pub fn handle_doctor(args: DoctorArgument) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(WORKER_THREADS)
+        .enable_all()
+        .build()
+        .context("prepare tokio runtime")?;
+
+    runtime.block_on(run_doctor(args))
+}
+
+/// Wall-clock durations of the individual doctor phases.
+struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+    start: Instant,
+    last: Instant,
+}
+
+impl Timings {
+    fn new() -> Self {
+        let now = Instant::now();
+        Timings {
+            phases: Vec::new(),
+            start: now,
+            last: now,
+        }
+    }
+
+    fn record(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now.duration_since(self.last)));
+        self.last = now;
+    }
+
+    fn report(&self) {
+        println!();
+        println!("Timing breakdown:");
+        for (phase, duration) in &self.phases {
+            println!("  {:<32} {:>10.1?}", phase, duration);
+        }
+        println!(
+            "  {:<32} {:>10.1?}",
+            "total",
+            self.last.duration_since(self.start)
+        );
+    }
+}
+
+async fn run_doctor(args: DoctorArgument) -> Result<()> {
+    let config_path = resolve_config(&args.runtime_class)?;
+    println!(
+        "doctor: runtime class {}, configuration {}",
+        args.runtime_class,
+        config_path.display()
+    );
+
+    let id = format!("kata-doctor-{}", std::process::id());
+    let bundle = std::env::temp_dir().join(&id);
+    prepare_bundle(&bundle, &id, &config_path, &args.busybox).context("prepare test bundle")?;
+
+    // Run the actual test with the bundle in place, then clean up no
+    // matter how it went.
+    let result = run_sandbox(&id, &bundle, args.timeout).await;
+    if let Err(e) = fs::remove_dir_all(&bundle) {
+        eprintln!("doctor: failed to remove {}: {}", bundle.display(), e);
+    }
+
+    let timings = result?;
+    println!("doctor: sandbox boot and echo container OK");
+    timings.report();
+    Ok(())
+}
+
+/// Map a runtime class name like "kata-dragonball" to its configuration
+/// file, searching the standard locations.
+fn resolve_config(runtime_class: &str) -> Result<PathBuf> {
+    let name = runtime_class.strip_prefix("kata-").unwrap_or(runtime_class);
+    let file_name = format!("configuration-{}.toml", name);
+
+    for dir in CONFIG_DIRS {
+        let candidate = Path::new(dir).join(&file_name);
+        if candidate.exists() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(anyhow!(
+        "no {} found in {}",
+        file_name,
+        CONFIG_DIRS.join(" or ")
+    ))
+}
+
+/// Build a throwaway OCI bundle whose rootfs holds nothing but a static
+/// busybox, with an echo command as the container process.
+fn prepare_bundle(bundle: &Path, id: &str, config_path: &Path, busybox: &str) -> Result<()> {
+    let bin_dir = bundle.join("rootfs").join("bin");
+    fs::create_dir_all(&bin_dir).with_context(|| format!("create {}", bin_dir.display()))?;
+
+    let target = bin_dir.join("busybox");
+    fs::copy(busybox, &target).with_context(|| {
+        format!(
+            "copy {} into the test rootfs; pass --busybox to point at a static busybox binary",
+            busybox
+        )
+    })?;
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+
+    let spec = serde_json::json!({
+        "ociVersion": "1.1.0",
+        "process": {
+            "terminal": false,
+            "user": { "uid": 0, "gid": 0 },
+            "args": ["/bin/busybox", "echo", ECHO_MESSAGE],
+            "cwd": "/",
+            "env": ["PATH=/bin:/usr/bin"]
+        },
+        "root": { "path": "rootfs", "readonly": false },
+        "hostname": id,
+        "annotations": {
+            "io.katacontainers.config_path": config_path.to_string_lossy(),
+        },
+        "linux": {
+            "namespaces": [
+                { "type": "pid" },
+                { "type": "ipc" },
+                { "type": "uts" },
+                { "type": "mount" }
+            ]
+        }
+    });
+
+    let config_json = bundle.join("config.json");
+    fs::write(&config_json, serde_json::to_vec_pretty(&spec)?)
+        .with_context(|| format!("write {}", config_json.display()))?;
+    Ok(())
+}
+
+async fn run_sandbox(id: &str, bundle: &Path, timeout: u64) -> Result<Timings> {
+    let mut timings = Timings::new();
+
+    // The sandbox reports lifecycle events over this channel; doctor
+    // only needs to acknowledge them.
+    let (sender, mut receiver) = channel::<Message>(MESSAGE_BUFFER_SIZE);
+    tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            if let Some(tx) = msg.resp_sender {
+                let _ = tx.send(Ok(())).await;
+            }
+        }
+    });
+
+    let manager = RuntimeHandlerManager::new(id, sender).context("create runtime manager")?;
+    timings.record("runtime init");
+
+    let req = TaskRequest::CreateContainer(ContainerConfig {
+        container_id: id.to_owned(),
+        bundle: bundle.to_string_lossy().into_owned(),
+        rootfs_mounts: Vec::new(),
+        terminal: false,
+        options: None,
+        stdin: None,
+        stdout: None,
+        stderr: None,
+    });
+    manager
+        .handler_message(req)
+        .await
+        .context("boot sandbox and create container")?;
+    timings.record("sandbox boot + container create");
+
+    let process = ContainerProcess::new(id, "")?;
+    manager
+        .handler_message(TaskRequest::StartProcess(process.clone()))
+        .await
+        .context("start echo container")?;
+    timings.record("container start");
+
+    let wait = manager.handler_message(TaskRequest::WaitProcess(process));
+    let resp = tokio::time::timeout(Duration::from_secs(timeout), wait)
+        .await
+        .map_err(|_| anyhow!("echo container did not finish within {}s", timeout))?
+        .context("wait for echo container")?;
+    timings.record("container exit");
+
+    if let TaskResponse::WaitProcess(status) = resp {
+        if status.exit_code != 0 {
+            return Err(anyhow!(
+                "echo container exited with code {}",
+                status.exit_code
+            ));
+        }
+    }
+
+    manager
+        .handler_message(TaskRequest::ShutdownContainer(ShutdownRequest {
+            container_id: id.to_owned(),
+            is_now: true,
+        }))
+        .await
+        .context("shut sandbox down")?;
+    timings.record("sandbox shutdown");
+
+    Ok(timings)
+}
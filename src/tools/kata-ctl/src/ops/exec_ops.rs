@@ -285,7 +285,10 @@ impl SockHandler for HvsockConfig {
     }
 }
 
-fn setup_client(server_url: String, dbg_console_port: u32) -> anyhow::Result<UnixStream> {
+pub(crate) fn setup_client(
+    server_url: String,
+    dbg_console_port: u32,
+) -> anyhow::Result<UnixStream> {
     // server address format: scheme://[cid|/x/domain.sock]:port
     let url_fields: Vec<&str> = server_url.split("://").collect();
     if url_fields.len() != 2 {
@@ -347,7 +350,7 @@ async fn get_agent_socket(sandbox_id: &str) -> anyhow::Result<String> {
     Ok(agent_sock)
 }
 
-fn get_server_socket(sandbox_id: &str) -> anyhow::Result<String> {
+pub(crate) fn get_server_socket(sandbox_id: &str) -> anyhow::Result<String> {
     let server_url = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()?
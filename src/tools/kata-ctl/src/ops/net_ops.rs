@@ -0,0 +1,177 @@
+// Copyright (c) 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Description:
+// Sandbox network diagnostics. Combines host-side checks (link and tap
+// state, tc qdisc counters) with guest probes run through the debug
+// console (link state, routes, gateway ping, DNS lookup) into a single
+// report to triage "pod has no network" issues.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use slog::o;
+
+use crate::args::{NetCommand, NetDiagArgs, NetSubCommand};
+use crate::ops::exec_ops::{get_server_socket, setup_client};
+
+// Per-probe timeout when talking to the guest over the debug console.
+const GUEST_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Marker used to detect the end of a guest probe's output.
+const PROBE_END_MARKER: &str = "KATA_NET_DIAG_END";
+
+// Convenience macro to obtain the scope logger
+macro_rules! sl {
+    () => {
+        slog_scope::logger().new(o!("subsystem" => "net_ops"))
+    };
+}
+
+// Host-side commands collected into the report. Interface-agnostic on
+// purpose: the tap/veth naming scheme differs between runtimes and CNIs,
+// so the full tables with counters are captured and left to the reader.
+const HOST_PROBES: &[(&str, &str, &[&str])] = &[
+    ("host link state", "ip", &["-d", "-s", "link", "show"]),
+    ("host tc qdisc counters", "tc", &["-s", "qdisc", "show"]),
+    ("host tc filter rules", "tc", &["filter", "show"]),
+];
+
+// Guest probes executed through the debug console shell.
+const GUEST_PROBES: &[(&str, &str)] = &[
+    ("guest link state", "ip -d -s link show"),
+    ("guest addresses", "ip addr show"),
+    ("guest route table", "ip route show"),
+    ("guest DNS configuration", "cat /etc/resolv.conf"),
+    (
+        "guest gateway ping",
+        "gw=$(ip route | awk '/^default/ {print $3; exit}'); \
+         if [ -n \"$gw\" ]; then ping -c 1 -W 2 \"$gw\"; else echo 'no default route'; fi",
+    ),
+    (
+        "guest DNS lookup",
+        "nslookup kubernetes.default.svc.cluster.local 2>&1 || true",
+    ),
+];
+
+fn report_section(title: &str, body: &str) {
+    println!("=== {} ===", title);
+    if body.trim().is_empty() {
+        println!("(no output)");
+    } else {
+        println!("{}", body.trim_end());
+    }
+    println!();
+}
+
+fn run_host_probes() {
+    for (title, cmd, args) in HOST_PROBES {
+        match Command::new(cmd).args(*args).output() {
+            Ok(output) => {
+                let mut body = String::from_utf8_lossy(&output.stdout).to_string();
+                if !output.status.success() {
+                    body.push_str(&String::from_utf8_lossy(&output.stderr));
+                }
+                report_section(title, &body);
+            }
+            Err(e) => report_section(title, &format!("failed to run {}: {}", cmd, e)),
+        }
+    }
+}
+
+// Run one shell command in the guest over the debug console and collect
+// its output up to the end marker.
+fn run_guest_probe(stream: &mut UnixStream, cmd: &str) -> Result<String> {
+    stream.set_read_timeout(Some(GUEST_PROBE_TIMEOUT))?;
+    stream.set_write_timeout(Some(GUEST_PROBE_TIMEOUT))?;
+
+    let wrapped = format!("{}; echo {}\n", cmd, PROBE_END_MARKER);
+    stream
+        .write_all(wrapped.as_bytes())
+        .context("write probe to debug console")?;
+
+    let reader_stream = stream.try_clone()?;
+    let mut reader = BufReader::new(reader_stream);
+    let mut output = String::new();
+
+    loop {
+        let mut line = String::new();
+        let n = reader
+            .read_line(&mut line)
+            .context("read probe output from debug console")?;
+        if n == 0 {
+            return Err(anyhow!("debug console closed while running probe"));
+        }
+        // The console echoes the command we sent (which contains the
+        // marker too), so only stop on the marker printed on its own.
+        if line.trim() == PROBE_END_MARKER {
+            break;
+        }
+        if !line.contains(PROBE_END_MARKER) {
+            output.push_str(&line);
+        }
+    }
+
+    Ok(output)
+}
+
+fn run_guest_probes(sandbox_id: &str, vport: u32) {
+    let server_url = match get_server_socket(sandbox_id) {
+        Ok(url) if !url.is_empty() => url,
+        Ok(_) => {
+            report_section("guest probes", "agent server URL is empty");
+            return;
+        }
+        Err(e) => {
+            report_section(
+                "guest probes",
+                &format!("cannot reach shim management server: {:#}", e),
+            );
+            return;
+        }
+    };
+
+    let mut stream = match setup_client(server_url, vport) {
+        Ok(s) => s,
+        Err(e) => {
+            report_section(
+                "guest probes",
+                &format!(
+                    "cannot connect to the guest debug console \
+                     (is agent.debug_console enabled?): {:#}",
+                    e
+                ),
+            );
+            return;
+        }
+    };
+
+    for (title, cmd) in GUEST_PROBES {
+        match run_guest_probe(&mut stream, cmd) {
+            Ok(output) => report_section(title, &output),
+            Err(e) => {
+                slog::warn!(sl!(), "guest probe failed"; "probe" => *title, "error" => format!("{:#}", e));
+                report_section(title, &format!("probe failed: {:#}", e));
+            }
+        }
+    }
+}
+
+pub fn handle_net(cmd: NetCommand) -> Result<()> {
+    match cmd.net_cmd {
+        NetSubCommand::Diag(args) => handle_net_diag(args),
+    }
+}
+
+fn handle_net_diag(args: NetDiagArgs) -> Result<()> {
+    println!("kata-ctl net diag report for sandbox {}\n", args.sandbox_id);
+
+    run_host_probes();
+    run_guest_probes(args.sandbox_id.as_str(), args.vport);
+
+    Ok(())
+}
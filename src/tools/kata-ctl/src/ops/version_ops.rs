@@ -0,0 +1,338 @@
+// Copyright (c) 2026 Kata Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Description:
+// Extended version reporting. `kata-ctl version` prints the kata-ctl
+// version as before; with `--all <sandbox-id>` it also queries the shim
+// binary, the agent and guest kernel of the running sandbox (through
+// GetGuestDetails) and the configured hypervisor binary, then checks
+// the collected versions against a compiled compatibility matrix and
+// warns on unsupported combinations.
+
+use anyhow::{anyhow, Context, Result};
+use semver::Version;
+use slog::o;
+
+use agent::kata::KataAgent;
+use agent::{Agent, AgentManager, GetGuestDetailsRequest};
+use kata_types::config::{Agent as AgentConfig, TomlConfig};
+
+use crate::args::VersionCommand;
+use crate::ops::check_ops;
+use crate::ops::env_ops::get_command_version;
+use crate::ops::exec_ops::get_server_socket;
+use crate::ops::version;
+
+// Convenience macro to obtain the scope logger
+macro_rules! sl {
+    () => {
+        slog_scope::logger().new(o!("subsystem" => "version_ops"))
+    };
+}
+
+// The shim binary queried for `--all`, resolved through PATH.
+const SHIM_BINARY: &str = "containerd-shim-kata-v2";
+
+// Minimum guest kernel version each hypervisor is supported with.
+// Older guest kernels lack virtio features the VMMs rely on, so such
+// combinations are reported as unsupported. Matched by substring so
+// that variants like "qemu-tdx" share the base hypervisor's entry.
+const MIN_GUEST_KERNEL: &[(&str, &str)] = &[
+    ("dragonball", "5.10.0"),
+    ("cloud-hypervisor", "5.10.0"),
+    ("firecracker", "4.14.0"),
+    ("qemu", "4.14.0"),
+];
+
+struct ComponentVersions {
+    kata_ctl: String,
+    shim: String,
+    agent: String,
+    guest_kernel: String,
+    hypervisor_name: String,
+    hypervisor: String,
+}
+
+// Extract the first semver-looking token from a raw version string.
+// Component outputs differ ("kata-runtime version 3.3.0", "QEMU emulator
+// version 7.2.0 ...", guest kernels like "5.10.25-100.fc33.x86_64"), so
+// take the leading dotted-digit part of each token and pad it out to
+// major.minor.patch.
+fn parse_semver(raw: &str) -> Option<Version> {
+    for token in raw.split_whitespace() {
+        let token = token.trim_start_matches('v');
+        let core: String = token
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let core = core.trim_end_matches('.');
+        if !core.contains('.') || core.starts_with('.') {
+            continue;
+        }
+
+        let mut parts: Vec<&str> = core.split('.').collect();
+        while parts.len() < 3 {
+            parts.push("0");
+        }
+
+        if let Ok(version) = Version::parse(&parts[..3].join(".")) {
+            return Some(version);
+        }
+    }
+
+    None
+}
+
+// Query the agent version and guest kernel version of a running sandbox
+// through the shim management endpoint and a GetGuestDetails call.
+fn get_guest_versions(sandbox_id: &str) -> Result<(String, String)> {
+    let server_url = get_server_socket(sandbox_id).context("get agent socket URL")?;
+
+    // The management endpoint returns "<socket address>:<server port>",
+    // e.g. "vsock://3:1024"; the agent client takes them separately.
+    let (address, port) = server_url
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("invalid agent URL {:?}", server_url))?;
+    let server_port: u32 = port
+        .parse()
+        .with_context(|| format!("invalid agent port in {:?}", server_url))?;
+
+    let config = AgentConfig {
+        server_port,
+        ..Default::default()
+    };
+    let address = address.to_string();
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            let kata_agent = KataAgent::new(config);
+            kata_agent
+                .start(&address)
+                .await
+                .context("connect to agent")?;
+
+            let resp = kata_agent
+                .get_guest_details(GetGuestDetailsRequest {
+                    mem_block_size: false,
+                    mem_hotplug_probe: false,
+                })
+                .await
+                .context("get guest details")?;
+            kata_agent.stop().await;
+
+            let details = resp
+                .agent_details
+                .ok_or_else(|| anyhow!("agent details missing from GetGuestDetails response"))?;
+
+            Ok((details.version, details.kernel_version))
+        })
+}
+
+fn collect_versions(sandbox_id: &str) -> Result<ComponentVersions> {
+    let kata_ctl = version::get().map_err(|e| anyhow!(e))?;
+
+    let shim = get_command_version(SHIM_BINARY)
+        .context("error getting shim version")?
+        .trim()
+        .to_string();
+
+    let (toml_config, _) = TomlConfig::load_from_default().context("load toml config")?;
+    let hypervisor_name = if toml_config.runtime.hypervisor_name.is_empty() {
+        // Fall back to the first entry for configs where hypervisor_name
+        // is absent, matching get_hypervisor_info().
+        toml_config
+            .hypervisor
+            .keys()
+            .next()
+            .cloned()
+            .ok_or_else(|| anyhow!("missing hypervisor config"))?
+    } else {
+        toml_config.runtime.hypervisor_name.clone()
+    };
+    let hypervisor_config = toml_config
+        .hypervisor
+        .get(&hypervisor_name)
+        .ok_or_else(|| anyhow!("could not find hypervisor config in configuration"))?;
+    let hypervisor = get_command_version(&hypervisor_config.path)
+        .context("error getting hypervisor version")?
+        .trim()
+        .to_string();
+
+    let (agent, guest_kernel) =
+        get_guest_versions(sandbox_id).context("error getting guest versions")?;
+
+    Ok(ComponentVersions {
+        kata_ctl,
+        shim,
+        agent,
+        guest_kernel,
+        hypervisor_name,
+        hypervisor,
+    })
+}
+
+// Check the collected versions against the compatibility matrix and
+// return a warning per unsupported or unverifiable combination.
+fn check_compatibility(versions: &ComponentVersions) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    match (parse_semver(&versions.shim), parse_semver(&versions.agent)) {
+        (Some(shim), Some(agent)) => {
+            if shim.major != agent.major || shim.minor != agent.minor {
+                warnings.push(format!(
+                    "unsupported combination: shim {} and agent {} are from different releases",
+                    shim, agent
+                ));
+            }
+        }
+        _ => warnings.push(format!(
+            "cannot check shim/agent compatibility: unparseable versions (shim {:?}, agent {:?})",
+            versions.shim, versions.agent
+        )),
+    }
+
+    match (
+        parse_semver(&versions.kata_ctl),
+        parse_semver(&versions.shim),
+    ) {
+        (Some(kata_ctl), Some(shim)) => {
+            if kata_ctl.major != shim.major {
+                warnings.push(format!(
+                    "unsupported combination: kata-ctl {} and shim {} differ in major version",
+                    kata_ctl, shim
+                ));
+            }
+        }
+        _ => warnings.push(format!(
+            "cannot check kata-ctl/shim compatibility: unparseable versions (kata-ctl {:?}, shim {:?})",
+            versions.kata_ctl, versions.shim
+        )),
+    }
+
+    if let Some(kernel) = parse_semver(&versions.guest_kernel) {
+        for (hypervisor, min_kernel) in MIN_GUEST_KERNEL {
+            if versions.hypervisor_name.contains(hypervisor) {
+                // Matrix entries are well-formed semver.
+                let min = Version::parse(min_kernel).unwrap();
+                if kernel < min {
+                    warnings.push(format!(
+                        "unsupported combination: guest kernel {} is older than the minimum {} required by {}",
+                        kernel, min, hypervisor
+                    ));
+                }
+                break;
+            }
+        }
+    } else {
+        warnings.push(format!(
+            "cannot check guest kernel compatibility: unparseable version {:?}",
+            versions.guest_kernel
+        ));
+    }
+
+    warnings
+}
+
+// kata-ctl handle version command starts here.
+pub fn handle_version(args: VersionCommand) -> Result<()> {
+    check_ops::handle_version()?;
+
+    if !args.all {
+        return Ok(());
+    }
+
+    let sandbox_id = args
+        .sandbox_id
+        .as_deref()
+        .ok_or_else(|| anyhow!("--all requires a sandbox ID"))?;
+
+    let versions = collect_versions(sandbox_id)?;
+
+    info!(sl!(), "shim version {:?}", versions.shim);
+    info!(sl!(), "agent version {:?}", versions.agent);
+    info!(sl!(), "guest kernel version {:?}", versions.guest_kernel);
+    info!(
+        sl!(),
+        "hypervisor {} version {:?}", versions.hypervisor_name, versions.hypervisor
+    );
+
+    let warnings = check_compatibility(&versions);
+    for warning in &warnings {
+        warn!(sl!(), "{}", warning);
+    }
+    if warnings.is_empty() {
+        info!(sl!(), "all component versions are compatible");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions() -> ComponentVersions {
+        ComponentVersions {
+            kata_ctl: "3.3.0-rc0".to_string(),
+            shim: "kata-runtime version 3.3.0".to_string(),
+            agent: "3.3.0".to_string(),
+            guest_kernel: "5.10.25-100.fc33.x86_64".to_string(),
+            hypervisor_name: "qemu".to_string(),
+            hypervisor: "QEMU emulator version 7.2.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_semver() {
+        let version = parse_semver("kata-runtime version 3.3.0").unwrap();
+        assert_eq!(version, Version::new(3, 3, 0));
+
+        let version = parse_semver("5.10.25-100.fc33.x86_64").unwrap();
+        assert_eq!(version, Version::new(5, 10, 25));
+
+        let version = parse_semver("Linux version 5.15").unwrap();
+        assert_eq!(version, Version::new(5, 15, 0));
+
+        assert!(parse_semver("unknown").is_none());
+        assert!(parse_semver("").is_none());
+    }
+
+    #[test]
+    fn test_check_compatibility_ok() {
+        assert!(check_compatibility(&versions()).is_empty());
+    }
+
+    #[test]
+    fn test_check_compatibility_release_mismatch() {
+        let mut versions = versions();
+        versions.agent = "3.2.0".to_string();
+
+        let warnings = check_compatibility(&versions);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("different releases"));
+    }
+
+    #[test]
+    fn test_check_compatibility_old_guest_kernel() {
+        let mut versions = versions();
+        versions.hypervisor_name = "dragonball".to_string();
+        versions.guest_kernel = "4.19.0".to_string();
+
+        let warnings = check_compatibility(&versions);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("older than the minimum"));
+    }
+
+    #[test]
+    fn test_check_compatibility_unparseable() {
+        let mut versions = versions();
+        versions.guest_kernel = "unknown".to_string();
+
+        let warnings = check_compatibility(&versions);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("cannot check guest kernel compatibility"));
+    }
+}
@@ -13,14 +13,15 @@ use kata_types::mount::{
 };
 use nix;
 use reqwest::StatusCode;
-use slog::{info, o};
+use slog::{info, o, warn};
 use std::fs;
 use url;
 
 use agent::ResizeVolumeRequest;
 use shim_interface::shim_mgmt::client::MgmtClient;
 use shim_interface::shim_mgmt::{
-    DIRECT_VOLUME_PATH_KEY, DIRECT_VOLUME_RESIZE_URL, DIRECT_VOLUME_STATS_URL,
+    DIRECT_VOLUME_PATH_KEY, DIRECT_VOLUME_REMOVE_URL, DIRECT_VOLUME_RESIZE_URL,
+    DIRECT_VOLUME_STATS_URL,
 };
 
 use crate::utils::TIMEOUT;
@@ -42,7 +43,7 @@ pub fn handle_direct_volume(vol_cmd: DirectVolumeCommand) -> Result<()> {
     let command = vol_cmd.directvol_cmd;
     let cmd_result: Option<String> = match command {
         DirectVolSubcommand::Add(args) => add(&args.volume_path, &args.mount_info)?,
-        DirectVolSubcommand::Remove(args) => remove(&args.volume_path)?,
+        DirectVolSubcommand::Remove(args) => executor::block_on(remove(&args.volume_path))?,
         DirectVolSubcommand::Stats(args) => executor::block_on(stats(&args.volume_path))?,
         DirectVolSubcommand::Resize(args) => {
             executor::block_on(resize(&args.volume_path, args.resize_size))?
@@ -119,7 +120,22 @@ pub fn add(volume_path: &str, mount_info: &str) -> Result<Option<String>> {
 }
 
 // remove deletes the direct volume path including all the files inside it.
-pub fn remove(volume_path: &str) -> Result<Option<String>> {
+// If the volume is still attached to a running sandbox (CSI unstage racing
+// with sandbox shutdown), the shim is asked to hot-unplug the backing
+// device first. The unplug is best effort: whether or not the sandbox
+// still responds, the mount-info metadata must not be left behind.
+pub async fn remove(volume_path: &str) -> Result<Option<String>> {
+    // A sandbox id is only present while the volume is mounted into a
+    // sandbox; its absence simply means there is nothing to hot-unplug.
+    if let Ok(sandbox_id) = get_sandbox_id_for_volume(volume_path) {
+        if let Err(e) = hot_unplug(&sandbox_id, volume_path).await {
+            warn!(
+                sl!(),
+                "failed to hot-remove volume {} from sandbox {}: {:?}", volume_path, sandbox_id, e
+            );
+        }
+    }
+
     let path = join_path(KATA_DIRECT_VOLUME_ROOT_PATH, volume_path)?;
     // removes path and any children it contains.
     fs::remove_dir_all(path)?;
@@ -127,6 +143,27 @@ pub fn remove(volume_path: &str) -> Result<Option<String>> {
     Ok(None)
 }
 
+// hot_unplug asks the shim to unplug the backing device of a direct
+// volume from the running sandbox. The device is identified by the host
+// device path recorded in the mount info, which is how the shim tracks
+// the volume.
+async fn hot_unplug(sandbox_id: &str, volume_path: &str) -> Result<()> {
+    let mount_info = get_volume_mount_info(volume_path)?;
+    let req_url = url::form_urlencoded::Serializer::new(String::from(DIRECT_VOLUME_REMOVE_URL))
+        .append_pair(DIRECT_VOLUME_PATH_KEY, &mount_info.device)
+        .finish();
+
+    let shim_client = MgmtClient::new(sandbox_id, Some(TIMEOUT))?;
+    let response = shim_client.post(&req_url, &String::from(""), "").await?;
+    let status = response.status();
+    if status != StatusCode::OK {
+        let body = format!("{:?}", response.into_body());
+        return Err(anyhow!("shim returned ({:?}): {:?}", status, body));
+    }
+
+    Ok(())
+}
+
 // get_sandbox_id_for_volume finds the id of the first sandbox found in the dir.
 // We expect a direct-assigned volume is associated with only a sandbox at a time.
 pub fn get_sandbox_id_for_volume(volume_path: &str) -> Result<String> {
@@ -278,7 +315,7 @@ mod tests {
         add(&volume_path, &mount_info).expect("add failed");
         let expected_file_path = volume_path;
         let expected: DirectVolumeMountInfo = get_volume_mount_info(&expected_file_path).unwrap();
-        remove(&expected_file_path).expect("remove failed");
+        executor::block_on(remove(&expected_file_path)).expect("remove failed");
         assert_eq!(actual.device, expected.device);
         assert_eq!(actual.fs_type, expected.fs_type);
         assert_eq!(actual.metadata, expected.metadata);